@@ -187,6 +187,8 @@ fn create_buffer(field_ident: &Ident, field_type: &Type) -> Stmt {
         parse_quote!(let #field_ident = self.buffer_provider.buffer_premerge(#field_name);)
     } else if *field_type == parse_quote!(BufferRef<Scalar<i64>>) {
         parse_quote!(let #field_ident = self.buffer_provider.buffer_scalar_i64(#field_name);)
+    } else if *field_type == parse_quote!(BufferRef<Scalar<OrderedFloat<f64>>>) {
+        parse_quote!(let #field_ident = self.buffer_provider.buffer_scalar_f64(#field_name);)
     } else if *field_type == parse_quote!(BufferRef<Scalar<String>>) {
         parse_quote!(let #field_ident = self.buffer_provider.buffer_scalar_string(#field_name);)
     } else if *field_type == parse_quote!(BufferRef<Scalar<&'static str>>) {
@@ -227,6 +229,8 @@ fn convert(expr: Expr, field_type: &Type) -> Expr {
         parse_quote!(#expr.premerge().unwrap())
     } else if *field_type == parse_quote!(BufferRef<Scalar<i64>>) {
         parse_quote!(#expr.scalar_i64().unwrap())
+    } else if *field_type == parse_quote!(BufferRef<Scalar<OrderedFloat<f64>>>) {
+        parse_quote!(#expr.scalar_f64().unwrap())
     } else if *field_type == parse_quote!(BufferRef<Scalar<String>>) {
         parse_quote!(#expr.scalar_string().unwrap())
     } else if *field_type == parse_quote!(BufferRef<Scalar<&'static str>>) {
@@ -241,12 +245,15 @@ fn hash(field_ident: &Ident, field_type: &Type) -> Stmt {
         parse_quote!(hasher.update(&#field_ident.as_bytes());)
     } else if *field_type == parse_quote!(usize) || *field_type == parse_quote!(i64) {
         parse_quote!(hasher.update(&#field_ident.to_ne_bytes());)
+    } else if *field_type == parse_quote!(OrderedFloat<f64>) {
+        parse_quote!(hasher.update(&#field_ident.to_bits().to_ne_bytes());)
     } else if *field_type == parse_quote!(u8) {
         parse_quote!(hasher.update(&[#field_ident]);)
     } else if *field_type == parse_quote!(bool) {
         parse_quote!(hasher.update(&[#field_ident as u8]);)
     } else if *field_type == parse_quote!(Aggregator) {
-        parse_quote!(hasher.update(&[#field_ident as u8]);)
+        // Debug representation includes parameters such as the quantile of `ApproxPercentile`
+        parse_quote!(hasher.update(format!("{:?}", #field_ident).as_bytes());)
     } else if *field_type == parse_quote!(TypedBufferRef) {
         parse_quote!(hasher.update(&#field_ident.buffer.i.to_ne_bytes());)
     } else {
@@ -284,6 +291,8 @@ fn parse_type(field_ident: &Ident, type_def: String) -> Option<(Expr, Option<FnA
             parse_quote!(EncodingType::U8)
         } else if base == "str" {
             parse_quote!(EncodingType::Str)
+        } else if base == "f64" {
+            parse_quote!(EncodingType::F64)
         } else {
             let ident = Ident::new(base, Span::call_site());
             parse_quote!(#ident.tag)
@@ -199,21 +199,25 @@ pub fn reify_types(input: TokenStream) -> TokenStream {
 fn types(t: &Ident) -> Option<Vec<Type>> {
     match t.to_string().as_ref() {
         "Str" => Some(vec![Type::Str]),
+        "OptStr" => Some(vec![Type::OptStr]),
         "IntegerNoU64" => Some(vec![Type::U8, Type::U16, Type::U32, Type::I64]),
         "NumberNoU64" => Some(vec![Type::U8, Type::U16, Type::U32, Type::I64, Type::F64]),
         "Integer" => Some(vec![Type::U8, Type::U16, Type::U32, Type::U64, Type::I64]),
         "Float" => Some(vec![Type::F64]),
         "NullableInteger" => Some(vec![Type::NullableU8, Type::NullableU16, Type::NullableU32, Type::NullableI64]),
         "NullableFloat" => Some(vec![Type::NullableF64]),
+        "NullableStr" => Some(vec![Type::NullableStr]),
         "Primitive" => Some(vec![Type::U8, Type::U16, Type::U32, Type::U64, Type::I64, Type::F64, Type::Str, Type::OptStr]),
         "NullablePrimitive" => Some(vec![Type::NullableU8, Type::NullableU16, Type::NullableU32, Type::NullableI64, Type::NullableF64, Type::NullableStr]),
         "PrimitiveUSize" => Some(vec![Type::U8, Type::U16, Type::U32, Type::U64, Type::I64, Type::F64, Type::Str, Type::USize]),
         "PrimitiveNoU64" => Some(vec![Type::U8, Type::U16, Type::U32, Type::I64, Type::F64, Type::Str]),
         "Const" => Some(vec![Type::ScalarI64, Type::ScalarStr]),
         "ScalarI64" => Some(vec![Type::ScalarI64]),
+        "ScalarF64" => Some(vec![Type::ScalarF64]),
         "ScalarStr" => Some(vec![Type::ScalarStr]),
         "IntAggregator" => Some(vec![Type::AggregatorCount, Type::AggregatorSumI64, Type::AggregatorMaxI64, Type::AggregatorMinI64]),
         "FloatAggregator" => Some(vec![Type::AggregatorCount, Type::AggregatorSumF64, Type::AggregatorMaxF64, Type::AggregatorMinF64]),
+        "StrAggregator" => Some(vec![Type::AggregatorMaxStr, Type::AggregatorMinStr]),
         _ => None,
     }
 }
@@ -237,6 +241,7 @@ enum Type {
     NullableStr,
 
     ScalarI64,
+    ScalarF64,
     ScalarStr,
     USize,
 
@@ -247,6 +252,8 @@ enum Type {
     AggregatorMaxF64,
     AggregatorMinI64,
     AggregatorMinF64,
+    AggregatorMaxStr,
+    AggregatorMinStr,
 }
 
 impl Type {
@@ -268,6 +275,7 @@ impl Type {
             Type::NullableStr => parse_quote!(EncodingType::NullableStr),
             Type::USize => parse_quote!(EncodingType::USize),
             Type::ScalarI64 => parse_quote!(EncodingType::ScalarI64),
+            Type::ScalarF64 => parse_quote!(EncodingType::ScalarF64),
             Type::ScalarStr => parse_quote!(EncodingType::ScalarStr),
             Type::AggregatorCount => parse_quote!(Aggregator::Count),
             Type::AggregatorSumI64 => parse_quote!(Aggregator::SumI64),
@@ -276,6 +284,8 @@ impl Type {
             Type::AggregatorMaxF64 => parse_quote!(Aggregator::MaxF64),
             Type::AggregatorMinI64 => parse_quote!(Aggregator::MinI64),
             Type::AggregatorMinF64 => parse_quote!(Aggregator::MinF64),
+            Type::AggregatorMaxStr => parse_quote!(Aggregator::MaxStr),
+            Type::AggregatorMinStr => parse_quote!(Aggregator::MinStr),
         }
     }
 
@@ -297,6 +307,7 @@ impl Type {
             Type::NullableStr => parse_quote!( let #variable = #variable.buffer.nullable_str(); ),
             Type::USize => parse_quote!( let #variable = #variable.buffer.usize(); ),
             Type::ScalarI64 => parse_quote!( let #variable = #variable.buffer.scalar_i64(); ),
+            Type::ScalarF64 => parse_quote!( let #variable = #variable.buffer.scalar_f64(); ),
             Type::ScalarStr => parse_quote!( let #variable = #variable.buffer.scalar_str(); ),
             Type::AggregatorCount => parse_quote!( let #variable = PhantomData::<Count>; ),
             Type::AggregatorSumI64 => parse_quote!( let #variable = PhantomData::<SumI64>; ),
@@ -305,6 +316,8 @@ impl Type {
             Type::AggregatorMaxF64 => parse_quote!( let #variable = PhantomData::<MaxF64>; ),
             Type::AggregatorMinI64 => parse_quote!( let #variable = PhantomData::<MinI64>; ),
             Type::AggregatorMinF64 => parse_quote!( let #variable = PhantomData::<MinF64>; ),
+            Type::AggregatorMaxStr => parse_quote!( let #variable = PhantomData::<MaxStr>; ),
+            Type::AggregatorMinStr => parse_quote!( let #variable = PhantomData::<MinStr>; ),
         }
     }
 }
@@ -1,4 +1,6 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use failure::Fail;
 use futures::executor::block_on;
@@ -23,6 +25,14 @@ struct Opt {
     #[structopt(long, name = "PATH", parse(from_os_str))]
     db_path: Option<PathBuf>,
 
+    /// Directory of the write-ahead log used to recover rows that were not stored as a partition before a crash
+    #[structopt(long, name = "WAL_PATH", parse(from_os_str))]
+    wal_path: Option<PathBuf>,
+
+    /// Interval in milliseconds at which the write-ahead log is synced to disk, 0 syncs on every ingest
+    #[structopt(long, name = "MILLIS", default_value = "1000")]
+    wal_fsync_interval: u64,
+
     /// Load .csv or .csv.gz files into the database
     #[structopt(long, name = "FILES", parse(from_os_str))]
     load: Vec<PathBuf>,
@@ -46,10 +56,51 @@ struct Opt {
     #[structopt(long)]
     mem_lz4: bool,
 
+    /// Compression of partitions stored on disk: `none`, `lz4` or `zstd` [default: lz4, none if built with lz4 enabled]
+    #[structopt(long, name = "CODEC")]
+    disk_compression: Option<locustdb::DiskCompression>,
+
     /// Number of rows per partition when loading new data
     #[structopt(long, name = "ROWS", default_value = "65536")]
     partition_size: usize,
 
+    /// Merge adjacent partitions with fewer rows than this in the background, 0 disables merging
+    #[structopt(long, name = "MIN_ROWS", default_value = "0")]
+    compact_below_rows: usize,
+
+    /// Number of query results kept until a queried table changes, 0 disables the result cache
+    #[structopt(long, name = "RESULTS", default_value = "0")]
+    result_cache_size: usize,
+
+    /// Number of ingested rows buffered before a new partition is created
+    #[structopt(long, name = "BATCH_ROWS", default_value = "1048576")]
+    batch_size: usize,
+
+    /// Comma separated batch sizes of specific tables, e.g. `events=65536,logs=1024`
+    #[structopt(long, name = "TABLE=ROWS", use_delimiter = true, parse(try_from_str = parse_table_batch_size))]
+    table_batch_size: Vec<(String, usize)>,
+
+    /// Comma separated encodings of specific columns, e.g. `events.user_id=raw,logs.*=dictionary`
+    /// (encodings: raw, dictionary, u8, u16, u32)
+    #[structopt(long, name = "TABLE.COLUMN=ENCODING", use_delimiter = true, parse(try_from_str = parse_column_encoding))]
+    column_encoding: Vec<(String, String, locustdb::ColumnEncoding)>,
+
+    /// Comma separated tables whose rows are assigned a `_ingest_ts` column with the time at which they were ingested
+    #[structopt(long, name = "TABLES", use_delimiter = true)]
+    ingest_timestamp_tables: Vec<String>,
+
+    /// Queries returning more rows than this fail instead of returning a result [default: unlimited]
+    #[structopt(long, name = "MAX_ROWS")]
+    max_result_rows: Option<usize>,
+
+    /// Maximum size in MiB of the intermediate results of a single query [default: unlimited]
+    #[structopt(long, name = "QUERY_MB")]
+    query_memory_limit: Option<usize>,
+
+    /// Level from 1 to 9 at which the HTTP server compresses responses, 0 disables compression
+    #[structopt(long, name = "LEVEL", default_value = "6")]
+    http_compression_level: u32,
+
     /// How much data to load at a time in MiB when reading from disk
     #[structopt(long, name = "MB", default_value = "256")]
     readahead: usize,
@@ -73,6 +124,30 @@ struct Opt {
     // TODO: make this a subcommand
     #[structopt(long)]
     server: bool,
+
+    /// Address the server binds to, either `host:port` or `unix:/path/to/socket`
+    #[structopt(long, name = "ADDR", default_value = "127.0.0.1:8080")]
+    addr: String,
+
+    /// Comma separated origins allowed to query the server from a browser, `*` allows any origin [default: http://localhost,http://127.0.0.1]
+    #[structopt(long, name = "ORIGINS", use_delimiter = true)]
+    cors_allow_origins: Vec<String>,
+
+    /// Comma separated HTTP methods allowed in cross-origin requests [default: GET,POST,DELETE,OPTIONS]
+    #[structopt(long, name = "METHODS", use_delimiter = true)]
+    cors_allow_methods: Vec<String>,
+
+    /// Comma separated headers allowed in cross-origin requests [default: Content-Type]
+    #[structopt(long, name = "HEADERS", use_delimiter = true)]
+    cors_allow_headers: Vec<String>,
+
+    /// Comma separated API keys that may call any endpoint of the server [default: no key required]
+    #[structopt(long, name = "KEYS", env = "LOCUSTDB_API_KEYS", hide_env_values = true, use_delimiter = true)]
+    api_keys: Vec<String>,
+
+    /// Comma separated API keys that may only run queries and read metadata
+    #[structopt(long, name = "READ_KEYS", env = "LOCUSTDB_READ_ONLY_API_KEYS", hide_env_values = true, use_delimiter = true)]
+    read_only_api_keys: Vec<String>,
 }
 
 fn main() {
@@ -80,28 +155,63 @@ fn main() {
 
     let Opt {
         db_path,
+        wal_path,
+        wal_fsync_interval,
         load,
         table,
         mem_limit_tables,
         schema,
         mem_lz4,
+        disk_compression,
         partition_size,
+        compact_below_rows,
+        result_cache_size,
+        batch_size,
+        table_batch_size,
+        column_encoding,
+        ingest_timestamp_tables,
+        max_result_rows,
+        query_memory_limit,
+        http_compression_level,
         readahead,
         seq_disk_read,
         threads,
         reduced_trips,
         trips,
         server,
+        addr,
+        cors_allow_origins,
+        cors_allow_methods,
+        cors_allow_headers,
+        api_keys,
+        read_only_api_keys,
     } = Opt::from_args();
 
     let options = locustdb::Options {
         threads: threads.unwrap_or_else(num_cpus::get),
         read_threads: if seq_disk_read { 1 } else { num_cpus::get() },
         db_path: db_path.clone(),
+        wal_path,
+        wal_fsync_interval: if wal_fsync_interval == 0 { None } else { Some(Duration::from_millis(wal_fsync_interval)) },
         mem_size_limit_tables: mem_limit_tables * 1024 * 1024 * 1024,
         mem_lz4,
+        disk_compression: disk_compression.unwrap_or_else(|| locustdb::Options::default().disk_compression),
         readahead: readahead * 1024 * 1024,
         seq_disk_read,
+        compact_below_rows,
+        result_cache_size,
+        batch_size,
+        table_batch_sizes: table_batch_size.into_iter().collect(),
+        column_encodings: column_encoding.into_iter().fold(HashMap::new(), |mut encodings, (table, column, encoding)| {
+            encodings.entry(table).or_default().insert(column, encoding);
+            encodings
+        }),
+        ingest_timestamp_tables: ingest_timestamp_tables.into_iter().collect(),
+        max_result_rows,
+        query_memory_limit: query_memory_limit.map(|mb| mb * 1024 * 1024),
+        http_compression_level: if http_compression_level == 0 { None } else { Some(http_compression_level) },
+        api_keys: api_keys.into_iter().filter(|key| !key.is_empty()).collect(),
+        read_only_api_keys: read_only_api_keys.into_iter().filter(|key| !key.is_empty()).collect(),
     };
 
     if db_path.is_some() && !cfg!(feature = "enable_rocksdb") {
@@ -149,14 +259,44 @@ fn main() {
     table_stats(&locustdb);
 
     if server {
+        let mut cors = locustdb::server::CorsOptions::default();
+        if !cors_allow_origins.is_empty() {
+            cors.allowed_origins = cors_allow_origins;
+        }
+        if !cors_allow_methods.is_empty() {
+            cors.allowed_methods = cors_allow_methods;
+        }
+        if !cors_allow_headers.is_empty() {
+            cors.allowed_headers = cors_allow_headers;
+        }
         actix_web::rt::System::new()
-            .block_on(locustdb::server::run(locustdb))
+            .block_on(locustdb::server::run(locustdb, &addr, cors))
             .unwrap();
     } else {
         repl(&locustdb);
     }
 }
 
+fn parse_table_batch_size(arg: &str) -> Result<(String, usize), String> {
+    match arg.split_once('=') {
+        Some((table, rows)) => match rows.parse() {
+            Ok(rows) => Ok((table.to_string(), rows)),
+            Err(err) => Err(format!("invalid batch size `{}`: {}", rows, err)),
+        },
+        None => Err(format!("expected `TABLE=ROWS`, found `{}`", arg)),
+    }
+}
+
+fn parse_column_encoding(arg: &str) -> Result<(String, String, locustdb::ColumnEncoding), String> {
+    match arg.split_once('=') {
+        Some((column, encoding)) => match column.split_once('.') {
+            Some((table, column)) => Ok((table.to_string(), column.to_string(), encoding.parse()?)),
+            None => Err(format!("expected `TABLE.COLUMN`, found `{}`", column)),
+        },
+        None => Err(format!("expected `TABLE.COLUMN=ENCODING`, found `{}`", arg)),
+    }
+}
+
 fn table_stats(locustdb: &LocustDB) {
     let stats = block_on(locustdb.table_stats()).expect("!?!");
     for table in stats {
@@ -285,12 +425,29 @@ fn repl(locustdb: &LocustDB) {
             continue;
         }
 
-        let query = locustdb.run_query(s, explain, show);
-        match block_on(query) {
-            Ok(result) => match result {
-                Ok(output) => print_results::print_query_result(&output),
-                Err(fail) => print_error(&fail),
-            },
+        if !show.is_empty() {
+            let query = locustdb.run_query(s, explain, show, None);
+            match block_on(query) {
+                Ok(result) => match result {
+                    Ok(output) => print_results::print_query_result(&output),
+                    Err(fail) => print_error(&fail),
+                },
+                _ => println!("Error: Query execution was canceled!"),
+            }
+            continue;
+        }
+        match block_on(locustdb.run_statements(s, explain, None)) {
+            Ok(Ok(outputs)) => {
+                for output in outputs {
+                    print_results::print_query_result(&output);
+                }
+            }
+            Ok(Err((statement, fail))) => {
+                if statement > 0 {
+                    println!("Statement {} failed:", statement + 1);
+                }
+                print_error(&fail)
+            }
             _ => println!("Error: Query execution was canceled!"),
         }
     }
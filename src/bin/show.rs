@@ -14,7 +14,7 @@ fn main() {
     load.unwrap();
     let query = "select pickup_ntaname, to_year(pickup_datetime), trip_distance / 1000, count(0), sum(total_amount) from default where cab_type = \"CMS\";";
     // let query = "select payment_method, count(0), sum(total_amount) from default;";
-    block_on(locustdb.run_query(query, false, vec![0]))
+    block_on(locustdb.run_query(query, false, vec![0], None))
         .unwrap()
         .unwrap();
 }
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
 use std::sync::Arc;
 
 use crate::mem_store::column::Column;
@@ -10,18 +12,120 @@ pub trait DiskStore: Sync + Send + 'static {
     fn load_column_range(&self, start: PartitionID, end: PartitionID, column_name: &str, ldb: &InnerLocustDB);
     fn bulk_load(&self, ldb: &InnerLocustDB);
     fn store_partition(&self, partition: PartitionID, tablename: &str, columns: &[Arc<Column>]);
+    /// Atomically stores a new partition and deletes the partitions (with their column names) it replaces.
+    fn replace_partitions(
+        &self,
+        partition: PartitionID,
+        tablename: &str,
+        columns: &[Arc<Column>],
+        replaced: &[(PartitionID, Vec<String>)],
+    );
+    /// Deletes the partitions (with their column names) from storage.
+    fn delete_partitions(&self, partitions: &[(PartitionID, Vec<String>)]);
+    /// Stores whether each row of a stored partition has been removed by `DELETE`, replacing previously stored
+    /// tombstones of the partition. Tombstones are deleted together with their partition.
+    fn store_tombstones(&self, partition: PartitionID, deleted: &[bool]);
+    /// Number of bytes stored for each partition.
+    fn partition_sizes(&self) -> Vec<PartitionSize>;
 }
 
 pub type PartitionID = u64;
 
+/// Compression applied by the storage backend to the columns of partitions stored on disk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiskCompression {
+    None,
+    Lz4,
+    Zstd,
+}
+
+impl FromStr for DiskCompression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<DiskCompression, String> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(DiskCompression::None),
+            "lz4" => Ok(DiskCompression::Lz4),
+            "zstd" => Ok(DiskCompression::Zstd),
+            _ => Err(format!("unknown disk compression `{}`, expected `none`, `lz4` or `zstd`", s)),
+        }
+    }
+}
+
 pub struct PartitionMetadata {
     pub id: PartitionID,
     pub tablename: String,
     pub len: usize,
     pub columns: Vec<ColumnMetadata>,
+    /// Whether each row has been removed by `DELETE`, `None` if no rows have been deleted.
+    pub tombstones: Option<Vec<bool>>,
 }
 
 pub struct ColumnMetadata {
     pub name: String,
     pub size_bytes: usize,
 }
+
+pub struct PartitionSize {
+    pub id: PartitionID,
+    pub tablename: String,
+    pub size_bytes: usize,
+}
+
+/// Disk usage of a table, which is independent of how much of the table is currently loaded into memory.
+#[derive(Debug, Clone)]
+pub struct TableDiskUsage {
+    pub name: String,
+    pub size_bytes: usize,
+    /// Id and size in bytes of each stored partition.
+    pub partitions: Vec<(PartitionID, usize)>,
+}
+
+impl TableDiskUsage {
+    /// Groups partition sizes by table, ordered by table name.
+    pub fn from_partition_sizes(partition_sizes: Vec<PartitionSize>) -> Vec<TableDiskUsage> {
+        let mut tables = BTreeMap::<String, TableDiskUsage>::new();
+        for partition in partition_sizes {
+            let table = tables.entry(partition.tablename.clone()).or_insert_with(|| TableDiskUsage {
+                name: partition.tablename,
+                size_bytes: 0,
+                partitions: Vec::new(),
+            });
+            table.size_bytes += partition.size_bytes;
+            table.partitions.push((partition.id, partition.size_bytes));
+        }
+        let mut tables = tables.into_values().collect::<Vec<_>>();
+        for table in &mut tables {
+            table.partitions.sort_unstable();
+        }
+        tables
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_partition_sizes() {
+        let partition = |id, tablename: &str, size_bytes| PartitionSize { id, tablename: tablename.to_string(), size_bytes };
+        let tables = TableDiskUsage::from_partition_sizes(vec![
+            partition(3, "b", 10),
+            partition(2, "a", 5),
+            partition(1, "b", 7),
+        ]);
+        assert_eq!(tables.len(), 2);
+        assert_eq!((tables[0].name.as_str(), tables[0].size_bytes), ("a", 5));
+        assert_eq!(tables[0].partitions, vec![(2, 5)]);
+        assert_eq!((tables[1].name.as_str(), tables[1].size_bytes), ("b", 17));
+        assert_eq!(tables[1].partitions, vec![(1, 7), (3, 10)]);
+    }
+
+    #[test]
+    fn test_parse_disk_compression() {
+        assert_eq!("none".parse(), Ok(DiskCompression::None));
+        assert_eq!("LZ4".parse(), Ok(DiskCompression::Lz4));
+        assert_eq!("zstd".parse(), Ok(DiskCompression::Zstd));
+        assert!("gzip".parse::<DiskCompression>().is_err());
+    }
+}
@@ -14,4 +14,8 @@ impl DiskStore for NoopStorage {
     fn load_column_range(&self, _: PartitionID, _: PartitionID, _: &str, _: &InnerLocustDB) {}
     fn bulk_load(&self, _: &InnerLocustDB) {}
     fn store_partition(&self, _: PartitionID, _: &str, _: &[Arc<Column>]) {}
+    fn replace_partitions(&self, _: PartitionID, _: &str, _: &[Arc<Column>], _: &[(PartitionID, Vec<String>)]) {}
+    fn delete_partitions(&self, _: &[(PartitionID, Vec<String>)]) {}
+    fn store_tombstones(&self, _: PartitionID, _: &[bool]) {}
+    fn partition_sizes(&self) -> Vec<PartitionSize> { Vec::new() }
 }
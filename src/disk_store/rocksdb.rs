@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::str;
 use std::path::Path;
@@ -23,15 +24,17 @@ pub struct RocksDB {
 }
 
 impl RocksDB {
-    pub fn new<P: AsRef<Path>>(path: P) -> RocksDB {
+    pub fn new<P: AsRef<Path>>(path: P, compression: DiskCompression) -> RocksDB {
         let mut options = Options::default();
         options.create_if_missing(true);
         options.create_missing_column_families(true);
 
         let mut partitions_options = Options::default();
-        if cfg!(feature = "enable_lz4") {
-            partitions_options.set_compression_type(DBCompressionType::None);
-        }
+        partitions_options.set_compression_type(match compression {
+            DiskCompression::None => DBCompressionType::None,
+            DiskCompression::Lz4 => DBCompressionType::Lz4,
+            DiskCompression::Zstd => DBCompressionType::Zstd,
+        });
         partitions_options.set_write_buffer_size(256 * 1024 * 1024);
         partitions_options.set_max_bytes_for_level_base(1024 * 1024 * 1024);
         let mut block_opts = BlockBasedOptions::default();
@@ -44,6 +47,7 @@ impl RocksDB {
         let db = DB::open_cf_descriptors(&options, path, vec![
             ColumnFamilyDescriptor::new("metadata", Options::default()),
             ColumnFamilyDescriptor::new("partitions", partitions_options),
+            ColumnFamilyDescriptor::new("tombstones", Options::default()),
         ]).unwrap();
         RocksDB { db }
     }
@@ -55,6 +59,10 @@ impl RocksDB {
     fn partitions(&self) -> &ColumnFamily {
         self.db.cf_handle("partitions").unwrap()
     }
+
+    fn tombstones(&self) -> &ColumnFamily {
+        self.db.cf_handle("tombstones").unwrap()
+    }
 }
 
 impl DiskStore for RocksDB {
@@ -64,7 +72,10 @@ impl DiskStore for RocksDB {
         for entry in iter {
             let (key, value) = entry.unwrap();
             let partition_id = BigEndian::read_u64(&key) as PartitionID;
-            metadata.push(deserialize_meta_data(&value, partition_id))
+            let mut md = deserialize_meta_data(&value, partition_id);
+            md.tombstones = self.db.get_cf(self.tombstones(), &key).unwrap()
+                .map(|deleted| deleted.iter().map(|&d| d != 0).collect());
+            metadata.push(md)
         }
         metadata
     }
@@ -115,6 +126,68 @@ impl DiskStore for RocksDB {
 
     fn store_partition(&self, partition: PartitionID, tablename: &str, columns: &[Arc<Column>]) {
         let mut tx = WriteBatch::default();
+        self.put_partition(&mut tx, partition, tablename, columns);
+        self.db.write(tx).unwrap();
+    }
+
+    fn replace_partitions(&self,
+                          partition: PartitionID,
+                          tablename: &str,
+                          columns: &[Arc<Column>],
+                          replaced: &[(PartitionID, Vec<String>)]) {
+        let mut tx = WriteBatch::default();
+        self.delete_partitions_tx(&mut tx, replaced);
+        self.put_partition(&mut tx, partition, tablename, columns);
+        self.db.write(tx).unwrap();
+    }
+
+    fn delete_partitions(&self, partitions: &[(PartitionID, Vec<String>)]) {
+        let mut tx = WriteBatch::default();
+        self.delete_partitions_tx(&mut tx, partitions);
+        self.db.write(tx).unwrap();
+    }
+
+    fn store_tombstones(&self, partition: PartitionID, deleted: &[bool]) {
+        let mut key = [0; 8];
+        BigEndian::write_u64(&mut key, partition);
+        let deleted = deleted.iter().map(|&d| d as u8).collect::<Vec<u8>>();
+        self.db.put_cf(self.tombstones(), key, &deleted).unwrap();
+    }
+
+    /// Sizes are the number of bytes of all keys and values of a partition before compression by RocksDB.
+    /// This iterates over all stored data and may take a while for large databases.
+    fn partition_sizes(&self) -> Vec<PartitionSize> {
+        let mut sizes = HashMap::<PartitionID, PartitionSize>::new();
+        for entry in self.db.iterator_cf(self.metadata(), IteratorMode::Start) {
+            let (key, value) = entry.unwrap();
+            let id = BigEndian::read_u64(&key) as PartitionID;
+            let metadata = deserialize_meta_data(&value, id);
+            sizes.insert(id, PartitionSize {
+                id,
+                tablename: metadata.tablename,
+                size_bytes: key.len() + value.len(),
+            });
+        }
+        for entry in self.db.iterator_cf(self.partitions(), IteratorMode::Start) {
+            let (key, value) = entry.unwrap();
+            let (id, _) = deserialize_column_key(&key);
+            if let Some(size) = sizes.get_mut(&id) {
+                size.size_bytes += key.len() + value.len();
+            }
+        }
+        for entry in self.db.iterator_cf(self.tombstones(), IteratorMode::Start) {
+            let (key, value) = entry.unwrap();
+            let id = BigEndian::read_u64(&key) as PartitionID;
+            if let Some(size) = sizes.get_mut(&id) {
+                size.size_bytes += key.len() + value.len();
+            }
+        }
+        sizes.into_values().collect()
+    }
+}
+
+impl RocksDB {
+    fn put_partition(&self, tx: &mut WriteBatch, partition: PartitionID, tablename: &str, columns: &[Arc<Column>]) {
         let mut key = [0; 8];
         BigEndian::write_u64(&mut key, partition);
         let md = serialize_meta_data(tablename, columns);
@@ -124,8 +197,18 @@ impl DiskStore for RocksDB {
             let data = serialize_column(column.as_ref());
             tx.put_cf(self.partitions(), &key, &data);
         }
+    }
 
-        self.db.write(tx).unwrap();
+    fn delete_partitions_tx(&self, tx: &mut WriteBatch, partitions: &[(PartitionID, Vec<String>)]) {
+        for (id, column_names) in partitions {
+            let mut key = [0; 8];
+            BigEndian::write_u64(&mut key, *id);
+            tx.delete_cf(self.metadata(), key);
+            tx.delete_cf(self.tombstones(), key);
+            for column_name in column_names {
+                tx.delete_cf(self.partitions(), column_key(*id, column_name));
+            }
+        }
     }
 }
 
@@ -257,6 +340,7 @@ fn deserialize_meta_data(data: &[u8], partition_id: PartitionID) -> PartitionMet
                 size_bytes: c.get_size_bytes() as usize,
             }
         }).collect(),
+        tombstones: None,
     }
 }
 
@@ -18,6 +18,10 @@ pub type BoxedData<'a> = Box<dyn Data<'a> + 'a>;
 
 pub trait Data<'a>: Send + Sync {
     fn len(&self) -> usize;
+    /// Number of bytes allocated on the heap by this buffer, not including data it references.
+    fn heap_size_of_children(&self) -> usize {
+        0
+    }
     fn get_raw(&self, i: usize) -> RawVal;
     fn get_type(&self) -> EncodingType;
     fn type_error(&self, func_name: &str) -> String;
@@ -74,6 +78,9 @@ pub trait Data<'a>: Send + Sync {
     fn cast_scalar_i64(&self) -> i64 {
         panic!("{}", self.type_error("cast_scalar_i64"))
     }
+    fn cast_scalar_f64(&self) -> OrderedFloat<f64> {
+        panic!("{}", self.type_error("cast_scalar_f64"))
+    }
     fn cast_scalar_str(&self) -> &'a str {
         panic!("{}", self.type_error("cast_scalar_str"))
     }
@@ -147,6 +154,9 @@ impl<'a> DataSource for BoxedData<'a> {
     fn range(&self) -> Option<(i64, i64)> {
         None
     }
+    fn value_range(&self) -> Option<(i64, i64)> {
+        None
+    }
     fn codec(&self) -> Codec {
         Codec::identity(self.get_type().cast_to_basic())
     }
@@ -187,6 +197,9 @@ impl<'a, T: VecData<T> + 'a> Data<'a> for Vec<T> {
     fn len(&self) -> usize {
         Vec::len(self)
     }
+    fn heap_size_of_children(&self) -> usize {
+        self.capacity() * mem::size_of::<T>()
+    }
     fn get_raw(&self, i: usize) -> RawVal {
         T::wrap_one(self[i])
     }
@@ -374,7 +387,8 @@ impl<'a> Data<'a> for Vec<i64> {
         self
     }
     fn to_mixed(&self) -> Vec<Val<'a>> {
-        self.iter().map(|i| Val::Integer(*i)).collect()
+        // `i64::MIN` represents null in fused nullable integers
+        self.iter().map(|&i| if i == i64::MIN { Val::Null } else { Val::Integer(i) }).collect()
     }
 }
 
@@ -462,8 +476,9 @@ impl<'a, T: VecData<T> + 'a> Data<'a> for &'a [T] {
         Box::new(&self[from..to])
     }
 
-    fn append_all(&mut self, _other: &dyn Data<'a>, _count: usize) -> Option<BoxedData<'a>> {
-        panic!("append_all on borrow")
+    fn append_all(&mut self, other: &dyn Data<'a>, count: usize) -> Option<BoxedData<'a>> {
+        let mut owned = self.to_vec();
+        Some(owned.append_all(other, count).unwrap_or_else(|| Box::new(owned)))
     }
 
     fn type_error(&self, func_name: &str) -> String {
@@ -508,12 +523,18 @@ impl<'a, T: VecData<T> + 'a> Data<'a> for &'a [T] {
     default fn cast_ref_mixed(&self) -> &[Val<'a>] {
         panic!("{}", self.type_error("cast_ref_mixed"))
     }
+    default fn to_mixed(&self) -> Vec<Val<'a>> {
+        panic!("{}", self.type_error("to_mixed"))
+    }
 }
 
 impl<'a> Data<'a> for &'a [&'a str] {
     fn cast_ref_str(&self) -> &[&'a str] {
         self
     }
+    fn to_mixed(&self) -> Vec<Val<'a>> {
+        self.iter().map(|s| Val::Str(s)).collect()
+    }
 }
 
 impl<'a> Data<'a> for &'a [Val<'a>] {
@@ -532,6 +553,10 @@ impl<'a> Data<'a> for &'a [i64] {
     fn cast_ref_i64(&self) -> &[i64] {
         self
     }
+    fn to_mixed(&self) -> Vec<Val<'a>> {
+        // `i64::MIN` represents null in fused nullable integers
+        self.iter().map(|&i| if i == i64::MIN { Val::Null } else { Val::Integer(i) }).collect()
+    }
 }
 
 impl<'a> Data<'a> for &'a [u64] {
@@ -562,6 +587,9 @@ impl<'a> Data<'a> for &'a [OrderedFloat<f64>] {
     fn cast_ref_f64(&self) -> &[OrderedFloat<f64>] {
         self
     }
+    fn to_mixed(&self) -> Vec<Val<'a>> {
+        self.iter().map(|f| Val::Float(*f)).collect()
+    }
 }
 
 impl<'a> Data<'a> for &'a [MergeOp] {
@@ -647,7 +675,7 @@ impl<'a> Data<'a> for RawVal {
     }
     fn cast_scalar_i64(&self) -> i64 {
         match self {
-            RawVal::Int(i) => *i,
+            RawVal::Int(i) | RawVal::Timestamp(i) => *i,
             _ => panic!("{}.cast_i64_const", &self),
         }
     }
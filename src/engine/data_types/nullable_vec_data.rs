@@ -18,6 +18,9 @@ impl<'a, T: VecData<T> + 'a> Data<'a> for NullableVec<T> {
     fn len(&self) -> usize {
         self.data.len()
     }
+    fn heap_size_of_children(&self) -> usize {
+        self.data.capacity() * std::mem::size_of::<T>() + self.present.capacity()
+    }
     fn get_raw(&self, i: usize) -> RawVal {
         if self.present.is_set(i) {
             T::wrap_one(self.data[i])
@@ -1,6 +1,8 @@
 use std::fmt;
 use std::mem;
 
+use ordered_float::OrderedFloat;
+
 use crate::ingest::raw_val::RawVal;
 
 use super::*;
@@ -16,6 +18,12 @@ impl<'a> Data<'a> for ScalarVal<i64> {
     }
 }
 
+impl<'a> Data<'a> for ScalarVal<OrderedFloat<f64>> {
+    fn cast_scalar_f64(&self) -> OrderedFloat<f64> {
+        self.val
+    }
+}
+
 impl<'a> Data<'a> for ScalarVal<&'a str> {
     default fn len(&self) -> usize {
         1
@@ -87,6 +95,9 @@ impl<'a, T: ScalarData<T>> Data<'a> for ScalarVal<T> {
     default fn cast_scalar_i64(&self) -> i64 {
         panic!("{}", self.type_error("cast_scalar_i64"))
     }
+    default fn cast_scalar_f64(&self) -> OrderedFloat<f64> {
+        panic!("{}", self.type_error("cast_scalar_f64"))
+    }
 }
 
 impl<'a> Data<'a> for ScalarVal<String> {
@@ -113,6 +124,18 @@ impl ScalarData<i64> for i64 {
     }
 }
 
+impl ScalarData<OrderedFloat<f64>> for OrderedFloat<f64> {
+    fn unwrap(vec: &dyn Data) -> OrderedFloat<f64> {
+        vec.cast_scalar_f64()
+    }
+    fn raw_val(val: &OrderedFloat<f64>) -> RawVal {
+        RawVal::Float(*val)
+    }
+    fn t() -> EncodingType {
+        EncodingType::ScalarF64
+    }
+}
+
 impl<'a> ScalarData<&'a str> for &'a str {
     fn unwrap(vec: &dyn Data) -> &'a str {
         // TODO(#96): fix. wait for associated type constructors?
@@ -24,6 +24,7 @@ pub enum EncodingType {
     Null,
 
     ScalarI64,
+    ScalarF64,
     ScalarStr,
     ScalarString,
     ConstVal,
@@ -40,6 +41,9 @@ impl EncodingType {
             EncodingType::Str => BasicType::String,
             EncodingType::I64 => BasicType::Integer,
             EncodingType::F64 => BasicType::Float,
+            EncodingType::U8 => BasicType::Boolean,
+            // Nullable booleans are represented by the type of their buffers
+            EncodingType::NullableU8 => BasicType::Boolean,
             EncodingType::NullableStr => BasicType::NullableString,
             EncodingType::NullableI64 => BasicType::NullableInteger,
             EncodingType::NullableF64 => BasicType::NullableFloat,
@@ -57,6 +61,7 @@ impl EncodingType {
             EncodingType::U16 => EncodingType::NullableU16,
             EncodingType::U32 => EncodingType::NullableU32,
             EncodingType::U64 => EncodingType::NullableU64,
+            EncodingType::F64 => EncodingType::NullableF64,
             EncodingType::OptStr => EncodingType::NullableStr,
             EncodingType::NullableStr => EncodingType::NullableStr,
             EncodingType::NullableI64 => EncodingType::NullableI64,
@@ -64,6 +69,7 @@ impl EncodingType {
             EncodingType::NullableU16 => EncodingType::NullableU16,
             EncodingType::NullableU32 => EncodingType::NullableU32,
             EncodingType::NullableU64 => EncodingType::NullableU64,
+            EncodingType::NullableF64 => EncodingType::NullableF64,
             EncodingType::Val => EncodingType::Val,
             _ => panic!("{:?} does not have a corresponding nullable type", &self),
         }
@@ -73,6 +79,9 @@ impl EncodingType {
         match self {
             EncodingType::NullableStr => EncodingType::OptStr,
             EncodingType::NullableI64 => EncodingType::I64,
+            EncodingType::NullableF64 => EncodingType::Val,
+            // Integers are decoded to i64 before being returned, so nullable u8 values are booleans
+            EncodingType::NullableU8 => EncodingType::Val,
             _ => panic!(
                 "{:?} does not have a corresponding fused nullable type",
                 &self
@@ -89,6 +98,14 @@ impl EncodingType {
                 | EncodingType::NullableU16
                 | EncodingType::NullableU32
                 | EncodingType::NullableU64
+                | EncodingType::NullableF64
+        )
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            EncodingType::U8 | EncodingType::U16 | EncodingType::U32 | EncodingType::U64 | EncodingType::I64
         )
     }
 
@@ -100,6 +117,7 @@ impl EncodingType {
             EncodingType::NullableU16 => EncodingType::U16,
             EncodingType::NullableU32 => EncodingType::U32,
             EncodingType::NullableU64 => EncodingType::U64,
+            EncodingType::NullableF64 => EncodingType::F64,
             EncodingType::OptStr => EncodingType::Str,
             _ => *self,
         }
@@ -112,8 +130,11 @@ impl EncodingType {
             match (self, other) {
                 (EncodingType::Val, _) => EncodingType::Val,
                 (_, EncodingType::Val) => EncodingType::Val,
+                (EncodingType::Null, _) | (_, EncodingType::Null) => EncodingType::Val,
                 (EncodingType::OptStr, EncodingType::Str) => EncodingType::OptStr,
                 (EncodingType::Str, EncodingType::OptStr) => EncodingType::OptStr,
+                (EncodingType::I64, EncodingType::F64) => EncodingType::F64,
+                (EncodingType::F64, EncodingType::I64) => EncodingType::F64,
                 _ => unimplemented!("lub not implemented for {:?} and {:?}", self, other),
             }
         }
@@ -150,13 +171,14 @@ impl BasicType {
     }
 
     pub fn is_nullable(self) -> bool {
-        matches!(self, BasicType::NullableInteger | BasicType::NullableString)
+        matches!(self, BasicType::NullableInteger | BasicType::NullableString | BasicType::NullableFloat)
     }
 
     pub fn non_nullable(self) -> BasicType {
         match self {
             BasicType::NullableInteger => BasicType::Integer,
             BasicType::NullableString => BasicType::String,
+            BasicType::NullableFloat => BasicType::Float,
             _ => self,
         }
     }
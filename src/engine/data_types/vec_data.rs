@@ -26,6 +26,8 @@ pub trait VecData<T>: PartialEq + Ord + Copy + Debug + Sync + Send {
 impl VecData<u8> for u8 {
     fn unwrap<'a, 'b>(vec: &'b dyn Data<'a>) -> &'b [u8] where u8: 'a { vec.cast_ref_u8() }
     fn unwrap_mut<'a, 'b>(vec: &'b mut dyn Data<'a>) -> &'b mut Vec<u8> where u8: 'a { vec.cast_ref_mut_u8() }
+    // Integers are always decoded to i64 before being returned, so any u8 results are booleans
+    fn wrap_one(value: u8) -> RawVal { RawVal::Bool(value != 0) }
     fn t() -> EncodingType { EncodingType::U8 }
 }
 
@@ -12,7 +12,10 @@ pub struct BatchResult<'a> {
     pub columns: Vec<BoxedData<'a>>,
     pub projection: Vec<usize>,
     pub aggregations: Vec<(usize, Aggregator)>,
-    pub order_by: Vec<(usize, bool)>,
+    pub order_by: Vec<(usize, bool, bool)>,
+    /// Planned type of each projection followed by each aggregation, which is retained when merged columns of
+    /// different partitions are converted to `Val`.
+    pub types: Vec<BasicType>,
     pub level: u32,
     pub batch_count: usize,
     pub show: bool,
@@ -66,6 +69,7 @@ pub fn combine<'a>(
     batch1: BatchResult<'a>,
     batch2: BatchResult<'a>,
     limit: usize,
+    limits: QueryLimits,
 ) -> Result<BatchResult<'a>, QueryError> {
     ensure!(
         batch1.projection.len() == batch2.projection.len(),
@@ -91,6 +95,7 @@ pub fn combine<'a>(
 
     if !batch1.aggregations.is_empty() {
         // Aggregation query
+        let (left_len, right_len) = (batch1.len(), batch2.len());
         let left = batch1
             .columns
             .into_iter()
@@ -116,7 +121,14 @@ pub fn combine<'a>(
             let ops = qp
                 .constant_vec(data.len(), EncodingType::MergeOp)
                 .merge_op()?;
-            data.push(Box::new(vec![MergeOp::TakeLeft, MergeOp::MergeRight]));
+            // Without grouping columns, each batch holds a single row unless no rows passed the filter
+            let merge_ops = match (left_len, right_len) {
+                (0, 0) => vec![],
+                (_, 0) => vec![MergeOp::TakeLeft],
+                (0, _) => vec![MergeOp::TakeRight],
+                _ => vec![MergeOp::TakeLeft, MergeOp::MergeRight],
+            };
+            data.push(Box::new(merge_ops));
             (vec![], ops)
         } else if lprojection.len() == 1 {
             let (l, r) = unify_types(&mut qp, left[lprojection[0]], right[rprojection[0]]);
@@ -124,10 +136,10 @@ pub fn combine<'a>(
             (vec![merged.any()], ops)
         } else {
             let (l, r) = unify_types(&mut qp, left[lprojection[0]], right[rprojection[0]]);
-            let mut partitioning = qp.partition(l, r, limit, false);
+            let mut partitioning = qp.partition(l, r, limit, false, true);
             for i in 1..(lprojection.len() - 1) {
                 let (l, r) = unify_types(&mut qp, left[lprojection[i]], right[rprojection[i]]);
-                partitioning = qp.subpartition(partitioning, l, r, false);
+                partitioning = qp.subpartition(partitioning, l, r, false, true);
             }
 
             let last = lprojection.len() - 1;
@@ -149,14 +161,20 @@ pub fn combine<'a>(
         for (&(ileft, aggregator), &(iright, _)) in
             batch1.aggregations.iter().zip(batch2.aggregations.iter())
         {
-            let left = left[ileft];
-            let right = right[iright];
+            let (left, right) = unify_types(&mut qp, left[ileft], right[iright]);
+            // Partitions that only contain integers aggregate with the integer variant of the aggregator
+            let aggregator = match left.tag {
+                EncodingType::F64 => aggregator.float(),
+                EncodingType::Str | EncodingType::OptStr => aggregator.string(),
+                _ => aggregator,
+            };
             let aggregated = qp.merge_aggregate(ops, left, right, aggregator);
             aggregates.push((aggregated.any(), aggregator));
         }
 
         let mut executor = qp.prepare(data)?;
         let mut results = executor.prepare_no_columns();
+        results.set_limits(limits);
         executor.run(1, &mut results, batch1.show || batch2.show)?;
 
         let (columns, projection, aggregations, _) =
@@ -166,6 +184,7 @@ pub fn combine<'a>(
             projection,
             aggregations,
             order_by: vec![],
+            types: unify_basic_types(&batch1.types, &batch2.types),
             level: batch1.level + 1,
             batch_count: batch1.batch_count + batch2.batch_count,
             show: batch1.show && batch2.show,
@@ -200,36 +219,36 @@ pub fn combine<'a>(
                 })
                 .collect::<Vec<_>>();
 
-            let (final_sort_col_index1, final_desc) = *batch1.order_by.last().unwrap();
+            let (final_sort_col_index1, final_desc, final_nulls_first) = *batch1.order_by.last().unwrap();
             let final_sort_col_index2 = batch2.order_by.last().unwrap().0;
             #[allow(clippy::branches_sharing_code)]
             let (merge_ops, merged_final_sort_col) = if batch1.order_by.len() == 1 {
-                let (index1, desc) = batch1.order_by[0];
-                let (index2, _) = batch2.order_by[0];
+                let (index1, desc, nulls_first) = batch1.order_by[0];
+                let (index2, _, _) = batch2.order_by[0];
                 let (left, right) = unify_types(&mut qp, left[index1], right[index2]);
-                qp.merge(left, right, limit, desc)
+                qp.merge(left, right, limit, desc, nulls_first)
             } else {
-                let (first_sort_col_index1, desc) = batch1.order_by[0];
-                let (first_sort_col_index2, _) = batch2.order_by[0];
+                let (first_sort_col_index1, desc, nulls_first) = batch1.order_by[0];
+                let (first_sort_col_index2, _, _) = batch2.order_by[0];
                 let (l, r) = unify_types(
                     &mut qp,
                     left[first_sort_col_index1],
                     right[first_sort_col_index2],
                 );
-                let mut partitioning = qp.partition(l, r, limit, desc);
+                let mut partitioning = qp.partition(l, r, limit, desc, nulls_first);
 
-                for i in 1..(left.len() - 1) {
-                    let (index1, desc) = batch1.order_by[i];
-                    let (index2, _) = batch1.order_by[i];
+                for i in 1..(batch1.order_by.len() - 1) {
+                    let (index1, desc, nulls_first) = batch1.order_by[i];
+                    let (index2, _, _) = batch2.order_by[i];
                     let (l, r) = unify_types(&mut qp, left[index1], right[index2]);
-                    partitioning = qp.subpartition(partitioning, l, r, desc);
+                    partitioning = qp.subpartition(partitioning, l, r, desc, nulls_first);
                 }
                 let (l, r) = unify_types(
                     &mut qp,
                     left[final_sort_col_index1],
                     right[final_sort_col_index2],
                 );
-                qp.merge_partitioned(partitioning, l, r, limit, batch1.order_by.last().unwrap().1)
+                qp.merge_partitioned(partitioning, l, r, limit, final_desc, final_nulls_first)
             };
 
             let mut projection = Vec::new();
@@ -243,18 +262,19 @@ pub fn combine<'a>(
                 }
             }
             let mut order_by = vec![];
-            for (&(ileft, desc), &(iright, _)) in batch1.order_by[0..batch1.order_by.len() - 1]
+            for (&(ileft, desc, nulls_first), &(iright, _, _)) in batch1.order_by[0..batch1.order_by.len() - 1]
                 .iter()
                 .zip(batch2.order_by.iter())
             {
                 let (l, r) = unify_types(&mut qp, left[ileft], right[iright]);
                 let merged = qp.merge_keep(merge_ops, l, r);
-                order_by.push((merged.any(), desc));
+                order_by.push((merged.any(), desc, nulls_first));
             }
-            order_by.push((merged_final_sort_col.any(), final_desc));
+            order_by.push((merged_final_sort_col.any(), final_desc, final_nulls_first));
 
             let mut executor = qp.prepare(data)?;
             let mut results = executor.prepare_no_columns();
+            results.set_limits(limits);
             executor.run(1, &mut results, batch1.show || batch2.show)?;
             let (columns, projection, _, order_by) =
                 results.collect_aliased(&projection, &[], &order_by);
@@ -264,6 +284,7 @@ pub fn combine<'a>(
                 projection,
                 order_by,
                 aggregations: vec![],
+                types: unify_basic_types(&batch1.types, &batch2.types),
                 level: batch1.level + 1,
                 batch_count: batch1.batch_count + batch2.batch_count,
                 show: batch1.show && batch2.show,
@@ -312,6 +333,7 @@ pub fn combine<'a>(
                 projection: batch1.projection,
                 aggregations: vec![],
                 order_by: vec![],
+                types: unify_basic_types(&batch1.types, &batch2.types),
                 level: batch1.level + 1,
                 batch_count: batch1.batch_count + batch2.batch_count,
                 show: batch1.show && batch2.show,
@@ -332,10 +354,34 @@ fn unify_types(
 ) -> (TypedBufferRef, TypedBufferRef) {
     let lub = left.tag.least_upper_bound(right.tag);
     if left.tag != lub {
-        left = qp.cast(left, lub);
+        left = cast(qp, left, lub);
     }
     if right.tag != lub {
-        right = qp.cast(right, lub);
+        right = cast(qp, right, lub);
     }
     (left, right)
 }
+
+fn cast(qp: &mut QueryPlanner, plan: TypedBufferRef, tag: EncodingType) -> TypedBufferRef {
+    // Results of type u8 are booleans, which are converted to `Val` like nullable booleans
+    if plan.tag == EncodingType::U8 && tag == EncodingType::Val {
+        let nullable = qp.make_nullable(plan);
+        qp.fuse_nulls(nullable)
+    } else {
+        qp.cast(plan, tag)
+    }
+}
+
+fn unify_basic_types(left: &[BasicType], right: &[BasicType]) -> Vec<BasicType> {
+    left.iter()
+        .zip(right)
+        .map(|(&left, &right)| match (left.non_nullable(), right.non_nullable()) {
+            (l, r) if l == r && left.is_nullable() => left,
+            (l, r) if l == r => right,
+            (BasicType::Null, _) => right,
+            (_, BasicType::Null) => left,
+            (BasicType::Integer, BasicType::Float) | (BasicType::Float, BasicType::Integer) => BasicType::Float,
+            _ => BasicType::Val,
+        })
+        .collect()
+}
@@ -62,6 +62,7 @@ impl BufferRef<Any> {
     pub fn nullable_str<'a>(self) -> BufferRef<Nullable<&'a str>> { self.transmute() }
 
     pub fn scalar_i64(self) -> BufferRef<Scalar<i64>> { self.transmute() }
+    pub fn scalar_f64(self) -> BufferRef<Scalar<OrderedFloat<f64>>> { self.transmute() }
     pub fn scalar_str<'a>(self) -> BufferRef<Scalar<&'a str>> { self.transmute() }
     pub fn scalar_string(self) -> BufferRef<Scalar<String>> { self.transmute() }
 
@@ -142,6 +143,12 @@ impl From<BufferRef<Scalar<i64>>> for TypedBufferRef {
     }
 }
 
+impl From<BufferRef<Scalar<OrderedFloat<f64>>>> for TypedBufferRef {
+    fn from(buffer: BufferRef<Scalar<OrderedFloat<f64>>>) -> TypedBufferRef {
+        TypedBufferRef::new(buffer.any(), EncodingType::ScalarF64)
+    }
+}
+
 impl From<BufferRef<usize>> for TypedBufferRef {
     fn from(buffer: BufferRef<usize>) -> TypedBufferRef {
         TypedBufferRef::new(buffer.any(), EncodingType::USize)
@@ -332,6 +339,11 @@ impl TypedBufferRef {
         Ok(self.buffer.scalar_i64())
     }
 
+    pub fn scalar_f64(&self) -> Result<BufferRef<Scalar<OrderedFloat<f64>>>, QueryError> {
+        ensure!(self.tag == EncodingType::ScalarF64, "{:?} != ScalarF64", self.tag);
+        Ok(self.buffer.scalar_f64())
+    }
+
     pub fn scalar_str<'a>(&self) -> Result<BufferRef<Scalar<&'a str>>, QueryError> {
         ensure!(self.tag == EncodingType::ScalarStr, "{:?} != ScalarStr", self.tag);
         Ok(self.buffer.scalar_str())
@@ -1,6 +1,7 @@
 use crate::bitvec::BitVec;
 use crate::engine::*;
 use crate::ingest::raw_val::RawVal;
+use ordered_float::OrderedFloat;
 use std::cmp;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
@@ -84,6 +85,12 @@ impl<'a> QueryExecutor<'a> {
             .unwrap()
     }
 
+    pub fn buffer_scalar_f64(&mut self, name: &'static str) -> BufferRef<Scalar<OrderedFloat<f64>>> {
+        self.named_buffer(name, EncodingType::ScalarF64)
+            .scalar_f64()
+            .unwrap()
+    }
+
     pub fn buffer_scalar_str<'b>(&mut self, name: &'static str) -> BufferRef<Scalar<&'b str>> {
         self.named_buffer(name, EncodingType::ScalarStr)
             .scalar_str()
@@ -523,9 +530,15 @@ impl<'a> QueryExecutor<'a> {
         let mut has_more = true;
         let mut iters = 0;
         while has_more {
+            if scratchpad.is_cancelled() {
+                return Err(QueryError::Cancelled);
+            }
             has_more = false;
             for &(op, streamable) in &self.stages[stage].ops {
                 self.ops[op].execute(stream && streamable, scratchpad)?;
+                if scratchpad.tracks_memory() {
+                    scratchpad.track_memory(&self.ops[op].outputs())?;
+                }
                 if show && iters == 0 {
                     println!("{}", self.ops[op].display(true));
                     for output in self.ops[op].outputs() {
@@ -0,0 +1,45 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::errors::QueryError;
+
+/// Shared by all executors of a query to enforce its memory limit and to abort it once it is cancelled.
+#[derive(Clone, Debug, Default)]
+pub struct QueryLimits {
+    pub memory: Option<Arc<MemoryBudget>>,
+    pub cancelled: Arc<AtomicBool>,
+}
+
+impl QueryLimits {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// Tracks the memory allocated by the buffers of all executors of a query.
+#[derive(Debug)]
+pub struct MemoryBudget {
+    limit: usize,
+    used: AtomicUsize,
+}
+
+impl MemoryBudget {
+    pub fn new(limit: usize) -> MemoryBudget {
+        MemoryBudget { limit, used: AtomicUsize::new(0) }
+    }
+
+    /// Records that a buffer changed size from `old` to `new` bytes.
+    /// Fails with `QueryError::ResourceLimit` if the query now uses more memory than its limit.
+    pub fn resize(&self, old: usize, new: usize) -> Result<(), QueryError> {
+        if new < old {
+            self.used.fetch_sub(old - new, Ordering::SeqCst);
+            return Ok(());
+        }
+        let used = self.used.fetch_add(new - old, Ordering::SeqCst) + new - old;
+        if used > self.limit {
+            Err(QueryError::ResourceLimit(self.limit))
+        } else {
+            Ok(())
+        }
+    }
+}
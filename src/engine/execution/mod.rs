@@ -2,9 +2,11 @@ pub mod query_task;
 mod buffer;
 mod executor;
 mod batch_merging;
+mod limits;
 mod scratchpad;
 
 pub use self::buffer::*;
 pub use self::scratchpad::*;
 pub use self::executor::*;
+pub use self::limits::{MemoryBudget, QueryLimits};
 pub use self::batch_merging::{BatchResult, combine};
\ No newline at end of file
@@ -1,19 +1,22 @@
 use std::cmp;
 use std::collections::HashMap;
 use std::collections::HashSet;
-use std::iter::Iterator;
+use std::iter::{self, Iterator};
 use std::mem;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
+use futures::channel::{mpsc, oneshot};
 use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 
 use crate::engine::*;
 use crate::ingest::raw_val::RawVal;
 use crate::mem_store::column::DataSource;
-use crate::mem_store::partition::Partition;
+use crate::mem_store::column_builder::null_column;
+use crate::mem_store::partition::{deleted_column_name, Partition};
 use crate::scheduler::disk_read_scheduler::DiskReadScheduler;
 use crate::scheduler::*;
 use crate::syntax::expression::*;
@@ -22,13 +25,20 @@ use crate::QueryResult;
 
 pub struct QueryTask {
     main_phase: NormalFormQuery,
+    aggregate_pass: Option<NormalFormQuery>,
     final_pass: Option<NormalFormQuery>,
     explain: bool,
     show: Vec<usize>,
     partitions: Vec<Arc<Partition>>,
     referenced_cols: HashSet<String>,
+    /// Types of referenced columns that do not exist in all partitions, e.g. because they were added to the table later.
+    /// Partitions without the column are queried as if it contained only null values.
+    missing_col_types: HashMap<String, BasicType>,
     output_colnames: Vec<String>,
+    /// Whether each output column contains timestamps, whose integer values are returned as `RawVal::Timestamp`.
+    timestamp_outputs: Vec<bool>,
     start_time_ns: i128,
+    deadline: Option<Instant>,
     db: Arc<DiskReadScheduler>,
 
     // Lifetime is not actually static, but tied to the lifetime of this struct.
@@ -39,6 +49,12 @@ pub struct QueryTask {
     batch_index: AtomicUsize,
     completed: AtomicBool,
     sender: SharedSender<QueryResult>,
+    /// If set, result rows are sent here instead of being returned as part of `QueryOutput`.
+    row_sink: Option<mpsc::UnboundedSender<Vec<RawVal>>>,
+    max_result_rows: Option<usize>,
+    limits: QueryLimits,
+    /// Registration in the list of running queries, removed once the result has been sent.
+    registration: Mutex<Option<Arc<QueryRegistration>>>,
 }
 
 pub struct QueryState<'a> {
@@ -47,17 +63,29 @@ pub struct QueryState<'a> {
     explains: Vec<String>,
     rows_scanned: usize,
     rows_collected: usize,
+    rows_streamed: usize,
+    coltypes: Option<Vec<String>>,
     colstacks: Vec<Vec<HashMap<String, Arc<dyn DataSource>>>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryOutput {
     pub colnames: Vec<String>,
+    /// Type of each output column, one of `integer`, `timestamp`, `float`, `string`, `boolean`, `null` or `mixed`.
+    pub coltypes: Vec<String>,
     pub rows: Vec<Vec<RawVal>>,
     pub query_plans: HashMap<String, u32>,
     pub stats: QueryStats,
 }
 
+/// Result of a streaming query, rows are received as they become available.
+pub struct QueryStream {
+    pub colnames: Vec<String>,
+    pub rows: mpsc::UnboundedReceiver<Vec<RawVal>>,
+    /// Resolves once the query has completed, `rows` of the output is always empty.
+    pub result: oneshot::Receiver<QueryResult>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct QueryStats {
     pub runtime_ns: u64,
@@ -70,10 +98,12 @@ impl QueryTask {
         explain: bool,
         show: Vec<usize>,
         source: Vec<Arc<Partition>>,
+        timeout: Option<Duration>,
         db: Arc<DiskReadScheduler>,
         sender: SharedSender<QueryResult>,
     ) -> Result<QueryTask, QueryError> {
         let start_time_ns = OffsetDateTime::unix_epoch().unix_timestamp_nanos();
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
         if query.is_select_star() {
             query.select = find_all_cols(&source)
                 .into_iter()
@@ -84,23 +114,46 @@ impl QueryTask {
                 .collect();
         }
 
+        query.inline_aliases(&find_all_cols(&source).into_iter().collect())?;
+
         let referenced_cols = query.find_referenced_cols();
 
-        let (main_phase, final_pass) = query.normalize()?;
-        let output_colnames = match &final_pass {
-            Some(final_pass) => final_pass.result_column_names()?,
-            None => main_phase.result_column_names()?,
+        let (main_phase, aggregate_pass, final_pass) = match query.split_distinct_aggregates()? {
+            Some((pre_aggregation, query)) => {
+                let (aggregate_pass, final_pass) = query.normalize()?;
+                (pre_aggregation, Some(aggregate_pass), final_pass)
+            }
+            None => {
+                let (main_phase, final_pass) = query.normalize()?;
+                (main_phase, None, final_pass)
+            }
+        };
+        let output_colnames = match (&final_pass, &aggregate_pass) {
+            (Some(final_pass), _) => final_pass.result_column_names()?,
+            (None, Some(aggregate_pass)) => aggregate_pass.result_column_names()?,
+            (None, None) => main_phase.result_column_names()?,
         };
 
+        // Columns read by the query must not be evicted before the query completes
+        for partition in &source {
+            partition.pin(&referenced_cols);
+        }
+        let missing_col_types = find_missing_col_types(&source, &referenced_cols, &db);
+
+        let timestamp_outputs = vec![false; output_colnames.len()];
         let task = QueryTask {
             main_phase,
+            aggregate_pass,
             final_pass,
             explain,
             show,
             partitions: source,
             referenced_cols,
+            missing_col_types,
             output_colnames,
+            timestamp_outputs,
             start_time_ns,
+            deadline,
             db,
 
             unsafe_state: Mutex::new(QueryState {
@@ -109,17 +162,24 @@ impl QueryTask {
                 explains: Vec::new(),
                 rows_scanned: 0,
                 rows_collected: 0,
+                rows_streamed: 0,
+                coltypes: None,
                 colstacks: Vec::new(),
             }),
             batch_index: AtomicUsize::new(0),
             completed: AtomicBool::new(false),
             sender,
+            row_sink: None,
+            max_result_rows: None,
+            limits: QueryLimits::default(),
+            registration: Mutex::new(None),
         };
 
         // If table is empty and there are no partitions we need to return result immediately, otherwise sender is dropped since no threads execute.
         if task.completed() {
             task.sender.send(Ok(QueryOutput {
                 colnames: task.output_colnames.clone(),
+                coltypes: vec!["null".to_string(); task.output_colnames.len()],
                 rows: vec![],
                 query_plans: Default::default(),
                 stats: QueryStats {
@@ -132,6 +192,55 @@ impl QueryTask {
         Ok(task)
     }
 
+    /// Sends result rows to `row_sink` instead of returning them in the `QueryOutput`.
+    /// Unordered selects send the rows of each partition as soon as it has been processed.
+    pub fn with_row_sink(mut self, row_sink: mpsc::UnboundedSender<Vec<RawVal>>) -> QueryTask {
+        if self.completed.load(Ordering::SeqCst) {
+            row_sink.close_channel();
+        }
+        self.row_sink = Some(row_sink);
+        self
+    }
+
+    /// Fails the query with `QueryError::ResultTooLarge` if it would return more than `max_result_rows` rows.
+    pub fn with_max_result_rows(mut self, max_result_rows: Option<usize>) -> QueryTask {
+        self.max_result_rows = max_result_rows;
+        self
+    }
+
+    /// Lists the query as running until it completes and aborts it with `QueryError::Cancelled` once it is cancelled.
+    pub fn with_registration(mut self, registration: Arc<QueryRegistration>) -> QueryTask {
+        self.limits.cancelled = registration.cancelled.clone();
+        if !self.completed.load(Ordering::SeqCst) {
+            self.registration = Mutex::new(Some(registration));
+        }
+        self
+    }
+
+    /// Fails the query with `QueryError::ResourceLimit` if the buffers of its executors use more than `memory_limit` bytes.
+    pub fn with_memory_limit(mut self, memory_limit: Option<usize>) -> QueryTask {
+        self.limits.memory = memory_limit.map(|limit| Arc::new(MemoryBudget::new(limit)));
+        self
+    }
+
+    /// Returns the output columns with the given names as timestamps.
+    pub fn with_timestamp_outputs(mut self, timestamp_outputs: &HashSet<String>) -> QueryTask {
+        self.timestamp_outputs = self.output_colnames.iter().map(|name| timestamp_outputs.contains(name)).collect();
+        self
+    }
+
+    pub fn output_colnames(&self) -> &[String] {
+        &self.output_colnames
+    }
+
+    fn streams_batches(&self) -> bool {
+        self.row_sink.is_some()
+            && self.aggregate_pass.is_none()
+            && self.final_pass.is_none()
+            && self.main_phase.aggregate.is_empty()
+            && self.main_phase.order_by.is_empty()
+    }
+
     pub fn run(&self) {
         let mut rows_scanned = 0;
         let mut rows_collected = 0;
@@ -139,21 +248,42 @@ impl QueryTask {
         let mut batch_results = Vec::<BatchResult>::new();
         let mut explains = Vec::new();
         while let Some((partition, id)) = self.next_partition() {
+            if let Some(error) = self.aborted() {
+                self.fail_with(error);
+                return;
+            }
             let show = self.show.iter().any(|&x| x == id);
-            let cols = partition.get_cols(&self.referenced_cols, &self.db);
-            rows_scanned += cols.iter().next().map_or(0, |c| c.1.len());
+            let mut cols = partition.get_cols(&self.referenced_cols, &self.db);
+            for (name, &basic_type) in &self.missing_col_types {
+                if !cols.contains_key(name) {
+                    let nulls = null_column(name, partition.len(), basic_type);
+                    cols.insert(name.clone(), Arc::new(nulls));
+                }
+            }
+            let partition_rows = cols.iter().next().map_or(0, |c| c.1.len());
+            rows_scanned += partition_rows;
+            let without_deleted_rows;
+            let main_phase = match partition.deleted_column() {
+                Some(deleted) => {
+                    let name = deleted_column_name(&self.referenced_cols);
+                    without_deleted_rows = self.main_phase.without_deleted_rows(&name);
+                    cols.insert(name, Arc::new(deleted));
+                    &without_deleted_rows
+                }
+                None => &self.main_phase,
+            };
             let unsafe_cols = unsafe {
                 mem::transmute::<
                     &HashMap<String, Arc<dyn DataSource>>,
                     &'static HashMap<String, Arc<dyn DataSource>>,
                 >(&cols)
             };
-            let (mut batch_result, explain) = match if self.main_phase.aggregate.is_empty() {
-                self.main_phase
-                    .run(unsafe_cols, self.explain, show, id, partition.len())
+            let (mut batch_result, explain) = match if main_phase.aggregate.is_empty() {
+                main_phase
+                    .run(unsafe_cols, self.explain, show, id, partition.len(), self.limits.clone())
             } else {
-                self.main_phase
-                    .run_aggregate(unsafe_cols, self.explain, show, id, partition.len())
+                main_phase
+                    .run_aggregate(unsafe_cols, self.explain, show, id, partition.len(), self.limits.clone())
             } {
                 Ok(result) => result,
                 Err(error) => {
@@ -161,8 +291,19 @@ impl QueryTask {
                     return;
                 }
             };
+            if self.streams_batches() {
+                self.stream_batch(&batch_result, partition_rows, explain);
+                if self.completed.load(Ordering::SeqCst) {
+                    return;
+                }
+                continue;
+            }
             colstack.push(cols);
             rows_collected += batch_result.len();
+            if let Some(max_result_rows) = self.unordered_select_exceeds_max_rows(rows_collected) {
+                self.fail_with(QueryError::ResultTooLarge(max_result_rows));
+                return;
+            }
             if let Some(explain) = explain {
                 explains.push(explain);
             }
@@ -170,7 +311,7 @@ impl QueryTask {
             // Merge only with previous batch results of same level to get O(n log n) complexity
             while let Some(br) = batch_results.pop() {
                 if br.level == batch_result.level {
-                    match combine(br, batch_result, self.combined_limit()) {
+                    match combine(br, batch_result, self.combined_limit(), self.limits.clone()) {
                         Ok(result) => batch_result = result,
                         Err(error) => {
                             self.fail_with(error);
@@ -192,7 +333,7 @@ impl QueryTask {
             }
         }
 
-        match QueryTask::combine_results(batch_results, self.combined_limit()) {
+        match QueryTask::combine_results(batch_results, self.combined_limit(), &self.limits) {
             Ok(Some(result)) => self.push_result(result, rows_scanned, rows_collected, explains),
             Err(error) => self.fail_with(error),
             _ => {}
@@ -201,14 +342,15 @@ impl QueryTask {
         self.push_colstack(colstack);
     }
 
-    fn combine_results(
-        batch_results: Vec<BatchResult>,
+    fn combine_results<'a>(
+        batch_results: Vec<BatchResult<'a>>,
         limit: usize,
-    ) -> Result<Option<BatchResult>, QueryError> {
+        limits: &QueryLimits,
+    ) -> Result<Option<BatchResult<'a>>, QueryError> {
         let mut full_result = None;
         for batch_result in batch_results {
             if let Some(partial) = full_result {
-                full_result = Some(combine(partial, batch_result, limit)?);
+                full_result = Some(combine(partial, batch_result, limit, limits.clone())?);
             } else {
                 full_result = Some(batch_result);
             }
@@ -240,7 +382,7 @@ impl QueryTask {
         {
             let mut owned_results = Vec::with_capacity(0);
             mem::swap(&mut owned_results, &mut state.partial_results);
-            let full_result = match QueryTask::combine_results(owned_results, self.combined_limit())
+            let full_result = match QueryTask::combine_results(owned_results, self.combined_limit(), &self.limits)
             {
                 Ok(result) => result.unwrap(),
                 Err(error) => {
@@ -248,7 +390,38 @@ impl QueryTask {
                     return;
                 }
             };
+            let pre_aggregation_sources;
+            let _pre_aggregation_buffers;
+            let full_result = if let Some(aggregate_pass) = &self.aggregate_pass {
+                let mut full_result = full_result;
+                _pre_aggregation_buffers = mem::take(&mut full_result.unsafe_referenced_buffers);
+                pre_aggregation_sources = full_result.into_columns();
+                let cols = unsafe {
+                    mem::transmute::<
+                        &HashMap<String, Arc<dyn DataSource>>,
+                        &'static HashMap<String, Arc<dyn DataSource>>,
+                    >(&pre_aggregation_sources)
+                };
+                match aggregate_pass.run_aggregate(
+                    cols,
+                    self.explain,
+                    !self.show.is_empty(),
+                    0xdead_beef,
+                    cols.iter().next().map(|(_, c)| c.len()).unwrap_or(0),
+                    self.limits.clone(),
+                ) {
+                    Ok((result, _)) => result,
+                    Err(error) => {
+                        self.fail_with_no_lock(error);
+                        return;
+                    }
+                }
+            } else {
+                full_result
+            };
             let final_result = if let Some(final_pass) = &self.final_pass {
+                let mut full_result = full_result;
+                let _referenced_buffers = mem::take(&mut full_result.unsafe_referenced_buffers);
                 let data_sources = full_result.into_columns();
                 let cols = unsafe {
                     mem::transmute::<
@@ -256,21 +429,118 @@ impl QueryTask {
                         &'static HashMap<String, Arc<dyn DataSource>>,
                     >(&data_sources)
                 };
-                let full_result = final_pass
-                    .run(
-                        cols,
-                        self.explain,
-                        !self.show.is_empty(),
-                        0xdead_beef,
-                        cols.iter().next().map(|(_, c)| c.len()).unwrap_or(0),
-                    )
-                    .unwrap()
-                    .0;
+                let full_result = match final_pass.run(
+                    cols,
+                    self.explain,
+                    !self.show.is_empty(),
+                    0xdead_beef,
+                    cols.iter().next().map(|(_, c)| c.len()).unwrap_or(0),
+                    self.limits.clone(),
+                ) {
+                    Ok((result, _)) => result,
+                    Err(error) => {
+                        self.fail_with_no_lock(error);
+                        return;
+                    }
+                };
                 self.convert_to_output_format(&full_result, state.rows_scanned, &state.explains)
             } else {
                 self.convert_to_output_format(&full_result, state.rows_scanned, &state.explains)
             };
-            self.sender.send(Ok(final_result));
+            let final_result = match final_result {
+                Ok(final_result) => final_result,
+                Err(error) => {
+                    self.fail_with_no_lock(error);
+                    return;
+                }
+            };
+            let final_result = match &self.row_sink {
+                Some(row_sink) => {
+                    let mut final_result = final_result;
+                    for row in final_result.rows.drain(..) {
+                        let _ = row_sink.unbounded_send(row);
+                    }
+                    row_sink.close_channel();
+                    final_result
+                }
+                None => final_result,
+            };
+            self.send_result(Ok(final_result));
+            self.completed.store(true, Ordering::SeqCst);
+        }
+    }
+
+    fn stream_batch(&self, batch: &BatchResult, rows_scanned: usize, explain: Option<String>) {
+        let mut state = self.unsafe_state.lock().unwrap();
+        if self.completed.load(Ordering::SeqCst) {
+            return;
+        }
+        state.completed_batches += batch.batch_count;
+        state.rows_scanned += rows_scanned;
+        state.explains.extend(explain);
+        let row_sink = self.row_sink.as_ref().unwrap();
+        let offset = self.main_phase.limit.offset as usize;
+        let limit = self.combined_limit();
+        // Keep rows of the first nonempty batch to infer types of columns that can hold any value
+        let infer_types = state.coltypes.is_none();
+        let mut rows = Vec::new();
+        for i in 0..batch.len() {
+            if state.rows_streamed >= limit {
+                break;
+            }
+            if state.rows_streamed >= offset {
+                if let Some(max_result_rows) = self.max_result_rows {
+                    if state.rows_streamed - offset >= max_result_rows {
+                        self.fail_with_no_lock(QueryError::ResultTooLarge(max_result_rows));
+                        return;
+                    }
+                }
+                let row = batch
+                    .projection
+                    .iter()
+                    .enumerate()
+                    .map(|(k, &j)| self.output_value(k, batch.columns[j].get_raw(i)))
+                    .collect::<Vec<_>>();
+                if infer_types {
+                    rows.push(row.clone());
+                }
+                let _ = row_sink.unbounded_send(row);
+            }
+            state.rows_streamed += 1;
+        }
+        if state.coltypes.is_none() && !rows.is_empty() {
+            state.coltypes = Some(
+                batch
+                    .projection
+                    .iter()
+                    .enumerate()
+                    .map(|(k, &j)| {
+                        self.output_type(k, batch.types[k], &*batch.columns[j], rows.iter().map(|row| &row[k]))
+                    })
+                    .collect(),
+            );
+        }
+
+        if state.completed_batches == self.partitions.len() || state.rows_streamed >= limit {
+            let coltypes = state.coltypes.take().unwrap_or_else(|| {
+                batch
+                    .projection
+                    .iter()
+                    .enumerate()
+                    .map(|(k, &j)| self.output_type(k, batch.types[k], &*batch.columns[j], iter::empty()))
+                    .collect()
+            });
+            self.send_result(Ok(QueryOutput {
+                colnames: self.output_colnames.clone(),
+                coltypes,
+                rows: vec![],
+                query_plans: query_plans(&state.explains),
+                stats: QueryStats {
+                    runtime_ns: (OffsetDateTime::unix_epoch().unix_timestamp_nanos() - self.start_time_ns) as u64,
+                    rows_scanned: state.rows_scanned,
+                },
+            }));
+            row_sink.close_channel();
             self.completed.store(true, Ordering::SeqCst);
         }
     }
@@ -292,7 +562,44 @@ impl QueryTask {
         self.completed.store(true, Ordering::SeqCst);
         self.batch_index
             .store(self.partitions.len(), Ordering::SeqCst);
-        self.sender.send(Err(error));
+        if let Some(row_sink) = &self.row_sink {
+            row_sink.close_channel();
+        }
+        self.send_result(Err(error));
+    }
+
+    fn send_result(&self, result: QueryResult) {
+        self.registration.lock().unwrap().take();
+        self.sender.send(result);
+    }
+
+    fn timed_out(&self) -> bool {
+        self.deadline.map_or(false, |deadline| Instant::now() >= deadline)
+    }
+
+    /// Error of a query that was aborted because it timed out or was cancelled.
+    fn aborted(&self) -> Option<QueryError> {
+        if self.limits.is_cancelled() {
+            Some(QueryError::Cancelled)
+        } else if self.timed_out() {
+            Some(QueryError::Timeout)
+        } else {
+            None
+        }
+    }
+
+    /// Unordered selects return all collected rows up to the limit, so they can be aborted as soon as too many rows were collected.
+    fn unordered_select_exceeds_max_rows(&self, rows_collected: usize) -> Option<usize> {
+        let max_result_rows = self.max_result_rows?;
+        let unordered_select =
+            self.main_phase.aggregate.is_empty() && self.main_phase.order_by.is_empty();
+        let limit = &self.main_phase.limit;
+        let rows = cmp::min(rows_collected.saturating_sub(limit.offset as usize), limit.limit as usize);
+        if unordered_select && self.aggregate_pass.is_none() && self.final_pass.is_none() && rows > max_result_rows {
+            Some(max_result_rows)
+        } else {
+            None
+        }
     }
 
     fn sufficient_rows(&self, rows_collected: usize) -> bool {
@@ -311,37 +618,78 @@ impl QueryTask {
         full_result: &BatchResult,
         rows_scanned: usize,
         explains: &[String],
-    ) -> QueryOutput {
-        let lo = self.final_pass.as_ref().map(|x| &x.limit).unwrap_or(&self.main_phase.limit);
+    ) -> Result<QueryOutput, QueryError> {
+        let lo = self
+            .final_pass
+            .as_ref()
+            .or(self.aggregate_pass.as_ref())
+            .map(|x| &x.limit)
+            .unwrap_or(&self.main_phase.limit);
         let limit = lo.limit as usize;
         let offset = lo.offset as usize;
         let mut result_rows = Vec::new();
-        let count = cmp::min(limit, full_result.len() - offset);
+        let count = cmp::min(limit, full_result.len().saturating_sub(offset));
+        if let Some(max_result_rows) = self.max_result_rows {
+            if count > max_result_rows {
+                return Err(QueryError::ResultTooLarge(max_result_rows));
+            }
+        }
         for i in offset..(count + offset) {
             let mut record = Vec::with_capacity(self.output_colnames.len());
             // TODO(#99): use column order of original query
-            for &j in &full_result.projection {
-                record.push(full_result.columns[j].get_raw(i));
-            }
-            for &(aggregation, _) in &full_result.aggregations {
-                record.push(full_result.columns[aggregation].get_raw(i));
+            let columns = full_result
+                .projection
+                .iter()
+                .cloned()
+                .chain(full_result.aggregations.iter().map(|&(aggregation, _)| aggregation));
+            for (k, j) in columns.enumerate() {
+                record.push(self.output_value(k, full_result.columns[j].get_raw(i)));
             }
             result_rows.push(record);
         }
-
-        let mut query_plans = HashMap::new();
-        for plan in explains {
-            *query_plans.entry(plan.to_owned()).or_insert(0) += 1
-        }
-
-        QueryOutput {
+        let coltypes = full_result
+            .projection
+            .iter()
+            .cloned()
+            .chain(full_result.aggregations.iter().map(|&(aggregation, _)| aggregation))
+            .enumerate()
+            .map(|(k, j)| {
+                let values = result_rows.iter().map(|row| &row[k]);
+                self.output_type(k, full_result.types[k], &*full_result.columns[j], values)
+            })
+            .collect();
+
+        Ok(QueryOutput {
             colnames: self.output_colnames.clone(),
+            coltypes,
             rows: result_rows,
-            query_plans,
+            query_plans: query_plans(explains),
             stats: QueryStats {
                 runtime_ns: (OffsetDateTime::unix_epoch().unix_timestamp_nanos() - self.start_time_ns) as u64,
                 rows_scanned,
             },
+        })
+    }
+
+    fn output_value(&self, column: usize, value: RawVal) -> RawVal {
+        match value {
+            RawVal::Int(millis) if self.timestamp_outputs.get(column) == Some(&true) => RawVal::Timestamp(millis),
+            value => value,
+        }
+    }
+
+    fn output_type<'a>(
+        &self,
+        column: usize,
+        planned_type: BasicType,
+        data: &dyn Data,
+        values: impl Iterator<Item = &'a RawVal>,
+    ) -> String {
+        let output_type = output_type(planned_type, data, values);
+        if output_type == "integer" && self.timestamp_outputs.get(column) == Some(&true) {
+            "timestamp".to_string()
+        } else {
+            output_type
         }
     }
 
@@ -350,6 +698,65 @@ impl QueryTask {
     }
 }
 
+fn query_plans(explains: &[String]) -> HashMap<String, u32> {
+    let mut query_plans = HashMap::new();
+    for plan in explains {
+        *query_plans.entry(plan.to_owned()).or_insert(0) += 1
+    }
+    query_plans
+}
+
+/// Name of the type of an output column as reported to clients.
+/// Columns of type `Val` report the planned type of the column, and only inspect the values if the planned type is
+/// not specific either, so that the type does not depend on which rows are returned.
+fn output_type<'a>(planned_type: BasicType, column: &dyn Data, values: impl Iterator<Item = &'a RawVal>) -> String {
+    let basic_type = match column.get_type() {
+        EncodingType::Str
+        | EncodingType::OptStr
+        | EncodingType::NullableStr
+        | EncodingType::ScalarStr
+        | EncodingType::ScalarString => BasicType::String,
+        EncodingType::I64
+        | EncodingType::U16
+        | EncodingType::U32
+        | EncodingType::U64
+        | EncodingType::NullableI64
+        | EncodingType::NullableU16
+        | EncodingType::NullableU32
+        | EncodingType::NullableU64
+        | EncodingType::ScalarI64 => BasicType::Integer,
+        EncodingType::U8 | EncodingType::NullableU8 => BasicType::Boolean,
+        EncodingType::F64 | EncodingType::NullableF64 | EncodingType::ScalarF64 => BasicType::Float,
+        _ if !matches!(planned_type, BasicType::Val | BasicType::Null) => planned_type,
+        // Values of e.g. `Val` columns can have any type, so inspect the values themselves
+        _ => {
+            let mut types = values.map(|v| v.get_type()).filter(|&t| t != BasicType::Null);
+            match types.next() {
+                Some(t) if types.all(|other| other == t) => t,
+                Some(_) => BasicType::Val,
+                None => BasicType::Null,
+            }
+        }
+    };
+    match basic_type {
+        BasicType::String | BasicType::NullableString => "string",
+        BasicType::Integer | BasicType::NullableInteger => "integer",
+        BasicType::Float | BasicType::NullableFloat => "float",
+        BasicType::Boolean => "boolean",
+        BasicType::Null => "null",
+        BasicType::Val => "mixed",
+    }
+    .to_string()
+}
+
+impl Drop for QueryTask {
+    fn drop(&mut self) {
+        for partition in &self.partitions {
+            partition.unpin(&self.referenced_cols);
+        }
+    }
+}
+
 impl Task for QueryTask {
     fn execute(&self) {
         self.run();
@@ -361,6 +768,14 @@ impl Task for QueryTask {
     fn multithreaded(&self) -> bool {
         true
     }
+    fn cancelled(&self) -> bool {
+        self.aborted().is_some()
+    }
+    fn cancel(&self) {
+        if let Some(error) = self.aborted() {
+            self.fail_with(error);
+        }
+    }
 }
 
 fn find_all_cols(source: &[Arc<Partition>]) -> Vec<String> {
@@ -373,3 +788,24 @@ fn find_all_cols(source: &[Arc<Partition>]) -> Vec<String> {
 
     cols.into_iter().collect()
 }
+
+pub(crate) fn find_missing_col_types(
+    source: &[Arc<Partition>],
+    referenced_cols: &HashSet<String>,
+    db: &DiskReadScheduler,
+) -> HashMap<String, BasicType> {
+    let mut missing_col_types = HashMap::new();
+    for name in referenced_cols {
+        let has_col = |partition: &Arc<Partition>| partition.col_names().contains(&name.as_str());
+        if source.iter().all(has_col) {
+            continue;
+        }
+        if let Some(partition) = source.iter().find(|p| has_col(p)) {
+            let cols = partition.get_cols(&[name.clone()].into_iter().collect(), db);
+            if let Some(col) = cols.get(name) {
+                missing_col_types.insert(name.clone(), col.full_type().decoded);
+            }
+        }
+    }
+    missing_col_types
+}
@@ -11,6 +11,9 @@ pub struct Scratchpad<'a> {
     null_maps: Vec<Option<usize>>,
     columns: HashMap<String, Vec<&'a dyn Data<'a>>>,
     pinned: Vec<bool>,
+    limits: QueryLimits,
+    /// Size of each buffer when it was last recorded in the memory budget.
+    buffer_sizes: Vec<usize>,
 }
 
 impl<'a> Scratchpad<'a> {
@@ -25,9 +28,42 @@ impl<'a> Scratchpad<'a> {
             null_maps: vec![None; count],
             columns,
             pinned: vec![false; count],
+            limits: QueryLimits::default(),
+            buffer_sizes: vec![0; count],
         }
     }
 
+    /// Accounts the memory used by the buffers of this scratchpad to the memory budget of `limits`.
+    pub fn set_limits(&mut self, limits: QueryLimits) {
+        self.limits = limits;
+    }
+
+    pub fn tracks_memory(&self) -> bool {
+        self.limits.memory.is_some()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.limits.is_cancelled()
+    }
+
+    /// Records the current size of `buffers` in the memory budget.
+    pub fn track_memory(&mut self, buffers: &[BufferRef<Any>]) -> Result<(), QueryError> {
+        let memory = match &self.limits.memory {
+            Some(memory) => memory,
+            None => return Ok(()),
+        };
+        for buffer in buffers {
+            let i = self.resolve(buffer);
+            let mut size = self.buffers[i].borrow().heap_size_of_children();
+            if let Some(null_map) = self.null_maps[i] {
+                size += self.buffers[null_map].borrow().heap_size_of_children();
+            }
+            let old = mem::replace(&mut self.buffer_sizes[i], size);
+            memory.resize(old, size)?;
+        }
+        Ok(())
+    }
+
     pub fn get_any(&self, index: BufferRef<Any>) -> Ref<dyn Data<'a>> {
         Ref::map(self.buffer(index).borrow(), |x| x.as_ref())
     }
@@ -160,12 +196,12 @@ impl<'a> Scratchpad<'a> {
         &mut self,
         projections: &[BufferRef<Any>],
         aggregations: &[(BufferRef<Any>, Aggregator)],
-        rankings: &[(BufferRef<Any>, bool)],
+        rankings: &[(BufferRef<Any>, bool, bool)],
     ) -> (
         Vec<BoxedData<'a>>,
         Vec<usize>,
         Vec<(usize, Aggregator)>,
-        Vec<(usize, bool)>,
+        Vec<(usize, bool, bool)>,
     ) {
         let mut collected_buffers = HashMap::<usize, usize>::default();
         let mut columns = Vec::new();
@@ -192,13 +228,13 @@ impl<'a> Scratchpad<'a> {
             }
         }
         let mut ranking_indices = Vec::new();
-        for &(ranking, desc) in rankings {
+        for &(ranking, desc, nulls_first) in rankings {
             let i = self.resolve(&ranking);
             if collected_buffers.contains_key(&i) {
-                ranking_indices.push((collected_buffers[&i], desc));
+                ranking_indices.push((collected_buffers[&i], desc, nulls_first));
             } else {
                 collected_buffers.insert(i, columns.len());
-                ranking_indices.push((columns.len(), desc));
+                ranking_indices.push((columns.len(), desc, nulls_first));
                 columns.push(self.collect_one(ranking));
             }
         }
@@ -360,11 +396,19 @@ impl<'a> Scratchpad<'a> {
         self.pinned[i] = false;
     }
 
-    pub fn collect_pinned(self) -> Vec<BoxedData<'a>> {
-        self.buffers
+    pub fn collect_pinned(mut self) -> Vec<BoxedData<'a>> {
+        mem::take(&mut self.buffers)
             .into_iter()
             .zip(self.pinned.iter())
             .filter_map(|(d, pinned)| if *pinned { Some(d.into_inner()) } else { None })
             .collect()
     }
 }
+
+impl<'a> Drop for Scratchpad<'a> {
+    fn drop(&mut self) {
+        if let Some(memory) = &self.limits.memory {
+            let _ = memory.resize(self.buffer_sizes.iter().sum(), 0);
+        }
+    }
+}
@@ -9,6 +9,8 @@ pub trait Aggregator<T, Acc> {
     fn unit() -> Acc;
     fn accumulate(accumulator: Acc, value: T) ->Acc;
     fn combine(accumulator1: Acc, accumulator2: Acc) -> Acc;
+    /// Aggregators that retain references to their inputs require the input buffers to stay alive.
+    fn can_stream_input() -> bool { true }
 }
 
 pub trait CheckedAggregator<T, Acc>: Aggregator<T, Acc> {
@@ -93,6 +95,34 @@ impl<V> Aggregator<V, OrderedFloat<f64>> for MinF64 where V: Into<OrderedFloat<f
     fn combine(accumulator1: OrderedFloat<f64>, accumulator2: OrderedFloat<f64>) -> OrderedFloat<f64> { std::cmp::min(accumulator1, accumulator2) }
 }
 
+pub struct MaxStr;
+
+impl<'a> Aggregator<&'a str, Option<&'a str>> for MaxStr {
+    fn unit() -> Option<&'a str> { None }
+    #[inline]
+    fn accumulate(accumulator: Option<&'a str>, value: &'a str) -> Option<&'a str> { std::cmp::max(accumulator, Some(value)) }
+    #[inline]
+    fn combine(accumulator1: Option<&'a str>, accumulator2: Option<&'a str>) -> Option<&'a str> { std::cmp::max(accumulator1, accumulator2) }
+    fn can_stream_input() -> bool { false }
+}
+
+pub struct MinStr;
+
+impl<'a> Aggregator<&'a str, Option<&'a str>> for MinStr {
+    fn unit() -> Option<&'a str> { None }
+    #[inline]
+    fn accumulate(accumulator: Option<&'a str>, value: &'a str) -> Option<&'a str> {
+        Some(accumulator.map_or(value, |acc| std::cmp::min(acc, value)))
+    }
+    #[inline]
+    fn combine(accumulator1: Option<&'a str>, accumulator2: Option<&'a str>) -> Option<&'a str> {
+        match (accumulator1, accumulator2) {
+            (Some(acc1), Some(acc2)) => Some(std::cmp::min(acc1, acc2)),
+            _ => accumulator1.or(accumulator2),
+        }
+    }
+    fn can_stream_input() -> bool { false }
+}
 
 pub struct Aggregate<T, U, V, A> {
     pub input: BufferRef<T>,
@@ -103,7 +133,7 @@ pub struct Aggregate<T, U, V, A> {
 }
 
 impl<'a, T, U, V, A: Aggregator<T, V>> VecOperator<'a> for Aggregate<T, U, V, A> where
-    T: VecData<T> + 'static, U: GenericIntVec<U>, V: VecData<V> + 'static {
+    T: VecData<T> + 'a, U: GenericIntVec<U>, V: VecData<V> + 'a {
     fn execute(&mut self, _: bool, scratchpad: &mut Scratchpad<'a>) -> Result<(), QueryError> {
         let nums = scratchpad.get(self.input);
         let grouping = scratchpad.get(self.grouping);
@@ -128,7 +158,7 @@ impl<'a, T, U, V, A: Aggregator<T, V>> VecOperator<'a> for Aggregate<T, U, V, A>
 
     fn inputs(&self) -> Vec<BufferRef<Any>> { vec![self.grouping.any(), self.input.any(), self.max_index.any()] }
     fn outputs(&self) -> Vec<BufferRef<Any>> { vec![self.output.any()] }
-    fn can_stream_input(&self, _: usize) -> bool { true }
+    fn can_stream_input(&self, _: usize) -> bool { A::can_stream_input() }
     fn can_stream_output(&self, _: usize) -> bool { false }
     fn allocates(&self) -> bool { true }
 
@@ -147,7 +177,7 @@ pub struct AggregateNullable<T, U, V, A> where A: Aggregator<T, V> {
 }
 
 impl<'a, T, U, V, A: Aggregator<T, V>> VecOperator<'a> for AggregateNullable<T, U, V, A> where
-    T: VecData<T> + 'static, U: GenericIntVec<U>, V: VecData<V> + 'static {
+    T: VecData<T> + 'a, U: GenericIntVec<U>, V: VecData<V> + 'a {
     fn execute(&mut self, _: bool, scratchpad: &mut Scratchpad<'a>) -> Result<(), QueryError> {
         let (nums, present) = scratchpad.get_nullable(self.input);
         let grouping = scratchpad.get(self.grouping);
@@ -173,7 +203,7 @@ impl<'a, T, U, V, A: Aggregator<T, V>> VecOperator<'a> for AggregateNullable<T,
 
     fn inputs(&self) -> Vec<BufferRef<Any>> { vec![self.grouping.any(), self.input.any(), self.max_index.any()] }
     fn outputs(&self) -> Vec<BufferRef<Any>> { vec![self.output.any()] }
-    fn can_stream_input(&self, _: usize) -> bool { true }
+    fn can_stream_input(&self, _: usize) -> bool { A::can_stream_input() }
     fn can_stream_output(&self, _: usize) -> bool { false }
     fn allocates(&self) -> bool { true }
 
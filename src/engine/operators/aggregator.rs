@@ -1,5 +1,8 @@
+use ordered_float::OrderedFloat;
+
 // TODO: would probably be better to have two types here, an UntypedAggregator emitted by parser which is then converted into the right TypedAggregator by query planner
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
 pub enum Aggregator {
     SumI64 = 0,
     SumF64 = 1,
@@ -8,4 +11,38 @@ pub enum Aggregator {
     MaxF64 = 4,
     MinI64 = 5,
     MinF64 = 6,
-}
\ No newline at end of file
+    /// Only emitted by the parser, expanded into `SumI64` and `Count` by `Query::normalize`.
+    Avg = 7,
+    /// Only emitted by the parser, rewritten into a grouping column by `Query::split_distinct_aggregates`.
+    CountDistinct = 8,
+    MaxStr = 9,
+    MinStr = 10,
+    /// Population variance, only emitted by the parser and expanded into sums and `Count` by `Query::normalize`.
+    VarPop = 11,
+    /// Population standard deviation, only emitted by the parser and expanded like `VarPop`.
+    StdDev = 12,
+    /// `PERCENTILE(expr, quantile)`, approximated from a histogram of the values in each group.
+    /// Emitted by the parser, the histogram is computed by `Query::split_distinct_aggregates`.
+    ApproxPercentile(OrderedFloat<f64>) = 13,
+}
+
+impl Aggregator {
+    /// The variant of this aggregator that operates on floats.
+    pub fn float(self) -> Aggregator {
+        match self {
+            Aggregator::SumI64 => Aggregator::SumF64,
+            Aggregator::MaxI64 => Aggregator::MaxF64,
+            Aggregator::MinI64 => Aggregator::MinF64,
+            _ => self,
+        }
+    }
+
+    /// The variant of this aggregator that operates on strings.
+    pub fn string(self) -> Aggregator {
+        match self {
+            Aggregator::MaxI64 => Aggregator::MaxStr,
+            Aggregator::MinI64 => Aggregator::MinStr,
+            _ => self,
+        }
+    }
+}
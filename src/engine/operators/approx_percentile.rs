@@ -0,0 +1,82 @@
+use ordered_float::OrderedFloat;
+
+use crate::engine::*;
+
+/// Values are assigned to histogram buckets whose bounds grow by this factor, so percentiles have a relative error
+/// of at most half a percent.
+pub const BUCKET_GROWTH: f64 = 1.01;
+/// Added to the logarithmic bucket index of nonzero values, so that the buckets of positive values are positive,
+/// the buckets of negative values are negative and zero is in bucket 0.
+pub const BUCKET_OFFSET: i64 = 1 << 17;
+/// Buckets and the number of values they contain are packed into the single input `bucket * MAX_COUNT + count`.
+pub const MAX_COUNT: i64 = 1 << 40;
+
+/// Value that represents all values in `bucket`, the geometric mean of its bounds.
+pub fn bucket_value(bucket: i64) -> f64 {
+    if bucket == 0 {
+        return 0.0;
+    }
+    let exponent = (bucket.abs() - BUCKET_OFFSET) as f64 + 0.5;
+    bucket.signum() as f64 * BUCKET_GROWTH.powf(exponent)
+}
+
+/// Computes a percentile for each group from its histogram, given as packed buckets and counts.
+pub struct ApproxPercentile<T> {
+    pub input: BufferRef<i64>,
+    pub grouping: BufferRef<T>,
+    pub output: BufferRef<OrderedFloat<f64>>,
+    pub max_index: BufferRef<Scalar<i64>>,
+    pub quantile: f64,
+}
+
+impl<'a, T: GenericIntVec<T>> VecOperator<'a> for ApproxPercentile<T> {
+    fn execute(&mut self, _: bool, scratchpad: &mut Scratchpad<'a>) -> Result<(), QueryError> {
+        let len = scratchpad.get_scalar(&self.max_index) as usize + 1;
+        let mut histograms = vec![Vec::new(); len];
+        {
+            let packed = scratchpad.get(self.input);
+            let grouping = scratchpad.get(self.grouping);
+            for (g, &p) in grouping.iter().zip(packed.iter()) {
+                let count = p.rem_euclid(MAX_COUNT);
+                if count > 0 {
+                    histograms[g.cast_usize()].push((p.div_euclid(MAX_COUNT), count));
+                }
+            }
+        }
+
+        let percentiles = histograms
+            .into_iter()
+            .map(|mut histogram| {
+                histogram.sort_unstable();
+                let total = histogram.iter().map(|&(_, count)| count).sum::<i64>();
+                // Nearest rank, the smallest bucket that contains at least `quantile` of all values
+                let rank = ((self.quantile * total as f64).ceil() as i64).max(1);
+                let mut cumulative = 0;
+                for (bucket, count) in histogram {
+                    cumulative += count;
+                    if cumulative >= rank {
+                        return OrderedFloat(bucket_value(bucket));
+                    }
+                }
+                OrderedFloat(0.0)
+            })
+            .collect();
+        scratchpad.set(self.output, percentiles);
+        Ok(())
+    }
+
+    fn init(&mut self, _: usize, _: usize, scratchpad: &mut Scratchpad<'a>) {
+        scratchpad.set(self.output, Vec::with_capacity(0));
+    }
+
+    fn inputs(&self) -> Vec<BufferRef<Any>> { vec![self.grouping.any(), self.input.any(), self.max_index.any()] }
+    fn outputs(&self) -> Vec<BufferRef<Any>> { vec![self.output.any()] }
+    fn can_stream_input(&self, _: usize) -> bool { false }
+    fn can_stream_output(&self, _: usize) -> bool { false }
+    fn allocates(&self) -> bool { true }
+
+    fn display_op(&self, _: bool) -> String {
+        format!("{}[{}] = percentile({}, {})", self.output, self.grouping, self.input, self.quantile)
+    }
+    fn display_output(&self) -> bool { false }
+}
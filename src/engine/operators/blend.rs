@@ -0,0 +1,234 @@
+use std::cell::Ref;
+use std::fmt;
+
+use ordered_float::OrderedFloat;
+
+use crate::bitvec::*;
+use crate::engine::*;
+
+/// Input to a blend operator, which may be a vector, a nullable vector, a constant or null.
+#[derive(Debug, Clone, Copy)]
+pub enum BlendInput<T> {
+    Vec(BufferRef<T>),
+    Nullable(BufferRef<Nullable<T>>),
+    Scalar(BufferRef<Scalar<T>>),
+    Null,
+}
+
+enum BlendValues<'b, T> {
+    Vec(Ref<'b, [T]>),
+    Nullable(Ref<'b, [T]>, Ref<'b, [u8]>),
+    Scalar(T),
+    Null,
+}
+
+pub trait BlendData<'a>: VecData<Self> + Default + 'a {
+    fn blend_input(input: TypedBufferRef) -> Result<BlendInput<Self>, QueryError>;
+    fn scalar(scratchpad: &Scratchpad<'a>, buffer: BufferRef<Scalar<Self>>) -> Result<Self, QueryError>;
+}
+
+impl<'a> BlendData<'a> for i64 {
+    fn blend_input(input: TypedBufferRef) -> Result<BlendInput<i64>, QueryError> {
+        Ok(match input.tag {
+            EncodingType::Null => BlendInput::Null,
+            EncodingType::ScalarI64 => BlendInput::Scalar(input.scalar_i64()?),
+            EncodingType::NullableI64 => BlendInput::Nullable(input.nullable_i64()?),
+            _ => BlendInput::Vec(input.i64()?),
+        })
+    }
+
+    fn scalar(scratchpad: &Scratchpad<'a>, buffer: BufferRef<Scalar<i64>>) -> Result<i64, QueryError> {
+        Ok(scratchpad.get_scalar(&buffer))
+    }
+}
+
+impl<'a> BlendData<'a> for &'a str {
+    fn blend_input(input: TypedBufferRef) -> Result<BlendInput<&'a str>, QueryError> {
+        Ok(match input.tag {
+            EncodingType::Null => BlendInput::Null,
+            EncodingType::ScalarStr => BlendInput::Scalar(input.scalar_str()?),
+            EncodingType::NullableStr => BlendInput::Nullable(input.nullable_str()?),
+            _ => BlendInput::Vec(input.str()?),
+        })
+    }
+
+    fn scalar(scratchpad: &Scratchpad<'a>, buffer: BufferRef<Scalar<&'a str>>) -> Result<&'a str, QueryError> {
+        Ok(scratchpad.get_scalar(&buffer))
+    }
+}
+
+impl<'a> BlendData<'a> for OrderedFloat<f64> {
+    fn blend_input(input: TypedBufferRef) -> Result<BlendInput<OrderedFloat<f64>>, QueryError> {
+        Ok(match input.tag {
+            EncodingType::Null => BlendInput::Null,
+            EncodingType::ScalarF64 => BlendInput::Scalar(input.scalar_f64()?),
+            EncodingType::NullableF64 => BlendInput::Nullable(input.nullable_f64()?),
+            _ => BlendInput::Vec(input.f64()?),
+        })
+    }
+
+    fn scalar(scratchpad: &Scratchpad<'a>, buffer: BufferRef<Scalar<OrderedFloat<f64>>>) -> Result<OrderedFloat<f64>, QueryError> {
+        Ok(scratchpad.get_scalar(&buffer))
+    }
+}
+
+impl<'a> BlendData<'a> for u8 {
+    fn blend_input(input: TypedBufferRef) -> Result<BlendInput<u8>, QueryError> {
+        Ok(match input.tag {
+            EncodingType::Null => BlendInput::Null,
+            EncodingType::NullableU8 => BlendInput::Nullable(input.nullable_u8()?),
+            _ => BlendInput::Vec(input.u8()?),
+        })
+    }
+
+    fn scalar(_: &Scratchpad<'a>, buffer: BufferRef<Scalar<u8>>) -> Result<u8, QueryError> {
+        Err(fatal!("Boolean scalars are not supported: {}", buffer))
+    }
+}
+
+impl<'a, T: BlendData<'a>> BlendInput<T> {
+    fn get<'b>(&self, scratchpad: &'b Scratchpad<'a>) -> Result<BlendValues<'b, T>, QueryError> {
+        Ok(match *self {
+            BlendInput::Vec(buffer) => BlendValues::Vec(scratchpad.get(buffer)),
+            BlendInput::Nullable(buffer) => {
+                let (data, present) = scratchpad.get_nullable(buffer);
+                BlendValues::Nullable(data, present)
+            }
+            BlendInput::Scalar(buffer) => BlendValues::Scalar(T::scalar(scratchpad, buffer)?),
+            BlendInput::Null => BlendValues::Null,
+        })
+    }
+
+    fn any(&self) -> Option<BufferRef<Any>> {
+        match *self {
+            BlendInput::Vec(buffer) => Some(buffer.any()),
+            BlendInput::Nullable(buffer) => Some(buffer.any()),
+            BlendInput::Scalar(buffer) => Some(buffer.any()),
+            BlendInput::Null => None,
+        }
+    }
+}
+
+impl<T> fmt::Display for BlendInput<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BlendInput::Vec(ref buffer) => write!(f, "{}", buffer),
+            BlendInput::Nullable(ref buffer) => write!(f, "{}", buffer),
+            BlendInput::Scalar(ref buffer) => write!(f, "{}", buffer),
+            BlendInput::Null => write!(f, "null"),
+        }
+    }
+}
+
+impl<'b, T: Copy> BlendValues<'b, T> {
+    fn len(&self) -> usize {
+        match *self {
+            BlendValues::Vec(ref data) | BlendValues::Nullable(ref data, _) => data.len(),
+            BlendValues::Scalar(_) | BlendValues::Null => 0,
+        }
+    }
+
+    fn get(&self, i: usize) -> Option<T> {
+        match *self {
+            BlendValues::Vec(ref data) => Some(data[i]),
+            BlendValues::Nullable(ref data, ref present) => {
+                if (&**present).is_set(i) {
+                    Some(data[i])
+                } else {
+                    None
+                }
+            }
+            BlendValues::Scalar(value) => Some(value),
+            BlendValues::Null => None,
+        }
+    }
+}
+
+fn blend_inputs<'a, T: BlendData<'a>>(cond: &BlendInput<u8>, lhs: &BlendInput<T>, rhs: &BlendInput<T>) -> Vec<BufferRef<Any>> {
+    let mut inputs = vec![];
+    inputs.extend(cond.any());
+    inputs.extend(lhs.any());
+    inputs.extend(rhs.any());
+    inputs
+}
+
+/// Selects the value of `lhs` where `cond` is true and the value of `rhs` otherwise.
+pub struct Blend<T> {
+    pub cond: BlendInput<u8>,
+    pub lhs: BlendInput<T>,
+    pub rhs: BlendInput<T>,
+    pub blended: BufferRef<T>,
+}
+
+impl<'a, T: BlendData<'a>> VecOperator<'a> for Blend<T> {
+    fn execute(&mut self, stream: bool, scratchpad: &mut Scratchpad<'a>) -> Result<(), QueryError> {
+        let cond = self.cond.get(scratchpad)?;
+        let lhs = self.lhs.get(scratchpad)?;
+        let rhs = self.rhs.get(scratchpad)?;
+        let mut blended = scratchpad.get_mut(self.blended);
+        if stream { blended.clear(); }
+        for i in 0..cond.len() {
+            let value = if cond.get(i).map_or(false, |c| c != 0) { lhs.get(i) } else { rhs.get(i) };
+            blended.push(value.unwrap_or_default());
+        }
+        Ok(())
+    }
+
+    fn init(&mut self, _: usize, batch_size: usize, scratchpad: &mut Scratchpad<'a>) {
+        scratchpad.set(self.blended, Vec::with_capacity(batch_size));
+    }
+
+    fn inputs(&self) -> Vec<BufferRef<Any>> { blend_inputs(&self.cond, &self.lhs, &self.rhs) }
+    fn outputs(&self) -> Vec<BufferRef<Any>> { vec![self.blended.any()] }
+    fn can_stream_input(&self, _: usize) -> bool { true }
+    fn can_stream_output(&self, _: usize) -> bool { true }
+    fn allocates(&self) -> bool { true }
+
+    fn display_op(&self, _: bool) -> String {
+        format!("if {} then {} else {}", self.cond, self.lhs, self.rhs)
+    }
+}
+
+/// Selects the value of `lhs` where `cond` is true and the value of `rhs` otherwise.
+/// The output is null where the selected input is null.
+pub struct BlendNullable<T> {
+    pub cond: BlendInput<u8>,
+    pub lhs: BlendInput<T>,
+    pub rhs: BlendInput<T>,
+    pub blended: BufferRef<Nullable<T>>,
+}
+
+impl<'a, T: BlendData<'a>> VecOperator<'a> for BlendNullable<T> {
+    fn execute(&mut self, stream: bool, scratchpad: &mut Scratchpad<'a>) -> Result<(), QueryError> {
+        let cond = self.cond.get(scratchpad)?;
+        let lhs = self.lhs.get(scratchpad)?;
+        let rhs = self.rhs.get(scratchpad)?;
+        let (mut blended, mut present) = scratchpad.get_mut_nullable(self.blended);
+        if stream {
+            blended.clear();
+            present.clear();
+        }
+        for i in 0..cond.len() {
+            let value = if cond.get(i).map_or(false, |c| c != 0) { lhs.get(i) } else { rhs.get(i) };
+            if value.is_some() {
+                present.set(i);
+            }
+            blended.push(value.unwrap_or_default());
+        }
+        Ok(())
+    }
+
+    fn init(&mut self, _: usize, batch_size: usize, scratchpad: &mut Scratchpad<'a>) {
+        scratchpad.set_nullable(self.blended, Vec::with_capacity(batch_size), Vec::with_capacity(batch_size / 8 + 1));
+    }
+
+    fn inputs(&self) -> Vec<BufferRef<Any>> { blend_inputs(&self.cond, &self.lhs, &self.rhs) }
+    fn outputs(&self) -> Vec<BufferRef<Any>> { vec![self.blended.any()] }
+    fn can_stream_input(&self, _: usize) -> bool { true }
+    fn can_stream_output(&self, _: usize) -> bool { true }
+    fn allocates(&self) -> bool { true }
+
+    fn display_op(&self, _: bool) -> String {
+        format!("if {} then {} else {}", self.cond, self.lhs, self.rhs)
+    }
+}
@@ -0,0 +1,63 @@
+use crate::engine::*;
+use std::fmt;
+use std::mem;
+use std::str;
+
+/// Converts strings to lowercase or uppercase.
+pub struct ChangeCase<'a> {
+    pub input: BufferRef<&'a str>,
+    pub stringstore: BufferRef<u8>,
+    pub output: BufferRef<&'a str>,
+    pub uppercase: bool,
+}
+
+impl<'a> VecOperator<'a> for ChangeCase<'a> {
+    fn execute(&mut self, _: bool, scratchpad: &mut Scratchpad<'a>) -> Result<(), QueryError> {
+        let input = scratchpad.get(self.input).to_vec();
+        // Converting case may change the number of bytes in a string, so record where each one ends
+        let mut stringstore = Vec::with_capacity(input.iter().map(|s| s.len()).sum());
+        let mut ends = Vec::with_capacity(input.len());
+        for s in &input {
+            let converted = if self.uppercase { s.to_uppercase() } else { s.to_lowercase() };
+            stringstore.extend_from_slice(converted.as_bytes());
+            ends.push(stringstore.len());
+        }
+        scratchpad.set(self.stringstore, stringstore);
+
+        // Strings point into `stringstore` which is pinned and never modified afterwards
+        let stringstore = scratchpad.get_pinned(self.stringstore);
+        let mut output = scratchpad.get_mut(self.output);
+        let mut offset = 0;
+        for end in ends {
+            output.push(unsafe {
+                mem::transmute::<_, &'a str>(str::from_utf8_unchecked(&stringstore[offset..end]))
+            });
+            offset = end;
+        }
+        Ok(())
+    }
+
+    fn init(&mut self, _: usize, batch_size: usize, scratchpad: &mut Scratchpad<'a>) {
+        scratchpad.set(self.output, Vec::with_capacity(batch_size));
+    }
+
+    fn inputs(&self) -> Vec<BufferRef<Any>> { vec![self.input.any()] }
+    fn outputs(&self) -> Vec<BufferRef<Any>> { vec![self.output.any()] }
+    fn can_stream_input(&self, _: usize) -> bool { false }
+    fn can_stream_output(&self, _: usize) -> bool { false }
+    fn allocates(&self) -> bool { true }
+
+    fn display_op(&self, _: bool) -> String {
+        format!("{}({})", if self.uppercase { "upper" } else { "lower" }, self.input)
+    }
+}
+
+impl<'a> fmt::Debug for ChangeCase<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ChangeCase {{ input: {}, output: {}, uppercase: {} }}",
+            self.input, self.output, self.uppercase
+        )
+    }
+}
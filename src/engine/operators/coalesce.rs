@@ -0,0 +1,77 @@
+use crate::bitvec::*;
+use crate::engine::*;
+
+/// Selects the value of `lhs` where it is present and the value of `rhs` otherwise.
+pub struct Coalesce<T> {
+    pub lhs: BufferRef<Nullable<T>>,
+    pub rhs: BufferRef<T>,
+    pub coalesced: BufferRef<T>,
+}
+
+impl<'a, T: VecData<T> + 'a> VecOperator<'a> for Coalesce<T> {
+    fn execute(&mut self, stream: bool, scratchpad: &mut Scratchpad<'a>) -> Result<(), QueryError> {
+        let (lhs, present) = scratchpad.get_nullable(self.lhs);
+        let rhs = scratchpad.get(self.rhs);
+        let mut coalesced = scratchpad.get_mut(self.coalesced);
+        if stream { coalesced.clear(); }
+        for i in 0..lhs.len() {
+            if (&*present).is_set(i) {
+                coalesced.push(lhs[i]);
+            } else {
+                coalesced.push(rhs[i]);
+            }
+        }
+        Ok(())
+    }
+
+    fn init(&mut self, _: usize, batch_size: usize, scratchpad: &mut Scratchpad<'a>) {
+        scratchpad.set(self.coalesced, Vec::with_capacity(batch_size));
+    }
+
+    fn inputs(&self) -> Vec<BufferRef<Any>> { vec![self.lhs.any(), self.rhs.any()] }
+    fn outputs(&self) -> Vec<BufferRef<Any>> { vec![self.coalesced.any()] }
+    fn can_stream_input(&self, _: usize) -> bool { true }
+    fn can_stream_output(&self, _: usize) -> bool { true }
+    fn allocates(&self) -> bool { true }
+
+    fn display_op(&self, _: bool) -> String {
+        format!("coalesce({}, {})", self.lhs, self.rhs)
+    }
+}
+
+pub struct CoalesceScalar<T> {
+    pub lhs: BufferRef<Nullable<T>>,
+    pub rhs: BufferRef<Scalar<T>>,
+    pub coalesced: BufferRef<T>,
+}
+
+impl<'a, T: VecData<T> + ScalarData<T> + 'a> VecOperator<'a> for CoalesceScalar<T> {
+    fn execute(&mut self, stream: bool, scratchpad: &mut Scratchpad<'a>) -> Result<(), QueryError> {
+        let (lhs, present) = scratchpad.get_nullable(self.lhs);
+        let rhs = scratchpad.get_scalar(&self.rhs);
+        let mut coalesced = scratchpad.get_mut(self.coalesced);
+        if stream { coalesced.clear(); }
+        for i in 0..lhs.len() {
+            if (&*present).is_set(i) {
+                coalesced.push(lhs[i]);
+            } else {
+                coalesced.push(rhs);
+            }
+        }
+        Ok(())
+    }
+
+    fn init(&mut self, _: usize, batch_size: usize, scratchpad: &mut Scratchpad<'a>) {
+        scratchpad.set(self.coalesced, Vec::with_capacity(batch_size));
+    }
+
+    fn inputs(&self) -> Vec<BufferRef<Any>> { vec![self.lhs.any(), self.rhs.any()] }
+    fn outputs(&self) -> Vec<BufferRef<Any>> { vec![self.coalesced.any()] }
+    fn can_stream_input(&self, _: usize) -> bool { true }
+    fn can_stream_output(&self, _: usize) -> bool { true }
+    fn allocates(&self) -> bool { true }
+
+    fn display_op(&self, _: bool) -> String {
+        format!("coalesce({}, {})", self.lhs, self.rhs)
+    }
+}
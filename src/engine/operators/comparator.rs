@@ -110,3 +110,57 @@ impl<'a> Comparator<Option<&'a str>> for CmpGreaterThan {
     fn cmp_eq(left: Option<&str>, right: Option<&str>) -> bool { left >= right }
     fn is_less_than() -> bool { false }
 }
+
+
+/// Ascending order that treats fused nulls (`i64::MIN` and `None`) as larger than all other values.
+#[derive(Debug)]
+pub struct CmpLessThanNullsLast;
+
+/// Descending order that treats fused nulls (`i64::MIN` and `None`) as larger than all other values.
+#[derive(Debug)]
+pub struct CmpGreaterThanNullsFirst;
+
+// Types without a null representation are ordered as by `CmpLessThan`/`CmpGreaterThan`.
+macro_rules! impl_non_nullable_comparators {
+    ($($t:ty),*) => {
+        $(
+            impl<'a> Comparator<$t> for CmpLessThanNullsLast {
+                fn cmp(left: $t, right: $t) -> bool { <CmpLessThan as Comparator<$t>>::cmp(left, right) }
+                fn cmp_eq(left: $t, right: $t) -> bool { <CmpLessThan as Comparator<$t>>::cmp_eq(left, right) }
+                fn is_less_than() -> bool { <CmpLessThan as Comparator<$t>>::is_less_than() }
+            }
+
+            impl<'a> Comparator<$t> for CmpGreaterThanNullsFirst {
+                fn cmp(left: $t, right: $t) -> bool { <CmpGreaterThan as Comparator<$t>>::cmp(left, right) }
+                fn cmp_eq(left: $t, right: $t) -> bool { <CmpGreaterThan as Comparator<$t>>::cmp_eq(left, right) }
+                fn is_less_than() -> bool { <CmpGreaterThan as Comparator<$t>>::is_less_than() }
+            }
+        )*
+    }
+}
+
+impl_non_nullable_comparators!(u8, u16, u32, u64, OrderedFloat<f64>, &'a str);
+
+impl Comparator<i64> for CmpLessThanNullsLast {
+    fn cmp(left: i64, right: i64) -> bool { (left == i64::MIN, left) < (right == i64::MIN, right) }
+    fn cmp_eq(left: i64, right: i64) -> bool { (left == i64::MIN, left) <= (right == i64::MIN, right) }
+    fn is_less_than() -> bool { true }
+}
+
+impl<'a> Comparator<Option<&'a str>> for CmpLessThanNullsLast {
+    fn cmp(left: Option<&str>, right: Option<&str>) -> bool { (left.is_none(), left) < (right.is_none(), right) }
+    fn cmp_eq(left: Option<&str>, right: Option<&str>) -> bool { (left.is_none(), left) <= (right.is_none(), right) }
+    fn is_less_than() -> bool { true }
+}
+
+impl Comparator<i64> for CmpGreaterThanNullsFirst {
+    fn cmp(left: i64, right: i64) -> bool { (left == i64::MIN, left) > (right == i64::MIN, right) }
+    fn cmp_eq(left: i64, right: i64) -> bool { (left == i64::MIN, left) >= (right == i64::MIN, right) }
+    fn is_less_than() -> bool { false }
+}
+
+impl<'a> Comparator<Option<&'a str>> for CmpGreaterThanNullsFirst {
+    fn cmp(left: Option<&str>, right: Option<&str>) -> bool { (left.is_none(), left) > (right.is_none(), right) }
+    fn cmp_eq(left: Option<&str>, right: Option<&str>) -> bool { (left.is_none(), left) >= (right.is_none(), right) }
+    fn is_less_than() -> bool { false }
+}
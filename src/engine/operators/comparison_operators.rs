@@ -1,9 +1,39 @@
 use super::binary_operator::*;
 
 use num::PrimInt;
+use ordered_float::OrderedFloat;
 
 use crate::engine::data_types::GenericIntVec;
 
+/// Implements a comparison between floats and between floats and integers of any width.
+/// Comparisons are performed on the raw `f64` values rather than the total order of `OrderedFloat`,
+/// so that NaN does not match any comparison.
+macro_rules! float_comparison {
+    ($op:ident, |$l:ident, $r:ident| $cmp:expr, $symbol:expr) => {
+        impl BinaryOp<OrderedFloat<f64>, OrderedFloat<f64>, u8> for $op {
+            #[inline]
+            fn perform(l: OrderedFloat<f64>, r: OrderedFloat<f64>) -> u8 { let ($l, $r) = (l.0, r.0); $cmp as u8 }
+            fn symbol() -> &'static str { $symbol }
+        }
+        float_comparison!($op, |$l, $r| $cmp, $symbol, u8, u16, u32, i64);
+    };
+    ($op:ident, |$l:ident, $r:ident| $cmp:expr, $symbol:expr, $($int:ty),*) => {
+        $(
+            impl BinaryOp<OrderedFloat<f64>, $int, u8> for $op {
+                #[inline]
+                fn perform(l: OrderedFloat<f64>, r: $int) -> u8 { let ($l, $r) = (l.0, r as f64); $cmp as u8 }
+                fn symbol() -> &'static str { $symbol }
+            }
+
+            impl BinaryOp<$int, OrderedFloat<f64>, u8> for $op {
+                #[inline]
+                fn perform(l: $int, r: OrderedFloat<f64>) -> u8 { let ($l, $r) = (l as f64, r.0); $cmp as u8 }
+                fn symbol() -> &'static str { $symbol }
+            }
+        )*
+    };
+}
+
 
 pub struct LessThan;
 
@@ -29,6 +59,8 @@ impl<'a> BinaryOp<&'a str, &'a str, u8> for LessThan {
     fn symbol() -> &'static str { "<" }
 }
 
+float_comparison!(LessThan, |l, r| l < r, "<");
+
 
 impl<T, U, V> BinaryOp<T, U, u8> for LessThanEquals
     where T: Widen<U, Join=V>, V: PrimInt, T: GenericIntVec<T> {
@@ -45,6 +77,8 @@ impl<'a> BinaryOp<&'a str, &'a str, u8> for LessThanEquals {
     fn symbol() -> &'static str { "<=" }
 }
 
+float_comparison!(LessThanEquals, |l, r| l <= r, "<=");
+
 impl<T, U, V> BinaryOp<T, U, u8> for Equals
     where T: Widen<U, Join=V>, V: PrimInt, T: GenericIntVec<T> {
     fn perform(t: T, u: U) -> u8 {
@@ -60,6 +94,8 @@ impl<'a> BinaryOp<&'a str, &'a str, u8> for Equals {
     fn symbol() -> &'static str { "=" }
 }
 
+float_comparison!(Equals, |l, r| l == r, "=");
+
 
 impl<T, U, V> BinaryOp<T, U, u8> for NotEquals
     where T: Widen<U, Join=V>, V: PrimInt, T: GenericIntVec<T> {
@@ -76,6 +112,9 @@ impl<'a> BinaryOp<&'a str, &'a str, u8> for NotEquals {
     fn symbol() -> &'static str { "<>" }
 }
 
+// Unlike `!=`, this is false if either side is NaN
+float_comparison!(NotEquals, |l, r| l != r && !l.is_nan() && !r.is_nan(), "<>");
+
 
 pub trait Widen<T> {
     type Join: PrimInt;
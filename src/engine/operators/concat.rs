@@ -0,0 +1,94 @@
+use crate::engine::*;
+use std::fmt;
+use std::mem;
+use std::str;
+
+/// One side of a string concatenation, either a vector of strings or a string constant.
+#[derive(Debug, Clone, Copy)]
+pub enum StrInput<'a> {
+    Vec(BufferRef<&'a str>),
+    Scalar(BufferRef<Scalar<&'a str>>),
+}
+
+impl<'a> StrInput<'a> {
+    pub fn new(input: TypedBufferRef) -> Result<StrInput<'a>, QueryError> {
+        match input.tag {
+            EncodingType::ScalarStr => Ok(StrInput::Scalar(input.scalar_str()?)),
+            _ => Ok(StrInput::Vec(input.str()?)),
+        }
+    }
+
+    fn any(&self) -> BufferRef<Any> {
+        match *self {
+            StrInput::Vec(buffer) => buffer.any(),
+            StrInput::Scalar(buffer) => buffer.any(),
+        }
+    }
+
+    fn get(&self, scratchpad: &Scratchpad<'a>) -> (Vec<&'a str>, Option<&'a str>) {
+        match *self {
+            StrInput::Vec(buffer) => (scratchpad.get(buffer).to_vec(), None),
+            StrInput::Scalar(buffer) => (vec![], Some(scratchpad.get_scalar(&buffer))),
+        }
+    }
+}
+
+pub struct Concat<'a> {
+    pub lhs: StrInput<'a>,
+    pub rhs: StrInput<'a>,
+    pub stringstore: BufferRef<u8>,
+    pub concat: BufferRef<&'a str>,
+}
+
+impl<'a> VecOperator<'a> for Concat<'a> {
+    fn execute(&mut self, _: bool, scratchpad: &mut Scratchpad<'a>) -> Result<(), QueryError> {
+        let (lhs, lhs_const) = self.lhs.get(scratchpad);
+        let (rhs, rhs_const) = self.rhs.get(scratchpad);
+        let len = if lhs_const.is_none() { lhs.len() } else { rhs.len() };
+        let left = |i: usize| lhs_const.unwrap_or_else(|| lhs[i]);
+        let right = |i: usize| rhs_const.unwrap_or_else(|| rhs[i]);
+
+        let total_bytes = (0..len).map(|i| left(i).len() + right(i).len()).sum();
+        let mut stringstore = Vec::with_capacity(total_bytes);
+        for i in 0..len {
+            stringstore.extend_from_slice(left(i).as_bytes());
+            stringstore.extend_from_slice(right(i).as_bytes());
+        }
+        scratchpad.set(self.stringstore, stringstore);
+
+        // Strings point into `stringstore` which is pinned and never modified afterwards
+        let stringstore = scratchpad.get_pinned(self.stringstore);
+        let mut concat = scratchpad.get_mut(self.concat);
+        let mut offset = 0;
+        for i in 0..len {
+            let bytes = left(i).len() + right(i).len();
+            concat.push(unsafe {
+                mem::transmute::<_, &'a str>(str::from_utf8_unchecked(
+                    &stringstore[offset..offset + bytes],
+                ))
+            });
+            offset += bytes;
+        }
+        Ok(())
+    }
+
+    fn init(&mut self, _: usize, batch_size: usize, scratchpad: &mut Scratchpad<'a>) {
+        scratchpad.set(self.concat, Vec::with_capacity(batch_size));
+    }
+
+    fn inputs(&self) -> Vec<BufferRef<Any>> { vec![self.lhs.any(), self.rhs.any()] }
+    fn outputs(&self) -> Vec<BufferRef<Any>> { vec![self.concat.any()] }
+    fn can_stream_input(&self, _: usize) -> bool { false }
+    fn can_stream_output(&self, _: usize) -> bool { false }
+    fn allocates(&self) -> bool { true }
+
+    fn display_op(&self, _: bool) -> String {
+        format!("{} || {}", self.lhs.any(), self.rhs.any())
+    }
+}
+
+impl<'a> fmt::Debug for Concat<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Concat {{ lhs: {}, rhs: {}, concat: {} }}", self.lhs.any(), self.rhs.any(), self.concat)
+    }
+}
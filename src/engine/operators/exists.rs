@@ -29,7 +29,7 @@ impl<'a, T: GenericIntVec<T> + CastUsize> VecOperator<'a> for Exists<T> {
         scratchpad.set(self.output, Vec::with_capacity(0));
     }
 
-    fn inputs(&self) -> Vec<BufferRef<Any>> { vec![self.input.any()] }
+    fn inputs(&self) -> Vec<BufferRef<Any>> { vec![self.input.any(), self.max_index.any()] }
     fn outputs(&self) -> Vec<BufferRef<Any>> { vec![self.output.any()] }
     fn can_stream_input(&self, _: usize) -> bool { true }
     fn can_stream_output(&self, _: usize) -> bool { false }
@@ -1,4 +1,4 @@
-use crate::bitvec::BitVec;
+use crate::bitvec::*;
 use crate::engine::*;
 
 pub struct Filter<T> {
@@ -100,3 +100,111 @@ where
         format!("{}[{}]", self.input, self.filter)
     }
 }
+
+pub struct FilterNullable<T> {
+    pub input: BufferRef<Nullable<T>>,
+    pub filter: BufferRef<u8>,
+    pub output: BufferRef<Nullable<T>>,
+}
+
+impl<'a, T: 'a> VecOperator<'a> for FilterNullable<T>
+where
+    T: VecData<T>,
+{
+    fn execute(&mut self, stream: bool, scratchpad: &mut Scratchpad<'a>) -> Result<(), QueryError> {
+        let (data, present) = scratchpad.get_nullable(self.input);
+        let filter = scratchpad.get(self.filter);
+        let (mut filtered, mut filtered_present) = scratchpad.get_mut_nullable(self.output);
+        if stream {
+            filtered.clear();
+            filtered_present.clear();
+        }
+        for (i, (d, &select)) in data.iter().zip(filter.iter()).enumerate() {
+            if select > 0 {
+                if (&*present).is_set(i) {
+                    filtered_present.set(filtered.len());
+                }
+                filtered.push(*d);
+            }
+        }
+        Ok(())
+    }
+
+    fn init(&mut self, _: usize, batch_size: usize, scratchpad: &mut Scratchpad<'a>) {
+        scratchpad.set_nullable(self.output, Vec::with_capacity(batch_size), Vec::with_capacity(batch_size / 8 + 1));
+    }
+
+    fn inputs(&self) -> Vec<BufferRef<Any>> {
+        vec![self.input.any(), self.filter.any()]
+    }
+    fn outputs(&self) -> Vec<BufferRef<Any>> {
+        vec![self.output.any()]
+    }
+    fn can_stream_input(&self, _: usize) -> bool {
+        true
+    }
+    fn can_stream_output(&self, _: usize) -> bool {
+        true
+    }
+    fn allocates(&self) -> bool {
+        true
+    }
+
+    fn display_op(&self, _: bool) -> String {
+        format!("{}[{}]", self.input, self.filter)
+    }
+}
+
+pub struct NullableFilterNullable<T> {
+    pub input: BufferRef<Nullable<T>>,
+    pub filter: BufferRef<Nullable<u8>>,
+    pub output: BufferRef<Nullable<T>>,
+}
+
+impl<'a, T: 'a> VecOperator<'a> for NullableFilterNullable<T>
+where
+    T: VecData<T>,
+{
+    fn execute(&mut self, stream: bool, scratchpad: &mut Scratchpad<'a>) -> Result<(), QueryError> {
+        let (data, present) = scratchpad.get_nullable(self.input);
+        let (filter, filter_present) = scratchpad.get_nullable(self.filter);
+        let (mut filtered, mut filtered_present) = scratchpad.get_mut_nullable(self.output);
+        if stream {
+            filtered.clear();
+            filtered_present.clear();
+        }
+        for i in 0..data.len() {
+            if filter[i] > 0 && (&*filter_present).is_set(i) {
+                if (&*present).is_set(i) {
+                    filtered_present.set(filtered.len());
+                }
+                filtered.push(data[i]);
+            }
+        }
+        Ok(())
+    }
+
+    fn init(&mut self, _: usize, batch_size: usize, scratchpad: &mut Scratchpad<'a>) {
+        scratchpad.set_nullable(self.output, Vec::with_capacity(batch_size), Vec::with_capacity(batch_size / 8 + 1));
+    }
+
+    fn inputs(&self) -> Vec<BufferRef<Any>> {
+        vec![self.input.any(), self.filter.any()]
+    }
+    fn outputs(&self) -> Vec<BufferRef<Any>> {
+        vec![self.output.any()]
+    }
+    fn can_stream_input(&self, _: usize) -> bool {
+        true
+    }
+    fn can_stream_output(&self, _: usize) -> bool {
+        true
+    }
+    fn allocates(&self) -> bool {
+        true
+    }
+
+    fn display_op(&self, _: bool) -> String {
+        format!("{}[{}]", self.input, self.filter)
+    }
+}
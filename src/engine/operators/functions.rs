@@ -1,9 +1,11 @@
 use std::i64;
 
-use chrono::{NaiveDateTime, Datelike};
+use chrono::{NaiveDate, NaiveDateTime, Datelike};
+use ordered_float::OrderedFloat;
 use regex;
 
 use super::map_operator::MapOp;
+use super::type_conversion::float_to_i64;
 
 
 pub struct ToYear;
@@ -14,6 +16,67 @@ impl MapOp<i64, i64> for ToYear {
 }
 
 
+/// Granularity of the buckets that timestamps are truncated to by `date_trunc`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeUnit {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    /// Weeks start on Monday.
+    Week,
+    Month,
+    Year,
+}
+
+impl TimeUnit {
+    pub fn parse(unit: &str) -> Option<TimeUnit> {
+        match unit.to_lowercase().as_ref() {
+            "second" => Some(TimeUnit::Second),
+            "minute" => Some(TimeUnit::Minute),
+            "hour" => Some(TimeUnit::Hour),
+            "day" => Some(TimeUnit::Day),
+            "week" => Some(TimeUnit::Week),
+            "month" => Some(TimeUnit::Month),
+            "year" => Some(TimeUnit::Year),
+            _ => None,
+        }
+    }
+
+    /// Returns the start of the bucket that the unix timestamp `unix_ts` falls into.
+    pub fn truncate(self, unix_ts: i64) -> i64 {
+        const DAY: i64 = 24 * 60 * 60;
+        match self {
+            TimeUnit::Second => unix_ts,
+            TimeUnit::Minute => unix_ts - unix_ts.rem_euclid(60),
+            TimeUnit::Hour => unix_ts - unix_ts.rem_euclid(60 * 60),
+            TimeUnit::Day => unix_ts - unix_ts.rem_euclid(DAY),
+            // 1970-01-01 was a Thursday
+            TimeUnit::Week => unix_ts - (unix_ts.rem_euclid(7 * DAY) + 3 * DAY).rem_euclid(7 * DAY),
+            TimeUnit::Month | TimeUnit::Year => match NaiveDateTime::from_timestamp_opt(unix_ts, 0) {
+                Some(datetime) => {
+                    let month = if self == TimeUnit::Month { datetime.month() } else { 1 };
+                    NaiveDate::from_ymd_opt(datetime.year(), month, 1)
+                        .and_then(|date| date.and_hms_opt(0, 0, 0))
+                        .map(|start| start.timestamp())
+                        .unwrap_or(unix_ts)
+                }
+                None => unix_ts,
+            },
+        }
+    }
+}
+
+pub struct DateTrunc {
+    pub unit: TimeUnit,
+}
+
+impl MapOp<i64, i64> for DateTrunc {
+    fn apply(&self, unix_ts: i64) -> i64 { self.unit.truncate(unix_ts) }
+    fn name() -> &'static str { "date_trunc" }
+}
+
+
 pub struct BooleanNot;
 
 impl MapOp<u8, u8> for BooleanNot {
@@ -36,6 +99,184 @@ impl<'a> MapOp<&'a str, u8> for RegexMatch {
     fn name() -> &'static str { "not" }
 }
 
+pub struct LikeMatch {
+    pub r: regex::Regex
+}
+
+impl LikeMatch {
+    /// Compiles a SQL `LIKE` pattern where `%` matches any sequence of characters, `_` matches any
+    /// single character and `\` escapes the following character.
+    pub fn new(pattern: &str) -> LikeMatch {
+        let mut r = String::from("(?s)^");
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '%' => r.push_str(".*"),
+                '_' => r.push('.'),
+                '\\' => {
+                    let escaped = chars.next().unwrap_or('\\');
+                    r.push_str(&regex::escape(escaped.encode_utf8(&mut [0; 4])));
+                }
+                _ => r.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+            }
+        }
+        r.push('$');
+        LikeMatch { r: regex::Regex::new(&r).unwrap() }
+    }
+}
+
+impl<'a> MapOp<&'a str, u8> for LikeMatch {
+    fn apply(&self, s: &'a str) -> u8 { self.r.is_match(s) as u8 }
+    fn name() -> &'static str { "like" }
+}
+
+
+pub struct Floor;
+
+impl MapOp<OrderedFloat<f64>, i64> for Floor {
+    fn apply(&self, f: OrderedFloat<f64>) -> i64 { float_to_i64(f.floor()) }
+    fn name() -> &'static str { "floor" }
+}
+
+
+pub struct Ceil;
+
+impl MapOp<OrderedFloat<f64>, i64> for Ceil {
+    fn apply(&self, f: OrderedFloat<f64>) -> i64 { float_to_i64(f.ceil()) }
+    fn name() -> &'static str { "ceil" }
+}
+
+
+pub struct Round;
+
+impl MapOp<OrderedFloat<f64>, i64> for Round {
+    fn apply(&self, f: OrderedFloat<f64>) -> i64 { float_to_i64(f.round()) }
+    fn name() -> &'static str { "round" }
+}
+
+
+pub struct RoundDigits {
+    pub digits: i32,
+}
+
+impl MapOp<OrderedFloat<f64>, OrderedFloat<f64>> for RoundDigits {
+    fn apply(&self, f: OrderedFloat<f64>) -> OrderedFloat<f64> {
+        let factor = 10f64.powi(self.digits);
+        OrderedFloat((f.0 * factor).round() / factor)
+    }
+    fn name() -> &'static str { "round" }
+}
+
+
+pub struct Abs;
+
+impl MapOp<i64, i64> for Abs {
+    // Saturates since the absolute value of i64::MIN is not representable
+    fn apply(&self, i: i64) -> i64 { i.saturating_abs() }
+    fn name() -> &'static str { "abs" }
+}
+
+impl MapOp<OrderedFloat<f64>, OrderedFloat<f64>> for Abs {
+    fn apply(&self, f: OrderedFloat<f64>) -> OrderedFloat<f64> { OrderedFloat(f.abs()) }
+    fn name() -> &'static str { "abs" }
+}
+
+
+pub struct Sign;
+
+impl MapOp<i64, i64> for Sign {
+    fn apply(&self, i: i64) -> i64 { i.signum() }
+    fn name() -> &'static str { "sign" }
+}
+
+impl MapOp<OrderedFloat<f64>, i64> for Sign {
+    fn apply(&self, f: OrderedFloat<f64>) -> i64 {
+        if f.0 > 0.0 {
+            1
+        } else if f.0 < 0.0 {
+            -1
+        } else {
+            0
+        }
+    }
+    fn name() -> &'static str { "sign" }
+}
+
+
+pub struct Sqrt;
+
+impl MapOp<OrderedFloat<f64>, Option<OrderedFloat<f64>>> for Sqrt {
+    fn apply(&self, f: OrderedFloat<f64>) -> Option<OrderedFloat<f64>> {
+        if f.0 < 0.0 { None } else { Some(OrderedFloat(f.sqrt())) }
+    }
+    fn name() -> &'static str { "sqrt" }
+}
+
+impl MapOp<i64, Option<OrderedFloat<f64>>> for Sqrt {
+    fn apply(&self, i: i64) -> Option<OrderedFloat<f64>> { MapOp::<OrderedFloat<f64>, _>::apply(self, OrderedFloat(i as f64)) }
+    fn name() -> &'static str { "sqrt" }
+}
+
+
+pub struct Ln;
+
+impl MapOp<OrderedFloat<f64>, Option<OrderedFloat<f64>>> for Ln {
+    // Logarithm of zero is negative infinity
+    fn apply(&self, f: OrderedFloat<f64>) -> Option<OrderedFloat<f64>> {
+        if f.0 < 0.0 { None } else { Some(OrderedFloat(f.ln())) }
+    }
+    fn name() -> &'static str { "ln" }
+}
+
+impl MapOp<i64, Option<OrderedFloat<f64>>> for Ln {
+    fn apply(&self, i: i64) -> Option<OrderedFloat<f64>> { MapOp::<OrderedFloat<f64>, _>::apply(self, OrderedFloat(i as f64)) }
+    fn name() -> &'static str { "ln" }
+}
+
+
+pub struct Log10;
+
+impl MapOp<OrderedFloat<f64>, Option<OrderedFloat<f64>>> for Log10 {
+    fn apply(&self, f: OrderedFloat<f64>) -> Option<OrderedFloat<f64>> {
+        if f.0 < 0.0 { None } else { Some(OrderedFloat(f.log10())) }
+    }
+    fn name() -> &'static str { "log10" }
+}
+
+impl MapOp<i64, Option<OrderedFloat<f64>>> for Log10 {
+    fn apply(&self, i: i64) -> Option<OrderedFloat<f64>> { MapOp::<OrderedFloat<f64>, _>::apply(self, OrderedFloat(i as f64)) }
+    fn name() -> &'static str { "log10" }
+}
+
+
+pub struct NullIfZero;
+
+impl MapOp<OrderedFloat<f64>, Option<OrderedFloat<f64>>> for NullIfZero {
+    // Also maps negative zero to null
+    fn apply(&self, f: OrderedFloat<f64>) -> Option<OrderedFloat<f64>> {
+        if f.0 == 0.0 { None } else { Some(f) }
+    }
+    fn name() -> &'static str { "null_if_zero" }
+}
+
+impl MapOp<i64, Option<OrderedFloat<f64>>> for NullIfZero {
+    fn apply(&self, i: i64) -> Option<OrderedFloat<f64>> { MapOp::<OrderedFloat<f64>, _>::apply(self, OrderedFloat(i as f64)) }
+    fn name() -> &'static str { "null_if_zero" }
+}
+
+
+pub struct Exp;
+
+impl MapOp<OrderedFloat<f64>, OrderedFloat<f64>> for Exp {
+    fn apply(&self, f: OrderedFloat<f64>) -> OrderedFloat<f64> { OrderedFloat(f.exp()) }
+    fn name() -> &'static str { "exp" }
+}
+
+impl MapOp<i64, OrderedFloat<f64>> for Exp {
+    fn apply(&self, i: i64) -> OrderedFloat<f64> { OrderedFloat((i as f64).exp()) }
+    fn name() -> &'static str { "exp" }
+}
+
 
 pub struct Length;
 
@@ -1,3 +1,4 @@
+use crate::bitvec::*;
 use crate::engine::*;
 
 
@@ -37,6 +38,54 @@ impl<'a, Input, Output, Map> VecOperator<'a> for MapOperator<Input, Output, Map>
     }
 }
 
+/// Like `MapOperator`, but outputs null for inputs that `map` is not defined for.
+#[derive(Debug)]
+pub struct PartialMapOperator<Input, Output, Map> {
+    pub input: BufferRef<Input>,
+    pub output: BufferRef<Nullable<Output>>,
+    pub map: Map,
+}
+
+impl<'a, Input, Output, Map> VecOperator<'a> for PartialMapOperator<Input, Output, Map>
+    where Input: VecData<Input> + 'a,
+          Output: VecData<Output> + Default + 'a,
+          Map: MapOp<Input, Option<Output>> {
+    fn execute(&mut self, stream: bool, scratchpad: &mut Scratchpad<'a>) -> Result<(), QueryError>{
+        let input = scratchpad.get(self.input);
+        let (mut output, mut present) = scratchpad.get_mut_nullable(self.output);
+        if stream {
+            output.clear();
+            present.clear();
+        }
+        for i in input.iter() {
+            match self.map.apply(*i) {
+                Some(value) => {
+                    present.set(output.len());
+                    output.push(value);
+                }
+                None => output.push(Output::default()),
+            }
+        }
+        // Null map must cover all elements when combined with other null maps
+        present.resize((output.len() + 7) / 8, 0);
+        Ok(())
+    }
+
+    fn init(&mut self, _: usize, batch_size: usize, scratchpad: &mut Scratchpad<'a>) {
+        scratchpad.set_nullable(self.output, Vec::with_capacity(batch_size), Vec::with_capacity(batch_size / 8 + 1));
+    }
+
+    fn inputs(&self) -> Vec<BufferRef<Any>> { vec![self.input.any()] }
+    fn outputs(&self) -> Vec<BufferRef<Any>> { vec![self.output.any()] }
+    fn can_stream_input(&self, _: usize) -> bool { true }
+    fn can_stream_output(&self, _: usize) -> bool { true }
+    fn allocates(&self) -> bool { true }
+
+    fn display_op(&self, _: bool) -> String {
+        format!("{}({})", Map::name(), self.input)
+    }
+}
+
 pub trait MapOp<Input, Output> {
     fn apply(&self, input: Input) -> Output;
     fn name() -> &'static str;
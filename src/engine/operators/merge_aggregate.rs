@@ -89,4 +89,23 @@ impl Combinable<OrderedFloat<f64>> for OrderedFloat<f64> {
             _ => Err(fatal!("Unsupported aggregator for f64: {:?}", op)),
         }
     }
+}
+
+impl<'a> Combinable<&'a str> for &'a str {
+    fn combine(op: Aggregator, a: &'a str, b: &'a str) -> Result<&'a str, QueryError> {
+        match op {
+            Aggregator::MaxStr => Ok(std::cmp::max(a, b)),
+            Aggregator::MinStr => Ok(std::cmp::min(a, b)),
+            _ => Err(fatal!("Unsupported aggregator for str: {:?}", op)),
+        }
+    }
+}
+
+impl<'a> Combinable<Option<&'a str>> for Option<&'a str> {
+    fn combine(op: Aggregator, a: Option<&'a str>, b: Option<&'a str>) -> Result<Option<&'a str>, QueryError> {
+        match (a, b) {
+            (Some(a), Some(b)) => <&str>::combine(op, a, b).map(Some),
+            _ => Ok(a.or(b)),
+        }
+    }
 }
\ No newline at end of file
@@ -102,3 +102,27 @@ fn merge_keep_nullable<'a, T: Copy + 'a>(ops: &[u8],
     }
     (result, present)
 }
+
+#[derive(Debug)]
+pub struct MergeKeepNull {
+    pub merge_ops: BufferRef<u8>,
+    pub merged: BufferRef<Any>,
+}
+
+impl<'a> VecOperator<'a> for MergeKeepNull {
+    fn execute(&mut self, _: bool, scratchpad: &mut Scratchpad<'a>) -> Result<(), QueryError>{
+        let len = scratchpad.get(self.merge_ops).len();
+        scratchpad.set_any(self.merged, empty_data(len));
+        Ok(())
+    }
+
+    fn inputs(&self) -> Vec<BufferRef<Any>> { vec![self.merge_ops.any()] }
+    fn outputs(&self) -> Vec<BufferRef<Any>> { vec![self.merged.any()] }
+    fn can_stream_input(&self, _: usize) -> bool { false }
+    fn can_stream_output(&self, _: usize) -> bool { false }
+    fn allocates(&self) -> bool { false }
+
+    fn display_op(&self, _: bool) -> String {
+        format!("merge_keep_null({})", self.merge_ops)
+    }
+}
@@ -1,18 +1,25 @@
 pub use self::aggregator::*;
 pub use self::comparator::*;
 pub use self::vector_operator::*;
+pub use self::functions::TimeUnit;
+pub use self::numeric_operators::integer_pow;
 
 pub mod vector_operator;
 pub mod comparator;
+pub mod approx_percentile;
 
 mod aggregate;
 mod assemble_nullable;
 mod binary_operator;
 mod bit_unpack;
+mod blend;
 mod bool_op;
 mod column_ops;
+mod coalesce;
 mod combine_null_maps;
 mod compact;
+mod change_case;
+mod concat;
 mod comparison_operators;
 mod constant;
 mod constant_expand;
@@ -45,6 +52,8 @@ mod null_vec;
 mod numeric_operators;
 mod parameterized_vec_vec_int_op;
 mod propagate_nullability;
+mod sample;
+mod scalar_f64;
 mod scalar_i64;
 mod scalar_str;
 mod select;
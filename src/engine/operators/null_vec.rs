@@ -34,3 +34,36 @@ impl<'a> VecOperator<'a> for NullVec {
         "NullVec".to_string()
     }
 }
+
+#[derive(Debug)]
+pub struct NullVecLike {
+    pub input: BufferRef<Any>,
+    pub output: BufferRef<Any>,
+}
+
+impl<'a> VecOperator<'a> for NullVecLike {
+    fn execute(&mut self, _: bool, scratchpad: &mut Scratchpad<'a>) -> Result<(), QueryError> {
+        let len = scratchpad.get_any(self.input).len();
+        scratchpad.set_any(self.output, empty_data(len));
+        Ok(())
+    }
+
+    fn inputs(&self) -> Vec<BufferRef<Any>> {
+        vec![self.input.any()]
+    }
+    fn outputs(&self) -> Vec<BufferRef<Any>> {
+        vec![self.output.any()]
+    }
+    fn can_stream_input(&self, _: usize) -> bool {
+        false
+    }
+    fn can_stream_output(&self, _: usize) -> bool {
+        false
+    }
+    fn allocates(&self) -> bool {
+        false
+    }
+    fn display_op(&self, _: bool) -> String {
+        format!("NullVecLike({})", self.input)
+    }
+}
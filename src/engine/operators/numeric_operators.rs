@@ -31,6 +31,30 @@ pub struct Modulo<LHS, RHS> {
     rhs: PhantomData<RHS>,
 }
 
+pub struct Power<LHS, RHS> {
+    lhs: PhantomData<LHS>,
+    rhs: PhantomData<RHS>,
+}
+
+/// Raises `base` to the power of `exp`, returns `None` on overflow or if `base` is 0 and `exp` is negative.
+/// Negative exponents are truncated like integer division, e.g. `2^-1 = 1 / 2 = 0`.
+pub fn integer_pow(base: i64, exp: i64) -> Option<i64> {
+    if exp >= 0 {
+        match u32::try_from(exp) {
+            Ok(exp) => base.checked_pow(exp),
+            Err(_) if base.abs() <= 1 => base.checked_pow((exp % 2) as u32 + 2),
+            Err(_) => None,
+        }
+    } else {
+        match base {
+            0 => None,
+            1 => Some(1),
+            -1 => Some(if exp % 2 == 0 { 1 } else { -1 }),
+            _ => Some(0),
+        }
+    }
+}
+
 
 impl<LHS: PrimInt, RHS: PrimInt> BinaryOp<LHS, RHS, i64> for Addition<LHS, RHS> {
     #[inline]
@@ -48,6 +72,15 @@ impl<LHS: PrimInt, RHS: PrimInt> CheckedBinaryOp<LHS, RHS, i64> for Addition<LHS
     }
 }
 
+impl<LHS: ToPrimitive, RHS: ToPrimitive> BinaryOp<LHS, RHS, OrderedFloat<f64>> for Addition<LHS, RHS> {
+    #[inline]
+    fn perform(lhs: LHS, rhs: RHS) -> OrderedFloat<f64> {
+        OrderedFloat(lhs.to_f64().unwrap() + rhs.to_f64().unwrap())
+    }
+
+    fn symbol() -> &'static str { "+" }
+}
+
 impl<LHS: PrimInt, RHS: PrimInt> BinaryOp<LHS, RHS, i64> for Subtraction<LHS, RHS> {
     #[inline]
     fn perform(lhs: LHS, rhs: RHS) -> i64 {
@@ -64,6 +97,15 @@ impl<LHS: PrimInt, RHS: PrimInt> CheckedBinaryOp<LHS, RHS, i64> for Subtraction<
     }
 }
 
+impl<LHS: ToPrimitive, RHS: ToPrimitive> BinaryOp<LHS, RHS, OrderedFloat<f64>> for Subtraction<LHS, RHS> {
+    #[inline]
+    fn perform(lhs: LHS, rhs: RHS) -> OrderedFloat<f64> {
+        OrderedFloat(lhs.to_f64().unwrap() - rhs.to_f64().unwrap())
+    }
+
+    fn symbol() -> &'static str { "-" }
+}
+
 impl<LHS: PrimInt, RHS: PrimInt> BinaryOp<LHS, RHS, i64> for Multiplication<LHS, RHS, i64> {
     #[inline]
     fn perform(lhs: LHS, rhs: RHS) -> i64 {
@@ -109,6 +151,15 @@ impl<LHS: PrimInt, RHS: PrimInt> CheckedBinaryOp<LHS, RHS, i64> for Division<LHS
     }
 }
 
+impl<LHS: ToPrimitive, RHS: ToPrimitive> BinaryOp<LHS, RHS, OrderedFloat<f64>> for Division<LHS, RHS> {
+    #[inline]
+    fn perform(lhs: LHS, rhs: RHS) -> OrderedFloat<f64> {
+        OrderedFloat(lhs.to_f64().unwrap() / rhs.to_f64().unwrap())
+    }
+
+    fn symbol() -> &'static str { "/" }
+}
+
 impl<LHS: PrimInt, RHS: PrimInt> BinaryOp<LHS, RHS, i64> for Modulo<LHS, RHS> {
     #[inline]
     fn perform(lhs: LHS, rhs: RHS) -> i64 {
@@ -128,3 +179,40 @@ impl<LHS: PrimInt, RHS: PrimInt> CheckedBinaryOp<LHS, RHS, i64> for Modulo<LHS,
         }
     }
 }
+
+impl<LHS: ToPrimitive, RHS: ToPrimitive> BinaryOp<LHS, RHS, OrderedFloat<f64>> for Modulo<LHS, RHS> {
+    #[inline]
+    fn perform(lhs: LHS, rhs: RHS) -> OrderedFloat<f64> {
+        OrderedFloat(lhs.to_f64().unwrap() % rhs.to_f64().unwrap())
+    }
+
+    fn symbol() -> &'static str { "%" }
+}
+
+impl<LHS: PrimInt, RHS: PrimInt> CheckedBinaryOp<LHS, RHS, i64> for Power<LHS, RHS> {
+    #[inline]
+    fn perform_checked(lhs: LHS, rhs: RHS) -> (i64, bool) {
+        match integer_pow(lhs.to_i64().unwrap(), rhs.to_i64().unwrap()) {
+            Some(power) => (power, false),
+            None => (1, true),
+        }
+    }
+}
+
+impl<LHS: PrimInt, RHS: PrimInt> BinaryOp<LHS, RHS, i64> for Power<LHS, RHS> {
+    #[inline]
+    fn perform(lhs: LHS, rhs: RHS) -> i64 {
+        integer_pow(lhs.to_i64().unwrap(), rhs.to_i64().unwrap()).unwrap()
+    }
+
+    fn symbol() -> &'static str { "^" }
+}
+
+impl<LHS: ToPrimitive, RHS: ToPrimitive> BinaryOp<LHS, RHS, OrderedFloat<f64>> for Power<LHS, RHS> {
+    #[inline]
+    fn perform(lhs: LHS, rhs: RHS) -> OrderedFloat<f64> {
+        OrderedFloat(lhs.to_f64().unwrap().powf(rhs.to_f64().unwrap()))
+    }
+
+    fn symbol() -> &'static str { "^" }
+}
@@ -0,0 +1,45 @@
+use std::cmp;
+
+use rand::Rng;
+
+use crate::engine::*;
+
+/// Outputs `len` booleans which are independently true with probability `fraction`.
+#[derive(Debug)]
+pub struct Sample {
+    pub fraction: f64,
+    pub output: BufferRef<u8>,
+
+    pub current_index: usize,
+    pub len: usize,
+    pub batch_size: usize,
+}
+
+impl<'a> VecOperator<'a> for Sample {
+    fn execute(&mut self, stream: bool, scratchpad: &mut Scratchpad<'a>) -> Result<(), QueryError> {
+        let count = cmp::min(self.batch_size, self.len - self.current_index);
+        let mut output = scratchpad.get_mut(self.output);
+        if stream { output.clear(); }
+        let mut rng = rand::thread_rng();
+        output.extend((0..count).map(|_| rng.gen_bool(self.fraction) as u8));
+        self.current_index += count;
+        Ok(())
+    }
+
+    fn init(&mut self, _: usize, batch_size: usize, scratchpad: &mut Scratchpad<'a>) {
+        self.batch_size = batch_size;
+        scratchpad.set(self.output, Vec::with_capacity(batch_size));
+    }
+
+    fn inputs(&self) -> Vec<BufferRef<Any>> { vec![] }
+    fn outputs(&self) -> Vec<BufferRef<Any>> { vec![self.output.any()] }
+    fn can_stream_input(&self, _: usize) -> bool { false }
+    fn can_stream_output(&self, _: usize) -> bool { true }
+    fn allocates(&self) -> bool { true }
+    fn is_streaming_producer(&self) -> bool { true }
+    fn has_more(&self) -> bool { self.current_index < self.len }
+
+    fn display_op(&self, _: bool) -> String {
+        format!("sample({})", self.fraction)
+    }
+}
@@ -45,6 +45,7 @@ pub struct SortByNullable<T> {
     pub indices: BufferRef<usize>,
     pub output: BufferRef<usize>,
     pub descending: bool,
+    pub nulls_first: bool,
     pub stable: bool,
 }
 
@@ -54,36 +55,20 @@ impl<'a, T: VecData<T> + 'a> VecOperator<'a> for SortByNullable<T> {
         let (ranking, ranking_present) = scratchpad.get_nullable(self.ranking);
         let present = &*ranking_present;
         let mut indices = scratchpad.get_mut(self.indices);
-        if self.descending {
-            if self.stable {
-                indices.sort_by(|&j, &i| match (present.is_set(i), present.is_set(j)) {
-                    (true, true) => ranking[i].cmp(&ranking[j]),
-                    (false, true) => Ordering::Less,
-                    (true, false) => Ordering::Greater,
-                    (false, false) => Ordering::Equal,
-                })
-            } else {
-                indices.sort_unstable_by(|&j, &i| match (present.is_set(i), present.is_set(j)) {
-                    (true, true) => ranking[i].cmp(&ranking[j]),
-                    (false, true) => Ordering::Less,
-                    (true, false) => Ordering::Greater,
-                    (false, false) => Ordering::Equal,
-                })
-            }
-        } else if self.stable {
-                indices.sort_by(|&i, &j| match (present.is_set(i), present.is_set(j)) {
-                    (true, true) => ranking[i].cmp(&ranking[j]),
-                    (false, true) => Ordering::Less,
-                    (true, false) => Ordering::Greater,
-                    (false, false) => Ordering::Equal,
-                })
+        let (descending, nulls_first) = (self.descending, self.nulls_first);
+        let cmp = |&i: &usize, &j: &usize| match (present.is_set(i), present.is_set(j)) {
+            (true, true) if descending => ranking[j].cmp(&ranking[i]),
+            (true, true) => ranking[i].cmp(&ranking[j]),
+            (false, true) if nulls_first => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (true, false) if nulls_first => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => Ordering::Equal,
+        };
+        if self.stable {
+            indices.sort_by(cmp);
         } else {
-            indices.sort_unstable_by(|&i, &j| match (present.is_set(i), present.is_set(j)) {
-                (true, true) => ranking[i].cmp(&ranking[j]),
-                (false, true) => Ordering::Less,
-                (true, false) => Ordering::Greater,
-                (false, false) => Ordering::Equal,
-            })
+            indices.sort_unstable_by(cmp);
         }
         Ok(())
     }
@@ -95,6 +80,9 @@ impl<'a, T: VecData<T> + 'a> VecOperator<'a> for SortByNullable<T> {
     fn allocates(&self) -> bool { true }
 
     fn display_op(&self, _: bool) -> String {
-        format!("sort_by({}, {}; desc={}, stable={})", self.ranking, self.indices, self.descending, self.stable)
+        format!(
+            "sort_by({}, {}; desc={}, nulls_first={}, stable={})",
+            self.ranking, self.indices, self.descending, self.nulls_first, self.stable
+        )
     }
 }
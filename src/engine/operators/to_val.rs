@@ -1,6 +1,7 @@
 use crate::bitvec::*;
 use crate::engine::*;
 use crate::mem_store::Val;
+use ordered_float::OrderedFloat;
 
 pub struct NullableStrToVal<'a> {
     pub input: BufferRef<Nullable<&'a str>>,
@@ -113,6 +114,99 @@ impl<'a, T: GenericIntVec<T>> VecOperator<'a> for NullableIntToVal<'a, T> {
     }
 }
 
+pub struct NullableFloatToVal<'a> {
+    pub input: BufferRef<Nullable<OrderedFloat<f64>>>,
+    pub vals: BufferRef<Val<'a>>,
+}
+
+impl<'a> VecOperator<'a> for NullableFloatToVal<'a> {
+    fn execute(&mut self, stream: bool, scratchpad: &mut Scratchpad<'a>) -> Result<(), QueryError> {
+        let (input, present) = scratchpad.get_nullable(self.input);
+        let mut vals = scratchpad.get_mut(self.vals);
+        if stream { vals.clear(); }
+        for i in 0..input.len() {
+            if (&*present).is_set(i) {
+                vals.push(Val::Float(input[i]));
+            } else {
+                vals.push(Val::Null);
+            }
+        }
+        Ok(())
+    }
+
+    fn init(&mut self, _: usize, batch_size: usize, scratchpad: &mut Scratchpad<'a>) {
+        scratchpad.set(self.vals, Vec::with_capacity(batch_size));
+    }
+
+    fn inputs(&self) -> Vec<BufferRef<Any>> { vec![self.input.any()] }
+    fn outputs(&self) -> Vec<BufferRef<Any>> { vec![self.vals.any()] }
+    fn can_stream_input(&self, _: usize) -> bool { true }
+    fn can_stream_output(&self, _: usize) -> bool { true }
+    fn allocates(&self) -> bool { true }
+
+    fn display_op(&self, _: bool) -> String {
+        format!("NullableFloatToVal({})", self.vals)
+    }
+}
+
+pub struct NullableBoolToVal<'a> {
+    pub input: BufferRef<Nullable<u8>>,
+    pub vals: BufferRef<Val<'a>>,
+}
+
+impl<'a> VecOperator<'a> for NullableBoolToVal<'a> {
+    fn execute(&mut self, stream: bool, scratchpad: &mut Scratchpad<'a>) -> Result<(), QueryError> {
+        let (input, present) = scratchpad.get_nullable(self.input);
+        let mut vals = scratchpad.get_mut(self.vals);
+        if stream { vals.clear(); }
+        for i in 0..input.len() {
+            if (&*present).is_set(i) {
+                vals.push(Val::Bool(input[i] != 0));
+            } else {
+                vals.push(Val::Null);
+            }
+        }
+        Ok(())
+    }
+
+    fn init(&mut self, _: usize, batch_size: usize, scratchpad: &mut Scratchpad<'a>) {
+        scratchpad.set(self.vals, Vec::with_capacity(batch_size));
+    }
+
+    fn inputs(&self) -> Vec<BufferRef<Any>> { vec![self.input.any()] }
+    fn outputs(&self) -> Vec<BufferRef<Any>> { vec![self.vals.any()] }
+    fn can_stream_input(&self, _: usize) -> bool { true }
+    fn can_stream_output(&self, _: usize) -> bool { true }
+    fn allocates(&self) -> bool { true }
+
+    fn display_op(&self, _: bool) -> String {
+        format!("NullableBoolToVal({})", self.vals)
+    }
+}
+
+pub struct NullToVal<'a> {
+    pub input: BufferRef<Any>,
+    pub vals: BufferRef<Val<'a>>,
+}
+
+impl<'a> VecOperator<'a> for NullToVal<'a> {
+    fn execute(&mut self, _: bool, scratchpad: &mut Scratchpad<'a>) -> Result<(), QueryError> {
+        let len = scratchpad.get_any(self.input).len();
+        scratchpad.set(self.vals, vec![Val::Null; len]);
+        Ok(())
+    }
+
+    fn inputs(&self) -> Vec<BufferRef<Any>> { vec![self.input] }
+    fn outputs(&self) -> Vec<BufferRef<Any>> { vec![self.vals.any()] }
+    fn can_stream_input(&self, _: usize) -> bool { false }
+    fn can_stream_output(&self, _: usize) -> bool { false }
+    fn allocates(&self) -> bool { true }
+
+    fn display_op(&self, _: bool) -> String {
+        format!("NullToVal({})", self.input)
+    }
+}
+
 pub struct ValToNullableInt<'a, T> {
     pub vals: BufferRef<Val<'a>>,
     pub nullable: BufferRef<Nullable<T>>,
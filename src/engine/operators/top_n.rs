@@ -11,6 +11,10 @@ pub struct TopN<T, C> {
     pub keys: BufferRef<T>,
     pub n: usize,
     pub last_index: usize,
+    /// Also outputs all elements that compare equal to the `n`th element.
+    pub with_ties: bool,
+    /// Indices of elements not in the heap that are equal to the heap root.
+    pub ties: Vec<usize>,
     pub c: PhantomData<C>,
 }
 
@@ -49,7 +53,17 @@ impl<'a, T: VecData<T> + 'a, C: Comparator<T> + fmt::Debug> VecOperator<'a> for
         assert!(indices.len() == indices.capacity() || input.len() == 0);
         for (i, &key) in input.iter().enumerate() {
             if C::cmp(key, keys[0]) {
+                let (evicted_key, evicted_index) = (keys[0], indices[0]);
                 heap_replace::<_, C>(&mut keys, &mut indices, key, self.last_index + i, 0);
+                if self.with_ties {
+                    if keys[0] == evicted_key {
+                        self.ties.push(evicted_index);
+                    } else {
+                        self.ties.clear();
+                    }
+                }
+            } else if self.with_ties && key == keys[0] {
+                self.ties.push(self.last_index + i);
             }
         }
         self.last_index += input.len();
@@ -66,10 +80,12 @@ impl<'a, T: VecData<T> + 'a, C: Comparator<T> + fmt::Debug> VecOperator<'a> for
             } else {
                 sort_indices.sort_unstable_by(|i, j| keys[*i].cmp(&keys[*j]).reverse());
             }
-            let mut output = Vec::with_capacity(indices.len());
+            let mut output = Vec::with_capacity(indices.len() + self.ties.len());
             for i in sort_indices {
                 output.push(indices[i]);
             }
+            // Ties are equal to the last element and so preserve the order
+            output.append(&mut self.ties);
             output
         };
         scratchpad.set(self.indices, output);
@@ -82,7 +98,11 @@ impl<'a, T: VecData<T> + 'a, C: Comparator<T> + fmt::Debug> VecOperator<'a> for
     fn allocates(&self) -> bool { true }
 
     fn display_op(&self, _: bool) -> String {
-        format!("top_n({})", self.input)
+        if self.with_ties {
+            format!("top_n_with_ties({})", self.input)
+        } else {
+            format!("top_n({})", self.input)
+        }
     }
 }
 
@@ -43,6 +43,16 @@ pub trait Cast<T> {
     fn cast(self) -> T;
 }
 
+/// Converts a float to an integer by truncating towards zero.
+/// Values outside the range of `i64` saturate at `-i64::MAX` and `i64::MAX` (`i64::MIN` is reserved to represent null) and NaN is converted to 0.
+pub fn float_to_i64(f: f64) -> i64 {
+    if f.is_nan() {
+        0
+    } else {
+        (f as i64).max(-i64::MAX)
+    }
+}
+
 impl<T> Cast<T> for T { fn cast(self) -> T { self } }
 
 
@@ -97,16 +107,23 @@ impl<'a> Cast<Val<'a>> for u16 { fn cast(self) -> Val<'a> { Val::Integer(self as
 
 impl<'a> Cast<Val<'a>> for u32 { fn cast(self) -> Val<'a> { Val::Integer(self as i64) } }
 
-impl<'a> Cast<Val<'a>> for i64 { fn cast(self) -> Val<'a> { Val::Integer(self) } }
+// `i64::MIN` represents null in fused nullable integers
+impl<'a> Cast<Val<'a>> for i64 { fn cast(self) -> Val<'a> { if self == i64::MIN { Val::Null } else { Val::Integer(self) } } }
 
 impl<'a> Cast<Val<'a>> for &'a str { fn cast(self) -> Val<'a> { Val::Str(self) } }
 
 impl<'a> Cast<Val<'a>> for OrderedFloat<f64> { fn cast(self) -> Val<'a> { Val::Float(self) } }
 
+impl Cast<i64> for OrderedFloat<f64> { fn cast(self) -> i64 { float_to_i64(self.0) } }
+
+impl Cast<OrderedFloat<f64>> for i64 { fn cast(self) -> OrderedFloat<f64> { OrderedFloat(self as f64) } }
+
 impl<'a> Cast<u8> for Val<'a> {
     fn cast(self) -> u8 {
         match self {
             Val::Integer(i) => i as u8,
+            Val::Float(f) => float_to_i64(f.0) as u8,
+            Val::Bool(b) => b as u8,
             _ => panic!("Cast::<u8>{:?}", self)
         }
     }
@@ -116,6 +133,8 @@ impl<'a> Cast<u16> for Val<'a> {
     fn cast(self) -> u16 {
         match self {
             Val::Integer(i) => i as u16,
+            Val::Float(f) => float_to_i64(f.0) as u16,
+            Val::Bool(b) => b as u16,
             _ => panic!("Cast::<u16>{:?}", self)
         }
     }
@@ -125,6 +144,8 @@ impl<'a> Cast<u32> for Val<'a> {
     fn cast(self) -> u32 {
         match self {
             Val::Integer(i) => i as u32,
+            Val::Float(f) => float_to_i64(f.0) as u32,
+            Val::Bool(b) => b as u32,
             _ => panic!("Cast::<u32>{:?}", self)
         }
     }
@@ -134,6 +155,8 @@ impl<'a> Cast<i64> for Val<'a> {
     fn cast(self) -> i64 {
         match self {
             Val::Integer(i) => i,
+            Val::Float(f) => float_to_i64(f.0),
+            Val::Bool(b) => b as i64,
             _ => panic!("Cast::<i64>{:?}", self)
         }
     }
@@ -143,6 +166,7 @@ impl<'a> Cast<OrderedFloat<f64>> for Val<'a> {
     fn cast(self) -> OrderedFloat<f64> {
         match self {
             Val::Float(f) => f,
+            Val::Integer(i) => OrderedFloat(i as f64),
             _ => panic!("Cast::<f64>{:?}", self)
         }
     }
@@ -157,4 +181,24 @@ impl<'a> Cast<&'a str> for Val<'a> {
     }
 }
 
-impl<'a> Cast<Option<&'a str>> for &'a str { fn cast(self) -> Option<&'a str> { Some(self) } }
\ No newline at end of file
+impl<'a> Cast<Option<&'a str>> for &'a str { fn cast(self) -> Option<&'a str> { Some(self) } }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_float_to_int() {
+        assert_eq!(float_to_i64(2.9), 2);
+        assert_eq!(float_to_i64(-2.9), -2);
+        assert_eq!(float_to_i64(-0.5), 0);
+        assert_eq!(float_to_i64(f64::NAN), 0);
+        assert_eq!(float_to_i64(f64::INFINITY), i64::MAX);
+        assert_eq!(float_to_i64(f64::NEG_INFINITY), -i64::MAX);
+        assert_eq!(float_to_i64(1e19), i64::MAX);
+        assert_eq!(float_to_i64(-9_223_372_036_854_775_808.0), -i64::MAX);
+        assert_eq!(Cast::<i64>::cast(Val::Float(OrderedFloat(-7.99))), -7);
+        assert_eq!(Cast::<u8>::cast(Val::Float(OrderedFloat(255.5))), 255);
+        assert_eq!(Cast::<u32>::cast(Val::Float(OrderedFloat(f64::NAN))), 0);
+        assert_eq!(Cast::<OrderedFloat<f64>>::cast(Val::Integer(3)), OrderedFloat(3.0));
+    }
+}
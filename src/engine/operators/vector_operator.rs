@@ -14,13 +14,18 @@ use std::marker::PhantomData;
 use std::result::Result;
 
 use super::aggregate::*;
+use super::approx_percentile::ApproxPercentile;
 use super::assemble_nullable::AssembleNullable;
 use super::binary_operator::*;
 use super::bit_unpack::BitUnpackOperator;
+use super::blend::*;
 use super::bool_op::*;
 use super::column_ops::*;
-use super::combine_null_maps::CombineNullMaps;
+use super::coalesce::*;
+use super::combine_null_maps::{CombineNullMaps, UnionNullMaps};
 use super::compact::Compact;
+use super::change_case::ChangeCase;
+use super::concat::*;
 use super::comparison_operators::*;
 use super::constant::Constant;
 use super::constant_expand::ConstantExpand;
@@ -29,7 +34,7 @@ use super::delta_decode::*;
 use super::dict_lookup::*;
 use super::encode_const::*;
 use super::exists::Exists;
-use super::filter::{Filter, NullableFilter};
+use super::filter::{Filter, FilterNullable, NullableFilter, NullableFilterNullable};
 use super::functions::*;
 use super::fuse_nulls::*;
 use super::get_null_map::GetNullMap;
@@ -40,7 +45,7 @@ use super::identity::Identity;
 use super::indices::Indices;
 use super::is_null::*;
 use super::make_nullable::MakeNullable;
-use super::map_operator::MapOperator;
+use super::map_operator::{MapOperator, MapOp, PartialMapOperator};
 use super::merge::Merge;
 use super::merge_aggregate::MergeAggregate;
 use super::merge_deduplicate::MergeDeduplicate;
@@ -50,11 +55,13 @@ use super::merge_keep::*;
 use super::merge_partitioned::MergePartitioned;
 use super::nonzero_compact::NonzeroCompact;
 use super::nonzero_indices::NonzeroIndices;
-use super::null_vec::NullVec;
+use super::null_vec::{NullVec, NullVecLike};
 use super::numeric_operators::*;
 use super::parameterized_vec_vec_int_op::*;
 use super::partition::Partition;
 use super::propagate_nullability::PropagateNullability;
+use super::sample::Sample;
+use super::scalar_f64::ScalarF64;
 use super::scalar_i64::ScalarI64;
 use super::scalar_str::ScalarStr;
 use super::select::*;
@@ -164,6 +171,11 @@ pub mod operator {
                 present,
                 nullable_data: nullable_data.nullable_str()?,
             })),
+            EncodingType::F64 => Ok(Box::new(AssembleNullable {
+                data: data.f64()?,
+                present,
+                nullable_data: nullable_data.nullable_f64()?,
+            })),
             _ => Err(fatal!("nullable not implemented for type {:?}", data.tag)),
         }
     }
@@ -189,6 +201,11 @@ pub mod operator {
                 present,
                 nullable_data: nullable_data.nullable_str()?,
             })),
+            EncodingType::F64 => Ok(Box::new(MakeNullable {
+                data: data.f64()?,
+                present,
+                nullable_data: nullable_data.nullable_f64()?,
+            })),
             _ => Err(fatal!(
                 "make_nullable not implemented for type {:?}",
                 data.tag
@@ -208,6 +225,106 @@ pub mod operator {
         }))
     }
 
+    pub fn union_null_maps<'a>(
+        lhs: TypedBufferRef,
+        rhs: TypedBufferRef,
+        output: BufferRef<u8>,
+    ) -> Result<BoxedOperator<'a>, QueryError> {
+        Ok(Box::new(UnionNullMaps {
+            lhs: lhs.nullable_any()?,
+            rhs: rhs.nullable_any()?,
+            output,
+        }))
+    }
+
+    pub fn coalesce<'a>(
+        lhs: TypedBufferRef,
+        rhs: TypedBufferRef,
+        coalesced: TypedBufferRef,
+    ) -> Result<BoxedOperator<'a>, QueryError> {
+        match (lhs.tag, rhs.tag) {
+            (EncodingType::NullableI64, EncodingType::I64) => Ok(Box::new(Coalesce {
+                lhs: lhs.nullable_i64()?,
+                rhs: rhs.i64()?,
+                coalesced: coalesced.i64()?,
+            })),
+            (EncodingType::NullableI64, EncodingType::ScalarI64) => Ok(Box::new(CoalesceScalar {
+                lhs: lhs.nullable_i64()?,
+                rhs: rhs.scalar_i64()?,
+                coalesced: coalesced.i64()?,
+            })),
+            (EncodingType::NullableF64, EncodingType::F64) => Ok(Box::new(Coalesce {
+                lhs: lhs.nullable_f64()?,
+                rhs: rhs.f64()?,
+                coalesced: coalesced.f64()?,
+            })),
+            (EncodingType::NullableStr, EncodingType::Str) => Ok(Box::new(Coalesce {
+                lhs: lhs.nullable_str()?,
+                rhs: rhs.str()?,
+                coalesced: coalesced.str()?,
+            })),
+            (EncodingType::NullableStr, EncodingType::ScalarStr) => Ok(Box::new(CoalesceScalar {
+                lhs: lhs.nullable_str()?,
+                rhs: rhs.scalar_str()?,
+                coalesced: coalesced.str()?,
+            })),
+            (EncodingType::NullableU8, EncodingType::U8) => Ok(Box::new(Coalesce {
+                lhs: lhs.nullable_u8()?,
+                rhs: rhs.u8()?,
+                coalesced: coalesced.u8()?,
+            })),
+            _ => Err(fatal!("coalesce not supported for types {:?}, {:?}", lhs.tag, rhs.tag)),
+        }
+    }
+
+    pub fn blend<'a>(
+        cond: TypedBufferRef,
+        lhs: TypedBufferRef,
+        rhs: TypedBufferRef,
+        blended: TypedBufferRef,
+    ) -> Result<BoxedOperator<'a>, QueryError> {
+        let cond = u8::blend_input(cond)?;
+        match blended.tag {
+            EncodingType::I64 => Ok(Box::new(Blend {
+                cond,
+                lhs: i64::blend_input(lhs)?,
+                rhs: i64::blend_input(rhs)?,
+                blended: blended.i64()?,
+            })),
+            EncodingType::NullableI64 => Ok(Box::new(BlendNullable {
+                cond,
+                lhs: i64::blend_input(lhs)?,
+                rhs: i64::blend_input(rhs)?,
+                blended: blended.nullable_i64()?,
+            })),
+            EncodingType::Str => Ok(Box::new(Blend {
+                cond,
+                lhs: <&str>::blend_input(lhs)?,
+                rhs: <&str>::blend_input(rhs)?,
+                blended: blended.str()?,
+            })),
+            EncodingType::NullableStr => Ok(Box::new(BlendNullable {
+                cond,
+                lhs: <&str>::blend_input(lhs)?,
+                rhs: <&str>::blend_input(rhs)?,
+                blended: blended.nullable_str()?,
+            })),
+            EncodingType::F64 => Ok(Box::new(Blend {
+                cond,
+                lhs: OrderedFloat::blend_input(lhs)?,
+                rhs: OrderedFloat::blend_input(rhs)?,
+                blended: blended.f64()?,
+            })),
+            EncodingType::NullableF64 => Ok(Box::new(BlendNullable {
+                cond,
+                lhs: OrderedFloat::blend_input(lhs)?,
+                rhs: OrderedFloat::blend_input(rhs)?,
+                blended: blended.nullable_f64()?,
+            })),
+            _ => Err(fatal!("blend not supported for type {:?}", blended.tag)),
+        }
+    }
+
     pub fn propagate_nullability<'a>(
         nullability: BufferRef<Nullable<Any>>,
         data: TypedBufferRef,
@@ -224,6 +341,11 @@ pub mod operator {
                 to: data.i64()?,
                 output: output.nullable_i64()?,
             })),
+            EncodingType::F64 => Ok(Box::new(PropagateNullability {
+                from: nullability,
+                to: data.f64()?,
+                output: output.nullable_f64()?,
+            })),
             EncodingType::Str => Ok(Box::new(PropagateNullability {
                 from: nullability,
                 to: data.str()?,
@@ -255,6 +377,16 @@ pub mod operator {
                 input: input.nullable_i64()?,
                 fused: fused.i64()?,
             }))
+        } else if input.tag == EncodingType::NullableF64 {
+            Ok(Box::new(NullableFloatToVal {
+                input: input.nullable_f64()?,
+                vals: fused.val()?,
+            }))
+        } else if input.tag == EncodingType::NullableU8 {
+            Ok(Box::new(NullableBoolToVal {
+                input: input.nullable_u8()?,
+                vals: fused.val()?,
+            }))
         } else {
             Ok(Box::new(FuseNullsStr {
                 input: input.nullable_str()?,
@@ -495,7 +627,9 @@ pub mod operator {
         reify_types! {
             "filter";
             input, output: PrimitiveUSize;
-            Ok(Box::new(Filter { input, filter, output }))
+            Ok(Box::new(Filter { input, filter, output }));
+            input, output: NullablePrimitive;
+            Ok(Box::new(FilterNullable { input, filter, output }))
         }
     }
 
@@ -507,7 +641,9 @@ pub mod operator {
         reify_types! {
             "nullable_filter";
             input, output: PrimitiveUSize;
-            Ok(Box::new(NullableFilter { input, filter, output }))
+            Ok(Box::new(NullableFilter { input, filter, output }));
+            input, output: NullablePrimitive;
+            Ok(Box::new(NullableFilterNullable { input, filter, output }))
         }
     }
 
@@ -516,10 +652,15 @@ pub mod operator {
         indices: BufferRef<usize>,
         output: TypedBufferRef,
     ) -> Result<BoxedOperator<'a>, QueryError> {
+        if input.tag == EncodingType::Null {
+            return Ok(Box::new(NullVecLike { input: indices.any(), output: output.any() }));
+        }
         reify_types! {
             "select";
             input, output: PrimitiveUSize;
             Ok(Box::new(Select { input, indices, output }));
+            input, output: OptStr;
+            Ok(Box::new(Select { input, indices, output }));
             input, output: NullablePrimitive;
             Ok(Box::new(SelectNullable { input, indices, output }))
         }
@@ -549,6 +690,10 @@ pub mod operator {
         })
     }
 
+    pub fn scalar_f64<'a>(val: OrderedFloat<f64>, output: BufferRef<Scalar<OrderedFloat<f64>>>) -> BoxedOperator<'a> {
+        Box::new(ScalarF64 { val, output })
+    }
+
     pub fn scalar_str(
         val: String,
         pinned: BufferRef<Scalar<String>>,
@@ -565,6 +710,10 @@ pub mod operator {
         Box::new(NullVec { len, output })
     }
 
+    pub fn null_vec_like<'a>(input: BufferRef<Any>, output: BufferRef<Any>) -> BoxedOperator<'a> {
+        Box::new(NullVecLike { input, output })
+    }
+
     pub fn constant_expand<'a>(
         val: i64,
         len: usize,
@@ -585,6 +734,16 @@ pub mod operator {
         }
     }
 
+    pub fn sample<'a>(fraction: f64, len: usize, output: BufferRef<u8>) -> BoxedOperator<'a> {
+        Box::new(Sample {
+            fraction,
+            output,
+            current_index: 0,
+            len,
+            batch_size: 0, // initialized later
+        })
+    }
+
     pub fn constant_vec(val: BoxedData, output: BufferRef<Any>) -> BoxedOperator {
         Box::new(ConstantVec { val, output })
     }
@@ -608,7 +767,20 @@ pub mod operator {
             lhs: ScalarI64, rhs: IntegerNoU64;
             Ok(Box::new(BinarySVOperator { lhs, rhs, output, op: PhantomData::<LessThan> }));
             lhs: IntegerNoU64, rhs: IntegerNoU64;
-            Ok(Box::new(BinaryOperator { lhs, rhs, output, op: PhantomData::<LessThan> }))
+            Ok(Box::new(BinaryOperator { lhs, rhs, output, op: PhantomData::<LessThan> }));
+
+            lhs: Float, rhs: NumberNoU64;
+            Ok(Box::new(BinaryOperator { lhs, rhs, output, op: PhantomData::<LessThan> }));
+            lhs: IntegerNoU64, rhs: Float;
+            Ok(Box::new(BinaryOperator { lhs, rhs, output, op: PhantomData::<LessThan> }));
+            lhs: Float, rhs: ScalarI64;
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output, op: PhantomData::<LessThan> }));
+            lhs: ScalarI64, rhs: Float;
+            Ok(Box::new(BinarySVOperator { lhs, rhs, output, op: PhantomData::<LessThan> }));
+            lhs: NumberNoU64, rhs: ScalarF64;
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output, op: PhantomData::<LessThan> }));
+            lhs: ScalarF64, rhs: NumberNoU64;
+            Ok(Box::new(BinarySVOperator { lhs, rhs, output, op: PhantomData::<LessThan> }))
         }
     }
 
@@ -631,7 +803,20 @@ pub mod operator {
             lhs: ScalarI64, rhs: IntegerNoU64;
             Ok(Box::new(BinarySVOperator { lhs, rhs, output, op: PhantomData::<LessThanEquals> }));
             lhs: IntegerNoU64, rhs: IntegerNoU64;
-            Ok(Box::new(BinaryOperator { lhs, rhs, output, op: PhantomData::<LessThanEquals> }))
+            Ok(Box::new(BinaryOperator { lhs, rhs, output, op: PhantomData::<LessThanEquals> }));
+
+            lhs: Float, rhs: NumberNoU64;
+            Ok(Box::new(BinaryOperator { lhs, rhs, output, op: PhantomData::<LessThanEquals> }));
+            lhs: IntegerNoU64, rhs: Float;
+            Ok(Box::new(BinaryOperator { lhs, rhs, output, op: PhantomData::<LessThanEquals> }));
+            lhs: Float, rhs: ScalarI64;
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output, op: PhantomData::<LessThanEquals> }));
+            lhs: ScalarI64, rhs: Float;
+            Ok(Box::new(BinarySVOperator { lhs, rhs, output, op: PhantomData::<LessThanEquals> }));
+            lhs: NumberNoU64, rhs: ScalarF64;
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output, op: PhantomData::<LessThanEquals> }));
+            lhs: ScalarF64, rhs: NumberNoU64;
+            Ok(Box::new(BinarySVOperator { lhs, rhs, output, op: PhantomData::<LessThanEquals> }))
         }
     }
 
@@ -654,7 +839,20 @@ pub mod operator {
             lhs: ScalarI64, rhs: IntegerNoU64;
             Ok(Box::new(BinaryVSOperator { lhs: rhs, rhs: lhs, output, op: PhantomData::<Equals> }));
             lhs: IntegerNoU64, rhs: IntegerNoU64;
-            Ok(Box::new(BinaryOperator { lhs, rhs, output, op: PhantomData::<Equals> }))
+            Ok(Box::new(BinaryOperator { lhs, rhs, output, op: PhantomData::<Equals> }));
+
+            lhs: Float, rhs: NumberNoU64;
+            Ok(Box::new(BinaryOperator { lhs, rhs, output, op: PhantomData::<Equals> }));
+            lhs: IntegerNoU64, rhs: Float;
+            Ok(Box::new(BinaryOperator { lhs, rhs, output, op: PhantomData::<Equals> }));
+            lhs: Float, rhs: ScalarI64;
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output, op: PhantomData::<Equals> }));
+            lhs: ScalarI64, rhs: Float;
+            Ok(Box::new(BinaryVSOperator { lhs: rhs, rhs: lhs, output, op: PhantomData::<Equals> }));
+            lhs: NumberNoU64, rhs: ScalarF64;
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output, op: PhantomData::<Equals> }));
+            lhs: ScalarF64, rhs: NumberNoU64;
+            Ok(Box::new(BinaryVSOperator { lhs: rhs, rhs: lhs, output, op: PhantomData::<Equals> }))
         }
     }
 
@@ -677,23 +875,48 @@ pub mod operator {
             lhs: ScalarI64, rhs: IntegerNoU64;
             Ok(Box::new(BinaryVSOperator { lhs: rhs, rhs: lhs, output, op: PhantomData::<NotEquals> }));
             lhs: IntegerNoU64, rhs: IntegerNoU64;
-            Ok(Box::new(BinaryOperator { lhs, rhs, output, op: PhantomData::<NotEquals> }))
+            Ok(Box::new(BinaryOperator { lhs, rhs, output, op: PhantomData::<NotEquals> }));
+
+            lhs: Float, rhs: NumberNoU64;
+            Ok(Box::new(BinaryOperator { lhs, rhs, output, op: PhantomData::<NotEquals> }));
+            lhs: IntegerNoU64, rhs: Float;
+            Ok(Box::new(BinaryOperator { lhs, rhs, output, op: PhantomData::<NotEquals> }));
+            lhs: Float, rhs: ScalarI64;
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output, op: PhantomData::<NotEquals> }));
+            lhs: ScalarI64, rhs: Float;
+            Ok(Box::new(BinaryVSOperator { lhs: rhs, rhs: lhs, output, op: PhantomData::<NotEquals> }));
+            lhs: NumberNoU64, rhs: ScalarF64;
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output, op: PhantomData::<NotEquals> }));
+            lhs: ScalarF64, rhs: NumberNoU64;
+            Ok(Box::new(BinaryVSOperator { lhs: rhs, rhs: lhs, output, op: PhantomData::<NotEquals> }))
         }
     }
 
     pub fn addition<'a>(
         lhs: TypedBufferRef,
         rhs: TypedBufferRef,
-        output: BufferRef<i64>,
+        output: TypedBufferRef,
     ) -> Result<BoxedOperator<'a>, QueryError> {
         reify_types! {
             "addition";
             lhs: ScalarI64, rhs: IntegerNoU64;
-            Ok(Box::new(BinaryVSOperator { lhs: rhs, rhs: lhs, output, op: PhantomData::<Addition<_, _>> }));
+            Ok(Box::new(BinaryVSOperator { lhs: rhs, rhs: lhs, output: output.i64()?, op: PhantomData::<Addition<_, _>> }));
             lhs: IntegerNoU64, rhs: ScalarI64;
-            Ok(Box::new(BinaryVSOperator { lhs, rhs, output, op: PhantomData::<Addition<_, _>> }));
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output: output.i64()?, op: PhantomData::<Addition<_, _>> }));
             lhs: IntegerNoU64, rhs: IntegerNoU64;
-            Ok(Box::new(BinaryOperator { lhs, rhs, output, op: PhantomData::<Addition<_, _>> }))
+            Ok(Box::new(BinaryOperator { lhs, rhs, output: output.i64()?, op: PhantomData::<Addition<_, _>> }));
+            lhs: Float, rhs: NumberNoU64;
+            Ok(Box::new(BinaryOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Addition<_, _>> }));
+            lhs: NumberNoU64, rhs: Float;
+            Ok(Box::new(BinaryOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Addition<_, _>> }));
+            lhs: ScalarI64, rhs: Float;
+            Ok(Box::new(BinarySVOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Addition<_, _>> }));
+            lhs: Float, rhs: ScalarI64;
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Addition<_, _>> }));
+            lhs: ScalarF64, rhs: NumberNoU64;
+            Ok(Box::new(BinarySVOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Addition<_, _>> }));
+            lhs: NumberNoU64, rhs: ScalarF64;
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Addition<_, _>> }))
         }
     }
 
@@ -733,16 +956,28 @@ pub mod operator {
     pub fn subtraction<'a>(
         lhs: TypedBufferRef,
         rhs: TypedBufferRef,
-        output: BufferRef<i64>,
+        output: TypedBufferRef,
     ) -> Result<BoxedOperator<'a>, QueryError> {
         reify_types! {
             "subtraction";
             lhs: ScalarI64, rhs: IntegerNoU64;
-            Ok(Box::new(BinarySVOperator { lhs, rhs, output, op: PhantomData::<Subtraction<_, _>> }));
+            Ok(Box::new(BinarySVOperator { lhs, rhs, output: output.i64()?, op: PhantomData::<Subtraction<_, _>> }));
             lhs: IntegerNoU64, rhs: ScalarI64;
-            Ok(Box::new(BinaryVSOperator { lhs, rhs, output, op: PhantomData::<Subtraction<_, _>> }));
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output: output.i64()?, op: PhantomData::<Subtraction<_, _>> }));
             lhs: IntegerNoU64, rhs: IntegerNoU64;
-            Ok(Box::new(BinaryOperator { lhs, rhs, output, op: PhantomData::<Subtraction<_, _>> }))
+            Ok(Box::new(BinaryOperator { lhs, rhs, output: output.i64()?, op: PhantomData::<Subtraction<_, _>> }));
+            lhs: Float, rhs: NumberNoU64;
+            Ok(Box::new(BinaryOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Subtraction<_, _>> }));
+            lhs: NumberNoU64, rhs: Float;
+            Ok(Box::new(BinaryOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Subtraction<_, _>> }));
+            lhs: ScalarI64, rhs: Float;
+            Ok(Box::new(BinarySVOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Subtraction<_, _>> }));
+            lhs: Float, rhs: ScalarI64;
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Subtraction<_, _>> }));
+            lhs: ScalarF64, rhs: NumberNoU64;
+            Ok(Box::new(BinarySVOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Subtraction<_, _>> }));
+            lhs: NumberNoU64, rhs: ScalarF64;
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Subtraction<_, _>> }))
         }
     }
 
@@ -799,6 +1034,10 @@ pub mod operator {
             lhs: ScalarI64, rhs: Float;
             Ok(Box::new(BinarySVOperator { lhs, rhs, output: output.into(), op: PhantomData::<Multiplication<_, _, OrderedFloat<f64>>> }));
             lhs: Float, rhs: ScalarI64;
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output: output.into(), op: PhantomData::<Multiplication<_, _, OrderedFloat<f64>>> }));
+            lhs: ScalarF64, rhs: NumberNoU64;
+            Ok(Box::new(BinarySVOperator { lhs, rhs, output: output.into(), op: PhantomData::<Multiplication<_, _, OrderedFloat<f64>>> }));
+            lhs: NumberNoU64, rhs: ScalarF64;
             Ok(Box::new(BinaryVSOperator { lhs, rhs, output: output.into(), op: PhantomData::<Multiplication<_, _, OrderedFloat<f64>>> }))
         }
     }
@@ -839,16 +1078,28 @@ pub mod operator {
     pub fn division<'a>(
         lhs: TypedBufferRef,
         rhs: TypedBufferRef,
-        output: BufferRef<i64>,
+        output: TypedBufferRef,
     ) -> Result<BoxedOperator<'a>, QueryError> {
         reify_types! {
             "division";
             lhs: ScalarI64, rhs: IntegerNoU64;
-            Ok(Box::new(BinarySVOperator { lhs, rhs, output, op: PhantomData::<Division<_, _>> }));
+            Ok(Box::new(BinarySVOperator { lhs, rhs, output: output.i64()?, op: PhantomData::<Division<_, _>> }));
             lhs: IntegerNoU64, rhs: ScalarI64;
-            Ok(Box::new(BinaryVSOperator { lhs, rhs, output, op: PhantomData::<Division<_, _>> }));
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output: output.i64()?, op: PhantomData::<Division<_, _>> }));
             lhs: IntegerNoU64, rhs: IntegerNoU64;
-            Ok(Box::new(BinaryOperator { lhs, rhs, output, op: PhantomData::<Division<_, _>> }))
+            Ok(Box::new(BinaryOperator { lhs, rhs, output: output.i64()?, op: PhantomData::<Division<_, _>> }));
+            lhs: Float, rhs: NumberNoU64;
+            Ok(Box::new(BinaryOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Division<_, _>> }));
+            lhs: NumberNoU64, rhs: Float;
+            Ok(Box::new(BinaryOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Division<_, _>> }));
+            lhs: ScalarI64, rhs: Float;
+            Ok(Box::new(BinarySVOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Division<_, _>> }));
+            lhs: Float, rhs: ScalarI64;
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Division<_, _>> }));
+            lhs: ScalarF64, rhs: NumberNoU64;
+            Ok(Box::new(BinarySVOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Division<_, _>> }));
+            lhs: NumberNoU64, rhs: ScalarF64;
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Division<_, _>> }))
         }
     }
 
@@ -888,16 +1139,28 @@ pub mod operator {
     pub fn modulo<'a>(
         lhs: TypedBufferRef,
         rhs: TypedBufferRef,
-        output: BufferRef<i64>,
+        output: TypedBufferRef,
     ) -> Result<BoxedOperator<'a>, QueryError> {
         reify_types! {
             "modulo";
             lhs: ScalarI64, rhs: IntegerNoU64;
-            Ok(Box::new(BinarySVOperator { lhs, rhs, output, op: PhantomData::<Modulo<_, _>> }));
+            Ok(Box::new(BinarySVOperator { lhs, rhs, output: output.i64()?, op: PhantomData::<Modulo<_, _>> }));
             lhs: IntegerNoU64, rhs: ScalarI64;
-            Ok(Box::new(BinaryVSOperator { lhs, rhs, output, op: PhantomData::<Modulo<_, _>> }));
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output: output.i64()?, op: PhantomData::<Modulo<_, _>> }));
             lhs: IntegerNoU64, rhs: IntegerNoU64;
-            Ok(Box::new(BinaryOperator { lhs, rhs, output, op: PhantomData::<Modulo<_, _>> }))
+            Ok(Box::new(BinaryOperator { lhs, rhs, output: output.i64()?, op: PhantomData::<Modulo<_, _>> }));
+            lhs: Float, rhs: NumberNoU64;
+            Ok(Box::new(BinaryOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Modulo<_, _>> }));
+            lhs: NumberNoU64, rhs: Float;
+            Ok(Box::new(BinaryOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Modulo<_, _>> }));
+            lhs: ScalarI64, rhs: Float;
+            Ok(Box::new(BinarySVOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Modulo<_, _>> }));
+            lhs: Float, rhs: ScalarI64;
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Modulo<_, _>> }));
+            lhs: ScalarF64, rhs: NumberNoU64;
+            Ok(Box::new(BinarySVOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Modulo<_, _>> }));
+            lhs: NumberNoU64, rhs: ScalarF64;
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output: output.f64()?, op: PhantomData::<Modulo<_, _>> }))
         }
     }
 
@@ -934,6 +1197,61 @@ pub mod operator {
         }
     }
 
+    pub fn power<'a>(
+        lhs: TypedBufferRef,
+        rhs: TypedBufferRef,
+        output: BufferRef<OrderedFloat<f64>>,
+    ) -> Result<BoxedOperator<'a>, QueryError> {
+        reify_types! {
+            "power";
+            lhs: Float, rhs: NumberNoU64;
+            Ok(Box::new(BinaryOperator { lhs, rhs, output, op: PhantomData::<Power<_, _>> }));
+            lhs: NumberNoU64, rhs: Float;
+            Ok(Box::new(BinaryOperator { lhs, rhs, output, op: PhantomData::<Power<_, _>> }));
+            lhs: ScalarI64, rhs: Float;
+            Ok(Box::new(BinarySVOperator { lhs, rhs, output, op: PhantomData::<Power<_, _>> }));
+            lhs: Float, rhs: ScalarI64;
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output, op: PhantomData::<Power<_, _>> }));
+            lhs: ScalarF64, rhs: NumberNoU64;
+            Ok(Box::new(BinarySVOperator { lhs, rhs, output, op: PhantomData::<Power<_, _>> }));
+            lhs: NumberNoU64, rhs: ScalarF64;
+            Ok(Box::new(BinaryVSOperator { lhs, rhs, output, op: PhantomData::<Power<_, _>> }))
+        }
+    }
+
+    pub fn checked_power<'a>(
+        lhs: TypedBufferRef,
+        rhs: TypedBufferRef,
+        output: BufferRef<i64>,
+    ) -> Result<BoxedOperator<'a>, QueryError> {
+        reify_types! {
+            "checked_power";
+            lhs: ScalarI64, rhs: IntegerNoU64;
+            Ok(Box::new(CheckedBinarySVOperator { lhs, rhs, output, op: PhantomData::<Power<_, _>> }));
+            lhs: IntegerNoU64, rhs: ScalarI64;
+            Ok(Box::new(CheckedBinaryVSOperator { lhs, rhs, output, op: PhantomData::<Power<_, _>> }));
+            lhs: IntegerNoU64, rhs: IntegerNoU64;
+            Ok(Box::new(CheckedBinaryOperator { lhs, rhs, output, op: PhantomData::<Power<_, _>> }))
+        }
+    }
+
+    pub fn nullable_checked_power<'a>(
+        lhs: TypedBufferRef,
+        rhs: TypedBufferRef,
+        present: BufferRef<u8>,
+        output: BufferRef<Nullable<i64>>,
+    ) -> Result<BoxedOperator<'a>, QueryError> {
+        reify_types! {
+            "nullable_checked_power";
+            lhs: ScalarI64, rhs: IntegerNoU64;
+            Ok(Box::new(NullableCheckedBinarySVOperator { lhs, rhs, output, present, op: PhantomData::<Power<_, _>> }));
+            lhs: IntegerNoU64, rhs: ScalarI64;
+            Ok(Box::new(NullableCheckedBinaryVSOperator { lhs, rhs, output, present, op: PhantomData::<Power<_, _>> }));
+            lhs: IntegerNoU64, rhs: IntegerNoU64;
+            Ok(Box::new(NullableCheckedBinaryOperator { lhs, rhs, output, present, op: PhantomData::<Power<_, _>> }))
+        }
+    }
+
     pub fn or<'a>(
         lhs: BufferRef<u8>,
         rhs: BufferRef<u8>,
@@ -1055,12 +1373,19 @@ pub mod operator {
     ) -> Result<BoxedOperator<'a>, QueryError> {
         if output.tag == EncodingType::Val {
             let output = output.val()?;
-            if input.tag.is_nullable() {
+            if input.tag == EncodingType::Null {
+                Ok(Box::new(NullToVal { input: input.any(), vals: output }) as BoxedOperator<'a>)
+            } else if input.tag.is_nullable() {
                 if input.tag == EncodingType::NullableStr {
                     Ok(Box::new(NullableStrToVal {
                         input: input.nullable_str()?,
                         vals: output,
                     }) as BoxedOperator<'a>)
+                } else if input.tag == EncodingType::NullableF64 {
+                    Ok(Box::new(NullableFloatToVal {
+                        input: input.nullable_f64()?,
+                        vals: output,
+                    }) as BoxedOperator<'a>)
                 } else {
                     reify_types! {
                         "nullable_int_to_val";
@@ -1104,6 +1429,12 @@ pub mod operator {
                     output: output.opt_str()?,
                 }));
             }
+            if input.tag == EncodingType::I64 && output.tag == EncodingType::F64 {
+                return Ok(Box::new(TypeConversionOperator {
+                    input: input.i64()?,
+                    output: output.f64()?,
+                }));
+            }
             reify_types! {
                 "type_conversion";
                 input: Integer, output: Integer;
@@ -1129,6 +1460,45 @@ pub mod operator {
         })
     }
 
+    pub fn date_trunc<'a>(
+        input: BufferRef<i64>,
+        unit: &str,
+        output: BufferRef<i64>,
+    ) -> Result<BoxedOperator<'a>, QueryError> {
+        let unit = match TimeUnit::parse(unit) {
+            Some(unit) => unit,
+            None => bail!(QueryError::TypeError, "Unsupported unit for date_trunc: {}", unit),
+        };
+        Ok(Box::new(MapOperator {
+            input,
+            output,
+            map: DateTrunc { unit },
+        }))
+    }
+
+    pub fn concat<'a>(
+        lhs: TypedBufferRef,
+        rhs: TypedBufferRef,
+        stringstore: BufferRef<u8>,
+        concat: BufferRef<&'a str>,
+    ) -> Result<BoxedOperator<'a>, QueryError> {
+        Ok(Box::new(Concat::<'a> {
+            lhs: StrInput::new(lhs)?,
+            rhs: StrInput::new(rhs)?,
+            stringstore,
+            concat,
+        }))
+    }
+
+    pub fn change_case<'a>(
+        input: BufferRef<&'a str>,
+        stringstore: BufferRef<u8>,
+        output: BufferRef<&'a str>,
+        uppercase: bool,
+    ) -> BoxedOperator<'a> {
+        Box::new(ChangeCase { input, stringstore, output, uppercase })
+    }
+
     pub fn regex<'a>(
         input: BufferRef<&'a str>,
         r: &str,
@@ -1143,6 +1513,135 @@ pub mod operator {
         })
     }
 
+    pub fn like<'a>(
+        input: BufferRef<&'a str>,
+        pattern: &str,
+        output: BufferRef<u8>,
+    ) -> BoxedOperator<'a> {
+        Box::new(MapOperator {
+            input,
+            output,
+            map: LikeMatch::new(pattern),
+        })
+    }
+
+    pub fn floor<'a>(input: BufferRef<OrderedFloat<f64>>, output: BufferRef<i64>) -> BoxedOperator<'a> {
+        Box::new(MapOperator {
+            input,
+            output,
+            map: Floor,
+        })
+    }
+
+    pub fn ceil<'a>(input: BufferRef<OrderedFloat<f64>>, output: BufferRef<i64>) -> BoxedOperator<'a> {
+        Box::new(MapOperator {
+            input,
+            output,
+            map: Ceil,
+        })
+    }
+
+    pub fn round<'a>(input: BufferRef<OrderedFloat<f64>>, output: BufferRef<i64>) -> BoxedOperator<'a> {
+        Box::new(MapOperator {
+            input,
+            output,
+            map: Round,
+        })
+    }
+
+    pub fn round_digits<'a>(
+        input: BufferRef<OrderedFloat<f64>>,
+        digits: i32,
+        output: BufferRef<OrderedFloat<f64>>,
+    ) -> BoxedOperator<'a> {
+        Box::new(MapOperator {
+            input,
+            output,
+            map: RoundDigits { digits },
+        })
+    }
+
+    pub fn abs<'a>(input: TypedBufferRef, output: TypedBufferRef) -> Result<BoxedOperator<'a>, QueryError> {
+        match input.tag {
+            EncodingType::I64 => Ok(Box::new(MapOperator {
+                input: input.i64()?,
+                output: output.i64()?,
+                map: Abs,
+            })),
+            EncodingType::F64 => Ok(Box::new(MapOperator {
+                input: input.f64()?,
+                output: output.f64()?,
+                map: Abs,
+            })),
+            _ => Err(fatal!("abs not supported for type {:?}", input.tag)),
+        }
+    }
+
+    pub fn sign<'a>(input: TypedBufferRef, output: BufferRef<i64>) -> Result<BoxedOperator<'a>, QueryError> {
+        match input.tag {
+            EncodingType::I64 => Ok(Box::new(MapOperator {
+                input: input.i64()?,
+                output,
+                map: Sign,
+            })),
+            EncodingType::F64 => Ok(Box::new(MapOperator {
+                input: input.f64()?,
+                output,
+                map: Sign,
+            })),
+            _ => Err(fatal!("sign not supported for type {:?}", input.tag)),
+        }
+    }
+
+    pub fn sqrt<'a>(input: TypedBufferRef, output: TypedBufferRef) -> Result<BoxedOperator<'a>, QueryError> {
+        partial_float_map(input, output, Sqrt)
+    }
+
+    pub fn ln<'a>(input: TypedBufferRef, output: TypedBufferRef) -> Result<BoxedOperator<'a>, QueryError> {
+        partial_float_map(input, output, Ln)
+    }
+
+    pub fn log10<'a>(input: TypedBufferRef, output: TypedBufferRef) -> Result<BoxedOperator<'a>, QueryError> {
+        partial_float_map(input, output, Log10)
+    }
+
+    pub fn float_null_if_zero<'a>(input: TypedBufferRef, output: TypedBufferRef) -> Result<BoxedOperator<'a>, QueryError> {
+        partial_float_map(input, output, NullIfZero)
+    }
+
+    fn partial_float_map<'a, M>(input: TypedBufferRef, output: TypedBufferRef, map: M) -> Result<BoxedOperator<'a>, QueryError>
+        where M: MapOp<i64, Option<OrderedFloat<f64>>> + MapOp<OrderedFloat<f64>, Option<OrderedFloat<f64>>> + 'a {
+        match input.tag {
+            EncodingType::I64 => Ok(Box::new(PartialMapOperator {
+                input: input.i64()?,
+                output: output.nullable_f64()?,
+                map,
+            })),
+            EncodingType::F64 => Ok(Box::new(PartialMapOperator {
+                input: input.f64()?,
+                output: output.nullable_f64()?,
+                map,
+            })),
+            _ => Err(fatal!("{} not supported for type {:?}", <M as MapOp<i64, _>>::name(), input.tag)),
+        }
+    }
+
+    pub fn exp<'a>(input: TypedBufferRef, output: BufferRef<OrderedFloat<f64>>) -> Result<BoxedOperator<'a>, QueryError> {
+        match input.tag {
+            EncodingType::I64 => Ok(Box::new(MapOperator {
+                input: input.i64()?,
+                output,
+                map: Exp,
+            })),
+            EncodingType::F64 => Ok(Box::new(MapOperator {
+                input: input.f64()?,
+                output,
+                map: Exp,
+            })),
+            _ => Err(fatal!("exp not supported for type {:?}", input.tag)),
+        }
+    }
+
     pub fn length(input: BufferRef<&str>, output: BufferRef<i64>) -> BoxedOperator {
         Box::new(MapOperator {
             input,
@@ -1195,6 +1694,43 @@ pub mod operator {
         }
     }
 
+    pub fn aggregate_str<'a>(
+        input: TypedBufferRef,
+        grouping: TypedBufferRef,
+        max_index: BufferRef<Scalar<i64>>,
+        aggregator: Aggregator,
+        output: TypedBufferRef,
+    ) -> Result<BoxedOperator<'a>, QueryError> {
+        let output = output.opt_str()?;
+        if input.is_nullable() {
+            reify_types! {
+                "nullable_aggregation";
+                input: NullableStr, grouping: Integer, aggregator: StrAggregator;
+                Ok(Box::new(AggregateNullable { input, grouping, output, max_index, a: aggregator }))
+            }
+        } else {
+            reify_types! {
+                "aggregation";
+                input: Str, grouping: Integer, aggregator: StrAggregator;
+                Ok(Box::new(Aggregate { input, grouping, output, max_index, a: aggregator }))
+            }
+        }
+    }
+
+    pub fn approx_percentile<'a>(
+        input: BufferRef<i64>,
+        grouping: TypedBufferRef,
+        max_index: BufferRef<Scalar<i64>>,
+        quantile: f64,
+        output: BufferRef<OrderedFloat<f64>>,
+    ) -> Result<BoxedOperator<'a>, QueryError> {
+        reify_types! {
+            "approx_percentile";
+            grouping: Integer;
+            Ok(Box::new(ApproxPercentile { input, grouping, output, max_index, quantile }))
+        }
+    }
+
     pub fn checked_aggregate<'a>(
         input: TypedBufferRef,
         grouping: TypedBufferRef,
@@ -1315,6 +1851,7 @@ pub mod operator {
         ranking: TypedBufferRef,
         indices: BufferRef<usize>,
         descending: bool,
+        nulls_first: bool,
         stable: bool,
         output: BufferRef<usize>,
     ) -> Result<BoxedOperator<'a>, QueryError> {
@@ -1340,7 +1877,7 @@ pub mod operator {
             reify_types! {
                 "sort_indices";
                 ranking: NullablePrimitive;
-                Ok(Box::new(SortByNullable { ranking, output, indices, descending, stable }))
+                Ok(Box::new(SortByNullable { ranking, output, indices, descending, nulls_first, stable }))
             }
         } else {
             reify_types! {
@@ -1356,19 +1893,20 @@ pub mod operator {
         keys: TypedBufferRef,
         n: usize,
         desc: bool,
+        with_ties: bool,
         indices_out: BufferRef<usize>,
     ) -> Result<BoxedOperator<'a>, QueryError> {
         if desc {
             reify_types! {
                 "top_n_desc";
                 input, keys: Primitive;
-                Ok(Box::new(TopN { input, keys, indices: indices_out, last_index: 0, n, c: PhantomData::<CmpGreaterThan> }))
+                Ok(Box::new(TopN { input, keys, indices: indices_out, last_index: 0, n, with_ties, ties: vec![], c: PhantomData::<CmpGreaterThan> }))
             }
         } else {
             reify_types! {
                 "top_n_asc";
                 input, keys: Primitive;
-                Ok(Box::new(TopN { input, keys, indices: indices_out, last_index: 0, n, c: PhantomData::<CmpLessThan> }))
+                Ok(Box::new(TopN { input, keys, indices: indices_out, last_index: 0, n, with_ties, ties: vec![], c: PhantomData::<CmpLessThan> }))
             }
         }
     }
@@ -1379,6 +1917,10 @@ pub mod operator {
         ops_out: BufferRef<MergeOp>,
         merged_out: TypedBufferRef,
     ) -> Result<BoxedOperator<'a>, QueryError> {
+        // Nullable floats and booleans are represented as `Val`
+        if let (EncodingType::Val, EncodingType::Val) = (left.tag, right.tag) {
+            return Ok(Box::new(MergeDeduplicate { left: left.val()?, right: right.val()?, deduplicated: merged_out.val()?, merge_ops: ops_out }));
+        }
         reify_types! {
             "merge_deduplicate";
             left, right, merged_out: Primitive;
@@ -1391,19 +1933,37 @@ pub mod operator {
         right: TypedBufferRef,
         limit: usize,
         desc: bool,
+        nulls_first: bool,
         partition_out: BufferRef<Premerge>,
     ) -> Result<BoxedOperator<'a>, QueryError> {
-        if desc {
-            reify_types! {
-                "partition";
-                left, right: Primitive;
-                Ok(Box::new(Partition { left, right, partitioning: partition_out, limit, c: PhantomData::<CmpGreaterThan> }))
+        match (desc, nulls_first) {
+            (true, false) => {
+                reify_types! {
+                    "partition";
+                    left, right: Primitive;
+                    Ok(Box::new(Partition { left, right, partitioning: partition_out, limit, c: PhantomData::<CmpGreaterThan> }))
+                }
             }
-        } else {
-            reify_types! {
-                "partition";
-                left, right: Primitive;
-                Ok(Box::new(Partition { left, right, partitioning: partition_out, limit, c: PhantomData::<CmpLessThan> }))
+            (true, true) => {
+                reify_types! {
+                    "partition";
+                    left, right: Primitive;
+                    Ok(Box::new(Partition { left, right, partitioning: partition_out, limit, c: PhantomData::<CmpGreaterThanNullsFirst> }))
+                }
+            }
+            (false, true) => {
+                reify_types! {
+                    "partition";
+                    left, right: Primitive;
+                    Ok(Box::new(Partition { left, right, partitioning: partition_out, limit, c: PhantomData::<CmpLessThan> }))
+                }
+            }
+            (false, false) => {
+                reify_types! {
+                    "partition";
+                    left, right: Primitive;
+                    Ok(Box::new(Partition { left, right, partitioning: partition_out, limit, c: PhantomData::<CmpLessThanNullsLast> }))
+                }
             }
         }
     }
@@ -1413,19 +1973,37 @@ pub mod operator {
         left: TypedBufferRef,
         right: TypedBufferRef,
         desc: bool,
+        nulls_first: bool,
         subpartition_out: BufferRef<Premerge>,
     ) -> Result<BoxedOperator<'a>, QueryError> {
-        if desc {
-            reify_types! {
-                "subpartition";
-                left, right: Primitive;
-                Ok(Box::new(SubPartition { partitioning, left, right, sub_partitioning: subpartition_out, c: PhantomData::<CmpGreaterThan> }))
+        match (desc, nulls_first) {
+            (true, false) => {
+                reify_types! {
+                    "subpartition";
+                    left, right: Primitive;
+                    Ok(Box::new(SubPartition { partitioning, left, right, sub_partitioning: subpartition_out, c: PhantomData::<CmpGreaterThan> }))
+                }
             }
-        } else {
-            reify_types! {
-                "subpartition";
-                left, right: Primitive;
-                Ok(Box::new(SubPartition { partitioning, left, right, sub_partitioning: subpartition_out, c: PhantomData::<CmpLessThan> }))
+            (true, true) => {
+                reify_types! {
+                    "subpartition";
+                    left, right: Primitive;
+                    Ok(Box::new(SubPartition { partitioning, left, right, sub_partitioning: subpartition_out, c: PhantomData::<CmpGreaterThanNullsFirst> }))
+                }
+            }
+            (false, true) => {
+                reify_types! {
+                    "subpartition";
+                    left, right: Primitive;
+                    Ok(Box::new(SubPartition { partitioning, left, right, sub_partitioning: subpartition_out, c: PhantomData::<CmpLessThan> }))
+                }
+            }
+            (false, false) => {
+                reify_types! {
+                    "subpartition";
+                    left, right: Primitive;
+                    Ok(Box::new(SubPartition { partitioning, left, right, sub_partitioning: subpartition_out, c: PhantomData::<CmpLessThanNullsLast> }))
+                }
             }
         }
     }
@@ -1479,30 +2057,63 @@ pub mod operator {
                 aggregated: aggregated_out.f64()?,
                 aggregator,
             })),
+            EncodingType::Str => Ok(Box::new(MergeAggregate {
+                merge_ops,
+                left: left.str()?,
+                right: right.str()?,
+                aggregated: aggregated_out.str()?,
+                aggregator,
+            })),
+            EncodingType::OptStr => Ok(Box::new(MergeAggregate {
+                merge_ops,
+                left: left.opt_str()?,
+                right: right.opt_str()?,
+                aggregated: aggregated_out.opt_str()?,
+                aggregator,
+            })),
             _ => panic!("Unsupported type {:?} for merge_aggregate", left.tag),
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn merge_partitioned<'a>(
         partitioning: BufferRef<Premerge>,
         left: TypedBufferRef,
         right: TypedBufferRef,
         limit: usize,
         desc: bool,
+        nulls_first: bool,
         ops_out: BufferRef<u8>,
         merged_out: TypedBufferRef,
     ) -> Result<BoxedOperator<'a>, QueryError> {
-        if desc {
-            reify_types! {
-                "merge_partitioned_desc";
-                left, right, merged_out: Primitive;
-                Ok(Box::new(MergePartitioned { partitioning, left, right, merged: merged_out, take_left: ops_out, limit, c: PhantomData::<CmpGreaterThan> }))
+        match (desc, nulls_first) {
+            (true, false) => {
+                reify_types! {
+                    "merge_partitioned_desc";
+                    left, right, merged_out: Primitive;
+                    Ok(Box::new(MergePartitioned { partitioning, left, right, merged: merged_out, take_left: ops_out, limit, c: PhantomData::<CmpGreaterThan> }))
+                }
             }
-        } else {
-            reify_types! {
-                "merge_partitioned_asc";
-                left, right, merged_out: Primitive;
-                Ok(Box::new(MergePartitioned { partitioning, left, right, merged: merged_out, take_left: ops_out, limit, c: PhantomData::<CmpLessThan> }))
+            (true, true) => {
+                reify_types! {
+                    "merge_partitioned_desc";
+                    left, right, merged_out: Primitive;
+                    Ok(Box::new(MergePartitioned { partitioning, left, right, merged: merged_out, take_left: ops_out, limit, c: PhantomData::<CmpGreaterThanNullsFirst> }))
+                }
+            }
+            (false, true) => {
+                reify_types! {
+                    "merge_partitioned_asc";
+                    left, right, merged_out: Primitive;
+                    Ok(Box::new(MergePartitioned { partitioning, left, right, merged: merged_out, take_left: ops_out, limit, c: PhantomData::<CmpLessThan> }))
+                }
+            }
+            (false, false) => {
+                reify_types! {
+                    "merge_partitioned_asc";
+                    left, right, merged_out: Primitive;
+                    Ok(Box::new(MergePartitioned { partitioning, left, right, merged: merged_out, take_left: ops_out, limit, c: PhantomData::<CmpLessThanNullsLast> }))
+                }
             }
         }
     }
@@ -1512,20 +2123,38 @@ pub mod operator {
         right: TypedBufferRef,
         limit: usize,
         desc: bool,
+        nulls_first: bool,
         ops_out: BufferRef<u8>,
         merged_out: TypedBufferRef,
     ) -> Result<BoxedOperator<'a>, QueryError> {
-        if desc {
-            reify_types! {
-                "merge_desc";
-                left, right, merged_out: Primitive;
-                Ok(Box::new(Merge { left, right, merged: merged_out, merge_ops: ops_out, limit, c: PhantomData::<CmpGreaterThan> }))
+        match (desc, nulls_first) {
+            (true, false) => {
+                reify_types! {
+                    "merge_desc";
+                    left, right, merged_out: Primitive;
+                    Ok(Box::new(Merge { left, right, merged: merged_out, merge_ops: ops_out, limit, c: PhantomData::<CmpGreaterThan> }))
+                }
             }
-        } else {
-            reify_types! {
-                "merge_desc";
-                left, right, merged_out: Primitive;
-                Ok(Box::new(Merge { left, right, merged: merged_out, merge_ops: ops_out, limit, c: PhantomData::<CmpLessThan> }))
+            (true, true) => {
+                reify_types! {
+                    "merge_desc";
+                    left, right, merged_out: Primitive;
+                    Ok(Box::new(Merge { left, right, merged: merged_out, merge_ops: ops_out, limit, c: PhantomData::<CmpGreaterThanNullsFirst> }))
+                }
+            }
+            (false, true) => {
+                reify_types! {
+                    "merge_desc";
+                    left, right, merged_out: Primitive;
+                    Ok(Box::new(Merge { left, right, merged: merged_out, merge_ops: ops_out, limit, c: PhantomData::<CmpLessThan> }))
+                }
+            }
+            (false, false) => {
+                reify_types! {
+                    "merge_desc";
+                    left, right, merged_out: Primitive;
+                    Ok(Box::new(Merge { left, right, merged: merged_out, merge_ops: ops_out, limit, c: PhantomData::<CmpLessThanNullsLast> }))
+                }
             }
         }
     }
@@ -1536,6 +2165,13 @@ pub mod operator {
         right: TypedBufferRef,
         merged_out: TypedBufferRef,
     ) -> Result<BoxedOperator<'a>, QueryError> {
+        // Nullable floats are represented as `Val`
+        if let (EncodingType::Val, EncodingType::Val) = (left.tag, right.tag) {
+            return Ok(Box::new(MergeKeep { merge_ops, left: left.val()?, right: right.val()?, merged: merged_out.val()? }));
+        }
+        if let (EncodingType::Null, EncodingType::Null) = (left.tag, right.tag) {
+            return Ok(Box::new(MergeKeepNull { merge_ops, merged: merged_out.any() }));
+        }
         reify_types! {
                 "merge_keep";
                 left, right, merged_out: NullablePrimitive;
@@ -8,4 +8,7 @@ pub use self::planner::QueryPlanner;
 pub use self::filter::Filter;
 pub use self::query::ColumnInfo;
 pub use self::query::Query;
+pub use self::query::UnionAll;
+pub use self::query::Join;
 pub use self::query::NormalFormQuery;
+pub use self::query::Subquery;
@@ -1,12 +1,18 @@
+use crate::disk_store::interface::PartitionID;
+use crate::engine::operators::approx_percentile;
+use crate::engine::query_task::{QueryOutput, QueryStats};
 use crate::engine::*;
 use crate::ingest::raw_val::RawVal;
 use crate::mem_store::column::DataSource;
 use crate::syntax::expression::*;
 use crate::syntax::limit::*;
 use crate::QueryError;
+use ordered_float::OrderedFloat;
+use std::cmp::{self, Ordering};
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::iter::Iterator;
+use std::mem;
 use std::sync::Arc;
 use std::u64;
 
@@ -16,6 +22,16 @@ pub struct ColumnInfo {
     pub name: Option<String>,
 }
 
+impl ColumnInfo {
+    /// Name of the column in query results, the alias if there is one and otherwise the expression, e.g. `cpu * 100`.
+    pub fn display_name(&self) -> String {
+        match self.name {
+            Some(ref name) => name.clone(),
+            None => self.expr.to_string(),
+        }
+    }
+}
+
 /// NormalFormQuery observes the following invariants:
 /// - none of the expressions contain aggregation functions
 /// - if aggregate.len() > 0 then order_by.len() == 0 and vice versa
@@ -24,7 +40,8 @@ pub struct NormalFormQuery {
     pub projection: Vec<ColumnInfo>,
     pub filter: Expr,
     pub aggregate: Vec<(Aggregator, ColumnInfo)>,
-    pub order_by: Vec<(Expr, bool)>,
+    /// Sort expressions, whether the order is descending and whether nulls come first.
+    pub order_by: Vec<(Expr, bool, bool)>,
     pub limit: LimitClause,
 }
 
@@ -33,10 +50,51 @@ pub struct Query {
     pub select: Vec<ColumnInfo>,
     pub table: String,
     pub filter: Expr,
-    pub order_by: Vec<(Expr, bool)>,
+    /// Filter applied to groups after aggregation.
+    pub having: Expr,
+    /// Sort expressions, whether the order is descending and whether nulls come first.
+    pub order_by: Vec<(Expr, bool, bool)>,
+    pub limit: LimitClause,
+    /// Predicate on `partition_id` that restricts which partitions of the table are scanned,
+    /// e.g. `FROM t WITH (partition_id BETWEEN 0 AND 9)`.
+    pub partition_filter: Expr,
+}
+
+/// Multiple queries whose results are concatenated, i.e. `SELECT ... UNION ALL SELECT ...`.
+#[derive(Debug, Clone)]
+pub struct UnionAll {
+    /// ORDER BY and LIMIT of the union are also applied to each query to reduce the number of rows that are merged.
+    pub queries: Vec<Query>,
+    /// Index of the output column to sort by, whether the order is descending and whether nulls come first.
+    pub order_by: Vec<(usize, bool, bool)>,
     pub limit: LimitClause,
 }
 
+/// Inner join of two tables on equal integer keys, i.e. `SELECT ... FROM a JOIN b ON a.id = b.id`.
+#[derive(Debug, Clone)]
+pub struct Join {
+    /// Queries that select the columns referenced from the left and right table, respectively.
+    pub queries: [Query; 2],
+    /// Index of the join key in the output of each query.
+    pub keys: [usize; 2],
+    /// Query (0 = left, 1 = right) and index of the column in its output for each output column.
+    pub projection: Vec<(usize, usize)>,
+    pub colnames: Vec<String>,
+    /// Index of the output column to sort by, whether the order is descending and whether nulls come first.
+    pub order_by: Vec<(usize, bool, bool)>,
+    pub limit: LimitClause,
+}
+
+/// `expr [NOT] IN (SELECT column FROM ...)` in the WHERE clause of a query. Until the subquery has been executed,
+/// the condition is represented by a reference to the column `placeholder` in the filter of the query.
+#[derive(Debug, Clone)]
+pub struct Subquery {
+    pub placeholder: String,
+    pub expr: Expr,
+    pub query: Query,
+    pub negated: bool,
+}
+
 impl NormalFormQuery {
     #[inline(never)] // produces more useful profiles
     pub fn run<'a>(
@@ -46,14 +104,18 @@ impl NormalFormQuery {
         show: bool,
         partition: usize,
         partition_len: usize,
+        limits: QueryLimits,
     ) -> Result<(BatchResult<'a>, Option<String>), QueryError> {
+        self.validate()?;
         println!("Running {:?}", self);
         let limit = (self.limit.limit + self.limit.offset) as usize;
         println!("limit: {limit}");
         let mut planner = QueryPlanner::default();
 
+        let no_match = Expr::Const(RawVal::Null);
+        let filter_expr = if self.filter_excludes(columns) { &no_match } else { &self.filter };
         let (filter_plan, _) = QueryPlan::compile_expr(
-            &self.filter,
+            filter_expr,
             Filter::None,
             columns,
             partition_len,
@@ -62,30 +124,49 @@ impl NormalFormQuery {
         let mut filter = match filter_plan.tag {
             EncodingType::U8 => Filter::U8(filter_plan.u8()?),
             EncodingType::NullableU8 => Filter::NullableU8(filter_plan.nullable_u8()?),
+            // A filter that is always NULL matches no rows
+            EncodingType::Null => Filter::U8(planner.constant_expand(0, partition_len, EncodingType::U8).u8()?),
+            // A constant filter such as `WHERE 1 = 0` matches either all or no rows
+            EncodingType::ScalarI64 => match *planner.resolve(&filter_plan) {
+                QueryPlan::ScalarI64 { value: 0, .. } => {
+                    Filter::U8(planner.constant_expand(0, partition_len, EncodingType::U8).u8()?)
+                }
+                _ => Filter::None,
+            },
             _ => Filter::None,
         };
 
         // Sorting
-        let mut sort_indices = None;
-        for (plan, desc) in self.order_by.iter().rev() {
+        let mut rankings = Vec::with_capacity(self.order_by.len());
+        for (plan, desc, nulls_first) in &self.order_by {
             let (ranking, _) = query_plan::order_preserving(
                 QueryPlan::compile_expr(plan, filter, columns, partition_len, &mut planner)?,
                 &mut planner,
             );
-
-            // PERF: better criterion for using top_n
-            // PERF: top_n for multiple columns?
-            sort_indices = Some(if limit < partition_len / 2 && self.order_by.len() == 1 {
-                planner.top_n(ranking, limit, *desc)
+            rankings.push((ranking, *desc, *nulls_first));
+        }
+        // PERF: better criterion for using top_n
+        let use_top_n = limit < partition_len / 2;
+        // With multiple sort keys, restrict the sort to the top `limit` rows by the first key plus
+        // all rows tied with the last of them. The candidates are then sorted by all keys.
+        let mut sort_indices = match rankings.first() {
+            Some(&(ranking, desc, _)) if use_top_n && rankings.len() > 1 && !ranking.is_nullable() => {
+                Some(planner.top_n(ranking, limit, desc, true))
+            }
+            _ => None,
+        };
+        for &(ranking, desc, nulls_first) in rankings.iter().rev() {
+            sort_indices = Some(if use_top_n && rankings.len() == 1 && !ranking.is_nullable() {
+                planner.top_n(ranking, limit, desc, false)
             } else {
                 // PERF: sort directly if only single column selected
                 match sort_indices {
                     None => {
                         let indices = planner.indices(ranking);
-                        planner.sort_by(ranking, indices, *desc, false /* unstable sort */)
+                        planner.sort_by(ranking, indices, desc, nulls_first, false /* unstable sort */)
                     }
                     Some(indices) => {
-                        planner.sort_by(ranking, indices, *desc, true /* stable sort */)
+                        planner.sort_by(ranking, indices, desc, nulls_first, true /* stable sort */)
                     }
                 }
             });
@@ -110,6 +191,7 @@ impl NormalFormQuery {
         }
 
         let mut select = Vec::new();
+        let mut types = Vec::new();
         for col_info in &self.projection {
             let (mut plan, plan_type) = QueryPlan::compile_expr(
                 &col_info.expr,
@@ -118,6 +200,7 @@ impl NormalFormQuery {
                 partition_len,
                 &mut planner,
             )?;
+            types.push(plan_type.decoded);
             if let Some(codec) = plan_type.codec {
                 plan = codec.decode(plan, &mut planner);
             }
@@ -127,7 +210,7 @@ impl NormalFormQuery {
             select.push(plan.any());
         }
         let mut order_by = Vec::new();
-        for (expr, desc) in &self.order_by {
+        for (expr, desc, nulls_first) in &self.order_by {
             let (mut plan, plan_type) =
                 QueryPlan::compile_expr(expr, filter, columns, partition_len, &mut planner)?;
             if let Some(codec) = plan_type.codec {
@@ -136,7 +219,7 @@ impl NormalFormQuery {
             if plan.is_nullable() {
                 plan = planner.fuse_nulls(plan);
             }
-            order_by.push((plan.any(), *desc));
+            order_by.push((plan.any(), *desc, *nulls_first));
         }
 
         for c in columns {
@@ -144,6 +227,7 @@ impl NormalFormQuery {
         }
         let mut executor = planner.prepare(vec![])?;
         let mut results = executor.prepare(NormalFormQuery::column_data(columns));
+        results.set_limits(limits);
         debug!("{:#}", &executor);
         executor.run(partition_len, &mut results, show)?;
         let (columns, projection, _, order_by) = results.collect_aliased(&select, &[], &order_by);
@@ -154,6 +238,7 @@ impl NormalFormQuery {
                 projection,
                 aggregations: vec![],
                 order_by,
+                types,
                 level: 0,
                 batch_count: 1,
                 show,
@@ -175,15 +260,28 @@ impl NormalFormQuery {
         show: bool,
         partition: usize,
         partition_len: usize,
+        limits: QueryLimits,
     ) -> Result<(BatchResult<'a>, Option<String>), QueryError> {
+        self.validate()?;
         let mut qp = QueryPlanner::default();
 
         // Filter
-        let (filter_plan, filter_type) =
-            QueryPlan::compile_expr(&self.filter, Filter::None, columns, partition_len, &mut qp)?;
-        let filter = match filter_type.encoding_type() {
+        let no_match = Expr::Const(RawVal::Null);
+        let filter_expr = if self.filter_excludes(columns) { &no_match } else { &self.filter };
+        let (filter_plan, _) =
+            QueryPlan::compile_expr(filter_expr, Filter::None, columns, partition_len, &mut qp)?;
+        let filter = match filter_plan.tag {
             EncodingType::U8 => Filter::U8(filter_plan.u8()?),
             EncodingType::NullableU8 => Filter::NullableU8(filter_plan.nullable_u8()?),
+            // A filter that is always NULL matches no rows
+            EncodingType::Null => Filter::U8(qp.constant_expand(0, partition_len, EncodingType::U8).u8()?),
+            // A constant filter such as `WHERE 1 = 0` matches either all or no rows
+            EncodingType::ScalarI64 => match *qp.resolve(&filter_plan) {
+                QueryPlan::ScalarI64 { value: 0, .. } => {
+                    Filter::U8(qp.constant_expand(0, partition_len, EncodingType::U8).u8()?)
+                }
+                _ => Filter::None,
+            },
             _ => Filter::None,
         };
 
@@ -233,20 +331,36 @@ impl NormalFormQuery {
         for (i, &(aggregator, ref col_info)) in self.aggregate.iter().enumerate() {
             let (plan, plan_type) =
                 QueryPlan::compile_expr(&col_info.expr, filter, columns, partition_len, &mut qp)?;
-            let (aggregate, t) = query_plan::prepare_aggregation(
-                plan,
-                plan_type,
-                grouping_key,
-                aggregation_cardinality,
-                aggregator,
-                &mut qp,
-            )?;
+            let always_null = plan.tag == EncodingType::Null;
+            let (aggregate, t) = if aggregator == Aggregator::Count && always_null {
+                // `COUNT` only counts non-null values, so expressions like `COUNT(NULL)` are zero for every group
+                let (zeros, _) = QueryPlan::compile_expr(
+                    &Expr::func1(Func1Type::IsNotNull, col_info.expr.clone()),
+                    filter,
+                    columns,
+                    partition_len,
+                    &mut qp,
+                )?;
+                (
+                    qp.aggregate(zeros, grouping_key, aggregation_cardinality, Aggregator::SumI64, EncodingType::I64),
+                    Type::unencoded(BasicType::Integer),
+                )
+            } else {
+                query_plan::prepare_aggregation(
+                    plan,
+                    plan_type,
+                    grouping_key,
+                    aggregation_cardinality,
+                    aggregator,
+                    &mut qp,
+                )?
+            };
             // PERF: if summation column is strictly positive, can use sum as well
-            if aggregator == Aggregator::Count && !plan.is_nullable() {
+            if aggregator == Aggregator::Count && !plan.is_nullable() && !always_null {
                 selector = Some((aggregate, t.encoding_type()));
                 selector_index = Some(i)
             }
-            aggregation_results.push((aggregator, aggregate, t, plan.is_nullable()))
+            aggregation_results.push((aggregator, aggregate, t, plan.is_nullable() || always_null))
         }
 
         // Determine selector
@@ -260,7 +374,31 @@ impl NormalFormQuery {
             None => qp.nonzero_indices(selector, grouping_key.tag),
             Some(x) => x,
         };
-        qp.connect(encoded_group_by_column, encoded_group_by_placeholder);
+        let encoded_sort_indices = if !is_grouping_key_order_preserving && is_raw_grouping_key_order_preserving {
+            let indices = qp.indices(encoded_group_by_column);
+            Some(qp.sort_by(
+                encoded_group_by_column,
+                indices,
+                false, /* desc */
+                true,  /* nulls_first */
+                false, /* stable */
+            ))
+        } else {
+            None
+        };
+        // Integer grouping keys are sorted before they are decoded, so that each group by column is decoded once and
+        // already in sorted order instead of permuting all decoded group by columns
+        let sorted_before_decode = match encoded_sort_indices {
+            Some(sort_indices) if encoded_group_by_column.tag.is_integer() => {
+                let sorted = qp.select(encoded_group_by_column, sort_indices);
+                qp.connect(sorted, encoded_group_by_placeholder);
+                true
+            }
+            _ => {
+                qp.connect(encoded_group_by_column, encoded_group_by_placeholder);
+                false
+            }
+        };
 
         // Compact and decode aggregation results
         let mut aggregation_cols = Vec::new();
@@ -271,7 +409,7 @@ impl NormalFormQuery {
                                       input_nullable: bool| {
                 let compacted = match aggregator {
                     // PERF: if summation column is strictly positive, can use NonzeroCompact
-                    Aggregator::SumI64 | Aggregator::MaxI64 | Aggregator::MinI64 | Aggregator::SumF64 | Aggregator::MaxF64 | Aggregator::MinF64 => {
+                    Aggregator::SumI64 | Aggregator::MaxI64 | Aggregator::MinI64 | Aggregator::SumF64 | Aggregator::MaxF64 | Aggregator::MinF64 | Aggregator::MaxStr | Aggregator::MinStr | Aggregator::Avg | Aggregator::CountDistinct | Aggregator::VarPop | Aggregator::StdDev | Aggregator::ApproxPercentile(_) => {
                         qp.compact(aggregate, selector)
                     }
                     Aggregator::Count => {
@@ -296,12 +434,7 @@ impl NormalFormQuery {
                     let decode_compacted =
                         decode_compact(aggregator, aggregate, t.clone(), input_nullable)?;
                     let aggregator = if aggregate.tag == EncodingType::F64 {
-                        match aggregator {
-                            Aggregator::SumI64 => Aggregator::SumF64,
-                            Aggregator::MaxI64 => Aggregator::MaxF64,
-                            Aggregator::MinI64 => Aggregator::MinF64,
-                            _ => aggregator,
-                        }
+                        aggregator.float()
                     } else {
                         aggregator
                     };
@@ -319,34 +452,31 @@ impl NormalFormQuery {
 
         //  Reconstruct all group by columns from grouping
         let mut grouping_columns = Vec::with_capacity(decode_plans.len());
-        for (decode_plan, _t) in decode_plans {
+        let mut types = Vec::with_capacity(decode_plans.len() + aggregation_results.len());
+        for (decode_plan, t) in decode_plans {
             grouping_columns.push(decode_plan);
+            types.push(t.decoded);
         }
+        types.extend(aggregation_results.iter().map(|(_, _, t, _)| t.decoded));
 
         // If the grouping is not order preserving, we need to sort all output columns by using the ordering constructed from the decoded group by columns
         // This is necessary to make it possible to efficiently merge with other batch results
         if !is_grouping_key_order_preserving {
-            let sort_indices = if is_raw_grouping_key_order_preserving {
-                let indices = qp.indices(encoded_group_by_column);
-                qp.sort_by(
-                    encoded_group_by_column,
-                    indices,
-                    false, /* desc */
-                    false, /* stable */
-                )
-            } else {
-                if grouping_columns.len() != 1 {
-                    bail!(QueryError::NotImplemented,
-                        "Grouping key is not order preserving and more than 1 grouping column\nGrouping key type: {:?}\nTODO: PLANNER",
-                        &grouping_key.tag)
+            let sort_indices = match encoded_sort_indices {
+                Some(sort_indices) => sort_indices,
+                None => {
+                    // Sort lexicographically by all group by columns, starting with the last one.
+                    // Nullable columns sort NULL first, which matches the order of fused nullable columns.
+                    let last = grouping_columns[grouping_columns.len() - 1];
+                    let indices = qp.indices(last);
+                    let mut sort_indices =
+                        qp.sort_by(last, indices, false /* desc */, true /* nulls_first */, false /* stable */);
+                    for &column in grouping_columns.iter().rev().skip(1) {
+                        sort_indices =
+                            qp.sort_by(column, sort_indices, false /* desc */, true /* nulls_first */, true /* stable */);
+                    }
+                    sort_indices
                 }
-                let indices = qp.indices(grouping_columns[0]);
-                qp.sort_by(
-                    grouping_columns[0],
-                    indices,
-                    false, /* desc */
-                    false, /* stable */
-                )
             };
 
             let mut aggregations2 = Vec::new();
@@ -355,11 +485,13 @@ impl NormalFormQuery {
             }
             aggregation_cols = aggregations2;
 
-            let mut grouping_columns2 = Vec::new();
-            for s in &grouping_columns {
-                grouping_columns2.push(qp.select(*s, sort_indices));
+            if !sorted_before_decode {
+                let mut grouping_columns2 = Vec::new();
+                for s in &grouping_columns {
+                    grouping_columns2.push(qp.select(*s, sort_indices));
+                }
+                grouping_columns = grouping_columns2;
             }
-            grouping_columns = grouping_columns2;
         }
 
         for plan in &mut grouping_columns {
@@ -373,6 +505,7 @@ impl NormalFormQuery {
         }
         let mut executor = qp.prepare(vec![])?;
         let mut results = executor.prepare(NormalFormQuery::column_data(columns));
+        results.set_limits(limits);
         debug!("{:#}", &executor);
         executor.run(partition_len, &mut results, show)?;
         let (columns, projection, aggregations, _) = results.collect_aliased(
@@ -389,6 +522,7 @@ impl NormalFormQuery {
             projection,
             aggregations,
             order_by: vec![],
+            types,
             level: 0,
             batch_count: 1,
             show,
@@ -410,6 +544,32 @@ impl NormalFormQuery {
         }
     }
 
+    /// Checks that the query does not both aggregate and sort, which the final pass created by `Query::normalize`
+    /// has to take care of. Running such a query would silently ignore the sort order.
+    fn validate(&self) -> Result<(), QueryError> {
+        if !self.aggregate.is_empty() && !self.order_by.is_empty() {
+            return Err(fatal!(
+                "Query with both aggregates and ORDER BY violates normal form: {:?}",
+                self
+            ));
+        }
+        Ok(())
+    }
+
+    /// Query that additionally skips rows for which `deleted_column` is 1, i.e. rows removed by `DELETE`.
+    pub fn without_deleted_rows(&self, deleted_column: &str) -> NormalFormQuery {
+        let not_deleted = Expr::func(
+            Func2Type::Equals,
+            Expr::ColName(deleted_column.to_string()),
+            Expr::Const(RawVal::Int(0)),
+        );
+        let filter = match &self.filter {
+            Expr::Const(RawVal::Int(1)) => not_deleted,
+            filter => Expr::func(Func2Type::And, filter.clone(), not_deleted),
+        };
+        NormalFormQuery { filter, ..self.clone() }
+    }
+
     fn column_data(
         columns: &HashMap<String, Arc<dyn DataSource>>,
     ) -> HashMap<String, Vec<&dyn Data>> {
@@ -420,24 +580,76 @@ impl NormalFormQuery {
     }
 
     pub fn result_column_names(&self) -> Result<Vec<String>, QueryError> {
-        let select_cols = self
-            .projection
-            .iter()
-            .map(|col_info| extract_display_colname(col_info.name.as_ref()).unwrap());
-
-        let aggregate_cols = self
-            .aggregate
-            .iter()
-            .map(|(_, col_info)| extract_display_colname(col_info.name.as_ref()).unwrap());
+        let select_cols = self.projection.iter().map(ColumnInfo::display_name);
+        let aggregate_cols = self.aggregate.iter().map(|(_, col_info)| col_info.display_name());
+        Ok(select_cols.chain(aggregate_cols).collect())
+    }
 
-        return Ok(select_cols.chain(aggregate_cols).collect());
+    /// Whether the filter is false for all rows of a partition, judging by the min and max values of its integer columns.
+    pub fn filter_excludes(&self, columns: &HashMap<String, Arc<dyn DataSource>>) -> bool {
+        NormalFormQuery::excludes(&self.filter, columns)
+    }
 
-        fn extract_display_colname(alias: Option<&String>) -> Result<String, QueryError> {
-            if alias.is_some() {
-                return Ok(alias.as_ref().unwrap().to_string());
+    fn excludes(expr: &Expr, columns: &HashMap<String, Arc<dyn DataSource>>) -> bool {
+        let (op, lhs, rhs) = match expr {
+            Expr::Func2(op, lhs, rhs) => (*op, lhs, rhs),
+            _ => return false,
+        };
+        match op {
+            Func2Type::And => {
+                return NormalFormQuery::excludes(lhs, columns)
+                    || NormalFormQuery::excludes(rhs, columns)
+            }
+            Func2Type::Or => {
+                return NormalFormQuery::excludes(lhs, columns)
+                    && NormalFormQuery::excludes(rhs, columns)
             }
-            Err(fatal!("No human readable expression found"))
+            _ => {}
         }
+        let (colname, op, x) = match (&**lhs, &**rhs) {
+            (Expr::ColName(colname), _) => match const_int(rhs) {
+                Some(x) => (colname, op, x),
+                None => return false,
+            },
+            // Flip `x < col` into `col > x`
+            (_, Expr::ColName(colname)) => match const_int(lhs) {
+                Some(x) => {
+                    let op = match op {
+                        Func2Type::LT => Func2Type::GT,
+                        Func2Type::LTE => Func2Type::GTE,
+                        Func2Type::GT => Func2Type::LT,
+                        Func2Type::GTE => Func2Type::LTE,
+                        op => op,
+                    };
+                    (colname, op, x)
+                }
+                None => return false,
+            },
+            _ => return false,
+        };
+        let (min, max) = match columns.get(colname).and_then(|c| c.value_range()) {
+            Some(range) => range,
+            None => return false,
+        };
+        match op {
+            Func2Type::Equals => x < min || x > max,
+            Func2Type::LT => min >= x,
+            Func2Type::LTE => min > x,
+            Func2Type::GT => max <= x,
+            Func2Type::GTE => max < x,
+            _ => false,
+        }
+    }
+}
+
+fn const_int(expr: &Expr) -> Option<i64> {
+    match expr {
+        Expr::Const(RawVal::Int(x)) => Some(*x),
+        Expr::Func1(Func1Type::Negate, inner) => match **inner {
+            Expr::Const(RawVal::Int(x)) => x.checked_neg(),
+            _ => None,
+        },
+        _ => None,
     }
 }
 
@@ -448,53 +660,78 @@ impl Query {
         let mut aggregate = Vec::new();
         let mut aggregate_colnames = Vec::new();
         let mut select_colnames = Vec::new();
+        let mut selected_exprs = Vec::<(Expr, String)>::new();
         for col_info in &self.select {
-            let (full_expr, aggregates) = Query::extract_aggregators(
-                &col_info.expr,
-                &mut aggregate_colnames,
-                col_info.name.clone(),
-            )?;
+            // Name columns after the original expression before it is rewritten in terms of intermediate columns
+            let name = Some(col_info.display_name());
+            let (full_expr, aggregates) =
+                Query::extract_aggregators(&col_info.expr, &mut aggregate_colnames, col_info.name.clone())?;
             if aggregates.is_empty() {
                 let column_name = format!("_cs{}", select_colnames.len());
                 select_colnames.push(column_name.clone());
+                selected_exprs.push((full_expr.clone(), column_name.clone()));
                 select.push(ColumnInfo {
                     expr: full_expr,
-                    name: col_info.name.clone(),
+                    name: name.clone(),
                 });
                 final_projection.push(ColumnInfo {
                     expr: Expr::ColName(column_name),
-                    name: col_info.name.clone(),
+                    name,
                 });
             } else {
                 aggregate.extend(aggregates);
                 final_projection.push(ColumnInfo {
                     expr: full_expr,
-                    name: col_info.name.clone(),
+                    name,
                 });
             }
         }
 
+        let having = Query::extract_having(
+            &self.having,
+            &mut aggregate_colnames,
+            &mut aggregate,
+            &mut select_colnames,
+            &mut select,
+        )?;
+
         let require_final_pass = (!aggregate.is_empty() && !self.order_by.is_empty())
+            || self
+                .order_by
+                .iter()
+                .any(|(expr, _, _)| Query::contains_aggregate(expr))
+            || !matches!(having, Expr::Const(RawVal::Int(1)))
             || final_projection
                 .iter()
                 .any(|col_info| !matches!(col_info.expr, Expr::ColName(_)));
 
         Ok(if require_final_pass {
             let mut final_order_by = Vec::new();
-            for (expr, desc) in &self.order_by {
+            for (expr, desc, nulls_first) in &self.order_by {
                 let (full_expr, aggregates) =
                     Query::extract_aggregators(expr, &mut aggregate_colnames, None)?;
                 if aggregates.is_empty() {
+                    // Sorting by a constant has no effect, and grouping by one is not supported
+                    let mut colnames = HashSet::new();
+                    full_expr.add_colnames(&mut colnames);
+                    if colnames.is_empty() {
+                        continue;
+                    }
+                    // Sort by the grouping column of a selected expression instead of adding a duplicate
+                    if let Some((_, column_name)) = selected_exprs.iter().find(|(expr, _)| *expr == full_expr) {
+                        final_order_by.push((Expr::ColName(column_name.clone()), *desc, *nulls_first));
+                        continue;
+                    }
                     let column_name = format!("_cs{}", select_colnames.len());
                     select_colnames.push(column_name.clone());
                     select.push(ColumnInfo {
                         expr: full_expr,
                         name: None,
                     });
-                    final_order_by.push((Expr::ColName(column_name), *desc));
+                    final_order_by.push((Expr::ColName(column_name), *desc, *nulls_first));
                 } else {
                     aggregate.extend(aggregates);
-                    final_order_by.push((full_expr, *desc));
+                    final_order_by.push((full_expr, *desc, *nulls_first));
                 }
             }
             (
@@ -510,7 +747,7 @@ impl Query {
                 },
                 Some(NormalFormQuery {
                     projection: final_projection,
-                    filter: Expr::Const(RawVal::Int(1)),
+                    filter: having,
                     aggregate: vec![],
                     order_by: final_order_by,
                     limit: self.limit.clone(),
@@ -530,45 +767,489 @@ impl Query {
         })
     }
 
+    /// Replaces aggregates in `expr` with references to generated `_ca` columns. Only an aggregate that makes up
+    /// the whole expression is named by `alias`, aggregates nested in a larger expression keep their generated name.
     pub fn extract_aggregators(
         expr: &Expr,
         column_names: &mut Vec<String>,
         alias: Option<String>,
     ) -> Result<(Expr, Vec<(Aggregator, ColumnInfo)>), QueryError> {
         Ok(match expr {
+            Expr::Aggregate(Aggregator::Avg, expr) => {
+                let sum_column_name = format!("_ca{}", column_names.len());
+                column_names.push(sum_column_name.clone());
+                let count_column_name = format!("_ca{}", column_names.len());
+                column_names.push(count_column_name.clone());
+                Query::ensure_no_aggregates(expr)?;
+                (
+                    Expr::func(
+                        Func2Type::Divide,
+                        Expr::ColName(sum_column_name.clone()),
+                        Expr::func1(Func1Type::NullIfZero, Expr::ColName(count_column_name.clone())),
+                    ),
+                    vec![
+                        (
+                            Aggregator::SumI64,
+                            ColumnInfo {
+                                expr: *expr.clone(),
+                                name: Some(sum_column_name),
+                            },
+                        ),
+                        (
+                            Aggregator::Count,
+                            ColumnInfo {
+                                expr: *expr.clone(),
+                                name: Some(count_column_name),
+                            },
+                        ),
+                    ],
+                )
+            }
+            Expr::Aggregate(aggregator @ (Aggregator::VarPop | Aggregator::StdDev), expr) => {
+                Query::ensure_no_aggregates(expr)?;
+                Query::extract_aggregators(&Query::expand_variance(*aggregator, expr), column_names, None)?
+            }
             Expr::Aggregate(aggregator, expr) => {
                 let column_name = format!("_ca{}", column_names.len());
                 column_names.push(column_name.clone());
                 Query::ensure_no_aggregates(expr)?;
                 (
-                    Expr::ColName(column_name),
+                    Expr::ColName(column_name.clone()),
                     vec![(
                         *aggregator,
                         ColumnInfo {
                             expr: *expr.clone(),
-                            name: alias,
+                            name: alias.or(Some(column_name)),
                         },
                     )],
                 )
             }
             Expr::Func1(t, expr) => {
-                let (expr, aggregates) = Query::extract_aggregators(expr, column_names, alias)?;
+                let (expr, aggregates) = Query::extract_aggregators(expr, column_names, None)?;
                 (Expr::Func1(*t, Box::new(expr)), aggregates)
             }
             Expr::Func2(t, expr1, expr2) => {
                 let (expr1, mut aggregates1) =
-                    Query::extract_aggregators(expr1, column_names, alias.clone())?;
-                let (expr2, aggregates2) = Query::extract_aggregators(expr2, column_names, alias)?;
+                    Query::extract_aggregators(expr1, column_names, None)?;
+                let (expr2, aggregates2) = Query::extract_aggregators(expr2, column_names, None)?;
                 aggregates1.extend(aggregates2);
                 (
                     Expr::Func2(*t, Box::new(expr1), Box::new(expr2)),
                     aggregates1,
                 )
             }
+            Expr::Case(branches, default) => {
+                let mut aggregates = vec![];
+                let mut rewritten_branches = Vec::with_capacity(branches.len());
+                for (cond, value) in branches {
+                    let (cond, cond_aggregates) =
+                        Query::extract_aggregators(cond, column_names, None)?;
+                    let (value, value_aggregates) =
+                        Query::extract_aggregators(value, column_names, None)?;
+                    aggregates.extend(cond_aggregates);
+                    aggregates.extend(value_aggregates);
+                    rewritten_branches.push((cond, value));
+                }
+                let (default, default_aggregates) =
+                    Query::extract_aggregators(default, column_names, None)?;
+                aggregates.extend(default_aggregates);
+                (Expr::Case(rewritten_branches, Box::new(default)), aggregates)
+            }
             Expr::Const(_) | Expr::ColName(_) => (expr.clone(), vec![]),
         })
     }
 
+    /// Expands `VarPop` or `StdDev` of `expr` into sums and counts using the sum-of-squares method,
+    /// `VAR(x) = SUM(x^2) / n - (SUM(x) / n)^2`, which can be computed in a single aggregation pass.
+    /// Values are converted to floats so that squares of large integers do not overflow. The subtraction loses
+    /// precision when the mean is large compared to the standard deviation, and results that are slightly negative
+    /// due to rounding are clamped to zero.
+    fn expand_variance(aggregator: Aggregator, expr: &Expr) -> Expr {
+        let float = Expr::func(Func2Type::Multiply, expr.clone(), Expr::Const(RawVal::Float(OrderedFloat(1.0))));
+        let count = Expr::func1(Func1Type::NullIfZero, Expr::Aggregate(Aggregator::Count, Box::new(expr.clone())));
+        let mean = Expr::func(Func2Type::Divide, Expr::sum(float.clone()), count.clone());
+        let mean_of_squares = Expr::func(
+            Func2Type::Divide,
+            Expr::sum(Expr::func(Func2Type::Multiply, float.clone(), float)),
+            count,
+        );
+        let variance = Expr::func(
+            Func2Type::Subtract,
+            mean_of_squares,
+            Expr::func(Func2Type::Multiply, mean.clone(), mean),
+        );
+        let variance = Expr::Case(
+            vec![(
+                Expr::func(Func2Type::LT, variance.clone(), Expr::Const(RawVal::Float(OrderedFloat(0.0)))),
+                Expr::Const(RawVal::Float(OrderedFloat(0.0))),
+            )],
+            Box::new(variance),
+        );
+        match aggregator {
+            Aggregator::StdDev => Expr::func1(Func1Type::Sqrt, variance),
+            _ => variance,
+        }
+    }
+
+    /// Rewrites a `HAVING` predicate to reference the output columns of the aggregation pass.
+    /// Aggregates are added to `aggregate`, referenced columns are added to `select`.
+    fn extract_having(
+        expr: &Expr,
+        aggregate_colnames: &mut Vec<String>,
+        aggregate: &mut Vec<(Aggregator, ColumnInfo)>,
+        select_colnames: &mut Vec<String>,
+        select: &mut Vec<ColumnInfo>,
+    ) -> Result<Expr, QueryError> {
+        Ok(match expr {
+            Expr::Const(_) => expr.clone(),
+            Expr::ColName(_) => {
+                let column_name = format!("_cs{}", select_colnames.len());
+                select_colnames.push(column_name.clone());
+                select.push(ColumnInfo {
+                    expr: expr.clone(),
+                    name: None,
+                });
+                Expr::ColName(column_name)
+            }
+            Expr::Func1(t, expr) => Expr::Func1(
+                *t,
+                Box::new(Query::extract_having(
+                    expr,
+                    aggregate_colnames,
+                    aggregate,
+                    select_colnames,
+                    select,
+                )?),
+            ),
+            Expr::Func2(t, expr1, expr2) => Expr::Func2(
+                *t,
+                Box::new(Query::extract_having(
+                    expr1,
+                    aggregate_colnames,
+                    aggregate,
+                    select_colnames,
+                    select,
+                )?),
+                Box::new(Query::extract_having(
+                    expr2,
+                    aggregate_colnames,
+                    aggregate,
+                    select_colnames,
+                    select,
+                )?),
+            ),
+            Expr::Case(branches, default) => {
+                let mut rewritten_branches = Vec::with_capacity(branches.len());
+                for (cond, value) in branches {
+                    rewritten_branches.push((
+                        Query::extract_having(
+                            cond,
+                            aggregate_colnames,
+                            aggregate,
+                            select_colnames,
+                            select,
+                        )?,
+                        Query::extract_having(
+                            value,
+                            aggregate_colnames,
+                            aggregate,
+                            select_colnames,
+                            select,
+                        )?,
+                    ));
+                }
+                Expr::Case(
+                    rewritten_branches,
+                    Box::new(Query::extract_having(
+                        default,
+                        aggregate_colnames,
+                        aggregate,
+                        select_colnames,
+                        select,
+                    )?),
+                )
+            }
+            Expr::Aggregate(_, _) => {
+                let (expr, aggregates) = Query::extract_aggregators(expr, aggregate_colnames, None)?;
+                aggregate.extend(aggregates);
+                expr
+            }
+        })
+    }
+
+    /// Rewrites a query containing `COUNT(DISTINCT expr)` or `PERCENTILE(expr, quantile)` into a pre-aggregation
+    /// that groups by the original grouping columns and all distinct expressions or histogram buckets, and a query
+    /// that aggregates over the (merged) output of the pre-aggregation. Other aggregates are split into partial
+    /// aggregates that are recombined by the second query.
+    pub fn split_distinct_aggregates(&self) -> Result<Option<(NormalFormQuery, Query)>, QueryError> {
+        let exprs = || {
+            self.select
+                .iter()
+                .map(|col_info| &col_info.expr)
+                .chain(self.order_by.iter().map(|(expr, _, _)| expr))
+                .chain(Some(&self.having))
+        };
+        let has_distinct = exprs().any(|expr| {
+            Query::contains_aggregator(expr, &|aggregator| aggregator == Aggregator::CountDistinct)
+        });
+        let has_percentile = exprs().any(|expr| {
+            Query::contains_aggregator(expr, &|aggregator| matches!(aggregator, Aggregator::ApproxPercentile(_)))
+        });
+        if has_distinct && has_percentile {
+            // Grouping by histogram buckets would count distinct values once per bucket
+            bail!(QueryError::NotImplemented, "PERCENTILE combined with COUNT(DISTINCT)")
+        }
+        if !has_distinct && !has_percentile {
+            return Ok(None);
+        }
+
+        let mut projection = Vec::new();
+        let mut aggregate = Vec::new();
+        let mut select = Vec::new();
+        for col_info in &self.select {
+            let expr = if Query::contains_aggregate(&col_info.expr) {
+                Query::split_aggregators(&col_info.expr, &mut projection, &mut aggregate)?
+            } else {
+                Query::push_pre_aggregation_column(&col_info.expr, &mut projection)
+            };
+            select.push(ColumnInfo {
+                expr,
+                name: col_info.name.clone(),
+            });
+        }
+        let mut order_by = Vec::new();
+        for (expr, desc, nulls_first) in &self.order_by {
+            let expr = if Query::contains_aggregate(expr) {
+                Query::split_aggregators(expr, &mut projection, &mut aggregate)?
+            } else {
+                Query::push_pre_aggregation_column(expr, &mut projection)
+            };
+            order_by.push((expr, *desc, *nulls_first));
+        }
+        let having = match self.having {
+            Expr::Const(_) => self.having.clone(),
+            _ if Query::contains_aggregate(&self.having) => {
+                Query::split_aggregators(&self.having, &mut projection, &mut aggregate)?
+            }
+            _ => Query::push_pre_aggregation_column(&self.having, &mut projection),
+        };
+        // The pre-aggregation has to group even if there are no other aggregates
+        if aggregate.is_empty() {
+            aggregate.push((
+                Aggregator::Count,
+                ColumnInfo {
+                    expr: Expr::Const(RawVal::Int(0)),
+                    name: None,
+                },
+            ));
+        }
+
+        Ok(Some((
+            NormalFormQuery {
+                projection,
+                filter: self.filter.clone(),
+                aggregate,
+                order_by: vec![],
+                limit: LimitClause {
+                    limit: u64::MAX,
+                    offset: 0,
+                },
+            },
+            Query {
+                select,
+                table: self.table.clone(),
+                filter: Expr::Const(RawVal::Int(1)),
+                having,
+                order_by,
+                limit: self.limit.clone(),
+                partition_filter: Expr::Const(RawVal::Int(1)),
+            },
+        )))
+    }
+
+    fn split_aggregators(
+        expr: &Expr,
+        projection: &mut Vec<ColumnInfo>,
+        aggregate: &mut Vec<(Aggregator, ColumnInfo)>,
+    ) -> Result<Expr, QueryError> {
+        let mut push_aggregate = |aggregator: Aggregator, expr: &Expr| {
+            let column_name = format!("_ca{}", aggregate.len());
+            aggregate.push((
+                aggregator,
+                ColumnInfo {
+                    expr: expr.clone(),
+                    name: None,
+                },
+            ));
+            Expr::ColName(column_name)
+        };
+        Ok(match expr {
+            Expr::Aggregate(aggregator, expr) => {
+                Query::ensure_no_aggregates(expr)?;
+                match aggregator {
+                    Aggregator::CountDistinct => {
+                        // Groups where the distinct value is null have a (non-null) count of zero
+                        Query::push_pre_aggregation_column(expr, projection);
+                        Expr::Aggregate(
+                            Aggregator::Count,
+                            Box::new(Expr::func1(
+                                Func1Type::NullIfZero,
+                                push_aggregate(Aggregator::Count, expr),
+                            )),
+                        )
+                    }
+                    Aggregator::Avg => Expr::func(
+                        Func2Type::Divide,
+                        Expr::Aggregate(
+                            Aggregator::SumI64,
+                            Box::new(push_aggregate(Aggregator::SumI64, expr)),
+                        ),
+                        Expr::func1(
+                            Func1Type::NullIfZero,
+                            Expr::Aggregate(
+                                Aggregator::SumI64,
+                                Box::new(push_aggregate(Aggregator::Count, expr)),
+                            ),
+                        ),
+                    ),
+                    Aggregator::Count => Expr::Aggregate(
+                        Aggregator::SumI64,
+                        Box::new(push_aggregate(Aggregator::Count, expr)),
+                    ),
+                    Aggregator::VarPop | Aggregator::StdDev => {
+                        return Query::split_aggregators(&Query::expand_variance(*aggregator, expr), projection, aggregate);
+                    }
+                    Aggregator::ApproxPercentile(_) => {
+                        // The pre-aggregation counts the values in each histogram bucket, which merge like any
+                        // other partial aggregate. Groups without values have a count of zero and a null percentile.
+                        let bucket = Query::push_pre_aggregation_column(&Query::histogram_bucket(expr), projection);
+                        let count = push_aggregate(Aggregator::Count, expr);
+                        let packed = Expr::func(
+                            Func2Type::Add,
+                            Expr::func(
+                                Func2Type::Multiply,
+                                bucket,
+                                Expr::Const(RawVal::Int(approx_percentile::MAX_COUNT)),
+                            ),
+                            count.clone(),
+                        );
+                        Expr::Case(
+                            vec![(
+                                Expr::func(
+                                    Func2Type::Equals,
+                                    Expr::Aggregate(Aggregator::SumI64, Box::new(count)),
+                                    Expr::Const(RawVal::Int(0)),
+                                ),
+                                Expr::Const(RawVal::Null),
+                            )],
+                            Box::new(Expr::Aggregate(*aggregator, Box::new(packed))),
+                        )
+                    }
+                    _ => Expr::Aggregate(*aggregator, Box::new(push_aggregate(*aggregator, expr))),
+                }
+            }
+            Expr::Func1(t, expr) => {
+                Expr::Func1(*t, Box::new(Query::split_aggregators(expr, projection, aggregate)?))
+            }
+            Expr::Func2(t, expr1, expr2) => Expr::Func2(
+                *t,
+                Box::new(Query::split_aggregators(expr1, projection, aggregate)?),
+                Box::new(Query::split_aggregators(expr2, projection, aggregate)?),
+            ),
+            Expr::Case(branches, default) => {
+                let mut rewritten_branches = Vec::with_capacity(branches.len());
+                for (cond, value) in branches {
+                    rewritten_branches.push((
+                        Query::split_aggregators(cond, projection, aggregate)?,
+                        Query::split_aggregators(value, projection, aggregate)?,
+                    ));
+                }
+                Expr::Case(
+                    rewritten_branches,
+                    Box::new(Query::split_aggregators(default, projection, aggregate)?),
+                )
+            }
+            Expr::Const(_) => expr.clone(),
+            Expr::ColName(_) => Query::push_pre_aggregation_column(expr, projection),
+        })
+    }
+
+    /// Index of the histogram bucket that contains the value of `expr`, see `approx_percentile::bucket_value`.
+    fn histogram_bucket(expr: &Expr) -> Expr {
+        let magnitude = Expr::func(
+            Func2Type::Add,
+            Expr::func1(
+                Func1Type::Floor,
+                Expr::func(
+                    Func2Type::Divide,
+                    Expr::func1(Func1Type::Ln, Expr::func1(Func1Type::Abs, expr.clone())),
+                    Expr::Const(RawVal::Float(OrderedFloat(approx_percentile::BUCKET_GROWTH.ln()))),
+                ),
+            ),
+            Expr::Const(RawVal::Int(approx_percentile::BUCKET_OFFSET)),
+        );
+        let zero = Expr::Const(RawVal::Int(0));
+        Expr::Case(
+            vec![
+                (Expr::func(Func2Type::GT, expr.clone(), zero.clone()), magnitude.clone()),
+                (Expr::func(Func2Type::LT, expr.clone(), zero.clone()), Expr::func1(Func1Type::Negate, magnitude)),
+            ],
+            Box::new(zero),
+        )
+    }
+
+    fn push_pre_aggregation_column(expr: &Expr, projection: &mut Vec<ColumnInfo>) -> Expr {
+        let column_name = format!("_cs{}", projection.len());
+        projection.push(ColumnInfo {
+            expr: expr.clone(),
+            name: None,
+        });
+        Expr::ColName(column_name)
+    }
+
+    pub(crate) fn contains_aggregate(expr: &Expr) -> bool {
+        match expr {
+            Expr::Aggregate(_, _) => true,
+            Expr::Func1(_, expr) => Query::contains_aggregate(expr),
+            Expr::Func2(_, expr1, expr2) => {
+                Query::contains_aggregate(expr1) || Query::contains_aggregate(expr2)
+            }
+            Expr::Case(branches, default) => {
+                branches.iter().any(|(cond, value)| {
+                    Query::contains_aggregate(cond) || Query::contains_aggregate(value)
+                }) || Query::contains_aggregate(default)
+            }
+            Expr::Const(_) | Expr::ColName(_) => false,
+        }
+    }
+
+    /// Whether running the query again on the same data returns the same result, e.g. false for `SAMPLE(0.1)`.
+    pub fn is_deterministic(&self) -> bool {
+        self.select.iter().all(|column| column.expr.is_deterministic())
+            && self.filter.is_deterministic()
+            && self.having.is_deterministic()
+            && self.order_by.iter().all(|(expr, _, _)| expr.is_deterministic())
+            && self.partition_filter.is_deterministic()
+    }
+
+    fn contains_aggregator(expr: &Expr, matches: &dyn Fn(Aggregator) -> bool) -> bool {
+        match expr {
+            Expr::Aggregate(aggregator, _) => matches(*aggregator),
+            Expr::Func1(_, expr) => Query::contains_aggregator(expr, matches),
+            Expr::Func2(_, expr1, expr2) => {
+                Query::contains_aggregator(expr1, matches)
+                    || Query::contains_aggregator(expr2, matches)
+            }
+            Expr::Case(branches, default) => {
+                branches.iter().any(|(cond, value)| {
+                    Query::contains_aggregator(cond, matches)
+                        || Query::contains_aggregator(value, matches)
+                }) || Query::contains_aggregator(default, matches)
+            }
+            Expr::Const(_) | Expr::ColName(_) => false,
+        }
+    }
+
     pub fn ensure_no_aggregates(expr: &Expr) -> Result<(), QueryError> {
         match expr {
             Expr::Aggregate(_, _) => {
@@ -581,6 +1262,13 @@ impl Query {
                 Query::ensure_no_aggregates(expr1)?;
                 Query::ensure_no_aggregates(expr2)?;
             }
+            Expr::Case(branches, default) => {
+                for (cond, value) in branches {
+                    Query::ensure_no_aggregates(cond)?;
+                    Query::ensure_no_aggregates(value)?;
+                }
+                Query::ensure_no_aggregates(default)?;
+            }
             Expr::Const(_) | Expr::ColName(_) => (),
         };
         Ok(())
@@ -594,6 +1282,93 @@ impl Query {
         }
     }
 
+    /// Converts time functions applied to timestamp columns and returns the names of the selected columns that are timestamps.
+    pub fn resolve_timestamps(&mut self, timestamp_columns: &HashSet<String>) -> HashSet<String> {
+        if self.is_select_star() {
+            return timestamp_columns.clone();
+        }
+        let mut timestamp_outputs = HashSet::new();
+        for col_info in &mut self.select {
+            if col_info.expr.is_timestamp(timestamp_columns) {
+                timestamp_outputs.insert(col_info.display_name());
+            }
+            col_info.expr = col_info.expr.clone().convert_timestamps(timestamp_columns);
+        }
+        for (expr, _, _) in &mut self.order_by {
+            *expr = expr.clone().convert_timestamps(timestamp_columns);
+        }
+        self.filter = self.filter.clone().convert_timestamps(timestamp_columns);
+        self.having = self.having.clone().convert_timestamps(timestamp_columns);
+        timestamp_outputs
+    }
+
+    /// Allows the filter, `HAVING` and `ORDER BY` to reference aliased select expressions,
+    /// e.g. `SELECT a * 2 AS b ... WHERE b > 10` or `SELECT COUNT(0) n ... ORDER BY n`.
+    /// Columns of the table take precedence over aliases with the same name.
+    pub fn inline_aliases(&mut self, columns: &HashSet<String>) -> Result<(), QueryError> {
+        let mut aliases = HashMap::new();
+        for col_info in &self.select {
+            if let Some(ref alias) = col_info.name {
+                if !columns.contains(alias) {
+                    aliases.entry(alias.clone()).or_insert_with(|| col_info.expr.clone());
+                }
+            }
+        }
+        if aliases.is_empty() {
+            return Ok(());
+        }
+        let mut filter_colnames = HashSet::new();
+        self.filter.add_colnames(&mut filter_colnames);
+        for (alias, expr) in &aliases {
+            if filter_colnames.contains(alias) && Query::ensure_no_aggregates(expr).is_err() {
+                bail!(
+                    QueryError::NotImplemented,
+                    "Alias `{}` refers to an aggregate and cannot be used in WHERE, use HAVING instead",
+                    alias
+                )
+            }
+        }
+        self.filter = self.filter.clone().inline_aliases(&aliases);
+        self.having = self.having.clone().inline_aliases(&aliases);
+        for (expr, _, _) in &mut self.order_by {
+            *expr = expr.clone().inline_aliases(&aliases);
+        }
+        Ok(())
+    }
+
+    /// Whether the partition with the given id is scanned according to `partition_filter`.
+    pub fn scans_partition(&self, id: PartitionID) -> Result<bool, QueryError> {
+        Query::eval_partition_filter(&self.partition_filter, id as i64)
+    }
+
+    fn eval_partition_filter(expr: &Expr, id: i64) -> Result<bool, QueryError> {
+        let value = |expr: &Expr| match expr {
+            Expr::ColName(name) if name == "partition_id" => Ok(id),
+            Expr::Const(RawVal::Int(value)) => Ok(*value),
+            _ => Err(QueryError::NotImplemented(format!(
+                "{:?} in partition filter, expected `partition_id` or an integer",
+                expr
+            ))),
+        };
+        Ok(match expr {
+            Expr::Const(RawVal::Int(value)) => *value != 0,
+            Expr::Func1(Func1Type::Not, expr) => !Query::eval_partition_filter(expr, id)?,
+            Expr::Func2(Func2Type::And, lhs, rhs) => {
+                Query::eval_partition_filter(lhs, id)? && Query::eval_partition_filter(rhs, id)?
+            }
+            Expr::Func2(Func2Type::Or, lhs, rhs) => {
+                Query::eval_partition_filter(lhs, id)? || Query::eval_partition_filter(rhs, id)?
+            }
+            Expr::Func2(Func2Type::Equals, lhs, rhs) => value(lhs)? == value(rhs)?,
+            Expr::Func2(Func2Type::NotEquals, lhs, rhs) => value(lhs)? != value(rhs)?,
+            Expr::Func2(Func2Type::LT, lhs, rhs) => value(lhs)? < value(rhs)?,
+            Expr::Func2(Func2Type::LTE, lhs, rhs) => value(lhs)? <= value(rhs)?,
+            Expr::Func2(Func2Type::GT, lhs, rhs) => value(lhs)? > value(rhs)?,
+            Expr::Func2(Func2Type::GTE, lhs, rhs) => value(lhs)? >= value(rhs)?,
+            _ => bail!(QueryError::NotImplemented, "Partition filter {:?}", expr),
+        })
+    }
+
     pub fn find_referenced_cols(&self) -> HashSet<String> {
         let mut colnames = HashSet::new();
         for col_info in &self.select {
@@ -603,6 +1378,182 @@ impl Query {
             expr.0.add_colnames(&mut colnames);
         }
         self.filter.add_colnames(&mut colnames);
+        self.having.add_colnames(&mut colnames);
         colnames
     }
 }
+
+impl UnionAll {
+    /// Combines the outputs of all queries into the result of the union.
+    pub fn merge_outputs(&self, outputs: Vec<QueryOutput>) -> Result<QueryOutput, QueryError> {
+        let mut outputs = outputs.into_iter();
+        let mut result = match outputs.next() {
+            Some(output) => output,
+            None => return Err(fatal!("UNION ALL without queries")),
+        };
+        for output in outputs {
+            if output.colnames.len() != result.colnames.len() {
+                bail!(
+                    QueryError::TypeError,
+                    "Each query in UNION ALL must select the same number of columns, found {} and {}",
+                    result.colnames.len(),
+                    output.colnames.len()
+                );
+            }
+            for (coltype, other) in result.coltypes.iter_mut().zip(output.coltypes) {
+                if *coltype == "null" {
+                    *coltype = other;
+                } else if *coltype != other && other != "null" {
+                    *coltype = "mixed".to_string();
+                }
+            }
+            result.rows.extend(output.rows);
+            for (plan, count) in output.query_plans {
+                *result.query_plans.entry(plan).or_insert(0) += count;
+            }
+            // Queries are executed concurrently
+            result.stats.runtime_ns = cmp::max(result.stats.runtime_ns, output.stats.runtime_ns);
+            result.stats.rows_scanned += output.stats.rows_scanned;
+        }
+
+        sort_and_limit(&mut result.rows, &self.order_by, &self.limit);
+        Ok(result)
+    }
+}
+
+impl Join {
+    /// Combines the outputs of the queries for the left and right table into the result of the join.
+    pub fn join_outputs(&self, left: QueryOutput, right: QueryOutput) -> Result<QueryOutput, QueryError> {
+        let outputs = [left, right];
+        // Build a hash table from the smaller output and probe it with the rows of the larger one
+        let build = if outputs[0].rows.len() <= outputs[1].rows.len() { 0 } else { 1 };
+        let probe = 1 - build;
+        let mut hash_table = HashMap::<i64, Vec<usize>>::default();
+        for (i, row) in outputs[build].rows.iter().enumerate() {
+            if let Some(key) = Join::key(&row[self.keys[build]])? {
+                hash_table.entry(key).or_default().push(i);
+            }
+        }
+        let mut rows = Vec::new();
+        for probe_row in &outputs[probe].rows {
+            // Null keys are never equal to any other key
+            let key = match Join::key(&probe_row[self.keys[probe]])? {
+                Some(key) => key,
+                None => continue,
+            };
+            for &i in hash_table.get(&key).into_iter().flatten() {
+                let build_row = &outputs[build].rows[i];
+                rows.push(
+                    self.projection
+                        .iter()
+                        .map(|&(side, index)| if side == build { build_row[index].clone() } else { probe_row[index].clone() })
+                        .collect(),
+                );
+            }
+        }
+        sort_and_limit(&mut rows, &self.order_by, &self.limit);
+
+        let [left, right] = outputs;
+        let coltypes = self
+            .projection
+            .iter()
+            .map(|&(side, index)| if side == 0 { left.coltypes[index].clone() } else { right.coltypes[index].clone() })
+            .collect();
+        let mut query_plans = left.query_plans;
+        for (plan, count) in right.query_plans {
+            *query_plans.entry(plan).or_insert(0) += count;
+        }
+        Ok(QueryOutput {
+            colnames: self.colnames.clone(),
+            coltypes,
+            rows,
+            query_plans,
+            stats: QueryStats {
+                // Queries are executed concurrently
+                runtime_ns: cmp::max(left.stats.runtime_ns, right.stats.runtime_ns),
+                rows_scanned: left.stats.rows_scanned + right.stats.rows_scanned,
+            },
+        })
+    }
+
+    fn key(val: &RawVal) -> Result<Option<i64>, QueryError> {
+        match *val {
+            RawVal::Int(key) => Ok(Some(key)),
+            RawVal::Null => Ok(None),
+            _ => bail!(QueryError::TypeError, "JOIN keys must be integers, found {}", val),
+        }
+    }
+}
+
+impl Subquery {
+    /// Replaces the placeholder in `filter` with a test for membership in `values`, the output of the subquery.
+    pub fn substitute(&self, filter: Expr, values: Vec<RawVal>) -> Result<Expr, QueryError> {
+        let contains_null = values.contains(&RawVal::Null);
+        let mut values = values.into_iter().filter(|value| *value != RawVal::Null).collect::<Vec<_>>();
+        values.sort();
+        values.dedup();
+        if let Some(first) = values.first() {
+            if let Some(other) = values.iter().find(|value| mem::discriminant(*value) != mem::discriminant(first)) {
+                bail!(QueryError::TypeError, "Mixed types in result of IN subquery: {} and {}", first, other);
+            }
+        }
+        // Same semantics as an IN list, `x IN (a, NULL)` only matches `a` and `x NOT IN (a, NULL)` matches nothing
+        let membership = match Subquery::any_equal(&self.expr, &values) {
+            _ if matches!(self.expr, Expr::Const(RawVal::Null)) => Expr::Const(RawVal::Null),
+            _ if self.negated && contains_null => Expr::Const(RawVal::Null),
+            Some(any_equal) if self.negated => Expr::func1(Func1Type::Not, any_equal),
+            Some(any_equal) => any_equal,
+            None if contains_null => Expr::Const(RawVal::Null),
+            None => Expr::Const(RawVal::Bool(self.negated)),
+        };
+        Ok(filter.inline_aliases(&[(self.placeholder.clone(), membership)].into_iter().collect()))
+    }
+
+    /// Disjunction of `expr = value` for all `values`, balanced to keep the expression shallow for large results.
+    fn any_equal(expr: &Expr, values: &[RawVal]) -> Option<Expr> {
+        match values {
+            [] => None,
+            [value] => Some(Expr::func(Func2Type::Equals, expr.clone(), Expr::Const(value.clone()))),
+            _ => {
+                let (lhs, rhs) = values.split_at(values.len() / 2);
+                Some(Expr::func(
+                    Func2Type::Or,
+                    Subquery::any_equal(expr, lhs)?,
+                    Subquery::any_equal(expr, rhs)?,
+                ))
+            }
+        }
+    }
+}
+
+/// Sorts the rows of a combined query output and applies its LIMIT and OFFSET.
+fn sort_and_limit(rows: &mut Vec<Vec<RawVal>>, order_by: &[(usize, bool, bool)], limit: &LimitClause) {
+    if !order_by.is_empty() {
+        rows.sort_by(|lhs, rhs| {
+            for &(index, desc, nulls_first) in order_by {
+                let ordering = cmp_union_vals(&lhs[index], &rhs[index], desc, nulls_first);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            Ordering::Equal
+        });
+    }
+    let offset = cmp::min(limit.offset as usize, rows.len());
+    rows.drain(..offset);
+    rows.truncate(limit.limit as usize);
+}
+
+/// Orders values like the query engine: nulls are placed according to `nulls_first` regardless of `desc`
+/// and integers and floats are compared by value.
+fn cmp_union_vals(lhs: &RawVal, rhs: &RawVal, desc: bool, nulls_first: bool) -> Ordering {
+    let ordering = match (lhs, rhs) {
+        (RawVal::Null, RawVal::Null) => return Ordering::Equal,
+        (RawVal::Null, _) => return if nulls_first { Ordering::Less } else { Ordering::Greater },
+        (_, RawVal::Null) => return if nulls_first { Ordering::Greater } else { Ordering::Less },
+        (RawVal::Int(lhs), RawVal::Float(rhs)) => OrderedFloat(*lhs as f64).cmp(rhs),
+        (RawVal::Float(lhs), RawVal::Int(rhs)) => lhs.cmp(&OrderedFloat(*rhs as f64)),
+        _ => lhs.cmp(rhs),
+    };
+    if desc { ordering.reverse() } else { ordering }
+}
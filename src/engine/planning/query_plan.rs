@@ -2,6 +2,7 @@
 #![allow(clippy::nonstandard_macro_braces, clippy::unused_unit)]
 use chrono::{Datelike, NaiveDateTime};
 use locustdb_derive::ASTBuilder;
+use ordered_float::OrderedFloat;
 use regex;
 use regex::Regex;
 
@@ -12,6 +13,7 @@ use crate::mem_store::value::Val;
 use crate::mem_store::*;
 use crate::syntax::expression::*;
 use crate::QueryError;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::i64;
 use std::result::Result;
@@ -53,6 +55,13 @@ pub enum QueryPlan {
         #[output]
         present: BufferRef<u8>,
     },
+    /// Unions the null maps of two vectors, setting the result to null only if both inputs were null.
+    UnionNullMaps {
+        lhs: TypedBufferRef,
+        rhs: TypedBufferRef,
+        #[output]
+        present: BufferRef<u8>,
+    },
     GetNullMap {
         nullable: TypedBufferRef,
         #[output]
@@ -66,8 +75,8 @@ pub enum QueryPlan {
         #[output(t = "base=data;null=_always")]
         nullable: TypedBufferRef,
     },
-    /// Converts NullableI64 or NullableStr into a representation where nulls are encoded as part
-    /// of the data (i64 with i64::MIN representing null for NullableI64, and Option<&str> for NullableStr).
+    /// Converts NullableI64, NullableStr or NullableF64 into a representation where nulls are encoded as part
+    /// of the data (i64 with i64::MIN representing null for NullableI64, Option<&str> for NullableStr and Val for NullableF64).
     FuseNulls {
         nullable: TypedBufferRef,
         #[output(t = "base=nullable;null=_fused")]
@@ -297,7 +306,7 @@ pub enum QueryPlan {
     Add {
         lhs: TypedBufferRef,
         rhs: TypedBufferRef,
-        #[output(t = "base=i64;null=lhs,rhs")]
+        #[output(t = "base=provided;null=lhs,rhs")]
         sum: TypedBufferRef,
     },
     CheckedAdd {
@@ -316,7 +325,7 @@ pub enum QueryPlan {
     Subtract {
         lhs: TypedBufferRef,
         rhs: TypedBufferRef,
-        #[output(t = "base=i64;null=lhs,rhs")]
+        #[output(t = "base=provided;null=lhs,rhs")]
         difference: TypedBufferRef,
     },
     CheckedSubtract {
@@ -354,7 +363,7 @@ pub enum QueryPlan {
     Divide {
         lhs: TypedBufferRef,
         rhs: TypedBufferRef,
-        #[output(t = "base=i64;null=lhs,rhs")]
+        #[output(t = "base=provided;null=lhs,rhs")]
         division: TypedBufferRef,
     },
     CheckedDivide {
@@ -373,7 +382,7 @@ pub enum QueryPlan {
     Modulo {
         lhs: TypedBufferRef,
         rhs: TypedBufferRef,
-        #[output(t = "base=i64;null=lhs,rhs")]
+        #[output(t = "base=provided;null=lhs,rhs")]
         modulo: TypedBufferRef,
     },
     CheckedModulo {
@@ -389,6 +398,25 @@ pub enum QueryPlan {
         #[output]
         modulo: BufferRef<Nullable<i64>>,
     },
+    Power {
+        lhs: TypedBufferRef,
+        rhs: TypedBufferRef,
+        #[output(t = "base=f64;null=lhs,rhs")]
+        power: TypedBufferRef,
+    },
+    CheckedPower {
+        lhs: TypedBufferRef,
+        rhs: TypedBufferRef,
+        #[output(t = "base=i64;null=lhs,rhs")]
+        power: TypedBufferRef,
+    },
+    NullableCheckedPower {
+        lhs: TypedBufferRef,
+        rhs: TypedBufferRef,
+        present: BufferRef<u8>,
+        #[output]
+        power: BufferRef<Nullable<i64>>,
+    },
     And {
         lhs: TypedBufferRef,
         rhs: TypedBufferRef,
@@ -402,26 +430,147 @@ pub enum QueryPlan {
         or: TypedBufferRef,
     },
     Not {
-        input: BufferRef<u8>,
-        #[output]
-        not: BufferRef<u8>,
+        input: TypedBufferRef,
+        #[output(t = "base=u8;null=input")]
+        not: TypedBufferRef,
     },
     ToYear {
         timestamp: TypedBufferRef,
         #[output(t = "base=i64;null=timestamp")]
         year: TypedBufferRef,
     },
+    /// Truncates unix timestamps to the start of the `unit` (e.g. "hour") they fall into.
+    DateTrunc {
+        timestamp: TypedBufferRef,
+        unit: String,
+        #[output(t = "base=i64;null=timestamp")]
+        truncated: TypedBufferRef,
+    },
     Regex {
         plan: BufferRef<&'static str>,
         regex: String,
         #[output]
         matches: BufferRef<u8>,
     },
+    /// Concatenates the strings in `lhs` and `rhs` row-wise.
+    Concat {
+        lhs: TypedBufferRef,
+        rhs: TypedBufferRef,
+        #[internal]
+        stringstore: BufferRef<u8>,
+        #[output(t = "base=str;null=lhs,rhs")]
+        concat: TypedBufferRef,
+    },
+    /// Evaluates the SQL `LIKE` pattern `pattern` against each string.
+    Like {
+        plan: TypedBufferRef,
+        pattern: String,
+        #[output(t = "base=u8;null=plan")]
+        matches: TypedBufferRef,
+    },
+    /// Rounds each float in `float` down to the nearest integer.
+    Floor {
+        float: TypedBufferRef,
+        #[output(t = "base=i64;null=float")]
+        floor: TypedBufferRef,
+    },
+    /// Rounds each float in `float` up to the nearest integer.
+    Ceil {
+        float: TypedBufferRef,
+        #[output(t = "base=i64;null=float")]
+        ceil: TypedBufferRef,
+    },
+    /// Rounds each float in `float` to the nearest integer, rounding half-way cases away from zero.
+    Round {
+        float: TypedBufferRef,
+        #[output(t = "base=i64;null=float")]
+        rounded: TypedBufferRef,
+    },
+    /// Rounds each float in `float` to `digits` decimal places.
+    RoundDigits {
+        float: TypedBufferRef,
+        digits: i64,
+        #[output(t = "base=f64;null=float")]
+        rounded: TypedBufferRef,
+    },
+    /// Absolute value of each integer or float in `plan`, saturating at `i64::MAX` for integers.
+    Abs {
+        plan: TypedBufferRef,
+        #[output(t = "base=plan;null=plan")]
+        abs: TypedBufferRef,
+    },
+    /// Maps each integer or float in `plan` to -1, 0 or 1 depending on its sign.
+    Sign {
+        plan: TypedBufferRef,
+        #[output(t = "base=i64;null=plan")]
+        sign: TypedBufferRef,
+    },
+    /// Square root of each integer or float in `plan`, null for negative values.
+    Sqrt {
+        plan: TypedBufferRef,
+        #[output(t = "base=f64;null=_always")]
+        sqrt: TypedBufferRef,
+    },
+    /// Natural logarithm of each integer or float in `plan`, null for negative values.
+    Ln {
+        plan: TypedBufferRef,
+        #[output(t = "base=f64;null=_always")]
+        ln: TypedBufferRef,
+    },
+    /// Base 10 logarithm of each integer or float in `plan`, null for negative values.
+    Log10 {
+        plan: TypedBufferRef,
+        #[output(t = "base=f64;null=_always")]
+        log10: TypedBufferRef,
+    },
+    /// Maps each zero in the float column `plan` to null, e.g. to make division by zero yield null.
+    FloatNullIfZero {
+        plan: TypedBufferRef,
+        #[output(t = "base=f64;null=_always")]
+        nullable: TypedBufferRef,
+    },
+    /// Exponential function of each integer or float in `plan`.
+    Exp {
+        plan: TypedBufferRef,
+        #[output(t = "base=f64;null=plan")]
+        exp: TypedBufferRef,
+    },
+    /// Selects the value of `lhs` where `cond` is true and the value of `rhs` otherwise.
+    Blend {
+        cond: TypedBufferRef,
+        lhs: TypedBufferRef,
+        rhs: TypedBufferRef,
+        #[output(t = "base=provided")]
+        blended: TypedBufferRef,
+    },
+    /// Selects the value of `lhs` where it is not null and the value of `rhs` otherwise.
+    Coalesce {
+        lhs: TypedBufferRef,
+        rhs: TypedBufferRef,
+        #[output(t = "base=provided;null=rhs")]
+        coalesced: TypedBufferRef,
+    },
     Length {
         string: BufferRef<&'static str>,
         #[output]
         length: BufferRef<i64>,
     },
+    /// Converts each string to lowercase.
+    Lower {
+        string: TypedBufferRef,
+        #[internal]
+        stringstore: BufferRef<u8>,
+        #[output(t = "base=str;null=string")]
+        lower: TypedBufferRef,
+    },
+    /// Converts each string to uppercase.
+    Upper {
+        string: TypedBufferRef,
+        #[internal]
+        stringstore: BufferRef<u8>,
+        #[output(t = "base=str;null=string")]
+        upper: TypedBufferRef,
+    },
     /// Outputs a vector of indices from `0..plan.len()`
     Indices {
         plan: TypedBufferRef,
@@ -429,19 +578,23 @@ pub enum QueryPlan {
         indices: BufferRef<usize>,
     },
     /// Outputs a permutation of `indices` under which `ranking` is sorted.
+    /// Null values of a nullable `ranking` are placed first iff `nulls_first` is set.
     SortBy {
         ranking: TypedBufferRef,
         indices: BufferRef<usize>,
         desc: bool,
+        nulls_first: bool,
         stable: bool,
         #[output]
         permutation: BufferRef<usize>,
     },
     /// Outputs the `n` largest/smallest elements of `ranking` and their corresponding indices.
+    /// If `with_ties` is set, also outputs all further elements equal to the `n`th element.
     TopN {
         ranking: TypedBufferRef,
         n: usize,
         desc: bool,
+        with_ties: bool,
         #[internal(t = "base=ranking")]
         tmp_keys: TypedBufferRef,
         #[output]
@@ -478,12 +631,23 @@ pub enum QueryPlan {
         #[output(t = "base=provided")]
         nulls: TypedBufferRef,
     },
+    /// Outputs a vector of nulls with the same length as `plan`.
+    NullVecLike {
+        plan: TypedBufferRef,
+        #[output(t = "base=provided")]
+        nulls: TypedBufferRef,
+    },
     ScalarI64 {
         value: i64,
         hide_value: bool,
         #[output]
         scalar_i64: BufferRef<Scalar<i64>>,
     },
+    ScalarF64 {
+        value: OrderedFloat<f64>,
+        #[output]
+        scalar_f64: BufferRef<Scalar<OrderedFloat<f64>>>,
+    },
     ScalarStr {
         value: String,
         #[internal]
@@ -498,12 +662,20 @@ pub enum QueryPlan {
         #[output(t = "base=provided")]
         expanded: TypedBufferRef,
     },
+    /// Outputs `len` booleans which are true with probability `fraction`, used to filter a random sample of rows.
+    Sample {
+        fraction: OrderedFloat<f64>,
+        len: usize,
+        #[output]
+        sample: BufferRef<u8>,
+    },
     /// Merges `lhs` and `rhs` and outputs a merge plan .
     Merge {
         lhs: TypedBufferRef,
         rhs: TypedBufferRef,
         limit: usize,
         desc: bool,
+        nulls_first: bool,
         #[output]
         merge_ops: BufferRef<u8>,
         #[output(t = "base=lhs;null=lhs,rhs")]
@@ -516,6 +688,7 @@ pub enum QueryPlan {
         rhs: TypedBufferRef,
         limit: usize,
         desc: bool,
+        nulls_first: bool,
         #[output]
         take_left: BufferRef<u8>,
         #[output(t = "base=lhs")]
@@ -548,6 +721,7 @@ pub enum QueryPlan {
         rhs: TypedBufferRef,
         limit: usize,
         desc: bool,
+        nulls_first: bool,
         #[output]
         partitioning: BufferRef<Premerge>,
     },
@@ -558,6 +732,7 @@ pub enum QueryPlan {
         lhs: TypedBufferRef,
         rhs: TypedBufferRef,
         desc: bool,
+        nulls_first: bool,
         #[output]
         subpartitioning: BufferRef<Premerge>,
     },
@@ -632,8 +807,27 @@ pub fn prepare_aggregation(
     planner: &mut QueryPlanner,
 ) -> Result<(TypedBufferRef, Type), QueryError> {
     Ok(match aggregator {
+        Aggregator::Count if matches!(plan.tag, EncodingType::NullableStr | EncodingType::OptStr) => {
+            // Only null-ness of values is relevant for count, so count nullable strings by summing their null map
+            let plan = if plan.tag == EncodingType::OptStr {
+                planner.unfuse_nulls(plan)
+            } else {
+                plan
+            };
+            let present = planner.is_not_null(plan.nullable_any()?);
+            (
+                planner.aggregate(
+                    present.into(),
+                    grouping_key,
+                    max_index,
+                    Aggregator::SumI64,
+                    EncodingType::I64,
+                ),
+                Type::unencoded(BasicType::Integer),
+            )
+        }
         Aggregator::Count => {
-            let plan = if plan.tag == EncodingType::ScalarI64 {
+            let plan = if matches!(plan.tag, EncodingType::ScalarI64 | EncodingType::Str | EncodingType::F64) {
                 grouping_key
             } else {
                 plan
@@ -685,16 +879,38 @@ pub fn prepare_aggregation(
         }
         Aggregator::MaxI64 | Aggregator::MinI64 if matches!(plan_type.decoded, BasicType::Integer | BasicType::NullableInteger) => {
             // PERF: don't always have to decode before taking max/min, and after is more efficient (e.g. dict encoded strings)
-            plan = plan_type.codec.unwrap().decode(plan, planner);
+            if let Some(codec) = plan_type.codec {
+                plan = codec.decode(plan, planner);
+            }
             (
                 planner.aggregate(plan, grouping_key, max_index, aggregator, EncodingType::I64),
                 Type::unencoded(BasicType::Integer),
             )
         }
+        Aggregator::MaxI64 | Aggregator::MinI64 if plan_type.decoded == BasicType::String
+            && plan_type.is_order_preserving()
+            && matches!(plan.tag, EncodingType::U8 | EncodingType::U16 | EncodingType::U32 | EncodingType::I64) => {
+            // Dictionary indices are ordered like the strings they encode, so only the resulting max/min has to be decoded
+            (
+                planner.aggregate(plan, grouping_key, max_index, aggregator, EncodingType::I64),
+                plan_type,
+            )
+        }
+        Aggregator::MaxI64 | Aggregator::MinI64 if matches!(plan_type.decoded, BasicType::String | BasicType::NullableString) => {
+            if let Some(codec) = plan_type.codec {
+                plan = codec.decode(plan, planner);
+            }
+            (
+                planner.aggregate(plan, grouping_key, max_index, aggregator.string(), EncodingType::OptStr),
+                Type::unencoded(BasicType::NullableString),
+            )
+        }
         Aggregator::MaxI64 | Aggregator::MinI64 => {
             // This fell through from the previous case, so we know that this is a float summation.
             // PERF: don't always have to decode before taking max/min, and after is more efficient (e.g. dict encoded strings)
-            plan = plan_type.codec.unwrap().decode(plan, planner);
+            if let Some(codec) = plan_type.codec {
+                plan = codec.decode(plan, planner);
+            }
             let aggregator = match aggregator {
                 Aggregator::MaxI64 => Aggregator::MaxF64,
                 Aggregator::MinI64 => Aggregator::MinF64,
@@ -705,8 +921,23 @@ pub fn prepare_aggregation(
                 Type::unencoded(BasicType::Float),
             )
         }
+        Aggregator::ApproxPercentile(_) => {
+            if let Some(codec) = plan_type.codec {
+                plan = codec.decode(plan, planner);
+            }
+            if plan.tag != EncodingType::I64 {
+                plan = planner.cast(plan, EncodingType::I64);
+            }
+            (
+                planner.aggregate(plan, grouping_key, max_index, aggregator, EncodingType::F64),
+                Type::unencoded(BasicType::Float),
+            )
+        }
         Aggregator::SumF64 => panic!("All sums are represented as SumI64 by the parser since it does not have access to type information"),
-        Aggregator::MaxF64 | Aggregator::MinF64 => panic!("All max/min are represented as MaxI64/MaxF64 by the parser since it does not have access to type information"),
+        Aggregator::MaxF64 | Aggregator::MinF64 | Aggregator::MaxStr | Aggregator::MinStr => panic!("All max/min are represented as MaxI64/MaxF64 by the parser since it does not have access to type information"),
+        Aggregator::Avg => panic!("Avg is expanded into SumI64 and Count during query normalization"),
+        Aggregator::VarPop | Aggregator::StdDev => panic!("VarPop and StdDev are expanded into SumI64 and Count during query normalization"),
+        Aggregator::CountDistinct => panic!("CountDistinct is rewritten into a grouping column before query normalization"),
     })
 }
 
@@ -763,6 +994,38 @@ impl Function2 {
             encoding_invariance: true,
         }
     }
+
+    pub fn string_op(factory: Factory) -> Function2 {
+        Function2 {
+            factory,
+            type_lhs: BasicType::String,
+            type_rhs: BasicType::String,
+            type_out: Type::unencoded(BasicType::String).mutable(),
+            encoding_invariance: false,
+        }
+    }
+
+    /// Ordering comparison of strings, evaluated on the decoded strings in byte order.
+    /// Unlike equality this can't be evaluated on dictionary indices, which only exist for strings in the dictionary.
+    pub fn string_ordering_op(factory: Factory) -> Function2 {
+        Function2 {
+            factory,
+            type_lhs: BasicType::String,
+            type_rhs: BasicType::String,
+            type_out: Type::unencoded(BasicType::Boolean).mutable(),
+            encoding_invariance: false,
+        }
+    }
+
+    pub fn float_comparison_op(factory: Factory, type_lhs: BasicType, type_rhs: BasicType) -> Function2 {
+        Function2 {
+            factory,
+            type_lhs,
+            type_rhs,
+            type_out: Type::unencoded(BasicType::Boolean).mutable(),
+            encoding_invariance: false,
+        }
+    }
 }
 
 lazy_static! {
@@ -773,15 +1036,37 @@ fn function2_registry() -> HashMap<Func2Type, Vec<Function2>> {
     vec![
         (
             Func2Type::Add,
-            vec![Function2::integer_op(Box::new(|qp, lhs, rhs| {
-                qp.checked_add(lhs, rhs)
-            }))],
+            vec![
+                Function2::integer_op(Box::new(|qp, lhs, rhs| {
+                    qp.checked_add(lhs, rhs)
+                })),
+                Function2::float_op(Box::new(|qp, lhs, rhs| {
+                    qp.add(lhs, rhs, EncodingType::F64)
+                }), BasicType::Integer, BasicType::Float),
+                Function2::float_op(Box::new(|qp, lhs, rhs| {
+                    qp.add(lhs, rhs, EncodingType::F64)
+                }), BasicType::Float, BasicType::Integer),
+                Function2::float_op(Box::new(|qp, lhs, rhs| {
+                    qp.add(lhs, rhs, EncodingType::F64)
+                }), BasicType::Float, BasicType::Float),
+            ],
         ),
         (
             Func2Type::Subtract,
-            vec![Function2::integer_op(Box::new(|qp, lhs, rhs| {
-                qp.checked_subtract(lhs, rhs)
-            }))],
+            vec![
+                Function2::integer_op(Box::new(|qp, lhs, rhs| {
+                    qp.checked_subtract(lhs, rhs)
+                })),
+                Function2::float_op(Box::new(|qp, lhs, rhs| {
+                    qp.subtract(lhs, rhs, EncodingType::F64)
+                }), BasicType::Integer, BasicType::Float),
+                Function2::float_op(Box::new(|qp, lhs, rhs| {
+                    qp.subtract(lhs, rhs, EncodingType::F64)
+                }), BasicType::Float, BasicType::Integer),
+                Function2::float_op(Box::new(|qp, lhs, rhs| {
+                    qp.subtract(lhs, rhs, EncodingType::F64)
+                }), BasicType::Float, BasicType::Float),
+            ],
         ),
         (
             Func2Type::Multiply,
@@ -802,14 +1087,59 @@ fn function2_registry() -> HashMap<Func2Type, Vec<Function2>> {
         ),
         (
             Func2Type::Divide,
-            vec![Function2::integer_op(Box::new(|qp, lhs, rhs| {
-                qp.checked_divide(lhs, rhs)
-            }))],
+            vec![
+                Function2::integer_op(Box::new(|qp, lhs, rhs| {
+                    qp.checked_divide(lhs, rhs)
+                })),
+                Function2::float_op(Box::new(|qp, lhs, rhs| {
+                    qp.divide(lhs, rhs, EncodingType::F64)
+                }), BasicType::Integer, BasicType::Float),
+                Function2::float_op(Box::new(|qp, lhs, rhs| {
+                    qp.divide(lhs, rhs, EncodingType::F64)
+                }), BasicType::Float, BasicType::Integer),
+                Function2::float_op(Box::new(|qp, lhs, rhs| {
+                    qp.divide(lhs, rhs, EncodingType::F64)
+                }), BasicType::Float, BasicType::Float),
+            ],
         ),
         (
             Func2Type::Modulo,
-            vec![Function2::integer_op(Box::new(|qp, lhs, rhs| {
-                qp.checked_modulo(lhs, rhs)
+            vec![
+                Function2::integer_op(Box::new(|qp, lhs, rhs| {
+                    qp.checked_modulo(lhs, rhs)
+                })),
+                Function2::float_op(Box::new(|qp, lhs, rhs| {
+                    qp.modulo(lhs, rhs, EncodingType::F64)
+                }), BasicType::Integer, BasicType::Float),
+                Function2::float_op(Box::new(|qp, lhs, rhs| {
+                    qp.modulo(lhs, rhs, EncodingType::F64)
+                }), BasicType::Float, BasicType::Integer),
+                Function2::float_op(Box::new(|qp, lhs, rhs| {
+                    qp.modulo(lhs, rhs, EncodingType::F64)
+                }), BasicType::Float, BasicType::Float),
+            ],
+        ),
+        (
+            Func2Type::Pow,
+            vec![
+                Function2::integer_op(Box::new(|qp, lhs, rhs| {
+                    qp.checked_power(lhs, rhs)
+                })),
+                Function2::float_op(Box::new(|qp, lhs, rhs| {
+                    qp.power(lhs, rhs)
+                }), BasicType::Integer, BasicType::Float),
+                Function2::float_op(Box::new(|qp, lhs, rhs| {
+                    qp.power(lhs, rhs)
+                }), BasicType::Float, BasicType::Integer),
+                Function2::float_op(Box::new(|qp, lhs, rhs| {
+                    qp.power(lhs, rhs)
+                }), BasicType::Float, BasicType::Float),
+            ],
+        ),
+        (
+            Func2Type::Concat,
+            vec![Function2::string_op(Box::new(|qp, lhs, rhs| {
+                qp.concat(lhs, rhs)
             }))],
         ),
         (
@@ -819,9 +1149,23 @@ fn function2_registry() -> HashMap<Func2Type, Vec<Function2>> {
                     Box::new(|qp, lhs, rhs| qp.less_than(lhs, rhs)),
                     BasicType::Integer,
                 ),
-                Function2::comparison_op(
+                Function2::string_ordering_op(
                     Box::new(|qp, lhs, rhs| qp.less_than(lhs, rhs)),
-                    BasicType::String,
+                ),
+                Function2::float_comparison_op(
+                    Box::new(|qp, lhs, rhs| qp.less_than(lhs, rhs)),
+                    BasicType::Float,
+                    BasicType::Float,
+                ),
+                Function2::float_comparison_op(
+                    Box::new(|qp, lhs, rhs| qp.less_than(lhs, rhs)),
+                    BasicType::Float,
+                    BasicType::Integer,
+                ),
+                Function2::float_comparison_op(
+                    Box::new(|qp, lhs, rhs| qp.less_than(lhs, rhs)),
+                    BasicType::Integer,
+                    BasicType::Float,
                 ),
             ],
         ),
@@ -832,9 +1176,23 @@ fn function2_registry() -> HashMap<Func2Type, Vec<Function2>> {
                     Box::new(|qp, lhs, rhs| qp.less_than_equals(lhs, rhs)),
                     BasicType::Integer,
                 ),
-                Function2::comparison_op(
+                Function2::string_ordering_op(
                     Box::new(|qp, lhs, rhs| qp.less_than_equals(lhs, rhs)),
-                    BasicType::String,
+                ),
+                Function2::float_comparison_op(
+                    Box::new(|qp, lhs, rhs| qp.less_than_equals(lhs, rhs)),
+                    BasicType::Float,
+                    BasicType::Float,
+                ),
+                Function2::float_comparison_op(
+                    Box::new(|qp, lhs, rhs| qp.less_than_equals(lhs, rhs)),
+                    BasicType::Float,
+                    BasicType::Integer,
+                ),
+                Function2::float_comparison_op(
+                    Box::new(|qp, lhs, rhs| qp.less_than_equals(lhs, rhs)),
+                    BasicType::Integer,
+                    BasicType::Float,
                 ),
             ],
         ),
@@ -845,9 +1203,23 @@ fn function2_registry() -> HashMap<Func2Type, Vec<Function2>> {
                     Box::new(|qp, lhs, rhs| qp.less_than(rhs, lhs)),
                     BasicType::Integer,
                 ),
-                Function2::comparison_op(
+                Function2::string_ordering_op(
                     Box::new(|qp, lhs, rhs| qp.less_than(rhs, lhs)),
-                    BasicType::String,
+                ),
+                Function2::float_comparison_op(
+                    Box::new(|qp, lhs, rhs| qp.less_than(rhs, lhs)),
+                    BasicType::Float,
+                    BasicType::Float,
+                ),
+                Function2::float_comparison_op(
+                    Box::new(|qp, lhs, rhs| qp.less_than(rhs, lhs)),
+                    BasicType::Float,
+                    BasicType::Integer,
+                ),
+                Function2::float_comparison_op(
+                    Box::new(|qp, lhs, rhs| qp.less_than(rhs, lhs)),
+                    BasicType::Integer,
+                    BasicType::Float,
                 ),
             ],
         ),
@@ -858,9 +1230,23 @@ fn function2_registry() -> HashMap<Func2Type, Vec<Function2>> {
                     Box::new(|qp, lhs, rhs| qp.less_than_equals(rhs, lhs)),
                     BasicType::Integer,
                 ),
-                Function2::comparison_op(
+                Function2::string_ordering_op(
                     Box::new(|qp, lhs, rhs| qp.less_than_equals(rhs, lhs)),
-                    BasicType::String,
+                ),
+                Function2::float_comparison_op(
+                    Box::new(|qp, lhs, rhs| qp.less_than_equals(rhs, lhs)),
+                    BasicType::Float,
+                    BasicType::Float,
+                ),
+                Function2::float_comparison_op(
+                    Box::new(|qp, lhs, rhs| qp.less_than_equals(rhs, lhs)),
+                    BasicType::Float,
+                    BasicType::Integer,
+                ),
+                Function2::float_comparison_op(
+                    Box::new(|qp, lhs, rhs| qp.less_than_equals(rhs, lhs)),
+                    BasicType::Integer,
+                    BasicType::Float,
                 ),
             ],
         ),
@@ -875,6 +1261,25 @@ fn function2_registry() -> HashMap<Func2Type, Vec<Function2>> {
                     Box::new(|qp, lhs, rhs| qp.equals(lhs, rhs)),
                     BasicType::String,
                 ),
+                Function2::comparison_op(
+                    Box::new(|qp, lhs, rhs| qp.equals(lhs, rhs)),
+                    BasicType::Boolean,
+                ),
+                Function2::float_comparison_op(
+                    Box::new(|qp, lhs, rhs| qp.equals(lhs, rhs)),
+                    BasicType::Float,
+                    BasicType::Float,
+                ),
+                Function2::float_comparison_op(
+                    Box::new(|qp, lhs, rhs| qp.equals(lhs, rhs)),
+                    BasicType::Float,
+                    BasicType::Integer,
+                ),
+                Function2::float_comparison_op(
+                    Box::new(|qp, lhs, rhs| qp.equals(lhs, rhs)),
+                    BasicType::Integer,
+                    BasicType::Float,
+                ),
             ],
         ),
         (
@@ -888,6 +1293,25 @@ fn function2_registry() -> HashMap<Func2Type, Vec<Function2>> {
                     Box::new(|qp, lhs, rhs| qp.not_equals(lhs, rhs)),
                     BasicType::String,
                 ),
+                Function2::comparison_op(
+                    Box::new(|qp, lhs, rhs| qp.not_equals(lhs, rhs)),
+                    BasicType::Boolean,
+                ),
+                Function2::float_comparison_op(
+                    Box::new(|qp, lhs, rhs| qp.not_equals(lhs, rhs)),
+                    BasicType::Float,
+                    BasicType::Float,
+                ),
+                Function2::float_comparison_op(
+                    Box::new(|qp, lhs, rhs| qp.not_equals(lhs, rhs)),
+                    BasicType::Float,
+                    BasicType::Integer,
+                ),
+                Function2::float_comparison_op(
+                    Box::new(|qp, lhs, rhs| qp.not_equals(lhs, rhs)),
+                    BasicType::Integer,
+                    BasicType::Float,
+                ),
             ],
         ),
     ]
@@ -924,23 +1348,31 @@ impl QueryPlan {
                     };
                     (plan, t)
                 }
-                None => (
-                    planner.null_vec(column_len, EncodingType::Null),
-                    Type::new(BasicType::Null, None),
-                ),
+                None => null_vec(filter, column_len, planner),
             },
             Func2(Or, ref lhs, ref rhs) => {
                 let (plan_lhs, type_lhs) =
                     QueryPlan::compile_expr(lhs, filter, columns, column_len, planner)?;
                 let (plan_rhs, type_rhs) =
                     QueryPlan::compile_expr(rhs, filter, columns, column_len, planner)?;
-                if type_lhs.decoded != BasicType::Boolean || type_rhs.decoded != BasicType::Boolean
-                {
+                let is_bool_or_null = |t: &Type| matches!(t.decoded, BasicType::Boolean | BasicType::Null);
+                if !is_bool_or_null(&type_lhs) || !is_bool_or_null(&type_rhs) {
                     bail!(
                         QueryError::TypeError,
-                        "Found {} OR {}, expected bool OR bool"
+                        "Found {:?} OR {:?}, expected bool OR bool",
+                        type_lhs,
+                        type_rhs
                     )
                 }
+                if type_lhs.decoded == BasicType::Null {
+                    return with_null(Or, (plan_rhs, &type_rhs), filter, column_len, planner);
+                }
+                if type_rhs.decoded == BasicType::Null {
+                    return with_null(Or, (plan_lhs, &type_lhs), filter, column_len, planner);
+                }
+                if let Some(constant) = short_circuit(Or, (plan_lhs, &type_lhs), (plan_rhs, &type_rhs), planner) {
+                    return Ok(constant);
+                }
                 (planner.or(plan_lhs, plan_rhs), Type::bit_vec())
             }
             Func2(And, ref lhs, ref rhs) => {
@@ -948,51 +1380,62 @@ impl QueryPlan {
                     QueryPlan::compile_expr(lhs, filter, columns, column_len, planner)?;
                 let (plan_rhs, type_rhs) =
                     QueryPlan::compile_expr(rhs, filter, columns, column_len, planner)?;
-                if type_lhs.decoded != BasicType::Boolean || type_rhs.decoded != BasicType::Boolean
-                {
+                let is_bool_or_null = |t: &Type| matches!(t.decoded, BasicType::Boolean | BasicType::Null);
+                if !is_bool_or_null(&type_lhs) || !is_bool_or_null(&type_rhs) {
                     bail!(
                         QueryError::TypeError,
-                        "Found {} AND {}, expected bool AND bool"
+                        "Found {:?} AND {:?}, expected bool AND bool",
+                        type_lhs,
+                        type_rhs
                     )
                 }
+                if type_lhs.decoded == BasicType::Null {
+                    return with_null(And, (plan_rhs, &type_rhs), filter, column_len, planner);
+                }
+                if type_rhs.decoded == BasicType::Null {
+                    return with_null(And, (plan_lhs, &type_lhs), filter, column_len, planner);
+                }
+                if let Some(constant) = short_circuit(And, (plan_lhs, &type_lhs), (plan_rhs, &type_rhs), planner) {
+                    return Ok(constant);
+                }
                 (planner.and(plan_lhs, plan_rhs), Type::bit_vec())
             }
-            Func2(Like, ref expr, ref pattern) => match pattern {
-                box Const(RawVal::Str(pattern)) => {
-                    let mut pattern = pattern.to_string();
-                    pattern = regex::escape(&pattern);
-                    pattern = Regex::new(r"([^\\])_")
-                        .unwrap()
-                        .replace_all(&pattern, "$1.")
-                        .to_string();
-                    pattern = Regex::new(r"\\_")
-                        .unwrap()
-                        .replace_all(&pattern, "_")
-                        .to_string();
-                    while pattern.contains("%%%%") {
-                        pattern = pattern.replace("%%%%", "%%");
-                    }
-                    pattern = pattern.replace("%%%", "(%.*)|(.*%)");
-                    pattern = Regex::new(r"([^%])%([^%])")
-                        .unwrap()
-                        .replace_all(&pattern, "$1.*$2")
-                        .to_string();
-                    pattern = Regex::new(r"^%([^%])")
-                        .unwrap()
-                        .replace_all(&pattern, ".*$1")
-                        .to_string();
-                    pattern = Regex::new(r"([^%])%$")
-                        .unwrap()
-                        .replace_all(&pattern, "$1.*")
-                        .to_string();
-                    pattern = Regex::new(r"%%")
-                        .unwrap()
-                        .replace_all(&pattern, "%")
-                        .to_string();
-                    pattern = format!("^{}$", pattern);
+            Func2(NullSafeEquals, ref lhs, ref rhs) => {
+                let (equals, equals_type) = QueryPlan::compile_expr(
+                    &Func2(Equals, lhs.clone(), rhs.clone()),
+                    filter,
+                    columns,
+                    column_len,
+                    planner,
+                )?;
+                if !equals.is_nullable() && equals_type.decoded != BasicType::Null {
+                    return Ok((equals, equals_type));
+                }
+                let both_null = if lhs == rhs {
+                    Func1(Func1Type::IsNull, lhs.clone())
+                } else {
+                    Func2(
+                        And,
+                        Box::new(Func1(Func1Type::IsNull, lhs.clone())),
+                        Box::new(Func1(Func1Type::IsNull, rhs.clone())),
+                    )
+                };
+                let (both_null, both_null_type) =
+                    QueryPlan::compile_expr(&both_null, filter, columns, column_len, planner)?;
+                if !equals.is_nullable() {
+                    return Ok((both_null, both_null_type));
+                }
+                // The data of null rows is arbitrary, so the comparison only counts where both sides are present.
+                // Boolean operators write into their left operand, which must not be the nullable comparison.
+                let present = planner.is_not_null(equals.nullable_any()?).into();
+                let present_and_equal = planner.and(present, equals.forget_nullability());
+                (planner.or(both_null, present_and_equal), Type::bit_vec())
+            }
+            Func2(Like, ref expr, ref pattern) => match pattern {
+                box Const(RawVal::Str(pattern)) => {
                     let (mut plan, t) =
                         QueryPlan::compile_expr(expr, filter, columns, column_len, planner)?;
-                    if t.decoded != BasicType::String {
+                    if t.decoded != BasicType::String && t.decoded != BasicType::NullableString {
                         bail!(QueryError::TypeError,
                                   "Expected expression of type `String` as first argument to LIKE. Actual: {:?}", t)
                     }
@@ -1000,7 +1443,7 @@ impl QueryPlan {
                         plan = codec.decode(plan, planner);
                     }
                     let type_out = Type::unencoded(BasicType::Boolean).mutable();
-                    (planner.regex(plan.str()?, &pattern).into(), type_out)
+                    (planner.like(plan, pattern), type_out)
                 }
                 _ => bail!(
                     QueryError::TypeError,
@@ -1040,12 +1483,206 @@ impl QueryPlan {
                     regex
                 ),
             },
+            Func1(ftype @ (Func1Type::Lower | Func1Type::Upper), box Const(RawVal::Str(ref string))) => {
+                let converted = match ftype {
+                    Func1Type::Lower => string.to_lowercase(),
+                    _ => string.to_uppercase(),
+                };
+                QueryPlan::compile_expr(&Const(RawVal::Str(converted)), filter, columns, column_len, planner)?
+            }
+            Func2(Concat, box Const(RawVal::Str(ref lhs)), box Const(RawVal::Str(ref rhs))) => {
+                QueryPlan::compile_expr(
+                    &Const(RawVal::Str(format!("{}{}", lhs, rhs))),
+                    filter,
+                    columns,
+                    column_len,
+                    planner,
+                )?
+            }
+            Func2(DateTrunc, ref unit, ref timestamp) => match unit {
+                box Const(RawVal::Str(unit)) => {
+                    if TimeUnit::parse(unit).is_none() {
+                        bail!(QueryError::TypeError, "Unsupported unit for date_trunc: {}", unit)
+                    }
+                    let (plan, t) =
+                        QueryPlan::compile_expr(timestamp, filter, columns, column_len, planner)?;
+                    if t.decoded != BasicType::Integer {
+                        bail!(
+                            QueryError::TypeError,
+                            "Found date_trunc('{}', {:?}), expected date_trunc(string, integer)",
+                            unit,
+                            &t
+                        )
+                    }
+                    let decoded = match t.codec.clone() {
+                        Some(codec) => codec.decode(plan, planner),
+                        None => plan,
+                    };
+                    (planner.date_trunc(decoded, unit), t.decoded())
+                }
+                _ => bail!(
+                    QueryError::TypeError,
+                    "Expected string constant as first argument to `date_trunc`, actual: {:?}",
+                    unit
+                ),
+            },
+            Func2(Round, ref expr, ref digits) => match digits {
+                box Const(RawVal::Int(digits)) => {
+                    let (plan, t) =
+                        QueryPlan::compile_expr(expr, filter, columns, column_len, planner)?;
+                    let plan = match t.codec.clone() {
+                        Some(codec) => codec.decode(plan, planner),
+                        None => plan,
+                    };
+                    match t.decoded.non_nullable() {
+                        BasicType::Integer if *digits >= 0 => (plan, t.decoded()),
+                        BasicType::Float => {
+                            if i32::try_from(*digits).is_err() {
+                                bail!(
+                                    QueryError::TypeError,
+                                    "Invalid number of digits for `round`: {}",
+                                    digits
+                                )
+                            }
+                            (
+                                planner.round_digits(plan, *digits),
+                                Type::unencoded(BasicType::Float).mutable(),
+                            )
+                        }
+                        _ => bail!(
+                            QueryError::TypeError,
+                            "Found round({:?}, {}), expected round(float, integer)",
+                            &t,
+                            digits
+                        ),
+                    }
+                }
+                _ => bail!(
+                    QueryError::TypeError,
+                    "Expected integer constant as second argument to `round`, actual: {:?}",
+                    digits
+                ),
+            },
+            Func2(Coalesce, ref lhs, ref rhs) => {
+                let (plan_lhs, type_lhs) =
+                    QueryPlan::compile_expr(lhs, filter, columns, column_len, planner)?;
+                let (plan_rhs, type_rhs) =
+                    QueryPlan::compile_expr(rhs, filter, columns, column_len, planner)?;
+                if type_lhs.decoded == BasicType::Null {
+                    return Ok((plan_rhs, type_rhs));
+                }
+                if type_rhs.decoded == BasicType::Null {
+                    return Ok((plan_lhs, type_lhs));
+                }
+                let mut plan_lhs = match type_lhs.codec {
+                    Some(ref codec) => codec.decode(plan_lhs, planner),
+                    None => plan_lhs,
+                };
+                if !plan_lhs.is_nullable() {
+                    return Ok((plan_lhs, type_lhs.decoded()));
+                }
+                let mut plan_rhs = match type_rhs.codec {
+                    Some(ref codec) => codec.decode(plan_rhs, planner),
+                    None => plan_rhs,
+                };
+                // Integers may be stored with different widths, coalesce them as i64
+                if type_lhs.decoded.non_nullable() == BasicType::Integer
+                    && plan_lhs.tag.non_nullable() != EncodingType::I64
+                {
+                    plan_lhs = planner.cast(plan_lhs, EncodingType::I64);
+                }
+                if type_rhs.decoded.non_nullable() == BasicType::Integer
+                    && !type_rhs.is_scalar
+                    && plan_rhs.tag.non_nullable() != EncodingType::I64
+                {
+                    plan_rhs = planner.cast(plan_rhs, EncodingType::I64);
+                }
+                let coalesced_type = plan_lhs.tag.non_nullable();
+                let rhs_type = match plan_rhs.tag {
+                    EncodingType::ScalarI64 => EncodingType::I64,
+                    EncodingType::ScalarStr => EncodingType::Str,
+                    t => t.non_nullable(),
+                };
+                if coalesced_type != rhs_type
+                    || ![EncodingType::I64, EncodingType::F64, EncodingType::Str].contains(&coalesced_type)
+                {
+                    bail!(
+                        QueryError::TypeError,
+                        "Found coalesce({:?}, {:?}), expected arguments of the same type",
+                        type_lhs,
+                        type_rhs
+                    )
+                }
+                let plan = planner.coalesce(plan_lhs, plan_rhs, coalesced_type);
+                (plan, Type::unencoded(plan.tag.cast_to_basic()).mutable())
+            }
             Func2(function, ref lhs, ref rhs) => {
                 let (mut plan_lhs, type_lhs) =
                     QueryPlan::compile_expr(lhs, filter, columns, column_len, planner)?;
-                let (mut plan_rhs, type_rhs) =
+                let (mut plan_rhs, mut type_rhs) =
                     QueryPlan::compile_expr(rhs, filter, columns, column_len, planner)?;
 
+                // Any function applied to NULL yields NULL
+                if type_lhs.decoded == BasicType::Null || type_rhs.decoded == BasicType::Null {
+                    return Ok(null_vec(filter, column_len, planner));
+                }
+
+                // Arithmetic on two constants is evaluated during planning
+                if matches!(function, Add | Subtract | Multiply | Divide | Modulo | Pow) {
+                    if let (Some(lhs), Some(rhs)) = (
+                        numeric_constant(&plan_lhs, &type_lhs, planner),
+                        numeric_constant(&plan_rhs, &type_rhs, planner),
+                    ) {
+                        return QueryPlan::compile_expr(
+                            &Const(fold_arithmetic(function, &lhs, &rhs)?),
+                            filter,
+                            columns,
+                            column_len,
+                            planner,
+                        );
+                    }
+                }
+
+                // Comparisons of two constants are evaluated during planning, e.g. `WHERE 1 = 0`
+                if matches!(function, Equals | NotEquals | LT | LTE | GT | GTE) {
+                    if let (Some(lhs), Some(rhs)) = (
+                        numeric_constant(&plan_lhs, &type_lhs, planner),
+                        numeric_constant(&plan_rhs, &type_rhs, planner),
+                    ) {
+                        return QueryPlan::compile_expr(
+                            &Const(RawVal::Bool(fold_comparison(function, &lhs, &rhs))),
+                            filter,
+                            columns,
+                            column_len,
+                            planner,
+                        );
+                    }
+                }
+
+                // Division and modulo by zero yield NULL, also for floats where they would otherwise be inf or NaN
+                if (function == Divide || function == Modulo)
+                    && matches!(type_rhs.decoded.non_nullable(), BasicType::Integer | BasicType::Float)
+                    && !matches!(**rhs, Func1(Func1Type::NullIfZero, _))
+                {
+                    match *planner.resolve(&plan_rhs) {
+                        // Expanded so that the quotient is a nullable column of the type of `lhs`
+                        QueryPlan::ScalarI64 { value: 0, .. } => {
+                            plan_rhs = expand_constant(0, filter, column_len, planner);
+                            type_rhs = Type::unencoded(BasicType::Integer);
+                        }
+                        QueryPlan::ScalarF64 { value, .. } if value.0 == 0.0 => {
+                            let zeros = expand_constant(0, filter, column_len, planner);
+                            let zeros = planner.cast(zeros, EncodingType::I64);
+                            plan_rhs = planner.cast(zeros, EncodingType::F64);
+                            type_rhs = Type::unencoded(BasicType::Float);
+                        }
+                        _ => {}
+                    }
+                    if !type_rhs.is_scalar {
+                        plan_rhs = null_if_zero(plan_rhs, &type_rhs, planner)?;
+                        type_rhs = type_rhs.decoded();
+                    }
+                }
                 let declarations = match FUNCTION2_REGISTRY.get(&function) {
                     Some(patterns) => patterns,
                     None => bail!(QueryError::NotImplemented, "function {:?}", function),
@@ -1124,6 +1761,171 @@ impl QueryPlan {
                 column_len,
                 planner,
             )?,
+            Func1(Func1Type::Negate, box Const(RawVal::Float(f))) => QueryPlan::compile_expr(
+                &Const(RawVal::Float(-f)),
+                filter,
+                columns,
+                column_len,
+                planner,
+            )?,
+            Func1(Func1Type::Negate, ref inner) => QueryPlan::compile_expr(
+                &Func2(Multiply, inner.clone(), Box::new(Const(RawVal::Int(-1)))),
+                filter,
+                columns,
+                column_len,
+                planner,
+            )?,
+            Func1(ftype @ (Func1Type::Floor | Func1Type::Ceil | Func1Type::Round), ref inner) => {
+                let (plan, t) =
+                    QueryPlan::compile_expr(inner, filter, columns, column_len, planner)?;
+                let plan = match t.codec.clone() {
+                    Some(codec) => codec.decode(plan, planner),
+                    None => plan,
+                };
+                match t.decoded.non_nullable() {
+                    // Integers are already rounded and NULL stays NULL
+                    BasicType::Integer | BasicType::Null => (plan, t.decoded()),
+                    BasicType::Float => {
+                        let plan = match ftype {
+                            Func1Type::Floor => planner.floor(plan),
+                            Func1Type::Ceil => planner.ceil(plan),
+                            _ => planner.round(plan),
+                        };
+                        (plan, Type::unencoded(BasicType::Integer).mutable())
+                    }
+                    _ => bail!(
+                        QueryError::TypeError,
+                        "Found {:?}({:?}), expected {:?}(float)",
+                        ftype,
+                        &t,
+                        ftype
+                    ),
+                }
+            }
+            Func1(Func1Type::Abs, box Const(RawVal::Int(i)))
+            | Func1(Func1Type::Abs, box Func1(Func1Type::Negate, box Const(RawVal::Int(i)))) => QueryPlan::compile_expr(
+                &Const(RawVal::Int(i.saturating_abs())),
+                filter,
+                columns,
+                column_len,
+                planner,
+            )?,
+            Func1(Func1Type::Sign, box Const(RawVal::Int(i))) => QueryPlan::compile_expr(
+                &Const(RawVal::Int(i.signum())),
+                filter,
+                columns,
+                column_len,
+                planner,
+            )?,
+            Func1(Func1Type::Sign, box Func1(Func1Type::Negate, box Const(RawVal::Int(i)))) => QueryPlan::compile_expr(
+                &Const(RawVal::Int(-i.signum())),
+                filter,
+                columns,
+                column_len,
+                planner,
+            )?,
+            Func1(ftype @ (Func1Type::Abs | Func1Type::Sign), ref inner) => {
+                let (plan, t) =
+                    QueryPlan::compile_expr(inner, filter, columns, column_len, planner)?;
+                let plan = match t.codec.clone() {
+                    Some(codec) => codec.decode(plan, planner),
+                    None => plan,
+                };
+                let (plan, basic_type) = match t.decoded.non_nullable() {
+                    BasicType::Integer if plan.tag.non_nullable() != EncodingType::I64 => {
+                        (planner.cast(plan, EncodingType::I64), BasicType::Integer)
+                    }
+                    basic_type @ (BasicType::Integer | BasicType::Float) => (plan, basic_type),
+                    // Any function applied to NULL yields NULL
+                    BasicType::Null => return Ok((plan, t.decoded())),
+                    _ => bail!(
+                        QueryError::TypeError,
+                        "Found {:?}({:?}), expected {:?}(integer) or {:?}(float)",
+                        ftype,
+                        &t,
+                        ftype,
+                        ftype
+                    ),
+                };
+                match ftype {
+                    Func1Type::Abs => (planner.abs(plan), Type::unencoded(basic_type).mutable()),
+                    _ => (planner.sign(plan), Type::unencoded(BasicType::Integer).mutable()),
+                }
+            }
+            Func1(ftype @ (Func1Type::Sqrt | Func1Type::Ln | Func1Type::Log10 | Func1Type::Exp), ref inner) => {
+                if let Some(f) = const_float(inner) {
+                    let value = match ftype {
+                        Func1Type::Sqrt | Func1Type::Ln | Func1Type::Log10 if f < 0.0 => RawVal::Null,
+                        Func1Type::Sqrt => RawVal::Float(OrderedFloat(f.sqrt())),
+                        Func1Type::Ln => RawVal::Float(OrderedFloat(f.ln())),
+                        Func1Type::Log10 => RawVal::Float(OrderedFloat(f.log10())),
+                        _ => RawVal::Float(OrderedFloat(f.exp())),
+                    };
+                    return QueryPlan::compile_expr(&Const(value), filter, columns, column_len, planner);
+                }
+                let (plan, t) =
+                    QueryPlan::compile_expr(inner, filter, columns, column_len, planner)?;
+                let plan = match t.codec.clone() {
+                    Some(codec) => codec.decode(plan, planner),
+                    None => plan,
+                };
+                let plan = match t.decoded.non_nullable() {
+                    BasicType::Integer if plan.tag.non_nullable() != EncodingType::I64 => {
+                        planner.cast(plan, EncodingType::I64)
+                    }
+                    BasicType::Integer | BasicType::Float => plan,
+                    BasicType::Null => return Ok((plan, t.decoded())),
+                    _ => bail!(
+                        QueryError::TypeError,
+                        "Found {:?}({:?}), expected {:?}(integer) or {:?}(float)",
+                        ftype,
+                        &t,
+                        ftype,
+                        ftype
+                    ),
+                };
+                match ftype {
+                    Func1Type::Sqrt => (planner.sqrt(plan), Type::unencoded(BasicType::Float).mutable()),
+                    Func1Type::Ln => (planner.ln(plan), Type::unencoded(BasicType::Float).mutable()),
+                    Func1Type::Log10 => (planner.log10(plan), Type::unencoded(BasicType::Float).mutable()),
+                    _ => (planner.exp(plan), Type::unencoded(BasicType::Float).mutable()),
+                }
+            }
+            Func1(Func1Type::Sample, ref inner) => {
+                let fraction = match const_float(inner) {
+                    Some(fraction) if (0.0..=1.0).contains(&fraction) => fraction,
+                    _ => bail!(
+                        QueryError::TypeError,
+                        "Found SAMPLE({:?}), expected a constant fraction between 0 and 1",
+                        inner
+                    ),
+                };
+                let plan = planner.sample(OrderedFloat(fraction), column_len).into();
+                let plan = match filter {
+                    Filter::U8(filter) => planner.filter(plan, filter),
+                    Filter::NullableU8(filter) => planner.nullable_filter(plan, filter),
+                    Filter::Indices(indices) => planner.select(plan, indices),
+                    Filter::None => plan,
+                };
+                (plan, Type::bit_vec())
+            }
+            Func1(ftype @ (Func1Type::IsNull | Func1Type::IsNotNull), ref inner) => {
+                let (plan, t) =
+                    QueryPlan::compile_expr(inner, filter, columns, column_len, planner)?;
+                let is_null = matches!(ftype, Func1Type::IsNull);
+                let plan = if plan.is_nullable() {
+                    if is_null {
+                        planner.is_null(plan.nullable_any()?).into()
+                    } else {
+                        planner.is_not_null(plan.nullable_any()?).into()
+                    }
+                } else {
+                    // Expressions of type null are always null, other non-nullable expressions never are
+                    let always_null = t.decoded == BasicType::Null;
+                    expand_constant((always_null == is_null) as i64, filter, column_len, planner)
+                };
+                (plan, Type::bit_vec())
+            }
             Func1(ftype, ref inner) => {
                 let (plan, t) =
                     QueryPlan::compile_expr(inner, filter, columns, column_len, planner)?;
@@ -1142,6 +1944,27 @@ impl QueryPlan {
                         }
                         planner.to_year(decoded)
                     }
+                    Func1Type::ToMonth | Func1Type::ToDay | Func1Type::ToHour => {
+                        let decoded = match t.codec.clone() {
+                            Some(codec) => codec.decode(plan, planner),
+                            None => plan,
+                        };
+                        let unit = match ftype {
+                            Func1Type::ToMonth => "month",
+                            Func1Type::ToDay => "day",
+                            _ => "hour",
+                        };
+                        if t.decoded != BasicType::Integer {
+                            bail!(
+                                QueryError::TypeError,
+                                "Found to_{}({:?}), expected to_{}(integer)",
+                                unit,
+                                &t,
+                                unit
+                            )
+                        }
+                        planner.date_trunc(decoded, unit)
+                    }
                     Func1Type::Length => {
                         let decoded = match t.codec.clone() {
                             Some(codec) => codec.decode(plan, planner),
@@ -1156,64 +1979,372 @@ impl QueryPlan {
                         }
                         planner.length(decoded.str()?).into()
                     }
+                    Func1Type::Lower | Func1Type::Upper => {
+                        let decoded = match t.codec.clone() {
+                            Some(codec) => codec.decode(plan, planner),
+                            None => plan,
+                        };
+                        let name = if let Func1Type::Lower = ftype { "lower" } else { "upper" };
+                        match t.decoded {
+                            BasicType::String | BasicType::NullableString => {}
+                            // Case conversion of null is null
+                            BasicType::Null => return Ok((decoded, t.decoded())),
+                            _ => bail!(
+                                QueryError::TypeError,
+                                "Found {}({:?}), expected {}(string)",
+                                name,
+                                &t,
+                                name
+                            ),
+                        }
+                        if let Func1Type::Lower = ftype {
+                            planner.lower(decoded)
+                        } else {
+                            planner.upper(decoded)
+                        }
+                    }
                     Func1Type::Not => {
                         let decoded = match t.codec.clone() {
                             Some(codec) => codec.decode(plan, planner),
                             None => plan,
                         };
-                        if t.decoded != BasicType::Boolean {
-                            bail!(
+                        match t.decoded {
+                            BasicType::Boolean => {}
+                            // Negation of null is null
+                            BasicType::Null => return Ok((decoded, t.decoded())),
+                            _ => bail!(
                                 QueryError::TypeError,
                                 "Found NOT({:?}), expected NOT(boolean)",
                                 &t
-                            )
+                            ),
                         }
-                        planner.not(decoded.u8()?).into()
+                        planner.not(decoded)
                     }
-                    Func1Type::IsNull => {
-                        if plan.is_nullable() {
-                            planner.is_null(plan.nullable_any()?).into()
-                        } else {
-                            planner.constant_expand(
-                                (false as u8) as i64,
-                                column_len,
-                                EncodingType::U8,
-                            )
+                    Func1Type::NullIfZero => null_if_zero(plan, &t, planner)?,
+                    Func1Type::Negate
+                    | Func1Type::Floor
+                    | Func1Type::Ceil
+                    | Func1Type::Round
+                    | Func1Type::Abs
+                    | Func1Type::Sign
+                    | Func1Type::Sqrt
+                    | Func1Type::Ln
+                    | Func1Type::Log10
+                    | Func1Type::Exp
+                    | Func1Type::Sample
+                    | Func1Type::IsNull
+                    | Func1Type::IsNotNull => unreachable!(),
+                };
+                (plan, t.decoded())
+            }
+            Case(ref branches, ref default) => {
+                let mut values = Vec::with_capacity(branches.len() + 1);
+                for value in branches.iter().map(|(_, value)| value).chain(Some(&**default)) {
+                    let (plan, t) =
+                        QueryPlan::compile_expr(value, filter, columns, column_len, planner)?;
+                    let plan = match t.codec {
+                        Some(ref codec) => codec.decode(plan, planner),
+                        None => plan,
+                    };
+                    values.push((plan, t));
+                }
+
+                // All values must have the same type, integers are widened to i64
+                let mut blended_type = None;
+                let mut nullable = false;
+                for (plan, t) in &mut values {
+                    let value_type = match t.decoded {
+                        BasicType::Null => {
+                            nullable = true;
+                            continue;
                         }
-                    }
-                    Func1Type::IsNotNull => {
-                        if plan.is_nullable() {
-                            planner.is_not_null(plan.nullable_any()?).into()
-                        } else {
-                            planner.constant_expand(
-                                (true as u8) as i64,
-                                column_len,
-                                EncodingType::U8,
-                            )
+                        BasicType::Integer | BasicType::NullableInteger => {
+                            if !t.is_scalar && plan.tag.non_nullable() != EncodingType::I64 {
+                                *plan = planner.cast(*plan, EncodingType::I64);
+                            }
+                            EncodingType::I64
                         }
-                    }
-                    Func1Type::Negate => {
-                        bail!(
+                        BasicType::String | BasicType::NullableString => EncodingType::Str,
+                        BasicType::Float | BasicType::NullableFloat => EncodingType::F64,
+                        _ => bail!(
                             QueryError::TypeError,
-                            "Unary minus not implemented for arbitrary expressions."
-                        )
+                            "Found CASE ... THEN {:?}, expected integer, string or float",
+                            t
+                        ),
+                    };
+                    nullable |= plan.is_nullable();
+                    match blended_type {
+                        Some(blended_type) if blended_type != value_type => bail!(
+                            QueryError::TypeError,
+                            "Values of CASE expression have different types {:?} and {:?}",
+                            blended_type,
+                            value_type
+                        ),
+                        _ => blended_type = Some(value_type),
                     }
+                }
+                let blended_type = match blended_type {
+                    Some(t) if nullable => t.nullable(),
+                    Some(t) => t,
+                    None => return Ok(null_vec(filter, column_len, planner)),
                 };
-                (plan, t.decoded())
+
+                let (mut plan, _) = values.pop().unwrap();
+                for ((cond, _), (value, _)) in branches.iter().zip(values).rev() {
+                    let (cond_plan, cond_type) =
+                        QueryPlan::compile_expr(cond, filter, columns, column_len, planner)?;
+                    let cond_plan = match cond_type.codec {
+                        Some(ref codec) => codec.decode(cond_plan, planner),
+                        None => cond_plan,
+                    };
+                    match cond_plan.tag {
+                        // Branches with NULL condition are never selected
+                        EncodingType::Null => continue,
+                        EncodingType::U8 | EncodingType::NullableU8 => {}
+                        _ => bail!(
+                            QueryError::TypeError,
+                            "Found CASE WHEN {:?}, expected boolean condition",
+                            cond_type
+                        ),
+                    }
+                    plan = planner.blend(cond_plan, value, plan, blended_type);
+                }
+                (plan, Type::unencoded(blended_type.cast_to_basic()).mutable())
             }
-            Const(RawVal::Int(i)) => (
+            Const(RawVal::Int(i)) | Const(RawVal::Timestamp(i)) => (
                 planner.scalar_i64(i, false).into(),
                 Type::scalar(BasicType::Integer),
             ),
+            Const(RawVal::Float(f)) => (
+                planner.scalar_f64(f).into(),
+                Type::scalar(BasicType::Float),
+            ),
             Const(RawVal::Str(ref s)) => (
                 planner.scalar_str(s).into(),
                 Type::scalar(BasicType::String),
             ),
+            Const(RawVal::Bool(b)) => (
+                planner.scalar_i64(b as i64, false).into(),
+                Type::scalar(BasicType::Boolean),
+            ),
+            Const(RawVal::Null) => null_vec(filter, column_len, planner),
             ref x => bail!(QueryError::NotImplemented, "{:?}.compile_vec()", x),
         })
     }
 }
 
+/// Returns the value of an unencoded integer or float constant.
+fn numeric_constant(plan: &TypedBufferRef, t: &Type, planner: &QueryPlanner) -> Option<RawVal> {
+    if !t.is_scalar || t.is_encoded() {
+        return None;
+    }
+    match *planner.resolve(plan) {
+        QueryPlan::ScalarI64 { value, .. } if t.decoded == BasicType::Integer => Some(RawVal::Int(value)),
+        QueryPlan::ScalarF64 { value, .. } => Some(RawVal::Float(value)),
+        _ => None,
+    }
+}
+
+/// Returns the value of a boolean constant, which are represented as integers.
+fn boolean_constant(plan: &TypedBufferRef, t: &Type, planner: &QueryPlanner) -> Option<bool> {
+    if !t.is_scalar || t.is_encoded() || t.decoded != BasicType::Boolean {
+        return None;
+    }
+    match *planner.resolve(plan) {
+        QueryPlan::ScalarI64 { value, .. } => Some(value != 0),
+        _ => None,
+    }
+}
+
+/// Short-circuits AND and OR if either operand is a boolean constant, e.g. `x = 1 AND 1 = 0` is always false.
+fn short_circuit(
+    function: Func2Type,
+    (plan_lhs, type_lhs): (TypedBufferRef, &Type),
+    (plan_rhs, type_rhs): (TypedBufferRef, &Type),
+    planner: &mut QueryPlanner,
+) -> Option<(TypedBufferRef, Type)> {
+    let (value, other) = match boolean_constant(&plan_lhs, type_lhs, planner) {
+        Some(value) => (value, (plan_rhs, type_rhs.clone())),
+        None => (boolean_constant(&plan_rhs, type_rhs, planner)?, (plan_lhs, type_lhs.clone())),
+    };
+    if value == (function == Func2Type::Or) {
+        Some((planner.scalar_i64(value as i64, false).into(), Type::scalar(BasicType::Boolean)))
+    } else {
+        Some(other)
+    }
+}
+
+/// Evaluates `x OR NULL`, which is true where `x` is true, and `x AND NULL`, which is false where `x` is false.
+/// All other rows are null.
+fn with_null(
+    function: Func2Type,
+    (plan, t): (TypedBufferRef, &Type),
+    filter: Filter,
+    column_len: usize,
+    planner: &mut QueryPlanner,
+) -> Result<(TypedBufferRef, Type), QueryError> {
+    if t.decoded == BasicType::Null {
+        return Ok(null_vec(filter, column_len, planner));
+    }
+    if let Some(value) = boolean_constant(&plan, t, planner) {
+        return Ok(if value == (function == Func2Type::Or) {
+            (planner.scalar_i64(value as i64, false).into(), Type::scalar(BasicType::Boolean))
+        } else {
+            null_vec(filter, column_len, planner)
+        });
+    }
+    let decoded = match t.codec.clone() {
+        Some(codec) => codec.decode(plan, planner),
+        None => plan,
+    };
+    // Set for the rows where the result is not null
+    let mut determined = decoded.forget_nullability();
+    if function == Func2Type::And {
+        determined = planner.not(determined);
+    }
+    if decoded.is_nullable() {
+        // Boolean operators write into their left operand, which must not be `decoded`.
+        let present = planner.is_not_null(decoded.nullable_any()?).into();
+        determined = planner.and(present, determined);
+    }
+    // Zeros become null, ones become `true` for OR and `false` for AND.
+    let offset = (function == Func2Type::And) as i64;
+    Ok((planner.unfuse_int_nulls(offset, determined), Type::bit_vec()))
+}
+
+/// Outputs a vector of nulls with one entry for each row selected by `filter`.
+fn null_vec(filter: Filter, column_len: usize, planner: &mut QueryPlanner) -> (TypedBufferRef, Type) {
+    let nulls = match filter {
+        Filter::None => planner.null_vec(column_len, EncodingType::Null),
+        _ => {
+            let selected = expand_constant(0, filter, column_len, planner);
+            planner.null_vec_like(selected, EncodingType::Null)
+        }
+    };
+    (nulls, Type::new(BasicType::Null, None))
+}
+
+/// Outputs a `u8` vector with all values equal to `value` and one entry for each row selected by `filter`.
+fn expand_constant(value: i64, filter: Filter, column_len: usize, planner: &mut QueryPlanner) -> TypedBufferRef {
+    let plan = planner.constant_expand(value, column_len, EncodingType::U8);
+    match filter {
+        Filter::U8(filter) => planner.filter(plan, filter),
+        Filter::NullableU8(filter) => planner.nullable_filter(plan, filter),
+        Filter::Indices(indices) => planner.select(plan, indices),
+        Filter::None => plan,
+    }
+}
+
+/// Evaluates a comparison of two numeric constants, integers are converted to float if either operand is a float.
+fn fold_comparison(function: Func2Type, lhs: &RawVal, rhs: &RawVal) -> bool {
+    let ordering = match (lhs, rhs) {
+        (&RawVal::Int(l), &RawVal::Int(r)) => l.cmp(&r),
+        _ => {
+            let as_f64 = |val: &RawVal| match *val {
+                RawVal::Int(i) => OrderedFloat(i as f64),
+                RawVal::Float(f) => f,
+                _ => unreachable!("{:?} is not numeric", val),
+            };
+            as_f64(lhs).cmp(&as_f64(rhs))
+        }
+    };
+    match function {
+        Func2Type::Equals => ordering == Ordering::Equal,
+        Func2Type::NotEquals => ordering != Ordering::Equal,
+        Func2Type::LT => ordering == Ordering::Less,
+        Func2Type::LTE => ordering != Ordering::Greater,
+        Func2Type::GT => ordering == Ordering::Greater,
+        Func2Type::GTE => ordering != Ordering::Less,
+        _ => unreachable!("{:?} is not a comparison", function),
+    }
+}
+
+/// Evaluates an arithmetic operation on two numeric constants.
+/// Integer operands use integer arithmetic, e.g. `7 / 2 = 3`.
+/// If either operand is a float, both are converted to float, e.g. `7.0 / 2 = 3.5`.
+/// Division and modulo by zero yield null for both integers and floats.
+fn fold_arithmetic(function: Func2Type, lhs: &RawVal, rhs: &RawVal) -> Result<RawVal, QueryError> {
+    use self::Func2Type::*;
+    match (lhs, rhs) {
+        (&RawVal::Int(l), &RawVal::Int(r)) => {
+            if (function == Divide || function == Modulo) && r == 0 {
+                return Ok(RawVal::Null);
+            }
+            let result = match function {
+                Add => l.checked_add(r),
+                Subtract => l.checked_sub(r),
+                Multiply => l.checked_mul(r),
+                Divide => l.checked_div(r),
+                Modulo => l.checked_rem(r),
+                Pow => integer_pow(l, r),
+                _ => bail!(QueryError::NotImplemented, "fold_arithmetic({:?})", function),
+            };
+            result.map(RawVal::Int).ok_or(QueryError::Overflow)
+        }
+        _ => {
+            let as_f64 = |val: &RawVal| match *val {
+                RawVal::Int(i) => Some(i as f64),
+                RawVal::Float(f) => Some(f.0),
+                _ => None,
+            };
+            let (l, r) = match (as_f64(lhs), as_f64(rhs)) {
+                (Some(l), Some(r)) => (l, r),
+                _ => bail!(QueryError::TypeError, "Found {:?}({:?}, {:?}), expected numbers", function, lhs, rhs),
+            };
+            if (function == Divide || function == Modulo) && r == 0.0 {
+                return Ok(RawVal::Null);
+            }
+            let result = match function {
+                Add => l + r,
+                Subtract => l - r,
+                Multiply => l * r,
+                Divide => l / r,
+                Modulo => l % r,
+                Pow => l.powf(r),
+                _ => bail!(QueryError::NotImplemented, "fold_arithmetic({:?})", function),
+            };
+            Ok(RawVal::Float(OrderedFloat(result)))
+        }
+    }
+}
+
+/// Value of an integer or float literal, possibly negated.
+fn const_float(expr: &Expr) -> Option<f64> {
+    match *expr {
+        Expr::Const(RawVal::Int(i)) => Some(i as f64),
+        Expr::Const(RawVal::Float(f)) => Some(f.0),
+        Expr::Func1(Func1Type::Negate, ref inner) => const_float(inner).map(|f| -f),
+        _ => None,
+    }
+}
+
+/// Maps zeros in an integer or float expression to null, e.g. to make division by zero yield null.
+fn null_if_zero(
+    plan: TypedBufferRef,
+    t: &Type,
+    planner: &mut QueryPlanner,
+) -> Result<TypedBufferRef, QueryError> {
+    let decoded = match t.codec.clone() {
+        Some(codec) => codec.decode(plan, planner),
+        None => plan,
+    };
+    match t.decoded.non_nullable() {
+        BasicType::Integer => {}
+        BasicType::Float => return Ok(planner.float_null_if_zero(decoded)),
+        _ => bail!(
+            QueryError::TypeError,
+            "Found null_if_zero({:?}), expected null_if_zero(integer) or null_if_zero(float)",
+            t
+        ),
+    }
+    if decoded.is_nullable() {
+        let fused = planner.fuse_int_nulls(0, decoded);
+        Ok(planner.unfuse_int_nulls(0, fused))
+    } else {
+        Ok(planner.unfuse_int_nulls(0, decoded))
+    }
+}
+
 fn encoding_range(plan: &TypedBufferRef, qp: &QueryPlanner) -> Option<(i64, i64)> {
     // This would benefit from more principled approach - it currently doesn't work for all partially decodings
     // Example: [LZ4, Add, Delta] will have as bottom decoding range the range after indices, max_index Delta, but without the Add :/
@@ -1227,7 +2358,16 @@ fn encoding_range(plan: &TypedBufferRef, qp: &QueryPlanner) -> Option<(i64, i64)
                 i64::from(NaiveDateTime::from_timestamp_opt(max, 0).unwrap().year()),
             )
         }),
-        Filter { ref plan, .. } => encoding_range(plan, qp),
+        DateTrunc {
+            timestamp,
+            ref unit,
+            ..
+        } => {
+            let unit = TimeUnit::parse(unit)?;
+            encoding_range(&timestamp, qp)
+                .map(|(min, max)| (unit.truncate(min), unit.truncate(max)))
+        }
+        Filter { ref plan, .. } | NullableFilter { ref plan, .. } => encoding_range(plan, qp),
         Divide {
             ref lhs, ref rhs, ..
         } => {
@@ -1267,11 +2407,30 @@ fn encoding_range(plan: &TypedBufferRef, qp: &QueryPlanner) -> Option<(i64, i64)
                 None
             }
         }
+        Multiply {
+            ref lhs, ref rhs, ..
+        }
+        | CheckedMultiply {
+            ref lhs, ref rhs, ..
+        } => {
+            if let ScalarI64 { value: c, .. } = qp.resolve(rhs) {
+                let (min, max) = encoding_range(lhs, qp)?;
+                let (min, max) = (min.checked_mul(*c)?, max.checked_mul(*c)?);
+                Some(if *c >= 0 { (min, max) } else { (max, min) })
+            } else {
+                None
+            }
+        }
         Cast { ref input, .. } => encoding_range(input, qp),
         LZ4Decode { bytes, .. } => encoding_range(&bytes.into(), qp),
         DeltaDecode { ref plan, .. } => encoding_range(plan, qp),
         AssembleNullable { ref data, .. } => encoding_range(data, qp),
-        UnpackStrings { .. } | UnhexpackStrings { .. } | Length { .. } => None,
+        ConstantExpand { value, .. } => Some((value, value)),
+        UnpackStrings { .. } | UnhexpackStrings { .. } | Concat { .. } | Length { .. } => None,
+        Lower { .. } | Upper { .. } => None,
+        Floor { .. } | Ceil { .. } | Round { .. } | RoundDigits { .. } => None,
+        Abs { .. } | Sign { .. } | Sqrt { .. } | Ln { .. } | Log10 { .. } | Exp { .. } | Coalesce { .. } | Blend { .. } => None,
+        FloatNullIfZero { .. } => None,
         ref plan => {
             error!("encoding_range not implement for {:?}", plan);
             None
@@ -1318,6 +2477,7 @@ pub fn compile_grouping_key(
                 let original_plan = gk_plan;
                 let encoding_range = encoding_range(&gk_plan, planner);
                 debug!("Encoding range of {:?} for {:?}", &encoding_range, &gk_plan);
+                let max_value = encoding_range.map(|(_, max)| max).unwrap_or(i64::MAX);
                 let (max_cardinality, offset) = match encoding_range {
                     Some((min, max)) => {
                         if min <= 0 && gk_plan.is_nullable() {
@@ -1333,14 +2493,28 @@ pub fn compile_grouping_key(
                     None => (1 << 62, None),
                 };
 
+                // Adding the offset may overflow narrow integer types
+                let widen = offset.map_or(false, |offset| {
+                    let type_max = match gk_plan.tag.non_nullable() {
+                        EncodingType::U8 => i64::from(u8::MAX),
+                        EncodingType::U16 => i64::from(u16::MAX),
+                        EncodingType::U32 => i64::from(u32::MAX),
+                        _ => i64::MAX,
+                    };
+                    max_value.saturating_add(offset) > type_max
+                });
                 if gk_plan.is_nullable() {
                     gk_plan = match offset {
+                        Some(offset) if widen => {
+                            let widened = planner.cast(gk_plan, EncodingType::I64);
+                            planner.fuse_int_nulls(offset, widened)
+                        }
                         Some(offset) => planner.fuse_int_nulls(offset, gk_plan),
                         None => planner.fuse_nulls(gk_plan),
                     }
                 } else if let Some(offset) = offset {
                     let offset = planner.scalar_i64(offset, true);
-                    gk_plan = planner.add(gk_plan, offset.into());
+                    gk_plan = planner.add(gk_plan, offset.into(), EncodingType::I64);
                 }
 
                 let encoded_group_by_placeholder = planner
@@ -1349,12 +2523,16 @@ pub fn compile_grouping_key(
                 let mut decoded_group_by = encoded_group_by_placeholder;
                 if original_plan.is_nullable() {
                     decoded_group_by = match offset {
+                        Some(offset) if widen => {
+                            let unfused = planner.unfuse_int_nulls(offset, decoded_group_by);
+                            planner.cast(unfused, original_plan.tag.non_nullable())
+                        }
                         Some(offset) => planner.unfuse_int_nulls(offset, decoded_group_by),
                         None => planner.unfuse_nulls(decoded_group_by),
                     }
                 } else if let Some(offset) = offset {
                     let offset = planner.scalar_i64(-offset, true);
-                    let sum = planner.add(decoded_group_by, offset.into());
+                    let sum = planner.add(decoded_group_by, offset.into(), EncodingType::I64);
                     decoded_group_by = planner.cast(sum, gk_type.encoding_type());
                 }
                 if let Some(codec) = gk_type.codec.clone() {
@@ -1467,6 +2645,9 @@ fn try_bitpacking(
             "Encoding range of {:?} for {:?}",
             &encoding_range, &query_plan
         );
+        // Ranges that span (almost) all of i64 cannot be bitpacked and would overflow below
+        let encoding_range = encoding_range
+            .filter(|&(min, max)| max.checked_sub(min).map_or(false, |range| range < i64::MAX - 1));
         if let Some((min, max)) = encoding_range {
             fn bits(max: i64) -> i64 {
                 ((max + 1) as f64).log2().ceil() as i64
@@ -1489,7 +2670,13 @@ fn try_bitpacking(
             };
             order_preserving = order_preserving && plan_type.is_order_preserving();
             let adjusted_query_plan = if query_plan.is_nullable() {
-                let fused = planner.fuse_int_nulls(-min + 1, query_plan);
+                // Widen before fusing since adding the offset may overflow narrow integer types
+                let widened = if query_plan.tag != EncodingType::NullableI64 {
+                    planner.cast(query_plan, EncodingType::I64)
+                } else {
+                    query_plan
+                };
+                let fused = planner.fuse_int_nulls(-min + 1, widened);
                 if fused.tag != EncodingType::I64 {
                     planner.cast(fused, EncodingType::I64).i64()?
                 } else {
@@ -1497,7 +2684,7 @@ fn try_bitpacking(
                 }
             } else if subtract_offset {
                 let offset = planner.scalar_i64(-min, true);
-                planner.add(query_plan, offset.into()).i64()?
+                planner.add(query_plan, offset.into(), EncodingType::I64).i64()?
             } else {
                 planner.cast(query_plan, EncodingType::I64).i64()?
             };
@@ -1519,7 +2706,7 @@ fn try_bitpacking(
                 decode_plan = planner.unfuse_int_nulls(-min + 1, decode_plan);
             } else if subtract_offset {
                 let offset = planner.scalar_i64(min, true);
-                decode_plan = planner.add(decode_plan, offset.into());
+                decode_plan = planner.add(decode_plan, offset.into(), EncodingType::I64);
             }
             decode_plan = planner.cast(decode_plan, plan_type.encoding_type());
             if let Some(codec) = plan_type.codec.clone() {
@@ -1587,6 +2774,16 @@ pub(super) fn prepare<'a>(
         QueryPlan::CombineNullMaps { lhs, rhs, present } => {
             operator::combine_null_maps(lhs, rhs, present)?
         }
+        QueryPlan::UnionNullMaps { lhs, rhs, present } => {
+            operator::union_null_maps(lhs, rhs, present)?
+        }
+        QueryPlan::Coalesce { lhs, rhs, coalesced } => operator::coalesce(lhs, rhs, coalesced)?,
+        QueryPlan::Blend {
+            cond,
+            lhs,
+            rhs,
+            blended,
+        } => operator::blend(cond, lhs, rhs, blended)?,
         QueryPlan::GetNullMap { nullable, present } => {
             operator::get_null_map(nullable.nullable_any()?, present)
         }
@@ -1626,17 +2823,20 @@ pub(super) fn prepare<'a>(
             hide_value,
             scalar_i64,
         } => operator::scalar_i64(value, hide_value, scalar_i64),
+        QueryPlan::ScalarF64 { value, scalar_f64 } => operator::scalar_f64(value, scalar_f64),
         QueryPlan::ScalarStr {
             value,
             pinned_string,
             scalar_str,
         } => operator::scalar_str(value, pinned_string, scalar_str),
         QueryPlan::NullVec { len, nulls } => operator::null_vec(len, nulls.any()),
+        QueryPlan::NullVecLike { plan, nulls } => operator::null_vec_like(plan.any(), nulls.any()),
         QueryPlan::ConstantExpand {
             value,
             len,
             expanded,
         } => operator::constant_expand(value, len, expanded)?,
+        QueryPlan::Sample { fraction, len, sample } => operator::sample(fraction.0, len, sample),
         QueryPlan::DictLookup {
             indices,
             offset_len,
@@ -1710,8 +2910,12 @@ pub(super) fn prepare<'a>(
             max_index,
             aggregator,
             aggregate,
-        } => if aggregate.tag == EncodingType::F64 {
+        } => if let Aggregator::ApproxPercentile(quantile) = aggregator {
+            operator::approx_percentile(plan.i64()?, grouping_key, max_index, quantile.0, aggregate.f64()?)?
+        } else if aggregate.tag == EncodingType::F64 {
             operator::aggregate_f64(plan, grouping_key, max_index, aggregator, aggregate)?
+        } else if aggregate.tag == EncodingType::OptStr {
+            operator::aggregate_str(plan, grouping_key, max_index, aggregator, aggregate)?
         } else {
             operator::aggregate(plan, grouping_key, max_index, aggregator, aggregate)?
         },
@@ -1791,7 +2995,7 @@ pub(super) fn prepare<'a>(
             rhs,
             not_equals,
         } => operator::not_equals(lhs, rhs, not_equals.u8()?)?,
-        QueryPlan::Add { lhs, rhs, sum } => operator::addition(lhs, rhs, sum.i64()?)?,
+        QueryPlan::Add { lhs, rhs, sum } => operator::addition(lhs, rhs, sum)?,
         QueryPlan::CheckedAdd { lhs, rhs, sum } => {
             operator::checked_addition(lhs, rhs, sum.i64()?)?
         }
@@ -1805,7 +3009,7 @@ pub(super) fn prepare<'a>(
             lhs,
             rhs,
             difference,
-        } => operator::subtraction(lhs, rhs, difference.i64()?)?,
+        } => operator::subtraction(lhs, rhs, difference)?,
         QueryPlan::CheckedSubtract {
             lhs,
             rhs,
@@ -1829,7 +3033,7 @@ pub(super) fn prepare<'a>(
             present,
             product,
         } => operator::nullable_checked_multiplication(lhs, rhs, present, product)?,
-        QueryPlan::Divide { lhs, rhs, division } => operator::division(lhs, rhs, division.i64()?)?,
+        QueryPlan::Divide { lhs, rhs, division } => operator::division(lhs, rhs, division)?,
         QueryPlan::CheckedDivide { lhs, rhs, division } => {
             operator::checked_division(lhs, rhs, division.i64()?)?
         }
@@ -1839,7 +3043,7 @@ pub(super) fn prepare<'a>(
             present,
             division,
         } => operator::nullable_checked_division(lhs, rhs, present, division)?,
-        QueryPlan::Modulo { lhs, rhs, modulo } => operator::modulo(lhs, rhs, modulo.i64()?)?,
+        QueryPlan::Modulo { lhs, rhs, modulo } => operator::modulo(lhs, rhs, modulo)?,
         QueryPlan::CheckedModulo { lhs, rhs, modulo } => {
             operator::checked_modulo(lhs, rhs, modulo.i64()?)?
         }
@@ -1849,49 +3053,100 @@ pub(super) fn prepare<'a>(
             present,
             modulo,
         } => operator::nullable_checked_modulo(lhs, rhs, present, modulo)?,
+        QueryPlan::Power { lhs, rhs, power } => operator::power(lhs, rhs, power.f64()?)?,
+        QueryPlan::CheckedPower { lhs, rhs, power } => {
+            operator::checked_power(lhs, rhs, power.i64()?)?
+        }
+        QueryPlan::NullableCheckedPower {
+            lhs,
+            rhs,
+            present,
+            power,
+        } => operator::nullable_checked_power(lhs, rhs, present, power)?,
         QueryPlan::Or { lhs, rhs, or } => operator::or(lhs.u8()?, rhs.u8()?, or.u8()?),
         QueryPlan::And { lhs, rhs, and } => operator::and(lhs.u8()?, rhs.u8()?, and.u8()?),
-        QueryPlan::Not { input, not } => operator::not(input, not),
+        QueryPlan::Not { input, not } => operator::not(input.u8()?, not.u8()?),
         QueryPlan::ToYear { timestamp, year } => operator::to_year(timestamp.i64()?, year.i64()?),
+        QueryPlan::DateTrunc {
+            timestamp,
+            unit,
+            truncated,
+        } => operator::date_trunc(timestamp.i64()?, &unit, truncated.i64()?)?,
+        QueryPlan::Concat {
+            lhs,
+            rhs,
+            stringstore,
+            concat,
+        } => operator::concat(lhs, rhs, stringstore, concat.str()?)?,
         QueryPlan::Regex {
             plan,
             regex,
             matches,
         } => operator::regex(plan, &regex, matches),
+        QueryPlan::Like {
+            plan,
+            pattern,
+            matches,
+        } => operator::like(plan.str()?, &pattern, matches.u8()?),
+        QueryPlan::Floor { float, floor } => operator::floor(float.f64()?, floor.i64()?),
+        QueryPlan::Ceil { float, ceil } => operator::ceil(float.f64()?, ceil.i64()?),
+        QueryPlan::Round { float, rounded } => operator::round(float.f64()?, rounded.i64()?),
+        QueryPlan::RoundDigits {
+            float,
+            digits,
+            rounded,
+        } => operator::round_digits(float.f64()?, digits as i32, rounded.f64()?),
+        QueryPlan::Abs { plan, abs } => operator::abs(plan, abs)?,
+        QueryPlan::Sign { plan, sign } => operator::sign(plan, sign.i64()?)?,
+        QueryPlan::Sqrt { plan, sqrt } => operator::sqrt(plan, sqrt)?,
+        QueryPlan::Ln { plan, ln } => operator::ln(plan, ln)?,
+        QueryPlan::Log10 { plan, log10 } => operator::log10(plan, log10)?,
+        QueryPlan::FloatNullIfZero { plan, nullable } => operator::float_null_if_zero(plan, nullable)?,
+        QueryPlan::Exp { plan, exp } => operator::exp(plan, exp.f64()?)?,
         QueryPlan::Length { string, length } => operator::length(string, length),
+        QueryPlan::Lower { string, stringstore, lower } => {
+            operator::change_case(string.str()?, stringstore, lower.str()?, false)
+        }
+        QueryPlan::Upper { string, stringstore, upper } => {
+            operator::change_case(string.str()?, stringstore, upper.str()?, true)
+        }
         QueryPlan::Indices { plan, indices } => operator::indices(plan, indices),
         QueryPlan::SortBy {
             ranking,
             indices,
             desc,
+            nulls_first,
             stable,
             permutation,
-        } => operator::sort_by(ranking, indices, desc, stable, permutation)?,
+        } => operator::sort_by(ranking, indices, desc, nulls_first, stable, permutation)?,
         QueryPlan::TopN {
             ranking,
             n,
             desc,
+            with_ties,
             tmp_keys,
             top_n,
-        } => operator::top_n(ranking, tmp_keys, n, desc, top_n)?,
+        } => operator::top_n(ranking, tmp_keys, n, desc, with_ties, top_n)?,
         QueryPlan::Connect { input, output } => operator::identity(input, output),
         QueryPlan::Merge {
             lhs,
             rhs,
             limit,
             desc,
+            nulls_first,
             merge_ops,
             merged,
-        } => operator::merge(lhs, rhs, limit, desc, merge_ops, merged)?,
+        } => operator::merge(lhs, rhs, limit, desc, nulls_first, merge_ops, merged)?,
         QueryPlan::MergePartitioned {
             partitioning,
             lhs,
             rhs,
             limit,
             desc,
+            nulls_first,
             take_left,
             merged,
-        } => operator::merge_partitioned(partitioning, lhs, rhs, limit, desc, take_left, merged)?,
+        } => operator::merge_partitioned(partitioning, lhs, rhs, limit, desc, nulls_first, take_left, merged)?,
         QueryPlan::MergeDeduplicate {
             lhs,
             rhs,
@@ -1910,15 +3165,17 @@ pub(super) fn prepare<'a>(
             rhs,
             limit,
             desc,
+            nulls_first,
             partitioning,
-        } => operator::partition(lhs, rhs, limit, desc, partitioning)?,
+        } => operator::partition(lhs, rhs, limit, desc, nulls_first, partitioning)?,
         QueryPlan::Subpartition {
             partitioning,
             lhs,
             rhs,
             desc,
+            nulls_first,
             subpartitioning,
-        } => operator::subpartition(partitioning, lhs, rhs, desc, subpartitioning)?,
+        } => operator::subpartition(partitioning, lhs, rhs, desc, nulls_first, subpartitioning)?,
         QueryPlan::MergeDrop {
             merge_ops,
             lhs,
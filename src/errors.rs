@@ -17,6 +17,22 @@ pub enum QueryError {
     TypeError(String),
     #[fail(display = "Overflow or division by zero")]
     Overflow,
+    #[fail(display = "Query exceeded timeout")]
+    Timeout,
+    #[fail(display = "Query result exceeds the maximum of {} rows", _0)]
+    ResultTooLarge(usize),
+    #[fail(display = "Query exceeded the memory limit of {} bytes", _0)]
+    ResourceLimit(usize),
+    #[fail(display = "Query was cancelled")]
+    Cancelled,
+}
+
+#[derive(Fail, Debug, PartialEq, Eq)]
+pub enum IngestError {
+    #[fail(display = "Column name must not be empty")]
+    EmptyColumnName,
+    #[fail(display = "Column {} appears more than once in the same row", _0)]
+    DuplicateColumn(String),
 }
 
 #[macro_export]
@@ -1,19 +1,59 @@
 use crate::ingest::input_column::InputColumn;
 use crate::ingest::raw_val::RawVal;
 use crate::mem_store::raw_col::MixedCol;
+use ordered_float::OrderedFloat;
 use std::cmp;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::mem;
 
 #[derive(PartialEq, Debug, Clone, Default)]
 pub struct Buffer {
     pub buffer: HashMap<String, MixedCol>,
     pub length: usize,
+    /// Columns that have received a float value. Integers ingested into these columns are stored as floats.
+    float_columns: HashSet<String>,
+    /// Keys of the buffered rows, maintained incrementally by `contains_key`.
+    dedup_keys: Option<DedupKeys>,
+}
+
+#[derive(PartialEq, Debug, Clone, Default)]
+struct DedupKeys {
+    columns: Vec<String>,
+    /// Key columns whose integers are converted to floats, both in `keys` and in keys that are looked up.
+    promoted: Vec<bool>,
+    keys: HashSet<Vec<RawVal>>,
+    /// Number of buffered rows whose keys have been added to `keys`.
+    rows: usize,
+}
+
+impl DedupKeys {
+    fn new(columns: &[String]) -> DedupKeys {
+        DedupKeys { columns: columns.to_vec(), promoted: vec![false; columns.len()], ..DedupKeys::default() }
+    }
+
+    fn promote(&self, mut key: Vec<RawVal>) -> Vec<RawVal> {
+        for (val, &promoted) in key.iter_mut().zip(&self.promoted) {
+            if let RawVal::Int(i) = *val {
+                if promoted {
+                    *val = RawVal::Float(OrderedFloat(i as f64));
+                }
+            }
+        }
+        key
+    }
 }
 
 impl Buffer {
+    /// Removes and returns all buffered rows, remembering which columns have received floats.
+    pub fn take(&mut self) -> Buffer {
+        let float_columns = self.float_columns.clone();
+        mem::replace(self, Buffer { float_columns, ..Buffer::default() })
+    }
+
     pub fn push_row(&mut self, row: Vec<(String, RawVal)>) {
         let len = self.len();
         for (name, input_val) in row {
+            let input_val = self.unify_type(&name, input_val);
             let buffered_col = self
                 .buffer
                 .entry(name)
@@ -28,14 +68,25 @@ impl Buffer {
         let len = self.len();
         let mut new_length = 0;
         for (name, input_col) in columns {
+            let input_col = match input_col {
+                InputColumn::Int(vec) if self.float_columns.contains(&name) => {
+                    InputColumn::Float(vec.into_iter().map(|i| i as f64).collect())
+                }
+                InputColumn::Float(vec) => {
+                    self.float_columns.insert(name.clone());
+                    InputColumn::Float(vec)
+                }
+                _ => input_col,
+            };
             let buffered_col = self
                 .buffer
                 .entry(name)
                 .or_insert_with(|| MixedCol::with_nulls(len));
             match input_col {
-                InputColumn::Int(vec) => buffered_col.push_ints(vec),
+                InputColumn::Int(vec) | InputColumn::Timestamp(vec) => buffered_col.push_ints(vec),
                 InputColumn::Str(vec) => buffered_col.push_strings(vec),
                 InputColumn::Float(vec) => buffered_col.push_floats(vec),
+                InputColumn::Bool(vec) => buffered_col.push_bools(vec),
                 InputColumn::Null(c) => buffered_col.push_nulls(c),
             }
             new_length = cmp::max(new_length, buffered_col.len())
@@ -48,6 +99,10 @@ impl Buffer {
         let len = self.len();
         let mut new_length = 0;
         for (name, input_vals) in columns {
+            let input_vals = input_vals
+                .into_iter()
+                .map(|val| self.unify_type(&name, val))
+                .collect::<Vec<_>>();
             let buffered_col = self
                 .buffer
                 .entry(name)
@@ -61,6 +116,20 @@ impl Buffer {
         self.extend_to_largest();
     }
 
+    /// Converts integers to floats for columns that have received floats before.
+    fn unify_type(&mut self, name: &str, val: RawVal) -> RawVal {
+        match val {
+            RawVal::Float(_) => {
+                if !self.float_columns.contains(name) {
+                    self.float_columns.insert(name.to_string());
+                }
+                val
+            }
+            RawVal::Int(i) if self.float_columns.contains(name) => RawVal::Float(OrderedFloat(i as f64)),
+            _ => val,
+        }
+    }
+
     fn extend_to_largest(&mut self) {
         let target_length = self.length;
         for buffered_col in self.buffer.values_mut() {
@@ -71,6 +140,46 @@ impl Buffer {
         }
     }
 
+    /// Values of the `key` columns in row `index`, columns that are not buffered are null.
+    pub fn key(&self, index: usize, key: &[String]) -> Vec<RawVal> {
+        key.iter()
+            .map(|name| self.buffer.get(name).map_or(RawVal::Null, |col| col.get(index).clone()))
+            .collect()
+    }
+
+    /// Whether a buffered row has the values `key` in the `columns`. Keys that contain null never match.
+    /// Integers are equal to floats of the same value if the column has received floats, or will receive one with `key`.
+    pub fn contains_key(&mut self, key: &[RawVal], columns: &[String]) -> bool {
+        let mut dedup_keys = match self.dedup_keys.take() {
+            Some(dedup_keys) if dedup_keys.columns == columns => dedup_keys,
+            _ => DedupKeys::new(columns),
+        };
+        for index in dedup_keys.rows..self.len() {
+            let buffered = dedup_keys.promote(self.key(index, columns));
+            if !buffered.contains(&RawVal::Null) {
+                dedup_keys.keys.insert(buffered);
+            }
+        }
+        dedup_keys.rows = self.len();
+        let promoted = columns
+            .iter()
+            .zip(key)
+            .zip(&dedup_keys.promoted)
+            .map(|((name, val), &promoted)| {
+                promoted || self.float_columns.contains(name) || matches!(val, RawVal::Float(_))
+            })
+            .collect::<Vec<_>>();
+        if promoted != dedup_keys.promoted {
+            dedup_keys.promoted = promoted;
+            let keys = mem::take(&mut dedup_keys.keys);
+            dedup_keys.keys = keys.into_iter().map(|key| dedup_keys.promote(key)).collect();
+        }
+        let key = dedup_keys.promote(key.to_vec());
+        let contained = !key.contains(&RawVal::Null) && dedup_keys.keys.contains(&key);
+        self.dedup_keys = Some(dedup_keys);
+        contained
+    }
+
     pub fn len(&self) -> usize {
         self.length
     }
@@ -319,6 +319,7 @@ impl RawCol {
                 self.uhex,
                 self.string_bytes,
                 present,
+                None,
             )
         } else if self.types.contains_float {
             let mut builder = FloatColBuilder::default();
@@ -1,8 +1,14 @@
+use serde::{Deserialize, Serialize};
+
 #[allow(dead_code)]
+#[derive(Serialize, Deserialize)]
 pub enum InputColumn {
     Int(Vec<i64>),
-    Float(Vec<f64>),
+    Float(#[serde(with = "crate::ingest::wal::floats")] Vec<f64>),
     Str(Vec<String>),
+    Bool(Vec<bool>),
+    /// Unix timestamps in milliseconds.
+    Timestamp(Vec<i64>),
     Null(usize),
 }
 
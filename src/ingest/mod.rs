@@ -1,4 +1,6 @@
 pub mod csv_loader;
+#[cfg(feature = "enable_parquet")]
+pub mod parquet_loader;
 pub mod raw_val;
 pub mod input_column;
 pub mod buffer;
@@ -6,4 +8,5 @@ pub mod extractor;
 pub mod nyc_taxi_data;
 pub mod colgen;
 pub mod schema;
+pub mod wal;
 mod alias_method_fork;
\ No newline at end of file
@@ -0,0 +1,185 @@
+extern crate parquet;
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::mem;
+use std::path::Path;
+
+use ordered_float::OrderedFloat;
+
+use self::parquet::file::reader::{FileReader, SerializedFileReader};
+use self::parquet::record::Field;
+
+use crate::ingest::input_column::InputColumn;
+use crate::ingest::raw_val::RawVal;
+use crate::scheduler::InnerLocustDB;
+
+/// Number of rows passed to a single ingest call.
+const BATCH_SIZE: usize = 1 << 16;
+
+/// Ingests all rows of the Parquet file at `path` into `table`, returns the number of ingested rows.
+/// Dates and timestamps become timestamp columns, nested groups, lists, maps and decimals are not supported.
+pub fn ingest_file(ldb: &InnerLocustDB, path: &Path, table: &str) -> Result<usize, String> {
+    let file = File::open(path).map_err(|err| err.to_string())?;
+    let reader = SerializedFileReader::new(file).map_err(|err| err.to_string())?;
+    let colnames = reader
+        .metadata()
+        .file_metadata()
+        .schema_descr()
+        .root_schema()
+        .get_fields()
+        .iter()
+        .map(|field| field.name().to_string())
+        .collect::<Vec<_>>();
+    let mut columns = vec![Vec::with_capacity(BATCH_SIZE); colnames.len()];
+    let mut rows = 0;
+    for row in reader.get_row_iter(None).map_err(|err| err.to_string())? {
+        let row = row.map_err(|err| err.to_string())?;
+        for (column, (colname, field)) in columns.iter_mut().zip(row.get_column_iter()) {
+            column.push(convert_field(colname, field)?);
+        }
+        rows += 1;
+        if rows % BATCH_SIZE == 0 {
+            ingest_batch(ldb, table, &colnames, &mut columns);
+        }
+    }
+    if rows % BATCH_SIZE != 0 {
+        ingest_batch(ldb, table, &colnames, &mut columns);
+    }
+    Ok(rows)
+}
+
+/// Ingests the buffered `columns` with their types if every column has a single type, e.g. contains no nulls.
+fn ingest_batch(ldb: &InnerLocustDB, table: &str, colnames: &[String], columns: &mut [Vec<RawVal>]) {
+    let columns = colnames
+        .iter()
+        .cloned()
+        .zip(columns.iter_mut().map(mem::take))
+        .collect::<HashMap<_, _>>();
+    if columns.values().all(|values| is_homogeneous(values)) {
+        let columns = columns
+            .into_iter()
+            .map(|(colname, values)| (colname, input_column(values)))
+            .collect();
+        ldb.ingest_homogeneous(table, columns);
+    } else {
+        ldb.ingest_heterogeneous(table, columns);
+    }
+}
+
+fn is_homogeneous(values: &[RawVal]) -> bool {
+    match values.first() {
+        Some(first) => values
+            .iter()
+            .all(|value| mem::discriminant(value) == mem::discriminant(first)),
+        None => true,
+    }
+}
+
+/// Converts values that all have the same type into the corresponding `InputColumn`.
+fn input_column(values: Vec<RawVal>) -> InputColumn {
+    match values.first() {
+        Some(RawVal::Int(_)) => InputColumn::Int(values.into_iter().map(|v| match v { RawVal::Int(i) => i, _ => unreachable!() }).collect()),
+        Some(RawVal::Float(_)) => InputColumn::Float(values.into_iter().map(|v| match v { RawVal::Float(f) => f.into_inner(), _ => unreachable!() }).collect()),
+        Some(RawVal::Str(_)) => InputColumn::Str(values.into_iter().map(|v| match v { RawVal::Str(s) => s, _ => unreachable!() }).collect()),
+        Some(RawVal::Bool(_)) => InputColumn::Bool(values.into_iter().map(|v| match v { RawVal::Bool(b) => b, _ => unreachable!() }).collect()),
+        Some(RawVal::Timestamp(_)) => InputColumn::Timestamp(values.into_iter().map(|v| match v { RawVal::Timestamp(t) => t, _ => unreachable!() }).collect()),
+        Some(RawVal::Null) | None => InputColumn::Null(values.len()),
+    }
+}
+
+fn convert_field(colname: &str, field: &Field) -> Result<RawVal, String> {
+    Ok(match *field {
+        Field::Null => RawVal::Null,
+        Field::Bool(b) => RawVal::Bool(b),
+        Field::Byte(i) => RawVal::Int(i64::from(i)),
+        Field::Short(i) => RawVal::Int(i64::from(i)),
+        Field::Int(i) => RawVal::Int(i64::from(i)),
+        Field::Long(i) => RawVal::Int(i),
+        Field::UByte(i) => RawVal::Int(i64::from(i)),
+        Field::UShort(i) => RawVal::Int(i64::from(i)),
+        Field::UInt(i) => RawVal::Int(i64::from(i)),
+        Field::ULong(i) => RawVal::Int(
+            i64::try_from(i).map_err(|_| format!("Value {} of column {} does not fit into a 64-bit signed integer", i, colname))?,
+        ),
+        Field::Float16(f) => RawVal::Float(OrderedFloat(f64::from(f))),
+        Field::Float(f) => RawVal::Float(OrderedFloat(f64::from(f))),
+        Field::Double(f) => RawVal::Float(OrderedFloat(f)),
+        Field::Str(ref s) => RawVal::Str(s.clone()),
+        Field::Bytes(ref bytes) => match bytes.as_utf8() {
+            Ok(s) => RawVal::Str(s.to_string()),
+            Err(_) => return Err(format!("Column {} contains binary data that is not valid UTF-8", colname)),
+        },
+        // Timestamps are stored as unix milliseconds
+        Field::Date(days) => RawVal::Timestamp(i64::from(days) * 24 * 60 * 60 * 1000),
+        Field::TimestampMillis(millis) => RawVal::Timestamp(millis),
+        Field::TimestampMicros(micros) => RawVal::Timestamp(micros.div_euclid(1000)),
+        Field::Decimal(_) => return Err(format!("Decimal column {} is not supported", colname)),
+        Field::Group(_) | Field::ListInternal(_) | Field::MapInternal(_) => {
+            return Err(format!("Nested column {} is not supported", colname))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures::executor::block_on;
+
+    use super::parquet::data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type};
+    use super::parquet::file::properties::WriterProperties;
+    use super::parquet::file::writer::SerializedFileWriter;
+    use super::parquet::schema::parser::parse_message_type;
+    use super::*;
+    use crate::LocustDB;
+
+    #[test]
+    fn test_load_parquet() {
+        let schema = Arc::new(
+            parse_message_type(
+                "message test {
+                    REQUIRED INT64 id;
+                    OPTIONAL DOUBLE score;
+                    REQUIRED BYTE_ARRAY name (UTF8);
+                    REQUIRED INT64 ts (TIMESTAMP(MICROS, true));
+                }",
+            )
+            .unwrap(),
+        );
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let props = Arc::new(WriterProperties::builder().build());
+        let mut writer = SerializedFileWriter::new(file.reopen().unwrap(), schema, props).unwrap();
+        let mut row_group = writer.next_row_group().unwrap();
+        let mut column = row_group.next_column().unwrap().unwrap();
+        column.typed::<Int64Type>().write_batch(&[1, 2, 3], None, None).unwrap();
+        column.close().unwrap();
+        let mut column = row_group.next_column().unwrap().unwrap();
+        column.typed::<DoubleType>().write_batch(&[0.5, 1.5], Some(&[1, 0, 1]), None).unwrap();
+        column.close().unwrap();
+        let mut column = row_group.next_column().unwrap().unwrap();
+        let names = ["a", "b", "c"].iter().map(|&s| ByteArray::from(s)).collect::<Vec<_>>();
+        column.typed::<ByteArrayType>().write_batch(&names, None, None).unwrap();
+        column.close().unwrap();
+        let mut column = row_group.next_column().unwrap().unwrap();
+        column.typed::<Int64Type>().write_batch(&[1_000, 2_000_000, -1], None, None).unwrap();
+        column.close().unwrap();
+        row_group.close().unwrap();
+        writer.close().unwrap();
+
+        let locustdb = LocustDB::memory_only();
+        assert_eq!(block_on(locustdb.load_parquet("t", file.path())).unwrap(), 3);
+        let result = block_on(locustdb.run_query("SELECT id, score, name, ts FROM t ORDER BY id;", false, vec![], None))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            result.rows,
+            vec![
+                vec![RawVal::Int(1), RawVal::Float(OrderedFloat(0.5)), RawVal::Str("a".to_string()), RawVal::Timestamp(1)],
+                vec![RawVal::Int(2), RawVal::Null, RawVal::Str("b".to_string()), RawVal::Timestamp(2_000)],
+                vec![RawVal::Int(3), RawVal::Float(OrderedFloat(1.5)), RawVal::Str("c".to_string()), RawVal::Timestamp(-1)],
+            ]
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use std::fmt;
 use std::mem;
 
+use chrono::NaiveDateTime;
 use ordered_float::OrderedFloat;
 use serde::{Deserialize, Serialize};
 
@@ -9,9 +10,12 @@ use crate::engine::data_types::BasicType;
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash, Serialize, Deserialize)]
 pub enum RawVal {
     Int(i64),
-    Float(OrderedFloat<f64>),
+    Float(#[serde(with = "crate::ingest::wal::float")] OrderedFloat<f64>),
     Str(String),
     Null,
+    Bool(bool),
+    /// Unix timestamp in milliseconds, formatted as ISO-8601 in query results.
+    Timestamp(i64),
 }
 
 impl RawVal {
@@ -21,6 +25,8 @@ impl RawVal {
             RawVal::Str(_) => BasicType::String,
             RawVal::Null => BasicType::Null,
             RawVal::Float(_) => BasicType::Float,
+            RawVal::Bool(_) => BasicType::Boolean,
+            RawVal::Timestamp(_) => BasicType::Integer,
         }
     }
 
@@ -30,10 +36,21 @@ impl RawVal {
             RawVal::Str(ref s) => s.capacity() * mem::size_of::<u8>(),
             RawVal::Null => 0,
             RawVal::Float(_) => 0,
+            RawVal::Bool(_) => 0,
+            RawVal::Timestamp(_) => 0,
         }
     }
 }
 
+/// Formats a unix timestamp in milliseconds as ISO-8601 in UTC, e.g. `2021-12-20T18:33:17.013Z`.
+/// Timestamps outside the range of representable dates are formatted as integers.
+pub fn format_timestamp(millis: i64) -> String {
+    match NaiveDateTime::from_timestamp_millis(millis) {
+        Some(datetime) => datetime.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+        None => millis.to_string(),
+    }
+}
+
 impl fmt::Display for RawVal {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -41,12 +58,14 @@ impl fmt::Display for RawVal {
             RawVal::Int(i) => write!(f, "{}", i),
             RawVal::Str(ref s) => write!(f, "\"{}\"", s),
             RawVal::Float(x) => write!(f, "{:e}", x),
+            RawVal::Bool(b) => write!(f, "{}", b),
+            RawVal::Timestamp(t) => write!(f, "{}", format_timestamp(t)),
         }
     }
 }
 
 pub mod syntax {
-    pub use super::RawVal::{Int, Null, Float};
+    pub use super::RawVal::{Int, Null, Float, Bool, Timestamp};
 
     #[allow(non_snake_case)]
     pub fn Str(s: &str) -> super::RawVal {
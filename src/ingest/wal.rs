@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use ordered_float::OrderedFloat;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::ingest::input_column::InputColumn;
+use crate::ingest::raw_val::RawVal;
+
+/// Append-only log of the rows ingested into a table that have not been stored as a partition yet.
+/// Each entry is written as a single line of JSON, a partially written last line is ignored on replay.
+pub struct Wal {
+    path: PathBuf,
+    writer: Mutex<BufWriter<File>>,
+    /// Whether every append is synced to disk, otherwise `sync` has to be called periodically.
+    sync_on_append: bool,
+}
+
+/// Single ingest call recorded in the write-ahead log.
+#[derive(Serialize, Deserialize)]
+pub enum WalEntry {
+    Rows(Vec<Vec<(String, RawVal)>>),
+    TypedColumns(HashMap<String, InputColumn>),
+    UntypedColumns(HashMap<String, Vec<RawVal>>),
+}
+
+/// Borrowed counterpart of `WalEntry` that avoids copying ingested data before it is written.
+#[derive(Serialize)]
+pub enum WalRecord<'a> {
+    Rows(&'a [Vec<(String, RawVal)>]),
+    TypedColumns(&'a HashMap<String, InputColumn>),
+    UntypedColumns(&'a HashMap<String, Vec<RawVal>>),
+}
+
+const EXTENSION: &str = "wal";
+
+/// JSON has no representation for NaN and infinities, so non-finite floats are written as strings.
+struct Float(f64);
+
+impl Serialize for Float {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.0.is_finite() {
+            serializer.serialize_f64(self.0)
+        } else {
+            serializer.serialize_str(&self.0.to_string())
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Float {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Float, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Finite(f64),
+            NonFinite(String),
+        }
+        match Repr::deserialize(deserializer)? {
+            Repr::Finite(value) => Ok(Float(value)),
+            Repr::NonFinite(value) => value.parse().map(Float).map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// Serde helpers for `RawVal::Float` that preserve non-finite values.
+pub mod float {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(value: &OrderedFloat<f64>, serializer: S) -> Result<S::Ok, S::Error> {
+        Float(value.0).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<OrderedFloat<f64>, D::Error> {
+        Float::deserialize(deserializer).map(|f| OrderedFloat(f.0))
+    }
+}
+
+/// Serde helpers for `InputColumn::Float` that preserve non-finite values.
+pub mod floats {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(values: &[f64], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(values.iter().map(|&v| Float(v)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<f64>, D::Error> {
+        Vec::<Float>::deserialize(deserializer).map(|values| values.into_iter().map(|f| f.0).collect())
+    }
+}
+
+impl Wal {
+    /// Opens the log of `table` in the directory `dir`, appending to any existing entries.
+    pub fn open(dir: &Path, table: &str, sync_on_append: bool) -> io::Result<Wal> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.{}", hex::encode(table), EXTENSION));
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Wal {
+            path,
+            writer: Mutex::new(BufWriter::new(file)),
+            sync_on_append,
+        })
+    }
+
+    /// Reads the entries of all logs in `dir` by table name, in the order in which they were appended.
+    pub fn replay(dir: &Path) -> io::Result<HashMap<String, Vec<WalEntry>>> {
+        let mut tables = HashMap::new();
+        if !dir.exists() {
+            return Ok(tables);
+        }
+        for file in fs::read_dir(dir)? {
+            let path = file?.path();
+            if path.extension().map_or(true, |ext| ext != EXTENSION) {
+                continue;
+            }
+            let table = match path
+                .file_stem()
+                .and_then(|stem| hex::decode(stem.to_string_lossy().as_ref()).ok())
+                .and_then(|name| String::from_utf8(name).ok())
+            {
+                Some(table) => table,
+                None => {
+                    warn!("Ignoring unexpected file {:?} in WAL directory", path);
+                    continue;
+                }
+            };
+            let mut entries = Vec::new();
+            for line in BufReader::new(File::open(&path)?).lines() {
+                match serde_json::from_str(&line?) {
+                    Ok(entry) => entries.push(entry),
+                    Err(err) => {
+                        warn!("Stopping replay of {:?} at invalid entry: {}", path, err);
+                        break;
+                    }
+                }
+            }
+            tables.insert(table, entries);
+        }
+        Ok(tables)
+    }
+
+    pub fn append(&self, record: &WalRecord) {
+        let mut writer = self.writer.lock().unwrap();
+        serde_json::to_writer(&mut *writer, record).expect("Failed to write WAL entry");
+        writer.write_all(b"\n").expect("Failed to write WAL entry");
+        if self.sync_on_append {
+            Wal::sync_writer(&mut writer);
+        }
+    }
+
+    /// Writes all appended entries to disk.
+    pub fn sync(&self) {
+        Wal::sync_writer(&mut self.writer.lock().unwrap());
+    }
+
+    /// Discards all entries, called once the logged rows have been persisted as a partition.
+    pub fn truncate(&self) {
+        let mut writer = self.writer.lock().unwrap();
+        writer.flush().expect("Failed to flush WAL");
+        writer.get_ref().set_len(0).expect("Failed to truncate WAL");
+        writer.get_ref().sync_data().expect("Failed to sync WAL");
+    }
+
+    pub fn delete(self) {
+        drop(self.writer);
+        if let Err(err) = fs::remove_file(&self.path) {
+            warn!("Failed to delete WAL {:?}: {}", self.path, err);
+        }
+    }
+
+    fn sync_writer(writer: &mut BufWriter<File>) {
+        writer.flush().expect("Failed to flush WAL");
+        writer.get_ref().sync_data().expect("Failed to sync WAL");
+    }
+}
@@ -15,18 +15,26 @@ extern crate lazy_static;
 #[macro_use]
 extern crate log;
 
+pub use crate::disk_store::interface::{DiskCompression, TableDiskUsage};
 pub use crate::disk_store::noop_storage::NoopStorage;
-pub use crate::engine::query_task::QueryOutput;
-pub use crate::errors::QueryError;
+pub use crate::engine::{Aggregator, ColumnInfo, Query};
+pub use crate::engine::query_task::{QueryOutput, QueryStream};
+pub use crate::errors::{IngestError, QueryError};
 pub use crate::ingest::colgen;
 pub use crate::ingest::csv_loader::Options as LoadOptions;
 pub use crate::ingest::extractor;
+pub use crate::ingest::input_column::InputColumn;
 pub use crate::ingest::nyc_taxi_data;
 pub use crate::ingest::raw_val::syntax as value_syntax;
 pub use crate::ingest::raw_val::RawVal as Value;
 pub use crate::locustdb::LocustDB;
 pub use crate::locustdb::Options;
-pub use crate::mem_store::table::TableStats;
+pub use crate::mem_store::column_builder::ColumnEncoding;
+pub use crate::mem_store::table::{ColumnTypes, TableStats};
+pub use crate::query_builder::QueryBuilder;
+pub use crate::scheduler::{DiskReadStats, ResultCacheStats, RunningQueryInfo};
+pub use crate::syntax::expression::{Expr, Func1Type, Func2Type};
+pub use crate::syntax::limit::LimitClause;
 
 #[macro_use]
 mod errors;
@@ -37,6 +45,7 @@ mod ingest;
 mod locustdb;
 pub mod logging_client;
 mod mem_store;
+mod query_builder;
 mod scheduler;
 pub mod server;
 mod stringpack;
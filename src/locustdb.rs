@@ -1,22 +1,32 @@
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::path::{Path, PathBuf};
 use std::str;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use futures::channel::oneshot;
+use futures::channel::{mpsc, oneshot};
 
 use crate::disk_store::interface::*;
 use crate::disk_store::noop_storage::NoopStorage;
-use crate::engine::query_task::QueryTask;
+use crate::engine::query_task::{QueryOutput, QueryStats, QueryStream, QueryTask};
+use crate::engine::Query;
 use crate::ingest::colgen::GenTable;
 use crate::ingest::csv_loader::{CSVIngestionTask, Options as LoadOptions};
+use crate::ingest::input_column::InputColumn;
 use crate::ingest::raw_val::RawVal;
+use crate::mem_store::column_builder::ColumnEncoding;
+use crate::mem_store::table::validate_rows;
 use crate::mem_store::*;
+use crate::query_builder::QueryBuilder;
 use crate::scheduler::*;
-use crate::syntax::parser;
-use crate::QueryError;
+use crate::syntax::parser::{self, ParsedQuery};
+use crate::{IngestError, QueryError};
 use crate::QueryResult;
 
+/// Maximum number of values returned by the subquery of `expr IN (SELECT ...)` if `Options::max_result_rows` is not set.
+pub const MAX_SUBQUERY_ROWS: usize = 100_000;
+
 // Cannot implement Clone on LocustDB without changing Drop implementation.
 pub struct LocustDB {
     inner_locustdb: Arc<InnerLocustDB>,
@@ -31,7 +41,7 @@ impl LocustDB {
         let disk_store = opts
             .db_path
             .as_ref()
-            .map(LocustDB::persistent_storage)
+            .map(|db_path| LocustDB::persistent_storage(db_path, opts.disk_compression))
             .unwrap_or_else(|| Arc::new(NoopStorage));
         let locustdb = Arc::new(InnerLocustDB::new(disk_store, opts));
         InnerLocustDB::start_worker_threads(&locustdb);
@@ -40,29 +50,311 @@ impl LocustDB {
         }
     }
 
+    /// Runs a query, completed queries are recorded in the `_meta_queries` table.
+    /// Rows removed by `DELETE` are excluded from subsequent queries and stay deleted after a restart, but they keep
+    /// occupying memory and disk and are included in table statistics until background compaction removes them.
     pub async fn run_query(
         &self,
         query: &str,
         explain: bool,
         show: Vec<usize>,
+        timeout: Option<Duration>,
     ) -> Result<QueryResult, oneshot::Canceled> {
-        let (sender, receiver) = oneshot::channel();
+        let result = self.execute_query(query, explain, show, timeout).await?;
+        if let Ok(output) = &result {
+            self.record_query(query, output);
+        }
+        Ok(result)
+    }
 
-        // PERF: perform compilation and table snapshot in asynchronous task?
-        let query = match parser::parse_query(query) {
-            Ok(query) => query,
+    /// Runs `;`-separated statements one after another and returns the output of each statement.
+    /// Stops at the first statement that fails and returns its index together with the error.
+    pub async fn run_statements(
+        &self,
+        statements: &str,
+        explain: bool,
+        timeout: Option<Duration>,
+    ) -> Result<Result<Vec<QueryOutput>, (usize, QueryError)>, oneshot::Canceled> {
+        let statements = match parser::split_statements(statements) {
+            Ok(statements) => statements,
+            Err(err) => return Ok(Err((0, err))),
+        };
+        let mut outputs = Vec::with_capacity(statements.len());
+        for (i, statement) in statements.iter().enumerate() {
+            match self.run_query(statement, explain, vec![], timeout).await? {
+                Ok(output) => outputs.push(output),
+                Err(err) => return Ok(Err((i, err))),
+            }
+        }
+        Ok(Ok(outputs))
+    }
+
+    /// Returns the most recently completed queries, newest first.
+    pub async fn query_history(&self, limit: u64) -> Result<QueryResult, oneshot::Canceled> {
+        self.inner_locustdb.create_table("_meta_queries", None);
+        let query = format!(
+            "SELECT timestamp_ns, query, runtime_ns, rows FROM _meta_queries ORDER BY timestamp_ns DESC LIMIT {};",
+            limit
+        );
+        self.execute_query(&query, false, vec![], None).await
+    }
+
+    /// Queries that are currently executing, oldest first.
+    pub fn running_queries(&self) -> Vec<RunningQueryInfo> {
+        self.inner_locustdb.running_queries().list()
+    }
+
+    /// Aborts the running query with the given id, which then fails with `QueryError::Cancelled`.
+    /// Returns false if there is no running query with that id.
+    pub fn cancel_query(&self, id: u64) -> bool {
+        self.inner_locustdb.running_queries().cancel(id)
+    }
+
+    /// Capacity, number of entries and hit rate of the query result cache.
+    pub fn cache_stats(&self) -> ResultCacheStats {
+        self.inner_locustdb.result_cache().stats()
+    }
+
+    /// Returns the output of a previous execution of the same query text if none of the queried tables have changed since.
+    async fn execute_query(
+        &self,
+        query: &str,
+        explain: bool,
+        show: Vec<usize>,
+        timeout: Option<Duration>,
+    ) -> Result<QueryResult, oneshot::Canceled> {
+        let registration = Arc::new(self.inner_locustdb.register_query(query));
+        let parsed = match parser::parse(query) {
+            Ok(parsed) => parsed,
             Err(err) => return Ok(Err(err)),
         };
+        let cache = self.inner_locustdb.result_cache();
+        let table_versions = if cache.is_enabled() && parsed.is_cacheable() && !explain && show.is_empty() {
+            let table_versions = self.inner_locustdb.table_versions(&parsed.tables());
+            if let Some(output) = cache.get(query, &table_versions) {
+                return Ok(Ok(output));
+            }
+            Some(table_versions)
+        } else {
+            None
+        };
+        let result = self.execute_parsed(parsed, explain, show, timeout, registration).await?;
+        if let (Ok(output), Some(table_versions)) = (&result, table_versions) {
+            cache.put(query, table_versions, output);
+        }
+        Ok(result)
+    }
+
+    async fn execute_parsed(
+        &self,
+        parsed: ParsedQuery,
+        explain: bool,
+        show: Vec<usize>,
+        timeout: Option<Duration>,
+        registration: Arc<QueryRegistration>,
+    ) -> Result<QueryResult, oneshot::Canceled> {
+        match parsed {
+            ParsedQuery::Select(query) => {
+                self.execute_registered_query(query, explain, show, timeout, registration).await
+            }
+            ParsedQuery::UnionAll(union) => {
+                let outputs = self.execute_queries(&union.queries, explain, show, timeout, registration).await?;
+                Ok(outputs.and_then(|outputs| union.merge_outputs(outputs)))
+            }
+            ParsedQuery::Join(join) => {
+                let outputs = self.execute_queries(&join.queries, explain, show, timeout, registration).await?;
+                Ok(outputs.and_then(|outputs| {
+                    let [left, right]: [QueryOutput; 2] = outputs.try_into().unwrap();
+                    join.join_outputs(left, right)
+                }))
+            }
+            ParsedQuery::WithSubqueries(mut query, subqueries) => {
+                // The values returned by a subquery are limited to bound the size of the membership test
+                let max_rows = self.inner_locustdb.opts().max_result_rows.unwrap_or(MAX_SUBQUERY_ROWS);
+                let queries = subqueries
+                    .iter()
+                    .map(|subquery| {
+                        let mut query = subquery.query.clone();
+                        query.limit.limit = query.limit.limit.min(max_rows as u64 + 1);
+                        query
+                    })
+                    .collect::<Vec<_>>();
+                let outputs = match self.execute_queries(&queries, false, vec![], timeout, registration.clone()).await? {
+                    Ok(outputs) => outputs,
+                    Err(err) => return Ok(Err(err)),
+                };
+                for (subquery, output) in subqueries.iter().zip(outputs) {
+                    if output.rows.len() > max_rows {
+                        return Ok(Err(QueryError::ResultTooLarge(max_rows)));
+                    }
+                    let values = output.rows.into_iter().map(|mut row| row.pop().unwrap()).collect();
+                    query.filter = match subquery.substitute(query.filter, values) {
+                        Ok(filter) => filter,
+                        Err(err) => return Ok(Err(err)),
+                    };
+                }
+                self.execute_registered_query(query, explain, show, timeout, registration).await
+            }
+            ParsedQuery::ShowTables => {
+                let inner = self.inner_locustdb.clone();
+                let (task, receiver) = <dyn Task>::from_fn(move || Ok(inner.show_tables()));
+                self.schedule(task);
+                receiver.await
+            }
+            ParsedQuery::ShowColumns(table) => {
+                let inner = self.inner_locustdb.clone();
+                let (task, receiver) = <dyn Task>::from_fn(move || inner.show_columns(&table));
+                self.schedule(task);
+                receiver.await
+            }
+            ParsedQuery::Delete { table, filter } => {
+                let inner = self.inner_locustdb.clone();
+                let (task, receiver) = <dyn Task>::from_fn(move || {
+                    inner.delete_rows(&table, &filter).map(|deleted| QueryOutput {
+                        colnames: vec!["deleted".to_string()],
+                        coltypes: vec!["integer".to_string()],
+                        rows: vec![vec![RawVal::Int(deleted as i64)]],
+                        query_plans: HashMap::default(),
+                        stats: QueryStats::default(),
+                    })
+                });
+                self.schedule(task);
+                receiver.await
+            }
+        }
+    }
+
+    /// Runs multiple queries concurrently and returns their outputs in order.
+    async fn execute_queries(
+        &self,
+        queries: &[Query],
+        explain: bool,
+        show: Vec<usize>,
+        timeout: Option<Duration>,
+        registration: Arc<QueryRegistration>,
+    ) -> Result<Result<Vec<QueryOutput>, QueryError>, oneshot::Canceled> {
+        let mut receivers = Vec::with_capacity(queries.len());
+        for query in queries {
+            let (sender, receiver) = oneshot::channel();
+            let sender = SharedSender::new(sender);
+            match self.query_task(query.clone(), explain, show.clone(), timeout, sender, registration.clone()) {
+                Ok(task) => {
+                    self.schedule(task);
+                    receivers.push(receiver);
+                }
+                Err(err) => return Ok(Err(err)),
+            }
+        }
+        let mut outputs = Vec::with_capacity(receivers.len());
+        for receiver in receivers {
+            match receiver.await? {
+                Ok(output) => outputs.push(output),
+                Err(err) => return Ok(Err(err)),
+            }
+        }
+        Ok(Ok(outputs))
+    }
+
+    /// Runs a query that has already been parsed or was constructed with `QueryBuilder`.
+    pub(crate) async fn execute_parsed_query(
+        &self,
+        query: Query,
+        explain: bool,
+        show: Vec<usize>,
+        timeout: Option<Duration>,
+    ) -> Result<QueryResult, oneshot::Canceled> {
+        let registration = Arc::new(self.inner_locustdb.register_query(&format!("{:?}", query)));
+        self.execute_registered_query(query, explain, show, timeout, registration).await
+    }
+
+    async fn execute_registered_query(
+        &self,
+        query: Query,
+        explain: bool,
+        show: Vec<usize>,
+        timeout: Option<Duration>,
+        registration: Arc<QueryRegistration>,
+    ) -> Result<QueryResult, oneshot::Canceled> {
+        let (sender, receiver) = oneshot::channel();
+        match self.query_task(query, explain, show, timeout, SharedSender::new(sender), registration) {
+            Ok(task) => {
+                self.schedule(task);
+                Ok(receiver.await?)
+            }
+            Err(err) => Ok(Err(err)),
+        }
+    }
+
+    /// Starts building a query that selects `columns` without writing SQL, e.g.
+    /// `db.select(&["country"]).column(Expr::count(), "count").from("users").execute()`.
+    pub fn select(&self, columns: &[&str]) -> QueryBuilder {
+        QueryBuilder::new(self).select(columns)
+    }
 
+    /// Ingests `query` into `_meta_queries` on a worker thread to avoid delaying the query result.
+    fn record_query(&self, query: &str, output: &QueryOutput) {
+        let timestamp_ns = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos() as i64;
+        let row = vec![
+            ("timestamp_ns".to_string(), RawVal::Int(timestamp_ns)),
+            ("query".to_string(), RawVal::Str(query.to_string())),
+            ("runtime_ns".to_string(), RawVal::Int(output.stats.runtime_ns as i64)),
+            ("rows".to_string(), RawVal::Int(output.rows.len() as i64)),
+        ];
+        let inner = self.inner_locustdb.clone();
+        let (task, _) = <dyn Task>::from_fn(move || inner.ingest("_meta_queries", row.clone()));
+        self.schedule(task);
+    }
+
+    /// Runs a query and returns its rows as a stream, unordered selects emit rows as soon as each partition is processed.
+    pub fn run_query_streaming(
+        &self,
+        query: &str,
+        timeout: Option<Duration>,
+    ) -> Result<QueryStream, QueryError> {
+        let (sender, result) = oneshot::channel();
+        let (row_sink, rows) = mpsc::unbounded();
+        let registration = Arc::new(self.inner_locustdb.register_query(query));
+        let task = self
+            .query_task(parser::parse_query(query)?, false, vec![], timeout, SharedSender::new(sender), registration)?
+            .with_row_sink(row_sink);
+        let colnames = task.output_colnames().to_vec();
+        self.schedule(task);
+        Ok(QueryStream {
+            colnames,
+            rows,
+            result,
+        })
+    }
+
+    fn query_task(
+        &self,
+        mut query: Query,
+        explain: bool,
+        show: Vec<usize>,
+        timeout: Option<Duration>,
+        sender: SharedSender<QueryResult>,
+        registration: Arc<QueryRegistration>,
+    ) -> Result<QueryTask, QueryError> {
+        // PERF: perform compilation and table snapshot in asynchronous task?
         let mut data = match self.inner_locustdb.snapshot(&query.table) {
             Some(data) => data,
             None => {
-                return Ok(Err(QueryError::NotImplemented(format!(
+                return Err(QueryError::NotImplemented(format!(
                     "Table {} does not exist!",
                     &query.table
-                ))))
+                )))
             }
         };
+        let mut scanned = Vec::with_capacity(data.len());
+        for partition in data {
+            if query.scans_partition(partition.id)? {
+                scanned.push(partition);
+            }
+        }
+        data = scanned;
+
+        let timestamp_columns = self.inner_locustdb.timestamp_columns(&query.table);
+        let timestamp_outputs = query.resolve_timestamps(&timestamp_columns);
 
         if self.inner_locustdb.opts().seq_disk_read {
             self.inner_locustdb
@@ -78,22 +370,19 @@ impl LocustDB {
             self.inner_locustdb.schedule(read_data);
         }
 
-        let query_task = QueryTask::new(
+        Ok(QueryTask::new(
             query,
             explain,
             show,
             data,
+            timeout,
             self.inner_locustdb.disk_read_scheduler().clone(),
-            SharedSender::new(sender),
-        );
-
-        match query_task {
-            Ok(task) => {
-                self.schedule(task);
-                Ok(receiver.await?)
-            }
-            Err(err) => Ok(Err(err)),
-        }
+            sender,
+        )?
+        .with_max_result_rows(self.inner_locustdb.opts().max_result_rows)
+        .with_memory_limit(self.inner_locustdb.opts().query_memory_limit)
+        .with_registration(registration)
+        .with_timestamp_outputs(&timestamp_outputs))
     }
 
     pub async fn load_csv(&self, options: LoadOptions) -> Result<(), Box<dyn Error>> {
@@ -107,12 +396,68 @@ impl LocustDB {
         Ok(receiver.await??)
     }
 
-    pub async fn ingest(&self, table: &str, rows: Vec<Vec<(String, RawVal)>>) {
+    /// Ingests `rows` into `table`.
+    /// If any row is invalid, no rows are ingested and the index and error of each invalid row are returned.
+    /// Ingests all rows of the Parquet file at `path` into `table`, returns the number of ingested rows.
+    #[cfg(feature = "enable_parquet")]
+    pub async fn load_parquet<P: AsRef<Path>>(&self, table: &str, path: P) -> Result<usize, Box<dyn Error>> {
+        let inner = self.inner_locustdb.clone();
+        let table = table.to_string();
+        let path = path.as_ref().to_path_buf();
+        let (task, receiver) =
+            <dyn Task>::from_fn(move || crate::ingest::parquet_loader::ingest_file(&inner, &path, &table));
+        self.schedule(task);
+        Ok(receiver.await??)
+    }
+
+    #[cfg(not(feature = "enable_parquet"))]
+    pub async fn load_parquet<P: AsRef<Path>>(&self, _: &str, _: P) -> Result<usize, Box<dyn Error>> {
+        Err("Parquet support is not enabled in this build of LocustDB. Set the `enable_parquet` feature.".into())
+    }
+
+    pub async fn ingest(&self, table: &str, rows: Vec<Vec<(String, RawVal)>>) -> Result<(), Vec<(usize, IngestError)>> {
+        validate_rows(&rows)?;
         // TODO: efficiency
         // TODO: async
-        for row in rows {
-            self.inner_locustdb.ingest(table, row);
+        for (i, row) in rows.into_iter().enumerate() {
+            self.inner_locustdb.ingest(table, row).map_err(|err| vec![(i, err)])?;
         }
+        Ok(())
+    }
+
+    /// Ingests `rows`, skipping rows whose values in the `dedup_on` columns match a buffered row or an
+    /// earlier row of `rows`. Returns the number of skipped rows.
+    /// Only rows that have not yet been turned into a partition are checked, see `Table::ingest_dedup`.
+    pub async fn ingest_dedup(
+        &self,
+        table: &str,
+        rows: Vec<Vec<(String, RawVal)>>,
+        dedup_on: &[String],
+    ) -> Result<usize, Vec<(usize, IngestError)>> {
+        self.inner_locustdb.ingest_dedup(table, rows, dedup_on)
+    }
+
+    pub async fn ingest_homogeneous(&self, table: &str, columns: HashMap<String, InputColumn>) {
+        self.inner_locustdb.ingest_homogeneous(table, columns);
+    }
+
+    pub async fn ingest_heterogeneous(&self, table: &str, columns: HashMap<String, Vec<RawVal>>) {
+        self.inner_locustdb.ingest_heterogeneous(table, columns);
+    }
+
+    /// Creates an empty table which buffers `batch_size` rows before creating a new partition.
+    /// Defaults to the batch size configured in `Options`, returns false if the table already exists.
+    pub async fn create_table(&self, table: &str, batch_size: Option<usize>) -> bool {
+        self.inner_locustdb.create_table(table, batch_size)
+    }
+
+    pub async fn table_exists(&self, table: &str) -> bool {
+        self.inner_locustdb.table_exists(table)
+    }
+
+    /// Drops `table` and all of its data, returns false if the table does not exist.
+    pub async fn drop_table(&self, table: &str) -> Result<bool, QueryError> {
+        self.inner_locustdb.drop_table(table)
     }
 
     pub async fn gen_table(&self, opts: GenTable) -> Result<(), oneshot::Canceled> {
@@ -133,8 +478,18 @@ impl LocustDB {
     }
 
     pub fn ast(&self, query: &str) -> String {
-        match parser::parse_query(query) {
-            Ok(query) => format!("{:#?}", query),
+        match parser::parse(query) {
+            Ok(ParsedQuery::Select(query)) => format!("{:#?}", query),
+            Ok(ParsedQuery::UnionAll(union)) => format!("{:#?}", union),
+            Ok(ParsedQuery::Join(join)) => format!("{:#?}", join),
+            Ok(
+                other @ (ParsedQuery::ShowTables
+                | ParsedQuery::ShowColumns(_)
+                | ParsedQuery::WithSubqueries(..)
+                | ParsedQuery::Delete { .. }),
+            ) => {
+                format!("{:#?}", other)
+            }
             Err(err) => format!("{:?}", err),
         }
     }
@@ -146,7 +501,7 @@ impl LocustDB {
                 .schedule_bulk_load(table, self.inner_locustdb.opts().readahead);
         }
         let mut receivers = Vec::new();
-        for _ in 0..self.inner_locustdb.opts().read_threads {
+        for _ in 0..self.inner_locustdb.disk_read_scheduler().read_threads() {
             let ldb = self.inner_locustdb.clone();
             let (read_data, receiver) =
                 <dyn Task>::from_fn(move || ldb.disk_read_scheduler().service_reads(&ldb));
@@ -178,18 +533,82 @@ impl LocustDB {
         receiver.await
     }
 
+    /// Number of bytes stored on disk per table and partition, tables that were never persisted are omitted.
+    pub async fn disk_usage(&self) -> Result<Vec<TableDiskUsage>, oneshot::Canceled> {
+        let inner = self.inner_locustdb.clone();
+        let (task, receiver) = <dyn Task>::from_fn(move || inner.disk_usage());
+        self.schedule(task);
+        receiver.await
+    }
+
+    /// Compacts `table` right away instead of waiting for background compaction, which merges small partitions
+    /// and removes deleted rows.
+    /// Returns the number of partitions that were replaced.
+    pub async fn compact(&self, table: &str) -> Result<Result<usize, QueryError>, oneshot::Canceled> {
+        let inner = self.inner_locustdb.clone();
+        let table = table.to_string();
+        let (task, receiver) = <dyn Task>::from_fn(move || inner.compact(&table));
+        self.schedule(task);
+        receiver.await
+    }
+
+    /// Grows or shrinks the pool of worker threads that execute queries and other tasks, e.g. to adapt to load.
+    /// At least one worker thread is kept, busy workers exit once they have completed their current task.
+    pub fn set_worker_threads(&self, threads: usize) {
+        InnerLocustDB::set_worker_threads(&self.inner_locustdb, threads)
+    }
+
+    pub fn worker_threads(&self) -> usize {
+        self.inner_locustdb.worker_threads()
+    }
+
+    /// Evicts least recently used columns from memory until tables take up at most `target_bytes`,
+    /// returns the number of freed bytes. Evicted columns are loaded from disk again when they are queried.
+    pub fn force_evict(&self, target_bytes: usize) -> usize {
+        self.inner_locustdb.force_evict(target_bytes)
+    }
+
+    /// Number of tasks that are waiting for a worker thread.
+    pub fn queued_tasks(&self) -> usize {
+        self.inner_locustdb.queued_tasks()
+    }
+
+    /// Changes the maximum number of concurrent disk reads, initially `Options::read_threads`.
+    /// Higher values help when the disk store serves many reads in parallel, lower values reduce contention on slow disks.
+    pub fn set_read_threads(&self, read_threads: usize) {
+        self.inner_locustdb.disk_read_scheduler().set_read_threads(read_threads)
+    }
+
+    pub fn read_threads(&self) -> usize {
+        self.inner_locustdb.disk_read_scheduler().read_threads()
+    }
+
+    /// Number of disk reads that are scheduled or waiting for a reader and have not started yet.
+    pub fn read_queue_depth(&self) -> usize {
+        self.inner_locustdb.disk_read_scheduler().queue_depth()
+    }
+
+    /// Returns the read concurrency, pending reads, bytes read and read latency of the disk read scheduler.
+    pub fn disk_read_stats(&self) -> DiskReadStats {
+        self.inner_locustdb.disk_read_scheduler().stats()
+    }
+
+    pub fn opts(&self) -> &Options {
+        self.inner_locustdb.opts()
+    }
+
     pub fn schedule<T: Task + 'static>(&self, task: T) {
         self.inner_locustdb.schedule(task)
     }
 
     #[cfg(feature = "enable_rocksdb")]
-    pub fn persistent_storage<P: AsRef<Path>>(db_path: P) -> Arc<dyn DiskStore> {
+    pub fn persistent_storage<P: AsRef<Path>>(db_path: P, compression: DiskCompression) -> Arc<dyn DiskStore> {
         use crate::disk_store::rocksdb;
-        Arc::new(rocksdb::RocksDB::new(db_path))
+        Arc::new(rocksdb::RocksDB::new(db_path, compression))
     }
 
     #[cfg(not(feature = "enable_rocksdb"))]
-    pub fn persistent_storage<P: AsRef<Path>>(_: P) -> Arc<dyn DiskStore> {
+    pub fn persistent_storage<P: AsRef<Path>>(_: P, _: DiskCompression) -> Arc<dyn DiskStore> {
         panic!("RocksDB storage backend is not enabled in this build of LocustDB. Create db with `memory_only`, or set the `enable_rocksdb` feature.")
     }
 }
@@ -199,10 +618,63 @@ pub struct Options {
     pub threads: usize,
     pub read_threads: usize,
     pub db_path: Option<PathBuf>,
+    /// Directory of the write-ahead log that allows rows which have not been stored as a partition yet to be
+    /// recovered after a crash, `None` disables the log. Recovered rows are only durable if `db_path` is also set.
+    pub wal_path: Option<PathBuf>,
+    /// Interval at which the write-ahead log is synced to disk in the background.
+    /// `None` syncs the log on every ingest, which is slower but does not lose any rows on a crash.
+    pub wal_fsync_interval: Option<Duration>,
     pub mem_size_limit_tables: usize,
     pub mem_lz4: bool,
+    /// Compression of partitions stored on disk.
+    pub disk_compression: DiskCompression,
     pub readahead: usize,
     pub seq_disk_read: bool,
+    /// Adjacent partitions with fewer rows than this are merged in the background, 0 disables merging.
+    /// Partitions that contain rows removed by `DELETE` are always rewritten in the background.
+    pub compact_below_rows: usize,
+    /// Number of query results that are kept until a queried table changes, 0 disables the result cache.
+    pub result_cache_size: usize,
+    /// Number of ingested rows that are buffered before they are stored as a new partition.
+    pub batch_size: usize,
+    /// Batch size of specific tables, takes precedence over `batch_size`.
+    pub table_batch_sizes: HashMap<String, usize>,
+    /// Encodings of specific columns by table and column name, which are used instead of automatically choosing
+    /// an encoding when rows are stored as a partition. The column name `*` applies to all other columns of the table.
+    pub column_encodings: HashMap<String, HashMap<String, ColumnEncoding>>,
+    /// Tables whose rows are assigned a `_ingest_ts` column containing the unix time in milliseconds at which they
+    /// were ingested.
+    pub ingest_timestamp_tables: HashSet<String>,
+    /// Queries that would return more rows than this fail with `QueryError::ResultTooLarge`, regardless of their `LIMIT`.
+    /// Also limits the number of values returned by the subquery of `expr IN (SELECT ...)`, see `MAX_SUBQUERY_ROWS`.
+    pub max_result_rows: Option<usize>,
+    /// Queries whose intermediate buffers take up more than this many bytes fail with `QueryError::ResourceLimit`.
+    pub query_memory_limit: Option<usize>,
+    /// Level from 0 to 9 at which the HTTP server compresses responses for clients that accept gzip or deflate,
+    /// `None` disables compression.
+    pub http_compression_level: Option<u32>,
+    /// API keys that may call any endpoint of the HTTP server.
+    /// If neither this nor `read_only_api_keys` contains any keys, requests do not require a key.
+    pub api_keys: HashSet<String>,
+    /// API keys that may only call endpoints of the HTTP server that do not modify data.
+    pub read_only_api_keys: HashSet<String>,
+}
+
+impl Options {
+    /// Batch size of `table` if it is created without specifying a batch size.
+    pub fn table_batch_size(&self, table: &str) -> usize {
+        match self.table_batch_sizes.get(table) {
+            Some(&batch_size) => batch_size,
+            None if table == "_meta_tables" => 1,
+            None if table == "_meta_queries" => 10,
+            None => self.batch_size,
+        }
+    }
+
+    /// Encodings configured for columns of `table`.
+    pub fn table_column_encodings(&self, table: &str) -> HashMap<String, ColumnEncoding> {
+        self.column_encodings.get(table).cloned().unwrap_or_default()
+    }
 }
 
 impl Default for Options {
@@ -211,10 +683,25 @@ impl Default for Options {
             threads: num_cpus::get(),
             read_threads: num_cpus::get(),
             db_path: None,
+            wal_path: None,
+            wal_fsync_interval: Some(Duration::from_secs(1)),
             mem_size_limit_tables: 8 * 1024 * 1024 * 1024, // 8 GiB
             mem_lz4: true,
+            // Columns are already LZ4 encoded when they are stored in builds with `enable_lz4`
+            disk_compression: if cfg!(feature = "enable_lz4") { DiskCompression::None } else { DiskCompression::Lz4 },
             readahead: 256 * 1024 * 1024, // 256 MiB
             seq_disk_read: false,
+            compact_below_rows: 0,
+            result_cache_size: 0,
+            batch_size: 1 << 20,
+            table_batch_sizes: HashMap::default(),
+            column_encodings: HashMap::default(),
+            ingest_timestamp_tables: HashSet::default(),
+            max_result_rows: None,
+            query_memory_limit: None,
+            http_compression_level: Some(6),
+            api_keys: HashSet::default(),
+            read_only_api_keys: HashSet::default(),
         }
     }
 }
@@ -24,6 +24,7 @@ struct BackgroundWorker {
 struct DataBatch {
     pub table: String,
     pub rows: Vec<HashMap<String, f64>>,
+    pub timestamp_columns: Vec<String>,
 }
 
 impl LoggingClient {
@@ -44,7 +45,7 @@ impl LoggingClient {
         let time_millis = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
-            .as_millis() as f64 / 1000.0;
+            .as_millis() as f64;
         row.insert("timestamp".to_string(), time_millis);
         let mut events = self.events.lock().unwrap();
         events.entry(table.to_string()).or_default().push(row);
@@ -61,7 +62,7 @@ impl BackgroundWorker {
             let buffer = mem::take(&mut *self.events.lock().unwrap());
             for (table, rows) in buffer.into_iter() {
                 log::debug!("Pushing {} events to {}", rows.len(), table);
-                let data_batch = DataBatch { table, rows };
+                let data_batch = DataBatch { table, rows, timestamp_columns: vec!["timestamp".to_string()] };
                 let result = self.client.post(&self.url).json(&data_batch).send().await;
                 if let Err(err) = result {
                     log::warn!(
@@ -0,0 +1,32 @@
+use crate::mem_store::*;
+use std::sync::Arc;
+
+pub struct BoolColumn;
+
+impl BoolColumn {
+    pub fn new_boxed(name: &str, mut values: Vec<u8>, null: Option<Vec<u8>>) -> Arc<Column> {
+        let null = null.map(|mut n| {
+            n.shrink_to_fit();
+            n
+        });
+        values.shrink_to_fit();
+        let mut column = match null {
+            Some(present) => Column::new(
+                name,
+                values.len(),
+                Some((0, 1)),
+                vec![CodecOp::PushDataSection(1), CodecOp::Nullable],
+                vec![DataSection::U8(values), DataSection::U8(present)],
+            ),
+            None => Column::new(
+                name,
+                values.len(),
+                None,
+                vec![],
+                vec![DataSection::U8(values)],
+            ),
+        };
+        column.lz4_encode();
+        Arc::new(column)
+    }
+}
@@ -127,7 +127,7 @@ impl Codec {
                 CodecOp::Add(_, x) => {
                     let lhs = stack.pop().unwrap();
                     let rhs = planner.scalar_i64(x, true).into();
-                    planner.add(lhs, rhs)
+                    planner.add(lhs, rhs, EncodingType::I64)
                 }
                 CodecOp::Delta(_) => planner.delta_decode(stack.pop().unwrap()).into(),
                 CodecOp::ToI64(_) => planner.cast(stack.pop().unwrap(), EncodingType::I64),
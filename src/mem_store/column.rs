@@ -12,6 +12,8 @@ pub struct Column {
     name: String,
     len: usize,
     range: Option<(i64, i64)>,
+    /// Minimum and maximum of the decoded integer values, used to skip partitions that cannot match a filter.
+    value_range: Option<(i64, i64)>,
     codec: Codec,
     data: Vec<DataSection>,
 }
@@ -19,6 +21,8 @@ pub struct Column {
 pub trait DataSource: fmt::Debug + Sync + Send {
     fn encoding_type(&self) -> EncodingType;
     fn range(&self) -> Option<(i64, i64)>;
+    /// Minimum and maximum of the decoded values, if known.
+    fn value_range(&self) -> Option<(i64, i64)>;
     fn codec(&self) -> Codec;
     fn len(&self) -> usize;
     fn data_sections(&self) -> Vec<&dyn Data>;
@@ -32,6 +36,9 @@ impl<T: DataSource> DataSource for Arc<T> {
     fn range(&self) -> Option<(i64, i64)> {
         (**self).range()
     }
+    fn value_range(&self) -> Option<(i64, i64)> {
+        (**self).value_range()
+    }
     fn codec(&self) -> Codec {
         (**self).codec()
     }
@@ -53,6 +60,9 @@ impl DataSource for Column {
     fn range(&self) -> Option<(i64, i64)> {
         self.range
     }
+    fn value_range(&self) -> Option<(i64, i64)> {
+        self.value_range
+    }
     fn codec(&self) -> Codec {
         self.codec.clone()
     }
@@ -90,6 +100,7 @@ impl Column {
             name: name.to_string(),
             len,
             range,
+            value_range: None,
             codec,
             data,
         }
@@ -100,6 +111,7 @@ impl Column {
             name: name.to_string(),
             len,
             range: None,
+            value_range: None,
             codec: Codec::identity(BasicType::Null),
             data: vec![DataSection::Null(len)],
         }
@@ -128,6 +140,10 @@ impl Column {
     pub fn name(&self) -> &str {
         &self.name
     }
+
+    pub fn set_value_range(&mut self, value_range: Option<(i64, i64)>) {
+        self.value_range = value_range;
+    }
     pub fn data(&self) -> &[DataSection] {
         &self.data
     }
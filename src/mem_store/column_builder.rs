@@ -1,17 +1,50 @@
 use std::cmp;
 use std::i64;
+use std::str::FromStr;
 use std::sync::Arc;
 
 use ordered_float::OrderedFloat;
 
+use crate::engine::data_types::EncodingType;
+use crate::engine::BasicType;
 use crate::mem_store::integers::*;
 use crate::mem_store::column::*;
 use crate::mem_store::strings::*;
 use crate::stringpack::*;
 
+use super::booleans::BoolColumn;
 use super::floats::FloatColumn;
 
 
+/// Encoding that overrides the encoding chosen automatically when a column is stored in a partition.
+/// Encodings that do not apply to the type of a column are ignored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnEncoding {
+    /// Strings are stored without dictionary and integers as 64-bit values, neither is LZ4 compressed.
+    Raw,
+    /// Strings are dictionary encoded regardless of the number of unique values.
+    Dictionary,
+    /// Integers are stored with at least 8/16/32 bits, larger values still use the smallest width they fit into.
+    U8,
+    U16,
+    U32,
+}
+
+impl FromStr for ColumnEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ColumnEncoding, String> {
+        match s.to_lowercase().as_str() {
+            "raw" => Ok(ColumnEncoding::Raw),
+            "dictionary" => Ok(ColumnEncoding::Dictionary),
+            "u8" => Ok(ColumnEncoding::U8),
+            "u16" => Ok(ColumnEncoding::U16),
+            "u32" => Ok(ColumnEncoding::U32),
+            _ => Err(format!("unknown column encoding `{}`, expected `raw`, `dictionary`, `u8`, `u16` or `u32`", s)),
+        }
+    }
+}
+
 pub trait ColumnBuilder<T: ?Sized>: Default {
     fn new() -> Self;
     fn push(&mut self, elem: &T);
@@ -23,6 +56,13 @@ pub struct StringColBuilder {
     lhex: bool,
     uhex: bool,
     string_bytes: usize,
+    encoding: Option<ColumnEncoding>,
+}
+
+impl StringColBuilder {
+    pub fn with_encoding(encoding: Option<ColumnEncoding>) -> StringColBuilder {
+        StringColBuilder { encoding, ..StringColBuilder::default() }
+    }
 }
 
 impl Default for StringColBuilder {
@@ -32,6 +72,7 @@ impl Default for StringColBuilder {
             lhex: true,
             uhex: true,
             string_bytes: 0,
+            encoding: None,
         }
     }
 }
@@ -49,7 +90,7 @@ impl<T: AsRef<str>> ColumnBuilder<T> for StringColBuilder {
 
     fn finalize(self, name: &str, present: Option<Vec<u8>>) -> Arc<Column> {
         fast_build_string_column(name, self.values.iter(), self.values.len(),
-                                 self.lhex, self.uhex, self.string_bytes, present)
+                                 self.lhex, self.uhex, self.string_bytes, present, self.encoding)
     }
 }
 
@@ -61,6 +102,13 @@ pub struct IntColBuilder {
     increasing: u64,
     allow_delta_encode: bool,
     last: i64,
+    encoding: Option<ColumnEncoding>,
+}
+
+impl IntColBuilder {
+    pub fn with_encoding(encoding: Option<ColumnEncoding>) -> IntColBuilder {
+        IntColBuilder { encoding, ..IntColBuilder::default() }
+    }
 }
 
 impl Default for IntColBuilder {
@@ -72,6 +120,7 @@ impl Default for IntColBuilder {
             increasing: 0,
             allow_delta_encode: true,
             last: i64::MIN,
+            encoding: None,
         }
     }
 }
@@ -96,13 +145,20 @@ impl ColumnBuilder<Option<i64>> for IntColBuilder {
 
     fn finalize(self, name: &str, present: Option<Vec<u8>>) -> Arc<Column> {
         // PERF: heuristic for deciding delta encoding could probably be improved
-        let delta_encode = self.allow_delta_encode &&
+        let delta_encode = self.allow_delta_encode && self.encoding != Some(ColumnEncoding::Raw) &&
             (self.increasing * 10 > self.data.len() as u64 * 9 && cfg!(feature = "enable_lz4"));
+        let min_width = match self.encoding {
+            Some(ColumnEncoding::Raw) => EncodingType::I64,
+            Some(ColumnEncoding::U16) => EncodingType::U16,
+            Some(ColumnEncoding::U32) => EncodingType::U32,
+            _ => EncodingType::U8,
+        };
         IntegerColumn::new_boxed(name,
                                  self.data,
                                  self.min,
                                  self.max,
                                  delta_encode,
+                                 min_width,
                                  present)
     }
 }
@@ -129,6 +185,62 @@ impl ColumnBuilder<Option<f64>> for FloatColBuilder {
     }
 }
 
+#[derive(Default)]
+pub struct BoolColBuilder {
+    data: Vec<u8>,
+}
+
+impl ColumnBuilder<Option<bool>> for BoolColBuilder {
+    fn new() -> BoolColBuilder { BoolColBuilder::default() }
+
+    #[inline]
+    fn push(&mut self, elem: &Option<bool>) {
+        self.data.push(elem.unwrap_or(false) as u8);
+    }
+
+    fn finalize(self, name: &str, present: Option<Vec<u8>>) -> Arc<Column> {
+        BoolColumn::new_boxed(name, self.data, present)
+    }
+}
+
+
+/// Column of `len` null values with the same type as other columns of type `basic_type`.
+pub fn null_column(name: &str, len: usize, basic_type: BasicType) -> Arc<Column> {
+    let present = Some(vec![0; (len + 7) / 8]);
+    match basic_type {
+        BasicType::Integer | BasicType::NullableInteger => {
+            let mut builder = IntColBuilder::default();
+            for _ in 0..len {
+                builder.push(&None);
+            }
+            builder.finalize(name, present)
+        }
+        BasicType::Float | BasicType::NullableFloat => {
+            let mut builder = FloatColBuilder::default();
+            for _ in 0..len {
+                builder.push(&None);
+            }
+            builder.finalize(name, present)
+        }
+        BasicType::String | BasicType::NullableString => {
+            let mut builder = StringColBuilder::default();
+            for _ in 0..len {
+                builder.push(&"");
+            }
+            ColumnBuilder::<&str>::finalize(builder, name, present)
+        }
+        BasicType::Boolean => {
+            let mut builder = BoolColBuilder::default();
+            for _ in 0..len {
+                builder.push(&None);
+            }
+            builder.finalize(name, present)
+        }
+        // Nullable values are not supported
+        _ => Arc::new(Column::null(name, len)),
+    }
+}
+
 
 fn is_lowercase_hex(string: &str) -> bool {
     string.len() & 1 == 0 && string.chars().all(|c| {
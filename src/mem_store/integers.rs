@@ -12,6 +12,7 @@ impl IntegerColumn {
                      mut min: i64,
                      mut max: i64,
                      delta_encode: bool,
+                     min_width: EncodingType,
                      null: Option<Vec<u8>>) -> Arc<Column> {
         let null = null.map(|mut n| {
             n.shrink_to_fit();
@@ -37,17 +38,23 @@ impl IntegerColumn {
         } else {
             (max - min) as u64
         };
-        let mut column = if min >= 0 && max <= From::from(u8::MAX) {
+        let (allow_u8, allow_u16, allow_u32) = match min_width {
+            EncodingType::U8 => (true, true, true),
+            EncodingType::U16 => (false, true, true),
+            EncodingType::U32 => (false, false, true),
+            _ => (false, false, false),
+        };
+        let mut column = if allow_u8 && min >= 0 && max <= From::from(u8::MAX) {
             IntegerColumn::create_col::<u8>(name, values, 0, min0, max0, delta_encode, null, EncodingType::U8)
-        } else if interval <= From::from(u8::MAX) {
+        } else if allow_u8 && interval <= From::from(u8::MAX) {
             IntegerColumn::create_col::<u8>(name, values, min, min0, max0, delta_encode, null, EncodingType::U8)
-        } else if min >= 0 && max <= From::from(u16::MAX) {
+        } else if allow_u16 && min >= 0 && max <= From::from(u16::MAX) {
             IntegerColumn::create_col::<u16>(name, values, 0, min0, max0, delta_encode, null, EncodingType::U16)
-        } else if interval <= From::from(u16::MAX) {
+        } else if allow_u16 && interval <= From::from(u16::MAX) {
             IntegerColumn::create_col::<u16>(name, values, min, min0, max0, delta_encode, null, EncodingType::U16)
-        } else if min >= 0 && max <= From::from(u32::MAX) {
+        } else if allow_u32 && min >= 0 && max <= From::from(u32::MAX) {
             IntegerColumn::create_col::<u32>(name, values, 0, min0, max0, delta_encode, null, EncodingType::U32)
-        } else if interval <= From::from(u32::MAX) {
+        } else if allow_u32 && interval <= From::from(u32::MAX) {
             IntegerColumn::create_col::<u32>(name, values, min, min0, max0, delta_encode, null, EncodingType::U32)
         } else {
             values.shrink_to_fit();
@@ -84,7 +91,12 @@ impl IntegerColumn {
                 }
             }
         };
-        column.lz4_encode();
+        // Nulls are stored as 0 and included in the range, which keeps it a valid bound for all non-null values
+        column.set_value_range(original_range);
+        // Columns with a minimum width of 64 bits are forced to be stored raw
+        if min_width != EncodingType::I64 {
+            column.lz4_encode();
+        }
         Arc::new(column)
     }
 
@@ -1,10 +1,13 @@
 use crate::mem_store::partition::ColumnKey;
 use lru::LruCache;
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 
 #[derive(Clone)]
 pub struct Lru {
     cache: Arc<Mutex<LruCache<ColumnKey, ()>>>,
+    /// Number of active queries referencing each column, pinned columns are never evicted.
+    pinned: Arc<Mutex<HashMap<ColumnKey, usize>>>,
 }
 
 impl Lru {
@@ -23,9 +26,33 @@ impl Lru {
         cache.pop(column);
     }
 
+    pub fn pin(&self, column: ColumnKey) {
+        let mut pinned = self.pinned.lock().unwrap();
+        *pinned.entry(column).or_insert(0) += 1;
+    }
+
+    pub fn unpin(&self, column: &ColumnKey) {
+        let mut pinned = self.pinned.lock().unwrap();
+        if let Some(count) = pinned.get_mut(column) {
+            *count -= 1;
+            if *count == 0 {
+                pinned.remove(column);
+            }
+        }
+    }
+
+    /// Removes and returns the least recently used column that is not pinned.
     pub fn evict(&self) -> Option<ColumnKey> {
         let mut cache = self.cache.lock().unwrap();
-        cache.pop_lru().map(|x| x.0)
+        let pinned = self.pinned.lock().unwrap();
+        let victim = cache
+            .iter()
+            .rev()
+            .map(|(column, _)| column)
+            .find(|column| !pinned.contains_key(*column))?
+            .clone();
+        cache.pop(&victim);
+        Some(victim)
     }
 }
 
@@ -33,6 +60,29 @@ impl Default for Lru {
     fn default() -> Lru {
         Lru {
             cache: Arc::new(Mutex::new(LruCache::unbounded())),
+            pinned: Arc::new(Mutex::new(HashMap::default())),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evict_skips_pinned() {
+        let lru = Lru::default();
+        let a = (0, "a".to_string());
+        let b = (0, "b".to_string());
+        lru.put(a.clone());
+        lru.put(b.clone());
+        lru.pin(a.clone());
+        lru.pin(a.clone());
+        assert_eq!(lru.evict(), Some(b));
+        assert_eq!(lru.evict(), None);
+        lru.unpin(&a);
+        assert_eq!(lru.evict(), None);
+        lru.unpin(&a);
+        assert_eq!(lru.evict(), Some(a));
+    }
+}
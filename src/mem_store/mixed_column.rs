@@ -5,9 +5,10 @@ impl RawVal {
     pub fn to_val(&self) -> Val {
         match *self {
             RawVal::Null => Val::Null,
-            RawVal::Int(i) => Val::Integer(i),
+            RawVal::Int(i) | RawVal::Timestamp(i) => Val::Integer(i),
             RawVal::Str(ref string) => Val::Str(string),
             RawVal::Float(f) => Val::Float(f),
+            RawVal::Bool(b) => Val::Bool(b),
         }
     }
 }
@@ -1,3 +1,4 @@
+pub mod booleans;
 pub mod codec;
 pub mod column;
 pub mod column_builder;
@@ -17,7 +18,7 @@ pub mod value;
 pub use self::codec::{Codec, CodecOp};
 pub use self::column::{Column, DataSection, DataSource};
 pub use self::lru::Lru;
-pub use self::table::TableStats;
+pub use self::table::{ColumnTypes, TableStats};
 pub use self::tree::*;
 pub use self::value::Val;
 
@@ -4,17 +4,37 @@ use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, MutexGuard};
 
 use crate::disk_store::interface::*;
+use crate::engine::data_types::EncodingType;
 use crate::ingest::buffer::Buffer;
+use crate::mem_store::column_builder::{ColumnBuilder, ColumnEncoding, IntColBuilder};
 use crate::mem_store::*;
 use crate::scheduler::disk_read_scheduler::DiskReadScheduler;
 
 pub type ColumnKey = (PartitionID, String);
 
+/// Name of the column that is 1 for rows removed by `DELETE` and 0 otherwise, followed by as many underscores as
+/// required to not shadow any of `referenced_cols`.
+pub fn deleted_column_name(referenced_cols: &HashSet<String>) -> String {
+    let mut name = "_deleted".to_string();
+    while referenced_cols.contains(&name) {
+        name.push('_');
+    }
+    name
+}
+
 pub struct Partition {
     pub id: PartitionID,
     len: usize,
     cols: Vec<ColumnHandle>,
     lru: Lru,
+    /// Rows removed by `DELETE` that are still stored until the partition is compacted.
+    tombstones: Mutex<Option<Tombstones>>,
+}
+
+struct Tombstones {
+    deleted: Vec<bool>,
+    count: usize,
+    column: Arc<Column>,
 }
 
 impl Partition {
@@ -34,6 +54,7 @@ impl Partition {
                     })
                     .collect(),
                 lru,
+                tombstones: Mutex::default(),
             },
             keys,
         )
@@ -53,19 +74,30 @@ impl Partition {
                 .map(|c| ColumnHandle::non_resident(id, c.name.to_string(), c.size_bytes))
                 .collect(),
             lru,
+            tombstones: Mutex::default(),
         }
     }
 
-    pub fn from_buffer(id: PartitionID, buffer: Buffer, lru: Lru) -> (Partition, Vec<ColumnKey>) {
-        Partition::new(
-            id,
-            buffer
-                .buffer
-                .into_iter()
-                .map(|(name, raw_col)| raw_col.finalize(&name))
-                .collect(),
-            lru,
-        )
+    pub fn from_buffer(
+        id: PartitionID,
+        buffer: Buffer,
+        encodings: &HashMap<String, ColumnEncoding>,
+        lru: Lru,
+    ) -> (Partition, Vec<ColumnKey>) {
+        Partition::new(id, Partition::finalize_buffer(buffer, encodings), lru)
+    }
+
+    /// Encodes the columns of `buffer`, using the encoding configured for each column in `encodings`,
+    /// or the one configured for `*`, instead of choosing one automatically.
+    pub fn finalize_buffer(buffer: Buffer, encodings: &HashMap<String, ColumnEncoding>) -> Vec<Arc<Column>> {
+        buffer
+            .buffer
+            .into_iter()
+            .map(|(name, raw_col)| {
+                let encoding = encodings.get(&name).or_else(|| encodings.get("*")).copied();
+                raw_col.finalize(&name, encoding)
+            })
+            .collect()
     }
 
     pub fn get_cols(
@@ -129,6 +161,23 @@ impl Partition {
         total_size
     }
 
+    /// Prevents the given columns from being evicted until they are unpinned again.
+    pub fn pin(&self, cols: &HashSet<String>) {
+        for handle in &self.cols {
+            if cols.contains(handle.name()) {
+                self.lru.pin(handle.key.clone());
+            }
+        }
+    }
+
+    pub fn unpin(&self, cols: &HashSet<String>) {
+        for handle in &self.cols {
+            if cols.contains(handle.name()) {
+                self.lru.unpin(&handle.key);
+            }
+        }
+    }
+
     pub fn restore(&self, col: &Arc<Column>) {
         for handle in &self.cols {
             if handle.name() == col.name() {
@@ -161,6 +210,50 @@ impl Partition {
         self.len
     }
 
+    /// Marks the rows for which `deleted` is true as deleted and returns the number of rows that were not deleted before.
+    pub fn delete_rows(&self, deleted: &[bool]) -> usize {
+        let mut tombstones = self.tombstones.lock().unwrap();
+        let mut merged = match &*tombstones {
+            Some(tombstones) => tombstones.deleted.clone(),
+            None => vec![false; self.len],
+        };
+        let mut newly_deleted = 0;
+        for (row, &delete) in merged.iter_mut().zip(deleted) {
+            if delete && !*row {
+                *row = true;
+                newly_deleted += 1;
+            }
+        }
+        if newly_deleted > 0 {
+            let mut builder = IntColBuilder::default();
+            for &row in &merged {
+                builder.push(&Some(row as i64));
+            }
+            let count = merged.iter().filter(|&&row| row).count();
+            *tombstones = Some(Tombstones {
+                deleted: merged,
+                count,
+                column: builder.finalize("_deleted", None),
+            });
+        }
+        newly_deleted
+    }
+
+    /// Number of rows that have been deleted but not yet removed by compaction.
+    pub fn deleted_rows(&self) -> usize {
+        self.tombstones.lock().unwrap().as_ref().map_or(0, |tombstones| tombstones.count)
+    }
+
+    /// Whether each row has been deleted, `None` if no rows have been deleted.
+    pub fn tombstones(&self) -> Option<Vec<bool>> {
+        self.tombstones.lock().unwrap().as_ref().map(|tombstones| tombstones.deleted.clone())
+    }
+
+    /// Column that is 1 for deleted rows and 0 otherwise, `None` if no rows have been deleted.
+    pub fn deleted_column(&self) -> Option<Arc<Column>> {
+        self.tombstones.lock().unwrap().as_ref().map(|tombstones| tombstones.column.clone())
+    }
+
     pub fn mem_tree(&self, coltrees: &mut HashMap<String, MemTreeColumn>, depth: usize) {
         if depth == 0 {
             return;
@@ -200,6 +293,35 @@ impl Partition {
             .collect()
     }
 
+    /// Decoded type and physical encoding of each resident column.
+    pub fn types_per_column(&self) -> Vec<(String, EncodingType, EncodingType)> {
+        self.cols
+            .iter()
+            .filter_map(|handle| {
+                let c = handle.col.lock().unwrap();
+                c.as_ref().map(|x| {
+                    (
+                        handle.name().to_string(),
+                        x.basic_type().to_encoded(),
+                        x.encoding_type(),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    pub fn value_range_per_column(&self) -> Vec<(String, (i64, i64))> {
+        self.cols
+            .iter()
+            .filter_map(|handle| {
+                let c = handle.col.lock().unwrap();
+                c.as_ref()
+                    .and_then(|x| x.value_range())
+                    .map(|range| (handle.name().to_string(), range))
+            })
+            .collect()
+    }
+
     pub fn heap_size_of_children(&self) -> usize {
         self.cols
             .iter()
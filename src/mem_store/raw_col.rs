@@ -5,7 +5,8 @@ use std::sync::Arc;
 
 use ordered_float::OrderedFloat;
 
-use crate::ingest::raw_val::RawVal;
+use crate::bitvec::*;
+use crate::ingest::raw_val::{format_timestamp, RawVal};
 use crate::mem_store::column_builder::*;
 use crate::mem_store::*;
 
@@ -38,13 +39,20 @@ impl MixedCol {
         self.data.extend(floats.into_iter().map(|f| RawVal::Float(OrderedFloat(f))));
     }
 
+    pub fn push_bools(&mut self, bools: Vec<bool>) {
+        self.types = self.types | ColType::bool();
+        self.data.extend(bools.into_iter().map(RawVal::Bool));
+    }
+
     pub fn push_strings(&mut self, strs: Vec<String>) {
         self.types = self.types | ColType::string();
         self.data.extend(strs.into_iter().map(RawVal::Str));
     }
 
     pub fn push_nulls(&mut self, count: usize) {
-        self.types = self.types | ColType::null();
+        if count > 0 {
+            self.types = self.types | ColType::null();
+        }
         self.data.extend(repeat(RawVal::Null).take(count));
     }
 
@@ -52,40 +60,73 @@ impl MixedCol {
         self.data.len()
     }
 
-    pub fn finalize(self, name: &str) -> Arc<Column> {
+    pub fn get(&self, index: usize) -> &RawVal {
+        &self.data[index]
+    }
+
+    /// Bitmap of non-null values, or `None` if the column contains no nulls.
+    fn present(&self) -> Option<Vec<u8>> {
+        if !self.types.contains_null {
+            return None;
+        }
+        let mut present = Vec::with_capacity((self.data.len() + 7) / 8);
+        for (i, v) in self.data.iter().enumerate() {
+            if *v != RawVal::Null {
+                present.set(i);
+            }
+        }
+        Some(present)
+    }
+
+    pub fn finalize(self, name: &str, encoding: Option<ColumnEncoding>) -> Arc<Column> {
+        let present = self.present();
         if self.types.contains_string {
-            let mut builder = StringColBuilder::default();
+            let mut builder = StringColBuilder::with_encoding(encoding);
             for v in self.data {
                 match v {
                     RawVal::Str(s) => builder.push(&s),
                     RawVal::Int(i) => builder.push(&i.to_string()),
+                    RawVal::Timestamp(t) => builder.push(&format_timestamp(t)),
                     RawVal::Null => builder.push(&""),
                     RawVal::Float(f) => builder.push(&f.to_string()),
+                    RawVal::Bool(b) => builder.push(&b.to_string()),
                 }
             }
-            ColumnBuilder::<String>::finalize(builder, name, None)
+            ColumnBuilder::<String>::finalize(builder, name, present)
         } else if self.types.contains_float {
             let mut builder = FloatColBuilder::default();
             for v in self.data {
                 match v {
                     RawVal::Str(_) => panic!("Unexpected string in float column!"),
-                    RawVal::Int(i) => builder.push(&Some(i as f64)),
+                    RawVal::Int(i) | RawVal::Timestamp(i) => builder.push(&Some(i as f64)),
                     RawVal::Null => builder.push(&None),
                     RawVal::Float(f) => builder.push(&Some(f.into_inner())),
+                    RawVal::Bool(b) => builder.push(&Some(b as u8 as f64)),
                 }
             }
-            builder.finalize(name, None)
+            builder.finalize(name, present)
         } else if self.types.contains_int {
-            let mut builder = IntColBuilder::default();
+            let mut builder = IntColBuilder::with_encoding(encoding);
             for v in self.data {
                 match v {
                     RawVal::Str(_) => panic!("Unexpected string in int column!"),
-                    RawVal::Int(i) => builder.push(&Some(i)),
+                    RawVal::Int(i) | RawVal::Timestamp(i) => builder.push(&Some(i)),
                     RawVal::Null => builder.push(&None),
                     RawVal::Float(_) => todo!("Unexpected float in int column!"),
+                    RawVal::Bool(b) => builder.push(&Some(b as i64)),
                 }
             }
-            builder.finalize(name, None)
+            builder.finalize(name, present)
+        } else if self.types.contains_bool {
+            let mut builder = BoolColBuilder::default();
+            for v in self.data {
+                match v {
+                    RawVal::Bool(b) => builder.push(&Some(b)),
+                    RawVal::Null => builder.push(&None),
+                    _ => panic!("Unexpected {} in bool column!", v),
+                }
+            }
+            builder.finalize(name, present)
         } else {
             Arc::new(Column::null(name, self.data.len()))
         }
@@ -118,45 +159,52 @@ struct ColType {
     contains_string: bool,
     contains_int: bool,
     contains_float: bool,
+    contains_bool: bool,
     contains_null: bool,
 }
 
 impl ColType {
-    fn new(string: bool, int: bool, float: bool, null: bool) -> ColType {
+    fn new(string: bool, int: bool, float: bool, bool: bool, null: bool) -> ColType {
         ColType {
             contains_string: string,
             contains_int: int,
             contains_float: float,
+            contains_bool: bool,
             contains_null: null,
         }
     }
 
     fn string() -> ColType {
-        ColType::new(true, false, false, false)
+        ColType::new(true, false, false, false, false)
     }
 
     fn int() -> ColType {
-        ColType::new(false, true, false, false)
+        ColType::new(false, true, false, false, false)
     }
 
     fn float() -> ColType {
-        ColType::new(false, false, true, false)
+        ColType::new(false, false, true, false, false)
+    }
+
+    fn bool() -> ColType {
+        ColType::new(false, false, false, true, false)
     }
 
     fn null() -> ColType {
-        ColType::new(false, false, false, true)
+        ColType::new(false, false, false, false, true)
     }
 
     fn nothing() -> ColType {
-        ColType::new(false, false, false, false)
+        ColType::new(false, false, false, false, false)
     }
 
     fn determine(v: &RawVal) -> ColType {
         match *v {
             RawVal::Null => ColType::null(),
             RawVal::Str(_) => ColType::string(),
-            RawVal::Int(_) => ColType::int(),
+            RawVal::Int(_) | RawVal::Timestamp(_) => ColType::int(),
             RawVal::Float(_) => ColType::float(),
+            RawVal::Bool(_) => ColType::bool(),
         }
     }
 }
@@ -168,6 +216,7 @@ impl BitOr for ColType {
             contains_string: self.contains_string | rhs.contains_string,
             contains_int: self.contains_int | rhs.contains_int,
             contains_float: self.contains_float | rhs.contains_float,
+            contains_bool: self.contains_bool | rhs.contains_bool,
             contains_null: self.contains_null | rhs.contains_null,
         }
     }
@@ -184,3 +233,9 @@ impl From<i64> for RawVal {
         RawVal::Int(val)
     }
 }
+
+impl From<f64> for RawVal {
+    fn from(val: f64) -> RawVal {
+        RawVal::Float(OrderedFloat(val))
+    }
+}
@@ -2,6 +2,7 @@ use hex;
 use seahash::SeaHasher;
 
 use crate::engine::data_types::*;
+use crate::mem_store::column_builder::ColumnEncoding;
 use crate::mem_store::*;
 use crate::stringpack::*;
 use std::collections::hash_set::HashSet;
@@ -16,6 +17,7 @@ type HashSetSea<K> = HashSet<K, BuildHasherDefault<SeaHasher>>;
 
 const DICTIONARY_RATIO: usize = 2;
 
+#[allow(clippy::too_many_arguments)]
 pub fn fast_build_string_column<'a, T>(
     name: &str,
     strings: T,
@@ -24,17 +26,23 @@ pub fn fast_build_string_column<'a, T>(
     uhex: bool,
     total_bytes: usize,
     present: Option<Vec<u8>>,
+    encoding: Option<ColumnEncoding>,
 ) -> Arc<Column>
 where
     T: Iterator<Item = &'a str> + Clone,
 {
+    if encoding == Some(ColumnEncoding::Raw) {
+        let packed = PackedStrings::from_iterator(strings);
+        let data = DataSection::U8(packed.into_vec());
+        return Arc::new(packed_string_column(name, len, string_pack_codec(), data, present));
+    }
     let mut unique_values = HashSetSea::default();
     for s in strings.clone() {
         unique_values.insert(s);
         // PERF: is 2 the right constant? and should probably also depend on the length of the strings
         // TODO(#103): len > 1000 || name == "string_packed" is a hack to make tests use dictionary encoding. Remove once we are able to group by string packed columns.
-        if unique_values.len() == len / DICTIONARY_RATIO {
-            let (mut codec, data) = if (lhex || uhex) && total_bytes / len > 5 {
+        if unique_values.len() == len / DICTIONARY_RATIO && encoding != Some(ColumnEncoding::Dictionary) {
+            let (codec, data) = if (lhex || uhex) && total_bytes / len > 5 {
                 let packed = PackedBytes::from_iterator(strings.map(|s| hex::decode(s).unwrap()));
                 (
                     vec![CodecOp::UnhexpackStrings(uhex, total_bytes)],
@@ -44,13 +52,7 @@ where
                 let packed = PackedStrings::from_iterator(strings);
                 (string_pack_codec(), DataSection::U8(packed.into_vec()))
             };
-            let mut column = if let Some(present) = present {
-                codec.push(CodecOp::PushDataSection(1));
-                codec.push(CodecOp::Nullable);
-                Column::new(name, len, None, codec, vec![data, DataSection::U8(present)])
-            } else {
-                Column::new(name, len, None, codec, vec![data])
-            };
+            let mut column = packed_string_column(name, len, codec, data, present);
             column.lz4_encode();
             return Arc::new(column);
         }
@@ -129,6 +131,22 @@ where
     Arc::new(column)
 }
 
+fn packed_string_column(
+    name: &str,
+    len: usize,
+    mut codec: Vec<CodecOp>,
+    data: DataSection,
+    present: Option<Vec<u8>>,
+) -> Column {
+    if let Some(present) = present {
+        codec.push(CodecOp::PushDataSection(1));
+        codec.push(CodecOp::Nullable);
+        Column::new(name, len, None, codec, vec![data, DataSection::U8(present)])
+    } else {
+        Column::new(name, len, None, codec, vec![data])
+    }
+}
+
 pub fn dict_codec(index_type: EncodingType) -> Vec<CodecOp> {
     vec![
         CodecOp::PushDataSection(1),
@@ -1,32 +1,131 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ops::DerefMut;
 use std::str;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::sync::{Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use crate::disk_store::interface::*;
+use crate::engine::data_types::EncodingType;
+use crate::errors::IngestError;
 use crate::ingest::buffer::Buffer;
 use crate::ingest::input_column::InputColumn;
 use crate::ingest::raw_val::RawVal;
+use crate::ingest::wal::{Wal, WalEntry, WalRecord};
+use crate::mem_store::column_builder::ColumnEncoding;
 use crate::mem_store::partition::{ColumnKey, Partition};
 use crate::mem_store::*;
 
+/// Column that records the time at which rows were ingested into tables listed in `Options::ingest_timestamp_tables`.
+pub const INGEST_TIMESTAMP_COLUMN: &str = "_ingest_ts";
+
 pub struct Table {
     name: String,
     batch_size: usize,
+    /// Encodings that override the automatically chosen encoding of columns, see `Options::column_encodings`.
+    column_encodings: HashMap<String, ColumnEncoding>,
     partitions: RwLock<HashMap<PartitionID, Arc<Partition>>>,
     buffer: Mutex<Buffer>,
     lru: Lru,
+    storage: Arc<dyn DiskStore>,
+    /// Partition id allocator shared by all tables.
+    next_partition_id: Arc<AtomicUsize>,
+    /// Columns that were ingested as timestamps, which are stored as integers.
+    timestamp_columns: RwLock<HashSet<String>>,
+    /// Incremented whenever rows are added or partitions are replaced.
+    version: AtomicU64,
+    /// Log of the buffered rows, see `Options::wal_path`.
+    wal: Option<Wal>,
+    /// Whether ingested rows are assigned an `INGEST_TIMESTAMP_COLUMN`.
+    ingest_timestamp: bool,
 }
 
 impl Table {
-    pub fn new(batch_size: usize, name: &str, lru: Lru) -> Table {
+    pub fn new(
+        batch_size: usize,
+        column_encodings: HashMap<String, ColumnEncoding>,
+        name: &str,
+        lru: Lru,
+        storage: Arc<dyn DiskStore>,
+        next_partition_id: Arc<AtomicUsize>,
+    ) -> Table {
         Table {
             name: name.to_string(),
-            batch_size: batch_size_override(batch_size, name),
+            batch_size,
+            column_encodings,
             partitions: RwLock::new(HashMap::new()),
             buffer: Mutex::new(Buffer::default()),
             lru,
+            storage,
+            next_partition_id,
+            timestamp_columns: RwLock::new(HashSet::new()),
+            version: AtomicU64::new(0),
+            wal: None,
+            ingest_timestamp: false,
+        }
+    }
+
+    /// Adds an `INGEST_TIMESTAMP_COLUMN` containing the current unix time in milliseconds to all subsequently ingested rows.
+    pub fn set_ingest_timestamp(&mut self, ingest_timestamp: bool) {
+        self.ingest_timestamp = ingest_timestamp;
+    }
+
+    fn add_ingest_timestamp(&self, row: &mut Vec<(String, RawVal)>, now: i64) {
+        if self.ingest_timestamp {
+            row.retain(|(name, _)| name != INGEST_TIMESTAMP_COLUMN);
+            row.push((INGEST_TIMESTAMP_COLUMN.to_string(), RawVal::Int(now)));
+        }
+    }
+
+    /// Logs all subsequently ingested rows to `wal` until they are stored as a partition.
+    pub fn set_wal(&mut self, wal: Wal) {
+        self.wal = Some(wal);
+    }
+
+    /// Deletes the write-ahead log, rows that are ingested afterwards are no longer logged.
+    pub fn delete_wal(&mut self) {
+        if let Some(wal) = self.wal.take() {
+            wal.delete();
+        }
+    }
+
+    /// Writes logged rows to disk.
+    pub fn sync_wal(&self) {
+        if let Some(wal) = &self.wal {
+            wal.sync();
+        }
+    }
+
+    /// Adds rows recovered from the write-ahead log to the buffer without logging them again.
+    pub fn replay(&self, entries: Vec<WalEntry>) {
+        let mut buffer = self.buffer.lock().unwrap();
+        for entry in entries {
+            match entry {
+                WalEntry::Rows(rows) => {
+                    for row in rows {
+                        self.record_timestamp_columns(timestamp_values(&row));
+                        buffer.push_row(row);
+                    }
+                }
+                WalEntry::TypedColumns(columns) => {
+                    self.record_timestamp_columns(typed_timestamp_columns(&columns));
+                    buffer.push_typed_cols(columns);
+                }
+                WalEntry::UntypedColumns(columns) => {
+                    self.record_timestamp_columns(untyped_timestamp_columns(&columns));
+                    buffer.push_untyped_cols(columns);
+                }
+            }
+        }
+        self.bump_version();
+        self.batch_if_needed(&mut buffer);
+    }
+
+    fn log(&self, record: WalRecord) {
+        if let Some(wal) = &self.wal {
+            wal.append(&record);
         }
     }
 
@@ -34,28 +133,46 @@ impl Table {
         &self.name
     }
 
+    /// Changes whenever the data of the table changes, used to determine whether cached query results are still valid.
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::SeqCst)
+    }
+
+    fn bump_version(&self) {
+        self.version.fetch_add(1, Ordering::SeqCst);
+    }
+
     pub fn snapshot(&self) -> Vec<Arc<Partition>> {
         let partitions = self.partitions.read().unwrap();
         let mut partitions: Vec<_> = partitions.values().cloned().collect();
         let buffer = self.buffer.lock().unwrap();
         if buffer.len() > 0 {
             partitions.push(Arc::new(
-                Partition::from_buffer(u64::MAX, buffer.clone(), self.lru.clone()).0,
+                Partition::from_buffer(u64::MAX, buffer.clone(), &self.column_encodings, self.lru.clone()).0,
             ));
         }
         partitions
     }
 
     pub fn load_table_metadata(
-        batch_size: usize,
-        storage: &dyn DiskStore,
+        batch_size: impl Fn(&str) -> usize,
+        column_encodings: impl Fn(&str) -> HashMap<String, ColumnEncoding>,
+        storage: &Arc<dyn DiskStore>,
         lru: &Lru,
+        next_partition_id: &Arc<AtomicUsize>,
     ) -> HashMap<String, Table> {
         let mut tables = HashMap::new();
         for md in storage.load_metadata() {
-            let table = tables
-                .entry(md.tablename.clone())
-                .or_insert_with(|| Table::new(batch_size, &md.tablename, lru.clone()));
+            let table = tables.entry(md.tablename.clone()).or_insert_with(|| {
+                Table::new(
+                    batch_size(&md.tablename),
+                    column_encodings(&md.tablename),
+                    &md.tablename,
+                    lru.clone(),
+                    storage.clone(),
+                    next_partition_id.clone(),
+                )
+            });
             table.insert_nonresident_partition(&md);
         }
         tables
@@ -78,31 +195,117 @@ impl Table {
             &md.columns,
             self.lru.clone(),
         ));
+        if let Some(deleted) = &md.tombstones {
+            partition.delete_rows(deleted);
+        }
         let mut partitions = self.partitions.write().unwrap();
         partitions.insert(md.id, partition);
+        self.bump_version();
     }
 
-    pub fn ingest(&self, row: Vec<(String, RawVal)>) {
+    pub fn ingest(&self, mut row: Vec<(String, RawVal)>) -> Result<(), IngestError> {
         log::debug!("Ingesting row: {:?}", row);
+        validate_row(&row)?;
+        self.add_ingest_timestamp(&mut row, unix_millis());
+        self.record_timestamp_columns(timestamp_values(&row));
         let mut buffer = self.buffer.lock().unwrap();
+        self.log(WalRecord::Rows(std::slice::from_ref(&row)));
         buffer.push_row(row);
+        self.bump_version();
         self.batch_if_needed(buffer.deref_mut());
+        Ok(())
+    }
+
+    /// Ingests `rows`, skipping rows whose values in the `dedup_on` columns match those of a buffered row
+    /// or of an earlier row in `rows`. Returns the number of skipped rows.
+    /// Rows with a null value in any of the `dedup_on` columns are never skipped.
+    /// Rows that have already been turned into a partition are not checked, so a retried batch is only
+    /// deduplicated while the rows of the original batch are still buffered.
+    /// If any row is invalid, no rows are ingested and the index and error of each invalid row are returned.
+    pub fn ingest_dedup(&self, rows: Vec<Vec<(String, RawVal)>>, dedup_on: &[String]) -> Result<usize, Vec<(usize, IngestError)>> {
+        validate_rows(&rows)?;
+        let now = unix_millis();
+        let mut buffer = self.buffer.lock().unwrap();
+        let mut skipped = 0;
+        for mut row in rows {
+            self.add_ingest_timestamp(&mut row, now);
+            self.record_timestamp_columns(timestamp_values(&row));
+            let key = dedup_on
+                .iter()
+                .map(|name| {
+                    row.iter()
+                        .find(|(colname, _)| colname == name)
+                        .map_or(RawVal::Null, |(_, val)| val.clone())
+                })
+                .collect::<Vec<_>>();
+            if !buffer.contains_key(&key, dedup_on) {
+                self.log(WalRecord::Rows(std::slice::from_ref(&row)));
+                buffer.push_row(row);
+                self.bump_version();
+                self.batch_if_needed(buffer.deref_mut());
+            } else {
+                skipped += 1;
+            }
+        }
+        Ok(skipped)
     }
 
-    pub fn ingest_homogeneous(&self, columns: HashMap<String, InputColumn>) {
+    pub fn ingest_homogeneous(&self, mut columns: HashMap<String, InputColumn>) {
+        if self.ingest_timestamp {
+            let len = columns.values().map(input_column_len).max().unwrap_or(0);
+            columns.insert(INGEST_TIMESTAMP_COLUMN.to_string(), InputColumn::Int(vec![unix_millis(); len]));
+        }
+        self.record_timestamp_columns(typed_timestamp_columns(&columns));
         let mut buffer = self.buffer.lock().unwrap();
+        self.log(WalRecord::TypedColumns(&columns));
         buffer.push_typed_cols(columns);
+        self.bump_version();
+        self.batch_if_needed(&mut buffer);
     }
 
-    pub fn ingest_heterogeneous(&self, columns: HashMap<String, Vec<RawVal>>) {
+    pub fn ingest_heterogeneous(&self, mut columns: HashMap<String, Vec<RawVal>>) {
+        if self.ingest_timestamp {
+            let len = columns.values().map(|vals| vals.len()).max().unwrap_or(0);
+            columns.insert(INGEST_TIMESTAMP_COLUMN.to_string(), vec![RawVal::Int(unix_millis()); len]);
+        }
+        self.record_timestamp_columns(untyped_timestamp_columns(&columns));
         let mut buffer = self.buffer.lock().unwrap();
+        self.log(WalRecord::UntypedColumns(&columns));
         buffer.push_untyped_cols(columns);
+        self.bump_version();
         self.batch_if_needed(&mut buffer);
     }
 
+    /// Names of the columns that contain timestamps.
+    pub fn timestamp_columns(&self) -> HashSet<String> {
+        self.timestamp_columns.read().unwrap().clone()
+    }
+
+    fn record_timestamp_columns<'a>(&self, names: impl Iterator<Item = &'a String>) {
+        let mut names = names.peekable();
+        if names.peek().is_none() {
+            return;
+        }
+        let mut timestamp_columns = self.timestamp_columns.write().unwrap();
+        for name in names {
+            if !timestamp_columns.contains(name) {
+                timestamp_columns.insert(name.clone());
+            }
+        }
+    }
+
+    /// Turns any buffered rows into a partition, regardless of batch size.
+    pub fn flush(&self) {
+        let mut buffer = self.buffer.lock().unwrap();
+        if buffer.len() > 0 {
+            self.batch(&mut buffer);
+        }
+    }
+
     pub fn load_partition(&self, partition: Partition) {
         let mut partitions = self.partitions.write().unwrap();
         partitions.insert(partition.id, Arc::new(partition));
+        self.bump_version();
     }
 
     fn batch_if_needed(&self, buffer: &mut Buffer) {
@@ -114,24 +317,133 @@ impl Table {
     }
 
     fn batch(&self, buffer: &mut Buffer) {
-        let buffer = std::mem::take(buffer);
-        self.persist_batch(&buffer);
-        let (mut new_partition, keys) = Partition::from_buffer(0, buffer, self.lru.clone());
+        let columns = Partition::finalize_buffer(buffer.take(), &self.column_encodings);
+        let id = self.next_partition_id.fetch_add(1, Ordering::SeqCst) as PartitionID;
+        self.persist_batch(id, &columns);
+        if let Some(wal) = &self.wal {
+            wal.truncate();
+        }
+        let (new_partition, keys) = Partition::new(id, columns, self.lru.clone());
         {
             let mut partitions = self.partitions.write().unwrap();
-            new_partition.id = partitions.len() as u64;
             partitions.insert(new_partition.id, Arc::new(new_partition));
         }
+        self.bump_version();
         for key in keys {
             self.lru.put(key);
         }
     }
 
+    /// Marks rows as deleted, `deleted` contains whether each row of a partition matches the `WHERE` clause of a `DELETE`.
+    /// The tombstones of each partition are stored before this returns, so deleted rows do not reappear after a restart.
+    /// Returns the number of rows that were not deleted before.
+    pub fn delete_rows(&self, deleted: &[(Arc<Partition>, Vec<bool>)]) -> usize {
+        let mut count = 0;
+        for (partition, rows) in deleted {
+            let newly_deleted = partition.delete_rows(rows);
+            if newly_deleted > 0 {
+                self.storage.store_tombstones(partition.id, &partition.tombstones().unwrap());
+                count += newly_deleted;
+            }
+        }
+        if count > 0 {
+            self.bump_version();
+        }
+        count
+    }
+
+    /// Partitions of the table, not including buffered rows.
+    pub fn partitions(&self) -> Vec<Arc<Partition>> {
+        self.partitions.read().unwrap().values().cloned().collect()
+    }
+
+    /// Groups adjacent partitions with fewer than `max_rows` rows into runs that can be merged.
+    /// Partitions that contain deleted rows are always included, so that they are rewritten without those rows.
+    pub fn compaction_candidates(&self, max_rows: usize) -> Vec<Vec<Arc<Partition>>> {
+        let mut partitions: Vec<_> = self.partitions.read().unwrap().values().cloned().collect();
+        partitions.sort_by_key(|p| p.id);
+        let mut runs = Vec::new();
+        let mut run = Vec::new();
+        let mut run_rows = 0;
+        let is_compacted = |run: &[Arc<Partition>]| run.len() > 1 || run.iter().any(|p| p.deleted_rows() > 0);
+        for partition in partitions {
+            if partition.len() < max_rows || partition.deleted_rows() > 0 {
+                run_rows += partition.len();
+                run.push(partition);
+                if run_rows < max_rows {
+                    continue;
+                }
+            }
+            if is_compacted(&run) {
+                runs.push(std::mem::take(&mut run));
+            }
+            run.clear();
+            run_rows = 0;
+        }
+        if is_compacted(&run) {
+            runs.push(run);
+        }
+        runs
+    }
+
+    /// Replaces `merged` with a single new partition containing all rows of `buffer`,
+    /// or removes `merged` if `buffer` is empty because all of their rows were deleted.
+    pub fn merge_partitions(&self, merged: &[Arc<Partition>], buffer: Buffer) {
+        let replaced = merged
+            .iter()
+            .map(|p| (p.id, p.col_names().iter().map(|c| c.to_string()).collect()))
+            .collect::<Vec<(PartitionID, Vec<String>)>>();
+        let new_partition = if buffer.len() == 0 {
+            self.storage.delete_partitions(&replaced);
+            None
+        } else {
+            let columns = Partition::finalize_buffer(buffer, &self.column_encodings);
+            let id = self.next_partition_id.fetch_add(1, Ordering::SeqCst) as PartitionID;
+            self.storage.replace_partitions(id, &self.name, &columns, &replaced);
+            Some(Partition::new(id, columns, self.lru.clone()))
+        };
+        let mut partitions = self.partitions.write().unwrap();
+        for (id, column_names) in replaced {
+            partitions.remove(&id);
+            for column_name in column_names {
+                self.lru.remove(&(id, column_name));
+            }
+        }
+        if let Some((new_partition, keys)) = new_partition {
+            partitions.insert(new_partition.id, Arc::new(new_partition));
+            for key in keys {
+                self.lru.put(key);
+            }
+        }
+        self.bump_version();
+    }
+
+    /// Deletes all partitions of the table from storage and discards buffered rows.
+    /// Blocks until queries that still hold a snapshot of the table have released it.
+    pub fn delete(self) {
+        let partitions = self.partitions.into_inner().unwrap();
+        while partitions.values().any(|p| Arc::strong_count(p) > 1) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let deleted = partitions
+            .values()
+            .map(|p| (p.id, p.col_names().iter().map(|c| c.to_string()).collect()))
+            .collect::<Vec<(PartitionID, Vec<String>)>>();
+        self.storage.delete_partitions(&deleted);
+        for (id, column_names) in deleted {
+            for column_name in column_names {
+                self.lru.remove(&(id, column_name));
+            }
+        }
+    }
+
     /*fn load_buffer(&self, buffer: Buffer) {
         self.load_batch(buffer.into());
     }*/
 
-    fn persist_batch(&self, _batch: &Buffer) {}
+    fn persist_batch(&self, id: PartitionID, columns: &[Arc<Column>]) {
+        self.storage.store_partition(id, &self.name, columns);
+    }
 
     pub fn mem_tree(&self, depth: usize) -> MemTreeTable {
         assert!(depth > 0);
@@ -157,6 +469,8 @@ impl Table {
     pub fn stats(&self) -> TableStats {
         let partitions = self.snapshot();
         let size_per_column = Table::size_per_column(&partitions);
+        let types_per_column = Table::types_per_column(&partitions);
+        let value_range_per_column = Table::value_range_per_column(&partitions);
         let buffer = self.buffer.lock().unwrap();
         TableStats {
             name: self.name().to_string(),
@@ -169,9 +483,16 @@ impl Table {
             buffer_length: buffer.len(),
             buffer_bytes: buffer.heap_size_of_children(),
             size_per_column,
+            types_per_column,
+            value_range_per_column,
         }
     }
 
+    /// Types of each column across all partitions, including buffered rows.
+    pub fn column_types(&self) -> Vec<ColumnTypes> {
+        Table::types_per_column(&self.snapshot())
+    }
+
     pub fn heap_size_of_children(&self) -> usize {
         let batches_size: usize = {
             let batches = self.partitions.read().unwrap();
@@ -204,15 +525,44 @@ impl Table {
             .map(|(name, size)| (name.to_string(), *size))
             .collect()
     }
-}
 
-fn batch_size_override(batch_size: usize, tablename: &str) -> usize {
-    if tablename == "_meta_tables" {
-        1
-    } else if tablename == "_meta_queries" {
-        10
-    } else {
-        batch_size
+    fn value_range_per_column(partitions: &[Arc<Partition>]) -> Vec<(String, (i64, i64))> {
+        let mut ranges: HashMap<String, (i64, i64)> = HashMap::default();
+        for partition in partitions {
+            for (colname, (min, max)) in partition.value_range_per_column() {
+                let range = ranges.entry(colname).or_insert((min, max));
+                *range = (range.0.min(min), range.1.max(max));
+            }
+        }
+        let mut ranges = ranges.into_iter().collect::<Vec<_>>();
+        ranges.sort();
+        ranges
+    }
+
+    fn types_per_column(partitions: &[Arc<Partition>]) -> Vec<ColumnTypes> {
+        let mut types: HashMap<String, ColumnTypes> = HashMap::default();
+        for partition in partitions {
+            for (colname, decoded, encoding) in partition.types_per_column() {
+                let column = types.entry(colname.clone()).or_insert_with(|| ColumnTypes {
+                    name: colname,
+                    types: vec![],
+                    encodings: vec![],
+                });
+                if !column.types.contains(&decoded) {
+                    column.types.push(decoded);
+                }
+                if !column.encodings.contains(&encoding) {
+                    column.encodings.push(encoding);
+                }
+            }
+        }
+        let mut types = types.into_values().collect::<Vec<_>>();
+        for column in &mut types {
+            column.types.sort_by_key(|t| format!("{:?}", t));
+            column.encodings.sort_by_key(|t| format!("{:?}", t));
+        }
+        types.sort_by(|a, b| a.name.cmp(&b.name));
+        types
     }
 }
 
@@ -222,6 +572,63 @@ pub struct Metadata {
     pub batch_count: u64,
 }
 
+/// Checks that `row` can be pushed into a `Buffer` without misaligning its columns.
+pub fn validate_row(row: &[(String, RawVal)]) -> Result<(), IngestError> {
+    let mut names = HashSet::with_capacity(row.len());
+    for (name, _) in row {
+        if name.is_empty() {
+            return Err(IngestError::EmptyColumnName);
+        }
+        if !names.insert(name) {
+            return Err(IngestError::DuplicateColumn(name.clone()));
+        }
+    }
+    Ok(())
+}
+
+/// Validates all `rows`, returning the index and error of each invalid row.
+pub fn validate_rows(rows: &[Vec<(String, RawVal)>]) -> Result<(), Vec<(usize, IngestError)>> {
+    let errors = rows
+        .iter()
+        .enumerate()
+        .filter_map(|(i, row)| validate_row(row).err().map(|err| (i, err)))
+        .collect::<Vec<_>>();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn timestamp_values(row: &[(String, RawVal)]) -> impl Iterator<Item = &String> {
+    row.iter().filter(|(_, val)| matches!(val, RawVal::Timestamp(_))).map(|(name, _)| name)
+}
+
+fn input_column_len(column: &InputColumn) -> usize {
+    match column {
+        InputColumn::Int(vec) | InputColumn::Timestamp(vec) => vec.len(),
+        InputColumn::Float(vec) => vec.len(),
+        InputColumn::Str(vec) => vec.len(),
+        InputColumn::Bool(vec) => vec.len(),
+        InputColumn::Null(len) => *len,
+    }
+}
+
+fn unix_millis() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as i64
+}
+
+fn typed_timestamp_columns(columns: &HashMap<String, InputColumn>) -> impl Iterator<Item = &String> {
+    columns.iter().filter(|(_, col)| matches!(col, InputColumn::Timestamp(_))).map(|(name, _)| name)
+}
+
+fn untyped_timestamp_columns(columns: &HashMap<String, Vec<RawVal>>) -> impl Iterator<Item = &String> {
+    columns
+        .iter()
+        .filter(|(_, vals)| vals.iter().any(|val| matches!(val, RawVal::Timestamp(_))))
+        .map(|(name, _)| name)
+}
+
 #[derive(Debug)]
 pub struct TableStats {
     pub name: String,
@@ -231,4 +638,345 @@ pub struct TableStats {
     pub buffer_length: usize,
     pub buffer_bytes: usize,
     pub size_per_column: Vec<(String, usize)>,
+    pub types_per_column: Vec<ColumnTypes>,
+    /// Minimum and maximum value of integer columns across all resident partitions.
+    pub value_range_per_column: Vec<(String, (i64, i64))>,
+}
+
+/// Types of a column across all resident partitions of a table.
+#[derive(Debug, Clone)]
+pub struct ColumnTypes {
+    pub name: String,
+    /// Decoded types, e.g. `I64` or `NullableStr`.
+    pub types: Vec<EncodingType>,
+    /// Physical encodings, e.g. `U8` for a dictionary encoded string column.
+    pub encodings: Vec<EncodingType>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scheduler::inner_locustdb::InnerLocustDB;
+
+    #[derive(Default)]
+    struct RecordingStore {
+        stored: Mutex<Vec<(PartitionID, String, usize)>>,
+        replaced: Mutex<Vec<PartitionID>>,
+        deleted: Mutex<Vec<PartitionID>>,
+        tombstones: Mutex<Vec<(PartitionID, Vec<bool>)>>,
+    }
+
+    impl DiskStore for RecordingStore {
+        fn load_metadata(&self) -> Vec<PartitionMetadata> { vec![] }
+        fn load_column(&self, _: PartitionID, _: &str) -> Column { unimplemented!() }
+        fn load_column_range(&self, _: PartitionID, _: PartitionID, _: &str, _: &InnerLocustDB) { unimplemented!() }
+        fn bulk_load(&self, _: &InnerLocustDB) { unimplemented!() }
+        fn store_partition(&self, partition: PartitionID, tablename: &str, columns: &[Arc<Column>]) {
+            self.stored.lock().unwrap().push((partition, tablename.to_string(), columns.len()));
+        }
+        fn replace_partitions(
+            &self,
+            partition: PartitionID,
+            tablename: &str,
+            columns: &[Arc<Column>],
+            replaced: &[(PartitionID, Vec<String>)],
+        ) {
+            self.store_partition(partition, tablename, columns);
+            self.replaced.lock().unwrap().extend(replaced.iter().map(|(id, _)| *id));
+        }
+        fn delete_partitions(&self, partitions: &[(PartitionID, Vec<String>)]) {
+            self.deleted.lock().unwrap().extend(partitions.iter().map(|(id, _)| *id));
+        }
+        fn store_tombstones(&self, partition: PartitionID, deleted: &[bool]) {
+            self.tombstones.lock().unwrap().push((partition, deleted.to_vec()));
+        }
+        fn partition_sizes(&self) -> Vec<PartitionSize> { vec![] }
+    }
+
+    #[test]
+    fn test_persist_batch() {
+        let store = Arc::new(RecordingStore::default());
+        let table = Table::new(2, HashMap::new(), "t", Lru::default(), store.clone(), Arc::new(AtomicUsize::new(5)));
+        for i in 0..5 {
+            table.ingest(vec![("a".to_string(), RawVal::Int(i)), ("b".to_string(), RawVal::Null)]).unwrap();
+        }
+        assert_eq!(
+            *store.stored.lock().unwrap(),
+            vec![(5, "t".to_string(), 2), (6, "t".to_string(), 2)]
+        );
+        assert_eq!(table.max_partition_id(), 6);
+    }
+
+    #[test]
+    fn test_stop_flushes_buffers() {
+        let store = Arc::new(RecordingStore::default());
+        let locustdb = InnerLocustDB::new(store.clone(), &crate::locustdb::Options::default());
+        locustdb.ingest("t", vec![("a".to_string(), RawVal::Int(1))]).unwrap();
+        let stored_t = || store.stored.lock().unwrap().iter().filter(|(_, t, _)| t == "t").count();
+        assert_eq!(stored_t(), 0);
+        locustdb.stop();
+        assert_eq!(stored_t(), 1);
+        assert_eq!(locustdb.stats().iter().map(|t| t.buffer_length).sum::<usize>(), 0);
+    }
+
+    #[test]
+    fn test_wal_replay() {
+        let wal_dir = tempfile::tempdir().unwrap();
+        let opts = crate::locustdb::Options {
+            wal_path: Some(wal_dir.path().to_path_buf()),
+            wal_fsync_interval: None,
+            ..Default::default()
+        };
+        let locustdb = InnerLocustDB::new(Arc::new(RecordingStore::default()), &opts);
+        locustdb.ingest("t", vec![("a".to_string(), RawVal::Int(1))]).unwrap();
+        locustdb.ingest_homogeneous("t", vec![("a".to_string(), InputColumn::Int(vec![2, 3]))].into_iter().collect());
+        locustdb.ingest_heterogeneous("t", vec![("b".to_string(), vec![RawVal::Timestamp(4)])].into_iter().collect());
+        // Simulates a crash, the buffered rows are never stored as a partition
+        drop(locustdb);
+
+        let locustdb = InnerLocustDB::new(Arc::new(RecordingStore::default()), &opts);
+        let buffered = |ldb: &InnerLocustDB| ldb.stats().iter().find(|t| t.name == "t").unwrap().buffer_length;
+        assert_eq!(buffered(&locustdb), 4);
+        assert!(locustdb.timestamp_columns("t").contains("b"));
+        locustdb.flush();
+        drop(locustdb);
+
+        let locustdb = InnerLocustDB::new(Arc::new(RecordingStore::default()), &opts);
+        assert_eq!(buffered(&locustdb), 0);
+        assert!(locustdb.drop_table("t").unwrap());
+        assert!(locustdb.snapshot("t").is_none());
+        drop(locustdb);
+        let locustdb = InnerLocustDB::new(Arc::new(RecordingStore::default()), &opts);
+        assert!(locustdb.snapshot("t").is_none());
+    }
+
+    #[test]
+    fn test_wal_replay_non_finite_floats() {
+        use ordered_float::OrderedFloat;
+        let wal_dir = tempfile::tempdir().unwrap();
+        let wal = Wal::open(wal_dir.path(), "t", true).unwrap();
+        let rows = vec![
+            vec![("f".to_string(), RawVal::Float(OrderedFloat(f64::NAN)))],
+            vec![("f".to_string(), RawVal::Float(OrderedFloat(f64::INFINITY)))],
+        ];
+        wal.append(&WalRecord::Rows(&rows));
+        let columns = vec![("f".to_string(), InputColumn::Float(vec![f64::NEG_INFINITY, 1.5, f64::NAN]))].into_iter().collect();
+        wal.append(&WalRecord::TypedColumns(&columns));
+        let last = vec![vec![("f".to_string(), RawVal::Float(OrderedFloat(2.0)))]];
+        wal.append(&WalRecord::Rows(&last));
+        drop(wal);
+
+        let mut entries = Wal::replay(wal_dir.path()).unwrap().remove("t").unwrap();
+        assert_eq!(entries.len(), 3);
+        match entries.remove(0) {
+            WalEntry::Rows(replayed) => assert_eq!(replayed, rows),
+            _ => panic!("Expected rows"),
+        }
+        match entries.remove(0) {
+            WalEntry::TypedColumns(mut replayed) => match replayed.remove("f") {
+                Some(InputColumn::Float(values)) => {
+                    assert_eq!(values[..2], [f64::NEG_INFINITY, 1.5]);
+                    assert!(values[2].is_nan());
+                }
+                _ => panic!("Expected float column"),
+            },
+            _ => panic!("Expected typed columns"),
+        }
+        match entries.remove(0) {
+            WalEntry::Rows(replayed) => assert_eq!(replayed, last),
+            _ => panic!("Expected rows"),
+        }
+    }
+
+    #[test]
+    fn test_force_evict() {
+        let locustdb = InnerLocustDB::new(Arc::new(RecordingStore::default()), &crate::locustdb::Options::default());
+        for i in 0..100 {
+            locustdb.ingest("t", vec![("a".to_string(), RawVal::Int(i)), ("s".to_string(), RawVal::Str(format!("s{}", i)))]).unwrap();
+        }
+        locustdb.flush();
+        let mem_usage = || locustdb.stats().iter().map(|t| t.batches_bytes).sum::<usize>();
+        let before = mem_usage();
+        assert!(before > 0);
+        assert_eq!(locustdb.force_evict(before), 0);
+        let freed = locustdb.force_evict(0);
+        assert_eq!(freed, before);
+        assert_eq!(mem_usage(), 0);
+        assert_eq!(locustdb.force_evict(0), 0);
+    }
+
+    #[test]
+    fn test_compact() {
+        let store = Arc::new(RecordingStore::default());
+        let opts = crate::locustdb::Options { compact_below_rows: 5, ..Default::default() };
+        let locustdb = InnerLocustDB::new(store.clone(), &opts);
+        for i in 0..4 {
+            locustdb.ingest("t", vec![("a".to_string(), RawVal::Int(2 * i)), ("s".to_string(), RawVal::Str("x".to_string()))]).unwrap();
+            locustdb.ingest("t", vec![("a".to_string(), RawVal::Int(2 * i + 1))]).unwrap();
+            locustdb.flush();
+        }
+        let ids = |ldb: &InnerLocustDB| {
+            let mut partitions = ldb.snapshot("t").unwrap();
+            partitions.sort_by_key(|p| p.id);
+            partitions.iter().map(|p| (p.id, p.len())).collect::<Vec<_>>()
+        };
+        let before = ids(&locustdb);
+        assert_eq!(before.iter().map(|p| p.1).collect::<Vec<_>>(), vec![2, 2, 2, 2]);
+
+        assert_eq!(locustdb.compact("t").unwrap(), 3);
+        let after = ids(&locustdb);
+        assert_eq!(after.len(), 2);
+        assert_eq!(after[0], before[3]);
+        assert_eq!(after[1].1, 6);
+        assert_eq!(*store.replaced.lock().unwrap(), vec![before[0].0, before[1].0, before[2].0]);
+        assert_eq!(locustdb.compact("t").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_delete_rows() {
+        let store = Arc::new(RecordingStore::default());
+        let locustdb = InnerLocustDB::new(store.clone(), &crate::locustdb::Options::default());
+        for i in 0..4 {
+            let row = vec![
+                ("a".to_string(), RawVal::Int(i)),
+                ("_deleted".to_string(), RawVal::Int(i % 2)),
+                ("_row_index".to_string(), RawVal::Int(10 + i)),
+            ];
+            locustdb.ingest("t", row).unwrap();
+            if i % 2 == 1 {
+                locustdb.flush();
+            }
+        }
+        let mut before = locustdb.snapshot("t").unwrap();
+        before.sort_by_key(|p| p.id);
+
+        let filter = crate::syntax::parser::parse_expr("_row_index = 10 OR (a > 1 AND _deleted = 1)").unwrap();
+        assert_eq!(locustdb.delete_rows("t", &filter).unwrap(), 2);
+        // Tombstones are stored before the DELETE returns, the partitions are only rewritten by compaction
+        assert_eq!(
+            *store.tombstones.lock().unwrap(),
+            vec![(before[0].id, vec![true, false]), (before[1].id, vec![false, true])]
+        );
+        assert!(store.replaced.lock().unwrap().is_empty());
+        assert_eq!(before.iter().map(|p| p.deleted_rows()).collect::<Vec<_>>(), vec![1, 1]);
+
+        // Rows that are already deleted are not counted again
+        let filter = crate::syntax::parser::parse_expr("_deleted = 1").unwrap();
+        assert_eq!(locustdb.delete_rows("t", &filter).unwrap(), 1);
+        assert_eq!(before[0].tombstones(), Some(vec![true, true]));
+
+        assert_eq!(locustdb.compact("t").unwrap(), 2);
+        assert_eq!(*store.deleted.lock().unwrap(), vec![before[0].id]);
+        assert_eq!(*store.replaced.lock().unwrap(), vec![before[1].id]);
+        let after = locustdb.snapshot("t").unwrap();
+        assert_eq!(after.iter().map(|p| (p.len(), p.deleted_rows())).collect::<Vec<_>>(), vec![(1, 0)]);
+        let filter = crate::syntax::parser::parse_expr("_deleted = 0 AND _row_index = 12").unwrap();
+        assert_eq!(locustdb.delete_rows("t", &filter).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_restore_tombstones() {
+        let table = Table::new(10, HashMap::new(), "t", Lru::default(), Arc::new(RecordingStore::default()), Arc::new(AtomicUsize::new(0)));
+        table.insert_nonresident_partition(&PartitionMetadata {
+            id: 3,
+            tablename: "t".to_string(),
+            len: 3,
+            columns: vec![],
+            tombstones: Some(vec![false, true, true]),
+        });
+        assert_eq!(table.partitions()[0].deleted_rows(), 2);
+        assert_eq!(table.compaction_candidates(0).len(), 1);
+    }
+
+    #[test]
+    fn test_drop_table() {
+        let store = Arc::new(RecordingStore::default());
+        let locustdb = InnerLocustDB::new(store.clone(), &crate::locustdb::Options::default());
+        locustdb.ingest("t", vec![("a".to_string(), RawVal::Int(1))]).unwrap();
+        locustdb.flush();
+        let stored = store.stored.lock().unwrap().iter().filter(|(_, t, _)| t == "t").map(|p| p.0).collect::<Vec<_>>();
+        let snapshot = locustdb.snapshot("t").unwrap();
+
+        assert!(locustdb.drop_table("t").unwrap());
+        assert!(locustdb.snapshot("t").is_none());
+        assert!(!locustdb.drop_table("t").unwrap());
+        assert!(locustdb.drop_table("_meta_tables").is_err());
+        thread::sleep(Duration::from_millis(50));
+        assert!(store.deleted.lock().unwrap().is_empty());
+
+        drop(snapshot);
+        for _ in 0..100 {
+            if !store.deleted.lock().unwrap().is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(*store.deleted.lock().unwrap(), stored);
+    }
+
+    #[test]
+    fn test_column_encodings() {
+        let store = Arc::new(RecordingStore::default());
+        let encodings = vec![
+            ("dict".to_string(), ColumnEncoding::Dictionary),
+            ("wide".to_string(), ColumnEncoding::U32),
+            ("*".to_string(), ColumnEncoding::Raw),
+        ];
+        let table = Table::new(300, encodings.into_iter().collect(), "t", Lru::default(), store.clone(), Arc::new(AtomicUsize::new(0)));
+        let default = Table::new(300, HashMap::new(), "d", Lru::default(), store, Arc::new(AtomicUsize::new(1)));
+        for i in 0..300 {
+            let row = vec![
+                ("dict".to_string(), RawVal::Str(format!("s{}", i))),
+                ("wide".to_string(), RawVal::Int(i)),
+                ("int".to_string(), RawVal::Int(i)),
+                ("str".to_string(), RawVal::Str(if i % 2 == 0 { "even" } else { "odd" }.to_string())),
+            ];
+            table.ingest(row.clone()).unwrap();
+            default.ingest(row).unwrap();
+        }
+        let encodings = |table: &Table| {
+            let mut types = table.snapshot()[0].types_per_column();
+            types.sort_by(|a, b| a.0.cmp(&b.0));
+            types.into_iter().map(|(name, _, encoding)| (name, encoding)).collect::<Vec<_>>()
+        };
+        let size = |table: &Table, column: &str| {
+            table.snapshot()[0].heap_size_per_column().into_iter().find(|(name, _)| name == column).unwrap().1
+        };
+        assert_eq!(
+            encodings(&default),
+            vec![
+                ("dict".to_string(), EncodingType::U8),
+                ("int".to_string(), EncodingType::U16),
+                ("str".to_string(), EncodingType::U8),
+                ("wide".to_string(), EncodingType::U16),
+            ]
+        );
+        assert_eq!(
+            encodings(&table),
+            vec![
+                ("dict".to_string(), EncodingType::U16),
+                ("int".to_string(), EncodingType::I64),
+                ("str".to_string(), EncodingType::U8),
+                ("wide".to_string(), EncodingType::U32),
+            ]
+        );
+        // Raw strings are not dictionary encoded
+        assert!(size(&table, "str") > 2 * size(&default, "str"));
+    }
+
+    #[test]
+    fn test_types_per_column() {
+        let store = Arc::new(RecordingStore::default());
+        let table = Table::new(2, HashMap::new(), "t", Lru::default(), store, Arc::new(AtomicUsize::new(0)));
+        table.ingest(vec![("a".to_string(), RawVal::Int(1)), ("s".to_string(), RawVal::Str("x".to_string()))]).unwrap();
+        table.ingest(vec![("a".to_string(), RawVal::Int(2)), ("s".to_string(), RawVal::Str("y".to_string()))]).unwrap();
+        table.ingest(vec![("a".to_string(), RawVal::Str("z".to_string()))]).unwrap();
+        assert_eq!(table.stats().value_range_per_column, vec![("a".to_string(), (1, 2))]);
+        let types = table.stats().types_per_column;
+        assert_eq!(types.len(), 2);
+        assert_eq!(types[0].name, "a");
+        assert_eq!(types[0].types, vec![EncodingType::I64, EncodingType::Str]);
+        assert_eq!(types[1].name, "s");
+        assert_eq!(types[1].types, vec![EncodingType::Str]);
+    }
 }
@@ -1,8 +1,11 @@
 use std::collections::HashMap;
 use std::fmt;
 use std::f64;
+use serde::Serialize;
+
 use crate::unit_fmt::*;
 
+#[derive(Serialize)]
 pub struct MemTreeTable {
     pub name: String,
     pub size_bytes: usize,
@@ -11,6 +14,7 @@ pub struct MemTreeTable {
     pub columns: HashMap<String, MemTreeColumn>,
 }
 
+#[derive(Serialize)]
 pub struct MemTreeColumn {
     pub name: String,
     pub size_bytes: usize,
@@ -21,7 +25,7 @@ pub struct MemTreeColumn {
     pub encodings: HashMap<String, MemTreeEncoding>,
 }
 
-#[derive(Default)]
+#[derive(Default, Serialize)]
 pub struct MemTreeEncoding {
     pub codec: String,
     pub size_bytes: usize,
@@ -33,6 +37,7 @@ pub struct MemTreeEncoding {
     pub sections: Vec<MemTreeSection>,
 }
 
+#[derive(Serialize)]
 pub struct MemTreeSection {
     pub id: usize,
     pub size_bytes: usize,
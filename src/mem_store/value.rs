@@ -66,7 +66,8 @@ impl<'a, 'b> From<&'a Val<'b>> for RawVal {
         match *val {
             Val::Integer(b) => RawVal::Int(b),
             Val::Str(s) => RawVal::Str(s.to_string()),
-            Val::Null | Val::Bool(_) => RawVal::Null,
+            Val::Bool(b) => RawVal::Bool(b),
+            Val::Null => RawVal::Null,
             Val::Float(f) => RawVal::Float(f),
         }
     }
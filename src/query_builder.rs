@@ -0,0 +1,157 @@
+use std::time::Duration;
+
+use futures::channel::oneshot;
+
+use crate::engine::{ColumnInfo, Query};
+use crate::ingest::raw_val::RawVal;
+use crate::locustdb::LocustDB;
+use crate::syntax::expression::{Expr, Func2Type};
+use crate::syntax::limit::LimitClause;
+use crate::QueryError;
+use crate::QueryResult;
+
+/// Constructs a `Query` without going through SQL, created by `LocustDB::select`.
+///
+/// As with SQL queries, results are implicitly grouped by all selected columns that do not contain
+/// an aggregation and at most 100 rows are returned unless `limit` is called.
+pub struct QueryBuilder<'a> {
+    db: &'a LocustDB,
+    select: Vec<ColumnInfo>,
+    table: Option<String>,
+    filter: Option<Expr>,
+    group_by: Vec<String>,
+    having: Option<Expr>,
+    order_by: Vec<(Expr, bool, bool)>,
+    limit: LimitClause,
+    timeout: Option<Duration>,
+}
+
+impl<'a> QueryBuilder<'a> {
+    pub fn new(db: &'a LocustDB) -> QueryBuilder<'a> {
+        QueryBuilder {
+            db,
+            select: vec![],
+            table: None,
+            filter: None,
+            group_by: vec![],
+            having: None,
+            order_by: vec![],
+            limit: LimitClause { limit: 100, offset: 0 },
+            timeout: None,
+        }
+    }
+
+    /// Selects the columns with the given names, `*` selects all columns.
+    pub fn select(mut self, columns: &[&str]) -> QueryBuilder<'a> {
+        for &column in columns {
+            self.select.push(ColumnInfo {
+                expr: Expr::col(column),
+                name: if column == "*" { None } else { Some(column.to_string()) },
+            });
+        }
+        self
+    }
+
+    /// Selects the result of `expr` as a column called `name`.
+    pub fn column(mut self, expr: Expr, name: &str) -> QueryBuilder<'a> {
+        self.select.push(ColumnInfo { expr, name: Some(name.to_string()) });
+        self
+    }
+
+    /// Selects the result of `expr` as a column that is named after the expression, e.g. `SUM(id) * 2`.
+    pub fn expr(mut self, expr: Expr) -> QueryBuilder<'a> {
+        self.select.push(ColumnInfo { expr, name: None });
+        self
+    }
+
+    pub fn from(mut self, table: &str) -> QueryBuilder<'a> {
+        self.table = Some(table.to_string());
+        self
+    }
+
+    /// Only includes rows for which `expr` is true, multiple filters are combined with `AND`.
+    pub fn filter(mut self, expr: Expr) -> QueryBuilder<'a> {
+        self.filter = Some(and(self.filter.take(), expr));
+        self
+    }
+
+    /// Groups by the selected columns with the given names.
+    /// Grouping is determined by the selected columns, so every name must refer to one of them.
+    pub fn group_by(mut self, columns: &[&str]) -> QueryBuilder<'a> {
+        self.group_by.extend(columns.iter().map(|column| column.to_string()));
+        self
+    }
+
+    /// Only includes groups for which `expr` is true, multiple conditions are combined with `AND`.
+    pub fn having(mut self, expr: Expr) -> QueryBuilder<'a> {
+        self.having = Some(and(self.having.take(), expr));
+        self
+    }
+
+    pub fn order_by(mut self, expr: Expr, desc: bool) -> QueryBuilder<'a> {
+        self.order_by.push((expr, desc, desc));
+        self
+    }
+
+    pub fn limit(mut self, limit: u64) -> QueryBuilder<'a> {
+        self.limit.limit = limit;
+        self
+    }
+
+    pub fn offset(mut self, offset: u64) -> QueryBuilder<'a> {
+        self.limit.offset = offset;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> QueryBuilder<'a> {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Returns the query that `execute` would run.
+    pub fn build(&self) -> Result<Query, QueryError> {
+        let table = match &self.table {
+            Some(table) => table.clone(),
+            None => bail!(QueryError::ParseError, "No table specified (Hint: use `from` to select a table)"),
+        };
+        if self.select.is_empty() {
+            bail!(QueryError::ParseError, "No columns selected");
+        }
+        for column in &self.group_by {
+            let projected = self.select.iter().any(|info| {
+                info.name.as_ref() == Some(column)
+                    || matches!(&info.expr, Expr::ColName(name) if name == column)
+            });
+            if !projected {
+                bail!(
+                    QueryError::NotImplemented,
+                    "Group By {} (Hint: results are implicitly grouped by all selected columns that do not contain an aggregation, so grouping columns must also be selected.)",
+                    column
+                );
+            }
+        }
+        Ok(Query {
+            select: self.select.clone(),
+            table,
+            filter: self.filter.clone().unwrap_or(Expr::Const(RawVal::Int(1))),
+            having: self.having.clone().unwrap_or(Expr::Const(RawVal::Int(1))),
+            order_by: self.order_by.clone(),
+            limit: self.limit.clone(),
+            partition_filter: Expr::Const(RawVal::Int(1)),
+        })
+    }
+
+    pub async fn execute(self) -> Result<QueryResult, oneshot::Canceled> {
+        match self.build() {
+            Ok(query) => self.db.execute_parsed_query(query, false, vec![], self.timeout).await,
+            Err(err) => Ok(Err(err)),
+        }
+    }
+}
+
+fn and(lhs: Option<Expr>, rhs: Expr) -> Expr {
+    match lhs {
+        Some(lhs) => Expr::func(Func2Type::And, lhs, rhs),
+        None => rhs,
+    }
+}
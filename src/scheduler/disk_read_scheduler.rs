@@ -3,8 +3,11 @@
 
 use std::collections::VecDeque;
 use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Condvar, Mutex};
-use std_semaphore::Semaphore;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
 
 use crate::disk_store::interface::DiskStore;
 use crate::disk_store::interface::PartitionID;
@@ -12,17 +15,57 @@ use crate::mem_store::partition::ColumnHandle;
 use crate::mem_store::partition::Partition;
 use crate::mem_store::*;
 use crate::scheduler::inner_locustdb::InnerLocustDB;
+use crate::unit_fmt::*;
 
 pub struct DiskReadScheduler {
     disk_store: Arc<dyn DiskStore>,
     task_queue: Mutex<VecDeque<DiskRun>>,
-    reader_semaphore: Semaphore,
+    readers: Mutex<ReaderSlots>,
+    reader_released: Condvar,
     lru: Lru,
     #[allow(dead_code)]
     lz4_decode: bool,
 
     background_load_wait_queue: Condvar,
     background_load_in_progress: Mutex<bool>,
+
+    reads: AtomicU64,
+    bytes_read: AtomicU64,
+    read_nanos: AtomicU64,
+}
+
+/// Limits the number of concurrent disk reads, the limit can be changed while reads are in progress.
+#[derive(Default)]
+struct ReaderSlots {
+    max: usize,
+    active: usize,
+    waiting: usize,
+}
+
+/// Held for the duration of a disk read, releases the reader slot when dropped.
+struct ReaderToken<'a>(&'a DiskReadScheduler);
+
+impl<'a> Drop for ReaderToken<'a> {
+    fn drop(&mut self) {
+        self.0.readers.lock().unwrap().active -= 1;
+        self.0.reader_released.notify_one();
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiskReadStats {
+    /// Maximum number of concurrent disk reads.
+    pub read_threads: usize,
+    /// Sequential reads that have been scheduled but not started yet.
+    pub queued_runs: usize,
+    /// Reads that are waiting for a reader slot.
+    pub waiting_reads: usize,
+    pub active_reads: usize,
+    /// Completed reads, each of which loads one column of one or more partitions.
+    pub reads: u64,
+    pub bytes_read: u64,
+    pub total_read_latency_ms: f64,
+    pub mean_read_latency_ms: f64,
 }
 
 #[derive(Default, Debug)]
@@ -43,12 +86,66 @@ impl DiskReadScheduler {
         DiskReadScheduler {
             disk_store,
             task_queue: Mutex::default(),
-            reader_semaphore: Semaphore::new(max_readers as isize),
+            readers: Mutex::new(ReaderSlots { max: max_readers.max(1), ..ReaderSlots::default() }),
+            reader_released: Condvar::default(),
             lru,
             lz4_decode,
             background_load_wait_queue: Condvar::default(),
             background_load_in_progress: Mutex::default(),
+            reads: AtomicU64::new(0),
+            bytes_read: AtomicU64::new(0),
+            read_nanos: AtomicU64::new(0),
+        }
+    }
+
+    /// Changes the maximum number of concurrent disk reads, at least one read is always allowed.
+    /// Reads in progress are not interrupted when the limit is lowered.
+    pub fn set_read_threads(&self, read_threads: usize) {
+        self.readers.lock().unwrap().max = read_threads.max(1);
+        self.reader_released.notify_all();
+    }
+
+    pub fn read_threads(&self) -> usize {
+        self.readers.lock().unwrap().max
+    }
+
+    /// Number of reads that have not started yet, either scheduled sequential reads or reads waiting for a reader slot.
+    pub fn queue_depth(&self) -> usize {
+        self.task_queue.lock().unwrap().len() + self.readers.lock().unwrap().waiting
+    }
+
+    pub fn stats(&self) -> DiskReadStats {
+        let queued_runs = self.task_queue.lock().unwrap().len();
+        let readers = self.readers.lock().unwrap();
+        let reads = self.reads.load(Ordering::Relaxed);
+        let total_read_latency_ms = self.read_nanos.load(Ordering::Relaxed) as f64 / 1e6;
+        DiskReadStats {
+            read_threads: readers.max,
+            queued_runs,
+            waiting_reads: readers.waiting,
+            active_reads: readers.active,
+            reads,
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            total_read_latency_ms,
+            mean_read_latency_ms: if reads == 0 { 0.0 } else { total_read_latency_ms / reads as f64 },
+        }
+    }
+
+    fn acquire_reader(&self) -> ReaderToken {
+        let mut readers = self.readers.lock().unwrap();
+        readers.waiting += 1;
+        while readers.active >= readers.max {
+            readers = self.reader_released.wait(readers).unwrap();
         }
+        readers.waiting -= 1;
+        readers.active += 1;
+        ReaderToken(self)
+    }
+
+    fn record_read(&self, reads: usize, bytes: usize, start: Instant) {
+        self.reads.fetch_add(reads as u64, Ordering::Relaxed);
+        self.bytes_read.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.read_nanos.fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
     }
 
     pub fn schedule_sequential_read(
@@ -147,8 +244,19 @@ impl DiskReadScheduler {
                 debug!("Point lookup for {}.{}", handle.name(), handle.id());
                 #[allow(unused_mut)]
                 let mut column = {
-                    let _token = self.reader_semaphore.access();
-                    self.disk_store.load_column(handle.id(), handle.name())
+                    let _token = self.acquire_reader();
+                    // Includes decompression by the storage backend
+                    let start = Instant::now();
+                    let column = self.disk_store.load_column(handle.id(), handle.name());
+                    self.record_read(1, column.heap_size_of_children(), start);
+                    debug!(
+                        "Loaded {}.{} ({}) in {:?}",
+                        handle.name(),
+                        handle.id(),
+                        byte(column.heap_size_of_children() as f64),
+                        start.elapsed()
+                    );
+                    column
                 };
                 // Need to hold lock when we put new value into lru
                 let mut maybe_column = handle.try_get();
@@ -156,7 +264,9 @@ impl DiskReadScheduler {
                 #[cfg(feature = "enable_lz4")]
                 {
                     if self.lz4_decode {
+                        let start = Instant::now();
                         column.lz4_decode();
+                        debug!("Decoded {}.{} in {:?}", handle.name(), handle.id(), start.elapsed());
                         handle.update_size_bytes(column.heap_size_of_children());
                     }
                 }
@@ -190,11 +300,20 @@ impl DiskReadScheduler {
     }
 
     fn service_sequential_read(&self, run: &DiskRun, ldb: &InnerLocustDB) {
-        let _token = self.reader_semaphore.access();
+        let _token = self.acquire_reader();
         debug!("Servicing read: {:?}", &run);
+        let start = Instant::now();
         for col in &run.columns {
             self.disk_store
                 .load_column_range(run.start, run.end, col, ldb);
         }
+        self.record_read(run.columns.len(), run.bytes, start);
+        let elapsed = start.elapsed();
+        debug!(
+            "Read {} in {:?} ({}/s)",
+            byte(run.bytes as f64),
+            elapsed,
+            byte(run.bytes as f64 / elapsed.as_secs_f64().max(1e-9))
+        );
     }
 }
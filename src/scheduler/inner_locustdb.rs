@@ -1,4 +1,4 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::str;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Condvar, Mutex, RwLock};
@@ -7,15 +7,27 @@ use std::time::Duration;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::disk_store::interface::*;
+use crate::engine::query_task::{find_missing_col_types, QueryOutput, QueryStats};
+use crate::engine::{BasicType, ColumnInfo, Query, QueryLimits};
+use crate::errors::{IngestError, QueryError};
+use crate::ingest::buffer::Buffer;
 use crate::ingest::colgen::GenTable;
 use crate::ingest::input_column::InputColumn;
 use crate::ingest::raw_val::RawVal;
+use crate::ingest::wal::Wal;
 use crate::locustdb::Options;
-use crate::mem_store::partition::Partition;
+use crate::mem_store::column_builder::{null_column, ColumnBuilder, IntColBuilder};
+use crate::mem_store::partition::{deleted_column_name, Partition};
 use crate::mem_store::table::*;
 use crate::mem_store::*;
 use crate::scheduler::disk_read_scheduler::DiskReadScheduler;
 use crate::scheduler::*;
+use crate::syntax::expression::Expr;
+use crate::syntax::limit::LimitClause;
+
+/// Name of the column that contains the index of each row, used to determine which rows are removed by a `DELETE`.
+/// Underscores are appended if the `WHERE` clause references a column of the same name.
+const ROW_INDEX_COLUMN: &str = "_row_index";
 
 pub struct InnerLocustDB {
     tables: RwLock<HashMap<String, Table>>,
@@ -25,19 +37,62 @@ pub struct InnerLocustDB {
 
     opts: Options,
 
-    next_partition_id: AtomicUsize,
+    next_partition_id: Arc<AtomicUsize>,
     running: AtomicBool,
     idle_queue: Condvar,
     task_queue: Mutex<VecDeque<Arc<dyn Task>>>,
+    /// Number of tasks currently being executed by worker threads.
+    active_tasks: Mutex<usize>,
+    task_finished: Condvar,
+    /// Number of running worker threads and number of worker threads that should be running.
+    /// Workers exit once they are idle while there are more workers than requested.
+    worker_threads: AtomicUsize,
+    target_worker_threads: AtomicUsize,
+    running_queries: Arc<RunningQueries>,
+    result_cache: ResultCache,
+    /// Held while rows are deleted or partitions are compacted, so that a partition is not rewritten by both
+    /// at the same time.
+    compaction_lock: Mutex<()>,
 }
 
 impl InnerLocustDB {
     pub fn new(storage: Arc<dyn DiskStore>, opts: &Options) -> InnerLocustDB {
         let lru = Lru::default();
-        let existing_tables = Table::load_table_metadata(1 << 20, storage.as_ref(), &lru);
+        let next_partition_id = Arc::new(AtomicUsize::new(0));
+        let mut existing_tables = Table::load_table_metadata(
+            |table| opts.table_batch_size(table),
+            |table| opts.table_column_encodings(table),
+            &storage,
+            &lru,
+            &next_partition_id,
+        );
         let max_pid = existing_tables.values().map(|t| t.max_partition_id())
             .max()
             .unwrap_or(0);
+        next_partition_id.store(max_pid as usize + 1, Ordering::SeqCst);
+        let recovered = match &opts.wal_path {
+            Some(wal_path) => Wal::replay(wal_path).expect("Failed to read WAL"),
+            None => HashMap::default(),
+        };
+        for tablename in recovered.keys() {
+            existing_tables.entry(tablename.clone()).or_insert_with(|| {
+                Table::new(
+                    opts.table_batch_size(tablename),
+                    opts.table_column_encodings(tablename),
+                    tablename,
+                    lru.clone(),
+                    storage.clone(),
+                    next_partition_id.clone(),
+                )
+            });
+        }
+        for (tablename, table) in &mut existing_tables {
+            InnerLocustDB::configure_table(opts, tablename, table);
+        }
+        for (tablename, entries) in recovered {
+            info!("Replaying {} WAL entries of table {}", entries.len(), tablename);
+            existing_tables[&tablename].replay(entries);
+        }
         let disk_read_scheduler = Arc::new(DiskReadScheduler::new(
             storage.clone(),
             lru.clone(),
@@ -54,19 +109,30 @@ impl InnerLocustDB {
 
             opts: opts.clone(),
 
-            next_partition_id: AtomicUsize::new(max_pid as usize + 1),
+            next_partition_id,
             idle_queue: Condvar::new(),
             task_queue: Mutex::new(VecDeque::new()),
+            active_tasks: Mutex::new(0),
+            task_finished: Condvar::new(),
+            worker_threads: AtomicUsize::new(0),
+            target_worker_threads: AtomicUsize::new(0),
+            running_queries: Arc::default(),
+            result_cache: ResultCache::new(opts.result_cache_size),
+            compaction_lock: Mutex::default(),
         }
     }
 
     pub fn start_worker_threads(locustdb: &Arc<InnerLocustDB>) {
-        for _ in 0..locustdb.opts.threads {
+        InnerLocustDB::set_worker_threads(locustdb, locustdb.opts.threads);
+        let cloned = locustdb.clone();
+        thread::spawn(move || InnerLocustDB::enforce_mem_limit(&cloned));
+        if let (Some(_), Some(interval)) = (&locustdb.opts.wal_path, locustdb.opts.wal_fsync_interval) {
             let cloned = locustdb.clone();
-            thread::spawn(move || InnerLocustDB::worker_loop(cloned));
+            thread::spawn(move || InnerLocustDB::sync_wal(&cloned, interval));
         }
+        // Also removes deleted rows if merging small partitions is disabled
         let cloned = locustdb.clone();
-        thread::spawn(move || InnerLocustDB::enforce_mem_limit(&cloned));
+        thread::spawn(move || InnerLocustDB::compact_partitions(&cloned));
     }
 
     pub fn snapshot(&self, table: &str) -> Option<Vec<Arc<Partition>>> {
@@ -74,23 +140,119 @@ impl InnerLocustDB {
         tables.get(table).map(|t| t.snapshot())
     }
 
+    /// Names of the columns of `table` that contain timestamps.
+    pub fn timestamp_columns(&self, table: &str) -> HashSet<String> {
+        let tables = self.tables.read().unwrap();
+        tables.get(table).map(|t| t.timestamp_columns()).unwrap_or_default()
+    }
+
+    /// Adds a query to the running queries until the returned registration is dropped.
+    pub fn register_query(&self, query: &str) -> QueryRegistration {
+        RunningQueries::register(&self.running_queries, query)
+    }
+
+    pub fn running_queries(&self) -> &RunningQueries {
+        &self.running_queries
+    }
+
+    pub fn result_cache(&self) -> &ResultCache {
+        &self.result_cache
+    }
+
+    /// Current version of each of the given tables, `None` for tables that do not exist.
+    pub fn table_versions(&self, tables: &[String]) -> Vec<(String, Option<u64>)> {
+        let all_tables = self.tables.read().unwrap();
+        tables
+            .iter()
+            .map(|table| (table.clone(), all_tables.get(table).map(|t| t.version())))
+            .collect()
+    }
+
     pub fn full_snapshot(&self) -> Vec<Vec<Arc<Partition>>> {
         let tables = self.tables.read().unwrap();
         tables.values().map(|t| t.snapshot()).collect()
     }
 
     pub fn stop(&self) {
-        // Acquire task_queue_guard to make sure that there are no threads that have checked self.running but not waited on idle_queue yet.
         info!("Stopping database...");
-        let _guard = self.task_queue.lock();
-        self.running.store(false, Ordering::SeqCst);
-        self.idle_queue.notify_all();
+        {
+            // Acquire task_queue_guard to make sure that there are no threads that have checked self.running but not waited on idle_queue yet.
+            let _guard = self.task_queue.lock();
+            self.running.store(false, Ordering::SeqCst);
+            self.idle_queue.notify_all();
+        }
+        // Tasks that were already picked up may still be ingesting data, wait for them before flushing
+        let mut active_tasks = self.active_tasks.lock().unwrap();
+        while *active_tasks > 0 {
+            active_tasks = self.task_finished.wait(active_tasks).unwrap();
+        }
+        drop(active_tasks);
+        self.flush();
+    }
+
+    /// Persists all buffered rows that have not been batched into a partition yet.
+    pub fn flush(&self) {
+        let tables = self.tables.read().unwrap();
+        for table in tables.values() {
+            table.flush();
+        }
+    }
+
+    /// Spawns or retires worker threads until `threads` workers are running, at least one worker is always kept.
+    /// Busy workers finish their current task before exiting.
+    pub fn set_worker_threads(locustdb: &Arc<InnerLocustDB>, threads: usize) {
+        let threads = threads.max(1);
+        // Holding the task queue lock ensures idle workers are either waiting on idle_queue or will observe the new target.
+        let _guard = locustdb.task_queue.lock().unwrap();
+        locustdb.target_worker_threads.store(threads, Ordering::SeqCst);
+        while locustdb.worker_threads.load(Ordering::SeqCst) < threads {
+            locustdb.worker_threads.fetch_add(1, Ordering::SeqCst);
+            let cloned = locustdb.clone();
+            thread::spawn(move || InnerLocustDB::worker_loop(cloned));
+        }
+        locustdb.idle_queue.notify_all();
+    }
+
+    /// Number of worker threads that are requested to be running.
+    pub fn worker_threads(&self) -> usize {
+        self.target_worker_threads.load(Ordering::SeqCst)
+    }
+
+    /// Number of tasks that are waiting for a worker thread.
+    pub fn queued_tasks(&self) -> usize {
+        self.task_queue.lock().unwrap().len()
+    }
+
+    fn excess_worker_threads(&self) -> bool {
+        self.worker_threads.load(Ordering::SeqCst) > self.target_worker_threads.load(Ordering::SeqCst)
+    }
+
+    /// Decrements the number of running workers if it exceeds the target, the calling worker must then exit.
+    fn retire_worker_thread(&self) -> bool {
+        let target = self.target_worker_threads.load(Ordering::SeqCst);
+        self.worker_threads
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |workers| {
+                if workers > target {
+                    Some(workers - 1)
+                } else {
+                    None
+                }
+            })
+            .is_ok()
     }
 
     fn worker_loop(locustdb: Arc<InnerLocustDB>) {
         while locustdb.running.load(Ordering::SeqCst) {
+            if locustdb.retire_worker_thread() {
+                break;
+            }
             if let Some(task) = InnerLocustDB::await_task(&locustdb) {
-                task.execute();
+                let _active = ActiveTask(&locustdb);
+                if task.cancelled() {
+                    task.cancel();
+                } else {
+                    task.execute();
+                }
             }
         }
         drop(locustdb) // Make clippy happy
@@ -99,11 +261,19 @@ impl InnerLocustDB {
     fn await_task(ldb: &Arc<InnerLocustDB>) -> Option<Arc<dyn Task>> {
         let mut task_queue = ldb.task_queue.lock().unwrap();
         while task_queue.is_empty() {
-            if !ldb.running.load(Ordering::SeqCst) {
+            if !ldb.running.load(Ordering::SeqCst) || ldb.excess_worker_threads() {
                 return None;
             }
             task_queue = ldb.idle_queue.wait(task_queue).unwrap();
         }
+        if !ldb.running.load(Ordering::SeqCst) {
+            return None;
+        }
+        if ldb.excess_worker_threads() {
+            // Pass the wakeup on to a worker that is not about to exit
+            ldb.idle_queue.notify_one();
+            return None;
+        }
         while let Some(task) = task_queue.pop_front() {
             if task.completed() {
                 continue;
@@ -114,6 +284,8 @@ impl InnerLocustDB {
             if !task_queue.is_empty() {
                 ldb.idle_queue.notify_one();
             }
+            // Register the task while holding the task queue lock so that `stop` cannot miss it
+            *ldb.active_tasks.lock().unwrap() += 1;
             return Some(task);
         }
         None
@@ -128,22 +300,28 @@ impl InnerLocustDB {
     }
 
     pub fn store_partition(&self, tablename: &str, partition: Vec<Arc<Column>>) {
-        self.create_if_empty(tablename);
-        let tables = self.tables.read().unwrap();
-        let table = tables.get(tablename).unwrap();
-        let pid = self.next_partition_id.fetch_add(1, Ordering::SeqCst) as u64;
-        self.storage.store_partition(pid, tablename, &partition);
-        let (new_partition, keys) = Partition::new(pid, partition, self.lru.clone());
-        table.load_partition(new_partition);
-        for key in keys {
-            self.lru.put(key);
-        }
+        self.with_table(tablename, |table| {
+            let pid = self.next_partition_id.fetch_add(1, Ordering::SeqCst) as u64;
+            self.storage.store_partition(pid, tablename, &partition);
+            let (new_partition, keys) = Partition::new(pid, partition, self.lru.clone());
+            table.load_partition(new_partition);
+            for key in keys {
+                self.lru.put(key);
+            }
+        })
     }
 
-    pub fn ingest(&self, table: &str, row: Vec<(String, RawVal)>) {
-        self.create_if_empty(table);
-        let tables = self.tables.read().unwrap();
-        tables.get(table).unwrap().ingest(row)
+    pub fn ingest(&self, table: &str, row: Vec<(String, RawVal)>) -> Result<(), IngestError> {
+        self.with_table(table, |table| table.ingest(row))
+    }
+
+    pub fn ingest_dedup(
+        &self,
+        table: &str,
+        rows: Vec<Vec<(String, RawVal)>>,
+        dedup_on: &[String],
+    ) -> Result<usize, Vec<(usize, IngestError)>> {
+        self.with_table(table, |table| table.ingest_dedup(rows, dedup_on))
     }
 
     pub fn restore(&self, id: PartitionID, column: Column) {
@@ -153,18 +331,42 @@ impl InnerLocustDB {
         }
     }
 
-    #[allow(dead_code)]
     pub fn ingest_homogeneous(&self, table: &str, columns: HashMap<String, InputColumn>) {
-        self.create_if_empty(table);
-        let tables = self.tables.read().unwrap();
-        tables.get(table).unwrap().ingest_homogeneous(columns)
+        self.with_table(table, |table| table.ingest_homogeneous(columns))
     }
 
-    #[allow(dead_code)]
     pub fn ingest_heterogeneous(&self, table: &str, columns: HashMap<String, Vec<RawVal>>) {
-        self.create_if_empty(table);
-        let tables = self.tables.read().unwrap();
-        tables.get(table).unwrap().ingest_heterogeneous(columns)
+        self.with_table(table, |table| table.ingest_heterogeneous(columns))
+    }
+
+    /// Removes `table` and records a tombstone in `_meta_tables`, returns false if the table does not exist.
+    /// Partitions are deleted from storage in the background once running queries have released their snapshot.
+    pub fn drop_table(&self, table: &str) -> Result<bool, QueryError> {
+        if table == "_meta_tables" {
+            bail!(QueryError::NotImplemented, "Cannot drop table {}", table);
+        }
+        let dropped = match self.tables.write().unwrap().remove(table) {
+            Some(mut dropped) => {
+                // Deleted while holding the lock, a table that is recreated with the same name reuses the path
+                dropped.delete_wal();
+                dropped
+            }
+            None => return Ok(false),
+        };
+        self.result_cache.invalidate(table);
+        thread::spawn(move || dropped.delete());
+        self.ingest(
+            "_meta_tables",
+            vec![
+                (
+                    "timestamp".to_string(),
+                    RawVal::Int(SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64)
+                ),
+                ("name".to_string(), RawVal::Str(table.to_string())),
+                ("dropped".to_string(), RawVal::Int(1)),
+            ],
+        ).expect("_meta_tables rows are valid");
+        Ok(true)
     }
 
     pub fn drop_pending_tasks(&self) {
@@ -177,77 +379,338 @@ impl InnerLocustDB {
         tables.values().map(|table| table.mem_tree(depth)).collect()
     }
 
+    /// Output of `SHOW TABLES`, the name of every table in alphabetical order.
+    pub fn show_tables(&self) -> QueryOutput {
+        let mut tables = self.tables.read().unwrap().keys().cloned().collect::<Vec<_>>();
+        tables.sort();
+        QueryOutput {
+            colnames: vec!["table".to_string()],
+            coltypes: vec!["string".to_string()],
+            rows: tables.into_iter().map(|table| vec![RawVal::Str(table)]).collect(),
+            query_plans: HashMap::default(),
+            stats: QueryStats::default(),
+        }
+    }
+
+    /// Output of `SHOW COLUMNS FROM table`, the name and types of every column in alphabetical order.
+    pub fn show_columns(&self, table: &str) -> Result<QueryOutput, QueryError> {
+        let columns = match self.tables.read().unwrap().get(table) {
+            Some(table) => table.column_types(),
+            None => bail!(QueryError::NotImplemented, "Table {} does not exist!", table),
+        };
+        let rows = columns
+            .into_iter()
+            .map(|column| {
+                let types = column.types.iter().map(|t| format!("{:?}", t)).collect::<Vec<_>>();
+                vec![RawVal::Str(column.name), RawVal::Str(types.join(", "))]
+            })
+            .collect();
+        Ok(QueryOutput {
+            colnames: vec!["column".to_string(), "types".to_string()],
+            coltypes: vec!["string".to_string(), "string".to_string()],
+            rows,
+            query_plans: HashMap::default(),
+            stats: QueryStats::default(),
+        })
+    }
+
     pub fn stats(&self) -> Vec<TableStats> {
         let tables = self.tables.read().unwrap();
         tables.values().map(|table| table.stats()).collect()
     }
 
+    pub fn disk_usage(&self) -> Vec<TableDiskUsage> {
+        TableDiskUsage::from_partition_sizes(self.storage.partition_sizes())
+    }
+
     pub fn gen_partition(&self, opts: &GenTable, p: u64) {
         opts.gen(self, p);
     }
 
+    fn with_table<T>(&self, table: &str, f: impl FnOnce(&Table) -> T) -> T {
+        loop {
+            self.create_if_empty(table);
+            let tables = self.tables.read().unwrap();
+            // Table may have been dropped again before acquiring the lock
+            if let Some(table) = tables.get(table) {
+                return f(table);
+            }
+        }
+    }
+
     fn create_if_empty(&self, table: &str) {
         let exists = {
             let tables = self.tables.read().unwrap();
             tables.contains_key(table)
         };
         if !exists {
-            {
-                let mut tables = self.tables.write().unwrap();
-                tables.insert(
-                    table.to_string(),
-                    Table::new(1 << 20, table, self.lru.clone()),
-                );
+            self.create_table(table, None);
+        }
+    }
+
+    pub fn table_exists(&self, table: &str) -> bool {
+        self.tables.read().unwrap().contains_key(table)
+    }
+
+    /// Creates `table` with the given or configured batch size, returns false if it already exists.
+    pub fn create_table(&self, table: &str, batch_size: Option<usize>) -> bool {
+        {
+            let mut tables = self.tables.write().unwrap();
+            if tables.contains_key(table) {
+                return false;
             }
-            self.ingest(
-                "_meta_tables",
-                vec![
-                    (
-                        "timestamp".to_string(),
-                        RawVal::Int(SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64)
-                    ),
-                    ("name".to_string(), RawVal::Str(table.to_string())),
-                ],
+            let mut new_table = Table::new(
+                batch_size.unwrap_or_else(|| self.opts.table_batch_size(table)),
+                self.opts.table_column_encodings(table),
+                table,
+                self.lru.clone(),
+                self.storage.clone(),
+                self.next_partition_id.clone(),
             );
+            InnerLocustDB::configure_table(&self.opts, table, &mut new_table);
+            tables.insert(table.to_string(), new_table);
         }
+        self.ingest(
+            "_meta_tables",
+            vec![
+                (
+                    "timestamp".to_string(),
+                    RawVal::Int(SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64)
+                ),
+                ("name".to_string(), RawVal::Str(table.to_string())),
+            ],
+        ).expect("_meta_tables rows are valid");
+        true
     }
 
     fn enforce_mem_limit(ldb: &Arc<InnerLocustDB>) {
         while ldb.running.load(Ordering::SeqCst) {
-            let mut mem_usage_bytes: usize = {
-                let tables = ldb.tables.read().unwrap();
-                tables
-                    .values()
-                    .map(|table| table.heap_size_of_children())
-                    .sum()
-            };
+            let mem_usage_bytes = ldb.table_mem_usage();
             if mem_usage_bytes > ldb.opts.mem_size_limit_tables {
                 info!("Evicting. mem_usage_bytes = {}", mem_usage_bytes);
-                while mem_usage_bytes > ldb.opts.mem_size_limit_tables {
-                    match ldb.lru.evict() {
-                        Some(victim) => {
-                            let tables = ldb.tables.read().unwrap();
-                            for t in tables.values() {
-                                mem_usage_bytes -= t.evict(&victim);
-                            }
-                        }
-                        None => {
-                            if ldb.opts.mem_size_limit_tables > 0 {
-                                warn!(
-                                    "Table memory usage is {} but failed to find column to evict!",
-                                    mem_usage_bytes
-                                );
-                            }
-                            break;
-                        }
+                let mem_usage_bytes = ldb.evict_until(mem_usage_bytes, ldb.opts.mem_size_limit_tables);
+                info!("mem_usage_bytes = {}", mem_usage_bytes);
+            }
+            thread::sleep(Duration::from_millis(1000));
+        }
+    }
+
+    /// Evicts least recently used columns until tables take up at most `target_bytes` of memory,
+    /// without waiting for the periodic check of `mem_size_limit_tables`. Returns the number of freed bytes.
+    pub fn force_evict(&self, target_bytes: usize) -> usize {
+        let mem_usage_bytes = self.table_mem_usage();
+        mem_usage_bytes - self.evict_until(mem_usage_bytes, target_bytes)
+    }
+
+    fn table_mem_usage(&self) -> usize {
+        let tables = self.tables.read().unwrap();
+        tables.values().map(|table| table.heap_size_of_children()).sum()
+    }
+
+    /// Evicts columns until `mem_usage_bytes` is at most `target_bytes` and returns the remaining memory usage.
+    fn evict_until(&self, mut mem_usage_bytes: usize, target_bytes: usize) -> usize {
+        while mem_usage_bytes > target_bytes {
+            match self.lru.evict() {
+                Some(victim) => {
+                    let tables = self.tables.read().unwrap();
+                    for t in tables.values() {
+                        mem_usage_bytes = mem_usage_bytes.saturating_sub(t.evict(&victim));
                     }
                 }
-                info!("mem_usage_bytes = {}", mem_usage_bytes);
+                None => {
+                    if target_bytes > 0 {
+                        warn!(
+                            "Table memory usage is {} but failed to find column to evict!",
+                            mem_usage_bytes
+                        );
+                    }
+                    break;
+                }
+            }
+        }
+        mem_usage_bytes
+    }
+
+    /// Applies the options that are configured per table, e.g. opens the write-ahead log of the table if the log is enabled.
+    fn configure_table(opts: &Options, tablename: &str, table: &mut Table) {
+        if let Some(wal_path) = &opts.wal_path {
+            table.set_wal(Wal::open(wal_path, tablename, opts.wal_fsync_interval.is_none()).expect("Failed to open WAL"));
+        }
+        table.set_ingest_timestamp(opts.ingest_timestamp_tables.contains(tablename));
+    }
+
+    fn sync_wal(ldb: &Arc<InnerLocustDB>, interval: Duration) {
+        while ldb.running.load(Ordering::SeqCst) {
+            thread::sleep(interval);
+            let tables = ldb.tables.read().unwrap();
+            for table in tables.values() {
+                table.sync_wal();
+            }
+        }
+    }
+
+    fn compact_partitions(ldb: &Arc<InnerLocustDB>) {
+        while ldb.running.load(Ordering::SeqCst) {
+            let tablenames = ldb.tables.read().unwrap().keys().cloned().collect::<Vec<_>>();
+            for tablename in tablenames {
+                if let Err(err) = ldb.compact(&tablename) {
+                    warn!("Failed to compact table {}: {:?}", tablename, err);
+                }
             }
             thread::sleep(Duration::from_millis(1000));
         }
     }
 
+    /// Merges runs of adjacent partitions smaller than `compact_below_rows`, rewrites partitions without their deleted rows
+    /// and returns the number of partitions that were replaced.
+    pub fn compact(&self, tablename: &str) -> Result<usize, QueryError> {
+        let _compaction = self.compaction_lock.lock().unwrap();
+        let runs = match self.tables.read().unwrap().get(tablename) {
+            Some(table) => table.compaction_candidates(self.opts.compact_below_rows),
+            None => return Ok(0),
+        };
+        let mut compacted = 0;
+        for run in runs {
+            let mut buffer = Buffer::default();
+            for partition in &run {
+                buffer.push_untyped_cols(self.decode_partition(tablename, partition)?);
+            }
+            match self.tables.read().unwrap().get(tablename) {
+                Some(table) => table.merge_partitions(&run, buffer),
+                None => break,
+            }
+            compacted += run.len();
+        }
+        Ok(compacted)
+    }
+
+    /// Marks the rows of `tablename` that match `filter` as deleted and returns the number of deleted rows.
+    /// Buffered rows are stored as a partition first. Deleted rows are excluded from queries and their tombstones are
+    /// persisted before this returns, but they are only removed from memory and disk once background compaction has
+    /// rewritten their partitions.
+    pub fn delete_rows(&self, tablename: &str, filter: &Expr) -> Result<usize, QueryError> {
+        let _compaction = self.compaction_lock.lock().unwrap();
+        let mut partitions = match self.tables.read().unwrap().get(tablename) {
+            Some(table) => {
+                table.flush();
+                table.partitions()
+            }
+            None => bail!(QueryError::NotImplemented, "Table {} does not exist!", tablename),
+        };
+        partitions.sort_by_key(|p| p.id);
+        let mut referenced_cols = HashSet::new();
+        filter.add_colnames(&mut referenced_cols);
+        let missing_col_types = find_missing_col_types(&partitions, &referenced_cols, &self.disk_read_scheduler);
+        let filter = filter.clone().convert_timestamps(&self.timestamp_columns(tablename));
+        // Constant expressions other than literals cannot be evaluated without referencing a column
+        let matches_all = match filter {
+            _ if !referenced_cols.is_empty() => None,
+            Expr::Const(RawVal::Int(x)) => Some(x != 0),
+            Expr::Const(RawVal::Bool(b)) => Some(b),
+            _ => bail!(QueryError::NotImplemented, "DELETE with a WHERE clause that does not reference any columns"),
+        };
+        let mut deleted = Vec::with_capacity(partitions.len());
+        for partition in partitions {
+            let rows = match matches_all {
+                Some(matches_all) => vec![matches_all; partition.len()],
+                None => self.matching_rows(tablename, &partition, &filter, &referenced_cols, &missing_col_types)?,
+            };
+            deleted.push((partition, rows));
+        }
+        let count = match self.tables.read().unwrap().get(tablename) {
+            Some(table) => table.delete_rows(&deleted),
+            None => 0,
+        };
+        if count > 0 {
+            self.result_cache.invalidate(tablename);
+        }
+        Ok(count)
+    }
+
+    /// Evaluates `filter` for each row of `partition`, rows for which it is null do not match.
+    /// The filter selects the index of matching rows from a column that contains the index of each row.
+    fn matching_rows(
+        &self,
+        tablename: &str,
+        partition: &Partition,
+        filter: &Expr,
+        referenced_cols: &HashSet<String>,
+        missing_col_types: &HashMap<String, BasicType>,
+    ) -> Result<Vec<bool>, QueryError> {
+        let mut cols = partition.get_cols(referenced_cols, &self.disk_read_scheduler);
+        for (name, &basic_type) in missing_col_types {
+            if !cols.contains_key(name) {
+                cols.insert(name.clone(), Arc::new(null_column(name, partition.len(), basic_type)));
+            }
+        }
+        let mut row_index_name = ROW_INDEX_COLUMN.to_string();
+        while referenced_cols.contains(&row_index_name) {
+            row_index_name.push('_');
+        }
+        let mut row_index = IntColBuilder::default();
+        for i in 0..partition.len() {
+            row_index.push(&Some(i as i64));
+        }
+        cols.insert(row_index_name.clone(), Arc::new(row_index.finalize(&row_index_name, None)));
+        let query = Query {
+            select: vec![ColumnInfo { expr: Expr::ColName(row_index_name), name: None }],
+            table: tablename.to_string(),
+            filter: filter.clone(),
+            having: Expr::Const(RawVal::Int(1)),
+            order_by: vec![],
+            limit: LimitClause { limit: partition.len() as u64, offset: 0 },
+            partition_filter: Expr::Const(RawVal::Int(1)),
+        };
+        let (main_phase, _) = query.normalize()?;
+        let (result, _) = main_phase.run(&cols, false, false, partition.id as usize, partition.len(), QueryLimits::default())?;
+        let row_index = &result.columns[result.projection[0]];
+        let mut matches = vec![false; partition.len()];
+        for i in 0..result.len() {
+            match row_index.get_raw(i) {
+                RawVal::Int(row) if (row as usize) < matches.len() => matches[row as usize] = true,
+                other => return Err(fatal!("Invalid row index {:?} when evaluating DELETE", other)),
+            }
+        }
+        Ok(matches)
+    }
+
+    fn decode_partition(
+        &self,
+        tablename: &str,
+        partition: &Partition,
+    ) -> Result<HashMap<String, Vec<RawVal>>, QueryError> {
+        let colnames = partition.col_names().iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        let mut cols = partition.get_cols(&colnames.iter().cloned().collect(), &self.disk_read_scheduler);
+        let deleted = partition.deleted_column();
+        let deleted_column = deleted_column_name(&colnames.iter().cloned().collect());
+        if let Some(deleted) = &deleted {
+            cols.insert(deleted_column.clone(), Arc::new(deleted.clone()));
+        }
+        let query = Query {
+            select: colnames
+                .iter()
+                .map(|name| ColumnInfo { expr: Expr::ColName(name.clone()), name: None })
+                .collect(),
+            table: tablename.to_string(),
+            filter: Expr::Const(RawVal::Int(1)),
+            having: Expr::Const(RawVal::Int(1)),
+            order_by: vec![],
+            limit: LimitClause { limit: partition.len() as u64, offset: 0 },
+            partition_filter: Expr::Const(RawVal::Int(1)),
+        };
+        let (mut main_phase, _) = query.normalize()?;
+        if deleted.is_some() {
+            main_phase = main_phase.without_deleted_rows(&deleted_column);
+        }
+        let (result, _) = main_phase.run(&cols, false, false, partition.id as usize, partition.len(), QueryLimits::default())?;
+        let mut columns = HashMap::with_capacity(colnames.len());
+        for (name, &j) in colnames.into_iter().zip(&result.projection) {
+            let values = (0..result.len()).map(|i| result.columns[j].get_raw(i)).collect();
+            columns.insert(name, values);
+        }
+        Ok(columns)
+    }
+
     pub fn max_partition_id(&self) -> u64 {
         self.next_partition_id.load(Ordering::SeqCst) as u64
     }
@@ -261,6 +724,16 @@ impl InnerLocustDB {
     }
 }
 
+/// Marks a task as in-flight until dropped, also when the task panics.
+struct ActiveTask<'a>(&'a InnerLocustDB);
+
+impl<'a> Drop for ActiveTask<'a> {
+    fn drop(&mut self) {
+        *self.0.active_tasks.lock().unwrap() -= 1;
+        self.0.task_finished.notify_all();
+    }
+}
+
 impl Drop for InnerLocustDB {
     fn drop(&mut self) {
         info!("Stopped");
@@ -1,8 +1,13 @@
+mod result_cache;
+mod running_queries;
 mod shared_sender;
 mod task;
 pub(crate) mod disk_read_scheduler;
 pub(crate) mod inner_locustdb;
 
+pub use self::disk_read_scheduler::DiskReadStats;
 pub use self::inner_locustdb::InnerLocustDB;
+pub use self::result_cache::{ResultCache, ResultCacheStats};
+pub use self::running_queries::{QueryRegistration, RunningQueries, RunningQueryInfo};
 pub use self::task::Task;
 pub use self::shared_sender::SharedSender;
\ No newline at end of file
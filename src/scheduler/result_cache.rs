@@ -0,0 +1,94 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+
+use crate::engine::query_task::QueryOutput;
+
+/// Outputs of recently executed queries, which are reused as long as none of the queried tables have changed.
+pub struct ResultCache {
+    entries: Mutex<LruCache<String, CachedResult>>,
+    capacity: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+struct CachedResult {
+    /// Version of each queried table at the time the query was started, `None` if the table did not exist.
+    table_versions: Vec<(String, Option<u64>)>,
+    output: QueryOutput,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResultCacheStats {
+    pub capacity: usize,
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl ResultCache {
+    /// Creates a cache holding up to `capacity` query outputs, a capacity of 0 disables caching.
+    pub fn new(capacity: usize) -> ResultCache {
+        ResultCache {
+            entries: Mutex::new(LruCache::new(capacity)),
+            capacity,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// Returns the cached output of `query` if it was computed from the given versions of the queried tables.
+    pub fn get(&self, query: &str, table_versions: &[(String, Option<u64>)]) -> Option<QueryOutput> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        let output = match entries.get(query) {
+            Some(cached) if cached.table_versions == table_versions => Some(cached.output.clone()),
+            Some(_) => {
+                entries.pop(query);
+                None
+            }
+            None => None,
+        };
+        let counter = if output.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        output
+    }
+
+    pub fn put(&self, query: &str, table_versions: Vec<(String, Option<u64>)>, output: &QueryOutput) {
+        if !self.is_enabled() {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.put(query.to_string(), CachedResult { table_versions, output: output.clone() });
+    }
+
+    /// Removes the outputs of all queries that read from `table`.
+    pub fn invalidate(&self, table: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        let stale = entries
+            .iter()
+            .filter(|(_, cached)| cached.table_versions.iter().any(|(name, _)| name == table))
+            .map(|(query, _)| query.clone())
+            .collect::<Vec<_>>();
+        for query in stale {
+            entries.pop(&query);
+        }
+    }
+
+    pub fn stats(&self) -> ResultCacheStats {
+        ResultCacheStats {
+            capacity: self.capacity,
+            entries: self.entries.lock().unwrap().len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
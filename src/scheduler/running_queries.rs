@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+/// Queries that are currently executing, which can be cancelled by id.
+#[derive(Default)]
+pub struct RunningQueries {
+    next_id: AtomicU64,
+    queries: Mutex<HashMap<u64, RunningQuery>>,
+}
+
+struct RunningQuery {
+    query: String,
+    started: Instant,
+    cancelled: Arc<AtomicBool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunningQueryInfo {
+    pub id: u64,
+    pub query: String,
+    pub runtime_ms: u64,
+}
+
+/// Removes the query from the running queries when dropped.
+pub struct QueryRegistration {
+    pub id: u64,
+    pub cancelled: Arc<AtomicBool>,
+    queries: Arc<RunningQueries>,
+}
+
+impl RunningQueries {
+    pub fn register(queries: &Arc<RunningQueries>, query: &str) -> QueryRegistration {
+        let id = queries.next_id.fetch_add(1, Ordering::SeqCst);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        queries.queries.lock().unwrap().insert(
+            id,
+            RunningQuery { query: query.to_string(), started: Instant::now(), cancelled: cancelled.clone() },
+        );
+        QueryRegistration { id, cancelled, queries: queries.clone() }
+    }
+
+    /// Aborts the query with the given id, returns false if no such query is running.
+    pub fn cancel(&self, id: u64) -> bool {
+        match self.queries.lock().unwrap().get(&id) {
+            Some(query) => {
+                query.cancelled.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Running queries ordered by id, i.e. by the time they were started.
+    pub fn list(&self) -> Vec<RunningQueryInfo> {
+        let mut queries = self
+            .queries
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(&id, query)| RunningQueryInfo {
+                id,
+                query: query.query.clone(),
+                runtime_ms: query.started.elapsed().as_millis() as u64,
+            })
+            .collect::<Vec<_>>();
+        queries.sort_by_key(|query| query.id);
+        queries
+    }
+}
+
+impl Drop for QueryRegistration {
+    fn drop(&mut self) {
+        self.queries.queries.lock().unwrap().remove(&self.id);
+    }
+}
@@ -5,6 +5,12 @@ pub trait Task: Sync + Send {
     fn execute(&self);
     fn completed(&self) -> bool;
     fn multithreaded(&self) -> bool;
+    /// Returns true if the task should be aborted instead of executed.
+    fn cancelled(&self) -> bool {
+        false
+    }
+    /// Called by a worker in place of `execute` once `cancelled` returns true.
+    fn cancel(&self) {}
 }
 
 impl Task for dyn Fn() + Send + Sync + 'static {
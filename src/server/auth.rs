@@ -0,0 +1,138 @@
+use std::collections::HashSet;
+
+use actix_web::http::header::{self, HeaderMap, HeaderName};
+use actix_web::http::Method;
+
+/// API keys accepted by the HTTP server.
+#[derive(Clone, Debug, Default)]
+pub struct ApiKeys {
+    /// Keys that may call any endpoint.
+    pub read_write: HashSet<String>,
+    /// Keys that may only call endpoints that do not modify data.
+    pub read_only: HashSet<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Authorization {
+    Granted,
+    /// A read-only key was sent with a request that may run queries. Statements that modify data, e.g. `DELETE`,
+    /// are only known once the query has been parsed and are rejected by the query handler.
+    ReadOnly,
+    /// No valid key was sent with the request.
+    Unauthorized,
+    /// A read-only key was sent with a request that modifies data.
+    Forbidden,
+}
+
+impl ApiKeys {
+    /// Requests to a server without any keys do not require authentication.
+    pub fn is_enabled(&self) -> bool {
+        !self.read_write.is_empty() || !self.read_only.is_empty()
+    }
+
+    /// Checks the key sent with a request, either as `Authorization: Bearer <key>` or in the `X-API-Key` header.
+    pub fn authorize(&self, method: &Method, path: &str, headers: &HeaderMap) -> Authorization {
+        if !self.is_enabled() {
+            return Authorization::Granted;
+        }
+        match request_key(headers) {
+            Some(key) if self.read_write.contains(key) => Authorization::Granted,
+            Some(key) if self.read_only.contains(key) => {
+                if modifies_data(method, path) {
+                    Authorization::Forbidden
+                } else {
+                    Authorization::ReadOnly
+                }
+            }
+            _ => Authorization::Unauthorized,
+        }
+    }
+}
+
+/// Other `Authorization` schemes, e.g. basic auth added by a proxy, fall back to the `X-API-Key` header.
+fn request_key(headers: &HeaderMap) -> Option<&str> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|authorization| authorization.to_str().ok())
+        .and_then(|authorization| authorization.strip_prefix("Bearer "));
+    match bearer {
+        Some(key) => Some(key.trim()),
+        None => headers.get(HeaderName::from_static("x-api-key"))?.to_str().ok(),
+    }
+}
+
+/// All `POST` endpoints except queries modify data, as does dropping a table.
+fn modifies_data(method: &Method, path: &str) -> bool {
+    match *method {
+        Method::GET | Method::HEAD | Method::OPTIONS => false,
+        Method::POST => !matches!(path, "/query" | "/query_stream" | "/echo"),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::header::HeaderValue;
+
+    fn authorize(keys: &ApiKeys, method: Method, path: &str, header: Option<(&'static str, &str)>) -> Authorization {
+        let mut headers = HeaderMap::new();
+        if let Some((name, value)) = header {
+            headers.insert(HeaderName::from_static(name), HeaderValue::from_str(value).unwrap());
+        }
+        keys.authorize(&method, path, &headers)
+    }
+
+    #[test]
+    fn test_authorize() {
+        assert_eq!(authorize(&ApiKeys::default(), Method::POST, "/insert", None), Authorization::Granted);
+
+        let keys = ApiKeys {
+            read_write: ["secret".to_string()].into_iter().collect(),
+            read_only: ["reader".to_string()].into_iter().collect(),
+        };
+        assert_eq!(authorize(&keys, Method::POST, "/query", None), Authorization::Unauthorized);
+        assert_eq!(
+            authorize(&keys, Method::POST, "/query", Some(("authorization", "Bearer wrong"))),
+            Authorization::Unauthorized
+        );
+        assert_eq!(
+            authorize(&keys, Method::POST, "/query", Some(("authorization", "secret"))),
+            Authorization::Unauthorized
+        );
+        assert_eq!(
+            authorize(&keys, Method::POST, "/insert", Some(("authorization", "Bearer secret"))),
+            Authorization::Granted
+        );
+        assert_eq!(
+            authorize(&keys, Method::DELETE, "/table/t", Some(("x-api-key", "secret"))),
+            Authorization::Granted
+        );
+        assert_eq!(
+            authorize(&keys, Method::POST, "/query", Some(("x-api-key", "reader"))),
+            Authorization::ReadOnly
+        );
+        assert_eq!(
+            authorize(&keys, Method::GET, "/tables", Some(("authorization", "Bearer reader"))),
+            Authorization::ReadOnly
+        );
+        assert_eq!(
+            authorize(&keys, Method::POST, "/insert", Some(("x-api-key", "reader"))),
+            Authorization::Forbidden
+        );
+        assert_eq!(
+            authorize(&keys, Method::DELETE, "/table/t", Some(("x-api-key", "reader"))),
+            Authorization::Forbidden
+        );
+    }
+
+    #[test]
+    fn test_api_key_header_fallback() {
+        let keys = ApiKeys { read_write: ["secret".to_string()].into_iter().collect(), ..ApiKeys::default() };
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_static("Basic dXNlcjpwYXNz"));
+        assert_eq!(keys.authorize(&Method::POST, "/insert", &headers), Authorization::Unauthorized);
+        headers.insert(HeaderName::from_static("x-api-key"), HeaderValue::from_static("secret"));
+        assert_eq!(keys.authorize(&Method::POST, "/insert", &headers), Authorization::Granted);
+    }
+}
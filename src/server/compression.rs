@@ -0,0 +1,133 @@
+use std::io::{self, Write};
+
+use actix_web::body::{self, BodySize, BoxBody, MessageBody};
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header::{self, HeaderMap, HeaderValue};
+use flate2::write::{DeflateEncoder, GzEncoder};
+use flate2::Compression;
+
+/// Responses smaller than this are sent uncompressed since compression would barely reduce their size.
+const MIN_COMPRESSED_BYTES: u64 = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Gzip,
+    Deflate,
+}
+
+impl Encoding {
+    /// Encoding to use for a request with the given headers, preferring gzip over deflate.
+    pub fn negotiate(headers: &HeaderMap) -> Option<Encoding> {
+        let accepted = headers.get(header::ACCEPT_ENCODING)?.to_str().ok()?;
+        let mut gzip = false;
+        let mut deflate = false;
+        for coding in accepted.split(',') {
+            let mut parts = coding.split(';');
+            let name = parts.next().unwrap_or("").trim();
+            let refused = parts.any(|param| {
+                param.trim().strip_prefix("q=").map_or(false, |q| q.trim().parse::<f32>() == Ok(0.0))
+            });
+            if refused {
+                continue;
+            }
+            match name.to_lowercase().as_ref() {
+                "gzip" | "*" => gzip = true,
+                "deflate" => deflate = true,
+                _ => {}
+            }
+        }
+        if gzip {
+            Some(Encoding::Gzip)
+        } else if deflate {
+            Some(Encoding::Deflate)
+        } else {
+            None
+        }
+    }
+
+    fn header_value(self) -> HeaderValue {
+        match self {
+            Encoding::Gzip => HeaderValue::from_static("gzip"),
+            Encoding::Deflate => HeaderValue::from_static("deflate"),
+        }
+    }
+
+    pub fn compress(self, data: &[u8], level: u32) -> io::Result<Vec<u8>> {
+        let level = Compression::new(level.min(9));
+        match self {
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), level);
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            Encoding::Deflate => {
+                let mut encoder = DeflateEncoder::new(Vec::new(), level);
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+/// Compresses the body of `response` with `encoding`.
+/// Streamed responses are passed through unchanged so that rows still reach the client as they are produced.
+pub async fn compress_response(
+    response: ServiceResponse<BoxBody>,
+    encoding: Encoding,
+    level: u32,
+) -> actix_web::Result<ServiceResponse<BoxBody>> {
+    let compressible = match response.response().body().size() {
+        BodySize::Sized(bytes) => bytes >= MIN_COMPRESSED_BYTES,
+        BodySize::None | BodySize::Stream => false,
+    };
+    if !compressible || response.headers().contains_key(header::CONTENT_ENCODING) {
+        return Ok(response);
+    }
+    let (request, response) = response.into_parts();
+    let (mut response, body) = response.into_parts();
+    let data = body::to_bytes(body)
+        .await
+        .map_err(|err| actix_web::error::ErrorInternalServerError(err.to_string()))?;
+    let compressed = encoding.compress(&data, level)?;
+    let headers = response.headers_mut();
+    headers.insert(header::CONTENT_ENCODING, encoding.header_value());
+    headers.append(header::VARY, HeaderValue::from_static("Accept-Encoding"));
+    headers.remove(header::CONTENT_LENGTH);
+    let response = response.set_body(compressed.boxed());
+    Ok(ServiceResponse::new(request, response))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    use super::*;
+
+    fn negotiate(accept_encoding: &str) -> Option<Encoding> {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT_ENCODING, HeaderValue::from_str(accept_encoding).unwrap());
+        Encoding::negotiate(&headers)
+    }
+
+    #[test]
+    fn test_negotiate() {
+        assert_eq!(negotiate("gzip, deflate, br"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("deflate"), Some(Encoding::Deflate));
+        assert_eq!(negotiate("GZIP;q=0.5"), Some(Encoding::Gzip));
+        assert_eq!(negotiate("gzip;q=0, deflate"), Some(Encoding::Deflate));
+        assert_eq!(negotiate("br, identity"), None);
+        assert_eq!(Encoding::negotiate(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_compress() {
+        let data = "[1, 2, 3]".repeat(1000);
+        let compressed = Encoding::Gzip.compress(data.as_bytes(), 6).unwrap();
+        assert!(compressed.len() < data.len() / 10);
+        let mut decompressed = String::new();
+        GzDecoder::new(&compressed[..]).read_to_string(&mut decompressed).unwrap();
+        assert_eq!(decompressed, data);
+    }
+}
@@ -0,0 +1,114 @@
+use actix_web::http::header::{self, HeaderMap, HeaderValue};
+use actix_web::HttpResponse;
+
+/// Cross-origin resource sharing settings of the HTTP server.
+#[derive(Clone, Debug)]
+pub struct CorsOptions {
+    /// Origins that may call the API from a browser, e.g. `https://example.com`.
+    /// `*` allows any origin and an origin without port matches all ports of that host.
+    pub allowed_origins: Vec<String>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+}
+
+impl Default for CorsOptions {
+    /// Allows requests from pages served on localhost.
+    fn default() -> CorsOptions {
+        CorsOptions {
+            allowed_origins: vec!["http://localhost".to_string(), "http://127.0.0.1".to_string()],
+            allowed_methods: vec![
+                "GET".to_string(),
+                "POST".to_string(),
+                "DELETE".to_string(),
+                "OPTIONS".to_string(),
+            ],
+            allowed_headers: vec!["Content-Type".to_string()],
+        }
+    }
+}
+
+impl CorsOptions {
+    /// Returns the `Origin` header of a request if that origin is allowed.
+    pub fn allowed_origin(&self, headers: &HeaderMap) -> Option<HeaderValue> {
+        let origin = headers.get(header::ORIGIN)?;
+        let origin_str = origin.to_str().ok()?;
+        let allowed = self.allowed_origins.iter().any(|allowed| {
+            allowed == "*"
+                || allowed == origin_str
+                || origin_str
+                    .strip_prefix(allowed.as_str())
+                    .and_then(|port| port.strip_prefix(':'))
+                    .map_or(false, |port| !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()))
+        });
+        if allowed {
+            Some(origin.clone())
+        } else {
+            None
+        }
+    }
+
+    /// Response to an `OPTIONS` request sent by the browser before the actual request.
+    pub fn preflight_response(&self, origin: HeaderValue) -> HttpResponse {
+        let mut response = HttpResponse::NoContent().finish();
+        self.add_headers(origin, response.headers_mut());
+        let headers = response.headers_mut();
+        if let Ok(methods) = HeaderValue::from_str(&self.allowed_methods.join(", ")) {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, methods);
+        }
+        if let Ok(allowed_headers) = HeaderValue::from_str(&self.allowed_headers.join(", ")) {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, allowed_headers);
+        }
+        headers.insert(header::ACCESS_CONTROL_MAX_AGE, HeaderValue::from_static("3600"));
+        response
+    }
+
+    /// Allows the `Authorization` and `X-API-Key` headers that carry API keys, unless they are allowed already.
+    pub fn allow_api_key_headers(&mut self) {
+        for name in ["Authorization", "X-API-Key"] {
+            if !self.allowed_headers.iter().any(|allowed| allowed.eq_ignore_ascii_case(name)) {
+                self.allowed_headers.push(name.to_string());
+            }
+        }
+    }
+
+    pub fn add_headers(&self, origin: HeaderValue, headers: &mut HeaderMap) {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        headers.append(header::VARY, HeaderValue::from_static("Origin"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn origin(cors: &CorsOptions, origin: &str) -> Option<HeaderValue> {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ORIGIN, HeaderValue::from_str(origin).unwrap());
+        cors.allowed_origin(&headers)
+    }
+
+    #[test]
+    fn test_allowed_origin() {
+        let cors = CorsOptions::default();
+        assert!(origin(&cors, "http://localhost").is_some());
+        assert!(origin(&cors, "http://localhost:3000").is_some());
+        assert!(origin(&cors, "http://127.0.0.1:8080").is_some());
+        assert!(origin(&cors, "http://localhost.evil.com").is_none());
+        assert!(origin(&cors, "http://localhost:").is_none());
+        assert!(origin(&cors, "https://example.com").is_none());
+        assert!(cors.allowed_origin(&HeaderMap::new()).is_none());
+
+        let cors = CorsOptions { allowed_origins: vec!["*".to_string()], ..CorsOptions::default() };
+        assert!(origin(&cors, "https://example.com").is_some());
+    }
+
+    #[test]
+    fn test_allow_api_key_headers() {
+        let mut cors = CorsOptions {
+            allowed_headers: vec!["Content-Type".to_string(), "x-api-key".to_string()],
+            ..CorsOptions::default()
+        };
+        cors.allow_api_key_headers();
+        assert_eq!(cors.allowed_headers, vec!["Content-Type", "x-api-key", "Authorization"]);
+    }
+}
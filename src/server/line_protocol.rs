@@ -0,0 +1,235 @@
+use std::collections::HashSet;
+
+use ordered_float::OrderedFloat;
+
+use crate::ingest::raw_val::RawVal;
+
+/// Name of the column that holds the timestamp of each point.
+pub const TIMESTAMP_COLUMN: &str = "time";
+
+/// A single line of InfluxDB line protocol, e.g. `cpu,host=a usage=0.5,cores=4i 1465839830100400200`.
+#[derive(Debug, PartialEq)]
+pub struct Point {
+    pub measurement: String,
+    /// Tags as strings, fields with their parsed type and the timestamp in `TIMESTAMP_COLUMN`.
+    pub columns: Vec<(String, RawVal)>,
+}
+
+/// Unit of the timestamps in a request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Precision {
+    Nanoseconds,
+    Microseconds,
+    Milliseconds,
+    Seconds,
+}
+
+impl Precision {
+    pub fn parse(precision: &str) -> Option<Precision> {
+        match precision {
+            "n" | "ns" => Some(Precision::Nanoseconds),
+            "u" | "us" => Some(Precision::Microseconds),
+            "ms" => Some(Precision::Milliseconds),
+            "s" => Some(Precision::Seconds),
+            _ => None,
+        }
+    }
+
+    fn to_millis(self, timestamp: i64) -> i64 {
+        match self {
+            Precision::Nanoseconds => timestamp.div_euclid(1_000_000),
+            Precision::Microseconds => timestamp.div_euclid(1_000),
+            Precision::Milliseconds => timestamp,
+            Precision::Seconds => timestamp.saturating_mul(1_000),
+        }
+    }
+}
+
+/// Parses all points in `body`, skipping empty lines and comments.
+/// Points without a timestamp are assigned `now`, which is given in milliseconds.
+pub fn parse_lines(body: &str, precision: Precision, now: i64) -> Result<Vec<Point>, String> {
+    let mut points = Vec::new();
+    for (i, line) in body.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let point = parse_line(line, precision, now).map_err(|err| format!("Invalid line {}: {}", i + 1, err))?;
+        points.push(point);
+    }
+    Ok(points)
+}
+
+fn parse_line(line: &str, precision: Precision, now: i64) -> Result<Point, String> {
+    let sections = split_unescaped(line, ' ', true)?
+        .into_iter()
+        .filter(|section| !section.is_empty())
+        .collect::<Vec<_>>();
+    let (series, fields, timestamp) = match sections[..] {
+        [series, fields] => (series, fields, None),
+        [series, fields, timestamp] => (series, fields, Some(timestamp)),
+        _ => return Err("expected measurement, fields and optional timestamp separated by spaces".to_string()),
+    };
+
+    let mut series = split_unescaped(series, ',', false)?.into_iter();
+    let measurement = unescape(series.next().unwrap_or(""));
+    if measurement.is_empty() {
+        return Err("missing measurement".to_string());
+    }
+    let mut colnames = HashSet::new();
+    let mut columns = Vec::new();
+    let mut push = |colname: String, value: RawVal| {
+        if colnames.insert(colname.clone()) {
+            columns.push((colname, value));
+            Ok(())
+        } else {
+            Err(format!("duplicate column {}", colname))
+        }
+    };
+    for tag in series {
+        let (key, value) = split_key_value(tag)?;
+        push(key, RawVal::Str(unescape(value)))?;
+    }
+    for field in split_unescaped(fields, ',', true)? {
+        let (key, value) = split_key_value(field)?;
+        let value = parse_field_value(value).map_err(|err| format!("{} in field {}", err, key))?;
+        push(key, value)?;
+    }
+    let timestamp = match timestamp {
+        Some(timestamp) => match timestamp.parse::<i64>() {
+            Ok(timestamp) => precision.to_millis(timestamp),
+            Err(err) => return Err(format!("invalid timestamp {}: {}", timestamp, err)),
+        },
+        None => now,
+    };
+    push(TIMESTAMP_COLUMN.to_string(), RawVal::Timestamp(timestamp))?;
+
+    Ok(Point { measurement, columns })
+}
+
+/// Splits at separators that are neither escaped with a backslash nor, if `quotes` is set, within double quotes.
+fn split_unescaped(s: &str, separator: char, quotes: bool) -> Result<Vec<&str>, String> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    let mut quoted = false;
+    for (i, c) in s.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if quotes && c == '"' {
+            quoted = !quoted;
+        } else if c == separator && !quoted {
+            parts.push(&s[start..i]);
+            start = i + 1;
+        }
+    }
+    if quoted {
+        return Err("unterminated string".to_string());
+    }
+    parts.push(&s[start..]);
+    Ok(parts)
+}
+
+fn split_key_value(s: &str) -> Result<(String, &str), String> {
+    match split_unescaped(s, '=', true)?[..] {
+        [key, value] if !key.is_empty() && !value.is_empty() => Ok((unescape(key), value)),
+        _ => Err(format!("expected key=value, found {}", s)),
+    }
+}
+
+fn unescape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('\\', Some(&next @ (',' | ' ' | '=' | '"' | '\\'))) => {
+                result.push(next);
+                chars.next();
+            }
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+/// Values are floats unless they have an `i` (integer) or `u` (unsigned) suffix, are quoted strings or booleans.
+fn parse_field_value(value: &str) -> Result<RawVal, String> {
+    if let Some(string) = value.strip_prefix('"') {
+        return match string.strip_suffix('"') {
+            Some(string) => Ok(RawVal::Str(unescape(string))),
+            None => Err(format!("unterminated string {}", value)),
+        };
+    }
+    match value {
+        "t" | "T" | "true" | "True" | "TRUE" => return Ok(RawVal::Bool(true)),
+        "f" | "F" | "false" | "False" | "FALSE" => return Ok(RawVal::Bool(false)),
+        _ => {}
+    }
+    if let Some(integer) = value.strip_suffix('i') {
+        integer.parse::<i64>().map(RawVal::Int).map_err(|err| format!("invalid integer {}: {}", value, err))
+    } else if let Some(unsigned) = value.strip_suffix('u') {
+        unsigned.parse::<i64>().map(RawVal::Int).map_err(|err| format!("invalid unsigned integer {}: {}", value, err))
+    } else {
+        value
+            .parse::<f64>()
+            .map(|float| RawVal::Float(OrderedFloat(float)))
+            .map_err(|err| format!("invalid float {}: {}", value, err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Point, String> {
+        parse_line(line, Precision::Nanoseconds, 7)
+    }
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(
+            parse("cpu,host=server\\ 1,region=eu usage=0.5,cores=4i,up=t,name=\"a \\\"b\\\", c\" 1465839830100400200"),
+            Ok(Point {
+                measurement: "cpu".to_string(),
+                columns: vec![
+                    ("host".to_string(), RawVal::Str("server 1".to_string())),
+                    ("region".to_string(), RawVal::Str("eu".to_string())),
+                    ("usage".to_string(), RawVal::Float(OrderedFloat(0.5))),
+                    ("cores".to_string(), RawVal::Int(4)),
+                    ("up".to_string(), RawVal::Bool(true)),
+                    ("name".to_string(), RawVal::Str("a \"b\", c".to_string())),
+                    ("time".to_string(), RawVal::Timestamp(1465839830100)),
+                ],
+            })
+        );
+        assert_eq!(
+            parse("disk\\,io reads=3u"),
+            Ok(Point {
+                measurement: "disk,io".to_string(),
+                columns: vec![
+                    ("reads".to_string(), RawVal::Int(3)),
+                    ("time".to_string(), RawVal::Timestamp(7)),
+                ],
+            })
+        );
+        assert!(parse("cpu").is_err());
+        assert!(parse("cpu usage").is_err());
+        assert!(parse("cpu usage=x").is_err());
+        assert!(parse("cpu usage=1 12 13").is_err());
+        assert!(parse("cpu,usage=a usage=1").is_err());
+        assert!(parse("cpu name=\"unterminated").is_err());
+    }
+
+    #[test]
+    fn test_parse_lines() {
+        let points = parse_lines("# comment\n\nmem used=1i 2\ncpu usage=2 3\n", Precision::Seconds, 0).unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[1].columns[1], ("time".to_string(), RawVal::Timestamp(3000)));
+        assert_eq!(
+            parse_lines("mem used=1i\nmem used=", Precision::Seconds, 0),
+            Err("Invalid line 2: expected key=value, found used=".to_string())
+        );
+    }
+}
@@ -1,18 +1,41 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Write;
 use std::sync::Arc;
 
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse};
 use actix_web::web::Data;
-use actix_web::{get, post, web, App, HttpResponse, HttpServer, Responder};
+use actix_web::http::{header, Method, StatusCode};
+use actix_web::{delete, get, post, web, App, HttpMessage, HttpRequest, HttpResponse, HttpServer, Responder};
+use chrono::DateTime;
+use futures::future::{self, Either};
+use futures::{stream, StreamExt};
 use ordered_float::OrderedFloat;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tera::{Context, Tera};
 
-use crate::ingest::raw_val::RawVal;
+use crate::ingest::input_column::InputColumn;
+use crate::ingest::raw_val::{format_timestamp, RawVal};
+use crate::mem_store::table::validate_row;
+use crate::syntax::parser;
+use crate::IngestError;
 use crate::LocustDB;
+use crate::QueryError;
+use crate::QueryOutput;
+use crate::QueryStream;
+use crate::TableStats;
 use crate::Value;
 
+mod auth;
+mod compression;
+mod cors;
+mod line_protocol;
+
+pub use self::cors::CorsOptions;
+use self::auth::{ApiKeys, Authorization};
+use self::compression::{compress_response, Encoding};
+use self::line_protocol::Precision;
+
 lazy_static! {
     pub static ref TEMPLATES: Tera = {
         let mut tera = match Tera::new("templates/**/*") {
@@ -32,8 +55,107 @@ lazy_static! {
 struct DataBatch {
     pub table: String,
     pub rows: Vec<HashMap<String, serde_json::Value>>,
+    /// Rows whose values in these columns match a row that is still buffered (not yet part of a
+    /// partition) or an earlier row of the batch are skipped, which makes retried inserts idempotent.
+    #[serde(default)]
+    pub dedup_on: Vec<String>,
+    /// Columns whose values are timestamps, given as unix milliseconds or RFC 3339 strings.
+    #[serde(default)]
+    pub timestamp_columns: Vec<String>,
+    /// Number of levels of nested objects that are flattened into dotted column names, e.g.
+    /// `{"user": {"id": 1}}` becomes column `user.id`. Deeper objects are stored as JSON strings.
+    #[serde(default = "default_flatten_depth")]
+    pub flatten_depth: usize,
+}
+
+fn default_flatten_depth() -> usize {
+    8
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ColumnarBatch {
+    pub table: String,
+    pub columns: HashMap<String, Vec<serde_json::Value>>,
+    /// Columns whose values are timestamps, given as unix milliseconds or RFC 3339 strings.
+    #[serde(default)]
+    pub timestamp_columns: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct InsertCsvParams {
+    table: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct LoadParquetRequest {
+    table: String,
+    /// Path of the file on the machine running the server.
+    path: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct WriteParams {
+    /// Unit of the timestamps, one of `ns` (default), `us`, `ms` or `s`.
+    precision: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct QueryHistoryParams {
+    /// Maximum number of queries to return.
+    #[serde(default = "default_query_history_limit")]
+    limit: u64,
+}
+
+fn default_query_history_limit() -> u64 {
+    100
+}
+
+#[derive(Deserialize, Debug)]
+struct TableParams {
+    /// Only show rows for which this expression is true, e.g. `status = 'error'`.
+    filter: Option<String>,
+    /// Maximum number of rows to show.
+    #[serde(default = "default_table_limit")]
+    limit: u64,
+}
+
+fn default_table_limit() -> u64 {
+    100
+}
+
+#[derive(Deserialize, Debug)]
+struct MemTreeParams {
+    /// 1 only lists tables, 2 adds columns, 3 adds encodings and 4 adds the data sections of each encoding.
+    #[serde(default = "default_mem_tree_depth")]
+    depth: usize,
+}
+
+fn default_mem_tree_depth() -> usize {
+    2
+}
+
+#[derive(Deserialize, Debug)]
+struct WorkerThreadsRequest {
+    threads: usize,
+}
+
+#[derive(Deserialize, Debug)]
+struct ReadThreadsRequest {
+    threads: usize,
 }
 
+#[derive(Deserialize, Debug)]
+struct EvictRequest {
+    /// Memory usage of tables in bytes to evict down to, by default all evictable columns are evicted.
+    #[serde(default)]
+    target_bytes: usize,
+}
+
+/// Number of rows used to infer the type of each column in `/insert_csv`.
+const CSV_TYPE_INFERENCE_ROWS: usize = 100;
+/// Number of rows passed to a single ingest call in `/insert_csv`.
+const CSV_INGEST_BATCH_SIZE: usize = 1 << 16;
+
 #[derive(Clone)]
 struct AppState {
     db: Arc<LocustDB>,
@@ -42,6 +164,47 @@ struct AppState {
 #[derive(Serialize, Deserialize, Debug)]
 struct QueryRequest {
     query: String,
+    /// Include the query plan in the response under `plan`.
+    #[serde(default)]
+    explain: bool,
+}
+
+fn query_error_response(err: QueryError) -> HttpResponse {
+    let (status, error) = query_error(err);
+    error_response(status, &error)
+}
+
+/// Status code and error message of a failed query.
+fn query_error(err: QueryError) -> (StatusCode, String) {
+    let (status, kind) = match err {
+        QueryError::SytaxErrorCharsRemaining(_)
+        | QueryError::SyntaxErrorBytesRemaining(_)
+        | QueryError::ParseError(_) => (StatusCode::BAD_REQUEST, "ParseError"),
+        QueryError::NotImplemented(_) => (StatusCode::BAD_REQUEST, "NotImplemented"),
+        QueryError::TypeError(_) => (StatusCode::BAD_REQUEST, "TypeError"),
+        QueryError::Overflow => (StatusCode::BAD_REQUEST, "Overflow"),
+        QueryError::Timeout => (StatusCode::REQUEST_TIMEOUT, "Timeout"),
+        QueryError::ResultTooLarge(_) => (StatusCode::BAD_REQUEST, "ResultTooLarge"),
+        QueryError::ResourceLimit(_) => (StatusCode::BAD_REQUEST, "ResourceLimit"),
+        QueryError::Cancelled => (StatusCode::BAD_REQUEST, "Cancelled"),
+        QueryError::FatalError(_, _) => (StatusCode::INTERNAL_SERVER_ERROR, "FatalError"),
+    };
+    (status, format!("{}: {}", kind, err))
+}
+
+fn canceled_response() -> HttpResponse {
+    error_response(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Canceled: worker terminated before completing the request",
+    )
+}
+
+fn error_response(status: StatusCode, error: &str) -> HttpResponse {
+    HttpResponse::build(status).json(json!({ "error": error }))
+}
+
+fn table_not_found_response(table: &str) -> HttpResponse {
+    error_response(StatusCode::NOT_FOUND, &format!("Table {} does not exist!", table))
 }
 
 #[get("/")]
@@ -75,22 +238,45 @@ async fn plot(_data: web::Data<AppState>) -> impl Responder {
 #[get("/table/{tablename}")]
 async fn table_handler(
     path: web::Path<String>,
+    params: web::Query<TableParams>,
     data: web::Data<AppState>,
 ) -> impl Responder {
-    let cols = data
-        .db
-        .run_query(
-            &format!("SELECT * FROM {} LIMIT 0", path.as_str()),
-            false,
-            vec![],
-        )
-        .await
-        .unwrap()
-        .unwrap()
-        .colnames;
+    if !data.db.table_exists(path.as_str()).await {
+        return table_not_found_response(path.as_str());
+    }
+    let filter = params.filter.as_deref().map(str::trim).filter(|filter| !filter.is_empty());
+    let mut select = data.db.select(&["*"]).from(path.as_str()).limit(params.limit);
+    // The filter is parsed on its own rather than spliced into a query string, so it can only ever be a single expression
+    if let Some(filter) = filter {
+        match parser::parse_expr(filter) {
+            Ok(filter) => select = select.filter(filter),
+            Err(err) => return query_error_response(err),
+        }
+    }
+    let result = match select.execute().await {
+        Ok(Ok(result)) => result,
+        Ok(Err(err)) => return query_error_response(err),
+        Err(_) => return canceled_response(),
+    };
+    let rows = result
+        .rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|val| match val {
+                    Value::Str(s) => s.clone(),
+                    Value::Float(f) => f.to_string(),
+                    _ => val.to_string(),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
 
     let mut context = Context::new();
-    context.insert("columns", &cols.join(", "));
+    context.insert("columns", &result.colnames);
+    context.insert("rows", &rows);
+    context.insert("filter", filter.unwrap_or(""));
+    context.insert("limit", &params.limit);
     context.insert("table", path.as_str());
     let body = TEMPLATES.render("table.html", &context).unwrap();
 
@@ -102,7 +288,10 @@ async fn table_handler(
 #[get("/tables")]
 async fn tables(data: web::Data<AppState>) -> impl Responder {
     println!("Requesting table stats");
-    let stats = data.db.table_stats().await.unwrap();
+    let stats = match data.db.table_stats().await {
+        Ok(stats) => stats,
+        Err(_) => return canceled_response(),
+    };
 
     let mut body = String::new();
     for table in stats {
@@ -117,6 +306,110 @@ async fn tables(data: web::Data<AppState>) -> impl Responder {
     HttpResponse::Ok().body(body)
 }
 
+/// Bytes stored on disk per table and partition next to the bytes currently resident in memory.
+#[get("/storage_stats")]
+async fn storage_stats(data: web::Data<AppState>) -> impl Responder {
+    let (disk_usage, table_stats) = match (data.db.disk_usage().await, data.db.table_stats().await) {
+        (Ok(disk_usage), Ok(table_stats)) => (disk_usage, table_stats),
+        _ => return canceled_response(),
+    };
+    let mut table_usage = BTreeMap::<String, serde_json::Value>::new();
+    for table in &table_stats {
+        table_usage.insert(
+            table.name.clone(),
+            json!({
+                "name": table.name,
+                "disk_bytes": 0,
+                "memory_bytes": table.batches_bytes + table.buffer_bytes,
+                "partitions": [],
+            }),
+        );
+    }
+    for table in &disk_usage {
+        let entry = table_usage.entry(table.name.clone()).or_insert_with(|| json!({"name": table.name, "memory_bytes": 0}));
+        entry["disk_bytes"] = json!(table.size_bytes);
+        entry["partitions"] = table
+            .partitions
+            .iter()
+            .map(|&(id, size_bytes)| json!({"id": id, "disk_bytes": size_bytes}))
+            .collect();
+    }
+    HttpResponse::Ok().json(json!({
+        "total_disk_bytes": disk_usage.iter().map(|table| table.size_bytes).sum::<usize>(),
+        "total_memory_bytes": table_stats.iter().map(|table| table.batches_bytes + table.buffer_bytes).sum::<usize>(),
+        "tables": table_usage.into_values().collect::<Vec<_>>(),
+    }))
+}
+
+/// Memory used by each table, broken down by column, encoding and data section up to the requested depth.
+#[get("/mem_tree")]
+async fn mem_tree(data: web::Data<AppState>, params: web::Query<MemTreeParams>) -> impl Responder {
+    if params.depth == 0 {
+        return error_response(StatusCode::BAD_REQUEST, "depth must be at least 1");
+    }
+    match data.db.mem_tree(params.depth).await {
+        Ok(mut trees) => {
+            trees.sort_by(|a, b| a.name.cmp(&b.name));
+            HttpResponse::Ok().json(json!({ "tables": trees }))
+        }
+        Err(_) => canceled_response(),
+    }
+}
+
+#[get("/schema")]
+async fn schema(data: web::Data<AppState>) -> impl Responder {
+    let mut stats = match data.db.table_stats().await {
+        Ok(stats) => stats,
+        Err(_) => return canceled_response(),
+    };
+    stats.sort_by(|a, b| a.name.cmp(&b.name));
+    let schemas = stats.iter().map(table_schema).collect::<Vec<_>>();
+    HttpResponse::Ok().json(json!({ "tables": schemas }))
+}
+
+#[get("/schema/{table}")]
+async fn table_schema_handler(
+    path: web::Path<String>,
+    data: web::Data<AppState>,
+) -> impl Responder {
+    let stats = match data.db.table_stats().await {
+        Ok(stats) => stats,
+        Err(_) => return canceled_response(),
+    };
+    match stats.iter().find(|table| table.name == *path) {
+        Some(table) => HttpResponse::Ok().json(table_schema(table)),
+        None => table_not_found_response(path.as_str()),
+    }
+}
+
+#[delete("/table/{tablename}")]
+async fn drop_table(path: web::Path<String>, data: web::Data<AppState>) -> impl Responder {
+    match data.db.drop_table(path.as_str()).await {
+        Ok(true) => HttpResponse::Ok().json(json!({"status": "ok"})),
+        Ok(false) => table_not_found_response(path.as_str()),
+        Err(err) => query_error_response(err),
+    }
+}
+
+fn table_schema(table: &TableStats) -> serde_json::Value {
+    let columns = table
+        .types_per_column
+        .iter()
+        .map(|column| {
+            json!({
+                "name": column.name,
+                "types": column.types.iter().map(|t| format!("{:?}", t)).collect::<Vec<_>>(),
+                "encodings": column.encodings.iter().map(|t| format!("{:?}", t)).collect::<Vec<_>>(),
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "name": table.name,
+        "rows": table.rows,
+        "columns": columns,
+    })
+}
+
 #[post("/echo")]
 async fn echo(req_body: String) -> impl Responder {
     HttpResponse::Ok().body(req_body)
@@ -134,27 +427,201 @@ async fn query_data(_data: web::Data<AppState>) -> impl Responder {
     HttpResponse::Ok().json(response)
 }
 
+/// Runs a query, multiple `;`-separated statements return an array with the result of each statement.
+/// If a statement fails, the index of the failed statement is returned in the `statement` field of the error.
 #[post("/query")]
-async fn query(data: web::Data<AppState>, req_body: web::Json<QueryRequest>) -> impl Responder {
+async fn query(req: HttpRequest, data: web::Data<AppState>, req_body: web::Json<QueryRequest>) -> impl Responder {
     log::info!("Query: {:?}", req_body);
-    let result = data
-        .db
-        .run_query(&req_body.query, false, vec![])
-        .await
-        .unwrap()
-        .unwrap();
+    let statements = match parser::split_statements(&req_body.query) {
+        Ok(statements) => statements,
+        Err(err) => return query_error_response(err),
+    };
+    if req.extensions().contains::<ReadOnlyKey>()
+        && statements
+            .iter()
+            .any(|statement| parser::parse(statement).map_or(false, |parsed| parsed.modifies_data()))
+    {
+        return error_response(StatusCode::FORBIDDEN, "API key is read-only");
+    }
+    if statements.len() <= 1 {
+        return match data.db.run_query(&req_body.query, req_body.explain, vec![], None).await {
+            Ok(Ok(result)) => HttpResponse::Ok().json(query_output_json(&result, req_body.explain)),
+            Ok(Err(err)) => query_error_response(err),
+            Err(_) => canceled_response(),
+        };
+    }
+    match data.db.run_statements(&req_body.query, req_body.explain, None).await {
+        Ok(Ok(results)) => HttpResponse::Ok().json(
+            results.iter().map(|result| query_output_json(result, req_body.explain)).collect::<Vec<_>>(),
+        ),
+        Ok(Err((statement, err))) => {
+            let (status, error) = query_error(err);
+            HttpResponse::build(status).json(json!({ "error": error, "statement": statement }))
+        }
+        Err(_) => canceled_response(),
+    }
+}
 
-    let response = json!({
+fn query_output_json(result: &QueryOutput, explain: bool) -> serde_json::Value {
+    let mut response = json!({
         "colnames": result.colnames,
-        "rows": result.rows.iter().map(|row| row.iter().map(|val| match val {
-            Value::Int(int) => json!(int),
-            Value::Str(str) => json!(str),
-            Value::Null => json!(null),
-            Value::Float(float) => json!(float.0),
-        }).collect::<Vec<_>>()).collect::<Vec<_>>(),
+        "coltypes": result.coltypes,
+        "rows": result.rows.iter().map(|row| row.iter().map(value_to_json).collect::<Vec<_>>()).collect::<Vec<_>>(),
         "stats": result.stats,
     });
-    HttpResponse::Ok().json(response)
+    if explain {
+        let mut plans = result.query_plans.iter().collect::<Vec<_>>();
+        plans.sort_by(|(plan1, count1), (plan2, count2)| count2.cmp(count1).then(plan1.cmp(plan2)));
+        let plan = plans
+            .iter()
+            .map(|(plan, count)| format!("Query plan in {} batches{}", count, plan))
+            .collect::<Vec<_>>()
+            .join("\n");
+        response["plan"] = json!(plan);
+    }
+    response
+}
+
+/// Returns query results as newline delimited JSON with one object per row.
+/// If the query fails after rows have been sent, the last line is an object with an `error` field.
+#[post("/query_stream")]
+async fn query_stream(data: web::Data<AppState>, req_body: web::Json<QueryRequest>) -> impl Responder {
+    log::info!("Streaming query: {:?}", req_body);
+    stream_query_response(&data.db, &req_body.query)
+}
+
+#[get("/query_stream")]
+async fn query_stream_get(data: web::Data<AppState>, params: web::Query<QueryRequest>) -> impl Responder {
+    log::info!("Streaming query: {:?}", params);
+    stream_query_response(&data.db, &params.query)
+}
+
+fn stream_query_response(db: &LocustDB, query_str: &str) -> HttpResponse {
+    let QueryStream { colnames, rows, result } = match db.run_query_streaming(query_str, None) {
+        Ok(stream) => stream,
+        Err(err) => return query_error_response(err),
+    };
+    let rows = rows.map(move |row| {
+        let object = colnames
+            .iter()
+            .cloned()
+            .zip(row.iter().map(value_to_json))
+            .collect::<serde_json::Map<_, _>>();
+        serde_json::Value::Object(object)
+    });
+    let error = stream::once(result).filter_map(|result| async move {
+        match result {
+            Ok(Ok(_)) => None,
+            Ok(Err(err)) => Some(json!({ "error": err.to_string() })),
+            Err(_) => Some(json!({ "error": "Canceled: worker terminated before completing the request" })),
+        }
+    });
+    let lines = rows
+        .chain(error)
+        .map(|line| Ok::<_, actix_web::Error>(web::Bytes::from(format!("{}\n", line))));
+    HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(lines)
+}
+
+fn value_to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Int(int) => json!(int),
+        Value::Str(str) => json!(str),
+        Value::Null => json!(null),
+        Value::Float(float) => json!(float.0),
+        Value::Bool(b) => json!(b),
+        Value::Timestamp(millis) => json!(format_timestamp(*millis)),
+    }
+}
+
+/// Returns the most recently completed queries, newest first.
+#[get("/query_history")]
+async fn query_history(data: web::Data<AppState>, params: web::Query<QueryHistoryParams>) -> impl Responder {
+    let result = match data.db.query_history(params.limit).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(err)) => return query_error_response(err),
+        Err(_) => return canceled_response(),
+    };
+    let queries = result
+        .rows
+        .iter()
+        .map(|row| {
+            let object = result
+                .colnames
+                .iter()
+                .cloned()
+                .zip(row.iter().map(value_to_json))
+                .collect::<serde_json::Map<_, _>>();
+            serde_json::Value::Object(object)
+        })
+        .collect::<Vec<_>>();
+    HttpResponse::Ok().json(json!({ "queries": queries }))
+}
+
+/// Lists the queries that are currently executing.
+#[get("/running_queries")]
+async fn running_queries(data: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok().json(json!({ "queries": data.db.running_queries() }))
+}
+
+/// Returns the capacity, number of entries, hits and misses of the query result cache.
+#[get("/cache_stats")]
+async fn cache_stats(data: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok().json(data.db.cache_stats())
+}
+
+/// Aborts a running query, which then fails with a `Cancelled` error.
+#[post("/cancel/{query_id}")]
+async fn cancel_query(path: web::Path<u64>, data: web::Data<AppState>) -> impl Responder {
+    let query_id = path.into_inner();
+    if data.db.cancel_query(query_id) {
+        HttpResponse::Ok().json(json!({"status": "ok"}))
+    } else {
+        error_response(StatusCode::NOT_FOUND, &format!("Query {} is not running", query_id))
+    }
+}
+
+/// Returns the size of the worker thread pool and the number of tasks waiting for a worker.
+#[get("/worker_threads")]
+async fn worker_threads(data: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok().json(json!({
+        "threads": data.db.worker_threads(),
+        "queued_tasks": data.db.queued_tasks(),
+    }))
+}
+
+/// Grows or shrinks the worker thread pool to `threads` workers.
+#[post("/worker_threads")]
+async fn set_worker_threads(data: web::Data<AppState>, req_body: web::Json<WorkerThreadsRequest>) -> impl Responder {
+    if req_body.threads == 0 {
+        return error_response(StatusCode::BAD_REQUEST, "threads must be at least 1");
+    }
+    data.db.set_worker_threads(req_body.threads);
+    HttpResponse::Ok().json(json!({"status": "ok", "threads": data.db.worker_threads()}))
+}
+
+/// Returns the read concurrency, number of pending reads, bytes read and read latency of the disk read scheduler.
+#[get("/read_stats")]
+async fn read_stats(data: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok().json(data.db.disk_read_stats())
+}
+
+/// Changes the maximum number of concurrent disk reads to `threads`.
+#[post("/read_threads")]
+async fn set_read_threads(data: web::Data<AppState>, req_body: web::Json<ReadThreadsRequest>) -> impl Responder {
+    if req_body.threads == 0 {
+        return error_response(StatusCode::BAD_REQUEST, "threads must be at least 1");
+    }
+    data.db.set_read_threads(req_body.threads);
+    HttpResponse::Ok().json(json!({"status": "ok", "threads": data.db.read_threads()}))
+}
+
+/// Evicts columns from memory until tables take up at most `target_bytes` and returns the number of freed bytes.
+#[post("/evict")]
+async fn evict(data: web::Data<AppState>, req_body: web::Json<EvictRequest>) -> impl Responder {
+    let freed_bytes = data.db.force_evict(req_body.target_bytes);
+    HttpResponse::Ok().json(json!({"status": "ok", "freed_bytes": freed_bytes}))
 }
 
 #[get("/query_cols")]
@@ -163,12 +630,18 @@ async fn query_cols(
     // req_body: web::Json<QueryRequest>,
 ) -> impl Responder {
     // log::info!("Query: {:?}", req_body);
-    let result = data
+    if !data.db.table_exists("test_metrics").await {
+        return table_not_found_response("test_metrics");
+    }
+    let result = match data
         .db
-        .run_query("SELECT timestamp, cpu * 100 AS cpu FROM test_metrics LIMIT 100000000", false, vec![])
+        .run_query("SELECT timestamp, cpu * 100 AS cpu FROM test_metrics LIMIT 100000000", false, vec![], None)
         .await
-        .unwrap()
-        .unwrap();
+    {
+        Ok(Ok(result)) => result,
+        Ok(Err(err)) => return query_error_response(err),
+        Err(_) => return canceled_response(),
+    };
 
     let mut cols: HashMap<String, Vec<serde_json::Value>> = HashMap::default();
     for col in &result.colnames {
@@ -176,12 +649,12 @@ async fn query_cols(
     }
     for row in result.rows {
         for (val, colname) in row.iter().zip(result.colnames.iter()) {
-            cols.get_mut(colname).unwrap().push(match val {
-                Value::Int(int) => json!(int),
-                Value::Str(str) => json!(str),
-                Value::Null => json!(null),
-                Value::Float(f) => json!(f.0),
-            });
+            // Plots expect timestamps as unix milliseconds
+            let val = match val {
+                Value::Timestamp(millis) => json!(millis),
+                _ => value_to_json(val),
+            };
+            cols.get_mut(colname).unwrap().push(val);
         }
     }
     let response = json!({
@@ -196,61 +669,539 @@ async fn query_cols(
 #[post("/insert")]
 async fn insert(data: web::Data<AppState>, req_body: web::Json<DataBatch>) -> impl Responder {
     log::info!("Inserting! {:?}", req_body);
-    let DataBatch { table, rows } = req_body.0;
-    data.db
-        .ingest(
-            &table,
-            rows.into_iter()
-                .map(|row| {
-                    row.into_iter()
-                        .map(|(colname, val)| {
-                            let val = match val {
-                                serde_json::Value::Null => RawVal::Null,
-                                serde_json::Value::Number(n) => {
-                                    if n.is_i64() { 
-                                        RawVal::Int(n.as_i64().unwrap())
-                                    } else if n.is_f64() {
-                                        RawVal::Float(OrderedFloat(n.as_f64().unwrap()))
-                                    } else {
-                                        panic!("Unsupported number {}", n)
-                                    }
-                                },
-                                serde_json::Value::String(s) => RawVal::Str(s),
-                                _ => panic!("Unsupported value: {:?}", val),
-                            };
-                            (colname, val)
-                        })
-                        .collect()
-                })
-                .collect(),
-        )
-        .await;
-    HttpResponse::Ok().json(r#"{"status": "ok"}"#)
+    let DataBatch { table, rows, dedup_on, timestamp_columns, flatten_depth } = req_body.0;
+    let mut converted_rows = Vec::with_capacity(rows.len());
+    for row in rows {
+        let mut flattened_row = Vec::with_capacity(row.len());
+        for (colname, val) in row {
+            flatten_json(colname, val, flatten_depth, &mut flattened_row);
+        }
+        let mut converted_row = Vec::with_capacity(flattened_row.len());
+        for (colname, val) in flattened_row {
+            let val = if timestamp_columns.contains(&colname) {
+                json_to_timestamp(&colname, val)
+            } else {
+                json_to_raw_val(&colname, val)
+            };
+            let val = match val {
+                Ok(val) => val,
+                Err(err) => return error_response(StatusCode::BAD_REQUEST, &err),
+            };
+            converted_row.push((colname, val));
+        }
+        converted_rows.push(converted_row);
+    }
+    let result = if dedup_on.is_empty() {
+        data.db.ingest(&table, converted_rows).await.map(|()| json!({"status": "ok"}))
+    } else {
+        data.db
+            .ingest_dedup(&table, converted_rows, &dedup_on)
+            .await
+            .map(|skipped| json!({"status": "ok", "skipped": skipped}))
+    };
+    match result {
+        Ok(response) => HttpResponse::Ok().json(response),
+        Err(failed_rows) => ingest_error_response(&failed_rows),
+    }
+}
+
+/// Reports the rows of a batch that failed validation, none of the rows of the batch were ingested.
+fn ingest_error_response(failed_rows: &[(usize, IngestError)]) -> HttpResponse {
+    let failed_rows = failed_rows
+        .iter()
+        .map(|(row, err)| json!({"row": row, "error": err.to_string()}))
+        .collect::<Vec<_>>();
+    HttpResponse::BadRequest().json(json!({
+        "error": format!("{} rows are invalid, no rows were ingested", failed_rows.len()),
+        "failed_rows": failed_rows,
+    }))
+}
+
+/// Ingests points in InfluxDB line protocol into tables named after their measurement.
+#[post("/write")]
+async fn write(data: web::Data<AppState>, params: web::Query<WriteParams>, req_body: String) -> impl Responder {
+    let precision = match params.precision.as_deref() {
+        None => Precision::Nanoseconds,
+        Some(precision) => match Precision::parse(precision) {
+            Some(precision) => precision,
+            None => return error_response(StatusCode::BAD_REQUEST, &format!("Invalid precision {}", precision)),
+        },
+    };
+    let now = chrono::Utc::now().timestamp_millis();
+    let points = match line_protocol::parse_lines(&req_body, precision, now) {
+        Ok(points) => points,
+        Err(err) => return error_response(StatusCode::BAD_REQUEST, &err),
+    };
+    log::info!("Writing {} points", points.len());
+    let failed_points = points
+        .iter()
+        .enumerate()
+        .filter_map(|(i, point)| validate_row(&point.columns).err().map(|err| (i, err)))
+        .collect::<Vec<_>>();
+    if !failed_points.is_empty() {
+        return ingest_error_response(&failed_points);
+    }
+    let mut rows_by_table = BTreeMap::<String, Vec<_>>::new();
+    for point in points {
+        rows_by_table.entry(point.measurement).or_default().push(point.columns);
+    }
+    for (table, rows) in rows_by_table {
+        if let Err(failed_rows) = data.db.ingest(&table, rows).await {
+            return ingest_error_response(&failed_rows);
+        }
+    }
+    HttpResponse::NoContent().finish()
+}
+
+#[post("/insert_columnar")]
+async fn insert_columnar(
+    data: web::Data<AppState>,
+    req_body: web::Json<ColumnarBatch>,
+) -> impl Responder {
+    let ColumnarBatch { table, columns, timestamp_columns } = req_body.0;
+    let rows = columns.values().next().map_or(0, |values| values.len());
+    if let Some((colname, values)) = columns.iter().find(|(_, values)| values.len() != rows) {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            &format!(
+                "All columns must have the same length, but column {} has {} values instead of {}",
+                colname,
+                values.len(),
+                rows
+            ),
+        );
+    }
+    log::info!("Inserting {} columns with {} rows into {}", columns.len(), rows, table);
+
+    let homogeneous = columns
+        .iter()
+        .map(|(colname, values)| {
+            let column = if timestamp_columns.contains(colname) {
+                json_timestamp_column(values)
+            } else {
+                json_homogeneous_column(values)
+            };
+            Some((colname.to_string(), column?))
+        })
+        .collect::<Option<HashMap<_, _>>>();
+    match homogeneous {
+        Some(columns) => data.db.ingest_homogeneous(&table, columns).await,
+        None => {
+            let mut converted = HashMap::with_capacity(columns.len());
+            for (colname, values) in columns {
+                let is_timestamp = timestamp_columns.contains(&colname);
+                let values = match values
+                    .into_iter()
+                    .map(|val| {
+                        if is_timestamp {
+                            json_to_timestamp(&colname, val)
+                        } else {
+                            json_to_raw_val(&colname, val)
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                {
+                    Ok(values) => values,
+                    Err(err) => return error_response(StatusCode::BAD_REQUEST, &err),
+                };
+                converted.insert(colname, values);
+            }
+            data.db.ingest_heterogeneous(&table, converted).await
+        }
+    }
+    HttpResponse::Ok().json(json!({"status": "ok", "rows": rows}))
+}
+
+fn json_to_raw_val(colname: &str, val: serde_json::Value) -> Result<RawVal, String> {
+    Ok(match val {
+        serde_json::Value::Null => RawVal::Null,
+        serde_json::Value::Number(n) => {
+            if n.is_i64() {
+                RawVal::Int(n.as_i64().unwrap())
+            } else if n.is_f64() {
+                RawVal::Float(OrderedFloat(n.as_f64().unwrap()))
+            } else {
+                return Err(format!("Unsupported number {} in column {}", n, colname));
+            }
+        }
+        serde_json::Value::String(s) => RawVal::Str(s),
+        serde_json::Value::Bool(b) => RawVal::Bool(b),
+        serde_json::Value::Array(_) | serde_json::Value::Object(_) => RawVal::Str(val.to_string()),
+    })
+}
+
+/// Splits nested objects into columns named by the dotted path of their fields, up to `depth` levels deep.
+fn flatten_json(colname: String, val: serde_json::Value, depth: usize, row: &mut Vec<(String, serde_json::Value)>) {
+    match val {
+        serde_json::Value::Object(fields) if depth > 0 => {
+            for (field, val) in fields {
+                flatten_json(format!("{}.{}", colname, field), val, depth - 1, row);
+            }
+        }
+        _ => row.push((colname, val)),
+    }
+}
+
+/// Converts unix milliseconds or an RFC 3339 string such as `2021-12-20T18:33:17.013Z` to a timestamp.
+fn json_to_timestamp(colname: &str, val: serde_json::Value) -> Result<RawVal, String> {
+    Ok(match val {
+        serde_json::Value::Null => RawVal::Null,
+        serde_json::Value::Number(ref n) if n.is_i64() => RawVal::Timestamp(n.as_i64().unwrap()),
+        serde_json::Value::Number(ref n) if n.is_f64() => RawVal::Timestamp(n.as_f64().unwrap().round() as i64),
+        serde_json::Value::String(ref s) => match DateTime::parse_from_rfc3339(s) {
+            Ok(datetime) => RawVal::Timestamp(datetime.timestamp_millis()),
+            Err(err) => return Err(format!("Invalid timestamp {} in column {}: {}", s, colname, err)),
+        },
+        _ => return Err(format!("Unsupported timestamp {} in column {}", val, colname)),
+    })
+}
+
+/// Converts a column of timestamps, or returns `None` if it contains nulls or invalid values.
+fn json_timestamp_column(values: &[serde_json::Value]) -> Option<InputColumn> {
+    let timestamps = values
+        .iter()
+        .map(|val| match json_to_timestamp("", val.clone()) {
+            Ok(RawVal::Timestamp(millis)) => Some(millis),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    Some(InputColumn::Timestamp(timestamps))
+}
+
+/// Converts a column of JSON values that all have the same type, or returns `None` if types are mixed or unsupported.
+fn json_homogeneous_column(values: &[serde_json::Value]) -> Option<InputColumn> {
+    if values.iter().all(|val| val.is_null()) {
+        Some(InputColumn::Null(values.len()))
+    } else if values.iter().all(|val| val.is_i64()) {
+        Some(InputColumn::Int(values.iter().map(|val| val.as_i64()).collect::<Option<_>>()?))
+    } else if values.iter().all(|val| val.is_number()) {
+        Some(InputColumn::Float(values.iter().map(|val| val.as_f64()).collect::<Option<_>>()?))
+    } else if values.iter().all(|val| val.is_boolean()) {
+        Some(InputColumn::Bool(values.iter().map(|val| val.as_bool()).collect::<Option<_>>()?))
+    } else if values.iter().all(|val| val.is_string()) {
+        Some(InputColumn::Str(
+            values
+                .iter()
+                .map(|val| val.as_str().map(|s| s.to_string()))
+                .collect::<Option<_>>()?,
+        ))
+    } else {
+        None
+    }
+}
+
+#[post("/insert_csv")]
+async fn insert_csv(
+    data: web::Data<AppState>,
+    params: web::Query<InsertCsvParams>,
+    req_body: web::Bytes,
+) -> impl Responder {
+    log::info!("Inserting CSV into {} ({} bytes)", params.table, req_body.len());
+    let mut reader = csv::ReaderBuilder::new().from_reader(&req_body[..]);
+    let colnames = match reader.headers() {
+        Ok(headers) => headers.iter().map(|h| h.to_string()).collect::<Vec<_>>(),
+        Err(err) => return error_response(StatusCode::BAD_REQUEST, &format!("Invalid CSV header: {}", err)),
+    };
+
+    let mut records = reader.records();
+    let mut batch = Vec::with_capacity(CSV_INGEST_BATCH_SIZE);
+    for record in records.by_ref().take(CSV_TYPE_INFERENCE_ROWS) {
+        match record {
+            Ok(record) => batch.push(record),
+            Err(err) => return error_response(StatusCode::BAD_REQUEST, &format!("Invalid CSV: {}", err)),
+        }
+    }
+    let types = infer_csv_types(colnames.len(), &batch);
+
+    let mut rows = 0;
+    loop {
+        for record in records.by_ref().take(CSV_INGEST_BATCH_SIZE - batch.len()) {
+            match record {
+                Ok(record) => batch.push(record),
+                Err(err) => return error_response(StatusCode::BAD_REQUEST, &format!("Invalid CSV: {}", err)),
+            }
+        }
+        if batch.is_empty() {
+            break;
+        }
+        rows += batch.len();
+        match csv_homogeneous_columns(&colnames, &types, &batch) {
+            Some(columns) => data.db.ingest_homogeneous(&params.table, columns).await,
+            None => {
+                let columns = csv_heterogeneous_columns(&colnames, &types, &batch);
+                data.db.ingest_heterogeneous(&params.table, columns).await
+            }
+        }
+        batch.clear();
+    }
+    HttpResponse::Ok().json(json!({"status": "ok", "rows": rows}))
+}
+
+/// Ingests a Parquet file that is located on the server into a table.
+#[post("/load_parquet")]
+async fn load_parquet(data: web::Data<AppState>, req_body: web::Json<LoadParquetRequest>) -> impl Responder {
+    log::info!("Loading {} into {}", req_body.path, req_body.table);
+    match data.db.load_parquet(&req_body.table, &req_body.path).await {
+        Ok(rows) => HttpResponse::Ok().json(json!({"status": "ok", "rows": rows})),
+        Err(err) => error_response(StatusCode::BAD_REQUEST, &format!("Failed to load {}: {}", req_body.path, err)),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum CsvType {
+    Int,
+    Float,
+    Str,
+}
+
+fn infer_csv_types(columns: usize, sample: &[csv::StringRecord]) -> Vec<CsvType> {
+    (0..columns)
+        .map(|i| {
+            let mut t = CsvType::Int;
+            for field in sample.iter().filter_map(|record| record.get(i)) {
+                if field.is_empty() || (t == CsvType::Int && field.parse::<i64>().is_ok()) {
+                    continue;
+                }
+                if field.parse::<f64>().is_ok() {
+                    t = CsvType::Float;
+                } else {
+                    return CsvType::Str;
+                }
+            }
+            t
+        })
+        .collect()
+}
+
+/// Builds typed columns for a batch of records, or returns `None` if any field is empty or does not match the inferred type.
+fn csv_homogeneous_columns(
+    colnames: &[String],
+    types: &[CsvType],
+    records: &[csv::StringRecord],
+) -> Option<HashMap<String, InputColumn>> {
+    let mut columns = HashMap::with_capacity(colnames.len());
+    for (i, (colname, t)) in colnames.iter().zip(types).enumerate() {
+        let fields = records.iter().map(|record| record.get(i).filter(|field| !field.is_empty()));
+        let column = match t {
+            CsvType::Int => InputColumn::Int(
+                fields.map(|field| field?.parse::<i64>().ok()).collect::<Option<_>>()?,
+            ),
+            CsvType::Float => InputColumn::Float(
+                fields.map(|field| field?.parse::<f64>().ok()).collect::<Option<_>>()?,
+            ),
+            CsvType::Str => InputColumn::Str(
+                fields.map(|field| Some(field?.to_string())).collect::<Option<_>>()?,
+            ),
+        };
+        columns.insert(colname.to_string(), column);
+    }
+    Some(columns)
+}
+
+fn csv_heterogeneous_columns(
+    colnames: &[String],
+    types: &[CsvType],
+    records: &[csv::StringRecord],
+) -> HashMap<String, Vec<RawVal>> {
+    let mut columns = HashMap::with_capacity(colnames.len());
+    for (i, (colname, t)) in colnames.iter().zip(types).enumerate() {
+        let column = records
+            .iter()
+            .map(|record| match record.get(i) {
+                None | Some("") => RawVal::Null,
+                Some(field) => match t {
+                    CsvType::Int => field.parse::<i64>().map(RawVal::Int).ok(),
+                    CsvType::Float => field.parse::<f64>().map(|f| RawVal::Float(OrderedFloat(f))).ok(),
+                    CsvType::Str => None,
+                }
+                .unwrap_or_else(|| RawVal::Str(field.to_string())),
+            })
+            .collect();
+        columns.insert(colname.to_string(), column);
+    }
+    columns
 }
 
 async fn manual_hello() -> impl Responder {
     HttpResponse::Ok().body("Hey there!")
 }
 
-pub async fn run(db: LocustDB) -> std::io::Result<()> {
+/// Marks requests made with a read-only API key.
+struct ReadOnlyKey;
+
+/// Rejects requests without a valid API key and requests that modify data with a read-only key.
+fn check_api_key<S>(
+    api_keys: &ApiKeys,
+    req: ServiceRequest,
+    srv: &S,
+) -> Either<future::Ready<Result<ServiceResponse, actix_web::Error>>, S::Future>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse, Error = actix_web::Error>,
+{
+    match api_keys.authorize(req.method(), req.path(), req.headers()) {
+        Authorization::Granted => Either::Right(srv.call(req)),
+        Authorization::ReadOnly => {
+            req.extensions_mut().insert(ReadOnlyKey);
+            Either::Right(srv.call(req))
+        }
+        Authorization::Unauthorized => {
+            let mut response = error_response(StatusCode::UNAUTHORIZED, "Missing or invalid API key");
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, header::HeaderValue::from_static("Bearer"));
+            Either::Left(future::ok(req.into_response(response)))
+        }
+        Authorization::Forbidden => {
+            let response = error_response(StatusCode::FORBIDDEN, "API key is read-only");
+            Either::Left(future::ok(req.into_response(response)))
+        }
+    }
+}
+
+/// Runs the HTTP server on `addr`, which is either `host:port` or `unix:/path/to/socket`.
+pub async fn run(db: LocustDB, addr: &str, cors: CorsOptions) -> std::io::Result<()> {
+    let compression_level = db.opts().http_compression_level;
+    let api_keys = Arc::new(ApiKeys {
+        read_write: db.opts().api_keys.clone(),
+        read_only: db.opts().read_only_api_keys.clone(),
+    });
+    let mut cors = cors;
+    if api_keys.is_enabled() {
+        cors.allow_api_key_headers();
+    }
     let db = Arc::new(db);
-    HttpServer::new(move || {
+    let cors = Arc::new(cors);
+    let server = HttpServer::new(move || {
         let app_state = AppState { db: db.clone() };
+        let cors = cors.clone();
+        let api_keys = api_keys.clone();
         App::new()
+            .wrap_fn(move |req, srv| check_api_key(&api_keys, req, srv))
+            .wrap_fn(move |req, srv| {
+                let encoding = compression_level.and_then(|_| Encoding::negotiate(req.headers()));
+                let response = srv.call(req);
+                async move {
+                    let response = response.await?;
+                    match (encoding, compression_level) {
+                        (Some(encoding), Some(level)) => compress_response(response, encoding, level).await,
+                        _ => Ok(response),
+                    }
+                }
+            })
+            .wrap_fn(move |req, srv| {
+                let origin = cors.allowed_origin(req.headers());
+                match origin {
+                    Some(origin)
+                        if req.method() == Method::OPTIONS
+                            && req.headers().contains_key(header::ACCESS_CONTROL_REQUEST_METHOD) =>
+                    {
+                        let response = req.into_response(cors.preflight_response(origin));
+                        Either::Left(future::ok(response))
+                    }
+                    _ => {
+                        let cors = cors.clone();
+                        let response = srv.call(req);
+                        Either::Right(async move {
+                            let mut response = response.await?;
+                            if let Some(origin) = origin {
+                                cors.add_headers(origin, response.headers_mut());
+                            }
+                            Ok(response)
+                        })
+                    }
+                }
+            })
             .app_data(Data::new(app_state))
             .app_data(Data::new(web::PayloadConfig::new(100 * 1024 * 1024)))
             .service(index)
             .service(echo)
             .service(tables)
+            .service(schema)
+            .service(storage_stats)
+            .service(mem_tree)
+            .service(table_schema_handler)
             .service(query)
+            .service(query_stream)
+            .service(query_stream_get)
+            .service(query_history)
+            .service(running_queries)
+            .service(cache_stats)
+            .service(cancel_query)
+            .service(worker_threads)
+            .service(set_worker_threads)
+            .service(read_stats)
+            .service(set_read_threads)
+            .service(evict)
             .service(table_handler)
+            .service(drop_table)
             .service(insert)
+            .service(insert_csv)
+            .service(load_parquet)
+            .service(insert_columnar)
+            .service(write)
             .service(query_data)
             .service(query_cols)
             .service(plot)
             .route("/hey", web::get().to(manual_hello))
-    })
-    .bind("127.0.0.1:8080")?
-    .run()
-    .await
+    });
+    let server = match addr.strip_prefix("unix:") {
+        #[cfg(unix)]
+        Some(path) => server.bind_uds(path)?,
+        #[cfg(not(unix))]
+        Some(_) => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "Unix sockets are not supported on this platform",
+            ))
+        }
+        None => server.bind(addr)?,
+    };
+    server.run().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LoadOptions;
+    use actix_web::test;
+
+    #[actix_web::test]
+    async fn test_read_only_key_cannot_delete() {
+        let db = LocustDB::memory_only();
+        db.load_csv(LoadOptions::new("test_data/edge_cases.csv", "default").with_partition_size(3))
+            .await
+            .unwrap();
+        let api_keys = ApiKeys {
+            read_write: ["secret".to_string()].into_iter().collect(),
+            read_only: ["reader".to_string()].into_iter().collect(),
+        };
+        let app = test::init_service(
+            App::new()
+                .wrap_fn(move |req, srv| check_api_key(&api_keys, req, srv))
+                .app_data(Data::new(AppState { db: Arc::new(db) }))
+                .service(query),
+        )
+        .await;
+        let run = |key: &'static str, sql: &'static str| {
+            let req = test::TestRequest::post()
+                .uri("/query")
+                .insert_header(("X-API-Key", key))
+                .set_json(json!({ "query": sql }))
+                .to_request();
+            test::call_service(&app, req)
+        };
+
+        let count = "SELECT COUNT(0) FROM default";
+        assert_eq!(run("reader", count).await.status(), StatusCode::OK);
+        let delete = "DELETE FROM default WHERE id < 3";
+        assert_eq!(run("reader", delete).await.status(), StatusCode::FORBIDDEN);
+        let batch = "SELECT COUNT(0) FROM default; DELETE FROM default WHERE id < 3";
+        assert_eq!(run("reader", batch).await.status(), StatusCode::FORBIDDEN);
+        let response: serde_json::Value = test::read_body_json(run("reader", count).await).await;
+        assert_eq!(response["rows"], json!([[10]]));
+
+        assert_eq!(run("secret", delete).await.status(), StatusCode::OK);
+        let response: serde_json::Value = test::read_body_json(run("reader", count).await).await;
+        assert_eq!(response["rows"], json!([[7]]));
+    }
 }
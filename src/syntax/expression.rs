@@ -1,15 +1,20 @@
 use self::Expr::*;
 use crate::engine::*;
-use crate::ingest::raw_val::RawVal;
-use std::collections::HashSet;
+use crate::ingest::raw_val::{format_timestamp, RawVal};
+use ordered_float::OrderedFloat;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
     ColName(String),
     Const(RawVal),
     Func1(Func1Type, Box<Expr>),
     Func2(Func2Type, Box<Expr>, Box<Expr>),
     Aggregate(Aggregator, Box<Expr>),
+    /// `CASE WHEN cond THEN value ... ELSE default END`, the first branch whose condition is true
+    /// determines the value. A missing `ELSE` is represented by a null constant.
+    Case(Vec<(Expr, Expr)>, Box<Expr>),
 }
 
 #[allow(clippy::upper_case_acronyms)]
@@ -17,6 +22,8 @@ pub enum Expr {
 pub enum Func2Type {
     Equals,
     NotEquals,
+    /// `a <=> b` or `a IS NOT DISTINCT FROM b`, like `=` except that NULL is equal to NULL and the result is never NULL.
+    NullSafeEquals,
     LT,
     LTE,
     GT,
@@ -26,21 +33,56 @@ pub enum Func2Type {
     Add,
     Subtract,
     Multiply,
+    /// Integer division if both operands are integers, e.g. `7 / 2 = 3`.
+    /// If either operand is a float the result is a float, e.g. `7.0 / 2 = 3.5`.
+    /// Division by zero yields NULL for both integers and floats, e.g. `7.0 / 0.0` is NULL rather than infinity.
     Divide,
+    /// Remainder of the division, NULL if the divisor is zero like for `Divide`.
     Modulo,
+    /// Integer power if both operands are integers, e.g. `POW(2, 10) = 1024`, where negative exponents truncate
+    /// like integer division. If either operand is a float the result is a float, e.g. `POW(2.0, 0.5) = 1.414...`.
+    Pow,
     RegexMatch,
     Like,
     NotLike,
+    Concat,
+    Round,
+    DateTrunc,
+    /// Returns the lhs if it is not null and the rhs otherwise.
+    Coalesce,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Func1Type {
     Negate,
     ToYear,
+    ToMonth,
+    ToDay,
+    ToHour,
     Not,
     IsNull,
     IsNotNull,
     Length,
+    /// Converts a string to lowercase, according to the Unicode `Lowercase` property.
+    Lower,
+    /// Converts a string to uppercase, according to the Unicode `Uppercase` property.
+    Upper,
+    Floor,
+    Ceil,
+    Round,
+    Abs,
+    Sign,
+    /// Square root, null for negative values.
+    Sqrt,
+    /// Natural logarithm, null for negative values.
+    Ln,
+    /// Base 10 logarithm, null for negative values.
+    Log10,
+    Exp,
+    /// `SAMPLE(fraction)`, true for a random subset of rows that contains each row with probability `fraction`.
+    Sample,
+    /// Maps zero to null, used to make division by empty counts yield null.
+    NullIfZero,
 }
 
 impl Expr {
@@ -55,10 +97,115 @@ impl Expr {
             }
             Func1(_, ref expr) => expr.add_colnames(result),
             Aggregate(_, ref expr) => expr.add_colnames(result),
+            Case(ref branches, ref default) => {
+                for (cond, value) in branches {
+                    cond.add_colnames(result);
+                    value.add_colnames(result);
+                }
+                default.add_colnames(result);
+            }
             Const(_) => {}
         }
     }
 
+    /// Whether the expression evaluates to the same values every time it is applied to the same rows.
+    pub fn is_deterministic(&self) -> bool {
+        match *self {
+            Func1(Func1Type::Sample, _) => false,
+            Func1(_, ref expr) | Aggregate(_, ref expr) => expr.is_deterministic(),
+            Func2(_, ref expr1, ref expr2) => expr1.is_deterministic() && expr2.is_deterministic(),
+            Case(ref branches, ref default) => {
+                branches.iter().all(|(cond, value)| cond.is_deterministic() && value.is_deterministic())
+                    && default.is_deterministic()
+            }
+            ColName(_) | Const(_) => true,
+        }
+    }
+
+    /// Whether the expression evaluates to a timestamp, given the names of the columns that contain timestamps.
+    pub fn is_timestamp(&self, timestamp_columns: &HashSet<String>) -> bool {
+        match *self {
+            ColName(ref name) => timestamp_columns.contains(name),
+            Const(RawVal::Timestamp(_)) => true,
+            Func2(Func2Type::DateTrunc, _, ref timestamp)
+            | Func1(Func1Type::ToMonth | Func1Type::ToDay | Func1Type::ToHour, ref timestamp) => {
+                timestamp.is_timestamp(timestamp_columns)
+            }
+            Aggregate(Aggregator::MaxI64 | Aggregator::MinI64, ref expr) => expr.is_timestamp(timestamp_columns),
+            _ => false,
+        }
+    }
+
+    /// Rewrites time functions applied to timestamps, which are in milliseconds, to operate on unix seconds.
+    /// Truncated timestamps, e.g. `date_trunc` or `to_day` of a timestamp, are converted back to milliseconds.
+    pub fn convert_timestamps(self, timestamp_columns: &HashSet<String>) -> Expr {
+        let to_seconds = |expr: Expr| Expr::func(Func2Type::Divide, expr, Const(RawVal::Int(1000)));
+        let to_millis = |expr: Expr| Expr::func(Func2Type::Multiply, expr, Const(RawVal::Int(1000)));
+        match self {
+            Func1(Func1Type::ToYear, expr) => {
+                let is_timestamp = expr.is_timestamp(timestamp_columns);
+                let expr = expr.convert_timestamps(timestamp_columns);
+                Expr::func1(Func1Type::ToYear, if is_timestamp { to_seconds(expr) } else { expr })
+            }
+            Func1(ftype @ (Func1Type::ToMonth | Func1Type::ToDay | Func1Type::ToHour), expr) => {
+                let is_timestamp = expr.is_timestamp(timestamp_columns);
+                let expr = expr.convert_timestamps(timestamp_columns);
+                if is_timestamp {
+                    to_millis(Expr::func1(ftype, to_seconds(expr)))
+                } else {
+                    Expr::func1(ftype, expr)
+                }
+            }
+            Func2(Func2Type::DateTrunc, unit, expr) => {
+                let is_timestamp = expr.is_timestamp(timestamp_columns);
+                let expr = expr.convert_timestamps(timestamp_columns);
+                if is_timestamp {
+                    to_millis(Func2(Func2Type::DateTrunc, unit, Box::new(to_seconds(expr))))
+                } else {
+                    Func2(Func2Type::DateTrunc, unit, Box::new(expr))
+                }
+            }
+            Func1(ftype, expr) => Expr::func1(ftype, expr.convert_timestamps(timestamp_columns)),
+            Func2(ftype, lhs, rhs) => Expr::func(
+                ftype,
+                lhs.convert_timestamps(timestamp_columns),
+                rhs.convert_timestamps(timestamp_columns),
+            ),
+            Aggregate(aggregator, expr) => Aggregate(aggregator, Box::new(expr.convert_timestamps(timestamp_columns))),
+            Case(branches, default) => Case(
+                branches
+                    .into_iter()
+                    .map(|(cond, value)| {
+                        (cond.convert_timestamps(timestamp_columns), value.convert_timestamps(timestamp_columns))
+                    })
+                    .collect(),
+                Box::new(default.convert_timestamps(timestamp_columns)),
+            ),
+            ColName(_) | Const(_) => self,
+        }
+    }
+
+    /// Replaces references to the given names with the corresponding expressions.
+    pub fn inline_aliases(self, aliases: &HashMap<String, Expr>) -> Expr {
+        match self {
+            ColName(name) => match aliases.get(&name) {
+                Some(expr) => expr.clone(),
+                None => ColName(name),
+            },
+            Func1(ftype, expr) => Expr::func1(ftype, expr.inline_aliases(aliases)),
+            Func2(ftype, lhs, rhs) => Expr::func(ftype, lhs.inline_aliases(aliases), rhs.inline_aliases(aliases)),
+            Aggregate(aggregator, expr) => Aggregate(aggregator, Box::new(expr.inline_aliases(aliases))),
+            Case(branches, default) => Case(
+                branches
+                    .into_iter()
+                    .map(|(cond, value)| (cond.inline_aliases(aliases), value.inline_aliases(aliases)))
+                    .collect(),
+                Box::new(default.inline_aliases(aliases)),
+            ),
+            Const(_) => self,
+        }
+    }
+
     pub fn func(ftype: Func2Type, expr1: Expr, expr2: Expr) -> Expr {
         Func2(ftype, Box::new(expr1), Box::new(expr2))
     }
@@ -66,4 +213,211 @@ impl Expr {
     pub fn func1(ftype: Func1Type, expr: Expr) -> Expr {
         Func1(ftype, Box::new(expr))
     }
+
+    pub fn col(name: &str) -> Expr {
+        ColName(name.to_string())
+    }
+
+    pub fn lit<T: Into<RawVal>>(value: T) -> Expr {
+        Const(value.into())
+    }
+
+    /// `COUNT(*)`
+    pub fn count() -> Expr {
+        Aggregate(Aggregator::Count, Box::new(Const(RawVal::Int(1))))
+    }
+
+    pub fn count_distinct(expr: Expr) -> Expr {
+        Aggregate(Aggregator::CountDistinct, Box::new(expr))
+    }
+
+    // Like the parser, sum/max/min are represented by their integer variants and converted by the
+    // query planner once the type of the argument is known.
+    pub fn sum(expr: Expr) -> Expr {
+        Aggregate(Aggregator::SumI64, Box::new(expr))
+    }
+
+    pub fn avg(expr: Expr) -> Expr {
+        Aggregate(Aggregator::Avg, Box::new(expr))
+    }
+
+    pub fn var_pop(expr: Expr) -> Expr {
+        Aggregate(Aggregator::VarPop, Box::new(expr))
+    }
+
+    pub fn stddev(expr: Expr) -> Expr {
+        Aggregate(Aggregator::StdDev, Box::new(expr))
+    }
+
+    pub fn max(expr: Expr) -> Expr {
+        Aggregate(Aggregator::MaxI64, Box::new(expr))
+    }
+
+    pub fn min(expr: Expr) -> Expr {
+        Aggregate(Aggregator::MinI64, Box::new(expr))
+    }
+
+    /// `PERCENTILE(expr, quantile)`, where `quantile` is a fraction between 0 and 1.
+    pub fn percentile(expr: Expr, quantile: f64) -> Expr {
+        Aggregate(Aggregator::ApproxPercentile(OrderedFloat(quantile)), Box::new(expr))
+    }
+}
+
+/// Formats the expression as SQL, which is used as the name of selected expressions without alias.
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ColName(name) => write!(f, "{}", name),
+            Const(RawVal::Str(s)) => write!(f, "'{}'", s),
+            Const(RawVal::Float(x)) => write!(f, "{}", x),
+            Const(RawVal::Timestamp(t)) => write!(f, "'{}'", format_timestamp(*t)),
+            Const(RawVal::Null) => write!(f, "NULL"),
+            Const(value) => write!(f, "{}", value),
+            Func1(Func1Type::Negate, expr) => write!(f, "-{}", Operand(expr)),
+            Func1(Func1Type::Not, expr) => write!(f, "NOT {}", Operand(expr)),
+            Func1(Func1Type::IsNull, expr) => write!(f, "{} IS NULL", Operand(expr)),
+            Func1(Func1Type::IsNotNull, expr) => write!(f, "{} IS NOT NULL", Operand(expr)),
+            Func1(Func1Type::NullIfZero, expr) => write!(f, "NULLIF({}, 0)", expr),
+            Func1(ftype, expr) => write!(f, "{}({})", func1_name(*ftype), expr),
+            Func2(ftype, lhs, rhs) => match infix_operator(*ftype) {
+                Some(operator) => write!(f, "{} {} {}", Operand(lhs), operator, Operand(rhs)),
+                None => write!(f, "{}({}, {})", func2_name(*ftype), lhs, rhs),
+            },
+            Aggregate(Aggregator::CountDistinct, expr) => write!(f, "COUNT(DISTINCT {})", expr),
+            Aggregate(Aggregator::ApproxPercentile(quantile), expr) => write!(f, "PERCENTILE({}, {})", expr, quantile),
+            Aggregate(aggregator, expr) => write!(f, "{}({})", aggregator_name(*aggregator), expr),
+            Case(branches, default) => {
+                write!(f, "CASE")?;
+                for (cond, value) in branches {
+                    write!(f, " WHEN {} THEN {}", cond, value)?;
+                }
+                if !matches!(**default, Const(RawVal::Null)) {
+                    write!(f, " ELSE {}", default)?;
+                }
+                write!(f, " END")
+            }
+        }
+    }
+}
+
+/// Operand of an operator, which is parenthesized if it is an operator expression itself.
+struct Operand<'a>(&'a Expr);
+
+impl<'a> fmt::Display for Operand<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            Func2(ftype, _, _) if infix_operator(*ftype).is_some() => write!(f, "({})", self.0),
+            Func1(Func1Type::Negate | Func1Type::Not | Func1Type::IsNull | Func1Type::IsNotNull, _) => {
+                write!(f, "({})", self.0)
+            }
+            expr => write!(f, "{}", expr),
+        }
+    }
+}
+
+fn infix_operator(ftype: Func2Type) -> Option<&'static str> {
+    Some(match ftype {
+        Func2Type::Equals => "=",
+        Func2Type::NotEquals => "<>",
+        Func2Type::NullSafeEquals => "<=>",
+        Func2Type::LT => "<",
+        Func2Type::LTE => "<=",
+        Func2Type::GT => ">",
+        Func2Type::GTE => ">=",
+        Func2Type::And => "AND",
+        Func2Type::Or => "OR",
+        Func2Type::Add => "+",
+        Func2Type::Subtract => "-",
+        Func2Type::Multiply => "*",
+        Func2Type::Divide => "/",
+        Func2Type::Modulo => "%",
+        Func2Type::Like => "LIKE",
+        Func2Type::NotLike => "NOT LIKE",
+        Func2Type::Concat => "||",
+        _ => return None,
+    })
+}
+
+fn func2_name(ftype: Func2Type) -> &'static str {
+    match ftype {
+        Func2Type::Pow => "POW",
+        Func2Type::RegexMatch => "REGEX",
+        Func2Type::Round => "ROUND",
+        Func2Type::DateTrunc => "DATE_TRUNC",
+        Func2Type::Coalesce => "COALESCE",
+        _ => unreachable!("{:?} is an infix operator", ftype),
+    }
+}
+
+fn func1_name(ftype: Func1Type) -> &'static str {
+    match ftype {
+        Func1Type::ToYear => "TO_YEAR",
+        Func1Type::ToMonth => "TO_MONTH",
+        Func1Type::ToDay => "TO_DAY",
+        Func1Type::ToHour => "TO_HOUR",
+        Func1Type::Length => "LENGTH",
+        Func1Type::Lower => "LOWER",
+        Func1Type::Upper => "UPPER",
+        Func1Type::Floor => "FLOOR",
+        Func1Type::Ceil => "CEIL",
+        Func1Type::Round => "ROUND",
+        Func1Type::Abs => "ABS",
+        Func1Type::Sign => "SIGN",
+        Func1Type::Sqrt => "SQRT",
+        Func1Type::Ln => "LN",
+        Func1Type::Log10 => "LOG10",
+        Func1Type::Exp => "EXP",
+        Func1Type::Sample => "SAMPLE",
+        Func1Type::Negate | Func1Type::Not | Func1Type::IsNull | Func1Type::IsNotNull | Func1Type::NullIfZero => {
+            unreachable!("{:?} is an operator", ftype)
+        }
+    }
+}
+
+fn aggregator_name(aggregator: Aggregator) -> &'static str {
+    match aggregator {
+        Aggregator::SumI64 | Aggregator::SumF64 => "SUM",
+        Aggregator::Count | Aggregator::CountDistinct => "COUNT",
+        Aggregator::MaxI64 | Aggregator::MaxF64 | Aggregator::MaxStr => "MAX",
+        Aggregator::MinI64 | Aggregator::MinF64 | Aggregator::MinStr => "MIN",
+        Aggregator::Avg => "AVG",
+        Aggregator::VarPop => "VAR_POP",
+        Aggregator::StdDev => "STDDEV",
+        Aggregator::ApproxPercentile(_) => "PERCENTILE",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let cpu = || Expr::col("cpu");
+        assert_eq!(Expr::func(Func2Type::Multiply, cpu(), Expr::lit(100)).to_string(), "cpu * 100");
+        assert_eq!(
+            Expr::func(
+                Func2Type::Multiply,
+                Expr::func(Func2Type::Add, cpu(), Expr::lit(1.5)),
+                Expr::func1(Func1Type::Negate, Expr::col("x")),
+            )
+            .to_string(),
+            "(cpu + 1.5) * (-x)"
+        );
+        assert_eq!(Expr::func1(Func1Type::Length, Expr::lit("abc")).to_string(), "LENGTH('abc')");
+        assert_eq!(Expr::func1(Func1Type::IsNull, cpu()).to_string(), "cpu IS NULL");
+        assert_eq!(Expr::func(Func2Type::NullSafeEquals, cpu(), Expr::col("x")).to_string(), "cpu <=> x");
+        assert_eq!(Expr::func(Func2Type::Round, cpu(), Expr::lit(2)).to_string(), "ROUND(cpu, 2)");
+        assert_eq!(Expr::count_distinct(cpu()).to_string(), "COUNT(DISTINCT cpu)");
+        assert_eq!(Expr::percentile(cpu(), 0.99).to_string(), "PERCENTILE(cpu, 0.99)");
+        assert_eq!(
+            Expr::func(Func2Type::Divide, Expr::sum(cpu()), Expr::count()).to_string(),
+            "SUM(cpu) / COUNT(1)"
+        );
+        assert_eq!(
+            Expr::Case(vec![(Expr::func(Func2Type::GT, cpu(), Expr::lit(90)), Expr::lit("high"))], Box::new(Expr::Const(RawVal::Null)))
+                .to_string(),
+            "CASE WHEN cpu > 90 THEN 'high' END"
+        );
+    }
 }
@@ -1,5 +1,6 @@
 extern crate sqlparser;
 
+use crate::engine::Join;
 use crate::engine::Query;
 use crate::engine::*;
 use crate::ingest::raw_val::RawVal;
@@ -7,17 +8,90 @@ use crate::syntax::expression::Expr;
 use crate::syntax::expression::*;
 use crate::syntax::limit::*;
 use crate::QueryError;
+use ordered_float::OrderedFloat;
 use sqlparser::ast::{Expr as ASTNode, *};
+use sqlparser::dialect::keywords::Keyword;
 use sqlparser::dialect::GenericDialect;
 use sqlparser::parser::{Parser, ParserError};
+use sqlparser::tokenizer::{Token, Tokenizer, Whitespace, Word};
+use std::collections::{HashMap, HashSet};
+
+/// Result of parsing a query, either a single `SELECT`, multiple `SELECT`s combined with `UNION ALL`,
+/// a `SELECT` from two joined tables, a `SHOW` statement or a `DELETE`.
+#[derive(Debug, Clone)]
+pub enum ParsedQuery {
+    Select(Query),
+    UnionAll(UnionAll),
+    Join(Join),
+    /// `SHOW TABLES`
+    ShowTables,
+    /// `SHOW COLUMNS FROM table`
+    ShowColumns(String),
+    /// `SELECT` whose WHERE clause contains `expr [NOT] IN (SELECT ...)`, the subqueries are executed first.
+    WithSubqueries(Query, Vec<Subquery>),
+    /// `DELETE FROM table WHERE filter`, the filter is `1` if there is no WHERE clause.
+    /// Matching rows are marked with tombstones and are physically removed by background compaction.
+    Delete { table: String, filter: Expr },
+}
+
+impl ParsedQuery {
+    /// Names of all tables read by the query.
+    pub fn tables(&self) -> Vec<String> {
+        let mut tables = match self {
+            ParsedQuery::ShowColumns(table) | ParsedQuery::Delete { table, .. } => vec![table.clone()],
+            _ => self.queries().iter().map(|query| query.table.clone()).collect::<Vec<_>>(),
+        };
+        tables.sort();
+        tables.dedup();
+        tables
+    }
+
+    /// All `SELECT` queries that are executed to compute the result, including subqueries.
+    fn queries(&self) -> Vec<&Query> {
+        match self {
+            ParsedQuery::Select(query) => vec![query],
+            ParsedQuery::UnionAll(union) => union.queries.iter().collect(),
+            ParsedQuery::Join(join) => join.queries.iter().collect(),
+            ParsedQuery::WithSubqueries(query, subqueries) => {
+                let mut queries = subqueries.iter().map(|subquery| &subquery.query).collect::<Vec<_>>();
+                queries.push(query);
+                queries
+            }
+            ParsedQuery::ShowTables | ParsedQuery::ShowColumns(_) | ParsedQuery::Delete { .. } => vec![],
+        }
+    }
+
+    /// Whether the result only changes when one of the `tables` changes.
+    /// `SHOW TABLES` also changes when tables are created or dropped, `DELETE` changes the table itself
+    /// and queries with non-deterministic expressions such as `SAMPLE` return different results every time.
+    pub fn is_cacheable(&self) -> bool {
+        !matches!(self, ParsedQuery::ShowTables | ParsedQuery::Delete { .. })
+            && self.queries().iter().all(|query| query.is_deterministic())
+    }
+
+    /// Whether running the query changes the contents of a table.
+    pub fn modifies_data(&self) -> bool {
+        matches!(self, ParsedQuery::Delete { .. })
+    }
+}
 
 // Convert sqlparser-rs `ASTNode` to LocustDB's `Query`
 pub fn parse_query(query: &str) -> Result<Query, QueryError> {
-    let dialect = GenericDialect {};
-    let mut ast = Parser::parse_sql(&dialect, query).map_err(|e| match e {
-        ParserError::ParserError(e_str) => QueryError::ParseError(e_str),
-        _ => fatal!("{:?}", e),
-    })?;
+    match parse(query)? {
+        ParsedQuery::Select(query) => Ok(query),
+        ParsedQuery::UnionAll(_) => Err(QueryError::NotImplemented("UNION ALL".to_string())),
+        ParsedQuery::Join(_) => Err(QueryError::NotImplemented("JOIN".to_string())),
+        ParsedQuery::ShowTables | ParsedQuery::ShowColumns(_) => Err(QueryError::NotImplemented("SHOW".to_string())),
+        ParsedQuery::WithSubqueries(..) => Err(QueryError::NotImplemented("IN subquery".to_string())),
+        ParsedQuery::Delete { .. } => Err(QueryError::NotImplemented("DELETE".to_string())),
+    }
+}
+
+pub fn parse(query: &str) -> Result<ParsedQuery, QueryError> {
+    if let Some(show) = parse_show(query)? {
+        return Ok(show);
+    }
+    let mut ast = parse_statements(query).map_err(convert_parser_error)?;
     if ast.len() > 1 {
         return Err(QueryError::ParseError(format!(
             "Expected a single query statement, but there are {}",
@@ -27,50 +101,20 @@ pub fn parse_query(query: &str) -> Result<Query, QueryError> {
 
     let query = match ast.pop().unwrap() {
         Statement::Query(query) => query,
+        Statement::Delete { table_name, selection } => {
+            let filter = match selection {
+                Some(ref s) => *convert_to_native_expr(s)?,
+                None => Expr::Const(RawVal::Int(1)),
+            };
+            return Ok(ParsedQuery::Delete { table: format!("{}", table_name), filter });
+        }
         _ => {
             return Err(QueryError::ParseError(
-                "Only SELECT queries are supported.".to_string(),
+                "Only SELECT and DELETE statements are supported.".to_string(),
             ))
         }
     };
 
-    let (projection, relation, selection, order_by, limit, offset) = get_query_components(query)?;
-    let projection = get_projection(projection)?;
-    let table = get_table_name(relation)?;
-    let filter = match selection {
-        Some(ref s) => *convert_to_native_expr(s)?,
-        None => Expr::Const(RawVal::Int(1)),
-    };
-    let order_by = get_order_by(order_by)?;
-    let limit_clause = LimitClause {
-        limit: get_limit(limit)?,
-        offset: get_offset(offset)?,
-    };
-
-    Ok(Query {
-        select: projection,
-        table,
-        filter,
-        order_by,
-        limit: limit_clause,
-    })
-}
-
-// TODO: use struct
-#[allow(clippy::type_complexity)]
-fn get_query_components(
-    query: Box<sqlparser::ast::Query>,
-) -> Result<
-    (
-        Vec<SelectItem>,
-        Option<TableFactor>,
-        Option<ASTNode>,
-        Option<Vec<OrderByExpr>>,
-        Option<ASTNode>,
-        Option<Offset>,
-    ),
-    QueryError,
-> {
     // TODO: return error if any unsupported query parts are present
     let sqlparser::ast::Query {
         body,
@@ -79,42 +123,356 @@ fn get_query_components(
         offset,
         ..
     } = *query;
+    let limit_clause = LimitClause {
+        limit: get_limit(limit)?,
+        offset: get_offset(offset)?,
+    };
+    match body {
+        SetExpr::SetOperation { .. } => {
+            let mut selects = Vec::new();
+            get_union_all_selects(body, &mut selects)?;
+            let order_by = get_union_order_by(order_by, &selects)?;
+            let mut queries = Vec::with_capacity(selects.len());
+            for select in selects {
+                let mut query = convert_select(select)?;
+                // Each query only needs to return the rows that could be part of the final result
+                query.order_by = order_by
+                    .iter()
+                    .map(|&(index, desc, nulls_first)| (query.select[index].expr.clone(), desc, nulls_first))
+                    .collect();
+                query.limit = LimitClause {
+                    limit: limit_clause.limit + limit_clause.offset,
+                    offset: 0,
+                };
+                queries.push(query);
+            }
+            Ok(ParsedQuery::UnionAll(UnionAll {
+                queries,
+                order_by,
+                limit: limit_clause,
+            }))
+        }
+        body => {
+            let mut select = get_select(body)?;
+            if select.from.iter().any(|table| !table.joins.is_empty()) {
+                let mut join = convert_join(select, order_by)?;
+                join.limit = limit_clause;
+                return Ok(ParsedQuery::Join(join));
+            }
+            let mut subqueries = Vec::new();
+            if let Some(selection) = &mut select.selection {
+                extract_subqueries(selection, &mut subqueries)?;
+            }
+            let mut query = convert_select(select)?;
+            query.order_by = get_order_by(order_by)?;
+            query.limit = limit_clause;
+            if subqueries.is_empty() {
+                Ok(ParsedQuery::Select(query))
+            } else {
+                Ok(ParsedQuery::WithSubqueries(query, subqueries))
+            }
+        }
+    }
+}
+
+/// Splits `;`-separated statements into the text of each statement, semicolons inside of literals are not separators.
+/// Empty statements are omitted, e.g. a single query followed by a semicolon is one statement.
+pub fn split_statements(query: &str) -> Result<Vec<String>, QueryError> {
+    let dialect = GenericDialect {};
+    let tokens = Tokenizer::new(&dialect, query)
+        .tokenize()
+        .map_err(|e| convert_parser_error(e.into()))?;
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut offset = 0;
+    let mut is_empty = true;
+    for token in tokens {
+        match token {
+            Token::SemiColon => {
+                if !is_empty {
+                    statements.push(query[start..offset].trim().to_string());
+                }
+                start = offset + 1;
+                is_empty = true;
+            }
+            Token::Whitespace(_) => {}
+            _ => is_empty = false,
+        }
+        offset += source_len(&token, &query[offset..]);
+    }
+    if !is_empty {
+        statements.push(query[start..].trim().to_string());
+    }
+    Ok(statements)
+}
+
+/// Length in bytes of `token` at the start of `text`. This is the length of the formatted token, except for string
+/// literals which are formatted without the escapes of quotes and `\r\n` which is formatted as `\n`.
+fn source_len(token: &Token, text: &str) -> usize {
+    match token {
+        Token::SingleQuotedString(s) | Token::NationalStringLiteral(s) | Token::HexStringLiteral(s) => {
+            token.to_string().len() + s.matches('\'').count()
+        }
+        Token::Whitespace(Whitespace::Newline) if text.starts_with("\r\n") => 2,
+        _ => token.to_string().len(),
+    }
+}
+
+/// Parses a single expression such as the condition of a `WHERE` clause.
+pub fn parse_expr(expr: &str) -> Result<Expr, QueryError> {
+    let dialect = GenericDialect {};
+    let tokens = Tokenizer::new(&dialect, expr)
+        .tokenize()
+        .map_err(|e| convert_parser_error(e.into()))?;
+    let tokens = rewrite_null_safe_equals(merge_exponents(tokens));
+    let mut parser = Parser::new(tokens);
+    let ast = parser.parse_expr().map_err(convert_parser_error)?;
+    if parser.peek_token() != Token::EOF {
+        return Err(QueryError::ParseError(format!(
+            "Expected end of expression, found: {}",
+            parser.peek_token()
+        )));
+    }
+    Ok(*convert_to_native_expr(&ast)?)
+}
+
+fn convert_parser_error(e: ParserError) -> QueryError {
+    match e {
+        ParserError::ParserError(e_str) => QueryError::ParseError(e_str),
+        ParserError::TokenizerError(e_str) => QueryError::ParseError(e_str),
+    }
+}
+
+/// Parses `SHOW TABLES` and `SHOW COLUMNS FROM table`, which sqlparser does not support.
+/// Returns `None` if `query` is not a `SHOW` statement.
+fn parse_show(query: &str) -> Result<Option<ParsedQuery>, QueryError> {
+    let dialect = GenericDialect {};
+    let tokens = Tokenizer::new(&dialect, query)
+        .tokenize()
+        .map_err(|e| convert_parser_error(e.into()))?;
+    let mut significant = tokens
+        .iter()
+        .filter(|token| !matches!(token, Token::Whitespace(_)))
+        .collect::<Vec<_>>();
+    while let Some(Token::SemiColon) = significant.last() {
+        significant.pop();
+    }
+    let is_keyword = |word: &Word, keyword: &str| word.quote_style.is_none() && word.value.eq_ignore_ascii_case(keyword);
+    match significant.first() {
+        Some(Token::Word(show)) if is_keyword(show, "SHOW") => {}
+        _ => return Ok(None),
+    }
+    match significant[1..] {
+        [Token::Word(tables)] if is_keyword(tables, "TABLES") => Ok(Some(ParsedQuery::ShowTables)),
+        [Token::Word(columns), Token::Word(from), Token::Word(table)]
+            if is_keyword(columns, "COLUMNS") && (is_keyword(from, "FROM") || is_keyword(from, "IN")) =>
+        {
+            Ok(Some(ParsedQuery::ShowColumns(table.value.clone())))
+        }
+        _ => Err(QueryError::ParseError(
+            "Expected SHOW TABLES or SHOW COLUMNS FROM <table>".to_string(),
+        )),
+    }
+}
+
+/// Same as `Parser::parse_sql`, but additionally accepts the MySQL `LIMIT offset, count` syntax.
+fn parse_statements(query: &str) -> Result<Vec<Statement>, ParserError> {
+    let dialect = GenericDialect {};
+    let mut tokens = rewrite_null_safe_equals(merge_exponents(Tokenizer::new(&dialect, query).tokenize()?));
+    check_negative_row_counts(&tokens)?;
+    rewrite_limit_offset(&mut tokens);
+    let mut parser = Parser::new(tokens);
+    let mut statements = Vec::new();
+    loop {
+        while parser.consume_token(&Token::SemiColon) {}
+        if parser.peek_token() == Token::EOF {
+            break;
+        }
+        if !statements.is_empty() {
+            return Err(ParserError::ParserError(format!(
+                "Expected end of statement, found: {}",
+                parser.peek_token()
+            )));
+        }
+        statements.push(parser.parse_statement()?);
+    }
+    Ok(statements)
+}
+
+/// The tokenizer does not support scientific notation and splits e.g. `1.5e-3` into `1.5`, `e`, `-`, `3`
+/// and `1e30` into `1`, `e30`. Joins these back into a single number.
+fn merge_exponents(tokens: Vec<Token>) -> Vec<Token> {
+    fn unquoted(w: &Word) -> &str {
+        if w.quote_style.is_none() { &w.value } else { "" }
+    }
+    let is_digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    let mut merged: Vec<Token> = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        if let Some(Token::Number(mantissa)) = merged.last_mut() {
+            match &tokens[i..] {
+                [Token::Word(w), ..] if unquoted(w).starts_with(['e', 'E']) && is_digits(&w.value[1..]) => {
+                    mantissa.push_str(&w.value);
+                    i += 1;
+                    continue;
+                }
+                [Token::Word(w), sign @ (Token::Plus | Token::Minus), Token::Number(exponent), ..]
+                    if matches!(unquoted(w), "e" | "E") && is_digits(exponent) =>
+                {
+                    mantissa.push_str(&format!("{}{}{}", w.value, sign, exponent));
+                    i += 3;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        merged.push(tokens[i].clone());
+        i += 1;
+    }
+    merged
+}
+
+/// Rewrites `LIMIT offset, count` into `LIMIT count OFFSET offset`.
+fn rewrite_limit_offset(tokens: &mut [Token]) {
+    let significant = tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, token)| !matches!(token, Token::Whitespace(_)))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    for window in significant.windows(4) {
+        if let [limit, offset, comma, count] = *window {
+            match (&tokens[limit], &tokens[offset], &tokens[comma], &tokens[count]) {
+                (Token::Word(w), Token::Number(_), Token::Comma, Token::Number(_))
+                    if w.keyword == Keyword::LIMIT =>
+                {
+                    tokens.swap(offset, count);
+                    tokens[comma] = Token::make_keyword("OFFSET");
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Negative numbers are not valid literals in `LIMIT`/`OFFSET`, so they are rejected here with a clearer error.
+fn check_negative_row_counts(tokens: &[Token]) -> Result<(), ParserError> {
+    let significant = tokens
+        .iter()
+        .filter(|token| !matches!(token, Token::Whitespace(_)))
+        .collect::<Vec<_>>();
+    for window in significant.windows(2) {
+        if let [Token::Word(w), Token::Minus] = window {
+            if w.keyword == Keyword::LIMIT || w.keyword == Keyword::OFFSET {
+                return Err(ParserError::ParserError(format!("{} must be non-negative", w.value.to_uppercase())));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Identifiers that `rewrite_null_safe_equals` inserts to mark `=` as `<=>` or `IS DISTINCT FROM`.
+/// The tokenizer never produces unquoted identifiers like these, so they cannot occur in queries.
+const NULL_SAFE_EQUALS: &str = "<=>";
+const IS_DISTINCT_FROM: &str = "IS DISTINCT FROM";
+
+/// The tokenizer splits `<=>` into `<=` and `>` and the parser supports neither `<=>` nor `IS [NOT] DISTINCT FROM`.
+/// These are rewritten into `= marker | `, so they take on the precedence of `=`, and `|` attaches the marker to the
+/// right operand. `null_safe_equals` recovers the operator from the resulting expression.
+fn rewrite_null_safe_equals(tokens: Vec<Token>) -> Vec<Token> {
+    let is_keyword = |token: &Token, keyword: Keyword| matches!(token, Token::Word(w) if w.keyword == keyword);
+    let marked_equals = |marker: &str| {
+        let marker = Word { value: marker.to_string(), quote_style: None, keyword: Keyword::NoKeyword };
+        [Token::Eq, Token::Word(marker), Token::Pipe]
+    };
+    let mut rewritten = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        if let [Token::LtEq, Token::Gt, ..] = &tokens[i..] {
+            rewritten.extend(marked_equals(NULL_SAFE_EQUALS));
+            i += 2;
+            continue;
+        }
+        let significant = tokens[i..]
+            .iter()
+            .enumerate()
+            .filter(|(_, token)| !matches!(token, Token::Whitespace(_)))
+            .take(4)
+            .collect::<Vec<_>>();
+        if let [(_, is), (_, not), (_, distinct), (last, from), ..] = significant[..] {
+            if is_keyword(is, Keyword::IS)
+                && is_keyword(not, Keyword::NOT)
+                && is_keyword(distinct, Keyword::DISTINCT)
+                && is_keyword(from, Keyword::FROM)
+            {
+                rewritten.extend(marked_equals(NULL_SAFE_EQUALS));
+                i += last + 1;
+                continue;
+            }
+        }
+        if let [(_, is), (_, distinct), (last, from), ..] = significant[..] {
+            if is_keyword(is, Keyword::IS)
+                && is_keyword(distinct, Keyword::DISTINCT)
+                && is_keyword(from, Keyword::FROM)
+            {
+                rewritten.extend(marked_equals(IS_DISTINCT_FROM));
+                i += last + 1;
+                continue;
+            }
+        }
+        rewritten.push(tokens[i].clone());
+        i += 1;
+    }
+    rewritten
+}
+
+/// Matches `left = marker | right` produced by `rewrite_null_safe_equals`.
+/// Returns the operands and whether the comparison is negated, i.e. `IS DISTINCT FROM`.
+fn null_safe_equals(node: &ASTNode) -> Option<(&ASTNode, &ASTNode, bool)> {
+    match node {
+        ASTNode::BinaryOp {
+            left,
+            op: BinaryOperator::Eq,
+            right: box ASTNode::BinaryOp { left: box ASTNode::Identifier(marker), op: BinaryOperator::BitwiseOr, right },
+        } if marker.quote_style.is_none() => match marker.value.as_str() {
+            NULL_SAFE_EQUALS => Some((left, right, false)),
+            IS_DISTINCT_FROM => Some((left, right, true)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Formats `node` as entered by the user, which is used as the name of the column.
+fn column_name(node: &ASTNode) -> String {
+    if let Some((left, right, negated)) = null_safe_equals(node) {
+        let op = if negated { IS_DISTINCT_FROM } else { NULL_SAFE_EQUALS };
+        return format!("{} {} {}", column_name(left), op, column_name(right));
+    }
+    match node {
+        ASTNode::BinaryOp { left, op, right } => format!("{} {} {}", column_name(left), op, column_name(right)),
+        ASTNode::UnaryOp { op: op @ UnaryOperator::Not, expr } => format!("{} {}", op, column_name(expr)),
+        ASTNode::UnaryOp { op, expr } => format!("{}{}", op, column_name(expr)),
+        ASTNode::Nested(expr) => format!("({})", column_name(expr)),
+        _ => format!("{}", node),
+    }
+}
+
+fn get_select(body: SetExpr) -> Result<Select, QueryError> {
     match body {
-        SetExpr::Select(box Select {
-            distinct,
-            projection,
-            mut from,
-            selection,
-            group_by,
-            having,
-            // TODO: ensure top is not set
-            top: _,
-        }) => {
-            if !group_by.is_empty() {
+        SetExpr::Select(box select) => {
+            // Results are implicitly grouped by all non-aggregate expressions in the SELECT clause,
+            // so GROUP BY is only accepted if it is consistent with that grouping.
+            if !select.group_by.iter().all(|expr| is_projected(expr, &select.projection)) {
                 Err(QueryError::NotImplemented("Group By  (Hint: If your SELECT clause contains any aggregation expressions, results will implicitly grouped by all other expresssions.)".to_string()))
-            } else if having.is_some() {
-                Err(QueryError::NotImplemented("Having".to_string()))
-            } else if distinct {
+            } else if select.distinct {
                 Err(QueryError::NotImplemented("DISTINCT".to_string()))
-            } else if from.len() > 1 {
+            } else if select.from.len() > 1 {
                 Err(QueryError::NotImplemented(
                     "Selecting from multiple tables.".to_string(),
                 ))
-            } else if !from.is_empty() && !from[0].joins.is_empty() {
-                Err(QueryError::NotImplemented("JOIN".to_string()))
             } else {
-                Ok((
-                    projection,
-                    from.pop().map(|t| t.relation),
-                    selection,
-                    if order_by.is_empty() {
-                        None
-                    } else {
-                        Some(order_by)
-                    },
-                    limit,
-                    offset,
-                ))
+                Ok(select)
             }
         }
         // TODO: more specific error messages
@@ -124,6 +482,333 @@ fn get_query_components(
     }
 }
 
+/// Replaces each `expr [NOT] IN (SELECT ...)` in `node` with a reference to a placeholder column and appends
+/// the subqueries to `subqueries`. Subqueries are only found when combined with AND, OR and NOT.
+fn extract_subqueries(node: &mut ASTNode, subqueries: &mut Vec<Subquery>) -> Result<(), QueryError> {
+    match node {
+        ASTNode::BinaryOp { left, right, .. } => {
+            extract_subqueries(left, subqueries)?;
+            extract_subqueries(right, subqueries)?;
+        }
+        ASTNode::UnaryOp { expr, .. } | ASTNode::Nested(expr) => extract_subqueries(expr, subqueries)?,
+        ASTNode::InSubquery { expr, subquery, negated } => {
+            let placeholder = format!("$subquery{}", subqueries.len());
+            subqueries.push(Subquery {
+                placeholder: placeholder.clone(),
+                expr: *convert_to_native_expr(expr)?,
+                query: convert_subquery(subquery)?,
+                negated: *negated,
+            });
+            *node = ASTNode::Identifier(Ident::new(placeholder));
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Converts the query of `expr IN (SELECT column FROM ...)`, which returns all rows unless it has a LIMIT.
+fn convert_subquery(subquery: &sqlparser::ast::Query) -> Result<Query, QueryError> {
+    let sqlparser::ast::Query { body, order_by, limit, offset, .. } = subquery.clone();
+    let select = get_select(body)?;
+    if select.from.iter().any(|table| !table.joins.is_empty()) {
+        return Err(QueryError::NotImplemented("JOIN in IN subquery".to_string()));
+    }
+    let mut query = convert_select(select)?;
+    if query.select.len() != 1 || query.is_select_star() {
+        return Err(QueryError::ParseError(
+            "Subquery of IN must select exactly one column".to_string(),
+        ));
+    }
+    query.order_by = get_order_by(order_by)?;
+    query.limit = LimitClause {
+        limit: if limit.is_some() { get_limit(limit)? } else { u64::MAX },
+        offset: get_offset(offset)?,
+    };
+    Ok(query)
+}
+
+/// Converts a `SELECT` without ORDER BY and LIMIT, which are part of the enclosing query.
+fn convert_select(select: Select) -> Result<Query, QueryError> {
+    if select.from.iter().any(|table| !table.joins.is_empty()) {
+        return Err(QueryError::NotImplemented("JOIN in UNION ALL".to_string()));
+    }
+    let Select {
+        projection,
+        mut from,
+        selection,
+        having,
+        // TODO: ensure top is not set
+        ..
+    } = select;
+    let projection = get_projection(projection)?;
+    let relation = from.pop().map(|t| t.relation);
+    let partition_filter = get_partition_filter(&relation)?;
+    let table = get_table_name(relation)?;
+    let filter = match selection {
+        Some(ref s) => *convert_to_native_expr(s)?,
+        None => Expr::Const(RawVal::Int(1)),
+    };
+    let having = match having {
+        Some(ref s) => *convert_to_native_expr(s)?,
+        None => Expr::Const(RawVal::Int(1)),
+    };
+    Ok(Query {
+        select: projection,
+        table,
+        filter,
+        having,
+        order_by: vec![],
+        limit: LimitClause { limit: 100, offset: 0 },
+        partition_filter,
+    })
+}
+
+/// Converts `SELECT ... FROM a JOIN b ON a.key = b.key` into a query for each table.
+/// Every expression in the SELECT and WHERE clause may only reference columns of one of the tables,
+/// and columns have to be qualified with the name or alias of their table, e.g. `a.key`.
+fn convert_join(mut select: Select, order_by: Vec<OrderByExpr>) -> Result<Join, QueryError> {
+    if select.having.is_some() {
+        return Err(QueryError::NotImplemented("HAVING in JOIN".to_string()));
+    }
+    if select.projection.iter().any(|item| !is_expr(item)) {
+        return Err(QueryError::NotImplemented("SELECT * in JOIN".to_string()));
+    }
+    let mut order = Vec::new();
+    for e in order_by {
+        match projection_index(&e.expr, &select.projection) {
+            Some(index) => {
+                let (desc, nulls_first) = get_sort_order(&e);
+                order.push((index, desc, nulls_first))
+            }
+            None => {
+                return Err(QueryError::NotImplemented(format!(
+                    "ORDER BY {} (Hint: ORDER BY in JOIN must refer to a selected column.)",
+                    e.expr
+                )))
+            }
+        }
+    }
+
+    let TableWithJoins { relation, mut joins } = select.from.pop().unwrap();
+    if joins.len() > 1 {
+        return Err(QueryError::NotImplemented("JOIN of more than two tables".to_string()));
+    }
+    let join = joins.pop().unwrap();
+    let constraint = match join.join_operator {
+        JoinOperator::Inner(constraint) => constraint,
+        JoinOperator::LeftOuter(_) => return Err(QueryError::NotImplemented("LEFT JOIN".to_string())),
+        JoinOperator::RightOuter(_) => return Err(QueryError::NotImplemented("RIGHT JOIN".to_string())),
+        JoinOperator::FullOuter(_) => return Err(QueryError::NotImplemented("FULL JOIN".to_string())),
+        _ => return Err(QueryError::NotImplemented(format!("{}", join).trim().to_string())),
+    };
+    let mut qualifiers = Vec::with_capacity(2);
+    let mut queries = Vec::with_capacity(2);
+    for relation in [relation, join.relation] {
+        let qualifier = match relation {
+            TableFactor::Table { alias: Some(ref alias), .. } => strip_quotes(&alias.name.value),
+            TableFactor::Table { ref name, .. } => format!("{}", name),
+            _ => String::new(),
+        };
+        if qualifiers.contains(&qualifier) {
+            return Err(QueryError::ParseError(format!(
+                "Table {} is joined with itself (Hint: Use an alias for one of the tables.)",
+                qualifier
+            )));
+        }
+        qualifiers.push(qualifier);
+        let partition_filter = get_partition_filter(&Some(relation.clone()))?;
+        queries.push(Query {
+            select: vec![],
+            table: get_table_name(Some(relation))?,
+            filter: Expr::Const(RawVal::Int(1)),
+            having: Expr::Const(RawVal::Int(1)),
+            order_by: vec![],
+            // All rows are required to compute the join
+            limit: LimitClause { limit: u64::MAX, offset: 0 },
+            partition_filter,
+        });
+    }
+
+    let mut keys = [0, 0];
+    let (left_key, right_key) = match constraint {
+        JoinConstraint::On(ref condition) => match *convert_to_native_expr(condition)? {
+            Expr::Func2(Func2Type::Equals, lhs, rhs) => {
+                let lhs = split_join_expr(*lhs, &qualifiers)?;
+                let rhs = split_join_expr(*rhs, &qualifiers)?;
+                match (lhs, rhs) {
+                    ((0, left_key), (1, right_key)) | ((1, right_key), (0, left_key)) => (left_key, right_key),
+                    _ => {
+                        return Err(QueryError::NotImplemented(format!(
+                            "JOIN condition {} (Hint: JOIN condition must compare a column of each table.)",
+                            condition
+                        )))
+                    }
+                }
+            }
+            _ => {
+                return Err(QueryError::NotImplemented(format!(
+                    "JOIN condition {} (Hint: Only equality of a column of each table is supported.)",
+                    condition
+                )))
+            }
+        },
+        JoinConstraint::Using(ref columns) if columns.len() == 1 => {
+            let column = Expr::ColName(strip_quotes(&columns[0].value));
+            (column.clone(), column)
+        }
+        _ => return Err(QueryError::NotImplemented(format!("JOIN {:?}", constraint))),
+    };
+    for (side, key) in [left_key, right_key].into_iter().enumerate() {
+        keys[side] = queries[side].select.len();
+        queries[side].select.push(ColumnInfo { expr: key, name: Some("join_key".to_string()) });
+    }
+
+    let mut projection = Vec::new();
+    let mut colnames = Vec::new();
+    for col_info in get_projection(select.projection)? {
+        if Query::contains_aggregate(&col_info.expr) {
+            return Err(QueryError::NotImplemented("Aggregation in JOIN".to_string()));
+        }
+        let (side, expr) = split_join_expr(col_info.expr, &qualifiers)?;
+        projection.push((side, queries[side].select.len()));
+        colnames.push(col_info.name.clone().unwrap_or_default());
+        queries[side].select.push(ColumnInfo { expr, name: col_info.name });
+    }
+    if let Some(ref selection) = select.selection {
+        let mut conjuncts = Vec::new();
+        get_conjuncts(*convert_to_native_expr(selection)?, &mut conjuncts);
+        for conjunct in conjuncts {
+            let (side, expr) = split_join_expr(conjunct, &qualifiers)?;
+            queries[side].filter = match queries[side].filter {
+                Expr::Const(RawVal::Int(1)) => expr,
+                ref filter => Expr::func(Func2Type::And, filter.clone(), expr),
+            };
+        }
+    }
+
+    Ok(Join {
+        queries: queries.try_into().unwrap(),
+        keys,
+        projection,
+        colnames,
+        order_by: order,
+        limit: LimitClause { limit: 100, offset: 0 },
+    })
+}
+
+/// Determines which table of a JOIN the expression refers to and removes the table qualifiers from its columns.
+fn split_join_expr(expr: Expr, qualifiers: &[String]) -> Result<(usize, Expr), QueryError> {
+    let mut colnames = HashSet::new();
+    expr.add_colnames(&mut colnames);
+    let mut side = None;
+    let mut unqualified = HashMap::new();
+    for colname in colnames {
+        let (table, column) = qualifiers
+            .iter()
+            .enumerate()
+            .find_map(|(table, qualifier)| {
+                colname.strip_prefix(qualifier.as_str())?.strip_prefix('.').map(|column| (table, column))
+            })
+            .ok_or_else(|| {
+                QueryError::ParseError(format!(
+                    "Column {} must be qualified with the name or alias of its table in JOIN.",
+                    colname
+                ))
+            })?;
+        if side.map_or(false, |side| side != table) {
+            return Err(QueryError::NotImplemented(format!(
+                "Expressions that reference columns of both tables in JOIN: {:?}",
+                expr
+            )));
+        }
+        side = Some(table);
+        unqualified.insert(colname.clone(), Expr::ColName(column.to_string()));
+    }
+    Ok((side.unwrap_or(0), expr.inline_aliases(&unqualified)))
+}
+
+/// Splits an expression into the expressions that are combined with `AND`.
+fn get_conjuncts(expr: Expr, conjuncts: &mut Vec<Expr>) {
+    match expr {
+        Expr::Func2(Func2Type::And, lhs, rhs) => {
+            get_conjuncts(*lhs, conjuncts);
+            get_conjuncts(*rhs, conjuncts);
+        }
+        expr => conjuncts.push(expr),
+    }
+}
+
+/// Collects the `SELECT`s combined by (possibly nested) `UNION ALL` operations.
+fn get_union_all_selects(body: SetExpr, selects: &mut Vec<Select>) -> Result<(), QueryError> {
+    match body {
+        SetExpr::SetOperation { op: SetOperator::Union, all: true, left, right } => {
+            get_union_all_selects(*left, selects)?;
+            get_union_all_selects(*right, selects)
+        }
+        SetExpr::SetOperation { op: SetOperator::Union, all: false, .. } => Err(QueryError::NotImplemented(
+            "UNION (Hint: UNION ALL is supported, but does not remove duplicate rows.)".to_string(),
+        )),
+        SetExpr::SetOperation { op, .. } => Err(QueryError::NotImplemented(format!("{}", op))),
+        body => {
+            let select = get_select(body)?;
+            if let Some(first) = selects.first() {
+                let wildcard = |select: &Select| select.projection.iter().any(|item| !is_expr(item));
+                if !wildcard(first) && !wildcard(&select) && first.projection.len() != select.projection.len() {
+                    return Err(QueryError::TypeError(format!(
+                        "Each query in UNION ALL must select the same number of columns, found {} and {}",
+                        first.projection.len(),
+                        select.projection.len(),
+                    )));
+                }
+            }
+            selects.push(select);
+            Ok(())
+        }
+    }
+}
+
+/// ORDER BY of a `UNION ALL` can only refer to selected columns, which are identified by their index.
+fn get_union_order_by(order_by: Vec<OrderByExpr>, selects: &[Select]) -> Result<Vec<(usize, bool, bool)>, QueryError> {
+    let mut order = Vec::new();
+    for e in order_by {
+        if selects.iter().any(|select| select.projection.iter().any(|item| !is_expr(item))) {
+            return Err(QueryError::NotImplemented("ORDER BY in UNION ALL of SELECT *".to_string()));
+        }
+        match projection_index(&e.expr, &selects[0].projection) {
+            Some(index) => {
+                let (desc, nulls_first) = get_sort_order(&e);
+                order.push((index, desc, nulls_first))
+            }
+            None => {
+                return Err(QueryError::NotImplemented(format!(
+                    "ORDER BY {} (Hint: ORDER BY in UNION ALL must refer to a column selected by the first query.)",
+                    e.expr
+                )))
+            }
+        }
+    }
+    Ok(order)
+}
+
+fn is_expr(item: &SelectItem) -> bool {
+    matches!(item, SelectItem::UnnamedExpr(_) | SelectItem::ExprWithAlias { .. })
+}
+
+fn is_projected(expr: &ASTNode, projection: &[SelectItem]) -> bool {
+    projection_index(expr, projection).is_some()
+}
+
+/// Position of the selected column that `expr` refers to, either by repeating the expression or by its alias.
+fn projection_index(expr: &ASTNode, projection: &[SelectItem]) -> Option<usize> {
+    projection.iter().position(|item| match item {
+        SelectItem::UnnamedExpr(e) => e == expr,
+        SelectItem::ExprWithAlias { expr: e, alias } => {
+            e == expr || *expr == ASTNode::Identifier(alias.clone())
+        }
+        _ => false,
+    })
+}
+
 fn get_projection(projection: Vec<SelectItem>) -> Result<Vec<ColumnInfo>, QueryError> {
     let mut result = Vec::<ColumnInfo>::new();
     for elem in &projection {
@@ -134,7 +819,7 @@ fn get_projection(projection: Vec<SelectItem>) -> Result<Vec<ColumnInfo>, QueryE
                 // These will later be used as colnames of query results.
                 result.push(ColumnInfo {
                     expr: *convert_to_native_expr(e)?,
-                    name: Some(format!("{}", e)),
+                    name: Some(column_name(e)),
                 })
             }
             SelectItem::Wildcard => result.push(ColumnInfo {
@@ -143,7 +828,7 @@ fn get_projection(projection: Vec<SelectItem>) -> Result<Vec<ColumnInfo>, QueryE
             }),
             SelectItem::ExprWithAlias { expr, alias } => result.push(ColumnInfo {
                 expr: *convert_to_native_expr(expr)?,
-                name: Some(alias.to_string()),
+                name: Some(alias.value.clone()),
             }),
             _ => {
                 return Err(QueryError::NotImplemented(format!(
@@ -157,6 +842,21 @@ fn get_projection(projection: Vec<SelectItem>) -> Result<Vec<ColumnInfo>, QueryE
     Ok(result)
 }
 
+/// Table hints such as `FROM t WITH (partition_id < 10, partition_id > 2)` restrict the partitions that are scanned.
+fn get_partition_filter(relation: &Option<TableFactor>) -> Result<Expr, QueryError> {
+    let mut partition_filter = Expr::Const(RawVal::Int(1));
+    if let Some(TableFactor::Table { with_hints, .. }) = relation {
+        for hint in with_hints {
+            let hint = *convert_to_native_expr(hint)?;
+            partition_filter = match partition_filter {
+                Expr::Const(RawVal::Int(1)) => hint,
+                _ => Expr::func(Func2Type::And, partition_filter, hint),
+            };
+        }
+    }
+    Ok(partition_filter)
+}
+
 fn get_table_name(relation: Option<TableFactor>) -> Result<String, QueryError> {
     match relation {
         // TODO: error message if any unused fields are set
@@ -169,19 +869,25 @@ fn get_table_name(relation: Option<TableFactor>) -> Result<String, QueryError> {
     }
 }
 
-fn get_order_by(order_by: Option<Vec<OrderByExpr>>) -> Result<Vec<(Expr, bool)>, QueryError> {
+fn get_order_by(order_by: Vec<OrderByExpr>) -> Result<Vec<(Expr, bool, bool)>, QueryError> {
     let mut order = Vec::new();
-    if let Some(sql_order_by_exprs) = order_by {
-        for e in sql_order_by_exprs {
-            order.push((*(convert_to_native_expr(&e.expr))?, !e.asc.unwrap_or(true)));
-        }
+    for e in order_by {
+        let (desc, nulls_first) = get_sort_order(&e);
+        order.push((*(convert_to_native_expr(&e.expr))?, desc, nulls_first));
     }
     Ok(order)
 }
 
+/// Returns whether the order is descending and whether nulls come first.
+/// Like Postgres, nulls come last in ascending and first in descending order unless specified otherwise.
+fn get_sort_order(e: &OrderByExpr) -> (bool, bool) {
+    let desc = !e.asc.unwrap_or(true);
+    (desc, e.nulls_first.unwrap_or(desc))
+}
+
 fn get_limit(limit: Option<ASTNode>) -> Result<u64, QueryError> {
     match limit {
-        Some(ASTNode::Value(Value::Number(int))) => Ok(int.parse::<u64>().unwrap()),
+        Some(ASTNode::Value(Value::Number(int))) => parse_row_count("LIMIT", &int),
         None => Ok(100),
         _ => Err(QueryError::NotImplemented(format!(
             "Invalid expression in limit clause: {:?}",
@@ -194,7 +900,7 @@ fn get_offset(offset: Option<Offset>) -> Result<u64, QueryError> {
     match offset {
         None => Ok(0),
         Some(offset) => match offset.value {
-            ASTNode::Value(Value::Number(rows)) => Ok(rows.parse::<u64>().unwrap()),
+            ASTNode::Value(Value::Number(rows)) => parse_row_count("OFFSET", &rows),
             expr => Err(QueryError::ParseError(format!(
                 "Invalid expression in offset clause: Expected constant integer, got {:?}",
                 expr,
@@ -203,7 +909,30 @@ fn get_offset(offset: Option<Offset>) -> Result<u64, QueryError> {
     }
 }
 
+fn parse_row_count(clause: &str, value: &str) -> Result<u64, QueryError> {
+    match value.parse::<u64>() {
+        Ok(count) => Ok(count),
+        Err(_) => match value.parse::<f64>() {
+            Ok(float) if float < 0.0 => Err(QueryError::ParseError(format!("{} must be non-negative", clause))),
+            Ok(float) if float.fract() == 0.0 => Err(QueryError::ParseError(format!(
+                "{} {} is too large, the maximum is {}",
+                clause,
+                value,
+                u64::MAX
+            ))),
+            _ => Err(QueryError::ParseError(format!(
+                "{} must be an integer, got {}",
+                clause, value
+            ))),
+        },
+    }
+}
+
 fn convert_to_native_expr(node: &ASTNode) -> Result<Box<Expr>, QueryError> {
+    if let Some((left, right, negated)) = null_safe_equals(node) {
+        let equals = Expr::Func2(Func2Type::NullSafeEquals, convert_to_native_expr(left)?, convert_to_native_expr(right)?);
+        return Ok(Box::new(if negated { Expr::Func1(Func1Type::Not, Box::new(equals)) } else { equals }));
+    }
     Ok(Box::new(match node {
         ASTNode::BinaryOp {
             ref left,
@@ -222,7 +951,20 @@ fn convert_to_native_expr(node: &ASTNode) -> Result<Box<Expr>, QueryError> {
         ASTNode::Identifier(ref identifier) => {
             Expr::ColName(strip_quotes(identifier.value.as_ref()))
         }
+        // Columns of flattened JSON objects are named by their dotted path, e.g. `user.id`
+        ASTNode::CompoundIdentifier(ref identifiers) => Expr::ColName(
+            identifiers.iter().map(|identifier| strip_quotes(identifier.value.as_ref())).collect::<Vec<_>>().join("."),
+        ),
         ASTNode::Nested(inner) => *convert_to_native_expr(inner)?,
+        ASTNode::InSubquery { .. } => {
+            return Err(QueryError::NotImplemented(
+                "IN subquery (Hint: Subqueries are only supported in the WHERE clause of a SELECT from a single table.)"
+                    .to_string(),
+            ))
+        }
+        ASTNode::Function(f) if f.distinct && format!("{}", f.name).to_uppercase() != "COUNT" => {
+            return Err(QueryError::NotImplemented(format!("DISTINCT in function {}", f.name)))
+        }
         ASTNode::Function(f) => match format!("{}", f.name).to_uppercase().as_ref() {
             "TO_YEAR" => {
                 if f.args.len() != 1 {
@@ -232,6 +974,32 @@ fn convert_to_native_expr(node: &ASTNode) -> Result<Box<Expr>, QueryError> {
                 }
                 Expr::Func1(Func1Type::ToYear, convert_to_native_expr(&f.args[0])?)
             }
+            "TO_MONTH" | "TO_DAY" | "TO_HOUR" => {
+                if f.args.len() != 1 {
+                    return Err(QueryError::ParseError(format!(
+                        "Expected one argument in {} function",
+                        f.name
+                    )));
+                }
+                let ftype = match format!("{}", f.name).to_uppercase().as_ref() {
+                    "TO_MONTH" => Func1Type::ToMonth,
+                    "TO_DAY" => Func1Type::ToDay,
+                    _ => Func1Type::ToHour,
+                };
+                Expr::Func1(ftype, convert_to_native_expr(&f.args[0])?)
+            }
+            "DATE_TRUNC" => {
+                if f.args.len() != 2 {
+                    return Err(QueryError::ParseError(
+                        "Expected two arguments in DATE_TRUNC function".to_string(),
+                    ));
+                }
+                Expr::Func2(
+                    Func2Type::DateTrunc,
+                    convert_to_native_expr(&f.args[0])?,
+                    convert_to_native_expr(&f.args[1])?,
+                )
+            }
             "REGEX" => {
                 if f.args.len() != 2 {
                     return Err(QueryError::ParseError(
@@ -244,6 +1012,97 @@ fn convert_to_native_expr(node: &ASTNode) -> Result<Box<Expr>, QueryError> {
                     convert_to_native_expr(&f.args[1])?,
                 )
             }
+            "FLOOR" | "CEIL" | "CEILING" => {
+                if f.args.len() != 1 {
+                    return Err(QueryError::ParseError(format!(
+                        "Expected one argument in {} function",
+                        f.name
+                    )));
+                }
+                let ftype = if format!("{}", f.name).to_uppercase() == "FLOOR" {
+                    Func1Type::Floor
+                } else {
+                    Func1Type::Ceil
+                };
+                Expr::Func1(ftype, convert_to_native_expr(&f.args[0])?)
+            }
+            "ABS" | "SIGN" => {
+                if f.args.len() != 1 {
+                    return Err(QueryError::ParseError(format!(
+                        "Expected one argument in {} function",
+                        f.name
+                    )));
+                }
+                let ftype = if format!("{}", f.name).to_uppercase() == "ABS" {
+                    Func1Type::Abs
+                } else {
+                    Func1Type::Sign
+                };
+                Expr::Func1(ftype, convert_to_native_expr(&f.args[0])?)
+            }
+            "SAMPLE" => {
+                if f.args.len() != 1 {
+                    return Err(QueryError::ParseError(
+                        "Expected one argument in SAMPLE function".to_string(),
+                    ));
+                }
+                Expr::Func1(Func1Type::Sample, convert_to_native_expr(&f.args[0])?)
+            }
+            "SQRT" | "LN" | "LOG10" | "EXP" => {
+                if f.args.len() != 1 {
+                    return Err(QueryError::ParseError(format!(
+                        "Expected one argument in {} function",
+                        f.name
+                    )));
+                }
+                let ftype = match format!("{}", f.name).to_uppercase().as_ref() {
+                    "SQRT" => Func1Type::Sqrt,
+                    "LN" => Func1Type::Ln,
+                    "LOG10" => Func1Type::Log10,
+                    _ => Func1Type::Exp,
+                };
+                Expr::Func1(ftype, convert_to_native_expr(&f.args[0])?)
+            }
+            "POW" | "POWER" => {
+                if f.args.len() != 2 {
+                    return Err(QueryError::ParseError(format!(
+                        "Expected two arguments in {} function",
+                        f.name
+                    )));
+                }
+                Expr::Func2(
+                    Func2Type::Pow,
+                    convert_to_native_expr(&f.args[0])?,
+                    convert_to_native_expr(&f.args[1])?,
+                )
+            }
+            "ROUND" => match f.args.len() {
+                1 => Expr::Func1(Func1Type::Round, convert_to_native_expr(&f.args[0])?),
+                2 => Expr::Func2(
+                    Func2Type::Round,
+                    convert_to_native_expr(&f.args[0])?,
+                    convert_to_native_expr(&f.args[1])?,
+                ),
+                _ => {
+                    return Err(QueryError::ParseError(
+                        "Expected one or two arguments in ROUND function".to_string(),
+                    ))
+                }
+            },
+            "COALESCE" => {
+                if f.args.is_empty() {
+                    return Err(QueryError::ParseError(
+                        "Expected at least one argument in COALESCE function".to_string(),
+                    ));
+                }
+                // COALESCE(a, b, c) is evaluated as COALESCE(a, COALESCE(b, c))
+                let mut args = f.args.iter().rev();
+                let mut expr = convert_to_native_expr(args.next().unwrap())?;
+                for arg in args {
+                    expr = Box::new(Expr::Func2(Func2Type::Coalesce, convert_to_native_expr(arg)?, expr));
+                }
+                *expr
+            }
             "LENGTH" => {
                 if f.args.len() != 1 {
                     return Err(QueryError::ParseError(
@@ -252,13 +1111,38 @@ fn convert_to_native_expr(node: &ASTNode) -> Result<Box<Expr>, QueryError> {
                 }
                 Expr::Func1(Func1Type::Length, convert_to_native_expr(&f.args[0])?)
             }
+            "LOWER" | "UPPER" => {
+                if f.args.len() != 1 {
+                    return Err(QueryError::ParseError(format!(
+                        "Expected one argument in {} function",
+                        f.name
+                    )));
+                }
+                let ftype = if format!("{}", f.name).to_uppercase() == "LOWER" {
+                    Func1Type::Lower
+                } else {
+                    Func1Type::Upper
+                };
+                Expr::Func1(ftype, convert_to_native_expr(&f.args[0])?)
+            }
             "COUNT" => {
                 if f.args.len() != 1 {
                     return Err(QueryError::ParseError(
                         "Expected one argument in COUNT function".to_string(),
                     ));
                 }
-                Expr::Aggregate(Aggregator::Count, convert_to_native_expr(&f.args[0])?)
+                match (&f.args[0], f.distinct) {
+                    (ASTNode::Wildcard, true) => {
+                        return Err(QueryError::ParseError(
+                            "COUNT(DISTINCT *) is not valid, specify an expression".to_string(),
+                        ))
+                    }
+                    (ASTNode::Wildcard, false) => {
+                        Expr::Aggregate(Aggregator::Count, Box::new(Expr::Const(RawVal::Int(1))))
+                    }
+                    (arg, true) => Expr::Aggregate(Aggregator::CountDistinct, convert_to_native_expr(arg)?),
+                    (arg, false) => Expr::Aggregate(Aggregator::Count, convert_to_native_expr(arg)?),
+                }
             }
             "SUM" => {
                 if f.args.len() != 1 {
@@ -274,17 +1158,21 @@ fn convert_to_native_expr(node: &ASTNode) -> Result<Box<Expr>, QueryError> {
                         "Expected one argument in AVG function".to_string(),
                     ));
                 }
-                Expr::Func2(
-                    Func2Type::Divide,
-                    Box::new(Expr::Aggregate(
-                        Aggregator::SumI64,
-                        convert_to_native_expr(&f.args[0])?,
-                    )),
-                    Box::new(Expr::Aggregate(
-                        Aggregator::Count,
-                        convert_to_native_expr(&f.args[0])?,
-                    )),
-                )
+                Expr::Aggregate(Aggregator::Avg, convert_to_native_expr(&f.args[0])?)
+            }
+            "VAR" | "VAR_POP" | "STDDEV" | "STDDEV_POP" => {
+                if f.args.len() != 1 {
+                    return Err(QueryError::ParseError(format!(
+                        "Expected one argument in {} function",
+                        f.name
+                    )));
+                }
+                let aggregator = if format!("{}", f.name).to_uppercase().starts_with("VAR") {
+                    Aggregator::VarPop
+                } else {
+                    Aggregator::StdDev
+                };
+                Expr::Aggregate(aggregator, convert_to_native_expr(&f.args[0])?)
             }
             "MAX" => {
                 if f.args.len() != 1 {
@@ -302,8 +1190,107 @@ fn convert_to_native_expr(node: &ASTNode) -> Result<Box<Expr>, QueryError> {
                 }
                 Expr::Aggregate(Aggregator::MinI64, convert_to_native_expr(&f.args[0])?)
             }
+            "PERCENTILE" => {
+                if f.args.len() != 2 {
+                    return Err(QueryError::ParseError(
+                        "Expected two arguments in PERCENTILE function".to_string(),
+                    ));
+                }
+                let quantile = match *convert_to_native_expr(&f.args[1])? {
+                    Expr::Const(RawVal::Int(q)) => q as f64,
+                    Expr::Const(RawVal::Float(q)) => q.0,
+                    _ => f64::NAN,
+                };
+                if !(0.0..=1.0).contains(&quantile) {
+                    return Err(QueryError::ParseError(format!(
+                        "Expected a constant fraction between 0 and 1 as second argument of PERCENTILE, found {}",
+                        f.args[1]
+                    )));
+                }
+                Expr::percentile(*convert_to_native_expr(&f.args[0])?, quantile)
+            }
             _ => return Err(QueryError::NotImplemented(format!("Function {:?}", f.name))),
         },
+        ASTNode::InList { ref expr, ref list, negated } => {
+            let expr = convert_to_native_expr(expr)?;
+            let mut list_type = None;
+            let mut in_list: Option<Box<Expr>> = None;
+            let mut contains_null = false;
+            for item in list {
+                let item = convert_to_native_expr(item)?;
+                if let Expr::Const(ref value) = *item {
+                    if value == &RawVal::Null {
+                        contains_null = true;
+                        continue;
+                    }
+                    let item_type = std::mem::discriminant(value);
+                    if *list_type.get_or_insert(item_type) != item_type {
+                        return Err(QueryError::TypeError(format!(
+                            "Mixed types in IN list: {}",
+                            node
+                        )));
+                    }
+                }
+                let equals = Box::new(Expr::Func2(Func2Type::Equals, expr.clone(), item));
+                in_list = Some(match in_list {
+                    Some(prev) => Box::new(Expr::Func2(Func2Type::Or, prev, equals)),
+                    None => equals,
+                });
+            }
+            // Comparisons with NULL are never true, so `x IN (a, NULL)` only matches `a`,
+            // `x NOT IN (a, NULL)` matches nothing and neither does `NULL NOT IN (a)`
+            match in_list {
+                _ if matches!(*expr, Expr::Const(RawVal::Null)) => Expr::Const(RawVal::Null),
+                _ if *negated && contains_null => Expr::Const(RawVal::Null),
+                Some(in_list) if *negated => Expr::Func1(Func1Type::Not, in_list),
+                Some(in_list) => *in_list,
+                None if contains_null => Expr::Const(RawVal::Null),
+                None => return Err(QueryError::ParseError("Empty IN list".to_string())),
+            }
+        }
+        ASTNode::Between { ref expr, negated, ref low, ref high } => {
+            let expr = convert_to_native_expr(expr)?;
+            let low = convert_to_native_expr(low)?;
+            let high = convert_to_native_expr(high)?;
+            if *negated {
+                Expr::Func2(
+                    Func2Type::Or,
+                    Box::new(Expr::Func2(Func2Type::LT, expr.clone(), low)),
+                    Box::new(Expr::Func2(Func2Type::GT, expr, high)),
+                )
+            } else {
+                Expr::Func2(
+                    Func2Type::And,
+                    Box::new(Expr::Func2(Func2Type::GTE, expr.clone(), low)),
+                    Box::new(Expr::Func2(Func2Type::LTE, expr, high)),
+                )
+            }
+        }
+        ASTNode::Case {
+            ref operand,
+            ref conditions,
+            ref results,
+            ref else_result,
+        } => {
+            let mut branches = Vec::with_capacity(conditions.len());
+            for (condition, result) in conditions.iter().zip(results) {
+                let mut condition = convert_to_native_expr(condition)?;
+                // `CASE x WHEN 1 THEN ...` is equivalent to `CASE WHEN x = 1 THEN ...`
+                if let Some(operand) = operand {
+                    condition = Box::new(Expr::Func2(
+                        Func2Type::Equals,
+                        convert_to_native_expr(operand)?,
+                        condition,
+                    ));
+                }
+                branches.push((*condition, *convert_to_native_expr(result)?));
+            }
+            let default = match else_result {
+                Some(else_result) => convert_to_native_expr(else_result)?,
+                None => Box::new(Expr::Const(RawVal::Null)),
+            };
+            Expr::Case(branches, default)
+        }
         ASTNode::IsNull(ref node) => Expr::Func1(Func1Type::IsNull, convert_to_native_expr(node)?),
         ASTNode::IsNotNull(ref node) => {
             Expr::Func1(Func1Type::IsNotNull, convert_to_native_expr(node)?)
@@ -345,6 +1332,7 @@ fn map_binary_operator(o: &BinaryOperator) -> Result<Func2Type, QueryError> {
         BinaryOperator::Or => Func2Type::Or,
         BinaryOperator::Like => Func2Type::Like,
         BinaryOperator::NotLike => Func2Type::NotLike,
+        BinaryOperator::StringConcat => Func2Type::Concat,
         _ => {
             return Err(QueryError::NotImplemented(format!(
                 "Unsupported operator {:?}",
@@ -357,9 +1345,17 @@ fn map_binary_operator(o: &BinaryOperator) -> Result<Func2Type, QueryError> {
 // Fn to map sqlparser-rs `Value` to LocustDB's `RawVal`.
 fn get_raw_val(constant: &Value) -> Result<RawVal, QueryError> {
     match constant {
-        Value::Number(int) => Ok(RawVal::Int(int.parse::<i64>().unwrap())),
+        Value::Number(number) if number.contains(['.', 'e', 'E']) => match number.parse::<f64>() {
+            Ok(float) => Ok(RawVal::Float(OrderedFloat(float))),
+            Err(_) => Err(QueryError::ParseError(format!("Invalid number {}", number))),
+        },
+        Value::Number(int) => match int.parse::<i64>() {
+            Ok(int) => Ok(RawVal::Int(int)),
+            Err(_) => Err(QueryError::ParseError(format!("Integer {} is out of range", int))),
+        },
         Value::SingleQuotedString(string) => Ok(RawVal::Str(string.to_string())),
         Value::Null => Ok(RawVal::Null),
+        Value::Boolean(b) => Ok(RawVal::Bool(*b)),
         _ => Err(QueryError::NotImplemented(format!("{:?}", constant))),
     }
 }
@@ -372,20 +1368,221 @@ mod tests {
     fn test_select_star() {
         assert_eq!(
             format!("{:?}", parse_query("select * from default")),
-            "Ok(Query { select: [ColumnInfo { expr: ColName(\"*\"), name: None }], table: \"default\", filter: Const(Int(1)), order_by: [], limit: LimitClause { limit: 100, offset: 0 } })");
+            "Ok(Query { select: [ColumnInfo { expr: ColName(\"*\"), name: None }], table: \"default\", filter: Const(Int(1)), having: Const(Int(1)), order_by: [], limit: LimitClause { limit: 100, offset: 0 }, partition_filter: Const(Int(1)) })");
     }
 
     #[test]
     fn test_alias() {
         assert_eq!(
             format!("{:?}", parse_query("select trip_id as id from default")),
-            "Ok(Query { select: [ColumnInfo { expr: ColName(\"trip_id\"), name: Some(\"id\") }], table: \"default\", filter: Const(Int(1)), order_by: [], limit: LimitClause { limit: 100, offset: 0 } })");
+            "Ok(Query { select: [ColumnInfo { expr: ColName(\"trip_id\"), name: Some(\"id\") }], table: \"default\", filter: Const(Int(1)), having: Const(Int(1)), order_by: [], limit: LimitClause { limit: 100, offset: 0 }, partition_filter: Const(Int(1)) })");
+    }
+
+    #[test]
+    fn test_implicit_alias() {
+        let query = parse_query("select trip_id id, fare * 2 \"double fare\" from default").unwrap();
+        let names = query.select.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names, vec![Some("id".to_string()), Some("double fare".to_string())]);
+    }
+
+    #[test]
+    fn test_invalid_row_counts() {
+        let err = |query: &str| format!("{:?}", parse_query(query).unwrap_err());
+        assert_eq!(err("select * from t limit -1"), "ParseError(\"LIMIT must be non-negative\")");
+        assert_eq!(err("select * from t limit 1 offset -2"), "ParseError(\"OFFSET must be non-negative\")");
+        assert_eq!(err("select * from t limit 1.5"), "ParseError(\"LIMIT must be an integer, got 1.5\")");
+        assert_eq!(err("select * from t limit 10 offset 2.5"), "ParseError(\"OFFSET must be an integer, got 2.5\")");
+        assert_eq!(
+            err("select * from t limit 100000000000000000000"),
+            "ParseError(\"LIMIT 100000000000000000000 is too large, the maximum is 18446744073709551615\")"
+        );
+        assert!(parse_query("select * from t limit 0 offset 3").is_ok());
+    }
+
+    #[test]
+    fn test_parse_expr() {
+        assert_eq!(
+            format!("{:?}", parse_expr("x > 1 AND s = 'a'")),
+            "Ok(Func2(And, Func2(GT, ColName(\"x\"), Const(Int(1))), Func2(Equals, ColName(\"s\"), Const(Str(\"a\")))))");
+        assert!(parse_expr("x > 1; DROP TABLE t").is_err());
+        assert!(parse_expr("x > 1) UNION ALL (SELECT * FROM t").is_err());
+    }
+
+    #[test]
+    fn test_null_safe_equals() {
+        let expected = "Ok(Func2(And, Func2(NullSafeEquals, ColName(\"a\"), ColName(\"b\")), Func2(GT, ColName(\"c\"), Const(Int(1)))))";
+        assert_eq!(format!("{:?}", parse_expr("a <=> b AND c > 1")), expected);
+        assert_eq!(format!("{:?}", parse_expr("a IS NOT DISTINCT FROM b AND c > 1")), expected);
+        assert_eq!(format!("{:?}", parse_expr("a is not\ndistinct from b and c > 1")), expected);
+        assert!(parse_expr("a < = > b").is_err());
+        assert_eq!(format!("{:?}", parse_expr("a ^ b")), "Err(NotImplemented(\"Unsupported operator BitwiseXor\"))");
+        // Same precedence as `=`
+        assert_eq!(
+            format!("{:?}", parse_expr("a = b <=> c + 1")),
+            "Ok(Func2(NullSafeEquals, Func2(Equals, ColName(\"a\"), ColName(\"b\")), Func2(Add, ColName(\"c\"), Const(Int(1)))))"
+        );
+        assert_eq!(
+            format!("{:?}", parse_expr("a IS DISTINCT FROM b OR c IS NOT DISTINCT FROM NULL")),
+            "Ok(Func2(Or, Func1(Not, Func2(NullSafeEquals, ColName(\"a\"), ColName(\"b\"))), Func2(NullSafeEquals, ColName(\"c\"), Const(Null))))"
+        );
+    }
+
+    #[test]
+    fn test_float_literals() {
+        assert_eq!(format!("{:?}", parse_expr("x > 0.5")), "Ok(Func2(GT, ColName(\"x\"), Const(Float(OrderedFloat(0.5)))))");
+        assert_eq!(format!("{:?}", parse_expr("1.5e-3")), "Ok(Const(Float(OrderedFloat(0.0015))))");
+        assert_eq!(format!("{:?}", parse_expr("2E+2")), "Ok(Const(Float(OrderedFloat(200.0))))");
+        assert_eq!(format!("{:?}", parse_expr("1e30")), "Ok(Const(Float(OrderedFloat(1e30))))");
+        assert_eq!(format!("{:?}", parse_expr("1 - e - 1")), "Ok(Func2(Subtract, Func2(Subtract, Const(Int(1)), ColName(\"e\")), Const(Int(1))))");
+    }
+
+    #[test]
+    fn test_show() {
+        assert_eq!(format!("{:?}", parse("show tables;")), "Ok(ShowTables)");
+        assert_eq!(format!("{:?}", parse("SHOW COLUMNS FROM \"my table\"")), "Ok(ShowColumns(\"my table\"))");
+        assert_eq!(format!("{:?}", parse("SHOW COLUMNS IN t;")), "Ok(ShowColumns(\"t\"))");
+        assert_eq!(
+            format!("{:?}", parse("SHOW TABLES FROM t")),
+            "Err(ParseError(\"Expected SHOW TABLES or SHOW COLUMNS FROM <table>\"))"
+        );
+    }
+
+    #[test]
+    fn test_split_statements() {
+        assert_eq!(
+            split_statements("SELECT a FROM t;\n SHOW TABLES ;; SELECT ';' FROM t WHERE b = 'x;y'").unwrap(),
+            vec!["SELECT a FROM t", "SHOW TABLES", "SELECT ';' FROM t WHERE b = 'x;y'"]
+        );
+        assert_eq!(split_statements("SELECT 1 FROM t;").unwrap(), vec!["SELECT 1 FROM t"]);
+        assert!(split_statements(" ; ").unwrap().is_empty());
+        assert_eq!(
+            split_statements("SELECT 'it''s;' FROM t;\r\nSELECT N'a''b', \"c\" FROM t -- x;\r\n").unwrap(),
+            vec!["SELECT 'it''s;' FROM t", "SELECT N'a''b', \"c\" FROM t -- x;"]
+        );
+    }
+
+    #[test]
+    fn test_compound_identifier() {
+        assert_eq!(
+            format!("{:?}", parse_query("select user.id from logs")),
+            "Ok(Query { select: [ColumnInfo { expr: ColName(\"user.id\"), name: Some(\"user.id\") }], table: \"logs\", filter: Const(Int(1)), having: Const(Int(1)), order_by: [], limit: LimitClause { limit: 100, offset: 0 }, partition_filter: Const(Int(1)) })");
+    }
+
+    #[test]
+    fn test_partition_filter() {
+        assert_eq!(
+            format!("{:?}", parse_query("select x from t with (partition_id > 1, partition_id < 5)").map(|q| q.partition_filter)),
+            "Ok(Func2(And, Func2(GT, ColName(\"partition_id\"), Const(Int(1))), Func2(LT, ColName(\"partition_id\"), Const(Int(5)))))");
     }
 
     #[test]
     fn test_to_year() {
         assert_eq!(
             format!("{:?}", parse_query("select to_year(ts) from default")),
-            "Ok(Query { select: [ColumnInfo { expr: Func1(ToYear, ColName(\"ts\")), name: Some(\"to_year(ts)\") }], table: \"default\", filter: Const(Int(1)), order_by: [], limit: LimitClause { limit: 100, offset: 0 } })");
+            "Ok(Query { select: [ColumnInfo { expr: Func1(ToYear, ColName(\"ts\")), name: Some(\"to_year(ts)\") }], table: \"default\", filter: Const(Int(1)), having: Const(Int(1)), order_by: [], limit: LimitClause { limit: 100, offset: 0 }, partition_filter: Const(Int(1)) })");
+    }
+
+    #[test]
+    fn test_min_max() {
+        assert_eq!(
+            format!("{:?}", parse_query("select min(latency), max(latency) from requests")),
+            "Ok(Query { select: [ColumnInfo { expr: Aggregate(MinI64, ColName(\"latency\")), name: Some(\"min(latency)\") }, ColumnInfo { expr: Aggregate(MaxI64, ColName(\"latency\")), name: Some(\"max(latency)\") }], table: \"requests\", filter: Const(Int(1)), having: Const(Int(1)), order_by: [], limit: LimitClause { limit: 100, offset: 0 }, partition_filter: Const(Int(1)) })");
+    }
+
+    #[test]
+    fn test_min_max_arity() {
+        assert!(matches!(parse_query("select min() from requests"), Err(QueryError::ParseError(_))));
+        assert!(matches!(parse_query("select max(a, b) from requests"), Err(QueryError::ParseError(_))));
+    }
+
+    #[test]
+    fn test_count_star() {
+        assert_eq!(
+            format!("{:?}", parse_query("select count(*) from requests")),
+            "Ok(Query { select: [ColumnInfo { expr: Aggregate(Count, Const(Int(1))), name: Some(\"count(*)\") }], table: \"requests\", filter: Const(Int(1)), having: Const(Int(1)), order_by: [], limit: LimitClause { limit: 100, offset: 0 }, partition_filter: Const(Int(1)) })");
+        assert!(matches!(parse_query("select count(distinct *) from requests"), Err(QueryError::ParseError(_))));
+    }
+
+    #[test]
+    fn test_limit_offset() {
+        let limit = LimitClause { limit: 10, offset: 5 };
+        assert_eq!(parse_query("select a from t limit 10 offset 5").unwrap().limit, limit);
+        assert_eq!(parse_query("select a from t limit 5, 10").unwrap().limit, limit);
+        assert_eq!(parse_query("select a from t limit 5,10;").unwrap().limit, limit);
+    }
+
+    #[test]
+    fn test_group_by_having() {
+        assert_eq!(
+            format!("{:?}", parse_query("select code, count(*) from requests group by code having count(*) > 5").unwrap().having),
+            "Func2(GT, Aggregate(Count, Const(Int(1))), Const(Int(5)))");
+        assert!(parse_query("select code as c, count(*) from requests group by c").is_ok());
+        assert!(matches!(parse_query("select code, count(*) from requests group by path"), Err(QueryError::NotImplemented(_))));
+    }
+
+    #[test]
+    fn test_in_list() {
+        assert_eq!(
+            format!("{:?}", parse_query("select code from requests where code in (200, 404)")),
+            "Ok(Query { select: [ColumnInfo { expr: ColName(\"code\"), name: Some(\"code\") }], table: \"requests\", filter: Func2(Or, Func2(Equals, ColName(\"code\"), Const(Int(200))), Func2(Equals, ColName(\"code\"), Const(Int(404)))), having: Const(Int(1)), order_by: [], limit: LimitClause { limit: 100, offset: 0 }, partition_filter: Const(Int(1)) })");
+        assert!(matches!(parse_query("select code from requests where code in (200, 'ok')"), Err(QueryError::TypeError(_))));
+    }
+
+    #[test]
+    fn test_nested() {
+        let nested = parse_query("select (1 + 2) * 3 from t").unwrap();
+        let flat = parse_query("select 1 + 2 * 3 from t").unwrap();
+        assert_eq!(
+            format!("{:?}", nested.select[0].expr),
+            "Func2(Multiply, Func2(Add, Const(Int(1)), Const(Int(2))), Const(Int(3)))");
+        assert_eq!(
+            format!("{:?}", flat.select[0].expr),
+            "Func2(Add, Const(Int(1)), Func2(Multiply, Const(Int(2)), Const(Int(3))))");
+        assert_eq!(
+            format!("{:?}", parse_query("select a from t where (a + b) * c > 10").unwrap().filter),
+            "Func2(GT, Func2(Multiply, Func2(Add, ColName(\"a\"), ColName(\"b\")), ColName(\"c\")), Const(Int(10)))");
+    }
+
+    #[test]
+    fn test_union_all() {
+        let union = match parse("select a as x, b from t1 union all select c, d from t2 order by x desc limit 5 offset 10") {
+            Ok(ParsedQuery::UnionAll(union)) => union,
+            result => panic!("Expected UNION ALL, got {:?}", result),
+        };
+        assert_eq!(union.queries.len(), 2);
+        assert_eq!(union.order_by, vec![(0, true, true)]);
+        assert_eq!(union.limit, LimitClause { limit: 5, offset: 10 });
+        assert_eq!(union.queries[1].table, "t2");
+        assert_eq!(format!("{:?}", union.queries[1].order_by), "[(ColName(\"c\"), true, true)]");
+        assert_eq!(union.queries[1].limit, LimitClause { limit: 15, offset: 0 });
+        assert!(matches!(parse_query("select a from t1 union all select a from t2"), Err(QueryError::NotImplemented(_))));
+        assert!(matches!(parse("select a from t1 except select a from t2"), Err(QueryError::NotImplemented(_))));
+    }
+
+    #[test]
+    fn test_join() {
+        let join = match parse("select b.y, a.x from a join t2 as b on a.id = b.a_id where a.x > 1 and b.y = 2") {
+            Ok(ParsedQuery::Join(join)) => join,
+            result => panic!("Expected JOIN, got {:?}", result),
+        };
+        assert_eq!(join.queries[0].table, "a");
+        assert_eq!(join.queries[1].table, "t2");
+        assert_eq!(join.keys, [0, 0]);
+        assert_eq!(join.projection, vec![(1, 1), (0, 1)]);
+        assert_eq!(join.colnames, vec!["b.y", "a.x"]);
+        assert_eq!(format!("{:?}", join.queries[0].select[1].expr), "ColName(\"x\")");
+        assert_eq!(format!("{:?}", join.queries[1].filter), "Func2(Equals, ColName(\"y\"), Const(Int(2)))");
+        assert!(matches!(parse("select a.x from a join b on a.id < b.id"), Err(QueryError::NotImplemented(_))));
+        assert!(matches!(parse("select a.x from a join b on a.id = b.id join c on a.id = c.id"), Err(QueryError::NotImplemented(_))));
+    }
+
+    #[test]
+    fn test_order_by_nulls() {
+        let order_by = |query: &str| {
+            parse_query(query).unwrap().order_by.into_iter().map(|(_, desc, nulls_first)| (desc, nulls_first)).collect::<Vec<_>>()
+        };
+        assert_eq!(order_by("select a from t order by a, b desc"), vec![(false, false), (true, true)]);
+        assert_eq!(order_by("select a from t order by a nulls first, b desc nulls last"), vec![(false, true), (true, false)]);
+        assert_eq!(order_by("select a from t order by a asc nulls last"), vec![(false, false)]);
     }
 }
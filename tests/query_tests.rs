@@ -20,9 +20,9 @@ fn test_query(query: &str, expected_rows: &[Vec<Value>]) {
             .load_csv(LoadOptions::new("test_data/tiny.csv", "default").with_partition_size(40)),
     );
     let result = if env::var("DEBUG_TESTS").is_ok() {
-        block_on(locustdb.run_query(query, true, vec![0, 1, 2])).unwrap()
+        block_on(locustdb.run_query(query, true, vec![0, 1, 2], None)).unwrap()
     } else {
-        block_on(locustdb.run_query(query, true, vec![])).unwrap()
+        block_on(locustdb.run_query(query, true, vec![], None)).unwrap()
     };
     assert_eq!(result.unwrap().rows, expected_rows);
 }
@@ -43,9 +43,9 @@ fn test_query_ec(query: &str, expected_rows: &[Vec<Value>]) {
         ),
     );
     let result = if env::var("DEBUG_TESTS").is_ok() {
-        block_on(locustdb.run_query(query, false, vec![0, 1, 2, 3])).unwrap()
+        block_on(locustdb.run_query(query, false, vec![0, 1, 2, 3], None)).unwrap()
     } else {
-        block_on(locustdb.run_query(query, false, vec![])).unwrap()
+        block_on(locustdb.run_query(query, false, vec![], None)).unwrap()
     };
     assert_eq!(result.unwrap().rows, expected_rows);
 }
@@ -66,9 +66,9 @@ fn test_query_ec_err(query: &str, _expected_err: QueryError) {
         ),
     );
     let result = if env::var("DEBUG_TESTS").is_ok() {
-        block_on(locustdb.run_query(query, false, vec![0, 1, 2, 3])).unwrap()
+        block_on(locustdb.run_query(query, false, vec![0, 1, 2, 3], None)).unwrap()
     } else {
-        block_on(locustdb.run_query(query, false, vec![])).unwrap()
+        block_on(locustdb.run_query(query, false, vec![], None)).unwrap()
     };
     assert!(result.is_err());
 }
@@ -89,7 +89,7 @@ fn test_query_nyc(query: &str, expected_rows: &[Vec<Value>]) {
         ),
     );
     load.unwrap();
-    let result = block_on(locustdb.run_query(query, false, vec![])).unwrap();
+    let result = block_on(locustdb.run_query(query, false, vec![], None)).unwrap();
     let actual_rows = result.unwrap().rows;
     assert_eq!(
         &actual_rows[..min(expected_rows.len(), actual_rows.len())],
@@ -113,13 +113,27 @@ fn test_query_colnames(query: &str, expected_result: Vec<String>) {
         ),
     );
     let result = if env::var("DEBUG_TESTS").is_ok() {
-        block_on(locustdb.run_query(query, false, vec![0, 1, 2, 3])).unwrap()
+        block_on(locustdb.run_query(query, false, vec![0, 1, 2, 3], None)).unwrap()
     } else {
-        block_on(locustdb.run_query(query, false, vec![])).unwrap()
+        block_on(locustdb.run_query(query, false, vec![], None)).unwrap()
     };
     assert_eq!(result.unwrap().colnames, expected_result);
 }
 
+fn test_query_coltypes(query: &str, expected_result: &[&str]) {
+    let _ = env_logger::try_init();
+    let locustdb = LocustDB::new(&Options::default());
+    let _ = block_on(
+        locustdb.load_csv(
+            LoadOptions::new("test_data/edge_cases.csv", "default")
+                .with_partition_size(3)
+                .allow_nulls_all_columns(),
+        ),
+    );
+    let result = block_on(locustdb.run_query(query, false, vec![], None)).unwrap();
+    assert_eq!(result.unwrap().coltypes, expected_result);
+}
+
 #[test]
 fn test_select_string() {
     test_query(
@@ -163,6 +177,22 @@ fn test_limit_offset() {
         "SELECT nullable_int FROM default ORDER BY id DESC LIMIT 4 OFFSET 5 ROWS;",
         &[vec![Int(10)], vec![Null], vec![Null], vec![Int(-40)]],
     );
+    test_query_ec(
+        "SELECT id FROM default ORDER BY id LIMIT 10 OFFSET 5;",
+        &[vec![Int(5)], vec![Int(6)], vec![Int(7)], vec![Int(8)], vec![Int(9)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default ORDER BY id LIMIT 6, 2;",
+        &[vec![Int(6)], vec![Int(7)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default ORDER BY id LIMIT 10 OFFSET 20;",
+        &[],
+    );
+    test_query_ec(
+        "SELECT enum, count(0) FROM default LIMIT 2 OFFSET 1;",
+        &[vec![Str("bb"), Int(3)], vec![Str("cc"), Int(2)]],
+    );
 }
 
 #[test]
@@ -307,6 +337,220 @@ fn test_sum() {
     );
 }
 
+#[test]
+fn test_sum_float() {
+    test_query_ec(
+        "SELECT sum(float), sum(float * 100), sum(id) FROM default WHERE id IN (2, 6, 8);",
+        &[vec![
+            Float(OrderedFloat(-125.0)),
+            Float(OrderedFloat(-12500.0)),
+            Int(16),
+        ]],
+    );
+    test_query_ec(
+        "SELECT avg(float), sum(float) / 2 FROM default WHERE id IN (2, 6, 8);",
+        &[vec![
+            Float(OrderedFloat(-125.0 / 3.0)),
+            Float(OrderedFloat(-62.5)),
+        ]],
+    );
+    test_query_ec(
+        "SELECT enum, avg(float) FROM default WHERE id IN (2, 6, 8);",
+        &[
+            vec![Str("aa"), Float(OrderedFloat(-124.0))],
+            vec![Str("cc"), Float(OrderedFloat(-0.5))],
+        ],
+    );
+    test_query_ec(
+        "SELECT id, float / 2 FROM default WHERE id IN (2, 6, 8) ORDER BY id;",
+        &[
+            vec![Int(2), Float(OrderedFloat(-62.0))],
+            vec![Int(6), Float(OrderedFloat(0.0))],
+            vec![Int(8), Float(OrderedFloat(-0.5))],
+        ],
+    );
+}
+
+#[test]
+fn test_floor_ceil_round() {
+    test_query_ec(
+        "SELECT id, floor(float), ceil(float), round(float) FROM default WHERE id IN (0, 2, 3, 8, 9) ORDER BY id;",
+        &[
+            vec![Int(0), Int(0), Int(1), Int(0)],
+            vec![Int(2), Int(-124), Int(-124), Int(-124)],
+            vec![Int(3), Int(3), Int(4), Int(3)],
+            vec![Int(8), Int(-1), Int(-1), Int(-1)],
+            vec![Int(9), Int(1234124), Int(1234125), Int(1234125)],
+        ],
+    );
+    test_query_ec(
+        "SELECT id, round(float, 2) FROM default WHERE id IN (0, 3, 9) ORDER BY id;",
+        &[
+            vec![Int(0), Float(OrderedFloat(0.12))],
+            vec![Int(3), Float(OrderedFloat(3.15))],
+            vec![Int(9), Float(OrderedFloat(1234124.51))],
+        ],
+    );
+    test_query_ec(
+        "SELECT floor(float), COUNT(0) FROM default WHERE id IN (3, 5, 6, 7);",
+        &[vec![Int(0), Int(3)], vec![Int(3), Int(1)]],
+    );
+    test_query_ec(
+        "SELECT round(id), ceil(id), round(id, 1) FROM default WHERE id = 7;",
+        &[vec![Int(7), Int(7), Int(7)]],
+    );
+    test_query_ec_err(
+        "SELECT floor(enum) FROM default;",
+        QueryError::TypeError("Found Floor(String), expected Floor(float)".to_string()),
+    );
+}
+
+#[test]
+fn test_abs_sign() {
+    test_query_ec(
+        "SELECT id, abs(negative), sign(negative), abs(float), sign(float) FROM default WHERE id IN (0, 2, 6, 8) ORDER BY id;",
+        &[
+            vec![Int(0), Int(199), Int(-1), Float(OrderedFloat(0.123412)), Int(1)],
+            vec![Int(2), Int(100), Int(-1), Float(OrderedFloat(124.0)), Int(-1)],
+            vec![Int(6), Int(130), Int(-1), Float(OrderedFloat(0.0)), Int(0)],
+            vec![Int(8), Int(4010), Int(1), Float(OrderedFloat(1.0)), Int(-1)],
+        ],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE abs(negative) > 150 ORDER BY id;",
+        &[vec![Int(0)], vec![Int(4)], vec![Int(8)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE abs(negative) = abs(-100) OR sign(id) = sign(-5);",
+        &[vec![Int(2)]],
+    );
+    test_query_ec(
+        "SELECT id, abs(largenum - 1) FROM default WHERE id IN (0, 1) ORDER BY id;",
+        &[
+            vec![Int(0), Int(9223372036854775807)],
+            vec![Int(1), Int(9223372036854775806)],
+        ],
+    );
+    test_query_ec_err(
+        "SELECT abs(enum) FROM default;",
+        QueryError::TypeError("Found Abs(String), expected Abs(integer) or Abs(float)".to_string()),
+    );
+}
+
+#[test]
+fn test_date_trunc() {
+    test_query(
+        "SELECT to_month(ts), to_day(ts), to_hour(ts) FROM default WHERE ts = 1477514391;",
+        &[vec![Int(1475280000), Int(1477440000), Int(1477512000)]],
+    );
+    test_query(
+        "SELECT date_trunc('minute', ts), date_trunc('week', ts), date_trunc('YEAR', ts) FROM default WHERE ts = 1477514391;",
+        &[vec![Int(1477514340), Int(1477267200), Int(1451606400)]],
+    );
+    test_query(
+        "SELECT to_month(ts), COUNT(0) FROM default WHERE ts < 1460000000;",
+        &[
+            vec![Int(1454284800), Int(2)],
+            vec![Int(1456790400), Int(6)],
+            vec![Int(1459468800), Int(3)],
+        ],
+    );
+    test_query(
+        "SELECT date_trunc('year', ts), COUNT(0) FROM default;",
+        &[vec![Int(1451606400), Int(89)], vec![Int(1483228800), Int(11)]],
+    );
+    test_query_ec_err(
+        "SELECT date_trunc('fortnight', id) FROM default;",
+        QueryError::TypeError("Unsupported unit for date_trunc: fortnight".to_string()),
+    );
+}
+
+fn test_query_bools(query: &str, expected_rows: &[Vec<Value>]) {
+    let _ = env_logger::try_init();
+    let locustdb = LocustDB::new(&Options::default());
+    let mut columns = std::collections::HashMap::new();
+    columns.insert("id".to_string(), (0..6).map(Int).collect());
+    columns.insert(
+        "active".to_string(),
+        [true, false, false, true, true, false].into_iter().map(Bool).collect(),
+    );
+    block_on(locustdb.ingest_heterogeneous("default", columns));
+    let result = block_on(locustdb.run_query(query, false, vec![], None)).unwrap();
+    assert_eq!(result.unwrap().rows, expected_rows);
+}
+
+#[test]
+fn test_bool_column() {
+    test_query_bools(
+        "SELECT id FROM default WHERE active = true ORDER BY id;",
+        &[vec![Int(0)], vec![Int(3)], vec![Int(4)]],
+    );
+    test_query_bools(
+        "SELECT id FROM default WHERE active <> true ORDER BY id;",
+        &[vec![Int(1)], vec![Int(2)], vec![Int(5)]],
+    );
+    test_query_bools(
+        "SELECT id FROM default WHERE active ORDER BY id;",
+        &[vec![Int(0)], vec![Int(3)], vec![Int(4)]],
+    );
+    test_query_bools(
+        "SELECT id FROM default WHERE NOT active AND id > 1 ORDER BY id;",
+        &[vec![Int(2)], vec![Int(5)]],
+    );
+    test_query_bools(
+        "SELECT id, active FROM default WHERE id < 3 ORDER BY id;",
+        &[
+            vec![Int(0), Bool(true)],
+            vec![Int(1), Bool(false)],
+            vec![Int(2), Bool(false)],
+        ],
+    );
+    test_query_bools(
+        "SELECT active, COUNT(0) FROM default;",
+        &[vec![Bool(false), Int(3)], vec![Bool(true), Int(3)]],
+    );
+    test_query_bools(
+        "SELECT id, id > 3 FROM default WHERE id >= 3 ORDER BY id;",
+        &[
+            vec![Int(3), Bool(false)],
+            vec![Int(4), Bool(true)],
+            vec![Int(5), Bool(true)],
+        ],
+    );
+}
+
+#[test]
+fn test_null_bool_column() {
+    let locustdb = LocustDB::new(&Options::default());
+    // Last row is stored in a separate partition without nulls
+    block_on(locustdb.create_table("bools", Some(3)));
+    let rows = vec![
+        vec![("i".to_string(), Int(0)), ("b".to_string(), Bool(true))],
+        vec![("i".to_string(), Int(1)), ("b".to_string(), Null)],
+        vec![("i".to_string(), Int(2))],
+        vec![("i".to_string(), Int(3)), ("b".to_string(), Bool(false))],
+    ];
+    block_on(locustdb.ingest("bools", rows)).unwrap();
+    let query = |query: &str| block_on(locustdb.run_query(query, false, vec![], None)).unwrap().unwrap().rows;
+    assert_eq!(
+        query("SELECT i, b FROM bools ORDER BY i;"),
+        vec![
+            vec![Int(0), Bool(true)],
+            vec![Int(1), Null],
+            vec![Int(2), Null],
+            vec![Int(3), Bool(false)],
+        ]
+    );
+    assert_eq!(query("SELECT i FROM bools WHERE b = false;"), vec![vec![Int(3)]]);
+    assert_eq!(query("SELECT i FROM bools WHERE b IS NULL ORDER BY i;"), vec![vec![Int(1)], vec![Int(2)]]);
+    assert_eq!(query("SELECT i FROM bools WHERE b;"), vec![vec![Int(0)]]);
+    assert_eq!(query("SELECT i FROM bools WHERE NOT b;"), vec![vec![Int(3)]]);
+    assert_eq!(
+        query("SELECT b, COUNT(0) FROM bools;"),
+        vec![vec![Null, Int(2)], vec![Bool(false), Int(1)], vec![Bool(true), Int(1)]]
+    );
+}
+
 #[test]
 fn test_sum_2() {
     test_query_ec(
@@ -357,6 +601,88 @@ fn test_division() {
     )
 }
 
+#[test]
+fn test_integer_vs_float_division() {
+    test_query_ec(
+        "SELECT id, id + 7 / 2, id * (7.0 / 2), id * (7 / 2.0), id / 2, id / 2.0, -1.5 * id FROM default WHERE id < 3 ORDER BY id;",
+        &[
+            vec![Int(0), Int(3), Float(OrderedFloat(0.0)), Float(OrderedFloat(0.0)), Int(0), Float(OrderedFloat(0.0)), Float(OrderedFloat(-0.0))],
+            vec![Int(1), Int(4), Float(OrderedFloat(3.5)), Float(OrderedFloat(3.5)), Int(0), Float(OrderedFloat(0.5)), Float(OrderedFloat(-1.5))],
+            vec![Int(2), Int(5), Float(OrderedFloat(7.0)), Float(OrderedFloat(7.0)), Int(1), Float(OrderedFloat(1.0)), Float(OrderedFloat(-3.0))],
+        ],
+    );
+    test_query_ec(
+        "SELECT SUM(id) / COUNT(0), SUM(id) / (COUNT(0) * 1.0) FROM default;",
+        &[vec![Int(4), Float(OrderedFloat(4.5))]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE id = 9 / 2 OR id = (1 + 2) * 3 ORDER BY id;",
+        &[vec![Int(4)], vec![Int(9)]],
+    );
+}
+
+#[test]
+fn test_modulo() {
+    test_query_ec(
+        "SELECT id FROM default WHERE id % 3 = 0 ORDER BY id;",
+        &[vec![Int(0)], vec![Int(3)], vec![Int(6)], vec![Int(9)]],
+    );
+    test_query_ec(
+        "SELECT id, negative % 7 FROM default ORDER BY id LIMIT 3;",
+        &[
+            vec![Int(0), Int(-3)],
+            vec![Int(1), Int(4)],
+            vec![Int(2), Int(-2)],
+        ],
+    );
+    test_query_ec(
+        "SELECT id, float % 3 FROM default WHERE float < 0 ORDER BY id;",
+        &[
+            vec![Int(2), Float(OrderedFloat(-1.0))],
+            vec![Int(8), Float(OrderedFloat(-1.0))],
+        ],
+    );
+}
+
+#[test]
+fn test_division_by_zero() {
+    test_query_ec(
+        "SELECT id, 10 / non_dense_ints, id % non_dense_ints FROM default ORDER BY id LIMIT 4;",
+        &[
+            vec![Int(0), Null, Null],
+            vec![Int(1), Int(5), Int(1)],
+            vec![Int(2), Int(3), Int(2)],
+            vec![Int(3), Int(10), Int(0)],
+        ],
+    );
+    test_query_ec(
+        "SELECT id, 100 / nullable_int FROM default WHERE id < 3 ORDER BY id;",
+        &[
+            vec![Int(0), Int(-100)],
+            vec![Int(1), Int(-2)],
+            vec![Int(2), Null],
+        ],
+    );
+    test_query_ec(
+        "SELECT id / 0, id % 0 FROM default LIMIT 1;",
+        &[vec![Null, Null]],
+    );
+    test_query_ec(
+        "SELECT float / 0, float % 0 FROM default WHERE id < 2;",
+        &[vec![Null, Null], vec![Null, Null]],
+    );
+    test_query_ec(
+        "SELECT id, float / 0, id % 0 FROM default WHERE id < 5 ORDER BY id;",
+        &[
+            vec![Int(0), Null, Null],
+            vec![Int(1), Null, Null],
+            vec![Int(2), Null, Null],
+            vec![Int(3), Null, Null],
+            vec![Int(4), Null, Null],
+        ],
+    );
+}
+
 #[test]
 fn test_regex() {
     test_query(
@@ -398,6 +724,74 @@ fn test_like_mismatch() {
     );
 }
 
+#[test]
+fn test_like_patterns() {
+    test_query_ec(
+        "SELECT string_packed FROM default WHERE string_packed LIKE 'a%' ORDER BY id;",
+        &[vec![Str("abc")], vec![Str("axz")], vec![Str("azy")], vec![Str("asd")]],
+    );
+    test_query_ec(
+        "SELECT string_packed FROM default WHERE string_packed LIKE '%z' ORDER BY id;",
+        &[vec![Str("xyz")], vec![Str("axz")]],
+    );
+    test_query_ec(
+        "SELECT string_packed FROM default WHERE string_packed LIKE '%s%' ORDER BY id;",
+        &[vec![Str("$sss")], vec![Str("asd")]],
+    );
+    test_query_ec(
+        "SELECT string_packed FROM default WHERE string_packed LIKE 'a_z' ORDER BY id;",
+        &[vec![Str("axz")]],
+    );
+    test_query_ec(
+        "SELECT string_packed FROM default WHERE string_packed LIKE '\\_%' ORDER BY id;",
+        &[vec![Str("_f")]],
+    );
+    test_query_ec(
+        "SELECT string_packed FROM default WHERE string_packed LIKE 'ax%' ORDER BY id;",
+        &[vec![Str("axz")]],
+    );
+}
+
+#[test]
+fn test_concat() {
+    test_query_ec(
+        "SELECT enum || string_packed FROM default WHERE id < 3 ORDER BY id;",
+        &[vec![Str("aaxyz")], vec![Str("aaabc")], vec![Str("aaaxz")]],
+    );
+    test_query_ec(
+        "SELECT enum || '-' || string_packed FROM default WHERE id = 9;",
+        &[vec![Str("bb-😈")]],
+    );
+    test_query_ec(
+        "SELECT 'id:' || enum FROM default WHERE id = 6;",
+        &[vec![Str("id:cc")]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE string_packed = 'a' || 'bc';",
+        &[vec![Int(1)]],
+    );
+    test_query_ec(
+        "SELECT enum || NULL FROM default LIMIT 1;",
+        &[vec![Null]],
+    );
+    test_query_ec(
+        "SELECT enum || 'x', COUNT(0) FROM default;",
+        &[
+            vec![Str("aax"), Int(5)],
+            vec![Str("bbx"), Int(3)],
+            vec![Str("ccx"), Int(2)],
+        ],
+    );
+}
+
+#[test]
+fn test_like_nullable() {
+    test_query_ec(
+        "SELECT id FROM default WHERE country LIKE '%an%' ORDER BY id;",
+        &[vec![Int(0)], vec![Int(2)], vec![Int(4)], vec![Int(9)]],
+    );
+}
+
 #[test]
 fn test_not_equals() {
     use crate::Value::*;
@@ -469,6 +863,23 @@ fn test_order_by_aggregate() {
     )
 }
 
+#[test]
+fn test_order_by_grouping_column_or_constant() {
+    test_query_ec(
+        "SELECT enum, COUNT(0) FROM default ORDER BY enum DESC;",
+        &[
+            vec![Str("cc"), Int(2)],
+            vec![Str("bb"), Int(3)],
+            vec![Str("aa"), Int(5)],
+        ],
+    );
+    test_query_ec("SELECT COUNT(0) FROM default ORDER BY 1;", &[vec![Int(10)]]);
+    test_query_ec(
+        "SELECT enum, MAX(id) FROM default ORDER BY 2, enum LIMIT 2 OFFSET 1;",
+        &[vec![Str("bb"), Int(9)], vec![Str("cc"), Int(8)]],
+    );
+}
+
 #[test]
 fn test_groupless_aggregate() {
     test_query_nyc("SELECT count(0) FROM default", &[vec![Int(10_000)]]);
@@ -520,6 +931,109 @@ fn test_average() {
     )
 }
 
+#[test]
+fn test_average_all_null_group() {
+    test_query_ec(
+        "select id, avg(nullable_int) from default;",
+        &[
+            vec![Int(0), Int(-1)],
+            vec![Int(1), Int(-40)],
+            vec![Int(2), Null],
+            vec![Int(3), Null],
+            vec![Int(4), Int(10)],
+            vec![Int(5), Null],
+            vec![Int(6), Null],
+            vec![Int(7), Int(20)],
+            vec![Int(8), Null],
+            vec![Int(9), Int(13)],
+        ],
+    )
+}
+
+#[test]
+fn test_count_distinct() {
+    test_query_ec(
+        "select count(distinct enum) from default;",
+        &[vec![Int(3)]],
+    );
+    test_query_ec(
+        "select count(distinct country) from default;",
+        &[vec![Int(4)]],
+    );
+    test_query_ec(
+        "select enum, count(distinct nullable_int2) from default;",
+        &[
+            vec![Str("aa"), Int(2)],
+            vec![Str("bb"), Int(3)],
+            vec![Str("cc"), Int(1)],
+        ],
+    );
+    test_query_ec(
+        "select enum, count(distinct country), count(0), sum(id) from default;",
+        &[
+            vec![Str("aa"), Int(3), Int(5), Int(15)],
+            vec![Str("bb"), Int(2), Int(3), Int(16)],
+            vec![Str("cc"), Int(1), Int(2), Int(14)],
+        ],
+    );
+}
+
+#[test]
+fn test_having() {
+    test_query_ec(
+        "SELECT enum, COUNT(*) FROM default GROUP BY enum HAVING COUNT(*) > 2;",
+        &[vec![Str("aa"), Int(5)], vec![Str("bb"), Int(3)]],
+    );
+    test_query_ec(
+        "SELECT enum FROM default GROUP BY enum HAVING SUM(id) < 15;",
+        &[vec![Str("cc")]],
+    );
+    test_query_ec(
+        "SELECT enum, COUNT(0) FROM default HAVING COUNT(0) > 2 AND enum <> 'aa';",
+        &[vec![Str("bb"), Int(3)]],
+    );
+    test_query_ec(
+        "SELECT enum, COUNT(0) FROM default GROUP BY enum HAVING COUNT(0) > 2 ORDER BY COUNT(0);",
+        &[vec![Str("bb"), Int(3)], vec![Str("aa"), Int(5)]],
+    );
+    test_query_ec(
+        "SELECT enum, COUNT(DISTINCT non_dense_ints) FROM default HAVING COUNT(DISTINCT non_dense_ints) > 2;",
+        &[vec![Str("aa"), Int(4)], vec![Str("bb"), Int(3)]],
+    );
+    test_query_ec(
+        "SELECT enum, COUNT(0) FROM default HAVING COUNT(0) > 100;",
+        &[],
+    );
+    test_query_ec_err(
+        "SELECT enum, COUNT(0) FROM default GROUP BY country;",
+        QueryError::NotImplemented("Group By".to_string()),
+    );
+}
+
+#[test]
+fn test_order_by_unprojected_aggregate() {
+    test_query_ec(
+        "SELECT enum FROM default GROUP BY enum ORDER BY SUM(id) DESC LIMIT 10;",
+        &[vec![Str("bb")], vec![Str("aa")], vec![Str("cc")]],
+    );
+    test_query_ec(
+        "SELECT enum FROM default ORDER BY SUM(id) LIMIT 2;",
+        &[vec![Str("cc")], vec![Str("aa")]],
+    );
+    test_query_ec(
+        "SELECT enum FROM default ORDER BY MAX(id) - MIN(id);",
+        &[vec![Str("cc")], vec![Str("bb")], vec![Str("aa")]],
+    );
+    test_query_ec(
+        "SELECT enum, COUNT(0) FROM default ORDER BY SUM(id) DESC;",
+        &[
+            vec![Str("bb"), Int(3)],
+            vec![Str("aa"), Int(5)],
+            vec![Str("cc"), Int(2)],
+        ],
+    );
+}
+
 #[test]
 fn test_count_by_passenger_count_pickup_year_trip_distance() {
     test_query_nyc(
@@ -758,16 +1272,16 @@ fn test_sort_by_nullable() {
          FROM default
          ORDER BY nullable_int, nullable_int2 DESC, country;",
         &[
-            vec![Null, Int(6), Null],
-            vec![Null, Int(1), Null],
-            vec![Null, Int(0), Null],
-            vec![Null, Null, Str("France")],
-            vec![Null, Null, Str("Turkey")],
             vec![Int(-40), Int(-40), Str("USA")],
             vec![Int(-1), Null, Str("Germany")],
             vec![Int(10), Int(9), Str("France")],
             vec![Int(13), Int(14), Str("Germany")],
             vec![Int(20), Null, Null],
+            vec![Null, Null, Str("France")],
+            vec![Null, Null, Str("Turkey")],
+            vec![Null, Int(6), Null],
+            vec![Null, Int(1), Null],
+            vec![Null, Int(0), Null],
         ],
     );
     test_query_ec(
@@ -775,16 +1289,16 @@ fn test_sort_by_nullable() {
          FROM default
          ORDER BY nullable_int2, country DESC;",
         &[
-            vec![Null, Str("Turkey")],
-            vec![Null, Str("Germany")],
-            vec![Null, Str("France")],
-            vec![Null, Null],
             vec![Int(-40), Str("USA")],
             vec![Int(0), Null],
             vec![Int(1), Null],
             vec![Int(6), Null],
             vec![Int(9), Str("France")],
             vec![Int(14), Str("Germany")],
+            vec![Null, Null],
+            vec![Null, Str("Turkey")],
+            vec![Null, Str("Germany")],
+            vec![Null, Str("France")],
         ],
     );
 }
@@ -935,6 +1449,292 @@ fn test_is_null() {
             vec![Int(9)],
         ],
     );
+    test_query_ec(
+        "SELECT COUNT(0) FROM default WHERE nullable_int IS NULL AND nullable_int2 IS NOT NULL;",
+        &[vec![Int(3)]],
+    );
+    test_query_ec(
+        "SELECT id IS NULL, NULL IS NULL, missing_col IS NOT NULL, COUNT(0) FROM default WHERE id < 4;",
+        &[vec![Bool(false), Bool(true), Bool(false), Int(4)]],
+    );
+}
+
+#[test]
+fn test_null_safe_equals() {
+    // Rows 2 and 6 are NULL in both columns, row 1 has equal values
+    test_query_ec(
+        "SELECT id FROM default WHERE nullable_int <=> nullable_int2 ORDER BY id;",
+        &[vec![Int(1)], vec![Int(2)], vec![Int(6)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE nullable_int IS NOT DISTINCT FROM nullable_int2 ORDER BY id;",
+        &[vec![Int(1)], vec![Int(2)], vec![Int(6)]],
+    );
+    test_query_ec(
+        "SELECT id, nullable_int <=> nullable_int2 FROM default WHERE id < 4 ORDER BY id;",
+        &[
+            vec![Int(0), Bool(false)],
+            vec![Int(1), Bool(true)],
+            vec![Int(2), Bool(true)],
+            vec![Int(3), Bool(false)],
+        ],
+    );
+    test_query_ec(
+        "SELECT COUNT(0) FROM default WHERE NOT (nullable_int <=> nullable_int2);",
+        &[vec![Int(7)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE nullable_int <=> NULL ORDER BY id;",
+        &[vec![Int(2)], vec![Int(3)], vec![Int(5)], vec![Int(6)], vec![Int(8)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE nullable_int <=> 10 OR id <=> 3 ORDER BY id;",
+        &[vec![Int(3)], vec![Int(4)]],
+    );
+    test_query_ec(
+        "SELECT COUNT(0) FROM default WHERE nullable_int IS DISTINCT FROM nullable_int2;",
+        &[vec![Int(7)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE id < 4 AND nullable_int IS DISTINCT FROM NULL ORDER BY id;",
+        &[vec![Int(0)], vec![Int(1)]],
+    );
+    test_query_colnames(
+        "SELECT nullable_int <=> NULL, NOT (id IS DISTINCT FROM 1) FROM default;",
+        vec!["nullable_int <=> NULL".to_string(), "NOT (id IS DISTINCT FROM 1)".to_string()],
+    );
+}
+
+#[test]
+fn test_gte_lte() {
+    test_query_ec(
+        "SELECT id FROM default WHERE id >= 7 ORDER BY id;",
+        &[vec![Int(7)], vec![Int(8)], vec![Int(9)]],
+    );
+    test_query_ec(
+        "SELECT string_packed FROM default WHERE string_packed <= 'abc' ORDER BY id;",
+        &[vec![Str("abc")], vec![Str("AXY")], vec![Str("$sss")], vec![Str("_f")]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE float >= 3 ORDER BY id;",
+        &[vec![Int(3)], vec![Int(4)], vec![Int(9)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE float * 2 <= float ORDER BY id;",
+        &[vec![Int(2)], vec![Int(6)], vec![Int(8)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE -1 >= float ORDER BY id;",
+        &[vec![Int(2)], vec![Int(8)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE float BETWEEN -2 AND 1 ORDER BY id;",
+        &[
+            vec![Int(0)],
+            vec![Int(1)],
+            vec![Int(5)],
+            vec![Int(6)],
+            vec![Int(7)],
+            vec![Int(8)],
+        ],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE nullable_int >= 10 ORDER BY id;",
+        &[vec![Int(4)], vec![Int(7)], vec![Int(9)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE nullable_int <= -1 ORDER BY id;",
+        &[vec![Int(0)], vec![Int(1)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE id >= NULL ORDER BY id;",
+        &[],
+    );
+}
+
+#[test]
+fn test_null_three_valued_logic() {
+    test_query_ec(
+        "SELECT id FROM default WHERE id = 1 OR id = NULL;",
+        &[vec![Int(1)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE nullable_int > 0 OR NULL ORDER BY id;",
+        &[vec![Int(4)], vec![Int(7)], vec![Int(9)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE id < 3 AND id = NULL;",
+        &[],
+    );
+    test_query_ec(
+        "SELECT id, id = 1 OR id = NULL, id = 1 AND id = NULL FROM default WHERE id < 3 ORDER BY id;",
+        &[
+            vec![Int(0), Null, Bool(false)],
+            vec![Int(1), Bool(true), Null],
+            vec![Int(2), Null, Bool(false)],
+        ],
+    );
+    test_query_ec(
+        "SELECT NULL FROM default WHERE id < 2;",
+        &[vec![Null], vec![Null]],
+    );
+    test_query_ec(
+        "SELECT id, NULL, missing_column FROM default WHERE id < 5 ORDER BY id;",
+        &[
+            vec![Int(0), Null, Null],
+            vec![Int(1), Null, Null],
+            vec![Int(2), Null, Null],
+            vec![Int(3), Null, Null],
+            vec![Int(4), Null, Null],
+        ],
+    );
+}
+
+#[test]
+fn test_negate() {
+    test_query_ec(
+        "SELECT id FROM default WHERE -negative > 0 ORDER BY id;",
+        &[vec![Int(0)], vec![Int(2)], vec![Int(6)], vec![Int(7)], vec![Int(9)]],
+    );
+    test_query_ec(
+        "SELECT -nullable_int FROM default ORDER BY id LIMIT 3;",
+        &[vec![Int(1)], vec![Int(40)], vec![Null]],
+    );
+    test_query_ec(
+        "SELECT -float FROM default WHERE id = 2;",
+        &[vec![Float(OrderedFloat(124.0))]],
+    );
+}
+
+#[test]
+fn test_not() {
+    test_query_ec(
+        "SELECT id FROM default WHERE NOT (id < 8) ORDER BY id;",
+        &[vec![Int(8)], vec![Int(9)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE NOT (nullable_int > 0) ORDER BY id;",
+        &[vec![Int(0)], vec![Int(1)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE NOT (country LIKE '%an%') ORDER BY id;",
+        &[vec![Int(1)], vec![Int(6)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE NOT (id < NULL);",
+        &[],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE NOT (id > 1 OR id = NULL) ORDER BY id;",
+        &[],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE NOT (id > 1 AND id = NULL) ORDER BY id;",
+        &[vec![Int(0)], vec![Int(1)]],
+    );
+    test_query_ec(
+        "SELECT id, NOT (id < NULL) FROM default WHERE id < 2 ORDER BY id;",
+        &[vec![Int(0), Null], vec![Int(1), Null]],
+    );
+}
+
+#[test]
+fn test_count_star() {
+    test_query_ec("SELECT COUNT(*) FROM default;", &[vec![Int(10)]]);
+    test_query_ec(
+        "SELECT count(*) FROM default WHERE id < 7;",
+        &[vec![Int(7)]],
+    );
+    test_query_ec(
+        "SELECT enum, count(*) FROM default;",
+        &[
+            vec![Str("aa"), Int(5)],
+            vec![Str("bb"), Int(3)],
+            vec![Str("cc"), Int(2)],
+        ],
+    );
+}
+
+#[test]
+fn test_in_list() {
+    test_query_ec(
+        "SELECT id FROM default WHERE id IN (1, 4, 7) ORDER BY id;",
+        &[vec![Int(1)], vec![Int(4)], vec![Int(7)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE enum IN ('bb', 'cc') ORDER BY id;",
+        &[
+            vec![Int(3)],
+            vec![Int(4)],
+            vec![Int(6)],
+            vec![Int(8)],
+            vec![Int(9)],
+        ],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE nullable_int IN (-40, 20) ORDER BY id;",
+        &[vec![Int(1)], vec![Int(7)]],
+    );
+    test_query_ec_err(
+        "SELECT id FROM default WHERE id IN (1, 'aa');",
+        QueryError::TypeError(String::new()),
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE id IN (1, NULL) ORDER BY id;",
+        &[vec![Int(1)]],
+    );
+}
+
+#[test]
+fn test_not_in_list() {
+    test_query_ec(
+        "SELECT id FROM default WHERE id NOT IN (1, 2, 3, 4, 5) ORDER BY id;",
+        &[vec![Int(0)], vec![Int(6)], vec![Int(7)], vec![Int(8)], vec![Int(9)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE enum NOT IN ('aa', 'bb') ORDER BY id;",
+        &[vec![Int(6)], vec![Int(8)]],
+    );
+    // Rows where the expression is NULL are excluded
+    test_query_ec(
+        "SELECT id FROM default WHERE nullable_int NOT IN (-40, 20) ORDER BY id;",
+        &[vec![Int(0)], vec![Int(4)], vec![Int(9)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE country NOT IN ('Germany', 'France') ORDER BY id;",
+        &[vec![Int(1)], vec![Int(6)]],
+    );
+    test_query_ec("SELECT id FROM default WHERE id NOT IN (1, NULL);", &[]);
+    test_query_ec("SELECT id FROM default WHERE NULL NOT IN (1, 2);", &[]);
+    test_query_ec(
+        "SELECT id, id NOT IN (1, 2) FROM default WHERE id < 4 ORDER BY id;",
+        &[
+            vec![Int(0), Bool(true)],
+            vec![Int(1), Bool(false)],
+            vec![Int(2), Bool(false)],
+            vec![Int(3), Bool(true)],
+        ],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE country NOT LIKE '%an%' ORDER BY id;",
+        &[vec![Int(1)], vec![Int(6)]],
+    );
+}
+
+#[test]
+fn test_between() {
+    test_query_ec(
+        "SELECT id FROM default WHERE id BETWEEN 3 AND 5 ORDER BY id;",
+        &[vec![Int(3)], vec![Int(4)], vec![Int(5)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE id NOT BETWEEN 2 AND 8 ORDER BY id;",
+        &[vec![Int(0)], vec![Int(1)], vec![Int(9)]],
+    );
+    test_query_ec(
+        "SELECT string_packed FROM default WHERE string_packed BETWEEN 'abc' AND 'axz' ORDER BY id;",
+        &[vec![Str("abc")], vec![Str("axz")], vec![Str("asd")]],
+    );
 }
 
 #[test]
@@ -992,10 +1792,44 @@ fn test_gen_table() {
         [Str("Hazelnut".to_string()), Int(76_356)],
         [Str("Walnut".to_string()), Int(23_868)],
     ];
-    let result = block_on(locustdb.run_query(query, true, vec![])).unwrap();
+    let result = block_on(locustdb.run_query(query, true, vec![], None)).unwrap();
     assert_eq!(result.unwrap().rows, expected_rows);
 }
 
+#[test]
+fn test_query_timeout() {
+    let _ = env_logger::try_init();
+    let locustdb = LocustDB::memory_only();
+    let _ = block_on(locustdb.gen_table(locustdb::colgen::GenTable {
+        name: "test".to_string(),
+        partitions: 64,
+        partition_size: 2 << 14,
+        columns: vec![
+            ("id".to_string(), locustdb::colgen::incrementing_int()),
+            ("value".to_string(), locustdb::colgen::int_uniform(-1000, 1000)),
+        ],
+    }));
+    let query = "SELECT id / 1000, sum(value) FROM test;";
+    let result = block_on(locustdb.run_query(
+        query,
+        false,
+        vec![],
+        Some(std::time::Duration::from_millis(1)),
+    ))
+    .unwrap();
+    assert!(matches!(result, Err(QueryError::Timeout)));
+
+    let query = "SELECT count(0) FROM test;";
+    let result = block_on(locustdb.run_query(
+        query,
+        false,
+        vec![],
+        Some(std::time::Duration::from_secs(600)),
+    ))
+    .unwrap();
+    assert_eq!(result.unwrap().rows, vec![vec![Int(64 * (2 << 14))]]);
+}
+
 #[test]
 fn test_column_with_null_partitions() {
     use crate::Value::*;
@@ -1020,9 +1854,9 @@ fn test_column_with_null_partitions() {
             ),
         )],
     }));
-    println!("{:?}", block_on(locustdb.run_query("SELECT * FROM test;", true, vec![])).unwrap().unwrap());
+    println!("{:?}", block_on(locustdb.run_query("SELECT * FROM test;", true, vec![], None)).unwrap().unwrap());
     let query = "SELECT partition_sparse FROM test;";
-    let result = block_on(locustdb.run_query(query, true, vec![]))
+    let result = block_on(locustdb.run_query(query, true, vec![], None))
         .unwrap()
         .unwrap();
     assert_eq!(result.rows.iter().filter(|&x| x == &[Null]).count(), 13);
@@ -1064,7 +1898,7 @@ fn test_group_by_string() {
     }));
 
     let query = "SELECT scrambled, count(1) FROM test LIMIT 5;";
-    let result = block_on(locustdb.run_query(query, true, vec![]))
+    let result = block_on(locustdb.run_query(query, true, vec![], None))
         .unwrap()
         .unwrap();
     let expected_rows = vec![
@@ -1077,7 +1911,7 @@ fn test_group_by_string() {
     assert_eq!(result.rows, expected_rows);
 
     let query = "SELECT scrambled, scrambled, count(1) FROM test LIMIT 5;";
-    let result = block_on(locustdb.run_query(query, true, vec![]))
+    let result = block_on(locustdb.run_query(query, true, vec![], None))
         .unwrap()
         .unwrap();
     let expected_rows = vec![
@@ -1090,7 +1924,7 @@ fn test_group_by_string() {
     assert_eq!(result.rows, expected_rows);
 
     let query = "SELECT hex, scrambled, count(1) FROM test LIMIT 5;";
-    let result = block_on(locustdb.run_query(query, true, vec![]))
+    let result = block_on(locustdb.run_query(query, true, vec![], None))
         .unwrap()
         .unwrap();
     let expected_rows = vec![
@@ -1103,7 +1937,7 @@ fn test_group_by_string() {
     assert_eq!(result.rows, expected_rows);
 
     let query = "SELECT ints, scrambled, count(1) FROM test LIMIT 5;";
-    let result = block_on(locustdb.run_query(query, true, vec![]))
+    let result = block_on(locustdb.run_query(query, true, vec![], None))
         .unwrap()
         .unwrap();
     let expected_rows = vec![
@@ -1156,7 +1990,7 @@ fn test_restore_from_disk() {
     thread::sleep(time::Duration::from_millis(2000));
     let locustdb = LocustDB::new(&opts);
     let query = "select passenger_count, to_year(pickup_datetime), trip_distance / 1000, count(0) from default;";
-    let result = block_on(locustdb.run_query(query, false, vec![])).unwrap();
+    let result = block_on(locustdb.run_query(query, false, vec![], None)).unwrap();
     let actual_rows = result.unwrap().rows;
     use Value::*;
     assert_eq!(
@@ -1192,4 +2026,1750 @@ fn test_colnames() {
         "SELECT u8_offset_encoded FROM default WHERE u8_offset_encoded = 256;",
         vec!["u8_offset_encoded".to_string()],
     );
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_coltypes() {
+    test_query_coltypes(
+        "SELECT id, float, enum, nullable_int, id > 3 FROM default;",
+        &["integer", "float", "string", "integer", "boolean"],
+    );
+    test_query_coltypes(
+        "SELECT enum, SUM(id), COUNT(0) FROM default;",
+        &["string", "integer", "integer"],
+    );
+    test_query_coltypes("SELECT id FROM default WHERE id > 100;", &["integer"]);
+}
+
+fn test_query_streaming(query: &str) -> (Vec<String>, Vec<Vec<Value>>) {
+    use futures::StreamExt;
+    let _ = env_logger::try_init();
+    let locustdb = LocustDB::new(&Options::default());
+    let _ = block_on(
+        locustdb.load_csv(
+            LoadOptions::new("test_data/edge_cases.csv", "default")
+                .with_partition_size(3)
+                .allow_nulls_all_columns(),
+        ),
+    );
+    let stream = locustdb.run_query_streaming(query, None).unwrap();
+    let rows = block_on(stream.rows.collect::<Vec<_>>());
+    let output = block_on(stream.result).unwrap().unwrap();
+    assert!(output.rows.is_empty());
+    (stream.colnames, rows)
+}
+
+#[test]
+fn test_streaming() {
+    let (colnames, mut rows) = test_query_streaming("SELECT id, enum FROM default;");
+    assert_eq!(colnames, vec!["id".to_string(), "enum".to_string()]);
+    rows.sort();
+    assert_eq!(rows.len(), 10);
+    assert_eq!(rows[0], vec![Int(0), Str("aa")]);
+    assert_eq!(rows[9], vec![Int(9), Str("bb")]);
+
+    let (_, rows) = test_query_streaming("SELECT id FROM default LIMIT 4 OFFSET 3;");
+    assert_eq!(rows.len(), 4);
+
+    let (_, rows) = test_query_streaming("SELECT enum, COUNT(0) FROM default ORDER BY COUNT(0) DESC;");
+    assert_eq!(
+        rows,
+        vec![
+            vec![Str("aa"), Int(5)],
+            vec![Str("bb"), Int(3)],
+            vec![Str("cc"), Int(2)],
+        ]
+    );
+}
+
+#[test]
+fn test_drop_table() {
+    let _ = env_logger::try_init();
+    let locustdb = LocustDB::new(&Options::default());
+    let _ = block_on(locustdb.load_csv(LoadOptions::new("test_data/tiny.csv", "default")));
+    assert!(block_on(locustdb.table_exists("default")));
+    assert!(block_on(locustdb.drop_table("default")).unwrap());
+    assert!(!block_on(locustdb.drop_table("default")).unwrap());
+    assert!(!block_on(locustdb.table_exists("default")));
+    assert!(block_on(locustdb.run_query("SELECT COUNT(0) FROM default;", false, vec![], None))
+        .unwrap()
+        .is_err());
+    let result = block_on(locustdb.run_query(
+        "SELECT name, dropped FROM _meta_tables;",
+        false,
+        vec![],
+        None,
+    ))
+    .unwrap()
+    .unwrap();
+    let mut rows = result.rows;
+    rows.sort();
+    assert_eq!(
+        rows,
+        vec![
+            vec![Str("_meta_tables"), Null],
+            vec![Str("default"), Int(1)],
+            vec![Str("default"), Null],
+        ]
+    );
+
+    block_on(locustdb.ingest("default", vec![vec![("a".to_string(), Int(1))]])).unwrap();
+    let result = block_on(locustdb.run_query("SELECT a FROM default;", false, vec![], None))
+        .unwrap()
+        .unwrap();
+    assert_eq!(result.rows, vec![vec![Int(1)]]);
+}
+
+#[test]
+fn test_partition_pruning() {
+    test_query_ec(
+        "SELECT id FROM default WHERE id >= 3 AND id < 6 OR 8 < id ORDER BY id;",
+        &[vec![Int(3)], vec![Int(4)], vec![Int(5)], vec![Int(9)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE id = 4 OR negative = -199 ORDER BY id;",
+        &[vec![Int(0)], vec![Int(4)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE nullable_int > 15;",
+        &[vec![Int(7)]],
+    );
+    test_query_ec(
+        "SELECT enum, COUNT(0) FROM default WHERE id > -1 AND id <= 2;",
+        &[vec![Str("aa"), Int(3)]],
+    );
+    test_query_ec(
+        "SELECT COUNT(0) FROM default WHERE id > 100;",
+        &[],
+    );
+}
+
+#[test]
+fn test_coalesce() {
+    test_query_ec(
+        "SELECT id, COALESCE(nullable_int, nullable_int2) FROM default WHERE id < 4 ORDER BY id;",
+        &[
+            vec![Int(0), Int(-1)],
+            vec![Int(1), Int(-40)],
+            vec![Int(2), Null],
+            vec![Int(3), Int(0)],
+        ],
+    );
+    test_query_ec(
+        "SELECT id, COALESCE(nullable_int, nullable_int2, id) FROM default WHERE id < 4 ORDER BY id;",
+        &[
+            vec![Int(0), Int(-1)],
+            vec![Int(1), Int(-40)],
+            vec![Int(2), Int(2)],
+            vec![Int(3), Int(0)],
+        ],
+    );
+    test_query_ec(
+        "SELECT id, COALESCE(nullable_int, 100) FROM default WHERE id < 4 ORDER BY id;",
+        &[
+            vec![Int(0), Int(-1)],
+            vec![Int(1), Int(-40)],
+            vec![Int(2), Int(100)],
+            vec![Int(3), Int(100)],
+        ],
+    );
+    test_query_ec_err(
+        "SELECT COALESCE(nullable_int, 'a') FROM default;",
+        QueryError::TypeError(String::new()),
+    );
+}
+
+#[test]
+fn test_case_when() {
+    test_query_ec(
+        "SELECT id, CASE WHEN id > 5 THEN 'high' ELSE 'low' END FROM default WHERE id > 3 AND id < 8 ORDER BY id;",
+        &[
+            vec![Int(4), Str("low")],
+            vec![Int(5), Str("low")],
+            vec![Int(6), Str("high")],
+            vec![Int(7), Str("high")],
+        ],
+    );
+    test_query_ec(
+        "SELECT id, CASE WHEN id < 2 THEN 1 WHEN id < 4 THEN id * 10 END FROM default WHERE id < 6 ORDER BY id;",
+        &[
+            vec![Int(0), Int(1)],
+            vec![Int(1), Int(1)],
+            vec![Int(2), Int(20)],
+            vec![Int(3), Int(30)],
+            vec![Int(4), Null],
+            vec![Int(5), Null],
+        ],
+    );
+    test_query_ec(
+        "SELECT id, CASE WHEN nullable_int > 0 THEN 'positive' ELSE 'other' END FROM default WHERE id < 5 ORDER BY id;",
+        &[
+            vec![Int(0), Str("other")],
+            vec![Int(1), Str("other")],
+            vec![Int(2), Str("other")],
+            vec![Int(3), Str("other")],
+            vec![Int(4), Str("positive")],
+        ],
+    );
+    test_query_ec(
+        "SELECT CASE enum WHEN 'aa' THEN 'a' ELSE 'b' END AS e, COUNT(0) FROM default;",
+        &[vec![Str("a"), Int(5)], vec![Str("b"), Int(5)]],
+    );
+    test_query_ec_err(
+        "SELECT CASE WHEN id > 5 THEN 1 ELSE 'a' END FROM default;",
+        QueryError::TypeError(String::new()),
+    );
+}
+
+#[test]
+fn test_table_batch_size() {
+    let _ = env_logger::try_init();
+    let mut opts = Options::default();
+    opts.table_batch_sizes.insert("configured".to_string(), 3);
+    let locustdb = LocustDB::new(&opts);
+    assert!(block_on(locustdb.create_table("created", Some(2))));
+    assert!(!block_on(locustdb.create_table("created", Some(100))));
+    for table in &["created", "configured", "default"] {
+        let rows = (0..5).map(|i| vec![("a".to_string(), Int(i))]).collect();
+        block_on(locustdb.ingest(table, rows)).unwrap();
+    }
+    let stats = block_on(locustdb.table_stats()).unwrap();
+    // Buffered rows are counted as an additional batch
+    let batches = |name: &str| {
+        let table = stats.iter().find(|t| t.name == name).unwrap();
+        (table.batches, table.buffer_length)
+    };
+    assert_eq!(batches("created"), (3, 1));
+    assert_eq!(batches("configured"), (2, 2));
+    assert_eq!(batches("default"), (1, 5));
+}
+
+#[test]
+fn test_max_result_rows() {
+    use futures::StreamExt;
+    let _ = env_logger::try_init();
+    let opts = Options {
+        max_result_rows: Some(5),
+        ..Options::default()
+    };
+    let locustdb = LocustDB::new(&opts);
+    let _ = block_on(
+        locustdb.load_csv(
+            LoadOptions::new("test_data/edge_cases.csv", "default")
+                .with_partition_size(3)
+                .allow_nulls_all_columns(),
+        ),
+    );
+    let run = |query: &str| block_on(locustdb.run_query(query, false, vec![], None)).unwrap();
+    for query in &[
+        "SELECT id FROM default LIMIT 100000000;",
+        "SELECT id FROM default ORDER BY id;",
+        "SELECT id, COUNT(0) FROM default;",
+        "SELECT id, COUNT(DISTINCT enum) FROM default LIMIT 1000;",
+    ] {
+        match run(query) {
+            Err(QueryError::ResultTooLarge(5)) => {}
+            result => panic!("Expected ResultTooLarge for {}, got {:?}", query, result.map(|r| r.rows)),
+        }
+    }
+    assert_eq!(run("SELECT id FROM default LIMIT 5;").unwrap().rows.len(), 5);
+    assert_eq!(run("SELECT id FROM default ORDER BY id LIMIT 5 OFFSET 8;").unwrap().rows.len(), 2);
+    assert_eq!(run("SELECT enum, COUNT(0) FROM default;").unwrap().rows.len(), 3);
+
+    let stream = locustdb.run_query_streaming("SELECT id FROM default;", None).unwrap();
+    let rows = block_on(stream.rows.collect::<Vec<_>>());
+    assert!(rows.len() <= 5);
+    assert!(matches!(block_on(stream.result).unwrap(), Err(QueryError::ResultTooLarge(5))));
+}
+
+#[test]
+fn test_query_history() {
+    let _ = env_logger::try_init();
+    let locustdb = LocustDB::new(&Options::default());
+    let history = block_on(locustdb.query_history(10)).unwrap().unwrap();
+    assert_eq!(history.rows.len(), 0);
+
+    let _ = block_on(
+        locustdb.load_csv(
+            LoadOptions::new("test_data/edge_cases.csv", "default")
+                .with_partition_size(3)
+                .allow_nulls_all_columns(),
+        ),
+    );
+    let first = "SELECT id FROM default WHERE id < 3;";
+    let second = "SELECT COUNT(0) FROM default;";
+    block_on(locustdb.run_query(first, false, vec![], None)).unwrap().unwrap();
+    block_on(locustdb.run_query(second, false, vec![], None)).unwrap().unwrap();
+    // Failed queries are not recorded
+    assert!(block_on(locustdb.run_query("SELECT id FROM missing;", false, vec![], None)).unwrap().is_err());
+
+    // Queries are recorded asynchronously
+    let mut history = block_on(locustdb.query_history(10)).unwrap().unwrap();
+    for _ in 0..100 {
+        if history.rows.len() == 2 {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        history = block_on(locustdb.query_history(10)).unwrap().unwrap();
+    }
+    assert_eq!(history.colnames, vec!["timestamp_ns", "query", "runtime_ns", "rows"]);
+    assert_eq!(history.rows.len(), 2);
+    let recorded = history.rows.iter().map(|row| (row[1].clone(), row[3].clone())).collect::<Vec<_>>();
+    assert_eq!(recorded, vec![(Str(second), Int(1)), (Str(first), Int(3))]);
+    assert_eq!(block_on(locustdb.query_history(1)).unwrap().unwrap().rows.len(), 1);
+}
+
+#[test]
+fn test_multi_column_top_n() {
+    test_query(
+        "SELECT tld, ts FROM default ORDER BY tld DESC, ts LIMIT 4;",
+        &[
+            vec![Str("org"), Int(1_457_993_744)],
+            vec![Str("org"), Int(1_458_931_180)],
+            vec![Str("org"), Int(1_462_244_218)],
+            vec![Str("org"), Int(1_468_675_315)],
+        ],
+    );
+    test_query(
+        "SELECT tld, ts FROM default ORDER BY tld, ts DESC LIMIT 3 OFFSET 7;",
+        &[
+            vec![Str(""), Int(1_456_591_230)],
+            vec![Str("biz"), Int(1_474_989_645)],
+            vec![Str("biz"), Int(1_472_460_792)],
+        ],
+    );
+}
+
+#[test]
+fn test_float_literals_in_filter() {
+    test_query_ec(
+        "SELECT id FROM default WHERE float > 0.5 ORDER BY id;",
+        &[vec![Int(3)], vec![Int(4)], vec![Int(9)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE float < -0.5 ORDER BY id;",
+        &[vec![Int(2)], vec![Int(8)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE float <= 1e-6 AND float >= -1.0 ORDER BY id;",
+        &[vec![Int(5)], vec![Int(6)], vec![Int(7)], vec![Int(8)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE id > 7.5 ORDER BY id;",
+        &[vec![Int(8)], vec![Int(9)]],
+    );
+}
+
+#[test]
+fn test_float_nan_and_infinity() {
+    // Multiplication with infinity yields inf for ids 0, 1, 3, 4, 5, 7, 9, -inf for ids 2, 8 and NaN for id 6
+    test_query_ec(
+        "SELECT id FROM default WHERE float * EXP(1000.0) < 0 ORDER BY id;",
+        &[vec![Int(2)], vec![Int(8)]],
+    );
+    test_query_ec(
+        "SELECT COUNT(0) FROM default WHERE float * EXP(1000.0) >= 0;",
+        &[vec![Int(7)]],
+    );
+    test_query_ec(
+        "SELECT COUNT(0) FROM default WHERE float * EXP(1000.0) = float * EXP(1000.0);",
+        &[vec![Int(9)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE float * EXP(1000.0) <> float * EXP(1000.0);",
+        &[],
+    );
+    test_query_ec(
+        "SELECT COUNT(0) FROM default WHERE float * EXP(1000.0) <> 1.5 AND float * EXP(1000.0) <> id;",
+        &[vec![Int(9)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE float = 3.15159 OR float <= -124 ORDER BY id;",
+        &[vec![Int(2)], vec![Int(3)]],
+    );
+    test_query_ec(
+        "SELECT id, FLOOR(float * EXP(1000.0)), CEIL(float * -10.0), ROUND(float * EXP(1000.0)) FROM default WHERE id IN (1, 2, 6) ORDER BY id;",
+        &[
+            vec![Int(1), Int(i64::MAX), Int(0), Int(i64::MAX)],
+            vec![Int(2), Int(-i64::MAX), Int(1240), Int(-i64::MAX)],
+            vec![Int(6), Int(0), Int(0), Int(0)],
+        ],
+    );
+
+    // Division and modulo by zero yield NULL instead of inf or NaN
+    test_query_ec(
+        "SELECT COUNT(0) FROM default WHERE float / 0.0 IS NULL AND float % 0.0 IS NULL AND float / 0 IS NULL AND id / 0.0 IS NULL;",
+        &[vec![Int(10)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE float / 0.0 < 0 OR float / 0.0 >= 0 OR float % -0.0 <> 1;",
+        &[],
+    );
+    test_query_ec(
+        "SELECT id, 1.5 / float, 7 % float, id / (float * 0.0) FROM default WHERE id IN (2, 6) ORDER BY id;",
+        &[
+            vec![Int(2), Float(OrderedFloat(1.5 / -124.0)), Float(OrderedFloat(7.0)), Null],
+            vec![Int(6), Null, Null, Null],
+        ],
+    );
+    let locustdb = LocustDB::new(&Options::default());
+    let _ = block_on(
+        locustdb.load_csv(
+            LoadOptions::new("test_data/edge_cases.csv", "default")
+                .with_partition_size(3)
+                .allow_nulls_all_columns(),
+        ),
+    );
+    let result = block_on(locustdb.run_query("SELECT float / 0, 1.0 % float FROM default;", false, vec![], None))
+        .unwrap()
+        .unwrap();
+    assert_eq!(result.coltypes, vec!["float".to_string(), "float".to_string()]);
+    assert_eq!(result.rows.len(), 10);
+    assert!(result.rows.iter().all(|row| row[0] == Null));
+}
+
+#[test]
+fn test_schema_evolution() {
+    let _ = env_logger::try_init();
+    let locustdb = LocustDB::new(&Options::default());
+    block_on(locustdb.create_table("evolving", Some(2)));
+    // Partitions [0, 1], [2, 3] and [4, 5], later rows have additional columns
+    let rows = (0..6)
+        .map(|i| {
+            let mut row = vec![("id".to_string(), Int(i))];
+            if i >= 3 {
+                row.push(("extra".to_string(), Int(i * 10)));
+            }
+            if i >= 4 {
+                row.push(("name".to_string(), Value::Str(format!("n{}", i))));
+                row.push(("score".to_string(), Float(OrderedFloat(i as f64 / 2.0))));
+            }
+            row
+        })
+        .collect();
+    block_on(locustdb.ingest("evolving", rows)).unwrap();
+    let query = |query: &str| {
+        block_on(locustdb.run_query(query, false, vec![], None))
+            .unwrap()
+            .unwrap()
+            .rows
+    };
+    assert_eq!(
+        query("SELECT id, extra, name, score FROM evolving ORDER BY id;"),
+        vec![
+            vec![Int(0), Null, Null, Null],
+            vec![Int(1), Null, Null, Null],
+            vec![Int(2), Null, Null, Null],
+            vec![Int(3), Int(30), Null, Null],
+            vec![Int(4), Int(40), Str("n4"), Float(OrderedFloat(2.0))],
+            vec![Int(5), Int(50), Str("n5"), Float(OrderedFloat(2.5))],
+        ]
+    );
+    let mut rows = query("SELECT id, score FROM evolving;");
+    rows.sort();
+    assert_eq!(rows[5], vec![Int(5), Float(OrderedFloat(2.5))]);
+    assert_eq!(
+        query("SELECT SUM(extra), COUNT(extra), MAX(score) FROM evolving;"),
+        vec![vec![Int(120), Int(3), Float(OrderedFloat(2.5))]]
+    );
+    let mut rows = query("SELECT name, COUNT(0) FROM evolving;");
+    rows.sort();
+    assert_eq!(rows, vec![vec![Str("n4"), Int(1)], vec![Str("n5"), Int(1)], vec![Null, Int(4)]]);
+    assert_eq!(
+        query("SELECT id, extra + 1 FROM evolving WHERE extra IS NULL ORDER BY id DESC;"),
+        vec![vec![Int(2), Null], vec![Int(1), Null], vec![Int(0), Null]]
+    );
+    assert_eq!(
+        query("SELECT id, extra + 1 FROM evolving WHERE extra > 35 ORDER BY id;"),
+        vec![vec![Int(4), Int(41)], vec![Int(5), Int(51)]]
+    );
+}
+
+#[test]
+fn test_sqrt_ln_log10_exp() {
+    let f = |x: f64| Float(OrderedFloat(x));
+    test_query_ec(
+        "SELECT id, sqrt(negative), ln(float), log10(float), exp(id) FROM default WHERE id IN (2, 3, 6, 8) ORDER BY id;",
+        &[
+            vec![Int(2), Null, Null, Null, f(2f64.exp())],
+            vec![Int(3), f(34f64.sqrt()), f(3.15159f64.ln()), f(3.15159f64.log10()), f(3f64.exp())],
+            vec![Int(6), Null, f(f64::NEG_INFINITY), f(f64::NEG_INFINITY), f(6f64.exp())],
+            vec![Int(8), f(4010f64.sqrt()), Null, Null, f(8f64.exp())],
+        ],
+    );
+    test_query_ec(
+        "SELECT id, sqrt(nullable_int), exp(nullable_int) FROM default WHERE id IN (1, 2, 7) ORDER BY id;",
+        &[
+            vec![Int(1), Null, f((-40f64).exp())],
+            vec![Int(2), Null, Null],
+            vec![Int(7), f(20f64.sqrt()), f(20f64.exp())],
+        ],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE sqrt(id) = 3 OR log10(id * 10) = 2 OR exp(id) < 2 ORDER BY id;",
+        &[vec![Int(0)], vec![Int(9)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE sqrt(negative) > 6 AND negative < sqrt(16129) ORDER BY id;",
+        &[vec![Int(1)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE negative > exp(0) * 4000 AND ln(-1) IS NULL ORDER BY id;",
+        &[vec![Int(4)], vec![Int(8)]],
+    );
+    test_query_ec_err(
+        "SELECT sqrt(enum) FROM default;",
+        QueryError::TypeError("Found Sqrt(String), expected Sqrt(integer) or Sqrt(float)".to_string()),
+    );
+}
+
+#[test]
+fn test_pow() {
+    let f = |x: f64| Float(OrderedFloat(x));
+    test_query_ec(
+        "SELECT id, POW(2, 10) + id, POW(2.0, 0.5) * id, POWER(id, 2), POW(non_dense_ints, id), POW(-1, -id) FROM default WHERE id IN (0, 1, 3) ORDER BY id;",
+        &[
+            vec![Int(0), Int(1024), f(0.0), Int(0), Int(1), Int(1)],
+            vec![Int(1), Int(1025), f(2f64.sqrt()), Int(1), Int(2), Int(-1)],
+            vec![Int(3), Int(1027), f(2f64.sqrt() * 3.0), Int(9), Int(1), Int(-1)],
+        ],
+    );
+    test_query_ec(
+        "SELECT id, POW(float, 2), POW(id, 0.5), POW(nullable_int, 2), POW(id, -1) FROM default WHERE id IN (2, 4, 7) ORDER BY id;",
+        &[
+            vec![Int(2), f(15376.0), f(2f64.sqrt()), Null, Int(0)],
+            vec![Int(4), f(0.1234e30f64.powf(2.0)), f(2.0), Int(100), Int(0)],
+            vec![Int(7), f(0.000001f64.powf(2.0)), f(7f64.sqrt()), Int(400), Int(0)],
+        ],
+    );
+    test_query_ec_err("SELECT POW(largenum, 2) FROM default;", QueryError::Overflow);
+    test_query_ec_err(
+        "SELECT POW(id) FROM default;",
+        QueryError::ParseError("Expected two arguments in POW function".to_string()),
+    );
+}
+
+#[test]
+fn test_variance_stddev() {
+    let f = |x: f64| Float(OrderedFloat(x));
+    let var_nullable_int = 2270.0 / 5.0 - (2.0 / 5.0) * (2.0 / 5.0);
+    test_query_ec(
+        "SELECT VAR(id), STDDEV(id), VAR(nullable_int), STDDEV_POP(nullable_int) FROM default;",
+        &[vec![f(8.25), f(8.25f64.sqrt()), f(var_nullable_int), f(var_nullable_int.sqrt())]],
+    );
+    // Groups with a single value have zero variance, groups without values have null variance
+    test_query_ec(
+        "SELECT id, VAR(nullable_int), STDDEV(float) FROM default WHERE id < 3;",
+        &[
+            vec![Int(0), f(0.0), f(0.0)],
+            vec![Int(1), f(0.0), f(0.0)],
+            vec![Int(2), Null, f(0.0)],
+        ],
+    );
+    test_query_ec(
+        "SELECT enum, COUNT(0) FROM default HAVING STDDEV(id) > 2;",
+        &[vec![Str("aa"), Int(5)], vec![Str("bb"), Int(3)]],
+    );
+    test_query_ec(
+        "SELECT id, float + id, 0.5 - float FROM default WHERE id IN (2, 3) ORDER BY id;",
+        &[vec![Int(2), f(-122.0), f(124.5)], vec![Int(3), f(3.15159 + 3.0), f(0.5 - 3.15159)]],
+    );
+}
+
+#[test]
+fn test_show_tables_and_columns() {
+    let locustdb = LocustDB::new(&Options::default());
+    let rows = vec![
+        vec![("b".to_string(), Int(1)), ("a".to_string(), Value::Str("x".to_string()))],
+        vec![("b".to_string(), Value::Str("y".to_string()))],
+    ];
+    block_on(locustdb.ingest("show", rows)).unwrap();
+    let run = |query: &str| block_on(locustdb.run_query(query, false, vec![], None)).unwrap().unwrap();
+    let tables = run("SHOW TABLES;");
+    assert_eq!(tables.colnames, vec!["table".to_string()]);
+    assert!(tables.rows.contains(&vec![Value::Str("show".to_string())]));
+    assert!(!run("SHOW TABLES;").rows.contains(&vec![Value::Str("later".to_string())]));
+    block_on(locustdb.create_table("later", None));
+    // Not served from the result cache after the set of tables has changed
+    assert!(run("SHOW TABLES;").rows.contains(&vec![Value::Str("later".to_string())]));
+    let columns = run("SHOW COLUMNS FROM show;");
+    assert_eq!(columns.colnames, vec!["column".to_string(), "types".to_string()]);
+    assert_eq!(
+        columns.rows,
+        vec![
+            vec![Value::Str("a".to_string()), Value::Str("NullableStr".to_string())],
+            vec![Value::Str("b".to_string()), Value::Str("Str".to_string())],
+        ]
+    );
+    assert!(block_on(locustdb.run_query("SHOW COLUMNS FROM missing", false, vec![], None)).unwrap().is_err());
+}
+
+#[test]
+fn test_ingest_invalid_rows() {
+    let locustdb = LocustDB::new(&Options::default());
+    let rows = vec![
+        vec![("a".to_string(), Int(1))],
+        vec![("a".to_string(), Int(2)), ("a".to_string(), Int(3))],
+        vec![("".to_string(), Int(4))],
+    ];
+    assert_eq!(
+        block_on(locustdb.ingest("invalid", rows)),
+        Err(vec![
+            (1, IngestError::DuplicateColumn("a".to_string())),
+            (2, IngestError::EmptyColumnName),
+        ])
+    );
+    // No rows of a batch with invalid rows are ingested
+    assert!(!block_on(locustdb.table_exists("invalid")));
+    let dedup_on = ["a".to_string()];
+    let rows = vec![vec![("a".to_string(), Int(1)), ("a".to_string(), Int(1))]];
+    assert_eq!(
+        block_on(locustdb.ingest_dedup("invalid", rows, &dedup_on)),
+        Err(vec![(0, IngestError::DuplicateColumn("a".to_string()))])
+    );
+}
+
+#[test]
+fn test_ingest_dedup() {
+    let locustdb = LocustDB::new(&Options::default());
+    block_on(locustdb.create_table("dedup", Some(4)));
+    let rows = |ids: &[i64]| {
+        ids.iter()
+            .map(|&id| vec![("id".to_string(), Int(id)), ("value".to_string(), Int(id * 10))])
+            .collect::<Vec<_>>()
+    };
+    let dedup_on = ["id".to_string()];
+    assert_eq!(block_on(locustdb.ingest_dedup("dedup", rows(&[0, 1, 1]), &dedup_on)), Ok(1));
+    // Retried batch only contains buffered keys
+    assert_eq!(block_on(locustdb.ingest_dedup("dedup", rows(&[0, 1, 1]), &dedup_on)), Ok(3));
+    // Fills the buffer, which turns it into a partition
+    assert_eq!(block_on(locustdb.ingest_dedup("dedup", rows(&[1, 2, 3]), &dedup_on)), Ok(1));
+    // Keys of rows that are no longer buffered are not checked
+    assert_eq!(block_on(locustdb.ingest_dedup("dedup", rows(&[2, 3]), &dedup_on)), Ok(0));
+    // Rows without key column have a null key, which never matches another key
+    let keyless = vec![vec![("value".to_string(), Int(-1))], vec![("value".to_string(), Int(-2))]];
+    assert_eq!(block_on(locustdb.ingest_dedup("dedup", keyless, &dedup_on)), Ok(0));
+    let null_key = vec![vec![("id".to_string(), Null), ("value".to_string(), Int(-3))]];
+    assert_eq!(block_on(locustdb.ingest_dedup("dedup", null_key, &dedup_on)), Ok(0));
+    let result = block_on(locustdb.run_query("SELECT id, value FROM dedup ORDER BY value;", false, vec![], None))
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        result.rows,
+        vec![
+            vec![Null, Int(-3)],
+            vec![Null, Int(-2)],
+            vec![Null, Int(-1)],
+            vec![Int(0), Int(0)],
+            vec![Int(1), Int(10)],
+            vec![Int(2), Int(20)],
+            vec![Int(2), Int(20)],
+            vec![Int(3), Int(30)],
+            vec![Int(3), Int(30)],
+        ]
+    );
+}
+
+#[test]
+fn test_ingest_dedup_float_keys() {
+    let locustdb = LocustDB::new(&Options::default());
+    let row = |k: Value| vec![vec![("k".to_string(), k)]];
+    let float = |f: f64| Float(OrderedFloat(f));
+    let dedup_on = ["k".to_string()];
+    assert_eq!(block_on(locustdb.ingest_dedup("ints_first", row(Int(1)), &dedup_on)), Ok(0));
+    // Buffered integers are converted to floats once the column receives a float
+    assert_eq!(block_on(locustdb.ingest_dedup("ints_first", row(float(1.0)), &dedup_on)), Ok(1));
+    assert_eq!(block_on(locustdb.ingest_dedup("ints_first", row(float(1.5)), &dedup_on)), Ok(0));
+    assert_eq!(block_on(locustdb.ingest_dedup("ints_first", row(Int(1)), &dedup_on)), Ok(1));
+    assert_eq!(block_on(locustdb.ingest_dedup("floats_first", row(float(1.0)), &dedup_on)), Ok(0));
+    assert_eq!(block_on(locustdb.ingest_dedup("floats_first", row(Int(1)), &dedup_on)), Ok(1));
+    for table in ["ints_first", "floats_first"] {
+        let query = format!("SELECT COUNT(0) FROM {};", table);
+        let result = block_on(locustdb.run_query(&query, false, vec![], None)).unwrap().unwrap();
+        let expected = if table == "ints_first" { 2 } else { 1 };
+        assert_eq!(result.rows, vec![vec![Int(expected)]]);
+    }
+}
+
+#[test]
+fn test_aggregate_column_arithmetic() {
+    test_query_ec(
+        "SELECT SUM(negative * id), SUM(negative + id) FROM default;",
+        &[vec![Int(46325), Int(7602)]],
+    );
+    test_query_ec(
+        "SELECT enum, SUM(negative * id), SUM(id + u8_offset_encoded) FROM default GROUP BY enum ORDER BY enum;",
+        &[
+            vec![Str("aa"), Int(-841), Int(1720)],
+            vec![Str("bb"), Int(15866), Int(1048)],
+            vec![Str("cc"), Int(31300), Int(868)],
+        ],
+    );
+    test_query_ec(
+        "SELECT MAX(negative * id), MIN(id - negative), SUM(nullable_int * id) FROM default;",
+        &[vec![Int(32080), Int(-4027), Int(257)]],
+    );
+    test_query_ec(
+        "SELECT enum, MAX(float * id), MIN(negative + id) FROM default GROUP BY enum ORDER BY enum;",
+        &[
+            vec![Str("aa"), Float(OrderedFloat(0.0003)), Int(-199)],
+            vec![Str("bb"), Float(OrderedFloat(4.936e29)), Int(-31)],
+            vec![Str("cc"), Float(OrderedFloat(0.0)), Int(-124)],
+        ],
+    );
+}
+
+#[test]
+fn test_query_builder() {
+    let locustdb = LocustDB::new(&Options::default());
+    let _ = block_on(locustdb.load_csv(
+        LoadOptions::new("test_data/edge_cases.csv", "default")
+            .with_partition_size(3)
+            .allow_nulls_all_columns(),
+    ));
+    let sql = block_on(locustdb.run_query(
+        "SELECT enum, COUNT(*), SUM(id) FROM default WHERE negative < 0 AND id > 2 GROUP BY enum ORDER BY enum DESC LIMIT 2;",
+        false,
+        vec![],
+        None,
+    ))
+    .unwrap()
+    .unwrap();
+    let built = block_on(
+        locustdb
+            .select(&["enum"])
+            .column(Expr::count(), "count")
+            .column(Expr::sum(Expr::col("id")), "sum")
+            .from("default")
+            .filter(Expr::func(Func2Type::LT, Expr::col("negative"), Expr::lit(0)))
+            .filter(Expr::func(Func2Type::GT, Expr::col("id"), Expr::lit(2)))
+            .group_by(&["enum"])
+            .order_by(Expr::col("enum"), true)
+            .limit(2)
+            .execute(),
+    )
+    .unwrap()
+    .unwrap();
+    assert!(!built.rows.is_empty());
+    assert_eq!(built.rows, sql.rows);
+    assert_eq!(built.colnames, vec!["enum", "count", "sum"]);
+
+    let offset = block_on(locustdb.select(&["id"]).from("default").order_by(Expr::col("id"), false).offset(7).execute())
+        .unwrap()
+        .unwrap();
+    assert_eq!(offset.rows, vec![vec![Int(7)], vec![Int(8)], vec![Int(9)]]);
+
+    assert!(matches!(locustdb.select(&["id"]).build(), Err(QueryError::ParseError(_))));
+    assert!(matches!(
+        locustdb.select(&["id"]).column(Expr::count(), "count").from("default").group_by(&["enum"]).build(),
+        Err(QueryError::NotImplemented(_))
+    ));
+    let missing = block_on(locustdb.select(&["id"]).from("missing").execute()).unwrap();
+    assert!(matches!(missing, Err(QueryError::NotImplemented(_))));
+}
+
+#[test]
+fn test_query_builder_expression_names() {
+    let locustdb = LocustDB::new(&Options::default());
+    let _ = block_on(locustdb.load_csv(LoadOptions::new("test_data/edge_cases.csv", "default").with_partition_size(3)));
+    let result = block_on(
+        locustdb
+            .select(&["enum"])
+            .expr(Expr::func(Func2Type::Multiply, Expr::sum(Expr::col("id")), Expr::lit(2)))
+            .expr(Expr::func1(Func1Type::Length, Expr::col("enum")))
+            .from("default")
+            .order_by(Expr::col("enum"), false)
+            .execute(),
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(result.colnames, vec!["enum", "SUM(id) * 2", "LENGTH(enum)"]);
+    assert_eq!(
+        result.rows,
+        vec![
+            vec![Str("aa"), Int(30), Int(2)],
+            vec![Str("bb"), Int(32), Int(2)],
+            vec![Str("cc"), Int(28), Int(2)],
+        ]
+    );
+}
+
+#[test]
+fn test_union_all() {
+    let locustdb = LocustDB::new(&Options::default());
+    for table in ["shard1", "shard2"] {
+        let _ = block_on(locustdb.load_csv(
+            LoadOptions::new("test_data/edge_cases.csv", table)
+                .with_partition_size(3)
+                .allow_nulls_all_columns(),
+        ));
+    }
+    let query = |query: &str| block_on(locustdb.run_query(query, false, vec![], None)).unwrap();
+    let rows = |query_str: &str| query(query_str).unwrap().rows;
+
+    assert_eq!(
+        rows("SELECT COUNT(*) FROM shard1 UNION ALL SELECT COUNT(*) FROM shard2 WHERE id < 3;"),
+        vec![vec![Int(10)], vec![Int(3)]]
+    );
+    assert_eq!(
+        rows("SELECT id, country FROM shard1 WHERE id < 2 UNION ALL SELECT id, country FROM shard2 WHERE id >= 8 ORDER BY id DESC;"),
+        vec![
+            vec![Int(9), Str("Germany")],
+            vec![Int(8), Null],
+            vec![Int(1), Str("USA")],
+            vec![Int(0), Str("Germany")],
+        ]
+    );
+    assert_eq!(
+        rows("SELECT country AS c, id FROM shard1 UNION ALL SELECT country, id + 10 FROM shard2 UNION ALL SELECT country, id + 20 FROM shard1 ORDER BY c, id LIMIT 4 OFFSET 12;"),
+        vec![
+            vec![Str("Turkey"), Int(6)],
+            vec![Str("Turkey"), Int(16)],
+            vec![Str("Turkey"), Int(26)],
+            vec![Str("USA"), Int(1)],
+        ]
+    );
+    let output = query("SELECT id FROM shard1 WHERE id < 1 UNION ALL SELECT enum FROM shard2 WHERE id < 1;").unwrap();
+    assert_eq!(output.colnames, vec!["id"]);
+    assert_eq!(output.coltypes, vec!["mixed"]);
+    assert_eq!(output.rows, vec![vec![Int(0)], vec![Str("aa")]]);
+
+    assert!(matches!(query("SELECT id FROM shard1 UNION SELECT id FROM shard2;"), Err(QueryError::NotImplemented(_))));
+    assert!(matches!(query("SELECT id FROM shard1 UNION ALL SELECT id, country FROM shard2;"), Err(QueryError::TypeError(_))));
+    assert!(matches!(query("SELECT id FROM shard1 UNION ALL SELECT id FROM shard2 ORDER BY country;"), Err(QueryError::NotImplemented(_))));
+    assert!(matches!(query("SELECT id FROM shard1 UNION ALL SELECT id FROM shard3;"), Err(QueryError::NotImplemented(_))));
+}
+
+#[test]
+fn test_sample() {
+    let locustdb = LocustDB::new(&Options::default());
+    let rows = (0..10_000)
+        .map(|i| vec![("id".to_string(), Int(i)), ("parity".to_string(), Int(i % 2))])
+        .collect();
+    block_on(locustdb.ingest("sampled", rows)).unwrap();
+    let query = |query: &str| block_on(locustdb.run_query(query, false, vec![], None)).unwrap();
+    let count = |query_str: &str| match query(query_str).unwrap().rows[..] {
+        [ref row] => match row[..] {
+            [Int(count)] => count,
+            _ => panic!("Expected integer count, got {:?}", row),
+        },
+        ref rows => panic!("Expected single row, got {:?}", rows),
+    };
+
+    let sampled = count("SELECT COUNT(0) FROM sampled WHERE sample(0.1);");
+    assert!((800..1200).contains(&sampled), "{}", sampled);
+    let sampled = count("SELECT COUNT(0) FROM sampled WHERE parity = 1 AND SAMPLE(0.5);");
+    assert!((2000..3000).contains(&sampled), "{}", sampled);
+    assert_eq!(count("SELECT COUNT(0) FROM sampled WHERE sample(1);"), 10_000);
+    assert_eq!(query("SELECT id FROM sampled WHERE sample(0);").unwrap().rows, Vec::<Vec<Value>>::new());
+    assert_eq!(query("SELECT id FROM sampled WHERE sample(0.5) LIMIT 10;").unwrap().rows.len(), 10);
+
+    assert!(matches!(query("SELECT id FROM sampled WHERE sample(1.5);"), Err(QueryError::TypeError(_))));
+    assert!(matches!(query("SELECT id FROM sampled WHERE sample(id);"), Err(QueryError::TypeError(_))));
+}
+
+#[test]
+fn test_set_worker_threads() {
+    let locustdb = LocustDB::new(&Options { threads: 2, ..Options::default() });
+    let _ = block_on(locustdb.load_csv(LoadOptions::new("test_data/tiny.csv", "default").with_partition_size(10)));
+    let count = || block_on(locustdb.run_query("SELECT COUNT(0) FROM default;", false, vec![], None)).unwrap().unwrap().rows;
+    let expected = count();
+    assert_eq!(locustdb.worker_threads(), 2);
+    for threads in [8, 1, 3, 0] {
+        locustdb.set_worker_threads(threads);
+        assert_eq!(locustdb.worker_threads(), threads.max(1));
+        for _ in 0..5 {
+            assert_eq!(count(), expected);
+        }
+    }
+}
+
+#[test]
+fn test_set_read_threads() {
+    let locustdb = LocustDB::new(&Options { read_threads: 2, ..Options::default() });
+    assert_eq!(locustdb.read_threads(), 2);
+    for threads in [8, 1, 0] {
+        locustdb.set_read_threads(threads);
+        assert_eq!(locustdb.read_threads(), threads.max(1));
+    }
+    // Without a disk store nothing is ever read from disk
+    let _ = block_on(locustdb.load_csv(LoadOptions::new("test_data/tiny.csv", "default").with_partition_size(10)));
+    block_on(locustdb.run_query("SELECT COUNT(0) FROM default;", false, vec![], None)).unwrap().unwrap();
+    let stats = locustdb.disk_read_stats();
+    assert_eq!(stats.read_threads, 1);
+    assert_eq!(locustdb.read_queue_depth(), 0);
+    assert_eq!((stats.reads, stats.bytes_read, stats.active_reads), (0, 0, 0));
+}
+
+#[test]
+fn test_count_nullable() {
+    // COUNT(*) counts rows, COUNT(expr) only counts values that are not null
+    test_query_ec(
+        "SELECT COUNT(*), COUNT(nullable_int), COUNT(nullable_int2), COUNT(country), COUNT(NULL) FROM default;",
+        &[vec![Int(10), Int(5), Int(6), Int(6), Int(0)]],
+    );
+    test_query_ec(
+        "SELECT enum, COUNT(*), COUNT(nullable_int), COUNT(NULL) FROM default;",
+        &[
+            vec![Str("aa"), Int(5), Int(3), Int(0)],
+            vec![Str("bb"), Int(3), Int(2), Int(0)],
+            vec![Str("cc"), Int(2), Int(0), Int(0)],
+        ],
+    );
+}
+
+#[test]
+fn test_aggregate_nullable_filtered() {
+    test_query_ec(
+        "SELECT COUNT(*), COUNT(nullable_int2), SUM(nullable_int2) FROM default WHERE id > 2;",
+        &[vec![Int(7), Int(5), Int(30)]],
+    );
+    test_query_ec(
+        "SELECT COUNT(*), COUNT(nullable_int), COUNT(nullable_int2), SUM(nullable_int2) FROM default WHERE nullable_int > 0;",
+        &[vec![Int(3), Int(3), Int(2), Int(23)]],
+    );
+    test_query_ec(
+        "SELECT enum, COUNT(*), COUNT(nullable_int2) FROM default WHERE nullable_int < 15;",
+        &[vec![Str("aa"), Int(2), Int(1)], vec![Str("bb"), Int(2), Int(2)]],
+    );
+    test_query_ec(
+        "SELECT id, nullable_int2 FROM default WHERE id > 5 ORDER BY id;",
+        &[vec![Int(6), Null], vec![Int(7), Null], vec![Int(8), Int(1)], vec![Int(9), Int(14)]],
+    );
+}
+
+#[test]
+fn test_lower_upper() {
+    test_query_ec(
+        "SELECT id FROM default WHERE lower(country) = 'germany' ORDER BY id;",
+        &[vec![Int(0)], vec![Int(9)]],
+    );
+    test_query_ec(
+        "SELECT id, upper(country), LOWER(enum) FROM default WHERE id < 4 ORDER BY id;",
+        &[
+            vec![Int(0), Str("GERMANY"), Str("aa")],
+            vec![Int(1), Str("USA"), Str("aa")],
+            vec![Int(2), Str("FRANCE"), Str("aa")],
+            vec![Int(3), Null, Str("bb")],
+        ],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE upper(country) = upper('usa');",
+        &[vec![Int(1)]],
+    );
+    test_query_ec_err("SELECT lower(id) FROM default;", QueryError::TypeError(String::new()));
+
+    let locustdb = LocustDB::new(&Options::default());
+    let names = ["Straße", "ÄÖÜ", "Ελλάδα", "İstanbul", "ascii"];
+    let rows = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| vec![("id".to_string(), Int(i as i64)), ("name".to_string(), Str(name))])
+        .collect();
+    block_on(locustdb.ingest("unicode", rows)).unwrap();
+    let query = |query: &str| block_on(locustdb.run_query(query, false, vec![], None)).unwrap().unwrap().rows;
+    let expected = names
+        .iter()
+        .map(|name| vec![Str(&name.to_lowercase()), Str(&name.to_uppercase())])
+        .collect::<Vec<_>>();
+    assert_eq!(query("SELECT lower(name), upper(name) FROM unicode ORDER BY id;"), expected);
+    assert_eq!(query("SELECT id FROM unicode WHERE upper(name) = 'STRASSE';"), vec![vec![Int(0)]]);
+    assert_eq!(query("SELECT id FROM unicode WHERE lower(name) = 'ελλάδα';"), vec![vec![Int(2)]]);
+}
+
+#[test]
+fn test_string_ordering() {
+    // Strings are compared by their bytes, also for dictionary encoded columns and constants not in the dictionary
+    test_query_ec(
+        "SELECT id FROM default WHERE 'bz' < enum ORDER BY id;",
+        &[vec![Int(6)], vec![Int(8)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE enum <= 'bb' AND enum > 'ab' ORDER BY id;",
+        &[vec![Int(3)], vec![Int(4)], vec![Int(9)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE country < 'G' ORDER BY id;",
+        &[vec![Int(2)], vec![Int(4)]],
+    );
+    test_query_ec(
+        "SELECT id FROM default WHERE string_packed >= 'az' ORDER BY id;",
+        &[vec![Int(0)], vec![Int(4)], vec![Int(8)], vec![Int(9)]],
+    );
+}
+
+#[test]
+fn test_timestamps() {
+    let locustdb = LocustDB::new(&Options::default());
+    // 2021-12-20T18:33:17.013Z, 2021-12-21T00:00:00.000Z, 2022-01-01T12:00:00.500Z
+    let timestamps = [1_640_025_197_013, 1_640_044_800_000, 1_641_038_400_500];
+    let rows = timestamps
+        .iter()
+        .enumerate()
+        .map(|(i, &ts)| vec![("id".to_string(), Int(i as i64)), ("ts".to_string(), Timestamp(ts))])
+        .collect();
+    block_on(locustdb.ingest("events", rows)).unwrap();
+    let query = |query: &str| block_on(locustdb.run_query(query, false, vec![], None)).unwrap().unwrap();
+
+    let result = query("SELECT id, ts FROM events ORDER BY id;");
+    assert_eq!(result.coltypes, vec!["integer".to_string(), "timestamp".to_string()]);
+    assert_eq!(
+        result.rows.iter().map(|row| row[1].to_string()).collect::<Vec<_>>(),
+        vec!["2021-12-20T18:33:17.013Z", "2021-12-21T00:00:00.000Z", "2022-01-01T12:00:00.500Z"],
+    );
+    assert_eq!(
+        query("SELECT id, to_year(ts), to_month(ts) FROM events ORDER BY id;").rows,
+        vec![
+            vec![Int(0), Int(2021), Timestamp(1_638_316_800_000)],
+            vec![Int(1), Int(2021), Timestamp(1_638_316_800_000)],
+            vec![Int(2), Int(2022), Timestamp(1_640_995_200_000)],
+        ],
+    );
+    assert_eq!(
+        query("SELECT date_trunc('month', ts) AS month, COUNT(0) FROM events;").rows,
+        vec![
+            vec![Timestamp(1_638_316_800_000), Int(2)],
+            vec![Timestamp(1_640_995_200_000), Int(1)],
+        ],
+    );
+    assert_eq!(query("SELECT MAX(ts) FROM events;").rows, vec![vec![Timestamp(1_641_038_400_500)]]);
+    assert_eq!(query("SELECT id FROM events WHERE ts >= 1640044800000 ORDER BY id;").rows, vec![vec![Int(1)], vec![Int(2)]]);
+    assert_eq!(query("SELECT ts / 1000 FROM events WHERE id = 0;").rows, vec![vec![Int(1_640_025_197)]]);
+    assert!(query("SELECT * FROM events WHERE id = 2;").rows[0].contains(&Timestamp(1_641_038_400_500)));
+
+    let mut columns = std::collections::HashMap::new();
+    columns.insert("ts".to_string(), InputColumn::Timestamp(vec![0, -1]));
+    block_on(locustdb.ingest_homogeneous("epoch", columns));
+    let result = query("SELECT ts FROM epoch ORDER BY ts;");
+    assert_eq!(result.coltypes, vec!["timestamp".to_string()]);
+    assert_eq!(
+        result.rows.iter().map(|row| row[0].to_string()).collect::<Vec<_>>(),
+        vec!["1969-12-31T23:59:59.999Z", "1970-01-01T00:00:00.000Z"],
+    );
+}
+
+#[test]
+fn test_mixed_int_float_ingestion() {
+    let locustdb = LocustDB::new(&Options::default());
+    assert!(block_on(locustdb.create_table("mixed", Some(2))));
+    // The first partition only contains integers, later integers are promoted to floats
+    let values = [Int(1), Int(2), Float(OrderedFloat(1.5)), Int(3), Int(4)];
+    let rows = values
+        .iter()
+        .enumerate()
+        .map(|(i, x)| vec![("id".to_string(), Int(i as i64)), ("x".to_string(), x.clone())])
+        .collect();
+    block_on(locustdb.ingest("mixed", rows)).unwrap();
+    let columns = [("id".to_string(), InputColumn::Int(vec![5, 6])), ("x".to_string(), InputColumn::Int(vec![5, 6]))];
+    block_on(locustdb.ingest_homogeneous("mixed", columns.into_iter().collect()));
+    let query = |query: &str| block_on(locustdb.run_query(query, false, vec![], None)).unwrap().unwrap().rows;
+
+    assert_eq!(
+        query("SELECT id, x FROM mixed ORDER BY id;"),
+        [1.0, 2.0, 1.5, 3.0, 4.0, 5.0, 6.0]
+            .iter()
+            .enumerate()
+            .map(|(i, &x)| vec![Int(i as i64), Float(OrderedFloat(x))])
+            .collect::<Vec<_>>(),
+    );
+    assert_eq!(
+        query("SELECT SUM(x), MAX(x), MIN(x), COUNT(x) FROM mixed;"),
+        vec![vec![Float(OrderedFloat(22.5)), Float(OrderedFloat(6.0)), Float(OrderedFloat(1.0)), Int(7)]],
+    );
+    assert_eq!(
+        query("SELECT id % 2, SUM(x) FROM mixed;"),
+        vec![vec![Int(0), Float(OrderedFloat(12.5))], vec![Int(1), Float(OrderedFloat(10.0))]],
+    );
+}
+
+#[test]
+fn test_query_memory_limit() {
+    let locustdb = LocustDB::new(&Options { query_memory_limit: Some(1 << 20), ..Options::default() });
+    let rows = (0..100_000)
+        .map(|i| vec![("id".to_string(), Int(i)), ("digit".to_string(), Int(i % 10))])
+        .collect();
+    block_on(locustdb.ingest("high_cardinality", rows)).unwrap();
+    let query = |query: &str| block_on(locustdb.run_query(query, false, vec![], None)).unwrap();
+
+    assert_eq!(
+        query("SELECT digit, COUNT(0) FROM high_cardinality ORDER BY digit LIMIT 2;").unwrap().rows,
+        vec![vec![Int(0), Int(10_000)], vec![Int(1), Int(10_000)]],
+    );
+    match query("SELECT id, COUNT(0) FROM high_cardinality;") {
+        Err(QueryError::ResourceLimit(limit)) => assert_eq!(limit, 1 << 20),
+        result => panic!("Expected memory limit to be exceeded, got {:?}", result.map(|output| output.rows.len())),
+    }
+}
+
+#[test]
+fn test_cancel_query() {
+    let locustdb = std::sync::Arc::new(LocustDB::new(&Options { threads: 1, ..Options::default() }));
+    assert!(block_on(locustdb.create_table("large", Some(1_000))));
+    let rows = (0..200_000).map(|i| vec![("id".to_string(), Int(i))]).collect();
+    block_on(locustdb.ingest("large", rows)).unwrap();
+    assert!(locustdb.running_queries().is_empty());
+
+    let query = "SELECT id % 1000, COUNT(0) FROM large;";
+    let db = locustdb.clone();
+    let handle = std::thread::spawn(move || block_on(db.run_query(query, false, vec![], None)).unwrap());
+    let running = loop {
+        if let Some(running) = locustdb.running_queries().pop() {
+            break running;
+        }
+        std::thread::yield_now();
+    };
+    assert_eq!(running.query, query);
+    assert!(locustdb.cancel_query(running.id));
+    match handle.join().unwrap() {
+        Err(QueryError::Cancelled) => {}
+        result => panic!("Expected query to be cancelled, got {:?}", result.map(|output| output.rows.len())),
+    }
+    assert!(locustdb.running_queries().is_empty());
+    assert!(!locustdb.cancel_query(running.id));
+}
+
+#[test]
+fn test_filter_on_select_alias() {
+    test_query_ec(
+        "SELECT id * 2 AS doubled FROM default WHERE doubled > 10 ORDER BY id;",
+        &[vec![Int(12)], vec![Int(14)], vec![Int(16)], vec![Int(18)]],
+    );
+    test_query_ec(
+        "SELECT id, id + nullable_int AS total FROM default WHERE total >= 10 ORDER BY id;",
+        &[vec![Int(4), Int(14)], vec![Int(7), Int(27)], vec![Int(9), Int(22)]],
+    );
+    test_query_ec(
+        "SELECT id * 10 AS id FROM default WHERE id < 2 ORDER BY id;",
+        &[vec![Int(0)], vec![Int(10)]],
+    );
+    test_query_ec_err(
+        "SELECT enum, COUNT(0) AS total FROM default WHERE total > 1;",
+        QueryError::NotImplemented(
+            "Alias `total` refers to an aggregate and cannot be used in WHERE, use HAVING instead".to_string(),
+        ),
+    );
+}
+
+#[test]
+fn test_order_by_multiple_merges_partitions() {
+    test_query_ec(
+        "SELECT enum, non_dense_ints, id, float FROM default ORDER BY enum DESC, id LIMIT 4;",
+        &[
+            vec![Str("cc"), Int(2), Int(6), Float(OrderedFloat(0.0))],
+            vec![Str("cc"), Int(2), Int(8), Float(OrderedFloat(-1.0))],
+            vec![Str("bb"), Int(1), Int(3), Float(OrderedFloat(3.15159))],
+            vec![Str("bb"), Int(4), Int(4), Float(OrderedFloat(0.1234e30))],
+        ],
+    );
+    test_query_ec(
+        "SELECT enum, id FROM default ORDER BY non_dense_ints, enum, float LIMIT 10;",
+        &[
+            vec![Str("aa"), Int(5)],
+            vec![Str("aa"), Int(0)],
+            vec![Str("aa"), Int(7)],
+            vec![Str("bb"), Int(3)],
+            vec![Str("aa"), Int(1)],
+            vec![Str("cc"), Int(8)],
+            vec![Str("cc"), Int(6)],
+            vec![Str("aa"), Int(2)],
+            vec![Str("bb"), Int(9)],
+            vec![Str("bb"), Int(4)],
+        ],
+    );
+    test_query_ec(
+        "SELECT enum, non_dense_ints, id FROM default ORDER BY enum DESC, non_dense_ints, id LIMIT 4 OFFSET 2;",
+        &[
+            vec![Str("bb"), Int(1), Int(3)],
+            vec![Str("bb"), Int(3), Int(9)],
+            vec![Str("bb"), Int(4), Int(4)],
+            vec![Str("aa"), Int(0), Int(0)],
+        ],
+    );
+}
+
+#[test]
+fn test_aliased_aggregate_expression() {
+    test_query_ec(
+        "SELECT SUM(id) - SUM(nullable_int) AS diff FROM default;",
+        &[vec![Int(43)]],
+    );
+    test_query_ec(
+        "SELECT enum, SUM(id) + COUNT(0) AS total FROM default;",
+        &[vec![Str("aa"), Int(20)], vec![Str("bb"), Int(19)], vec![Str("cc"), Int(16)]],
+    );
+}
+
+#[test]
+fn test_implicit_alias() {
+    test_query_ec(
+        "SELECT id * 2 doubled FROM default WHERE doubled > 14 ORDER BY doubled DESC;",
+        &[vec![Int(18)], vec![Int(16)]],
+    );
+    test_query_ec(
+        "SELECT enum, COUNT(0) n FROM default ORDER BY n;",
+        &[vec![Str("cc"), Int(2)], vec![Str("bb"), Int(3)], vec![Str("aa"), Int(5)]],
+    );
+    test_query_ec(
+        "SELECT enum, SUM(id) total FROM default HAVING total > 14 ORDER BY total DESC;",
+        &[vec![Str("bb"), Int(16)], vec![Str("aa"), Int(15)]],
+    );
+}
+
+#[test]
+fn test_partition_filter() {
+    let locustdb = LocustDB::new(&Options::default());
+    assert!(block_on(locustdb.create_table("t", Some(2))));
+    let rows = (0..6).map(|i| vec![("id".to_string(), Int(i))]).collect();
+    block_on(locustdb.ingest("t", rows)).unwrap();
+    let query = |query: &str| block_on(locustdb.run_query(query, false, vec![], None)).unwrap();
+
+    let mut partition_ids = vec![];
+    for id in 0..32 {
+        let rows = query(&format!("SELECT COUNT(0) FROM t WITH (partition_id = {});", id)).unwrap().rows;
+        if !rows.is_empty() {
+            assert_eq!(rows, vec![vec![Int(2)]]);
+            partition_ids.push(id);
+        }
+    }
+    assert_eq!(partition_ids.len(), 3);
+    assert_eq!(
+        query(&format!(
+            "SELECT COUNT(0) FROM t WITH (partition_id BETWEEN {} AND {});",
+            partition_ids[0], partition_ids[1]
+        ))
+        .unwrap()
+        .rows,
+        vec![vec![Int(4)]]
+    );
+    assert_eq!(
+        query(&format!(
+            "SELECT id FROM t WITH (partition_id >= {}, partition_id != {}) WHERE id < 5 ORDER BY id;",
+            partition_ids[1], partition_ids[2]
+        ))
+        .unwrap()
+        .rows,
+        vec![vec![Int(2)], vec![Int(3)]]
+    );
+    assert!(query("SELECT id FROM t WITH (id > 3);").is_err());
+}
+
+#[test]
+fn test_group_by_wide_range_column() {
+    test_query_ec(
+        "SELECT country, largenum, COUNT(0) FROM default;",
+        &[
+            vec![Null, Int(-9223372036854775807), Int(1)],
+            vec![Null, Int(9223372036854775807), Int(3)],
+            vec![Str("France"), Int(-9223372036854775807), Int(1)],
+            vec![Str("France"), Int(9223372036854775807), Int(1)],
+            vec![Str("Germany"), Int(-9223372036854775807), Int(1)],
+            vec![Str("Germany"), Int(9223372036854775807), Int(1)],
+            vec![Str("Turkey"), Int(-9223372036854775807), Int(1)],
+            vec![Str("USA"), Int(9223372036854775807), Int(1)],
+        ],
+    );
+}
+
+#[test]
+fn test_group_by_hashmap_sorted_before_decode() {
+    let locustdb = LocustDB::new(&Options::default());
+    let names = ["b", "c", "a"];
+    let rows = (0..70_000)
+        .map(|i| vec![("id".to_string(), Int(i)), ("s".to_string(), Str(names[(i % 3) as usize]))])
+        .collect();
+    block_on(locustdb.ingest("t", rows)).unwrap();
+    let result = block_on(locustdb.run_query("SELECT s, id, COUNT(0) FROM t LIMIT 3;", false, vec![], None)).unwrap();
+    assert_eq!(
+        result.unwrap().rows,
+        vec![
+            vec![Str("a"), Int(2), Int(1)],
+            vec![Str("a"), Int(5), Int(1)],
+            vec![Str("a"), Int(8), Int(1)],
+        ]
+    );
+}
+
+#[test]
+fn test_min_max_string() {
+    test_query_ec(
+        "SELECT max(string_packed), min(country) FROM default",
+        &[vec![Str("😈"), Str("France")]],
+    );
+    test_query_ec(
+        "SELECT non_dense_ints, max(enum), min(string_packed), max(country) FROM default",
+        &[
+            vec![Int(0), Str("aa"), Str("$sss"), Str("Germany")],
+            vec![Int(1), Str("bb"), Str("AXY"), Null],
+            vec![Int(2), Str("cc"), Str("abc"), Str("USA")],
+            vec![Int(3), Str("bb"), Str("axz"), Str("Germany")],
+            vec![Int(4), Str("bb"), Str("azy"), Str("France")],
+        ],
+    );
+}
+
+#[test]
+fn test_min_max_dictionary_encoded_string() {
+    test_query_nyc(
+        "SELECT passenger_count, max(payment_type), min(payment_type) FROM default",
+        &[
+            vec![Int(0), Str("2"), Str("2")],
+            vec![Int(1), Str("4"), Str("1")],
+            vec![Int(2), Str("4"), Str("1")],
+            vec![Int(3), Str("4"), Str("1")],
+            vec![Int(4), Str("2"), Str("1")],
+            vec![Int(5), Str("2"), Str("1")],
+            vec![Int(6), Str("2"), Str("1")],
+        ],
+    );
+}
+
+#[test]
+fn test_group_by_multiple_columns_with_nullable_string() {
+    test_query_ec(
+        "SELECT country, enum, count(0) FROM default",
+        &[
+            vec![Null, Str("aa"), Int(2)],
+            vec![Null, Str("bb"), Int(1)],
+            vec![Null, Str("cc"), Int(1)],
+            vec![Str("France"), Str("aa"), Int(1)],
+            vec![Str("France"), Str("bb"), Int(1)],
+            vec![Str("Germany"), Str("aa"), Int(1)],
+            vec![Str("Germany"), Str("bb"), Int(1)],
+            vec![Str("Turkey"), Str("cc"), Int(1)],
+            vec![Str("USA"), Str("aa"), Int(1)],
+        ],
+    );
+    test_query_ec(
+        "SELECT country, string_packed, non_dense_ints, count(0) FROM default WHERE id > 1",
+        &[
+            vec![Null, Str("$sss"), Int(0), Int(1)],
+            vec![Null, Str("AXY"), Int(1), Int(1)],
+            vec![Null, Str("_f"), Int(1), Int(1)],
+            vec![Null, Str("t"), Int(2), Int(1)],
+            vec![Str("France"), Str("axz"), Int(3), Int(1)],
+            vec![Str("France"), Str("azy"), Int(4), Int(1)],
+            vec![Str("Germany"), Str("😈"), Int(3), Int(1)],
+            vec![Str("Turkey"), Str("asd"), Int(2), Int(1)],
+        ],
+    );
+}
+
+#[test]
+fn test_order_by_nulls_first_last() {
+    test_query_ec(
+        "SELECT id, nullable_int FROM default ORDER BY nullable_int, id;",
+        &[
+            vec![Int(1), Int(-40)],
+            vec![Int(0), Int(-1)],
+            vec![Int(4), Int(10)],
+            vec![Int(9), Int(13)],
+            vec![Int(7), Int(20)],
+            vec![Int(2), Null],
+            vec![Int(3), Null],
+            vec![Int(5), Null],
+            vec![Int(6), Null],
+            vec![Int(8), Null],
+        ],
+    );
+    test_query_ec(
+        "SELECT id, nullable_int FROM default ORDER BY nullable_int DESC, id;",
+        &[
+            vec![Int(2), Null],
+            vec![Int(3), Null],
+            vec![Int(5), Null],
+            vec![Int(6), Null],
+            vec![Int(8), Null],
+            vec![Int(7), Int(20)],
+            vec![Int(9), Int(13)],
+            vec![Int(4), Int(10)],
+            vec![Int(0), Int(-1)],
+            vec![Int(1), Int(-40)],
+        ],
+    );
+    test_query_ec(
+        "SELECT id, nullable_int FROM default ORDER BY nullable_int ASC NULLS FIRST, id LIMIT 7;",
+        &[
+            vec![Int(2), Null],
+            vec![Int(3), Null],
+            vec![Int(5), Null],
+            vec![Int(6), Null],
+            vec![Int(8), Null],
+            vec![Int(1), Int(-40)],
+            vec![Int(0), Int(-1)],
+        ],
+    );
+    test_query_ec(
+        "SELECT id, nullable_int FROM default ORDER BY nullable_int DESC NULLS LAST, id DESC LIMIT 7;",
+        &[
+            vec![Int(7), Int(20)],
+            vec![Int(9), Int(13)],
+            vec![Int(4), Int(10)],
+            vec![Int(0), Int(-1)],
+            vec![Int(1), Int(-40)],
+            vec![Int(8), Null],
+            vec![Int(6), Null],
+        ],
+    );
+}
+
+#[test]
+fn test_join() {
+    let locustdb = LocustDB::new(&Options::default());
+    for table in ["events", "users"] {
+        let _ = block_on(locustdb.load_csv(
+            LoadOptions::new("test_data/edge_cases.csv", table)
+                .with_partition_size(3)
+                .allow_nulls_all_columns(),
+        ));
+    }
+    let query = |query: &str| block_on(locustdb.run_query(query, false, vec![], None)).unwrap();
+    let rows = |query_str: &str| query(query_str).unwrap().rows;
+
+    let output = query("SELECT e.id, u.id AS uid, u.nullable_int2 FROM events e JOIN users u ON u.nullable_int2 = e.id ORDER BY e.id;").unwrap();
+    assert_eq!(output.colnames, vec!["e.id", "uid", "u.nullable_int2"]);
+    assert_eq!(
+        output.rows,
+        vec![
+            vec![Int(0), Int(3), Int(0)],
+            vec![Int(1), Int(8), Int(1)],
+            vec![Int(6), Int(5), Int(6)],
+            vec![Int(9), Int(4), Int(9)],
+        ]
+    );
+    // Null keys do not match
+    assert_eq!(
+        rows("SELECT users.id FROM events JOIN users ON events.nullable_int = users.nullable_int ORDER BY users.id;"),
+        vec![vec![Int(0)], vec![Int(1)], vec![Int(4)], vec![Int(7)], vec![Int(9)]]
+    );
+    assert_eq!(
+        rows("SELECT e.id, u.country FROM events AS e INNER JOIN users AS u USING (id) WHERE e.id > 3 AND u.country = 'Germany';"),
+        vec![vec![Int(9), Str("Germany")]]
+    );
+    assert_eq!(
+        rows("SELECT e.id * 2 AS double FROM events e JOIN users u ON e.id = u.id ORDER BY double DESC LIMIT 2 OFFSET 1;"),
+        vec![vec![Int(16)], vec![Int(14)]]
+    );
+    assert!(matches!(query("SELECT e.id FROM events e JOIN users u ON e.id = u.country;"), Err(QueryError::TypeError(_))));
+    assert!(matches!(query("SELECT id FROM events e JOIN users u ON e.id = u.id;"), Err(QueryError::ParseError(_))));
+    assert!(matches!(query("SELECT e.id + u.id FROM events e JOIN users u ON e.id = u.id;"), Err(QueryError::NotImplemented(_))));
+    assert!(matches!(query("SELECT e.id FROM events e LEFT JOIN users u ON e.id = u.id;"), Err(QueryError::NotImplemented(_))));
+}
+
+#[test]
+fn test_result_cache() {
+    let locustdb = LocustDB::new(&Options { result_cache_size: 10, ..Options::default() });
+    let _ = block_on(locustdb.load_csv(LoadOptions::new("test_data/tiny.csv", "default").with_partition_size(10)));
+    let count = || block_on(locustdb.run_query("SELECT COUNT(0) FROM default;", false, vec![], None)).unwrap().unwrap().rows;
+    let expected = count();
+    assert_eq!(count(), expected);
+    let stats = locustdb.cache_stats();
+    assert_eq!((stats.capacity, stats.entries, stats.hits, stats.misses), (10, 1, 1, 1));
+
+    // Ingesting into the queried table invalidates the cached result
+    block_on(locustdb.ingest("default", vec![vec![("num".to_string(), Int(300))]])).unwrap();
+    let Value::Int(rows) = expected[0][0] else { panic!("expected integer count") };
+    assert_eq!(count(), vec![vec![Int(rows + 1)]]);
+    assert_eq!(locustdb.cache_stats().hits, 1);
+
+    // Explained queries are not served from the cache
+    block_on(locustdb.run_query("SELECT COUNT(0) FROM default;", true, vec![], None)).unwrap().unwrap();
+    assert_eq!(locustdb.cache_stats().hits, 1);
+    assert_eq!(count(), vec![vec![Int(rows + 1)]]);
+    assert_eq!(locustdb.cache_stats().hits, 2);
+
+    // Queries with random samples are not cached
+    let entries = locustdb.cache_stats().entries;
+    for query in &[
+        "SELECT COUNT(0) FROM default WHERE SAMPLE(0.5);",
+        "SELECT num FROM default ORDER BY SAMPLE(0.5);",
+        "SELECT num, SAMPLE(0.5) FROM default;",
+    ] {
+        block_on(locustdb.run_query(query, false, vec![], None)).unwrap().unwrap();
+        block_on(locustdb.run_query(query, false, vec![], None)).unwrap().unwrap();
+    }
+    let stats = locustdb.cache_stats();
+    assert_eq!((stats.entries, stats.hits), (entries, 2));
+}
+
+#[test]
+fn test_column_encodings() {
+    let mut column_encodings = std::collections::HashMap::new();
+    column_encodings.insert("raw".to_string(), vec![("*".to_string(), ColumnEncoding::Raw)].into_iter().collect());
+    column_encodings.insert(
+        "forced".to_string(),
+        vec![("name".to_string(), ColumnEncoding::Dictionary), ("value".to_string(), ColumnEncoding::U32)].into_iter().collect(),
+    );
+    let locustdb = LocustDB::new(&Options { batch_size: 64, column_encodings, ..Options::default() });
+    for table in &["auto", "raw", "forced"] {
+        let rows = (0..200)
+            .map(|i| vec![("name".to_string(), Value::Str(format!("user{}", i % 7))), ("value".to_string(), Int(i * 1000))])
+            .collect();
+        block_on(locustdb.ingest(table, rows)).unwrap();
+    }
+    let run = |query: String| block_on(locustdb.run_query(&query, false, vec![], None)).unwrap().unwrap().rows;
+    for query in &[
+        "SELECT name, COUNT(0), SUM(value) FROM {} GROUP BY name ORDER BY name;",
+        "SELECT value FROM {} WHERE name = 'user3' AND value > 50000 ORDER BY value LIMIT 5;",
+        "SELECT name, value FROM {} ORDER BY value DESC LIMIT 3;",
+    ] {
+        let expected = run(query.replace("{}", "auto"));
+        assert!(!expected.is_empty());
+        assert_eq!(run(query.replace("{}", "raw")), expected, "{}", query);
+        assert_eq!(run(query.replace("{}", "forced")), expected, "{}", query);
+    }
+}
+
+#[test]
+fn test_in_subquery() {
+    let ingest = |locustdb: &LocustDB| {
+        let users = (0..10)
+            .map(|i| vec![("id".to_string(), Int(i)), ("name".to_string(), Value::Str(format!("user{}", i)))])
+            .collect();
+        block_on(locustdb.ingest("users", users)).unwrap();
+        let blocked = [Int(2), Int(5), Int(7), Int(100), Value::Null]
+            .iter()
+            .map(|id| vec![("id".to_string(), id.clone()), ("name".to_string(), Value::Str("user5".to_string()))])
+            .collect();
+        block_on(locustdb.ingest("blocklist", blocked)).unwrap();
+    };
+    let locustdb = LocustDB::memory_only();
+    ingest(&locustdb);
+    let run = |locustdb: &LocustDB, query: &str| block_on(locustdb.run_query(query, false, vec![], None)).unwrap();
+    let ids = |query: &str| {
+        run(&locustdb, query).unwrap().rows.into_iter().map(|row| row[0].clone()).collect::<Vec<_>>()
+    };
+    assert_eq!(ids("SELECT id FROM users WHERE id IN (SELECT id FROM blocklist) ORDER BY id;"), vec![Int(2), Int(5), Int(7)]);
+    assert_eq!(
+        ids("SELECT id FROM users WHERE id NOT IN (SELECT id FROM blocklist WHERE id IS NOT NULL) AND id < 5 ORDER BY id;"),
+        vec![Int(0), Int(1), Int(3), Int(4)]
+    );
+    // Like a list containing NULL, a subquery returning NULL never matches in NOT IN
+    assert_eq!(ids("SELECT id FROM users WHERE id NOT IN (SELECT id FROM blocklist);"), vec![]);
+    assert_eq!(ids("SELECT id FROM users WHERE id IN (SELECT id FROM blocklist WHERE id > 100);"), vec![]);
+    assert_eq!(
+        ids("SELECT COUNT(0) FROM users WHERE id NOT IN (SELECT id FROM blocklist WHERE id > 100);"),
+        vec![Int(10)]
+    );
+    assert_eq!(
+        ids("SELECT id FROM users WHERE name IN (SELECT name FROM blocklist) OR id = 0 ORDER BY id;"),
+        vec![Int(0), Int(5)]
+    );
+    assert_eq!(
+        ids("SELECT id FROM users WHERE id IN (SELECT id FROM blocklist WHERE id IS NOT NULL ORDER BY id DESC LIMIT 2) ORDER BY id;"),
+        vec![Int(7)]
+    );
+    assert!(run(&locustdb, "SELECT id FROM users WHERE id IN (SELECT id, name FROM blocklist);").is_err());
+    assert!(run(&locustdb, "SELECT id IN (SELECT id FROM blocklist) FROM users;").is_err());
+
+    let limited = LocustDB::new(&Options { max_result_rows: Some(3), ..Options::default() });
+    ingest(&limited);
+    assert!(matches!(
+        run(&limited, "SELECT id FROM users WHERE id IN (SELECT id FROM blocklist) ORDER BY id;"),
+        Err(QueryError::ResultTooLarge(3))
+    ));
+    assert_eq!(
+        run(&limited, "SELECT id FROM users WHERE id IN (SELECT id FROM blocklist WHERE id < 6) ORDER BY id;").unwrap().rows,
+        vec![vec![Int(2)], vec![Int(5)]]
+    );
+}
+
+#[test]
+fn test_ingest_timestamp() {
+    let opts = Options { ingest_timestamp_tables: vec!["events".to_string()].into_iter().collect(), ..Options::default() };
+    let locustdb = LocustDB::new(&opts);
+    let now = || std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as i64;
+    let before = now();
+    for table in &["events", "plain"] {
+        let rows = (0..5)
+            .map(|i| vec![("x".to_string(), Int(i)), ("_ingest_ts".to_string(), Int(i))])
+            .collect();
+        block_on(locustdb.ingest(table, rows)).unwrap();
+    }
+    let after = now();
+    let run = |query: &str| block_on(locustdb.run_query(query, false, vec![], None)).unwrap().unwrap().rows;
+    let rows = run(&format!(
+        "SELECT COUNT(0), MIN(_ingest_ts) >= {}, MAX(_ingest_ts) <= {} FROM events WHERE _ingest_ts >= {};",
+        before, after, before
+    ));
+    assert_eq!(rows, vec![vec![Int(5), Value::Bool(true), Value::Bool(true)]]);
+    assert_eq!(run("SELECT COUNT(0) FROM plain WHERE _ingest_ts < 2;"), vec![vec![Int(2)]]);
+}
+
+#[test]
+fn test_empty_result_columns() {
+    let locustdb = LocustDB::new(&Options::default());
+    // Columns `n` and `f` are nullable in the first partition only
+    block_on(locustdb.create_table("t", Some(4)));
+    let rows = (0..10)
+        .map(|i| {
+            let (n, f) = if i < 3 { (Null, Null) } else { (Value::Str(format!("n{}", i)), Float(OrderedFloat(i as f64 / 2.0))) };
+            vec![
+                ("x".to_string(), Int(i)),
+                ("s".to_string(), Value::Str(format!("s{}", i))),
+                ("n".to_string(), n),
+                ("f".to_string(), f),
+            ]
+        })
+        .collect();
+    block_on(locustdb.ingest("t", rows)).unwrap();
+    let run = |query: &str| block_on(locustdb.run_query(query, false, vec![], None)).unwrap().unwrap();
+    for query in &[
+        "SELECT x, s, n, f FROM t WHERE 1 = 0;",
+        "SELECT x, s, n, f FROM t WHERE false;",
+        "SELECT x, s, n, f FROM t WHERE x > 3 AND 2.5 < 1;",
+        "SELECT x, s, n, f FROM t WHERE x > 100;",
+        "SELECT x, s, n, f FROM t WHERE x > 100 ORDER BY x;",
+        "SELECT x, s, n, f FROM t WHERE x > 100 ORDER BY n;",
+    ] {
+        let result = run(query);
+        let colnames = vec!["x".to_string(), "s".to_string(), "n".to_string(), "f".to_string()];
+        let coltypes = vec!["integer".to_string(), "string".to_string(), "string".to_string(), "float".to_string()];
+        assert_eq!(result.colnames, colnames, "{}", query);
+        assert_eq!(result.coltypes, coltypes, "{}", query);
+        assert!(result.rows.is_empty(), "{}", query);
+    }
+    let result = run("SELECT x, s, n, f FROM t WHERE x < 2;");
+    assert_eq!(result.coltypes, vec!["integer".to_string(), "string".to_string(), "string".to_string(), "float".to_string()]);
+    assert_eq!(result.rows, vec![vec![Int(0), Str("s0"), Null, Null], vec![Int(1), Str("s1"), Null, Null]]);
+    let result = run("SELECT s, COUNT(0) AS count FROM t WHERE 1 = 0;");
+    assert_eq!(result.colnames, vec!["s".to_string(), "count".to_string()]);
+    assert_eq!(result.coltypes, vec!["string".to_string(), "integer".to_string()]);
+    assert!(result.rows.is_empty());
+
+    assert_eq!(run("SELECT x FROM t WHERE 1 < 2 AND x < 2 ORDER BY x;").rows, vec![vec![Int(0)], vec![Int(1)]]);
+    assert_eq!(run("SELECT x FROM t WHERE x = 7 OR 1 = 0;").rows, vec![vec![Int(7)]]);
+    assert_eq!(run("SELECT COUNT(0) FROM t WHERE 1 = 1.0;").rows, vec![vec![Int(10)]]);
+
+    let _ = block_on(
+        locustdb.load_csv(
+            LoadOptions::new("test_data/edge_cases.csv", "edge_cases")
+                .with_partition_size(3)
+                .allow_nulls_all_columns(),
+        ),
+    );
+    for query in &[
+        "SELECT country FROM edge_cases WHERE 1 = 0;",
+        "SELECT country FROM edge_cases WHERE id > 1000;",
+    ] {
+        assert_eq!(run(query).coltypes, vec!["string".to_string()], "{}", query);
+    }
+}
+
+#[test]
+fn test_run_statements() {
+    let locustdb = LocustDB::memory_only();
+    let rows = (0..5)
+        .map(|i| vec![("x".to_string(), Int(i)), ("s".to_string(), Value::Str(format!("{};", i)))])
+        .collect();
+    block_on(locustdb.ingest("t", rows)).unwrap();
+    let run = |statements: &str| block_on(locustdb.run_statements(statements, false, None)).unwrap();
+
+    let outputs = run("SELECT COUNT(0) FROM t; SHOW TABLES;\n SELECT x FROM t WHERE x > 2 ORDER BY x;;").unwrap();
+    assert_eq!(outputs.len(), 3);
+    assert_eq!(outputs[0].rows, vec![vec![Int(5)]]);
+    assert!(outputs[1].rows.contains(&vec![Value::Str("t".to_string())]));
+    assert_eq!(outputs[2].rows, vec![vec![Int(3)], vec![Int(4)]]);
+    // Semicolons inside string literals do not separate statements
+    assert_eq!(run("SELECT x FROM t WHERE s = '3;'").unwrap()[0].rows, vec![vec![Int(3)]]);
+
+    assert!(matches!(run("SELECT x FROM t; SELECT y FROM missing; SELECT x FROM t;"), Err((1, _))));
+    assert!(matches!(run("SELECT FROM;"), Err((0, _))));
+}
+
+#[test]
+fn test_percentile() {
+    let locustdb = LocustDB::new(&Options { batch_size: 100, ..Options::default() });
+    let rows = Some(vec![("g".to_string(), Str("c")), ("latency".to_string(), Null)])
+        .into_iter()
+        .chain((1..=1000).map(|i| vec![("g".to_string(), Str("a")), ("latency".to_string(), Int(i))]))
+        .chain((0..200).map(|i| {
+            vec![("g".to_string(), Str("b")), ("latency".to_string(), Float(OrderedFloat(i as f64 * 0.5 - 50.0)))]
+        }))
+        .collect();
+    block_on(locustdb.ingest("requests", rows)).unwrap();
+    let query = |q: &str| block_on(locustdb.run_query(q, false, vec![], None)).unwrap();
+    let percentiles = |q: &str| {
+        query(q)
+            .unwrap()
+            .rows
+            .into_iter()
+            .map(|row| match row[row.len() - 1] {
+                Float(f) => Some(f.0),
+                Null => None,
+                ref other => panic!("Expected float, found {:?}", other),
+            })
+            .collect::<Vec<_>>()
+    };
+    let assert_approx = |actual: Option<f64>, expected: f64| {
+        let actual = actual.unwrap();
+        assert!((actual - expected).abs() <= expected.abs() * 0.01, "{} != {}", actual, expected);
+    };
+
+    let p = query("SELECT PERCENTILE(latency, 0.5), PERCENTILE(latency, 0.99), PERCENTILE(latency, 1) FROM requests WHERE g = 'a';")
+        .unwrap();
+    assert_eq!(p.colnames, vec!["PERCENTILE(latency, 0.5)", "PERCENTILE(latency, 0.99)", "PERCENTILE(latency, 1)"]);
+    let value = |v: &Value| match *v {
+        Float(f) => Some(f.0),
+        _ => None,
+    };
+    assert_approx(value(&p.rows[0][0]), 500.0);
+    assert_approx(value(&p.rows[0][1]), 990.0);
+    assert_approx(value(&p.rows[0][2]), 1000.0);
+
+    let p = percentiles("SELECT g, PERCENTILE(latency, 0.25) FROM requests ORDER BY g;");
+    assert_eq!(p.len(), 3);
+    assert_approx(p[0], 250.0);
+    assert_approx(p[1], -25.5);
+    assert_eq!(p[2], None);
+    let p = percentiles("SELECT g, COUNT(0), PERCENTILE(latency, 0) FROM requests WHERE g = 'b';");
+    assert_approx(p[0], -50.0);
+    let p = percentiles("SELECT g, PERCENTILE(latency, 0.5) * 2 FROM requests GROUP BY g HAVING PERCENTILE(latency, 0.9) > 100;");
+    assert_eq!(p.len(), 1);
+    assert_approx(p[0], 1000.0);
+
+    assert!(matches!(query("SELECT PERCENTILE(latency, 1.5) FROM requests;"), Err(QueryError::ParseError(_))));
+    assert!(matches!(query("SELECT PERCENTILE(latency) FROM requests;"), Err(QueryError::ParseError(_))));
+    assert!(query("SELECT PERCENTILE(latency, 0.5), COUNT(DISTINCT g) FROM requests;").is_err());
+}
+
+#[test]
+fn test_delete() {
+    let locustdb = LocustDB::memory_only();
+    let load = LoadOptions::new("test_data/edge_cases.csv", "default")
+        .with_partition_size(3)
+        .allow_nulls_all_columns();
+    block_on(locustdb.load_csv(load)).unwrap();
+    let query = |q: &str| block_on(locustdb.run_query(q, false, vec![], None)).unwrap().map(|output| output.rows);
+    let ids = || query("SELECT id FROM default ORDER BY id;").unwrap();
+
+    assert_eq!(query("DELETE FROM default WHERE id >= 7 AND enum <> 'bb';").unwrap(), vec![vec![Int(2)]]);
+    assert_eq!(
+        query("SELECT enum, COUNT(0) FROM default;").unwrap(),
+        vec![vec![Str("aa"), Int(4)], vec![Str("bb"), Int(3)], vec![Str("cc"), Int(1)]]
+    );
+    // Rows where the predicate is null are kept, deleted rows are not counted again
+    assert_eq!(query("DELETE FROM default WHERE nullable_int > 0;").unwrap(), vec![vec![Int(2)]]);
+    assert_eq!(query("DELETE FROM default WHERE nullable_int > 0;").unwrap(), vec![vec![Int(0)]]);
+    let remaining = vec![vec![Int(0)], vec![Int(1)], vec![Int(2)], vec![Int(3)], vec![Int(5)], vec![Int(6)]];
+    assert_eq!(ids(), remaining);
+
+    // Compaction removes deleted rows without changing query results
+    block_on(locustdb.compact("default")).unwrap().unwrap();
+    assert_eq!(ids(), remaining);
+    assert_eq!(query("DELETE FROM default WHERE id = 9;").unwrap(), vec![vec![Int(0)]]);
+    assert_eq!(query("SELECT COUNT(0) FROM default WHERE enum = 'aa';").unwrap(), vec![vec![Int(4)]]);
+
+    assert!(matches!(query("DELETE FROM missing WHERE id = 1;"), Err(QueryError::NotImplemented(_))));
+    assert_eq!(query("DELETE FROM default;").unwrap(), vec![vec![Int(6)]]);
+    assert_eq!(ids(), Vec::<Vec<Value>>::new());
+
+    // Columns named like the tombstones of a partition are not shadowed by them
+    block_on(locustdb.create_table("shadow", Some(4)));
+    let rows = (0..4).map(|i| vec![("i".to_string(), Int(i)), ("_deleted".to_string(), Int(i % 2))]).collect();
+    block_on(locustdb.ingest("shadow", rows)).unwrap();
+    assert_eq!(query("DELETE FROM shadow WHERE i = 1;").unwrap(), vec![vec![Int(1)]]);
+    assert_eq!(
+        query("SELECT i, _deleted FROM shadow WHERE _deleted = 1 OR i = 0 ORDER BY i;").unwrap(),
+        vec![vec![Int(0), Int(0)], vec![Int(3), Int(1)]]
+    );
+}